@@ -2,6 +2,7 @@ use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::mpsc;
 
+pub use patina_core::bus::ChannelCapabilities;
 use patina_core::bus::{InboundMessage, OutboundMessage};
 
 /// Trait that all chat channel implementations must satisfy.
@@ -30,4 +31,12 @@ pub trait Channel: Send + Sync {
     fn prompt_rules(&self) -> &str {
         ""
     }
+
+    /// What the channel can display. The manager strips markdown and splits
+    /// long replies to match before calling [`send`](Channel::send), and the
+    /// agent is told about the limits in its system prompt. Defaults to
+    /// plain text with no length limit.
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities::default()
+    }
 }
@@ -0,0 +1,301 @@
+//! The gateway's control socket, for acting on a running gateway from the
+//! command line, e.g. `patina channels restart telegram` when its polling has
+//! wedged.
+//!
+//! The gateway listens on `~/.patina/control.sock`, which only its user can
+//! open. Each connection sends one JSON [`ControlRequest`] line and reads one
+//! [`ControlReply`] line back. The web API's channel routes send the same
+//! requests through a [`ControlHandle`]. Either way they're applied one at a
+//! time by the task that owns the [`ChannelManager`].
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+use crate::manager::ChannelManager;
+
+/// Something to do to the running gateway.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum ControlRequest {
+    RestartChannel { channel: String },
+    DisableChannel { channel: String },
+    EnableChannel { channel: String },
+}
+
+/// What came of a [`ControlRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ControlReply {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl ControlReply {
+    fn done(message: String) -> Self {
+        Self { ok: true, message }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// Where the gateway listens: `~/.patina/control.sock`.
+pub fn socket_path() -> PathBuf {
+    patina_config::data_dir().join("control.sock")
+}
+
+type Pending = (ControlRequest, oneshot::Sender<ControlReply>);
+
+/// Sends requests to the task applying them. Cheap to clone.
+#[derive(Clone)]
+pub struct ControlHandle {
+    tx: mpsc::Sender<Pending>,
+}
+
+/// The receiving end of a [`ControlHandle`], for [`apply`].
+pub struct ControlRequests {
+    rx: mpsc::Receiver<Pending>,
+}
+
+/// A handle to give out before the channels start, and the requests to
+/// hand to [`apply`] once they have.
+pub fn channel() -> (ControlHandle, ControlRequests) {
+    let (tx, rx) = mpsc::channel(8);
+    (ControlHandle { tx }, ControlRequests { rx })
+}
+
+impl ControlHandle {
+    /// Send a request and wait for it to be applied.
+    pub async fn send(&self, request: ControlRequest) -> ControlReply {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if self.tx.send((request, reply_tx)).await.is_err() {
+            return ControlReply::error("The gateway is shutting down.");
+        }
+        reply_rx
+            .await
+            .unwrap_or_else(|_| ControlReply::error("The gateway is shutting down."))
+    }
+}
+
+/// Apply requests to `manager`, one at a time, until every handle is gone.
+pub fn apply(mut requests: ControlRequests, manager: Arc<Mutex<ChannelManager>>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some((request, reply_tx)) = requests.rx.recv().await {
+            let reply = handle(&manager, request).await;
+            let _ = reply_tx.send(reply);
+        }
+    })
+}
+
+async fn handle(manager: &Mutex<ChannelManager>, request: ControlRequest) -> ControlReply {
+    let mut manager = manager.lock().await;
+    let result = match request {
+        ControlRequest::RestartChannel { channel } => manager
+            .restart(&channel)
+            .await
+            .map(|()| format!("Restarted {channel}.")),
+        ControlRequest::DisableChannel { channel } => manager.disable(&channel).await.map(|()| {
+            format!(
+                "Disabled {channel}. Replies for it are dropped until it's enabled \
+                     or restarted, or the gateway restarts."
+            )
+        }),
+        ControlRequest::EnableChannel { channel } if manager.is_disabled(&channel) => manager
+            .restart(&channel)
+            .await
+            .map(|()| format!("Enabled {channel}.")),
+        ControlRequest::EnableChannel { channel } => {
+            if manager.enabled_channels().await.contains(&channel) {
+                Ok(format!("{channel} isn't disabled."))
+            } else {
+                Err(patina_core::error::ChannelError::NotRegistered { channel })
+            }
+        }
+    };
+    match result {
+        Ok(message) => ControlReply::done(message),
+        Err(e) => ControlReply::error(e.to_string()),
+    }
+}
+
+/// Accept requests on the socket at `path` until the gateway exits.
+#[cfg(unix)]
+pub async fn listen(path: &Path, handle: ControlHandle) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::{UnixListener, UnixStream};
+    use tracing::{info, warn};
+
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("another gateway is listening on {}", path.display());
+        }
+        // Left behind by a gateway that didn't exit cleanly
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    info!("Control socket listening on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Control socket accept failed: {e}");
+                    continue;
+                }
+            };
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                if let Err(e) = serve_connection(stream, &handle).await {
+                    warn!("Control socket connection failed: {e}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn listen(_path: &Path, _handle: ControlHandle) -> Result<()> {
+    anyhow::bail!("the control socket needs a Unix platform")
+}
+
+#[cfg(unix)]
+async fn serve_connection(stream: tokio::net::UnixStream, handle: &ControlHandle) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+    let reply = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => handle.send(request).await,
+        Err(e) => ControlReply::error(format!("invalid request: {e}")),
+    };
+    let mut out = serde_json::to_string(&reply)?;
+    out.push('\n');
+    write.write_all(out.as_bytes()).await?;
+    Ok(())
+}
+
+/// Send one request to the gateway listening at `path`.
+#[cfg(unix)]
+pub async fn request(path: &Path, request: &ControlRequest) -> Result<ControlReply> {
+    use anyhow::Context;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let stream = UnixStream::connect(path).await.with_context(|| {
+        format!(
+            "no gateway is listening on {} (is `patina serve` running?)",
+            path.display()
+        )
+    })?;
+    let (read, mut write) = stream.into_split();
+    let mut out = serde_json::to_string(request)?;
+    out.push('\n');
+    write.write_all(out.as_bytes()).await?;
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+    serde_json::from_str(&line).context("the gateway sent an invalid reply")
+}
+
+#[cfg(not(unix))]
+pub async fn request(_path: &Path, _request: &ControlRequest) -> Result<ControlReply> {
+    anyhow::bail!("the control socket needs a Unix platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Channel;
+    use async_trait::async_trait;
+    use patina_core::bus::{InboundMessage, OutboundMessage};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::broadcast;
+
+    struct Counting(AtomicUsize);
+
+    #[async_trait]
+    impl Channel for Counting {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn start(&self, _inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn send(&self, _msg: &OutboundMessage) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_allowed(&self, _sender_id: &str) -> bool {
+            true
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn socket_requests_restart_and_disable_channels() {
+        let (_outbound_tx, outbound_rx) = broadcast::channel(16);
+        let mut manager = ChannelManager::new(outbound_rx);
+        let channel = Arc::new(Counting(AtomicUsize::new(0)));
+        manager.register(channel.clone()).await;
+        manager.start_all(mpsc::channel(16).0).await.unwrap();
+
+        let (handle, requests) = super::channel();
+        apply(requests, Arc::new(Mutex::new(manager)));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        listen(&path, handle).await.unwrap();
+
+        let send = |request| {
+            let path = path.clone();
+            async move { super::request(&path, &request).await.unwrap() }
+        };
+        let reply = send(ControlRequest::RestartChannel {
+            channel: "counting".into(),
+        })
+        .await;
+        assert_eq!(reply, ControlReply::done("Restarted counting.".into()));
+        assert_eq!(channel.0.load(Ordering::SeqCst), 2);
+
+        let reply = send(ControlRequest::DisableChannel {
+            channel: "counting".into(),
+        })
+        .await;
+        assert!(reply.ok && reply.message.starts_with("Disabled counting."));
+        let reply = send(ControlRequest::EnableChannel {
+            channel: "counting".into(),
+        })
+        .await;
+        assert_eq!(reply, ControlReply::done("Enabled counting.".into()));
+        assert_eq!(channel.0.load(Ordering::SeqCst), 3);
+
+        let reply = send(ControlRequest::RestartChannel {
+            channel: "fax".into(),
+        })
+        .await;
+        assert_eq!(
+            reply,
+            ControlReply::error("no channel 'fax' is running in this gateway")
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Adapt outbound replies to what each channel can display.
+//!
+//! The agent writes markdown. Before a reply reaches a channel, the
+//! manager uses the channel's [`ChannelCapabilities`] to strip the markdown
+//! if the channel shows plain text, and to split the reply into messages
+//! that fit its length limit. Splits fall between paragraphs where they
+//! can, code blocks cut in two are closed and reopened so each part renders
+//! on its own, and every part ends with a `(2/3)` marker. Channel-specific
+//! conversion (Telegram HTML, Slack mrkdwn) happens per part in each
+//! channel's `send()`, so formatting never straddles two messages.
+
+use regex::Regex;
+
+use patina_core::bus::{ChannelCapabilities, OutboundMessage};
+
+/// Room kept for the continuation marker, e.g. "\n\n(12/15)".
+const MARKER_RESERVE: usize = 12;
+const FENCE: &str = "```";
+
+/// Turn one outbound message into the messages to send to a channel with
+/// `caps`. Status events are passed through untouched.
+pub fn adapt(msg: &OutboundMessage, caps: &ChannelCapabilities) -> Vec<OutboundMessage> {
+    if msg.metadata.contains_key("event") {
+        return vec![msg.clone()];
+    }
+    let content = if caps.supports_markdown {
+        msg.content.clone()
+    } else {
+        strip_markdown(&msg.content)
+    };
+    let parts = match caps.max_message_len {
+        Some(max) if content.len() > max => {
+            let parts = split_message(&content, max.saturating_sub(MARKER_RESERVE));
+            let total = parts.len();
+            parts
+                .into_iter()
+                .enumerate()
+                .map(|(i, part)| format!("{part}\n\n({}/{total})", i + 1))
+                .collect()
+        }
+        _ => vec![content],
+    };
+    parts
+        .into_iter()
+        .map(|content| OutboundMessage {
+            content,
+            ..msg.clone()
+        })
+        .collect()
+}
+
+/// Split markdown into chunks of at most `max_len` bytes.
+///
+/// Breaks between paragraphs if there's one in the last two thirds of the
+/// window, else at a line break, a space, or finally anywhere. A chunk that
+/// ends inside a code block gets a closing fence, and the next one reopens
+/// it with the same language.
+pub fn split_message(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut remaining = text.trim_end();
+    let mut reopen: Option<String> = None;
+
+    while !remaining.is_empty() {
+        let prefix = reopen.take().map(|f| f + "\n").unwrap_or_default();
+        let budget = max_len.saturating_sub(prefix.len());
+        if remaining.len() <= budget {
+            chunks.push(prefix + remaining);
+            break;
+        }
+
+        // Leave room to close a code block
+        let budget = budget.saturating_sub(FENCE.len() + 1);
+        let mut cut = break_point(remaining, budget);
+        if open_fence(&prefix, &remaining[..cut]).is_some() {
+            // Rather break just before the block, if that isn't too early
+            if let Some(start) = remaining[..cut].rfind(&format!("\n{FENCE}")) {
+                if start > budget / 3 && open_fence(&prefix, &remaining[..start]).is_none() {
+                    cut = start + 1;
+                }
+            }
+        }
+
+        let body = remaining[..cut].trim_end();
+        let mut chunk = prefix.clone() + body;
+        reopen = open_fence(&prefix, body);
+        if reopen.is_some() {
+            chunk.push('\n');
+            chunk.push_str(FENCE);
+        }
+        if !body.trim().is_empty() {
+            chunks.push(chunk);
+        }
+        remaining = remaining[cut..].trim_start_matches('\n');
+    }
+
+    chunks
+}
+
+/// Where to end a chunk of `text` no longer than `budget` bytes.
+fn break_point(text: &str, budget: usize) -> usize {
+    // Never cut a UTF-8 character in half
+    let mut limit = budget.min(text.len());
+    while !text.is_char_boundary(limit) {
+        limit -= 1;
+    }
+    if limit == 0 {
+        // Always make progress, even past the budget
+        return text.chars().next().map_or(0, char::len_utf8);
+    }
+    let window = &text[..limit];
+    for sep in ["\n\n", "\n", " "] {
+        if let Some(pos) = window.rfind(sep) {
+            if pos > limit / 3 {
+                return pos + sep.len();
+            }
+        }
+    }
+    limit
+}
+
+/// The opening line of the code block still open at the end of
+/// `prefix` + `body`, if any.
+fn open_fence(prefix: &str, body: &str) -> Option<String> {
+    let mut open = None;
+    for line in prefix.lines().chain(body.lines()) {
+        let line = line.trim();
+        if line.starts_with(FENCE) {
+            open = match open {
+                Some(_) => None,
+                None => Some(line.to_string()),
+            };
+        }
+    }
+    open
+}
+
+/// Remove markdown syntax, keeping the text (and link targets) readable.
+pub fn strip_markdown(text: &str) -> String {
+    let re_fence = Regex::new(r"(?m)^```.*\n?").unwrap();
+    let re_heading = Regex::new(r"(?m)^#{1,6}\s+").unwrap();
+    let re_bullet = Regex::new(r"(?m)^(\s*)[*+]\s+").unwrap();
+    let re_image = Regex::new(r"!\[([^\]]*)\]\(([^)]+)\)").unwrap();
+    let re_link = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
+    let re_bold = Regex::new(r"\*\*(.+?)\*\*").unwrap();
+    let re_italic = Regex::new(r"(^|[^\w*])\*([^*\s](?:[^*\n]*[^*\s])?)\*").unwrap();
+    let re_strike = Regex::new(r"~~(.+?)~~").unwrap();
+    let re_code = Regex::new(r"`([^`\n]+)`").unwrap();
+
+    let text = re_fence.replace_all(text, "");
+    let text = re_heading.replace_all(&text, "");
+    let text = re_bullet.replace_all(&text, "$1- ");
+    let text = re_image.replace_all(&text, "$1 ($2)");
+    let text = re_link.replace_all(&text, "$1 ($2)");
+    let text = re_bold.replace_all(&text, "$1");
+    let text = re_italic.replace_all(&text, "$1$2");
+    let text = re_strike.replace_all(&text, "$1");
+    let text = re_code.replace_all(&text, "$1");
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn msg(content: &str) -> OutboundMessage {
+        OutboundMessage {
+            channel: "test".into(),
+            chat_id: "1".into(),
+            content: content.into(),
+            reply_to: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn split_message_short() {
+        assert_eq!(split_message("hello", 4096), vec!["hello"]);
+    }
+
+    #[test]
+    fn split_message_prefers_paragraphs() {
+        let para = "word ".repeat(20).trim_end().to_string();
+        let text = format!("{para}\nsecond line\n\n{para}\n\n{para}");
+        let chunks = split_message(&text, 250);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], format!("{para}\nsecond line\n\n{para}"));
+        assert_eq!(chunks[1], para);
+    }
+
+    #[test]
+    fn split_message_no_break() {
+        let text = "a".repeat(5000);
+        let chunks = split_message(&text, 4096);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.len() <= 4096));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn split_message_respects_char_boundaries() {
+        let text = "é".repeat(40);
+        let chunks = split_message(&text, 15);
+        assert!(chunks.iter().all(|c| c.len() <= 15));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn split_message_reopens_code_blocks() {
+        let code: String = (0..30).map(|i| format!("let x{i} = {i};\n")).collect();
+        let intro = "Here is the whole file, with every variable spelled out.";
+        let text = format!("{intro}\n\n```rust\n{code}```\nDone.");
+        let chunks = split_message(&text, 150);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(chunk.len() <= 150, "{chunk}");
+            assert!(open_fence("", chunk).is_none(), "unbalanced: {chunk}");
+        }
+        // The intro breaks off before the block rather than inside it
+        assert_eq!(chunks[0], intro);
+        assert!(chunks[2].starts_with("```rust\nlet x"));
+        assert!(chunks.last().unwrap().ends_with("```\nDone."));
+    }
+
+    #[test]
+    fn strip_markdown_keeps_text() {
+        let md = "# Plan\n\n**Bold** and *soft* and `code`, see [docs](https://x.io).\n* one\n```rust\nlet x = 1;\n```\n";
+        assert_eq!(
+            strip_markdown(md),
+            "Plan\n\nBold and soft and code, see docs (https://x.io).\n- one\nlet x = 1;\n"
+        );
+        // Arithmetic and snake_case survive
+        assert_eq!(
+            strip_markdown("2 * 3 * 4 = some_var"),
+            "2 * 3 * 4 = some_var"
+        );
+    }
+
+    #[test]
+    fn adapt_per_capabilities() {
+        let plain = ChannelCapabilities {
+            max_message_len: Some(36),
+            ..Default::default()
+        };
+        assert_eq!(adapt(&msg("**hello**"), &plain)[0].content, "hello");
+        let parts = adapt(&msg("**hello** there, friend\n\nthe world is wide"), &plain);
+        let contents: Vec<&str> = parts.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["hello there, friend\n\n(1/2)", "the world is wide\n\n(2/2)"]
+        );
+        assert!(parts
+            .iter()
+            .all(|m| m.chat_id == "1" && m.content.len() <= 36));
+
+        let rich = ChannelCapabilities {
+            supports_markdown: true,
+            ..Default::default()
+        };
+        assert_eq!(adapt(&msg("**hello**"), &rich)[0].content, "**hello**");
+
+        let mut status = msg("**connected**");
+        status.metadata.insert("event".into(), "status".into());
+        assert_eq!(adapt(&status, &plain)[0].content, "**connected**");
+    }
+}
@@ -0,0 +1,214 @@
+//! Signed guest links for the web channel: sandboxed guest chats, and
+//! read-only views of an existing chat.
+//!
+//! A link carries a token `<payload>.<signature>`, where the payload is
+//! base64url JSON naming the pass and its expiry, and the signature is an
+//! HMAC-SHA256 over the payload. Nothing is stored server-side: a token is
+//! valid as long as it verifies and hasn't expired, so rotating the key
+//! revokes every outstanding link.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use patina_config::GuestLinksConfig;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Longest lifetime a link can be given: a year.
+pub const MAX_TTL_MINS: u64 = 60 * 24 * 365;
+
+/// A link lifetime of `mins` minutes (at least one). Errors past
+/// [`MAX_TTL_MINS`] instead of overflowing the expiry.
+pub fn link_ttl(mins: u64) -> Result<Duration> {
+    if mins > MAX_TTL_MINS {
+        bail!("link lifetime must be at most {MAX_TTL_MINS} minutes (a year)");
+    }
+    Ok(Duration::minutes(mins.max(1) as i64))
+}
+
+/// What a verified guest token grants.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuestPass {
+    /// Random id; guest chats are named after it.
+    pub id: String,
+    /// Free-form note from `patina share --label`, for logs.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    pub expires_at: DateTime<Utc>,
+    /// Existing web chat to watch; guest chats get their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chat: Option<String>,
+    /// Watch only: no messages or cancels.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub view_only: bool,
+}
+
+impl GuestPass {
+    /// The web chat this pass is confined to.
+    pub fn chat_id(&self) -> String {
+        match self.chat {
+            Some(ref chat) => chat.clone(),
+            None => format!("guest-{}", self.id),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+}
+
+/// Issues and verifies guest tokens.
+#[derive(Clone)]
+pub struct GuestLinks {
+    key: Vec<u8>,
+}
+
+impl GuestLinks {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self { key: secret.into() }
+    }
+
+    /// Use `config.secret`, or the key in ~/.patina/guest-link.key (created
+    /// on first use).
+    pub fn from_config(config: &GuestLinksConfig) -> Result<Self> {
+        if let Some(secret) = config.secret.as_deref().filter(|s| !s.is_empty()) {
+            return Ok(Self::new(secret));
+        }
+        let path = key_path();
+        if let Ok(key) = std::fs::read_to_string(&path) {
+            if !key.trim().is_empty() {
+                return Ok(Self::new(key.trim()));
+            }
+        }
+        let key = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, &key)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(Self::new(key))
+    }
+
+    /// Sign a new guest chat pass valid for `ttl` and return its token.
+    pub fn issue(&self, label: &str, ttl: Duration) -> String {
+        self.sign(&new_pass(label, ttl))
+    }
+
+    /// Sign a pass to watch web chat `chat_id` without taking part.
+    pub fn issue_view(&self, chat_id: &str, label: &str, ttl: Duration) -> String {
+        self.sign(&GuestPass {
+            chat: Some(chat_id.to_string()),
+            view_only: true,
+            ..new_pass(label, ttl)
+        })
+    }
+
+    fn sign(&self, pass: &GuestPass) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&pass).unwrap_or_default());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    /// Check a token's signature and expiry.
+    pub fn verify(&self, token: &str) -> Result<GuestPass> {
+        let Some((payload, signature)) = token.split_once('.') else {
+            bail!("malformed guest token");
+        };
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("malformed guest token")?;
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| anyhow::anyhow!("invalid guest token signature"))?;
+        let pass: GuestPass = serde_json::from_slice(
+            &URL_SAFE_NO_PAD
+                .decode(payload)
+                .context("malformed guest token")?,
+        )
+        .context("malformed guest token")?;
+        if pass.expired() {
+            bail!("guest link expired");
+        }
+        Ok(pass)
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+fn new_pass(label: &str, ttl: Duration) -> GuestPass {
+    GuestPass {
+        id: uuid::Uuid::new_v4().simple().to_string()[..12].to_string(),
+        label: label.to_string(),
+        expires_at: Utc::now() + ttl,
+        chat: None,
+        view_only: false,
+    }
+}
+
+fn key_path() -> PathBuf {
+    patina_config::data_dir().join("guest-link.key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_verify() {
+        let links = GuestLinks::new("secret");
+        let token = links.issue("demo", Duration::minutes(5));
+        let pass = links.verify(&token).unwrap();
+        assert_eq!(pass.label, "demo");
+        assert!(pass.chat_id().starts_with("guest-"));
+
+        // Another key, a tampered payload, and junk are all rejected
+        assert!(GuestLinks::new("other").verify(&token).is_err());
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = URL_SAFE_NO_PAD.encode(
+            serde_json::to_vec(&GuestPass {
+                expires_at: pass.expires_at + Duration::days(365),
+                ..pass
+            })
+            .unwrap(),
+        );
+        assert!(links.verify(&format!("{forged}.{signature}")).is_err());
+        assert!(links.verify("not-a-token").is_err());
+
+        let view = links
+            .verify(&links.issue_view("abc-123", "", Duration::minutes(5)))
+            .unwrap();
+        assert!(view.view_only);
+        assert_eq!(view.chat_id(), "abc-123");
+
+        let expired = links.issue("", Duration::minutes(-1));
+        let err = links.verify(&expired).unwrap_err();
+        assert!(err.to_string().contains("expired"), "{err}");
+    }
+
+    #[test]
+    fn test_link_ttl_bounds() {
+        assert_eq!(link_ttl(0).unwrap(), Duration::minutes(1));
+        assert_eq!(link_ttl(MAX_TTL_MINS).unwrap(), Duration::days(365));
+        assert!(link_ttl(MAX_TTL_MINS + 1).is_err());
+        assert!(link_ttl(u64::MAX).is_err());
+    }
+}
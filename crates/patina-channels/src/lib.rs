@@ -1,8 +1,19 @@
 pub mod base;
+pub mod control;
+pub mod format;
+pub mod guest;
 pub mod manager;
 pub mod markdown;
+pub mod notify;
+pub mod outbound_queue;
 pub mod slack;
 pub mod slack_markdown;
+pub mod teams;
+pub mod teams_card;
 pub mod telegram;
+pub mod voice;
 pub mod web;
 pub mod web_assets;
+pub mod webhook;
+pub mod whatsapp;
+pub mod whatsapp_markdown;
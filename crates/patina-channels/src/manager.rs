@@ -1,25 +1,97 @@
 //! Channel manager: coordinates the lifecycle of all enabled channels
 //! and dispatches outbound messages to the appropriate channel.
-
-use std::collections::HashMap;
-use std::sync::Arc;
+//!
+//! Outbound messages go through a bounded [`OutboundQueue`] per channel,
+//! each drained by its own delivery task, so one slow channel doesn't hold
+//! up the rest. The delivery task adapts each message to the channel's
+//! [`ChannelCapabilities`] with [`format::adapt`]. Each channel's state
+//! (running, stopped, disabled, or failed) is kept process-wide for
+//! `/api/status`.
+//!
+//! Single channels can be restarted or disabled while the rest keep running,
+//! through [`crate::control`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
 
 use anyhow::Result;
+use serde::Serialize;
 use tokio::sync::{broadcast, mpsc, RwLock};
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
+use patina_config::OutboundConfig;
 use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::bus_journal::{journal_id, BusJournal, JOURNAL_ID};
+use patina_core::error::ChannelError;
+
+use crate::base::{Channel, ChannelCapabilities};
+use crate::format;
+use crate::outbound_queue::{self, OutboundQueue};
+
+/// Where a channel's task is in its life.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelState {
+    /// Started without an error. Some channels' `start()` runs for as long
+    /// as they do, others spawn their work and return.
+    Running,
+    /// Stopped by `stop_all()`.
+    Stopped,
+    /// Stopped by `disable()`; replies for it are dropped.
+    Disabled,
+    /// `start()` returned an error or panicked.
+    Failed,
+}
 
-use crate::base::Channel;
+/// Snapshot of one channel's task.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStatus {
+    pub state: ChannelState,
+    /// When it entered `state` (RFC 3339).
+    pub since: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn statuses() -> &'static Mutex<BTreeMap<String, ChannelStatus>> {
+    static STATUSES: OnceLock<Mutex<BTreeMap<String, ChannelStatus>>> = OnceLock::new();
+    STATUSES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn set_status(name: &str, state: ChannelState, error: Option<String>) {
+    let status = ChannelStatus {
+        state,
+        since: chrono::Utc::now().to_rfc3339(),
+        error,
+    };
+    lock(statuses()).insert(name.to_string(), status);
+}
+
+/// The state of every channel started in this process, by name.
+pub fn channel_statuses() -> BTreeMap<String, ChannelStatus> {
+    lock(statuses()).clone()
+}
+
+/// How long `restart()` waits to see whether the channel failed to start.
+const RESTART_CHECK: Duration = Duration::from_millis(500);
 
 /// Coordinates the lifecycle of all enabled channels and dispatches
 /// outbound messages to the appropriate channel by name.
 pub struct ChannelManager {
     channels: Arc<RwLock<HashMap<String, Arc<dyn Channel>>>>,
     outbound_rx: Option<broadcast::Receiver<OutboundMessage>>,
+    journal: Option<Arc<BusJournal>>,
+    outbound: OutboundConfig,
     dispatch_handle: Option<JoinHandle<()>>,
+    delivery_handles: Vec<JoinHandle<()>>,
     channel_handles: Vec<(String, JoinHandle<Result<()>>)>,
+    /// Kept from `start_all()` for restarts.
+    inbound_tx: Option<mpsc::Sender<InboundMessage>>,
+    /// Channels whose outbound messages are dropped.
+    disabled: Arc<Mutex<HashSet<String>>>,
 }
 
 impl ChannelManager {
@@ -28,8 +100,13 @@ impl ChannelManager {
         Self {
             channels: Arc::new(RwLock::new(HashMap::new())),
             outbound_rx: Some(outbound_rx),
+            journal: None,
+            outbound: OutboundConfig::default(),
             dispatch_handle: None,
+            delivery_handles: Vec::new(),
             channel_handles: Vec::new(),
+            inbound_tx: None,
+            disabled: Arc::default(),
         }
     }
 
@@ -41,20 +118,52 @@ impl ChannelManager {
         channels.insert(name, channel);
     }
 
+    /// Journal outbound messages until their channel has taken them, and
+    /// resend the ones left undelivered by the last run on `start_all()`.
+    pub fn set_journal(&mut self, journal: Arc<BusJournal>) {
+        self.journal = Some(journal);
+    }
+
+    /// Queue sizes and overflow policies for outbound messages. Must be
+    /// called before `start_all()`.
+    pub fn set_outbound_config(&mut self, config: OutboundConfig) {
+        self.outbound = config;
+    }
+
     /// List the names of all registered channels.
     pub async fn enabled_channels(&self) -> Vec<String> {
         let channels = self.channels.read().await;
         channels.keys().cloned().collect()
     }
 
-    /// Collect prompt rules from all registered channels.
+    /// What each registered channel can display, by channel name.
+    pub async fn capabilities(&self) -> HashMap<String, ChannelCapabilities> {
+        let channels = self.channels.read().await;
+        channels
+            .iter()
+            .map(|(name, ch)| (name.clone(), ch.capabilities()))
+            .collect()
+    }
+
+    /// Collect prompt rules from all registered channels, followed by
+    /// guidance derived from their capabilities.
     /// Returns a map of channel name → prompt rules string.
     pub async fn prompt_rules(&self) -> HashMap<String, String> {
         let channels = self.channels.read().await;
         channels
             .iter()
-            .filter(|(_, ch)| !ch.prompt_rules().is_empty())
-            .map(|(name, ch)| (name.clone(), ch.prompt_rules().to_string()))
+            .map(|(name, ch)| {
+                let rules = [
+                    ch.prompt_rules().to_string(),
+                    ch.capabilities().prompt_rules(),
+                ]
+                .into_iter()
+                .filter(|r| !r.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+                (name.clone(), rules)
+            })
+            .filter(|(_, rules)| !rules.is_empty())
             .collect()
     }
 
@@ -62,47 +171,57 @@ impl ChannelManager {
     ///
     /// Each channel's `start()` is spawned as a separate task.
     /// The outbound dispatcher runs in another task, routing outbound
-    /// messages to the appropriate channel by name.
-    pub async fn start_all(&mut self, inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+    /// messages into each channel's queue, and every channel gets a
+    /// delivery task draining its queue.
+    pub async fn start_all(
+        &mut self,
+        inbound_tx: mpsc::Sender<InboundMessage>,
+    ) -> Result<(), ChannelError> {
         // Start each channel
-        let channels = self.channels.read().await;
-        for (name, channel) in channels.iter() {
-            let ch = channel.clone();
-            let tx = inbound_tx.clone();
-            let ch_name = name.clone();
-            let handle = tokio::spawn(async move { ch.start(tx).await });
-            self.channel_handles.push((ch_name, handle));
+        let channels: Vec<(String, Arc<dyn Channel>)> = self
+            .channels
+            .read()
+            .await
+            .iter()
+            .map(|(name, ch)| (name.clone(), ch.clone()))
+            .collect();
+        for (name, channel) in channels {
+            self.spawn_channel(&name, channel, inbound_tx.clone());
         }
-        drop(channels);
+        self.inbound_tx = Some(inbound_tx);
 
         // Detect immediate startup failures.
         for (name, handle) in &mut self.channel_handles {
-            if !handle.is_finished() {
-                continue;
-            }
-            let join = handle.await;
-            match join {
-                Ok(Ok(())) => {
-                    warn!("Channel {name} exited during startup without error");
-                }
-                Ok(Err(e)) => {
-                    error!("Channel {name} failed to start: {e}");
-                    return Err(anyhow::anyhow!("channel '{name}' failed to start: {e}"));
-                }
-                Err(e) => {
-                    error!("Channel {name} startup task panicked: {e}");
-                    return Err(anyhow::anyhow!(
-                        "channel '{name}' startup task panicked: {e}"
-                    ));
-                }
+            if let Some(e) = startup_failure(name, handle).await {
+                return Err(e);
             }
         }
 
         // Start outbound dispatcher
         if let Some(outbound_rx) = self.outbound_rx.take() {
-            let channels = self.channels.clone();
+            let spill_dir = outbound_queue::spill_dir(&self.outbound);
+            let mut queues = HashMap::new();
+            for (name, channel) in self.channels.read().await.iter() {
+                let (capacity, policy) = self.outbound.for_channel(name);
+                // With a journal, spilled leftovers are resent from there
+                let queue = Arc::new(OutboundQueue::new(
+                    name,
+                    capacity,
+                    policy,
+                    &spill_dir,
+                    self.journal.is_none(),
+                ));
+                queues.insert(name.clone(), queue.clone());
+                let channel = channel.clone();
+                let journal = self.journal.clone();
+                self.delivery_handles.push(tokio::spawn(async move {
+                    deliver(channel, queue, journal).await;
+                }));
+            }
+            let journal = self.journal.clone();
+            let disabled = self.disabled.clone();
             self.dispatch_handle = Some(tokio::spawn(async move {
-                dispatch_outbound(outbound_rx, channels).await;
+                dispatch_outbound(outbound_rx, queues, disabled, journal).await;
             }));
         }
 
@@ -115,6 +234,9 @@ impl ChannelManager {
             handle.abort();
             info!("Stopped outbound dispatcher");
         }
+        for handle in self.delivery_handles.drain(..) {
+            handle.abort();
+        }
 
         let channels = self.channels.read().await;
         for (name, channel) in channels.iter() {
@@ -125,10 +247,127 @@ impl ChannelManager {
         }
         for (name, handle) in self.channel_handles.drain(..) {
             handle.abort();
+            set_status(&name, ChannelState::Stopped, None);
             info!("Stopped channel task: {name}");
         }
         Ok(())
     }
+
+    /// Stop one channel and start it again, e.g. when Telegram's polling has
+    /// wedged, leaving the others running. Re-enables a disabled channel.
+    pub async fn restart(&mut self, name: &str) -> Result<(), ChannelError> {
+        let channel = self.channel(name).await?;
+        let Some(inbound_tx) = self.inbound_tx.clone() else {
+            return Err(ChannelError::Start {
+                channel: name.to_string(),
+                message: "channels haven't been started".into(),
+            });
+        };
+        self.halt(name, &channel).await;
+        lock(&self.disabled).remove(name);
+        self.spawn_channel(name, channel, inbound_tx);
+        info!("Restarted channel {name}");
+
+        // Catch a start that fails straight away, e.g. on a port still in use
+        tokio::time::sleep(RESTART_CHECK).await;
+        if let Some((_, handle)) = self.channel_handles.iter_mut().find(|(n, _)| n == name) {
+            if let Some(e) = startup_failure(name, handle).await {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop one channel until it's restarted or the gateway restarts.
+    /// Replies meant for it are dropped meanwhile instead of queueing up.
+    pub async fn disable(&mut self, name: &str) -> Result<(), ChannelError> {
+        let channel = self.channel(name).await?;
+        self.halt(name, &channel).await;
+        lock(&self.disabled).insert(name.to_string());
+        set_status(name, ChannelState::Disabled, None);
+        info!("Disabled channel {name}");
+        Ok(())
+    }
+
+    /// Whether `disable()` stopped the channel.
+    pub fn is_disabled(&self, name: &str) -> bool {
+        lock(&self.disabled).contains(name)
+    }
+
+    async fn channel(&self, name: &str) -> Result<Arc<dyn Channel>, ChannelError> {
+        self.channels
+            .read()
+            .await
+            .get(name)
+            .cloned()
+            .ok_or_else(|| ChannelError::NotRegistered {
+                channel: name.to_string(),
+            })
+    }
+
+    fn spawn_channel(
+        &mut self,
+        name: &str,
+        channel: Arc<dyn Channel>,
+        inbound_tx: mpsc::Sender<InboundMessage>,
+    ) {
+        let ch_name = name.to_string();
+        set_status(name, ChannelState::Running, None);
+        let handle = tokio::spawn(async move {
+            let result = channel.start(inbound_tx).await;
+            if let Err(ref e) = result {
+                set_status(&ch_name, ChannelState::Failed, Some(e.to_string()));
+            }
+            result
+        });
+        self.channel_handles.push((name.to_string(), handle));
+    }
+
+    /// Stop a channel and abort its task.
+    async fn halt(&mut self, name: &str, channel: &Arc<dyn Channel>) {
+        if let Err(e) = channel.stop().await {
+            warn!("Error stopping channel {name}: {e}");
+        }
+        self.channel_handles.retain(|(n, handle)| {
+            if n == name {
+                handle.abort();
+            }
+            n != name
+        });
+        set_status(name, ChannelState::Stopped, None);
+    }
+}
+
+/// The error a channel's task ended with, if it has already ended.
+async fn startup_failure(name: &str, handle: &mut JoinHandle<Result<()>>) -> Option<ChannelError> {
+    if !handle.is_finished() {
+        return None;
+    }
+    match handle.await {
+        Ok(Ok(())) => {
+            warn!("Channel {name} exited during startup without error");
+            None
+        }
+        Ok(Err(e)) => {
+            error!("Channel {name} failed to start: {e}");
+            Some(ChannelError::Start {
+                channel: name.to_string(),
+                message: e.to_string(),
+            })
+        }
+        Err(e) => {
+            error!("Channel {name} startup task panicked: {e}");
+            set_status(name, ChannelState::Failed, Some(e.to_string()));
+            Some(ChannelError::Start {
+                channel: name.to_string(),
+                message: format!("startup task panicked: {e}"),
+            })
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
 }
 
 #[cfg(test)]
@@ -136,6 +375,7 @@ impl ChannelManager {
 mod tests {
     use super::*;
     use async_trait::async_trait;
+    use patina_config::OverflowPolicy;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use tokio::time::{sleep, Duration};
 
@@ -226,6 +466,144 @@ mod tests {
         assert_eq!(ch.stops(), 1);
     }
 
+    #[tokio::test]
+    async fn channel_status_follows_its_task() {
+        let (_outbound_tx, outbound_rx) = broadcast::channel(16);
+        let mut manager = ChannelManager::new(outbound_rx);
+        manager
+            .register(Arc::new(MockChannel::new("status-test")))
+            .await;
+        let (inbound_tx, _inbound_rx) = mpsc::channel(16);
+        manager.start_all(inbound_tx).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(
+            channel_statuses()["status-test"].state,
+            ChannelState::Running
+        );
+
+        manager.stop_all().await.unwrap();
+        assert_eq!(
+            channel_statuses()["status-test"].state,
+            ChannelState::Stopped
+        );
+    }
+
+    #[tokio::test]
+    async fn disabled_channel_drops_replies_until_restarted() {
+        let (outbound_tx, outbound_rx) = broadcast::channel(16);
+        let mut manager = ChannelManager::new(outbound_rx);
+        let ch = Arc::new(MockChannel::new("disable-test"));
+        let ch_dyn: Arc<dyn Channel> = ch.clone();
+        manager.register(ch_dyn).await;
+        let (inbound_tx, _inbound_rx) = mpsc::channel(16);
+        manager.start_all(inbound_tx).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        let reply = OutboundMessage {
+            channel: "disable-test".to_string(),
+            chat_id: "1".to_string(),
+            content: "hello".to_string(),
+            reply_to: None,
+            metadata: HashMap::new(),
+        };
+
+        manager.disable("disable-test").await.unwrap();
+        assert!(manager.is_disabled("disable-test"));
+        assert_eq!(
+            channel_statuses()["disable-test"].state,
+            ChannelState::Disabled
+        );
+        outbound_tx.send(reply.clone()).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(ch.sends(), 0);
+
+        manager.restart("disable-test").await.unwrap();
+        assert!(!manager.is_disabled("disable-test"));
+        assert_eq!(ch.starts(), 2);
+        outbound_tx.send(reply).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(ch.sends(), 1);
+
+        assert!(matches!(
+            manager.restart("fax").await,
+            Err(ChannelError::NotRegistered { .. })
+        ));
+        manager.stop_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn manager_resends_undelivered_from_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Arc::new(BusJournal::open(&dir.path().join("bus.db")).unwrap());
+        let reply = OutboundMessage {
+            channel: "telegram".to_string(),
+            chat_id: "1".to_string(),
+            content: "left over".to_string(),
+            reply_to: None,
+            metadata: HashMap::new(),
+        };
+        journal.record_outbound(&reply).unwrap();
+
+        let (outbound_tx, outbound_rx) = broadcast::channel(16);
+        let mut manager = ChannelManager::new(outbound_rx);
+        manager.set_journal(journal.clone());
+        manager.set_outbound_config(OutboundConfig {
+            spill_dir: Some(dir.path().display().to_string()),
+            ..Default::default()
+        });
+        let ch = Arc::new(MockChannel::new("telegram"));
+        let ch_dyn: Arc<dyn Channel> = ch.clone();
+        manager.register(ch_dyn).await;
+        let (inbound_tx, _inbound_rx) = mpsc::channel(16);
+        manager.start_all(inbound_tx).await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(ch.sends(), 1);
+
+        outbound_tx.send(reply).unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(ch.sends(), 2);
+        // Both delivered, so nothing is left for the next start
+        assert!(journal.unfinished_outbound().unwrap().is_empty());
+        manager.stop_all().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dropped_messages_are_completed_in_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Arc::new(BusJournal::open(&dir.path().join("bus.db")).unwrap());
+        let queue = Arc::new(OutboundQueue::new(
+            "drop-journal",
+            1,
+            OverflowPolicy::DropOldest,
+            dir.path(),
+            false,
+        ));
+        let queues = HashMap::from([("drop-journal".to_string(), queue.clone())]);
+        let disabled = Mutex::new(HashSet::new());
+        for content in ["old", "new"] {
+            let mut msg = OutboundMessage {
+                channel: "drop-journal".to_string(),
+                chat_id: "1".to_string(),
+                content: content.to_string(),
+                reply_to: None,
+                metadata: HashMap::new(),
+            };
+            let id = journal.record_outbound(&msg).unwrap();
+            msg.metadata.insert(JOURNAL_ID.into(), id.into());
+            enqueue(msg, &queues, &disabled, Some(&journal)).await;
+        }
+
+        // Only the queued message is left to replay
+        let left: Vec<String> = journal
+            .unfinished_outbound()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(left, ["new"]);
+        assert_eq!(queue.pop().await.msg.content, "new");
+    }
+
     #[tokio::test]
     async fn manager_ignores_unknown_outbound_channel() {
         let (outbound_tx, outbound_rx) = broadcast::channel(16);
@@ -256,27 +634,44 @@ mod tests {
 }
 
 /// Outbound dispatcher loop: receives outbound messages from the bus
-/// and routes them to the appropriate channel by name.
+/// and routes them into the appropriate channel's queue.
 async fn dispatch_outbound(
     mut outbound_rx: broadcast::Receiver<OutboundMessage>,
-    channels: Arc<RwLock<HashMap<String, Arc<dyn Channel>>>>,
+    queues: HashMap<String, Arc<OutboundQueue>>,
+    disabled: Arc<Mutex<HashSet<String>>>,
+    journal: Option<Arc<BusJournal>>,
 ) {
+    if let Some(ref journal) = journal {
+        match journal.unfinished_outbound() {
+            Ok(undelivered) => {
+                if !undelivered.is_empty() {
+                    info!("Resending {} undelivered message(s)", undelivered.len());
+                }
+                for msg in undelivered {
+                    enqueue(msg, &queues, &disabled, Some(journal)).await;
+                }
+            }
+            Err(e) => warn!("Failed to read outbound journal: {e}"),
+        }
+    }
     loop {
         match outbound_rx.recv().await {
-            Ok(msg) => {
-                let channels = channels.read().await;
-                if let Some(channel) = channels.get(&msg.channel) {
-                    if let Err(e) = channel.send(&msg).await {
-                        error!("Error sending to channel {}: {e}", msg.channel);
-                    }
-                } else {
-                    // For CLI mode or system messages, just log
-                    if msg.channel != "cli" && msg.channel != "system" {
-                        warn!("No channel registered for: {}", msg.channel);
+            Ok(mut msg) => {
+                // Status events are only meaningful live
+                if let Some(ref journal) = journal {
+                    if !msg.metadata.contains_key("event") {
+                        match journal.record_outbound(&msg) {
+                            Ok(id) => {
+                                msg.metadata.insert(JOURNAL_ID.into(), id.into());
+                            }
+                            Err(e) => warn!("Failed to journal outbound message: {e}"),
+                        }
                     }
                 }
+                enqueue(msg, &queues, &disabled, journal.as_ref()).await;
             }
             Err(broadcast::error::RecvError::Lagged(n)) => {
+                outbound_queue::missed(n);
                 warn!("Outbound dispatcher lagged, missed {n} messages");
             }
             Err(broadcast::error::RecvError::Closed) => {
@@ -286,3 +681,60 @@ async fn dispatch_outbound(
         }
     }
 }
+
+/// Queue one message for its channel. Messages for unknown or disabled
+/// channels, and those the queue drops to make room, are completed in the
+/// journal straight away so they aren't replayed.
+async fn enqueue(
+    msg: OutboundMessage,
+    queues: &HashMap<String, Arc<OutboundQueue>>,
+    disabled: &Mutex<HashSet<String>>,
+    journal: Option<&Arc<BusJournal>>,
+) {
+    let complete = |msg: &OutboundMessage| {
+        if let (Some(journal), Some(id)) = (journal, journal_id(&msg.metadata)) {
+            journal.complete(id);
+        }
+    };
+    if lock(disabled).contains(&msg.channel) {
+        warn!(
+            "Dropping message for disabled channel {} (chat {})",
+            msg.channel, msg.chat_id
+        );
+    } else if let Some(queue) = queues.get(&msg.channel) {
+        if let Some(dropped) = queue.push(msg).await {
+            complete(&dropped);
+        }
+        return;
+    } else if msg.channel != "cli" && msg.channel != "system" {
+        // CLI and system messages have no channel to go to
+        warn!("No channel registered for: {}", msg.channel);
+    }
+    complete(&msg);
+}
+
+/// Delivery loop for one channel: sends queued messages in order, adapted
+/// to the channel's capabilities, completing each journal entry once the
+/// channel has taken it.
+async fn deliver(
+    channel: Arc<dyn Channel>,
+    queue: Arc<OutboundQueue>,
+    journal: Option<Arc<BusJournal>>,
+) {
+    let caps = channel.capabilities();
+    loop {
+        let item = queue.pop().await;
+        let mut result = Ok(());
+        for part in format::adapt(&item.msg, &caps) {
+            result = channel.send(&part).await;
+            if let Err(ref e) = result {
+                error!("Error sending to channel {}: {e}", item.msg.channel);
+                break;
+            }
+        }
+        queue.delivered(&item, result.is_ok());
+        if let (Some(journal), Some(id)) = (journal.as_ref(), journal_id(&item.msg.metadata)) {
+            journal.complete(id);
+        }
+    }
+}
@@ -166,6 +166,11 @@ pub fn markdown_to_telegram_html(text: &str) -> String {
     // 9. Italic _text_ (avoid matching inside words like some_var_name)
     //    Rust regex doesn't support lookbehind/lookahead, so we use a manual approach.
     text = replace_italic(&text);
+    //    and *text*, as long as the stars hug the text (not `2 * 3 * 4` or bullets)
+    let re_italic_star = Regex::new(r"(^|[^\w*])\*([^*\s](?:[^*\n]*[^*\s])?)\*").unwrap();
+    text = re_italic_star
+        .replace_all(&text, "$1<i>$2</i>")
+        .into_owned();
 
     // 10. Strikethrough ~~text~~
     let re_strike = Regex::new(r"~~(.+?)~~").unwrap();
@@ -239,6 +244,12 @@ mod tests {
             markdown_to_telegram_html("this is _italic_ text"),
             "this is <i>italic</i> text"
         );
+        assert_eq!(
+            markdown_to_telegram_html("this is *italic* text"),
+            "this is <i>italic</i> text"
+        );
+        assert_eq!(markdown_to_telegram_html("2 * 3 * 4"), "2 * 3 * 4");
+        assert_eq!(markdown_to_telegram_html("* item"), "\u{2022} item");
     }
 
     #[test]
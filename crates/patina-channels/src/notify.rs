@@ -0,0 +1,254 @@
+//! Outbound-only notification channel.
+//!
+//! Pushes messages to ntfy, Pushover, Gotify, or any webhook, for cron job
+//! results and heartbeat findings that should reach a phone without a chat
+//! app. There's nothing to receive: `start()` only checks in. A message's
+//! chat ID names the target in `channels.notify.targets`, so a cron job
+//! created with `--channel notify --to phone` goes to the `phone` target.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use patina_config::{NotifyConfig, NotifyService, NotifyTarget};
+use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+
+use crate::base::{Channel, ChannelCapabilities};
+
+/// Pushover's message limit.
+const PUSHOVER_MAX_CHARS: usize = 1024;
+
+/// Notification channel for the targets in `channels.notify`.
+pub struct NotifyChannel {
+    targets: HashMap<String, NotifyTarget>,
+    http: reqwest::Client,
+}
+
+impl NotifyChannel {
+    /// Create the channel, checking each target has what its service needs.
+    pub fn new(config: NotifyConfig) -> Result<Self, ChannelError> {
+        if config.targets.is_empty() {
+            return Err(ChannelError::NotConfigured {
+                channel: "Notify".into(),
+                what: "any targets".into(),
+            });
+        }
+        for (name, target) in &config.targets {
+            if let Some(what) = missing_setting(target) {
+                return Err(ChannelError::NotConfigured {
+                    channel: "Notify".into(),
+                    what: format!("{what} for target '{name}'"),
+                });
+            }
+        }
+        Ok(Self {
+            targets: config.targets,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    fn target(&self, name: &str) -> Result<&NotifyTarget> {
+        self.targets.get(name).with_context(|| {
+            let mut names: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            format!(
+                "no notify target '{name}' (configured: {})",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl Channel for NotifyChannel {
+    fn name(&self) -> &str {
+        "notify"
+    }
+
+    async fn start(&self, _inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+        let mut names: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        info!("Notify channel ready (targets: {})", names.join(", "));
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, msg: &OutboundMessage) -> Result<()> {
+        let target = self.target(&msg.chat_id)?;
+        build_request(&self.http, target, &msg.content)
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("notify target '{}' rejected the message", msg.chat_id))?;
+        debug!("Sent notification to '{}'", msg.chat_id);
+        Ok(())
+    }
+
+    /// Nobody writes in, so there's no one to allow.
+    fn is_allowed(&self, _sender_id: &str) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            supports_markdown: false,
+            supports_edit: false,
+            supports_attachments: false,
+            // ntfy's message limit; Pushover's smaller one is applied in build_request()
+            max_message_len: Some(4096),
+        }
+    }
+}
+
+/// The setting a target's service can't do without, if it's missing.
+fn missing_setting(target: &NotifyTarget) -> Option<&'static str> {
+    let unset = |value: &Option<String>| value.as_deref().is_none_or(str::is_empty);
+    match target.service {
+        NotifyService::Ntfy if unset(&target.topic) => Some("topic"),
+        NotifyService::Pushover if unset(&target.token) => Some("API token (token)"),
+        NotifyService::Pushover if unset(&target.user) => Some("user key (user)"),
+        NotifyService::Gotify if unset(&target.url) => Some("server URL (url)"),
+        NotifyService::Gotify if unset(&target.token) => Some("app token (token)"),
+        NotifyService::Webhook if unset(&target.url) => Some("URL (url)"),
+        _ => None,
+    }
+}
+
+/// The request that delivers `message` to `target`.
+fn build_request(
+    http: &reqwest::Client,
+    target: &NotifyTarget,
+    message: &str,
+) -> reqwest::RequestBuilder {
+    let title = target.title.as_deref().unwrap_or("patina");
+    let url = target
+        .url
+        .as_deref()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    match target.service {
+        NotifyService::Ntfy => {
+            let server = if url.is_empty() {
+                "https://ntfy.sh"
+            } else {
+                url
+            };
+            let mut body = json!({
+                "topic": target.topic,
+                "title": title,
+                "message": message,
+            });
+            if let Some(priority) = target.priority {
+                body["priority"] = json!(priority);
+            }
+            let request = http.post(server).json(&body);
+            match target.token {
+                Some(ref token) => request.bearer_auth(token),
+                None => request,
+            }
+        }
+        NotifyService::Pushover => {
+            let message: String = message.chars().take(PUSHOVER_MAX_CHARS).collect();
+            let mut form = vec![
+                ("token", target.token.clone().unwrap_or_default()),
+                ("user", target.user.clone().unwrap_or_default()),
+                ("title", title.to_string()),
+                ("message", message),
+            ];
+            if let Some(priority) = target.priority {
+                form.push(("priority", priority.to_string()));
+            }
+            http.post("https://api.pushover.net/1/messages.json")
+                .form(&form)
+        }
+        NotifyService::Gotify => {
+            let mut body = json!({ "title": title, "message": message });
+            if let Some(priority) = target.priority {
+                body["priority"] = json!(priority);
+            }
+            http.post(format!("{url}/message"))
+                .header("X-Gotify-Key", target.token.as_deref().unwrap_or_default())
+                .json(&body)
+        }
+        NotifyService::Webhook => {
+            let mut request = http
+                .post(url)
+                .json(&json!({ "title": title, "message": message }));
+            for (name, value) in &target.headers {
+                request = request.header(name, value);
+            }
+            request
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(service: NotifyService) -> NotifyTarget {
+        NotifyTarget {
+            service,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn targets_need_their_service_settings() {
+        assert_eq!(missing_setting(&target(NotifyService::Ntfy)), Some("topic"));
+        let mut pushover = target(NotifyService::Pushover);
+        pushover.token = Some("app".into());
+        assert_eq!(missing_setting(&pushover), Some("user key (user)"));
+        pushover.user = Some("user".into());
+        assert_eq!(missing_setting(&pushover), None);
+
+        let config = NotifyConfig {
+            enabled: true,
+            targets: HashMap::from([("ops".to_string(), target(NotifyService::Gotify))]),
+        };
+        let err = NotifyChannel::new(config).err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("server URL (url) for target 'ops'"));
+    }
+
+    #[test]
+    fn requests_match_each_service() {
+        let http = reqwest::Client::new();
+
+        let mut ntfy = target(NotifyService::Ntfy);
+        ntfy.topic = Some("alerts".into());
+        ntfy.priority = Some(4);
+        let request = build_request(&http, &ntfy, "Disk full").build().unwrap();
+        assert_eq!(request.url().as_str(), "https://ntfy.sh/");
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body["topic"], "alerts");
+        assert_eq!(body["title"], "patina");
+        assert_eq!(body["priority"], 4);
+
+        let mut gotify = target(NotifyService::Gotify);
+        gotify.url = Some("https://gotify.example/".into());
+        gotify.token = Some("app-token".into());
+        let request = build_request(&http, &gotify, "hi").build().unwrap();
+        assert_eq!(request.url().as_str(), "https://gotify.example/message");
+        assert_eq!(request.headers()["X-Gotify-Key"], "app-token");
+
+        let mut pushover = target(NotifyService::Pushover);
+        pushover.token = Some("app".into());
+        pushover.user = Some("user".into());
+        let long = "x".repeat(2000);
+        let request = build_request(&http, &pushover, &long).build().unwrap();
+        let form = String::from_utf8(request.body().unwrap().as_bytes().unwrap().to_vec()).unwrap();
+        assert!(form.contains(&format!("message={}", "x".repeat(PUSHOVER_MAX_CHARS))));
+        assert!(!form.contains(&"x".repeat(PUSHOVER_MAX_CHARS + 1)));
+    }
+}
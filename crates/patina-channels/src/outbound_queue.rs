@@ -0,0 +1,400 @@
+//! Bounded per-channel outbound queues.
+//!
+//! The bus fans replies out over a tokio broadcast channel, which drops
+//! messages for a receiver that falls behind. [`ChannelManager`] drains it
+//! into one [`OutboundQueue`] per channel, so a slow channel only backs up
+//! its own queue, and what happens when that queue is full is an explicit
+//! [`OverflowPolicy`]. Counters for every queue are kept process-wide for
+//! `/api/metrics`.
+//!
+//! [`ChannelManager`]: crate::manager::ChannelManager
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use chrono::{DateTime, Utc};
+use patina_config::{OutboundConfig, OverflowPolicy};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::warn;
+
+use patina_core::bus::OutboundMessage;
+
+/// Messages the outbound dispatcher missed because the bus overran it.
+static MISSED: AtomicU64 = AtomicU64::new(0);
+
+fn registry() -> &'static Mutex<BTreeMap<String, Arc<Counters>>> {
+    static QUEUES: OnceLock<Mutex<BTreeMap<String, Arc<Counters>>>> = OnceLock::new();
+    QUEUES.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Snapshot of one channel's queue.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// Messages waiting, including any spilled to disk.
+    pub queued: usize,
+    pub sent: u64,
+    pub failed: u64,
+    /// Messages dropped by the `dropOldest` policy.
+    pub dropped: u64,
+    /// Messages written to disk by the `spill` policy.
+    pub spilled: u64,
+    /// How long the last delivered message waited, in milliseconds.
+    pub lag_ms: u64,
+    pub max_lag_ms: u64,
+}
+
+/// Snapshot of all outbound queues.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboundStats {
+    /// Messages lost before reaching any queue because the dispatcher
+    /// lagged behind the bus.
+    pub missed: u64,
+    pub channels: BTreeMap<String, QueueStats>,
+}
+
+pub fn queue_stats() -> OutboundStats {
+    let queues = lock(registry());
+    OutboundStats {
+        missed: MISSED.load(Ordering::Relaxed),
+        channels: queues
+            .iter()
+            .map(|(name, c)| (name.clone(), c.snapshot()))
+            .collect(),
+    }
+}
+
+pub(crate) fn missed(n: u64) {
+    MISSED.fetch_add(n, Ordering::Relaxed);
+}
+
+/// Where spilled messages go: `config.spill_dir` or ~/.patina/spill.
+pub fn spill_dir(config: &OutboundConfig) -> PathBuf {
+    match config.spill_dir {
+        Some(ref dir) => PathBuf::from(dir),
+        None => patina_config::data_dir().join("spill"),
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    sent: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+    spilled: AtomicU64,
+    lag_ms: AtomicU64,
+    max_lag_ms: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> QueueStats {
+        QueueStats {
+            queued: self.queued.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            spilled: self.spilled.load(Ordering::Relaxed),
+            lag_ms: self.lag_ms.load(Ordering::Relaxed),
+            max_lag_ms: self.max_lag_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A message waiting in a queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Queued {
+    pub msg: OutboundMessage,
+    pub queued_at: DateTime<Utc>,
+}
+
+struct State {
+    memory: VecDeque<Queued>,
+    /// Messages in the spill file, all newer than those in memory.
+    on_disk: usize,
+}
+
+/// What [`OutboundQueue::try_push`] did with an item.
+enum Pushed {
+    /// Queued, evicting this older message to make room if set.
+    Queued(Option<Queued>),
+    /// Full under the `block` policy; the item is handed back.
+    Full(Queued),
+}
+
+/// One channel's queue. Meant for a single producer (the dispatcher) and a
+/// single consumer (the channel's delivery task).
+pub struct OutboundQueue {
+    channel: String,
+    capacity: usize,
+    policy: OverflowPolicy,
+    spill_path: PathBuf,
+    state: Mutex<State>,
+    space: Notify,
+    items: Notify,
+    counters: Arc<Counters>,
+}
+
+impl OutboundQueue {
+    /// Create the queue for `channel`. Messages a previous run left in its
+    /// spill file are picked up when `resume` is set and discarded otherwise
+    /// (the bus journal resends them instead).
+    pub fn new(
+        channel: &str,
+        capacity: usize,
+        policy: OverflowPolicy,
+        spill_dir: &Path,
+        resume: bool,
+    ) -> Self {
+        let spill_path = spill_dir.join(format!("{channel}.jsonl"));
+        let on_disk = match std::fs::read_to_string(&spill_path) {
+            Ok(_) if !resume => {
+                let _ = std::fs::remove_file(&spill_path);
+                0
+            }
+            Ok(text) => text.lines().filter(|l| !l.trim().is_empty()).count(),
+            Err(_) => 0,
+        };
+        let counters = Arc::new(Counters::default());
+        counters.queued.store(on_disk, Ordering::Relaxed);
+        lock(registry()).insert(channel.to_string(), counters.clone());
+        Self {
+            channel: channel.to_string(),
+            capacity: capacity.max(1),
+            policy,
+            spill_path,
+            state: Mutex::new(State {
+                memory: VecDeque::new(),
+                on_disk,
+            }),
+            space: Notify::new(),
+            items: Notify::new(),
+            counters,
+        }
+    }
+
+    /// Add a message, applying the overflow policy if the queue is full.
+    /// Returns the message `dropOldest` evicted to make room, which the
+    /// caller still has to account for (e.g. in the bus journal).
+    pub async fn push(&self, msg: OutboundMessage) -> Option<OutboundMessage> {
+        let mut item = Queued {
+            msg,
+            queued_at: Utc::now(),
+        };
+        loop {
+            let space = self.space.notified();
+            match self.try_push(item) {
+                Pushed::Queued(dropped) => {
+                    self.counters.queued.fetch_add(1, Ordering::Relaxed);
+                    self.items.notify_one();
+                    return dropped.map(|old| old.msg);
+                }
+                Pushed::Full(back) => item = back,
+            }
+            space.await;
+        }
+    }
+
+    /// Queue `item`, or hand it back if the policy is to block and there's
+    /// no room.
+    fn try_push(&self, item: Queued) -> Pushed {
+        let mut state = lock(&self.state);
+        // Once anything is on disk, newer messages follow it there
+        if state.on_disk > 0 || state.memory.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Block if state.on_disk == 0 => return Pushed::Full(item),
+                OverflowPolicy::DropOldest if state.on_disk == 0 => {
+                    let dropped = state.memory.pop_front();
+                    if let Some(ref old) = dropped {
+                        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                        warn!(
+                            "Outbound queue for {} is full, dropped a message to {}",
+                            self.channel, old.msg.chat_id
+                        );
+                    }
+                    state.memory.push_back(item);
+                    return Pushed::Queued(dropped);
+                }
+                _ => match self.spill(&item) {
+                    Ok(()) => {
+                        state.on_disk += 1;
+                        self.counters.spilled.fetch_add(1, Ordering::Relaxed);
+                        return Pushed::Queued(None);
+                    }
+                    // Better over capacity than lost
+                    Err(e) => warn!("Failed to spill outbound message for {}: {e}", self.channel),
+                },
+            }
+        }
+        state.memory.push_back(item);
+        Pushed::Queued(None)
+    }
+
+    /// Wait for the next message.
+    pub async fn pop(&self) -> Queued {
+        loop {
+            let items = self.items.notified();
+            if let Some(item) = self.try_pop() {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                self.space.notify_one();
+                return item;
+            }
+            items.await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<Queued> {
+        let mut state = lock(&self.state);
+        if state.memory.is_empty() && state.on_disk > 0 {
+            self.refill(&mut state);
+        }
+        state.memory.pop_front()
+    }
+
+    /// Record the outcome of sending `item`.
+    pub fn delivered(&self, item: &Queued, ok: bool) {
+        let lag = (Utc::now() - item.queued_at).num_milliseconds().max(0) as u64;
+        self.counters.lag_ms.store(lag, Ordering::Relaxed);
+        self.counters.max_lag_ms.fetch_max(lag, Ordering::Relaxed);
+        if ok {
+            self.counters.sent.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.counters.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn spill(&self, item: &Queued) -> std::io::Result<()> {
+        if let Some(parent) = self.spill_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.spill_path)?;
+        writeln!(file, "{}", serde_json::to_string(item)?)
+    }
+
+    /// Move up to `capacity` spilled messages back into memory.
+    fn refill(&self, state: &mut State) {
+        let text = match std::fs::read_to_string(&self.spill_path) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!(
+                    "Lost {} spilled message(s) for {}: {e}",
+                    state.on_disk, self.channel
+                );
+                self.counters
+                    .queued
+                    .fetch_sub(state.on_disk, Ordering::Relaxed);
+                state.on_disk = 0;
+                return;
+            }
+        };
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        for line in lines.by_ref().take(self.capacity) {
+            match serde_json::from_str(line) {
+                Ok(item) => state.memory.push_back(item),
+                Err(e) => {
+                    warn!(
+                        "Dropping unreadable spilled message for {}: {e}",
+                        self.channel
+                    );
+                    self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+        let rest: Vec<&str> = lines.collect();
+        state.on_disk = rest.len();
+        let result = if rest.is_empty() {
+            std::fs::remove_file(&self.spill_path)
+        } else {
+            std::fs::write(&self.spill_path, rest.join("\n") + "\n")
+        };
+        if let Err(e) = result {
+            warn!("Failed to rewrite spill file for {}: {e}", self.channel);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(g) => g,
+        Err(e) => e.into_inner(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn msg(content: &str) -> OutboundMessage {
+        OutboundMessage {
+            channel: "test".into(),
+            chat_id: "1".into(),
+            content: content.into(),
+            reply_to: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    async fn drain(queue: &OutboundQueue, n: usize) -> Vec<String> {
+        let mut out = Vec::new();
+        for _ in 0..n {
+            let item = queue.pop().await;
+            queue.delivered(&item, true);
+            out.push(item.msg.content);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let queue =
+            OutboundQueue::new("drop-test", 2, OverflowPolicy::DropOldest, dir.path(), true);
+        assert!(queue.push(msg("a")).await.is_none());
+        assert!(queue.push(msg("b")).await.is_none());
+        // The evicted message is handed back so its journal entry can be completed
+        assert_eq!(queue.push(msg("c")).await.unwrap().content, "a");
+        assert_eq!(drain(&queue, 2).await, ["b", "c"]);
+        let stats = queue_stats().channels["drop-test"];
+        assert_eq!((stats.dropped, stats.sent, stats.queued), (1, 2, 0));
+
+        // Spilled messages keep their order, and survive a restart
+        let queue = OutboundQueue::new("spill-test", 2, OverflowPolicy::Spill, dir.path(), true);
+        for content in ["a", "b", "c", "d", "e"] {
+            queue.push(msg(content)).await;
+        }
+        assert_eq!(queue_stats().channels["spill-test"].spilled, 3);
+        assert_eq!(drain(&queue, 4).await, ["a", "b", "c", "d"]);
+        let queue = OutboundQueue::new("spill-test", 2, OverflowPolicy::Spill, dir.path(), true);
+        assert_eq!(drain(&queue, 1).await, ["e"]);
+        assert!(!dir.path().join("spill-test.jsonl").exists());
+
+        // Blocking waits for room
+        let queue = Arc::new(OutboundQueue::new(
+            "block-test",
+            1,
+            OverflowPolicy::Block,
+            dir.path(),
+            true,
+        ));
+        queue.push(msg("a")).await;
+        let pusher = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push(msg("b")).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!pusher.is_finished());
+        assert_eq!(drain(&queue, 2).await, ["a", "b"]);
+        pusher.await.unwrap();
+    }
+}
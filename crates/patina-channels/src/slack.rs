@@ -6,8 +6,12 @@
 //! - Thread support via thread_ts
 //! - File attachment URLs
 //! - Allowlist access control
+//! - Slash commands (`/patina ask ...`, `/patina task ...`), answered through
+//!   the command's response URL so they work where the bot can't post or DM
+//! - An App Home tab listing open tasks and recent Slack conversations
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -18,15 +22,30 @@ use tracing::{debug, error, info, warn};
 
 use patina_config::SlackConfig;
 use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+use patina_core::session::{SessionInfo, SessionManager};
+use patina_core::task::{Task, TaskManager, TaskPriority, TaskStatus};
 
-use crate::base::Channel;
+use crate::base::{Channel, ChannelCapabilities};
 use crate::slack_markdown::markdown_to_slack_mrkdwn;
 
-/// State passed to the Socket Mode push event handler via SlackClientEventsUserState.
+/// Metadata key holding a slash command's response URL. Replies to the
+/// command are posted there instead of through `chat.postMessage`.
+const RESPONSE_URL: &str = "response_url";
+
+/// Open tasks and recent conversations shown on the App Home tab.
+const HOME_TASKS: usize = 20;
+const HOME_SESSIONS: usize = 10;
+
+/// State passed to the Socket Mode event handlers via SlackClientEventsUserState.
 struct SlackPushState {
     inbound_tx: mpsc::Sender<InboundMessage>,
     allow_from: Vec<String>,
     bot_token: SlackApiToken,
+    replies_in_channel: bool,
+    commands: Vec<(String, String)>,
+    tasks: Option<Arc<Mutex<TaskManager>>>,
+    sessions_dir: Option<PathBuf>,
 }
 
 /// Slack channel using Socket Mode for receiving events and Web API for sending.
@@ -34,23 +53,33 @@ pub struct SlackChannel {
     config: SlackConfig,
     client: Arc<SlackHyperClient>,
     bot_token: SlackApiToken,
+    http: reqwest::Client,
+    commands: Vec<(String, String)>,
+    tasks: Option<Arc<Mutex<TaskManager>>>,
+    sessions_dir: Option<PathBuf>,
     shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
 }
 
 impl SlackChannel {
     /// Create a new Slack channel from config.
-    pub fn new(config: SlackConfig) -> Result<Self> {
+    pub fn new(config: SlackConfig) -> Result<Self, ChannelError> {
+        let not_configured = |what: &str| ChannelError::NotConfigured {
+            channel: "Slack".into(),
+            what: what.into(),
+        };
         if config.app_token.is_empty() {
-            return Err(anyhow::anyhow!("Slack app token (xapp-*) not configured"));
+            return Err(not_configured("app token (xapp-*)"));
         }
         if config.bot_token.is_empty() {
-            return Err(anyhow::anyhow!("Slack bot token (xoxb-*) not configured"));
+            return Err(not_configured("bot token (xoxb-*)"));
         }
 
-        let client = Arc::new(SlackClient::new(
-            SlackClientHyperConnector::new()
-                .map_err(|e| anyhow::anyhow!("Failed to create Slack HTTP connector: {e}"))?,
-        ));
+        let client = Arc::new(SlackClient::new(SlackClientHyperConnector::new().map_err(
+            |e| ChannelError::Setup {
+                channel: "Slack".into(),
+                source: anyhow::anyhow!("failed to create HTTP connector: {e}"),
+            },
+        )?));
 
         let bot_token = SlackApiToken::new(config.bot_token.clone().into());
 
@@ -58,10 +87,48 @@ impl SlackChannel {
             config,
             client,
             bot_token,
+            http: reqwest::Client::new(),
+            commands: Vec::new(),
+            tasks: None,
+            sessions_dir: None,
             shutdown_tx: Mutex::new(None),
         })
     }
 
+    /// Chat commands `/patina <name>` runs, as `(name, description)` pairs.
+    /// Other text after `/patina` is asked as a question.
+    pub fn with_commands(mut self, commands: Vec<(String, String)>) -> Self {
+        self.commands = commands;
+        self
+    }
+
+    /// List these tasks on the App Home tab.
+    pub fn with_tasks(mut self, tasks: Arc<Mutex<TaskManager>>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// List recent Slack conversations from this directory on the App Home tab.
+    pub fn with_sessions_dir(mut self, sessions_dir: PathBuf) -> Self {
+        self.sessions_dir = Some(sessions_dir);
+        self
+    }
+
+    /// Post a reply to a slash command's response URL.
+    async fn respond(&self, response_url: &str, text: String) -> Result<()> {
+        let body = serde_json::json!({
+            "response_type": response_type(self.config.slash_replies_in_channel),
+            "text": text,
+        });
+        self.http
+            .post(response_url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
     fn check_allowed(&self, sender_id: &str) -> bool {
         is_sender_allowed(sender_id, &self.config.allow_from)
     }
@@ -76,6 +143,7 @@ async fn handle_push_event(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let msg_event = match event.event {
         SlackEventCallbackBody::Message(ev) => ev,
+        SlackEventCallbackBody::AppHomeOpened(ev) => return publish_home(ev, client, states).await,
         _ => return Ok(()),
     };
 
@@ -113,23 +181,7 @@ async fn handle_push_event(
     let sender_id = if let Some(ref username) = msg_event.sender.username {
         format!("{user_id}|{username}")
     } else {
-        // Try to look up the username via API
-        let session = client.open_session(bot_token);
-        match session
-            .users_info(&SlackApiUsersInfoRequest::new(
-                msg_event.sender.user.clone().unwrap(),
-            ))
-            .await
-        {
-            Ok(info) => {
-                if let Some(ref name) = info.user.name {
-                    format!("{user_id}|{name}")
-                } else {
-                    user_id.clone()
-                }
-            }
-            Err(_) => user_id.clone(),
-        }
+        lookup_sender(&client, bot_token, msg_event.sender.user.clone().unwrap()).await
     };
 
     // Check allowlist
@@ -215,6 +267,267 @@ async fn handle_push_event(
     Ok(())
 }
 
+/// "U1234|username", or just the ID when the name can't be looked up.
+async fn lookup_sender(
+    client: &SlackHyperClient,
+    bot_token: &SlackApiToken,
+    user: SlackUserId,
+) -> String {
+    let user_id = user.to_string();
+    let session = client.open_session(bot_token);
+    match session
+        .users_info(&SlackApiUsersInfoRequest::new(user))
+        .await
+    {
+        Ok(info) => match info.user.name {
+            Some(ref name) => format!("{user_id}|{name}"),
+            None => user_id,
+        },
+        Err(_) => user_id,
+    }
+}
+
+/// Slash command handler. The command is acknowledged straight away (Slack
+/// wants an answer within 3 seconds) and the reply follows through its
+/// response URL.
+async fn handle_command_event(
+    event: SlackCommandEvent,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) -> Result<SlackCommandEventResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let ephemeral = |text: String| {
+        Ok(
+            SlackCommandEventResponse::new(SlackMessageContent::new().with_text(text))
+                .with_response_type(SlackMessageResponseType::Ephemeral),
+        )
+    };
+
+    let state_guard = states.read().await;
+    let push_state = state_guard
+        .get_user_state::<SlackPushState>()
+        .expect("SlackPushState not found in user state");
+
+    let user_id = event.user_id.to_string();
+    let sender_id = lookup_sender(&client, &push_state.bot_token, event.user_id).await;
+    if !is_sender_allowed(&sender_id, &push_state.allow_from) {
+        warn!("Access denied for sender {sender_id} on Slack. Add to allowFrom to grant access.");
+        return ephemeral("You don't have access to this bot.".into());
+    }
+
+    let command = event.command.to_string();
+    let text = event.text.unwrap_or_default();
+    let content = match parse_slash(&text, &push_state.commands) {
+        SlashAction::Help => return ephemeral(slash_usage(&command, &push_state.commands)),
+        SlashAction::Ask(content) | SlashAction::Command(content) => content,
+    };
+    debug!("Slack slash command from {sender_id}: {command} {text}");
+
+    let channel_id = event.channel_id.to_string();
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        RESPONSE_URL.to_string(),
+        serde_json::Value::String(event.response_url.0.to_string()),
+    );
+    metadata.insert("user_id".to_string(), serde_json::Value::String(user_id));
+    metadata.insert(
+        "channel_id".to_string(),
+        serde_json::Value::String(channel_id.clone()),
+    );
+
+    let inbound = InboundMessage {
+        channel: "slack".to_string(),
+        sender_id,
+        chat_id: channel_id,
+        content,
+        media: Vec::new(),
+        metadata,
+        timestamp: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+    };
+    if let Err(e) = push_state.inbound_tx.send(inbound).await {
+        error!("Failed to send inbound Slack command: {e}");
+        return ephemeral("Sorry, I can't take that right now.".into());
+    }
+    if push_state.replies_in_channel {
+        // An in-channel acknowledgement with no text shows the command itself
+        Ok(SlackCommandEventResponse::new(SlackMessageContent::new())
+            .with_response_type(SlackMessageResponseType::InChannel))
+    } else {
+        ephemeral("Working on it…".into())
+    }
+}
+
+fn response_type(in_channel: bool) -> &'static str {
+    if in_channel {
+        "in_channel"
+    } else {
+        "ephemeral"
+    }
+}
+
+/// What `/patina <text>` asks for.
+#[derive(Debug, PartialEq)]
+enum SlashAction {
+    /// No text: show the usage.
+    Help,
+    /// A question for the agent.
+    Ask(String),
+    /// A chat command, as the `/name args` text the gateway dispatches.
+    Command(String),
+}
+
+/// `ask <question>` asks, `<command> <args>` runs one of `commands`, and
+/// anything else is asked as it is.
+fn parse_slash(text: &str, commands: &[(String, String)]) -> SlashAction {
+    let text = text.trim();
+    let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let rest = rest.trim();
+    let word = word.to_lowercase();
+    if text.is_empty() || (word == "ask" && rest.is_empty()) {
+        return SlashAction::Help;
+    }
+    if word == "ask" {
+        return SlashAction::Ask(rest.to_string());
+    }
+    if commands.iter().any(|(name, _)| *name == word) {
+        let command = format!("/{word} {rest}");
+        return SlashAction::Command(command.trim_end().to_string());
+    }
+    SlashAction::Ask(text.to_string())
+}
+
+fn slash_usage(command: &str, commands: &[(String, String)]) -> String {
+    let mut out = format!("`{command} ask <question>`: ask a question");
+    for (name, description) in commands {
+        out.push_str(&format!("\n`{command} {name}`: {description}"));
+    }
+    out
+}
+
+/// Render the App Home tab for whoever opened it.
+async fn publish_home(
+    event: SlackAppHomeOpenedEvent,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if event.tab.as_deref() != Some("home") {
+        return Ok(());
+    }
+    let state_guard = states.read().await;
+    let push_state = state_guard
+        .get_user_state::<SlackPushState>()
+        .expect("SlackPushState not found in user state");
+
+    let sender_id = lookup_sender(&client, &push_state.bot_token, event.user.clone()).await;
+    let blocks = if is_sender_allowed(&sender_id, &push_state.allow_from) {
+        let tasks: Vec<Task> = match push_state.tasks {
+            Some(ref tasks) => tasks
+                .lock()
+                .await
+                .list(None, None)
+                .into_iter()
+                .filter(|t| t.status != TaskStatus::Done)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let sessions: Vec<SessionInfo> = match push_state.sessions_dir {
+            Some(ref dir) => SessionManager::new(dir.clone())
+                .list_sessions()
+                .into_iter()
+                .filter(|s| s.key.starts_with("slack:"))
+                .take(HOME_SESSIONS)
+                .collect(),
+            None => Vec::new(),
+        };
+        home_blocks(tasks, &sessions)
+    } else {
+        vec![markdown_section(
+            "You don't have access to this bot. Ask its owner to add you to `allowFrom`.".into(),
+        )]
+    };
+
+    let session = client.open_session(&push_state.bot_token);
+    session
+        .views_publish(&SlackApiViewsPublishRequest::new(
+            event.user,
+            SlackView::Home(SlackHomeView::new(blocks)),
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Open tasks, in progress first and then by priority, followed by the most
+/// recent conversations.
+fn home_blocks(mut tasks: Vec<Task>, sessions: &[SessionInfo]) -> Vec<SlackBlock> {
+    let status_rank = |t: &Task| match t.status {
+        TaskStatus::InProgress => 0,
+        TaskStatus::Todo => 1,
+        TaskStatus::Backlog => 2,
+        TaskStatus::Done => 3,
+    };
+    let priority_rank = |t: &Task| match t.priority {
+        TaskPriority::Urgent => 0,
+        TaskPriority::High => 1,
+        TaskPriority::Medium => 2,
+        TaskPriority::Low => 3,
+    };
+    tasks.sort_by_key(|t| (status_rank(t), priority_rank(t), -t.updated_at_ms));
+
+    let mut blocks = vec![header("Tasks")];
+    if tasks.is_empty() {
+        blocks.push(markdown_section("No open tasks.".into()));
+    }
+    for task in tasks.iter().take(HOME_TASKS) {
+        let mut line = format!(
+            "*{}*\n{} · {:?}",
+            task.title,
+            task.status.as_str().replace('_', " "),
+            task.priority
+        );
+        if let Some(ref assignee) = task.assignee {
+            line.push_str(&format!(" · {assignee}"));
+        }
+        line.push_str(&format!(" · `{}`", task.id));
+        blocks.push(markdown_section(line));
+    }
+    if tasks.len() > HOME_TASKS {
+        blocks.push(markdown_section(format!(
+            "…and {} more on the task board.",
+            tasks.len() - HOME_TASKS
+        )));
+    }
+
+    blocks.push(SlackDividerBlock::new().into());
+    blocks.push(header("Recent conversations"));
+    if sessions.is_empty() {
+        blocks.push(markdown_section("No conversations yet.".into()));
+    }
+    for info in sessions {
+        let chat = info.key.trim_start_matches("slack:");
+        let (channel_id, thread) = parse_chat_id(chat).unwrap_or_default();
+        let mut line = format!("<#{channel_id}>");
+        if thread.is_some() {
+            line.push_str(" (thread)");
+        }
+        if let Ok(updated) = chrono::DateTime::parse_from_rfc3339(&info.updated_at) {
+            let updated = updated.with_timezone(&chrono::Local);
+            line.push_str(&format!(" · {}", updated.format("%b %-d, %H:%M")));
+        }
+        blocks.push(markdown_section(line));
+    }
+    blocks
+}
+
+fn header(text: &str) -> SlackBlock {
+    SlackHeaderBlock::new(SlackBlockPlainTextOnly::from(text)).into()
+}
+
+fn markdown_section(text: String) -> SlackBlock {
+    SlackSectionBlock::new()
+        .with_text(SlackBlockText::MarkDown(SlackBlockMarkDownText::new(text)))
+        .into()
+}
+
 fn slack_error_handler(
     err: Box<dyn std::error::Error + Send + Sync>,
     _client: Arc<SlackHyperClient>,
@@ -257,12 +570,18 @@ impl Channel for SlackChannel {
 
         let client = self.client.clone();
 
-        let callbacks = SlackSocketModeListenerCallbacks::new().with_push_events(handle_push_event);
+        let callbacks = SlackSocketModeListenerCallbacks::new()
+            .with_push_events(handle_push_event)
+            .with_command_events(handle_command_event);
 
         let push_state = SlackPushState {
             inbound_tx,
             allow_from: self.config.allow_from.clone(),
             bot_token: self.bot_token.clone(),
+            replies_in_channel: self.config.slash_replies_in_channel,
+            commands: self.commands.clone(),
+            tasks: self.tasks.clone(),
+            sessions_dir: self.sessions_dir.clone(),
         };
 
         let listener_environment = Arc::new(
@@ -311,27 +630,31 @@ impl Channel for SlackChannel {
     async fn send(&self, msg: &OutboundMessage) -> Result<()> {
         let (channel_id, thread_ts) = parse_chat_id(&msg.chat_id)?;
 
-        let session = self.client.open_session(&self.bot_token);
+        let mrkdwn_content = markdown_to_slack_mrkdwn(&msg.content);
 
-        // Split into chunks if needed (Slack limit: 40,000 chars)
-        let chunks = split_message(&msg.content, 40_000);
+        // A reply to a slash command. Its URL expires after 30 minutes, so
+        // fall back to posting in the channel.
+        if let Some(url) = msg.metadata.get(RESPONSE_URL).and_then(|v| v.as_str()) {
+            match self.respond(url, mrkdwn_content.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Slack response URL failed, posting instead: {e}"),
+            }
+        }
 
-        for chunk in &chunks {
-            let mrkdwn_content = markdown_to_slack_mrkdwn(chunk);
+        let session = self.client.open_session(&self.bot_token);
 
-            let mut request = SlackApiChatPostMessageRequest::new(
-                channel_id.clone().into(),
-                SlackMessageContent::new().with_text(mrkdwn_content),
-            );
+        let mut request = SlackApiChatPostMessageRequest::new(
+            channel_id.into(),
+            SlackMessageContent::new().with_text(mrkdwn_content),
+        );
 
-            if let Some(ref ts) = thread_ts {
-                request = request.with_thread_ts(ts.clone().into());
-            }
+        if let Some(ts) = thread_ts {
+            request = request.with_thread_ts(ts.into());
+        }
 
-            if let Err(e) = session.chat_post_message(&request).await {
-                error!("Error sending Slack message: {e}");
-                return Err(anyhow::anyhow!("Failed to send Slack message: {e}"));
-            }
+        if let Err(e) = session.chat_post_message(&request).await {
+            error!("Error sending Slack message: {e}");
+            return Err(anyhow::anyhow!("Failed to send Slack message: {e}"));
         }
 
         Ok(())
@@ -347,6 +670,15 @@ impl Channel for SlackChannel {
             .as_deref()
             .unwrap_or("No markdown tables. Never use markdown table syntax — Slack does not support table formatting. Use plain text lists instead.")
     }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: false,
+            supports_attachments: false,
+            max_message_len: Some(40_000),
+        }
+    }
 }
 
 /// Parse chat_id into channel ID and optional thread_ts.
@@ -359,33 +691,6 @@ fn parse_chat_id(chat_id: &str) -> Result<(String, Option<String>)> {
     Ok((channel_id, thread_ts))
 }
 
-/// Split a message into chunks that fit within Slack's character limit.
-fn split_message(text: &str, max_len: usize) -> Vec<&str> {
-    if text.len() <= max_len {
-        return vec![text];
-    }
-
-    let mut chunks = Vec::new();
-    let mut remaining = text;
-
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining);
-            break;
-        }
-
-        let split_at = remaining[..max_len]
-            .rfind('\n')
-            .map(|pos| pos + 1)
-            .unwrap_or(max_len);
-
-        chunks.push(&remaining[..split_at]);
-        remaining = &remaining[split_at..];
-    }
-
-    chunks
-}
-
 /// Check if a sender is allowed based on the allow_from list.
 ///
 /// Matches against the full sender_id string, the user ID part,
@@ -449,27 +754,60 @@ mod tests {
     }
 
     #[test]
-    fn split_message_short() {
-        let chunks = split_message("hello", 40_000);
-        assert_eq!(chunks, vec!["hello"]);
-    }
-
-    #[test]
-    fn split_message_at_newline() {
-        let line = "a".repeat(20_000);
-        let text = format!("{line}\n{line}\n{line}");
-        let chunks = split_message(&text, 40_001);
-        assert_eq!(chunks.len(), 2);
-        assert!(chunks[0].ends_with('\n'));
+    fn slash_text_asks_or_runs_a_command() {
+        let commands = vec![("task".to_string(), "Add a task".to_string())];
+        assert_eq!(parse_slash("", &commands), SlashAction::Help);
+        assert_eq!(parse_slash(" ask ", &commands), SlashAction::Help);
+        assert_eq!(
+            parse_slash("ask what's due?", &commands),
+            SlashAction::Ask("what's due?".into())
+        );
+        assert_eq!(
+            parse_slash("Task  buy milk", &commands),
+            SlashAction::Command("/task buy milk".into())
+        );
+        assert_eq!(
+            parse_slash("what's due?", &commands),
+            SlashAction::Ask("what's due?".into())
+        );
+        assert!(slash_usage("/patina", &commands).contains("`/patina task`: Add a task"));
     }
 
     #[test]
-    fn split_message_no_newline() {
-        let text = "a".repeat(50_000);
-        let chunks = split_message(&text, 40_000);
-        assert_eq!(chunks.len(), 2);
-        assert_eq!(chunks[0].len(), 40_000);
-        assert_eq!(chunks[1].len(), 10_000);
+    fn home_lists_tasks_in_progress_first() {
+        let task = |id: &str, status: TaskStatus, priority: TaskPriority| Task {
+            id: id.into(),
+            title: format!("title-{id}"),
+            description: String::new(),
+            status,
+            priority,
+            assignee: None,
+            tags: Vec::new(),
+            created_by: "test".into(),
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            completed_at_ms: None,
+            comments: Vec::new(),
+        };
+        let tasks = vec![
+            task("a", TaskStatus::Backlog, TaskPriority::Urgent),
+            task("b", TaskStatus::Todo, TaskPriority::Low),
+            task("c", TaskStatus::Todo, TaskPriority::High),
+            task("d", TaskStatus::InProgress, TaskPriority::Low),
+        ];
+        let sessions = vec![SessionInfo {
+            key: "slack:C1:1700000000.1".into(),
+            created_at: String::new(),
+            updated_at: "2026-03-01T10:00:00Z".into(),
+            path: String::new(),
+        }];
+        let json = serde_json::to_string(&home_blocks(tasks, &sessions)).unwrap();
+        let order: Vec<usize> = ["title-d", "title-c", "title-b", "title-a"]
+            .iter()
+            .map(|t| json.find(t).unwrap())
+            .collect();
+        assert!(order.windows(2).all(|w| w[0] < w[1]));
+        assert!(json.contains("<#C1> (thread)"));
     }
 
     #[test]
@@ -0,0 +1,803 @@
+//! Microsoft Teams channel.
+//!
+//! Two ways in, both on a messaging endpoint at `/api/messages`:
+//! - Bot mode (default): an Azure Bot registration posts activities signed
+//!   with a Bot Framework JWT, checked against the framework's published
+//!   keys. Replies go out through the Bot Connector REST API with a
+//!   client-credentials token. Each conversation's service URL is kept in
+//!   `~/.patina/teams/conversations.json`, so cron jobs and the message tool
+//!   can write to a chat after a restart.
+//! - Outgoing webhook mode: a team's outgoing webhook posts @mentions signed
+//!   with HMAC-SHA256. The reply has to be the HTTP response, so the request
+//!   waits for it up to [`WEBHOOK_WAIT`]; later replies go to
+//!   `incomingWebhookUrl`.
+//!
+//! Replies are Adaptive Cards (see [`crate::teams_card`]). Senders are
+//! matched against `allowFrom` by their Entra (AAD) object ID.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use base64::Engine;
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tracing::{debug, error, info, warn};
+
+use patina_config::{TeamsConfig, TeamsMode};
+use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+
+use crate::base::{Channel, ChannelCapabilities};
+use crate::teams_card::markdown_to_adaptive_card;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long an outgoing webhook request waits for the reply. Teams gives
+/// up after 10 seconds.
+const WEBHOOK_WAIT: Duration = Duration::from_secs(9);
+
+const OPENID_CONFIG: &str = "https://login.botframework.com/v1/.well-known/openidconfiguration";
+const BOT_ISSUER: &str = "https://api.botframework.com";
+const CONNECTOR_SCOPE: &str = "https://api.botframework.com/.default";
+/// How long the framework's signing keys are trusted before refetching.
+const KEYS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// Least time between refetches for a key ID that isn't in the set.
+const KEYS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+/// An incoming Bot Framework activity; only the fields the channel reads.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Activity {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    service_url: Option<String>,
+    from: Account,
+    conversation: Conversation,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Account {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    aad_object_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Conversation {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Attachment {
+    content_type: String,
+    #[serde(default)]
+    content_url: Option<String>,
+    #[serde(default)]
+    content: Option<Value>,
+}
+
+/// Teams channel using the Bot Framework or an outgoing webhook.
+pub struct TeamsChannel {
+    config: TeamsConfig,
+    http: reqwest::Client,
+    webhook_key: Vec<u8>,
+    auth: Arc<BotAuth>,
+    conversations: Arc<Conversations>,
+    /// Outgoing webhook requests waiting for their reply, by chat ID.
+    pending: Arc<DashMap<String, oneshot::Sender<String>>>,
+    connector_token: Mutex<Option<(String, Instant)>>,
+    shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+#[derive(Clone)]
+struct TeamsState {
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    allow_from: Arc<Vec<String>>,
+    webhook_key: Arc<Vec<u8>>,
+    auth: Arc<BotAuth>,
+    conversations: Arc<Conversations>,
+    pending: Arc<DashMap<String, oneshot::Sender<String>>>,
+    late_replies: bool,
+}
+
+impl TeamsChannel {
+    /// Create a new Teams channel from config.
+    pub fn new(config: TeamsConfig) -> Result<Self, ChannelError> {
+        let not_configured = |what: &str| ChannelError::NotConfigured {
+            channel: "Teams".into(),
+            what: what.into(),
+        };
+        let mut webhook_key = Vec::new();
+        match config.mode {
+            TeamsMode::Bot => {
+                if config.app_id.is_empty() {
+                    return Err(not_configured("app ID (appId)"));
+                }
+                if config.app_password.is_empty() {
+                    return Err(not_configured("app password (appPassword)"));
+                }
+            }
+            TeamsMode::OutgoingWebhook => {
+                if config.webhook_secret.is_empty() {
+                    return Err(not_configured("outgoing webhook secret (webhookSecret)"));
+                }
+                webhook_key = base64::engine::general_purpose::STANDARD
+                    .decode(config.webhook_secret.trim())
+                    .map_err(|e| ChannelError::Setup {
+                        channel: "Teams".into(),
+                        source: anyhow::anyhow!("webhookSecret isn't base64: {e}"),
+                    })?;
+            }
+        }
+
+        let http = reqwest::Client::new();
+        let path = patina_config::data_dir()
+            .join("teams")
+            .join("conversations.json");
+        Ok(Self {
+            auth: Arc::new(BotAuth::new(http.clone(), config.app_id.clone())),
+            conversations: Arc::new(Conversations::load(path)),
+            config,
+            http,
+            webhook_key,
+            pending: Arc::new(DashMap::new()),
+            connector_token: Mutex::new(None),
+            shutdown_tx: Mutex::new(None),
+        })
+    }
+
+    /// A Bot Connector token, reused until shortly before it expires.
+    async fn connector_token(&self) -> Result<String> {
+        let mut cached = self.connector_token.lock().await;
+        if let Some((ref token, expires)) = *cached {
+            if Instant::now() < expires {
+                return Ok(token.clone());
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct TokenResponse {
+            access_token: String,
+            expires_in: u64,
+        }
+        let tenant = self
+            .config
+            .tenant_id
+            .as_deref()
+            .unwrap_or("botframework.com");
+        let response: TokenResponse = self
+            .http
+            .post(format!(
+                "https://login.microsoftonline.com/{tenant}/oauth2/v2.0/token"
+            ))
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", self.config.app_id.as_str()),
+                ("client_secret", self.config.app_password.as_str()),
+                ("scope", CONNECTOR_SCOPE),
+            ])
+            .send()
+            .await?
+            .error_for_status()
+            .context("Teams token request failed")?
+            .json()
+            .await?;
+        let lifetime = Duration::from_secs(response.expires_in.saturating_sub(300));
+        *cached = Some((response.access_token.clone(), Instant::now() + lifetime));
+        Ok(response.access_token)
+    }
+
+    async fn send_bot(&self, msg: &OutboundMessage, card: Value) -> Result<()> {
+        let service_url = msg
+            .metadata
+            .get("service_url")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .or_else(|| self.conversations.service_url(&msg.chat_id))
+            .with_context(|| {
+                format!(
+                    "no Teams conversation '{}' on record; someone there has to message the bot first",
+                    msg.chat_id
+                )
+            })?;
+        let mut activity = json!({ "type": "message", "attachments": [attachment(card)] });
+        if let Some(id) = msg.metadata.get("activity_id").and_then(|v| v.as_str()) {
+            activity["replyToId"] = json!(id);
+        }
+        let conversation: String =
+            url::form_urlencoded::byte_serialize(msg.chat_id.as_bytes()).collect();
+        let url = format!(
+            "{}/v3/conversations/{conversation}/activities",
+            service_url.trim_end_matches('/')
+        );
+        let token = self.connector_token().await?;
+        self.http
+            .post(url)
+            .bearer_auth(token)
+            .json(&activity)
+            .send()
+            .await?
+            .error_for_status()
+            .context("Teams rejected the message")?;
+        Ok(())
+    }
+
+    async fn send_webhook(&self, msg: &OutboundMessage, card: Value) -> Result<()> {
+        if let Some((_, waiting)) = self.pending.remove(&msg.chat_id) {
+            if waiting.send(msg.content.clone()).is_ok() {
+                return Ok(());
+            }
+        }
+        let url = self.config.incoming_webhook_url.as_deref().context(
+            "the reply came after Teams stopped waiting; set incomingWebhookUrl to deliver late replies",
+        )?;
+        let body = json!({ "type": "message", "attachments": [attachment(card)] });
+        self.http
+            .post(url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()
+            .context("Teams incoming webhook rejected the message")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Channel for TeamsChannel {
+    fn name(&self) -> &str {
+        "teams"
+    }
+
+    async fn start(&self, inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+        let state = TeamsState {
+            inbound_tx,
+            allow_from: Arc::new(self.config.allow_from.clone()),
+            webhook_key: Arc::new(self.webhook_key.clone()),
+            auth: self.auth.clone(),
+            conversations: self.conversations.clone(),
+            pending: self.pending.clone(),
+            late_replies: self.config.incoming_webhook_url.is_some(),
+        };
+        let router = match self.config.mode {
+            TeamsMode::Bot => Router::new().route("/api/messages", post(handle_bot)),
+            TeamsMode::OutgoingWebhook => {
+                Router::new().route("/api/messages", post(handle_webhook))
+            }
+        }
+        .with_state(state);
+
+        let listen = self.config.listen.as_deref().unwrap_or("0.0.0.0");
+        let port = self.config.port.unwrap_or(3978);
+        let addr: SocketAddr = format!("{listen}:{port}")
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid Teams listen address '{listen}:{port}': {e}"))?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!(
+            "Teams channel ({:?} mode) listening on http://{addr}/api/messages",
+            self.config.mode
+        );
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        *self.shutdown_tx.lock().await = Some(shutdown_tx);
+        tokio::spawn(async move {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            });
+            if let Err(e) = server.await {
+                error!("Teams server error: {e}");
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        info!("Stopping Teams channel...");
+        if let Some(tx) = self.shutdown_tx.lock().await.take() {
+            let _ = tx.send(());
+        }
+        self.pending.clear();
+        Ok(())
+    }
+
+    async fn send(&self, msg: &OutboundMessage) -> Result<()> {
+        let card = markdown_to_adaptive_card(&msg.content);
+        match self.config.mode {
+            TeamsMode::Bot => self.send_bot(msg, card).await,
+            TeamsMode::OutgoingWebhook => self.send_webhook(msg, card).await,
+        }
+    }
+
+    fn is_allowed(&self, sender_id: &str) -> bool {
+        is_sender_allowed(sender_id, &self.config.allow_from)
+    }
+
+    fn prompt_rules(&self) -> &str {
+        self.config.system_prompt_rules.as_deref().unwrap_or(
+            "Replies are shown as cards in Microsoft Teams. Keep tables to a few narrow columns.",
+        )
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: false,
+            supports_attachments: false,
+            // Teams caps a message, card JSON included, at about 28 KB
+            max_message_len: Some(20_000),
+        }
+    }
+}
+
+/// Bot mode: check the framework's token, then pass the message on. Teams
+/// only needs to hear that it arrived; the reply is sent separately.
+async fn handle_bot(
+    State(state): State<TeamsState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let activity: Activity = match serde_json::from_slice(&body) {
+        Ok(activity) => activity,
+        Err(e) => {
+            warn!("Ignoring malformed Teams activity: {e}");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    let service_url = activity.service_url.clone().unwrap_or_default();
+    let authorization = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if let Err(e) = state.auth.verify(authorization, &service_url).await {
+        warn!("Rejected Teams request: {e:#}");
+        return StatusCode::UNAUTHORIZED;
+    }
+    if !service_url.is_empty() {
+        state
+            .conversations
+            .remember(&activity.conversation.id, &service_url);
+    }
+
+    let Some(inbound) = to_inbound(activity) else {
+        return StatusCode::OK;
+    };
+    if !is_sender_allowed(&inbound.sender_id, &state.allow_from) {
+        warn!(
+            "Access denied for sender {} on Teams. Add their AAD object ID to allowFrom to grant access.",
+            inbound.sender_id
+        );
+        return StatusCode::OK;
+    }
+    if let Err(e) = state.inbound_tx.send(inbound).await {
+        error!("Failed to send inbound Teams message: {e}");
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    StatusCode::OK
+}
+
+/// Outgoing webhook mode: check the signature, pass the message on, and
+/// answer with the reply if it comes in time.
+async fn handle_webhook(
+    State(state): State<TeamsState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let reply = |text: &str| axum::Json(json!({ "type": "message", "text": text })).into_response();
+
+    let signature = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("HMAC "));
+    if !signature.is_some_and(|s| verify_signature(&state.webhook_key, &body, s)) {
+        warn!("Rejected Teams webhook request with a bad signature");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let activity: Activity = match serde_json::from_slice(&body) {
+        Ok(activity) => activity,
+        Err(e) => {
+            warn!("Ignoring malformed Teams activity: {e}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    let Some(inbound) = to_inbound(activity) else {
+        return reply("");
+    };
+    if !is_sender_allowed(&inbound.sender_id, &state.allow_from) {
+        warn!(
+            "Access denied for sender {} on Teams. Add their AAD object ID to allowFrom to grant access.",
+            inbound.sender_id
+        );
+        return reply("You don't have access to this bot.");
+    }
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+    let chat_id = inbound.chat_id.clone();
+    state.pending.insert(chat_id.clone(), reply_tx);
+    if let Err(e) = state.inbound_tx.send(inbound).await {
+        error!("Failed to send inbound Teams message: {e}");
+        state.pending.remove(&chat_id);
+        return reply("Sorry, I can't take that right now.");
+    }
+    match tokio::time::timeout(WEBHOOK_WAIT, reply_rx).await {
+        Ok(Ok(content)) => axum::Json(json!({
+            "type": "message",
+            "attachments": [attachment(markdown_to_adaptive_card(&content))],
+        }))
+        .into_response(),
+        _ => {
+            state.pending.remove(&chat_id);
+            if state.late_replies {
+                reply("Working on it. The answer will follow in this channel.")
+            } else {
+                reply("That's taking longer than Teams waits for, so the answer is lost. Set incomingWebhookUrl to get late answers.")
+            }
+        }
+    }
+}
+
+/// Whether `signature` is the base64 HMAC-SHA256 of `body` under `key`.
+fn verify_signature(key: &[u8], body: &[u8], signature: &str) -> bool {
+    let Ok(signature) = base64::engine::general_purpose::STANDARD.decode(signature.trim()) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// The inbound message for a message activity with text or files.
+fn to_inbound(activity: Activity) -> Option<InboundMessage> {
+    if activity.kind != "message" {
+        return None;
+    }
+    let media: Vec<String> = activity
+        .attachments
+        .iter()
+        .filter_map(|a| {
+            if a.content_type == "application/vnd.microsoft.teams.file.download.info" {
+                a.content
+                    .as_ref()
+                    .and_then(|c| c.get("downloadUrl"))
+                    .and_then(|u| u.as_str())
+                    .map(str::to_string)
+            } else if a.content_type == "text/html" {
+                // Teams repeats the message text as HTML
+                None
+            } else {
+                a.content_url.clone()
+            }
+        })
+        .collect();
+    let mut content = strip_mentions(activity.text.as_deref().unwrap_or_default());
+    if content.is_empty() && media.is_empty() {
+        return None;
+    }
+    for url in &media {
+        content.push_str(&format!("\n[file: {url}]"));
+    }
+
+    let sender_id = activity
+        .from
+        .aad_object_id
+        .clone()
+        .unwrap_or_else(|| activity.from.id.clone());
+    debug!(
+        "Teams message from {sender_id}: {}...",
+        content.chars().take(50).collect::<String>()
+    );
+
+    let mut metadata = HashMap::new();
+    if let Some(url) = activity.service_url {
+        metadata.insert("service_url".to_string(), Value::String(url));
+    }
+    if let Some(id) = activity.id {
+        metadata.insert("activity_id".to_string(), Value::String(id));
+    }
+    if let Some(name) = activity.from.name {
+        metadata.insert("user_name".to_string(), Value::String(name));
+    }
+
+    Some(InboundMessage {
+        channel: "teams".to_string(),
+        sender_id,
+        chat_id: activity.conversation.id,
+        content,
+        media,
+        metadata,
+        timestamp: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+    })
+}
+
+/// Drop `<at>Bot</at>` mentions, which channel messages have to start with.
+fn strip_mentions(text: &str) -> String {
+    let re_mention = Regex::new(r"<at>[^<]*</at>").unwrap();
+    re_mention
+        .replace_all(text, "")
+        .replace("&nbsp;", " ")
+        .trim()
+        .to_string()
+}
+
+fn attachment(card: Value) -> Value {
+    json!({ "contentType": "application/vnd.microsoft.card.adaptive", "content": card })
+}
+
+/// Check a sender's AAD object ID against allow_from. GUIDs compare
+/// case-insensitively.
+fn is_sender_allowed(sender_id: &str, allow_from: &[String]) -> bool {
+    allow_from.is_empty() || allow_from.iter().any(|a| a.eq_ignore_ascii_case(sender_id))
+}
+
+/// Verifies the JWTs the Bot Framework signs its requests with.
+struct BotAuth {
+    http: reqwest::Client,
+    app_id: String,
+    keys: Mutex<Option<(JwkSet, Instant)>>,
+}
+
+#[derive(Deserialize)]
+struct BotClaims {
+    #[serde(default)]
+    serviceurl: Option<String>,
+}
+
+impl BotAuth {
+    fn new(http: reqwest::Client, app_id: String) -> Self {
+        Self {
+            http,
+            app_id,
+            keys: Mutex::new(None),
+        }
+    }
+
+    /// Check the `Authorization` header of a request for `service_url`.
+    async fn verify(&self, authorization: Option<&str>, service_url: &str) -> Result<()> {
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .context("no bearer token")?;
+        let kid = jsonwebtoken::decode_header(token)?
+            .kid
+            .context("token has no key ID")?;
+        let key = DecodingKey::from_jwk(&self.key(&kid).await?)?;
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[&self.app_id]);
+        validation.set_issuer(&[BOT_ISSUER]);
+        validation.leeway = 300;
+        let claims = jsonwebtoken::decode::<BotClaims>(token, &key, &validation)?.claims;
+        check_service_url(claims.serviceurl.as_deref(), service_url)
+    }
+
+    /// The signing key `kid`, refetching the set when it's old or doesn't
+    /// have that key yet.
+    async fn key(&self, kid: &str) -> Result<Jwk> {
+        let mut keys = self.keys.lock().await;
+        let (stale, known) = match *keys {
+            Some((ref set, fetched)) => (
+                fetched.elapsed() > KEYS_MAX_AGE
+                    || (set.find(kid).is_none() && fetched.elapsed() > KEYS_MIN_REFRESH),
+                set.find(kid).cloned(),
+            ),
+            None => (true, None),
+        };
+        if !stale {
+            return known.context("unknown signing key");
+        }
+
+        #[derive(Deserialize)]
+        struct OpenIdConfig {
+            jwks_uri: String,
+        }
+        let config: OpenIdConfig = self.http.get(OPENID_CONFIG).send().await?.json().await?;
+        let set: JwkSet = self.http.get(&config.jwks_uri).send().await?.json().await?;
+        let key = set.find(kid).cloned();
+        *keys = Some((set, Instant::now()));
+        key.context("unknown signing key")
+    }
+}
+
+/// The activity's `serviceUrl` later receives our connector token, so the
+/// token must name it.
+fn check_service_url(claim: Option<&str>, service_url: &str) -> Result<()> {
+    match claim {
+        None => anyhow::bail!("token has no service URL"),
+        Some(url) if url.trim_end_matches('/') != service_url.trim_end_matches('/') => {
+            anyhow::bail!("token was issued for another service URL")
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Where each conversation's Bot Connector service lives, by conversation ID.
+struct Conversations {
+    path: PathBuf,
+    urls: std::sync::Mutex<HashMap<String, String>>,
+}
+
+impl Conversations {
+    fn load(path: PathBuf) -> Self {
+        let urls = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            urls: std::sync::Mutex::new(urls),
+        }
+    }
+
+    fn service_url(&self, conversation_id: &str) -> Option<String> {
+        let urls = self.urls.lock().unwrap_or_else(|e| e.into_inner());
+        urls.get(conversation_id).cloned()
+    }
+
+    /// Record the conversation's service URL, saving when it's new.
+    fn remember(&self, conversation_id: &str, service_url: &str) {
+        let mut urls = self.urls.lock().unwrap_or_else(|e| e.into_inner());
+        if urls.get(conversation_id).map(String::as_str) == Some(service_url) {
+            return;
+        }
+        urls.insert(conversation_id.to_string(), service_url.to_string());
+        let saved = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                let json = serde_json::to_string_pretty(&*urls)?;
+                std::fs::write(&self.path, json)
+            });
+        if let Err(e) = saved {
+            warn!(
+                "Failed to save Teams conversations to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(json: Value) -> Activity {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn message_activity_becomes_inbound() {
+        let inbound = to_inbound(activity(json!({
+            "type": "message",
+            "id": "1700000000000",
+            "text": "<at>patina</at>&nbsp;what's on today?",
+            "serviceUrl": "https://smba.trafficmanager.net/emea/",
+            "from": { "id": "29:abc", "name": "Ada", "aadObjectId": "6B1F0C8E-AAAA" },
+            "conversation": { "id": "19:xyz@thread.tacv2;messageid=1700000000000" },
+            "attachments": [
+                { "contentType": "text/html", "content": "<p>ignored</p>" },
+                {
+                    "contentType": "application/vnd.microsoft.teams.file.download.info",
+                    "content": { "downloadUrl": "https://files.example/report.pdf" }
+                }
+            ]
+        })))
+        .unwrap();
+        assert_eq!(inbound.channel, "teams");
+        assert_eq!(inbound.sender_id, "6B1F0C8E-AAAA");
+        assert_eq!(
+            inbound.chat_id,
+            "19:xyz@thread.tacv2;messageid=1700000000000"
+        );
+        assert_eq!(
+            inbound.content,
+            "what's on today?\n[file: https://files.example/report.pdf]"
+        );
+        assert_eq!(inbound.metadata["activity_id"], "1700000000000");
+        assert_eq!(inbound.metadata["user_name"], "Ada");
+    }
+
+    #[test]
+    fn other_activities_are_ignored() {
+        let update = activity(json!({
+            "type": "conversationUpdate",
+            "from": { "id": "29:abc" },
+            "conversation": { "id": "a:1" }
+        }));
+        assert!(to_inbound(update).is_none());
+        let empty = activity(json!({
+            "type": "message",
+            "text": "<at>patina</at>",
+            "from": { "id": "29:abc" },
+            "conversation": { "id": "a:1" }
+        }));
+        assert!(to_inbound(empty).is_none());
+    }
+
+    #[test]
+    fn allowlist_matches_aad_ids_case_insensitively() {
+        let allow = vec!["6b1f0c8e-aaaa".to_string()];
+        assert!(is_sender_allowed("6B1F0C8E-AAAA", &allow));
+        assert!(!is_sender_allowed("29:abc", &allow));
+        assert!(is_sender_allowed("anyone", &[]));
+    }
+
+    #[test]
+    fn webhook_signature_is_checked() {
+        let key = b"secret-key";
+        let body = br#"{"type":"message"}"#;
+        let mut mac = HmacSha256::new_from_slice(key).unwrap();
+        mac.update(body);
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+        assert!(verify_signature(key, body, &signature));
+        assert!(!verify_signature(key, b"tampered", &signature));
+        assert!(!verify_signature(key, body, "not base64!"));
+    }
+
+    #[test]
+    fn conversations_survive_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("teams").join("conversations.json");
+        Conversations::load(path.clone()).remember("a:1", "https://smba.example/");
+        let reloaded = Conversations::load(path);
+        assert_eq!(
+            reloaded.service_url("a:1").as_deref(),
+            Some("https://smba.example/")
+        );
+        assert!(reloaded.service_url("a:2").is_none());
+    }
+
+    #[test]
+    fn new_channel_checks_mode_credentials() {
+        let bot = TeamsConfig {
+            enabled: true,
+            app_id: "app".into(),
+            ..Default::default()
+        };
+        let err = TeamsChannel::new(bot).err().unwrap();
+        assert!(err.to_string().contains("app password"));
+
+        let webhook = TeamsConfig {
+            enabled: true,
+            mode: TeamsMode::OutgoingWebhook,
+            webhook_secret: "not base64!".into(),
+            ..Default::default()
+        };
+        assert!(TeamsChannel::new(webhook).is_err());
+    }
+
+    #[test]
+    fn token_must_name_the_activity_service_url() {
+        let url = "https://smba.trafficmanager.net/emea/";
+        assert!(check_service_url(Some("https://smba.trafficmanager.net/emea"), url).is_ok());
+        assert!(check_service_url(Some("https://evil.example/"), url).is_err());
+        assert!(check_service_url(None, url).is_err());
+    }
+}
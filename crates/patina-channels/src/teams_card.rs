@@ -0,0 +1,259 @@
+//! Markdown-to-Adaptive-Card converter for Teams.
+//!
+//! A card `TextBlock` renders only part of markdown: bold, italic, links,
+//! and bulleted or numbered lists. The rest is rebuilt from card elements:
+//! - Headers: bold `TextBlock`s, larger for `#` and `##`
+//! - Code blocks: monospace text in an emphasis `Container`, as a
+//!   `RichTextBlock` so the code isn't read as markdown
+//! - Tables: `Table` elements (card version 1.5), first row as header
+//! - Block quotes: emphasis `Container`s
+//! - Horizontal rules: a separator above the next element
+//! - Inline code and `~~strikethrough~~`: the markers are dropped
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+/// Convert markdown text to an Adaptive Card.
+pub fn markdown_to_adaptive_card(text: &str) -> Value {
+    let mut body = Body::default();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let raw = lines[i];
+        let line = raw.trim();
+
+        if line.starts_with("```") {
+            body.paragraph(&mut paragraph);
+            let start = i + 1;
+            i = start;
+            while i < lines.len() && !lines[i].trim().starts_with("```") {
+                i += 1;
+            }
+            body.push(code_block(&lines[start..i].join("\n")));
+            i += 1;
+            continue;
+        }
+
+        if is_table_row(line) {
+            body.paragraph(&mut paragraph);
+            let start = i;
+            while i < lines.len() && is_table_row(lines[i].trim()) {
+                i += 1;
+            }
+            body.push(table(&lines[start..i]));
+            continue;
+        }
+
+        if line.starts_with('>') {
+            body.paragraph(&mut paragraph);
+            let mut quote = Vec::new();
+            while i < lines.len() && lines[i].trim().starts_with('>') {
+                let text = lines[i].trim().trim_start_matches('>');
+                quote.push(text.strip_prefix(' ').unwrap_or(text));
+                i += 1;
+            }
+            body.push(json!({
+                "type": "Container",
+                "style": "emphasis",
+                "items": [text_block(&inline(&quote.join("\n")))],
+            }));
+            continue;
+        }
+
+        i += 1;
+        if let Some((level, title)) = heading(line) {
+            body.paragraph(&mut paragraph);
+            let size = match level {
+                1 => "Large",
+                2 => "Medium",
+                _ => "Default",
+            };
+            let mut block = text_block(&inline(title));
+            block["weight"] = json!("Bolder");
+            block["size"] = json!(size);
+            body.push(block);
+        } else if is_rule(line) {
+            body.paragraph(&mut paragraph);
+            body.separator = true;
+        } else if line.is_empty() {
+            body.paragraph(&mut paragraph);
+        } else {
+            paragraph.push(raw);
+        }
+    }
+    body.paragraph(&mut paragraph);
+
+    json!({
+        "type": "AdaptiveCard",
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "version": "1.5",
+        "body": body.elements,
+        "msteams": { "width": "Full" },
+    })
+}
+
+/// Card elements so far, and whether a rule asked for a separator above the
+/// next one.
+#[derive(Default)]
+struct Body {
+    elements: Vec<Value>,
+    separator: bool,
+}
+
+impl Body {
+    fn push(&mut self, mut element: Value) {
+        if std::mem::take(&mut self.separator) {
+            element["separator"] = json!(true);
+        }
+        self.elements.push(element);
+    }
+
+    /// Add the pending paragraph lines, if any, as one text block.
+    fn paragraph(&mut self, lines: &mut Vec<&str>) {
+        if lines.is_empty() {
+            return;
+        }
+        let text = inline(&lines.join("\n"));
+        lines.clear();
+        self.push(text_block(&text));
+    }
+}
+
+fn text_block(text: &str) -> Value {
+    json!({ "type": "TextBlock", "text": text, "wrap": true })
+}
+
+fn code_block(code: &str) -> Value {
+    json!({
+        "type": "Container",
+        "style": "emphasis",
+        "items": [{
+            "type": "RichTextBlock",
+            "inlines": [{ "type": "TextRun", "text": code, "fontType": "Monospace" }],
+        }],
+    })
+}
+
+fn table(lines: &[&str]) -> Value {
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .map(|line| line.trim().trim_matches('|'))
+        .filter(|line| !line.chars().all(|c| "-: |".contains(c)))
+        .map(|line| line.split('|').map(|c| inline(c.trim())).collect())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let rows: Vec<Value> = rows
+        .into_iter()
+        .map(|mut cells| {
+            cells.resize(columns, String::new());
+            let cells: Vec<Value> = cells
+                .iter()
+                .map(|cell| json!({ "type": "TableCell", "items": [text_block(cell)] }))
+                .collect();
+            json!({ "type": "TableRow", "cells": cells })
+        })
+        .collect();
+    json!({
+        "type": "Table",
+        "columns": vec![json!({ "width": 1 }); columns],
+        "firstRowAsHeader": true,
+        "rows": rows,
+    })
+}
+
+/// Drop the markdown a `TextBlock` would show literally.
+fn inline(text: &str) -> String {
+    let re_code = Regex::new(r"`([^`]+)`").unwrap();
+    let re_strike = Regex::new(r"~~(.+?)~~").unwrap();
+    let text = re_code.replace_all(text, "$1");
+    re_strike.replace_all(&text, "$1").into_owned()
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, title.trim()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].iter().any(|m| marks.iter().all(|c| c == m))
+}
+
+fn is_table_row(line: &str) -> bool {
+    line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(markdown: &str) -> Vec<Value> {
+        markdown_to_adaptive_card(markdown)["body"]
+            .as_array()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn paragraphs_keep_supported_markdown() {
+        let body = body("Some **bold** and `code`.\n- one\n- two\n\nNext ~~old~~ paragraph.");
+        assert_eq!(body.len(), 2);
+        assert_eq!(body[0]["text"], "Some **bold** and code.\n- one\n- two");
+        assert_eq!(body[1]["text"], "Next old paragraph.");
+        assert_eq!(body[1]["wrap"], true);
+    }
+
+    #[test]
+    fn headers_become_bold_text() {
+        let body = body("# Title\n### Small\ntext");
+        assert_eq!(body[0]["text"], "Title");
+        assert_eq!(body[0]["weight"], "Bolder");
+        assert_eq!(body[0]["size"], "Large");
+        assert_eq!(body[1]["size"], "Default");
+        assert_eq!(body[2]["text"], "text");
+        // Not a header without the space
+        assert_eq!(self::body("#hashtag")[0]["text"], "#hashtag");
+    }
+
+    #[test]
+    fn code_blocks_are_monospace_and_literal() {
+        let body = body("Run:\n```sh\necho *hi*\n```\nDone");
+        assert_eq!(body.len(), 3);
+        let run = &body[1]["items"][0]["inlines"][0];
+        assert_eq!(run["text"], "echo *hi*");
+        assert_eq!(run["fontType"], "Monospace");
+        assert_eq!(body[2]["text"], "Done");
+    }
+
+    #[test]
+    fn tables_become_table_elements() {
+        let body = body("| Name | Qty |\n|------|----:|\n| apples | 3 |\n| pears |");
+        let table = &body[0];
+        assert_eq!(table["type"], "Table");
+        assert_eq!(table["columns"].as_array().unwrap().len(), 2);
+        let rows = table["rows"].as_array().unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1]["cells"][0]["items"][0]["text"], "apples");
+        // Short rows are padded
+        assert_eq!(rows[2]["cells"][1]["items"][0]["text"], "");
+    }
+
+    #[test]
+    fn quotes_and_rules() {
+        let body = body("> quoted\n> more\n\n---\nafter");
+        assert_eq!(body[0]["style"], "emphasis");
+        assert_eq!(body[0]["items"][0]["text"], "quoted\nmore");
+        assert_eq!(body[1]["text"], "after");
+        assert_eq!(body[1]["separator"], true);
+    }
+
+    #[test]
+    fn empty_input_gives_an_empty_card() {
+        let card = markdown_to_adaptive_card("");
+        assert_eq!(card["type"], "AdaptiveCard");
+        assert!(card["body"].as_array().unwrap().is_empty());
+    }
+}
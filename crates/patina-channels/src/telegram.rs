@@ -9,9 +9,10 @@
 //! - Typing indicators while processing
 //! - Proxy support
 //! - /start, /new, /help slash commands
+//! - Inline keyboards for command choices (e.g. /persona); a press is sent on
+//!   as the button's command
 
 use std::collections::HashMap;
-use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -19,22 +20,29 @@ use async_trait::async_trait;
 use teloxide::net::Download;
 use teloxide::prelude::*;
 use teloxide::types::{
-    BotCommand, ChatAction, FileMeta, MediaKind, MessageKind, ParseMode, ThreadId,
+    BotCommand, ChatAction, FileMeta, InlineKeyboardButton, InlineKeyboardMarkup, MediaKind,
+    MessageKind, ParseMode, ReplyParameters, Seconds, ThreadId, User,
 };
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tracing::{debug, error, info, warn};
 
 use patina_config::TelegramConfig;
 use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+use patina_transcribe::limits::MediaLimits;
 
-use crate::base::Channel;
+use crate::base::{Channel, ChannelCapabilities};
+use crate::format::strip_markdown;
 use crate::markdown::markdown_to_telegram_html;
+use crate::voice::transcribe_media;
 
 /// Telegram channel supporting both long polling and webhook modes.
 pub struct TelegramChannel {
     config: TelegramConfig,
     bot: Bot,
     transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
+    limits: MediaLimits,
+    commands: Vec<(String, String)>,
     shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
     typing_tasks: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>,
 }
@@ -44,17 +52,23 @@ impl TelegramChannel {
     pub fn new(
         config: TelegramConfig,
         transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
-    ) -> Result<Self> {
+    ) -> Result<Self, ChannelError> {
         if config.token.is_empty() {
-            return Err(anyhow::anyhow!("Telegram bot token not configured"));
+            return Err(ChannelError::NotConfigured {
+                channel: "Telegram".into(),
+                what: "bot token".into(),
+            });
         }
 
         // Build bot with optional proxy
         let bot = match config.proxy.as_deref() {
             Some(proxy_url) if !proxy_url.is_empty() => {
-                let client = reqwest::Client::builder()
-                    .proxy(reqwest::Proxy::all(proxy_url)?)
-                    .build()?;
+                let client = reqwest::Proxy::all(proxy_url)
+                    .and_then(|proxy| reqwest::Client::builder().proxy(proxy).build())
+                    .map_err(|e| ChannelError::Setup {
+                        channel: "Telegram".into(),
+                        source: e.into(),
+                    })?;
                 Bot::with_client(&config.token, client)
             }
             _ => Bot::new(&config.token),
@@ -64,11 +78,25 @@ impl TelegramChannel {
             config,
             bot,
             transcriber,
+            limits: MediaLimits::default(),
+            commands: Vec::new(),
             shutdown_tx: Mutex::new(None),
             typing_tasks: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// Refuse voice and audio messages over these limits instead of downloading them.
+    pub fn with_media_limits(mut self, limits: MediaLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Publish these `(name, description)` pairs as the bot's command menu on start.
+    pub fn with_commands(mut self, commands: Vec<(String, String)>) -> Self {
+        self.commands = commands;
+        self
+    }
+
     /// Stop the typing indicator for a chat.
     async fn stop_typing(&self, chat_id_str: &str) {
         let mut tasks = self.typing_tasks.lock().await;
@@ -144,13 +172,15 @@ impl Channel for TelegramChannel {
         }
 
         // Register bot commands
-        let commands = vec![
-            BotCommand::new("start", "Start the bot"),
-            BotCommand::new("new", "Start a new conversation"),
-            BotCommand::new("help", "Show available commands"),
-        ];
-        if let Err(e) = self.bot.set_my_commands(commands).await {
-            warn!("Failed to register bot commands: {e}");
+        if !self.commands.is_empty() {
+            let commands: Vec<BotCommand> = self
+                .commands
+                .iter()
+                .map(|(name, description)| BotCommand::new(name, description))
+                .collect();
+            if let Err(e) = self.bot.set_my_commands(commands).await {
+                warn!("Failed to register bot commands: {e}");
+            }
         }
 
         // Get bot info
@@ -180,19 +210,40 @@ impl Channel for TelegramChannel {
         let config = self.config.clone();
         let typing_tasks = self.typing_tasks.clone();
         let transcriber = self.transcriber.clone();
+        let limits = self.limits;
 
         // Build the handler
-        let handler = Update::filter_message().endpoint(
-            move |bot: Bot, msg: Message, inbound_tx: mpsc::Sender<InboundMessage>| {
-                let config = config.clone();
-                let typing_tasks = typing_tasks.clone();
-                let transcriber = transcriber.clone();
-                async move {
-                    handle_message(bot, msg, inbound_tx, config, typing_tasks, transcriber).await;
-                    respond(())
-                }
-            },
-        );
+        let callback_config = config.clone();
+        let handler = dptree::entry()
+            .branch(Update::filter_message().endpoint(
+                move |bot: Bot, msg: Message, inbound_tx: mpsc::Sender<InboundMessage>| {
+                    let config = config.clone();
+                    let typing_tasks = typing_tasks.clone();
+                    let transcriber = transcriber.clone();
+                    async move {
+                        handle_message(
+                            bot,
+                            msg,
+                            inbound_tx,
+                            config,
+                            typing_tasks,
+                            transcriber,
+                            limits,
+                        )
+                        .await;
+                        respond(())
+                    }
+                },
+            ))
+            .branch(Update::filter_callback_query().endpoint(
+                move |bot: Bot, query: CallbackQuery, inbound_tx: mpsc::Sender<InboundMessage>| {
+                    let config = callback_config.clone();
+                    async move {
+                        handle_callback(bot, query, inbound_tx, config).await;
+                        respond(())
+                    }
+                },
+            ));
 
         // Build dispatcher
         let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
@@ -296,35 +347,36 @@ impl Channel for TelegramChannel {
         // Parse chat_id — format is "chat_id" or "chat_id:thread_id"
         let (chat_id, thread_id) = parse_chat_and_thread(&msg.chat_id, &msg.metadata)?;
 
-        // Split into chunks if the message exceeds Telegram's limit
-        let chunks = split_message(&msg.content, 4096);
+        let keyboard = choices_keyboard(&msg.metadata);
 
-        for &chunk in &chunks {
-            // Try sending as HTML first
-            let html_content = markdown_to_telegram_html(chunk);
-            let mut request = self
-                .bot
-                .send_message(ChatId(chat_id), &html_content)
-                .parse_mode(ParseMode::Html);
+        // Try sending as HTML first
+        let html_content = markdown_to_telegram_html(&msg.content);
+        let mut request = self
+            .bot
+            .send_message(ChatId(chat_id), &html_content)
+            .parse_mode(ParseMode::Html);
+
+        if let Some(tid) = thread_id {
+            request = request.message_thread_id(tid);
+        }
+        if let Some(keyboard) = keyboard.clone() {
+            request = request.reply_markup(keyboard);
+        }
 
+        if let Err(e) = request.await {
+            // Fallback to plain text if HTML parsing fails
+            warn!("HTML parse failed, falling back to plain text: {e}");
+            let plain = strip_markdown(&msg.content);
+            let mut fallback = self.bot.send_message(ChatId(chat_id), plain);
             if let Some(tid) = thread_id {
-                request = request.message_thread_id(tid);
+                fallback = fallback.message_thread_id(tid);
             }
-
-            match request.await {
-                Ok(_) => {}
-                Err(e) => {
-                    // Fallback to plain text if HTML parsing fails
-                    warn!("HTML parse failed, falling back to plain text: {e}");
-                    let mut fallback = self.bot.send_message(ChatId(chat_id), chunk);
-                    if let Some(tid) = thread_id {
-                        fallback = fallback.message_thread_id(tid);
-                    }
-                    if let Err(e2) = fallback.await {
-                        error!("Error sending Telegram message: {e2}");
-                        return Err(e2.into());
-                    }
-                }
+            if let Some(keyboard) = keyboard {
+                fallback = fallback.reply_markup(keyboard);
+            }
+            if let Err(e2) = fallback.await {
+                error!("Error sending Telegram message: {e2}");
+                return Err(e2.into());
             }
         }
 
@@ -341,6 +393,15 @@ impl Channel for TelegramChannel {
             .as_deref()
             .unwrap_or("No markdown tables. Never use markdown table syntax — it renders poorly on mobile chat clients. Use plain text lists instead.")
     }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: false,
+            supports_attachments: false,
+            max_message_len: Some(4096),
+        }
+    }
 }
 
 /// Handle an incoming Telegram message.
@@ -351,6 +412,7 @@ async fn handle_message(
     config: TelegramConfig,
     typing_tasks: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>,
     transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
+    limits: MediaLimits,
 ) {
     // Extract user info
     let user = match msg.from {
@@ -358,12 +420,7 @@ async fn handle_message(
         None => return,
     };
 
-    // Build sender_id: "numeric_id" or "numeric_id|username"
-    let sender_id = if let Some(ref username) = user.username {
-        format!("{}|{}", user.id, username)
-    } else {
-        user.id.to_string()
-    };
+    let sender_id = sender_id(user);
 
     // Check access
     if !is_sender_allowed(&sender_id, &config.allow_from) {
@@ -413,7 +470,12 @@ async fn handle_message(
                     }
                 }
             }
-            MediaKind::Voice(voice) => {
+            // Over-limit voice and audio get a refusal reply (in the guard) and
+            // never reach the agent
+            MediaKind::Voice(voice)
+                if within_limits(&bot, &msg, &voice.voice.file, voice.voice.duration, &limits)
+                    .await =>
+            {
                 match download_media(
                     &bot,
                     &voice.voice.file,
@@ -424,28 +486,16 @@ async fn handle_message(
                 {
                     Ok(path) => {
                         media_paths.push(path.clone());
-                        if let Some(ref t) = transcriber {
-                            match t.transcribe_file(&path).await {
-                                Ok(text) => {
-                                    if text.len() > 50 {
-                                        let mut end = 50;
-                                        while end > 0 && !text.is_char_boundary(end) {
-                                            end -= 1;
-                                        }
-                                        info!("Transcribed voice: {}...", &text[..end]);
-                                    } else {
-                                        info!("Transcribed voice: {text}");
-                                    }
-                                    content_parts.push(format!("[transcription: {text}]"));
-                                }
-                                Err(e) => {
-                                    warn!("Voice transcription failed: {e}");
-                                    content_parts.push(format!("[voice: {path}]"));
-                                }
-                            }
-                        } else {
-                            content_parts.push(format!("[voice: {path}]"));
-                        }
+                        content_parts.push(
+                            transcribe_media(
+                                transcriber.as_ref(),
+                                &path,
+                                "voice",
+                                &format!("telegram:{chat_id_str}"),
+                                &limits,
+                            )
+                            .await,
+                        );
                     }
                     Err(e) => {
                         error!("Failed to download voice: {e}");
@@ -453,7 +503,10 @@ async fn handle_message(
                     }
                 }
             }
-            MediaKind::Audio(audio) => {
+            MediaKind::Audio(audio)
+                if within_limits(&bot, &msg, &audio.audio.file, audio.audio.duration, &limits)
+                    .await =>
+            {
                 match download_media(
                     &bot,
                     &audio.audio.file,
@@ -464,28 +517,16 @@ async fn handle_message(
                 {
                     Ok(path) => {
                         media_paths.push(path.clone());
-                        if let Some(ref t) = transcriber {
-                            match t.transcribe_file(&path).await {
-                                Ok(text) => {
-                                    if text.len() > 50 {
-                                        let mut end = 50;
-                                        while end > 0 && !text.is_char_boundary(end) {
-                                            end -= 1;
-                                        }
-                                        info!("Transcribed audio: {}...", &text[..end]);
-                                    } else {
-                                        info!("Transcribed audio: {text}");
-                                    }
-                                    content_parts.push(format!("[transcription: {text}]"));
-                                }
-                                Err(e) => {
-                                    warn!("Audio transcription failed: {e}");
-                                    content_parts.push(format!("[audio: {path}]"));
-                                }
-                            }
-                        } else {
-                            content_parts.push(format!("[audio: {path}]"));
-                        }
+                        content_parts.push(
+                            transcribe_media(
+                                transcriber.as_ref(),
+                                &path,
+                                "audio",
+                                &format!("telegram:{chat_id_str}"),
+                                &limits,
+                            )
+                            .await,
+                        );
                     }
                     Err(e) => {
                         error!("Failed to download audio: {e}");
@@ -620,35 +661,141 @@ async fn handle_message(
     }
 }
 
-/// Split a message into chunks that fit within Telegram's character limit.
-///
-/// Tries to break at newline boundaries to keep output readable.
-/// Falls back to splitting at the limit if no newline is found.
-fn split_message(text: &str, max_len: usize) -> Vec<&str> {
-    if text.len() <= max_len {
-        return vec![text];
+/// Handle a press on a command's inline keyboard: the button's input is sent
+/// to the agent as if the user had typed it, and the buttons are removed so
+/// the choice can't be made twice.
+async fn handle_callback(
+    bot: Bot,
+    query: CallbackQuery,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    config: TelegramConfig,
+) {
+    // Stops the spinner on the button
+    if let Err(e) = bot.answer_callback_query(query.id.clone()).await {
+        debug!("Failed to answer callback query: {e}");
+    }
+    let (Some(data), Some(message)) = (query.data, query.message) else {
+        return;
+    };
+
+    let sender_id = sender_id(&query.from);
+    if !is_sender_allowed(&sender_id, &config.allow_from) {
+        warn!(
+            "Access denied for sender {} on Telegram. Add to allowFrom to grant access.",
+            sender_id
+        );
+        return;
     }
 
-    let mut chunks = Vec::new();
-    let mut remaining = text;
+    let chat = message.chat();
+    if let Err(e) = bot.edit_message_reply_markup(chat.id, message.id()).await {
+        debug!("Failed to remove the inline keyboard: {e}");
+    }
 
-    while !remaining.is_empty() {
-        if remaining.len() <= max_len {
-            chunks.push(remaining);
-            break;
-        }
+    let thread_id = message.regular_message().and_then(|m| m.thread_id);
+    let chat_id_str = match thread_id {
+        Some(tid) => format!("{}:{}", chat.id.0, (tid.0).0),
+        None => chat.id.0.to_string(),
+    };
+    let mut metadata = HashMap::new();
+    if let Some(tid) = thread_id {
+        metadata.insert(
+            "message_thread_id".to_string(),
+            serde_json::Value::Number((tid.0).0.into()),
+        );
+    }
+    metadata.insert(
+        "user_id".to_string(),
+        serde_json::Value::Number(serde_json::Number::from(query.from.id.0)),
+    );
+    if let Some(ref username) = query.from.username {
+        metadata.insert(
+            "username".to_string(),
+            serde_json::Value::String(username.clone()),
+        );
+    }
+    metadata.insert(
+        "first_name".to_string(),
+        serde_json::Value::String(query.from.first_name.clone()),
+    );
+    metadata.insert(
+        "is_group".to_string(),
+        serde_json::Value::Bool(chat.is_group() || chat.is_supergroup()),
+    );
 
-        // Find the last newline within the limit
-        let split_at = remaining[..max_len]
-            .rfind('\n')
-            .map(|pos| pos + 1) // include the newline in the current chunk
-            .unwrap_or(max_len);
+    debug!("Telegram button from {sender_id}: {data}");
+    let inbound = InboundMessage {
+        channel: "telegram".to_string(),
+        sender_id,
+        chat_id: chat_id_str,
+        content: data,
+        media: Vec::new(),
+        metadata,
+        timestamp: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+    };
+    if let Err(e) = inbound_tx.send(inbound).await {
+        error!("Failed to send inbound message: {e}");
+    }
+}
 
-        chunks.push(&remaining[..split_at]);
-        remaining = &remaining[split_at..];
+/// Sender ID: "numeric_id" or "numeric_id|username".
+fn sender_id(user: &User) -> String {
+    match user.username {
+        Some(ref username) => format!("{}|{}", user.id, username),
+        None => user.id.to_string(),
     }
+}
 
-    chunks
+/// Telegram's limit on a button's callback data, in bytes.
+const MAX_CALLBACK_DATA: usize = 64;
+
+/// An inline keyboard with one button per row for the reply's `choices`
+/// metadata (`[{"label", "input"}]`, from `CommandRegistry::choices`).
+/// Choices whose input is too long for callback data are left out.
+fn choices_keyboard(metadata: &HashMap<String, serde_json::Value>) -> Option<InlineKeyboardMarkup> {
+    let rows: Vec<Vec<InlineKeyboardButton>> = metadata
+        .get("choices")?
+        .as_array()?
+        .iter()
+        .filter_map(|choice| {
+            let label = choice.get("label")?.as_str()?;
+            let input = choice.get("input")?.as_str()?;
+            (input.len() <= MAX_CALLBACK_DATA)
+                .then(|| vec![InlineKeyboardButton::callback(label, input)])
+        })
+        .collect();
+    (!rows.is_empty()).then(|| InlineKeyboardMarkup::new(rows))
+}
+
+/// Check a voice or audio attachment against the transcription limits using the
+/// metadata Telegram sends with it. When it's over, reply with a polite refusal
+/// and return false so it's never downloaded.
+async fn within_limits(
+    bot: &Bot,
+    msg: &Message,
+    file: &FileMeta,
+    duration: Seconds,
+    limits: &MediaLimits,
+) -> bool {
+    let Err(e) = limits.check(Some(duration.seconds() as u64), known_size(file)) else {
+        return true;
+    };
+    info!("Refusing audio in chat {}: {e}", msg.chat.id);
+    let mut reply = bot
+        .send_message(msg.chat.id, e.refusal())
+        .reply_parameters(ReplyParameters::new(msg.id));
+    if let Some(tid) = msg.thread_id {
+        reply = reply.message_thread_id(tid);
+    }
+    if let Err(e) = reply.await {
+        warn!("Failed to send audio limit refusal: {e}");
+    }
+    false
+}
+
+/// File size from Telegram metadata; `u32::MAX` is teloxide's "unknown".
+fn known_size(file: &FileMeta) -> Option<u64> {
+    (file.size != u32::MAX).then_some(file.size as u64)
 }
 
 /// Check if a sender is allowed based on the allow_from list.
@@ -681,6 +828,27 @@ fn is_sender_allowed(sender_id: &str, allow_from: &[String]) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn choices_become_one_button_per_row() {
+        let long = format!("/persona {}", "x".repeat(MAX_CALLBACK_DATA));
+        let metadata = HashMap::from([(
+            "choices".to_string(),
+            serde_json::json!([
+                { "label": "Coder", "input": "/persona coder" },
+                { "label": "Too long", "input": long },
+                { "label": "No persona", "input": "/persona none" },
+            ]),
+        )]);
+        let keyboard = choices_keyboard(&metadata).unwrap();
+        let labels: Vec<&str> = keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row[0].text.as_str())
+            .collect();
+        assert_eq!(labels, ["Coder", "No persona"]);
+        assert!(choices_keyboard(&HashMap::new()).is_none());
+    }
+
     #[test]
     fn allowlist_matches_full_and_composite_parts() {
         assert!(is_sender_allowed("123|alice", &[]));
@@ -733,31 +901,6 @@ mod tests {
         assert!(parse_chat_and_thread("not_a_number", &metadata).is_err());
     }
 
-    #[test]
-    fn split_message_short() {
-        let chunks = split_message("hello", 4096);
-        assert_eq!(chunks, vec!["hello"]);
-    }
-
-    #[test]
-    fn split_message_at_newline() {
-        let line = "a".repeat(100);
-        let text = format!("{line}\n{line}\n{line}");
-        let chunks = split_message(&text, 202);
-        assert_eq!(chunks.len(), 2);
-        assert!(chunks[0].ends_with('\n'));
-        assert_eq!(chunks[1], line);
-    }
-
-    #[test]
-    fn split_message_no_newline() {
-        let text = "a".repeat(5000);
-        let chunks = split_message(&text, 4096);
-        assert_eq!(chunks.len(), 2);
-        assert_eq!(chunks[0].len(), 4096);
-        assert_eq!(chunks[1].len(), 904);
-    }
-
     #[test]
     fn get_extension_from_mime() {
         assert_eq!(
@@ -786,6 +929,18 @@ mod tests {
         assert_eq!(TelegramChannel::get_extension("document", None), "");
     }
 
+    #[test]
+    fn unknown_file_size_is_not_checked() {
+        let mut file = FileMeta {
+            id: teloxide::types::FileId("id".into()),
+            unique_id: teloxide::types::FileUniqueId("uid".into()),
+            size: 4096,
+        };
+        assert_eq!(known_size(&file), Some(4096));
+        file.size = u32::MAX;
+        assert_eq!(known_size(&file), None);
+    }
+
     #[test]
     fn new_channel_requires_token() {
         let config = TelegramConfig {
@@ -839,10 +994,7 @@ async fn download_media(
     let id_str = &file_meta.id.0;
     let short_id = &id_str[..id_str.len().min(16)];
 
-    let media_dir = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("media");
+    let media_dir = patina_config::data_dir().join("media");
     std::fs::create_dir_all(&media_dir)?;
 
     let file_path = media_dir.join(format!("{short_id}{ext}"));
@@ -0,0 +1,56 @@
+//! Voice note transcription shared by the channels that receive audio.
+
+use std::sync::Arc;
+
+use patina_transcribe::limits::MediaLimits;
+use tracing::{info, warn};
+
+/// Transcribe a downloaded voice or audio file into a content line for the agent.
+///
+/// The language hint for `chat_key` (`channel:chat_id`), if configured, is
+/// forwarded to the backend, and the detected language is included so the
+/// agent can reply in kind. Falls back to a plain `[kind: path]` reference
+/// when transcription is unavailable or fails, or when the file turns out to
+/// be over the limits.
+pub(crate) async fn transcribe_media(
+    transcriber: Option<&Arc<dyn patina_transcribe::Transcriber>>,
+    path: &str,
+    kind: &str,
+    chat_key: &str,
+    limits: &MediaLimits,
+) -> String {
+    let Some(t) = transcriber else {
+        return format!("[{kind}: {path}]");
+    };
+    // Metadata can be missing or wrong; check the real file before decoding it
+    if let Err(e) = limits.check_file(path).await {
+        info!("Not transcribing {kind}: {e}");
+        return format!("[{kind}: {path} (not transcribed: {e})]");
+    }
+    let options = patina_transcribe::TranscribeOptions {
+        language: None,
+        chat_key: Some(chat_key.to_string()),
+    };
+    match t.transcribe(path, &options).await {
+        Ok(transcript) => {
+            let text = transcript.text;
+            if text.len() > 50 {
+                let mut end = 50;
+                while end > 0 && !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                info!("Transcribed {kind}: {}...", &text[..end]);
+            } else {
+                info!("Transcribed {kind}: {text}");
+            }
+            match transcript.language {
+                Some(lang) => format!("[transcription ({lang}): {text}]"),
+                None => format!("[transcription: {text}]"),
+            }
+        }
+        Err(e) => {
+            warn!("Transcription of {kind} failed: {e}");
+            format!("[{kind}: {path}]")
+        }
+    }
+}
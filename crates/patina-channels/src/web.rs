@@ -1,13 +1,16 @@
 use std::collections::HashMap;
 use std::io::BufRead;
 use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{Path as AxumPath, Query, State, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Path as AxumPath, Query, Request, State, WebSocketUpgrade};
+use axum::middleware::Next;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse, Response};
 use axum::routing::{get, put};
 use axum::Router;
@@ -15,42 +18,116 @@ use dashmap::DashMap;
 use futures::stream::SplitSink;
 use futures::{SinkExt, StreamExt};
 use patina_config::schema::ModelPricing;
-use patina_config::{GatewayConfig, WebConfig};
-use patina_core::agent::ModelPool;
+use patina_config::{GatewayConfig, GeofenceConfig, WebConfig};
+use patina_core::agent::subagent::SubagentManager;
+use patina_core::agent::{ContextBuilder, MemoryIndex, MemoryStore, ModelPool};
 use patina_core::bus::InboundMessage;
+use patina_core::compare::{CompareSide, Comparison, ComparisonStore, Preference};
+use patina_core::cron::CronService;
+use patina_core::export::{export_session, ExportFormat};
+use patina_core::location::{self, LocationStore};
+use patina_core::log_stream::{self, LogLine};
 use patina_core::persona::PersonaStore;
+use patina_core::session::{read_metadata, safe_file_name, SessionManager};
 use patina_core::task::TaskManager;
-use patina_core::usage::{UsageFilter, UsageTracker};
-use rig::completion::{CompletionModel, CompletionRequest, Message as RigMessage};
+use patina_core::usage::{calculate_cost, UsageFilter, UsageRecord, UsageTracker};
+use rig::completion::{CompletionModel, CompletionRequest, GetTokenUsage, Message as RigMessage};
 use rig::message::{AssistantContent, Text, UserContent};
 use rig::OneOrMany;
 use serde::{Deserialize, Serialize};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tracing::{error, info, warn};
 
-use crate::base::Channel;
+use crate::base::{Channel, ChannelCapabilities};
+use crate::control::{ControlHandle, ControlRequest};
+use crate::guest::{GuestLinks, GuestPass};
+use crate::manager::channel_statuses;
 use crate::web_assets;
 
 type WsSender = mpsc::UnboundedSender<Message>;
 
+/// Upload size limit when `maxUploadMb` isn't set.
+const DEFAULT_MAX_UPLOAD_MB: u64 = 25;
+
+/// An open WebSocket. Guest connections only see their own chat, and
+/// nothing once their link expires.
+struct Connection {
+    tx: WsSender,
+    guest: Option<GuestPass>,
+    /// Shown to others watching the same chat.
+    name: String,
+    /// Chat the client last opened or wrote in.
+    viewing: Option<String>,
+}
+
+impl Connection {
+    /// Whether an event for `chat_id` (or a chat-less status event) should
+    /// reach this connection.
+    fn sees(&self, chat_id: Option<&str>) -> bool {
+        match (&self.guest, chat_id) {
+            (None, _) => true,
+            (Some(pass), Some(chat_id)) => !pass.expired() && pass.chat_id() == chat_id,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Who opened a WebSocket.
+enum WsAuth {
+    /// Password, user token, or no password configured.
+    Full {
+        user: Option<String>,
+    },
+    Guest(GuestPass),
+}
+
 pub struct WebChannel {
     config: WebConfig,
     gateway_config: GatewayConfig,
     sessions_dir: PathBuf,
-    connections: Arc<DashMap<String, WsSender>>,
+    connections: Arc<DashMap<String, Connection>>,
     persona_store: Arc<tokio::sync::Mutex<PersonaStore>>,
     models: ModelPool,
     usage_tracker: Option<Arc<UsageTracker>>,
     pricing: HashMap<String, ModelPricing>,
     task_manager: Option<Arc<tokio::sync::Mutex<TaskManager>>>,
+    memory: Option<MemoryStore>,
+    memory_index: Option<Arc<MemoryIndex>>,
+    user_tokens: Arc<HashMap<String, String>>,
+    guest_links: Option<GuestLinks>,
+    comparisons: Option<Arc<ComparisonStore>>,
+    workspace: Option<PathBuf>,
+    status: Option<StatusSources>,
+    control: Option<ControlHandle>,
+    locations: Option<Locations>,
     shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
 }
 
+/// Where `POST /api/location` stores pings, and the geofences they can
+/// trigger.
+#[derive(Clone)]
+pub struct Locations {
+    pub store: Arc<LocationStore>,
+    pub geofences: Arc<Vec<GeofenceConfig>>,
+}
+
+/// Gateway state the status page reports beyond what the web channel
+/// already holds.
+#[derive(Clone)]
+pub struct StatusSources {
+    pub cron: Arc<tokio::sync::Mutex<CronService>>,
+    pub subagents: Arc<SubagentManager>,
+    /// `transcription.mode`, and the backends it tries in order.
+    pub transcription_mode: String,
+    pub transcription_chain: Vec<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Clone)]
 struct AppState {
     config: WebConfig,
     sessions_dir: PathBuf,
-    connections: Arc<DashMap<String, WsSender>>,
+    connections: Arc<DashMap<String, Connection>>,
     inbound_tx: mpsc::Sender<InboundMessage>,
     persona_store: Arc<tokio::sync::Mutex<PersonaStore>>,
     models: ModelPool,
@@ -58,11 +135,21 @@ struct AppState {
     usage_tracker: Option<Arc<UsageTracker>>,
     pricing: HashMap<String, ModelPricing>,
     task_manager: Option<Arc<tokio::sync::Mutex<TaskManager>>>,
+    memory: Option<MemoryStore>,
+    memory_index: Option<Arc<MemoryIndex>>,
+    user_tokens: Arc<HashMap<String, String>>,
+    guest_links: Option<GuestLinks>,
+    comparisons: Option<Arc<ComparisonStore>>,
+    workspace: Option<PathBuf>,
+    status: Option<StatusSources>,
+    control: Option<ControlHandle>,
+    locations: Option<Locations>,
 }
 
 #[derive(Deserialize)]
 struct WsParams {
     password: Option<String>,
+    guest: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -99,6 +186,9 @@ struct WsInMsg {
     chat_id: String,
     #[serde(default)]
     persona: String,
+    /// Names of files uploaded to this chat to hand the agent with the message.
+    #[serde(default)]
+    files: Vec<String>,
 }
 
 impl WebChannel {
@@ -121,6 +211,15 @@ impl WebChannel {
             usage_tracker,
             pricing,
             task_manager: None,
+            memory: None,
+            memory_index: None,
+            user_tokens: Arc::new(HashMap::new()),
+            guest_links: None,
+            comparisons: None,
+            workspace: None,
+            status: None,
+            control: None,
+            locations: None,
             shutdown_tx: Mutex::new(None),
         })
     }
@@ -129,6 +228,53 @@ impl WebChannel {
     pub fn set_task_manager(&mut self, manager: Arc<tokio::sync::Mutex<TaskManager>>) {
         self.task_manager = Some(manager);
     }
+
+    /// Set the long-term memory (and its search index) for the memory API.
+    pub fn set_memory(&mut self, store: MemoryStore, index: Option<Arc<MemoryIndex>>) {
+        self.memory = Some(store);
+        self.memory_index = index;
+    }
+
+    /// Per-user web tokens (token → user name). A token is accepted in place
+    /// of the password, and messages from that connection carry the user's
+    /// name in `metadata["user"]`.
+    pub fn set_user_tokens(&mut self, tokens: HashMap<String, String>) {
+        self.user_tokens = Arc::new(tokens);
+    }
+
+    /// Accept guest and watch links signed with `links`, and issue watch
+    /// links from `POST /api/sessions/{id}/share`. Guest chats also need
+    /// `guestLinks.enabled`.
+    pub fn set_guest_links(&mut self, links: GuestLinks) {
+        self.guest_links = Some(links);
+    }
+
+    /// Set the agent's workspace. Enables file uploads (kept under
+    /// `workspace/uploads/<chat id>/`), and gives A/B comparisons without a
+    /// persona the system prompt built from it.
+    pub fn set_workspace(&mut self, workspace: PathBuf) {
+        self.workspace = Some(workspace);
+    }
+
+    /// Enable the A/B compare API, keeping comparisons in `store`.
+    pub fn set_comparisons(&mut self, store: Arc<ComparisonStore>) {
+        self.comparisons = Some(store);
+    }
+
+    /// Enable the status page's cron, subagent, and transcription sections.
+    pub fn set_status_sources(&mut self, sources: StatusSources) {
+        self.status = Some(sources);
+    }
+
+    /// Enable the API's channel restart, disable, and enable routes.
+    pub fn set_control(&mut self, control: ControlHandle) {
+        self.control = Some(control);
+    }
+
+    /// Accept location pings on `POST /api/location`.
+    pub fn set_locations(&mut self, locations: Locations) {
+        self.locations = Some(locations);
+    }
 }
 
 #[async_trait]
@@ -150,16 +296,36 @@ impl Channel for WebChannel {
             usage_tracker: self.usage_tracker.clone(),
             pricing: self.pricing.clone(),
             task_manager: self.task_manager.clone(),
+            memory: self.memory.clone(),
+            memory_index: self.memory_index.clone(),
+            user_tokens: self.user_tokens.clone(),
+            guest_links: self.guest_links.clone(),
+            comparisons: self.comparisons.clone(),
+            workspace: self.workspace.clone(),
+            status: self.status.clone(),
+            control: self.control.clone(),
+            locations: self.locations.clone(),
         };
 
-        let router = Router::new()
-            .route("/", get(serve_index))
-            .route("/ws", get(ws_upgrade))
+        let api = Router::new()
             .route("/api/sessions", get(api_list_sessions))
             .route(
                 "/api/sessions/{id}",
                 axum::routing::delete(api_delete_session),
             )
+            .route("/api/sessions/{id}/export", get(api_export_session))
+            .route(
+                "/api/sessions/{id}/share",
+                axum::routing::post(api_share_session),
+            )
+            .route(
+                "/api/sessions/{id}/uploads",
+                axum::routing::post(api_upload_file).layer(DefaultBodyLimit::max(
+                    self.config.max_upload_mb.unwrap_or(DEFAULT_MAX_UPLOAD_MB) as usize
+                        * 1024
+                        * 1024,
+                )),
+            )
             .route(
                 "/api/personas",
                 get(api_list_personas).post(api_create_persona),
@@ -173,9 +339,22 @@ impl Channel for WebChannel {
                 put(api_update_persona).delete(api_delete_persona),
             )
             .route("/api/model-tiers", get(api_model_tiers))
+            .route("/api/metrics", get(api_metrics))
+            .route("/api/status", get(api_status))
+            .route(
+                "/api/channels/{name}/{action}",
+                axum::routing::post(api_channel_action),
+            )
+            .route("/api/logs/stream", get(api_logs_stream))
             .route("/api/usage/summary", get(api_usage_summary))
             .route("/api/usage/daily", get(api_usage_daily))
             .route("/api/usage/filters", get(api_usage_filters))
+            .route("/api/compare", axum::routing::post(api_compare))
+            .route("/api/compare/stats", get(api_compare_stats))
+            .route(
+                "/api/compare/{id}/preference",
+                axum::routing::post(api_compare_preference),
+            )
             .route("/api/tasks", get(api_list_tasks).post(api_create_task))
             .route(
                 "/api/tasks/{id}",
@@ -188,6 +367,23 @@ impl Channel for WebChannel {
                 axum::routing::post(api_add_task_comment),
             )
             .route("/api/tasks/{id}/history", get(api_task_history))
+            .route("/api/memory", get(api_get_memory).put(api_put_memory))
+            .route("/api/memory/search", get(api_search_memory))
+            .route(
+                "/api/memory/facts/{line}",
+                put(api_edit_memory_fact).delete(api_delete_memory_fact),
+            )
+            .route_layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                require_password,
+            ));
+        let router = Router::new()
+            .route("/", get(serve_index))
+            .route("/health", get(health))
+            .route("/ws", get(ws_upgrade))
+            // Checks its own credentials, since OwnTracks sends Basic auth
+            .route("/api/location", axum::routing::post(api_location))
+            .merge(api)
             .with_state(state);
 
         let addr: SocketAddr = format!("{}:{}", self.gateway_config.host, self.gateway_config.port)
@@ -226,18 +422,22 @@ impl Channel for WebChannel {
     }
 
     async fn send(&self, msg: &patina_core::bus::OutboundMessage) -> Result<()> {
+        // Status events (e.g. model download progress) update the header, not a chat
+        let is_status = msg.metadata.get("event").and_then(|v| v.as_str()) == Some("status");
         if self.connections.is_empty() {
-            warn!(
-                "No active WebSocket connections, message for chat_id={} saved to session only",
-                msg.chat_id
-            );
+            if !is_status {
+                warn!(
+                    "No active WebSocket connections, message for chat_id={} saved to session only",
+                    msg.chat_id
+                );
+            }
             return Ok(());
         }
 
         let out = WsOutMsg {
-            msg_type: "message".to_string(),
+            msg_type: if is_status { "status" } else { "message" }.to_string(),
             content: Some(msg.content.clone()),
-            chat_id: Some(msg.chat_id.clone()),
+            chat_id: (!is_status).then(|| msg.chat_id.clone()),
             timestamp: Some(chrono::Local::now().to_rfc3339()),
             messages: None,
         };
@@ -245,8 +445,12 @@ impl Channel for WebChannel {
 
         // Broadcast to all connections — client filters by chatId
         for entry in self.connections.iter() {
+            if !entry.value().sees(out.chat_id.as_deref()) {
+                continue;
+            }
             if entry
                 .value()
+                .tx
                 .send(Message::Text(json.clone().into()))
                 .is_err()
             {
@@ -269,6 +473,16 @@ impl Channel for WebChannel {
     fn prompt_rules(&self) -> &str {
         self.config.system_prompt_rules.as_deref().unwrap_or("")
     }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        // Replies stream into place through `broadcast_chunk()`
+        ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: true,
+            supports_attachments: false,
+            max_message_len: None,
+        }
+    }
 }
 
 impl WebChannel {
@@ -283,7 +497,9 @@ impl WebChannel {
         };
         if let Ok(json) = serde_json::to_string(&out) {
             for entry in self.connections.iter() {
-                let _ = entry.value().send(Message::Text(json.clone().into()));
+                if entry.value().sees(Some(chat_id)) {
+                    let _ = entry.value().tx.send(Message::Text(json.clone().into()));
+                }
             }
         }
     }
@@ -331,6 +547,223 @@ async fn api_delete_session(
     }
 }
 
+#[derive(Deserialize)]
+struct ExportQueryParams {
+    format: Option<String>,
+}
+
+async fn api_export_session(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<ExportQueryParams>,
+) -> Response {
+    let error = |status: axum::http::StatusCode, message: String| {
+        (status, axum::Json(serde_json::json!({"error": message}))).into_response()
+    };
+    // Reject path traversal
+    if id.contains('/') || id.contains('\\') || id.contains("..") {
+        return error(
+            axum::http::StatusCode::BAD_REQUEST,
+            "invalid session id".into(),
+        );
+    }
+    let format: ExportFormat = match params.format.as_deref().unwrap_or("md").parse() {
+        Ok(f) => f,
+        Err(e) => return error(axum::http::StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    let sessions = SessionManager::new(state.sessions_dir.clone());
+    let session = match sessions.load(&format!("web:{id}")) {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            return error(
+                axum::http::StatusCode::NOT_FOUND,
+                "session not found".into(),
+            )
+        }
+        Err(e) => return error(axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let disposition = format!("attachment; filename=\"web_{id}.{}\"", format.extension());
+    (
+        [
+            (
+                axum::http::header::CONTENT_TYPE,
+                format.content_type().to_string(),
+            ),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        export_session(&session, format),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ShareRequest {
+    ttl_mins: Option<u64>,
+    #[serde(default)]
+    label: String,
+}
+
+/// Create a watch-only link for a web chat.
+async fn api_share_session(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+    body: Option<axum::Json<ShareRequest>>,
+) -> impl IntoResponse {
+    // Reject path traversal
+    if id.contains('/') || id.contains('\\') || id.contains("..") {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({"error": "invalid session id"})),
+        );
+    }
+    let Some(ref links) = state.guest_links else {
+        return (
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            axum::Json(serde_json::json!({"error": "sharing is not available"})),
+        );
+    };
+    let req = body.map(|b| b.0).unwrap_or_default();
+    let ttl_mins = req.ttl_mins.unwrap_or(state.config.guest_links.ttl_mins);
+    let ttl = match crate::guest::link_ttl(ttl_mins) {
+        Ok(ttl) => ttl,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": e.to_string()})),
+            )
+        }
+    };
+    let token = links.issue_view(&id, &req.label, ttl);
+    let expires_at = chrono::Utc::now() + ttl;
+    (
+        axum::http::StatusCode::OK,
+        axum::Json(serde_json::json!({
+            "url": format!("/?guest={token}"),
+            "expiresAt": expires_at.to_rfc3339(),
+        })),
+    )
+}
+
+#[derive(Deserialize)]
+struct UploadParams {
+    name: String,
+}
+
+/// Save the request body as a file in the chat's uploads dir. The reply's
+/// `name` goes in the `files` of the next chat message to attach it.
+async fn api_upload_file(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<UploadParams>,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    let error = |status: axum::http::StatusCode, message: &str| {
+        (status, axum::Json(serde_json::json!({"error": message})))
+    };
+    // Reject path traversal
+    if id.is_empty() || id.contains('/') || id.contains('\\') || id.contains("..") {
+        return error(axum::http::StatusCode::BAD_REQUEST, "invalid session id");
+    }
+    let Some(ref workspace) = state.workspace else {
+        return error(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "uploads are not available",
+        );
+    };
+    let Some(name) = upload_file_name(&params.name) else {
+        return error(axum::http::StatusCode::BAD_REQUEST, "invalid file name");
+    };
+    if body.is_empty() {
+        return error(axum::http::StatusCode::BAD_REQUEST, "empty file");
+    }
+    let dir = uploads_dir(workspace, &id);
+    if let Err(e) = tokio::fs::create_dir_all(&dir).await {
+        return error(
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("failed to create uploads dir: {e}"),
+        );
+    }
+    let path = unused_path(&dir, &name);
+    if let Err(e) = tokio::fs::write(&path, &body).await {
+        return error(
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("failed to save upload: {e}"),
+        );
+    }
+    info!("Saved upload {} ({} bytes)", path.display(), body.len());
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or(name);
+    (
+        axum::http::StatusCode::OK,
+        axum::Json(serde_json::json!({
+            "name": name,
+            "size": body.len(),
+        })),
+    )
+}
+
+/// Where files uploaded to a web chat are kept.
+fn uploads_dir(workspace: &Path, chat_id: &str) -> PathBuf {
+    workspace.join("uploads").join(safe_file_name(chat_id))
+}
+
+/// The last component of a browser-supplied file name, with anything but
+/// letters, digits, spaces, `.`, `-` and `_` replaced. `None` for names
+/// that end up empty or hidden.
+fn upload_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("").trim();
+    let clean: String = base
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(120)
+        .collect();
+    if clean.is_empty() || clean.starts_with('.') {
+        return None;
+    }
+    Some(clean)
+}
+
+/// `dir/name`, or `dir/name-1.ext`, `dir/name-2.ext`, ... if it's taken.
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{ext}")),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|i| dir.join(format!("{stem}-{i}{ext}")))
+        .find(|p| !p.exists())
+        .unwrap_or(path)
+}
+
+/// Paths of the named files uploaded to `chat_id`, skipping names that
+/// weren't uploaded there.
+fn resolve_uploads(workspace: &Path, chat_id: &str, names: &[String]) -> Vec<String> {
+    if chat_id.is_empty() {
+        return Vec::new();
+    }
+    let dir = uploads_dir(workspace, chat_id);
+    names
+        .iter()
+        .filter(|name| upload_file_name(name).as_deref() == Some(name.as_str()))
+        .map(|name| dir.join(name))
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
 // --- Persona API ---
 
 #[derive(Serialize)]
@@ -464,6 +897,253 @@ async fn api_model_tiers(State(state): State<AppState>) -> impl IntoResponse {
     axum::Json(state.model_tiers.clone())
 }
 
+/// Runtime counters for dashboards and scrapers.
+/// Liveness probe for container healthchecks; needs no password.
+async fn health() -> impl IntoResponse {
+    axum::Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn api_metrics() -> impl IntoResponse {
+    axum::Json(serde_json::json!({
+        "transcription": patina_transcribe::metrics::queue_stats(),
+        "outbound": crate::outbound_queue::queue_stats(),
+        "providers": patina_core::agent::rate_limit::queue_stats(),
+    }))
+}
+
+/// Everything the status page shows: `patina status` plus what the running
+/// gateway knows. Sections the gateway didn't provide are null.
+async fn api_status(State(state): State<AppState>) -> impl IntoResponse {
+    let queues = crate::outbound_queue::queue_stats().channels;
+    let channels: serde_json::Map<String, serde_json::Value> = channel_statuses()
+        .into_iter()
+        .map(|(name, status)| {
+            let queue = queues.get(&name);
+            let entry = serde_json::json!({
+                "state": status.state,
+                "since": status.since,
+                "error": status.error,
+                "queue": queue,
+            });
+            (name, entry)
+        })
+        .collect();
+
+    let mut tiers = state.model_tiers.clone();
+    tiers.sort_by_key(|t| (t != "default", t.clone()));
+    let models: Vec<serde_json::Value> = tiers
+        .iter()
+        .map(|tier| {
+            let (_, model, provider) = state.models.get(tier);
+            serde_json::json!({"tier": tier, "model": model, "provider": provider})
+        })
+        .collect();
+
+    let memory = state
+        .memory_index
+        .as_ref()
+        .and_then(|index| index.stats().ok());
+
+    let today = state.usage_tracker.as_ref().and_then(|tracker| {
+        let midnight = chrono::Local::now()
+            .date_naive()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_local_timezone(chrono::Local)
+            .earliest()?;
+        let filter = UsageFilter {
+            from: Some(midnight.with_timezone(&chrono::Utc).to_rfc3339()),
+            group_by: Some("model".into()),
+            ..Default::default()
+        };
+        let rows = tracker
+            .query_summary_with_cost(&filter, &state.pricing)
+            .ok()?;
+        let cost: f64 = rows.iter().filter_map(|r| r.estimated_cost).sum();
+        Some(serde_json::json!({
+            "calls": rows.iter().map(|r| r.calls).sum::<u64>(),
+            "totalTokens": rows.iter().map(|r| r.total_tokens).sum::<u64>(),
+            "cost": (!state.pricing.is_empty()).then_some(cost),
+        }))
+    });
+
+    let (started_at, transcription, cron, subagents) = match state.status {
+        Some(ref sources) => {
+            let mut jobs: Vec<serde_json::Value> = {
+                let mut cron = sources.cron.lock().await;
+                cron.list_jobs(false)
+                    .into_iter()
+                    .map(|job| {
+                        serde_json::json!({
+                            "id": job.id,
+                            "name": job.name,
+                            "kind": job.payload.kind,
+                            "nextRunAtMs": job.state.next_run_at_ms,
+                            "lastStatus": job.state.last_status,
+                            "lastError": job.state.last_error,
+                        })
+                    })
+                    .collect()
+            };
+            jobs.sort_by_key(|j| j["nextRunAtMs"].as_i64().unwrap_or(i64::MAX));
+            let subagents: Vec<serde_json::Value> = sources
+                .subagents
+                .list()
+                .await
+                .into_iter()
+                .map(|(id, label)| serde_json::json!({"id": id, "label": label}))
+                .collect();
+            let transcription = serde_json::json!({
+                "mode": sources.transcription_mode,
+                "chain": sources.transcription_chain,
+                "queue": patina_transcribe::metrics::queue_stats(),
+                "lastLoaded": patina_transcribe::provider::last_loaded(),
+            });
+            (
+                Some(sources.started_at),
+                Some(transcription),
+                Some(jobs),
+                Some(subagents),
+            )
+        }
+        None => (None, None, None, None),
+    };
+
+    axum::Json(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "startedAt": started_at.map(|t| t.to_rfc3339()),
+        "uptimeSecs": started_at.map(|t| (chrono::Utc::now() - t).num_seconds()),
+        "channels": channels,
+        "models": models,
+        "providers": patina_core::agent::rate_limit::queue_stats(),
+        "transcription": transcription,
+        "cron": cron,
+        "subagents": subagents,
+        "memory": memory,
+        "today": today,
+    }))
+}
+
+// --- Channel API ---
+
+/// Restart, disable, or enable one channel in this gateway. Like the log
+/// stream this needs the web password, and the web channel itself is left to
+/// `patina channels restart web`, since it would be restarting the server
+/// answering the request.
+async fn api_channel_action(
+    State(state): State<AppState>,
+    AxumPath((name, action)): AxumPath<(String, String)>,
+) -> Response {
+    let error = |status: axum::http::StatusCode, msg: &str| {
+        (status, axum::Json(serde_json::json!({"error": msg}))).into_response()
+    };
+    if state.config.password.is_empty() {
+        return error(
+            axum::http::StatusCode::FORBIDDEN,
+            "Set channels.web.password to manage channels",
+        );
+    }
+    let Some(control) = state.control else {
+        return error(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "Channel control isn't available",
+        );
+    };
+    let request = match action.as_str() {
+        "restart" => ControlRequest::RestartChannel {
+            channel: name.clone(),
+        },
+        "disable" => ControlRequest::DisableChannel {
+            channel: name.clone(),
+        },
+        "enable" => ControlRequest::EnableChannel {
+            channel: name.clone(),
+        },
+        _ => {
+            return error(
+                axum::http::StatusCode::NOT_FOUND,
+                "action must be restart, disable, or enable",
+            )
+        }
+    };
+    if name == "web" {
+        return error(
+            axum::http::StatusCode::CONFLICT,
+            &format!("Use `patina channels {action} web` to manage the web channel"),
+        );
+    }
+    let reply = control.send(request).await;
+    if reply.ok {
+        axum::Json(serde_json::json!({"message": reply.message})).into_response()
+    } else {
+        error(axum::http::StatusCode::BAD_REQUEST, &reply.message)
+    }
+}
+
+// --- Logs API ---
+
+#[derive(Deserialize)]
+struct LogStreamParams {
+    /// Least severe level to send (default "info").
+    level: Option<String>,
+    /// Only lines whose target starts with this, e.g. "patina_core::agent".
+    target: Option<String>,
+}
+
+/// Server-sent events with the process's log output: the recent lines, then
+/// each new one as it's logged, as `{"timestamp","level","target","message"}`.
+/// Logs can hold message text and chat ids, so this needs the web password
+/// even where the rest of the API is open.
+async fn api_logs_stream(
+    State(state): State<AppState>,
+    Query(params): Query<LogStreamParams>,
+) -> Response {
+    let error = |status: axum::http::StatusCode, msg: &str| {
+        (status, axum::Json(serde_json::json!({"error": msg}))).into_response()
+    };
+    if state.config.password.is_empty() {
+        return error(
+            axum::http::StatusCode::FORBIDDEN,
+            "Set channels.web.password to stream logs",
+        );
+    }
+    let Ok(max) = params
+        .level
+        .as_deref()
+        .unwrap_or("info")
+        .parse::<tracing::Level>()
+    else {
+        return error(
+            axum::http::StatusCode::BAD_REQUEST,
+            "level must be error, warn, info, debug, or trace",
+        );
+    };
+    let target = params.target.unwrap_or_default();
+    let keep = move |line: &LogLine| line.within(max) && line.target.starts_with(&target);
+
+    let (recent, rx) = log_stream::stream().subscribe();
+    let backlog: Vec<LogLine> = recent.into_iter().filter(&keep).collect();
+    let live = futures::stream::unfold(rx, |mut rx| async move {
+        let line = match rx.recv().await {
+            Ok(line) => line,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => LogLine {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                level: "WARN".into(),
+                target: "patina::logs".into(),
+                message: format!("Skipped {skipped} lines (the stream fell behind)"),
+            },
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((line, rx))
+    })
+    .filter(move |line| futures::future::ready(line.target == "patina::logs" || keep(line)));
+    let events = futures::stream::iter(backlog)
+        .chain(live)
+        .map(|line| Event::default().json_data(&line));
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
 // --- Usage API ---
 
 #[derive(Deserialize)]
@@ -560,7 +1240,7 @@ async fn api_generate_prompt(
         );
     }
 
-    let (model, _model_name, _provider_name) = state.models.default_model();
+    let (model, model_name, provider_name) = state.models.default_model();
 
     let prompt = format!(
         "Generate a system prompt for an AI assistant persona with the following details:\n\
@@ -579,7 +1259,9 @@ async fn api_generate_prompt(
     let request = CompletionRequest {
         preamble: None,
         chat_history: OneOrMany::one(RigMessage::User {
-            content: OneOrMany::one(UserContent::Text(Text { text: prompt })),
+            content: OneOrMany::one(UserContent::Text(Text {
+                text: prompt.clone(),
+            })),
         }),
         documents: Vec::new(),
         tools: Vec::new(),
@@ -589,21 +1271,348 @@ async fn api_generate_prompt(
         additional_params: None,
     };
 
-    match model.completion(request).await {
-        Ok(response) => {
-            let text: String = response
-                .choice
-                .iter()
-                .filter_map(|c| match c {
-                    AssistantContent::Text(t) => Some(t.text.clone()),
-                    _ => None,
-                })
-                .collect();
-            (
-                axum::http::StatusCode::OK,
-                axum::Json(serde_json::json!({"preamble": text.trim()})),
+    state.models.throttle(provider_name, &request).await;
+    match model.completion(request).await {
+        Ok(response) => {
+            let text: String = response
+                .choice
+                .iter()
+                .filter_map(|c| match c {
+                    AssistantContent::Text(t) => Some(t.text.clone()),
+                    _ => None,
+                })
+                .collect();
+            if let Some(ref tracker) = state.usage_tracker {
+                let mut record = UsageRecord {
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                    session_key: "web:generate-prompt".to_string(),
+                    model: model_name.to_string(),
+                    provider: provider_name.to_string(),
+                    agent: "default".to_string(),
+                    input_tokens: response.usage.input_tokens,
+                    output_tokens: response.usage.output_tokens,
+                    total_tokens: response.usage.total_tokens,
+                    cached_input_tokens: response.usage.cached_input_tokens,
+                    call_type: "persona_prompt".to_string(),
+                    ..Default::default()
+                };
+                record.fill_estimates(&prompt, &text);
+                tracker.record(&record);
+            }
+            (
+                axum::http::StatusCode::OK,
+                axum::Json(serde_json::json!({"preamble": text.trim()})),
+            )
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+// --- Compare API ---
+
+/// Chat messages sent along with a compared prompt, newest kept.
+const COMPARE_HISTORY_MESSAGES: usize = 20;
+const COMPARE_MAX_TOKENS: u64 = 4096;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompareRequest {
+    prompt: String,
+    tier_a: String,
+    tier_b: String,
+    /// Chat whose recent history is sent as context. It is read, never
+    /// written: neither reply is added to the chat.
+    #[serde(default)]
+    chat_id: String,
+    #[serde(default)]
+    persona: String,
+}
+
+/// Run one prompt against two tiers at once and stream both replies as
+/// newline-delimited JSON: `{"id"}` first, then `{"side","delta"}` chunks
+/// from either side as they arrive, a `{"side","done",...}` line per side
+/// with its latency, tokens and cost, and `{"done":true}` once the
+/// comparison is saved.
+async fn api_compare(
+    State(state): State<AppState>,
+    axum::Json(req): axum::Json<CompareRequest>,
+) -> Response {
+    let error = |status: axum::http::StatusCode, msg: String| {
+        (status, axum::Json(serde_json::json!({"error": msg}))).into_response()
+    };
+    let Some(store) = state.comparisons.clone() else {
+        return error(
+            axum::http::StatusCode::NOT_FOUND,
+            "Comparisons are not enabled".into(),
+        );
+    };
+    if req.prompt.trim().is_empty() {
+        return error(
+            axum::http::StatusCode::BAD_REQUEST,
+            "prompt is required".into(),
+        );
+    }
+    for tier in [&req.tier_a, &req.tier_b] {
+        if !state.model_tiers.contains(tier) {
+            return error(
+                axum::http::StatusCode::BAD_REQUEST,
+                format!("unknown model tier '{tier}'"),
+            );
+        }
+    }
+
+    let preamble = if req.persona.is_empty() {
+        state
+            .workspace
+            .as_ref()
+            .and_then(|ws| ContextBuilder::new(ws).build_system_prompt().ok())
+    } else {
+        match state.persona_store.lock().await.get(&req.persona) {
+            Some(p) => Some(p.preamble.clone()).filter(|s| !s.is_empty()),
+            None => {
+                return error(
+                    axum::http::StatusCode::NOT_FOUND,
+                    format!("persona '{}' not found", req.persona),
+                )
+            }
+        }
+    };
+    let session_key = (!req.chat_id.is_empty()).then(|| format!("web:{}", req.chat_id));
+    let mut messages = compare_history(&state, session_key.as_deref());
+    messages.push(RigMessage::User {
+        content: OneOrMany::one(UserContent::Text(Text {
+            text: req.prompt.clone(),
+        })),
+    });
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::channel::<serde_json::Value>(64);
+    let _ = tx.send(serde_json::json!({ "id": id })).await;
+    tokio::spawn(async move {
+        let agent = if req.persona.is_empty() {
+            "default"
+        } else {
+            req.persona.as_str()
+        };
+        let ctx = CompareContext {
+            state: &state,
+            id: &id,
+            agent,
+            preamble: preamble.as_deref(),
+            messages: &messages,
+            tx: &tx,
+        };
+        let (a, b) = tokio::join!(
+            compare_side(&ctx, "a", &req.tier_a),
+            compare_side(&ctx, "b", &req.tier_b)
+        );
+        let comparison = Comparison {
+            id: id.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            session_key,
+            prompt: req.prompt,
+            a,
+            b,
+            preference: None,
+        };
+        let saved = match store.record(&comparison) {
+            Ok(()) => serde_json::json!({ "done": true }),
+            Err(e) => {
+                warn!("Failed to save comparison {id}: {e}");
+                serde_json::json!({ "done": true, "error": e.to_string() })
+            }
+        };
+        let _ = tx.send(saved).await;
+    });
+
+    let lines = futures::stream::unfold(rx, |mut rx| async move {
+        let value = rx.recv().await?;
+        Some((Ok::<_, std::convert::Infallible>(format!("{value}\n")), rx))
+    });
+    (
+        [(axum::http::header::CONTENT_TYPE, "application/x-ndjson")],
+        axum::body::Body::from_stream(lines),
+    )
+        .into_response()
+}
+
+/// The chat's recent user and assistant messages, as model input.
+fn compare_history(state: &AppState, session_key: Option<&str>) -> Vec<RigMessage> {
+    let Some(key) = session_key else {
+        return Vec::new();
+    };
+    let history = load_session_history_by_key(&state.sessions_dir, key);
+    let recent: Vec<&HistoryMessage> = history
+        .iter()
+        .filter(|m| m.role != "system")
+        .rev()
+        .take(COMPARE_HISTORY_MESSAGES)
+        .collect();
+    recent
+        .into_iter()
+        .rev()
+        .map(|m| {
+            let text = Text {
+                text: m.content.clone(),
+            };
+            if m.role == "user" {
+                RigMessage::User {
+                    content: OneOrMany::one(UserContent::Text(text)),
+                }
+            } else {
+                RigMessage::Assistant {
+                    id: None,
+                    content: OneOrMany::one(AssistantContent::Text(text)),
+                }
+            }
+        })
+        .collect()
+}
+
+/// What both sides of a comparison share.
+struct CompareContext<'a> {
+    state: &'a AppState,
+    id: &'a str,
+    agent: &'a str,
+    preamble: Option<&'a str>,
+    messages: &'a [RigMessage],
+    tx: &'a mpsc::Sender<serde_json::Value>,
+}
+
+/// Stream one side of a comparison and record its usage.
+async fn compare_side(ctx: &CompareContext<'_>, side: &'static str, tier: &str) -> CompareSide {
+    let (model, model_name, provider_name) = ctx.state.models.get(tier);
+    let request = CompletionRequest {
+        preamble: ctx.preamble.map(str::to_string),
+        chat_history: OneOrMany::many(ctx.messages.to_vec())
+            .expect("compared messages always end with the prompt"),
+        documents: Vec::new(),
+        tools: Vec::new(),
+        temperature: None,
+        max_tokens: Some(COMPARE_MAX_TOKENS),
+        tool_choice: None,
+        additional_params: ctx.state.models.request_params(provider_name),
+    };
+
+    let mut result = CompareSide {
+        tier: tier.to_string(),
+        model: model_name.to_string(),
+        ..Default::default()
+    };
+    ctx.state.models.throttle(provider_name, &request).await;
+    let started = Instant::now();
+    let mut usage = None;
+    match model.stream(request).await {
+        Ok(mut stream) => {
+            while let Some(chunk) = stream.next().await {
+                match chunk {
+                    Ok(rig::streaming::StreamedAssistantContent::Text(t)) => {
+                        result.response.push_str(&t.text);
+                        let _ = ctx
+                            .tx
+                            .send(serde_json::json!({ "side": side, "delta": t.text }))
+                            .await;
+                    }
+                    Ok(rig::streaming::StreamedAssistantContent::Final(ref resp)) => {
+                        usage = resp.token_usage();
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        result.error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result.latency_ms = started.elapsed().as_millis() as u64;
+
+    let mut record = UsageRecord {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        session_key: format!("compare:{}", ctx.id),
+        model: model_name.to_string(),
+        provider: provider_name.to_string(),
+        agent: ctx.agent.to_string(),
+        input_tokens: usage.as_ref().map_or(0, |u| u.input_tokens),
+        output_tokens: usage.as_ref().map_or(0, |u| u.output_tokens),
+        total_tokens: usage.as_ref().map_or(0, |u| u.total_tokens),
+        cached_input_tokens: usage.as_ref().map_or(0, |u| u.cached_input_tokens),
+        call_type: "compare".to_string(),
+        ..Default::default()
+    };
+    let prompt = format!(
+        "{}{}",
+        ctx.preamble.unwrap_or_default(),
+        serde_json::to_string(ctx.messages).unwrap_or_default()
+    );
+    record.fill_estimates(&prompt, &result.response);
+    result.tokens = record.total_tokens;
+    result.cost = ctx.state.pricing.get(model_name).map(|p| {
+        calculate_cost(
+            record.input_tokens,
+            record.output_tokens,
+            record.cached_input_tokens,
+            p,
+        )
+    });
+    if let Some(ref tracker) = ctx.state.usage_tracker {
+        tracker.record(&record);
+    }
+
+    let mut done = serde_json::json!({
+        "side": side,
+        "done": true,
+        "tier": result.tier,
+        "model": result.model,
+        "latencyMs": result.latency_ms,
+        "tokens": result.tokens,
+        "cost": result.cost,
+    });
+    if let Some(ref e) = result.error {
+        done["error"] = serde_json::json!(e);
+    }
+    let _ = ctx.tx.send(done).await;
+    result
+}
+
+#[derive(Deserialize)]
+struct PreferenceRequest {
+    choice: String,
+}
+
+async fn api_compare_preference(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+    axum::Json(req): axum::Json<PreferenceRequest>,
+) -> impl IntoResponse {
+    let Some(store) = &state.comparisons else {
+        return (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({"error": "Comparisons are not enabled"})),
+        );
+    };
+    let choice: Preference = match req.choice.parse() {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({"error": e.to_string()})),
             )
         }
+    };
+    match store.set_preference(&id, choice) {
+        Ok(true) => (
+            axum::http::StatusCode::OK,
+            axum::Json(serde_json::json!({"ok": true})),
+        ),
+        Ok(false) => (
+            axum::http::StatusCode::NOT_FOUND,
+            axum::Json(serde_json::json!({"error": "comparison not found"})),
+        ),
         Err(e) => (
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             axum::Json(serde_json::json!({"error": e.to_string()})),
@@ -611,6 +1620,20 @@ async fn api_generate_prompt(
     }
 }
 
+/// Per-tier results and the most recent comparisons.
+async fn api_compare_stats(State(state): State<AppState>) -> impl IntoResponse {
+    let Some(store) = &state.comparisons else {
+        return axum::Json(serde_json::json!({"tiers": [], "recent": []}));
+    };
+    match (store.tier_stats(), store.recent(20)) {
+        (Ok(tiers), Ok(recent)) => axum::Json(serde_json::json!({
+            "tiers": tiers,
+            "recent": recent,
+        })),
+        (Err(e), _) | (_, Err(e)) => axum::Json(serde_json::json!({"error": e.to_string()})),
+    }
+}
+
 // --- Task API ---
 
 #[derive(Deserialize)]
@@ -941,46 +1964,368 @@ async fn api_task_history(
     axum::Json(serde_json::json!({ "messages": history }))
 }
 
+// --- Memory API ---
+
+#[derive(Deserialize)]
+struct PutMemoryRequest {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct MemorySearchParams {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct EditFactRequest {
+    expected: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct DeleteFactParams {
+    expected: String,
+}
+
+type JsonResponse = (axum::http::StatusCode, axum::Json<serde_json::Value>);
+
+fn memory_unavailable() -> JsonResponse {
+    (
+        axum::http::StatusCode::SERVICE_UNAVAILABLE,
+        axum::Json(serde_json::json!({"error": "memory not available"})),
+    )
+}
+
+/// MEMORY.md as raw content plus its facts.
+fn memory_view(store: &MemoryStore) -> JsonResponse {
+    match (store.read_long_term(), store.facts()) {
+        (Ok(content), Ok(facts)) => (
+            axum::http::StatusCode::OK,
+            axum::Json(serde_json::json!({"content": content, "facts": facts})),
+        ),
+        (Err(e), _) | (_, Err(e)) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// Respond to a memory write, reindexing so search reflects it.
+fn memory_written(state: &AppState, store: &MemoryStore, result: Result<bool>) -> JsonResponse {
+    match result {
+        Ok(true) => {
+            if let Some(index) = &state.memory_index {
+                if let Err(e) = index.reindex() {
+                    warn!("Memory reindex after edit failed: {e}");
+                }
+            }
+            memory_view(store)
+        }
+        Ok(false) => (
+            axum::http::StatusCode::CONFLICT,
+            axum::Json(
+                serde_json::json!({"error": "fact has changed since it was loaded; reload and try again"}),
+            ),
+        ),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+async fn api_get_memory(State(state): State<AppState>) -> impl IntoResponse {
+    match &state.memory {
+        Some(store) => memory_view(store),
+        None => memory_unavailable(),
+    }
+}
+
+async fn api_put_memory(
+    State(state): State<AppState>,
+    axum::Json(req): axum::Json<PutMemoryRequest>,
+) -> impl IntoResponse {
+    let Some(store) = &state.memory else {
+        return memory_unavailable();
+    };
+    let result = store.write_long_term(&req.content).map(|()| true);
+    memory_written(&state, store, result)
+}
+
+async fn api_search_memory(
+    State(state): State<AppState>,
+    Query(params): Query<MemorySearchParams>,
+) -> impl IntoResponse {
+    let Some(index) = &state.memory_index else {
+        return memory_unavailable();
+    };
+    match index.search(&params.q, params.limit.unwrap_or(10).min(50)) {
+        Ok(results) => {
+            let json: Vec<serde_json::Value> = results
+                .into_iter()
+                .map(|r| {
+                    serde_json::json!({
+                        "path": r.path,
+                        "startLine": r.start_line,
+                        "endLine": r.end_line,
+                        "content": r.content,
+                        "score": r.score,
+                    })
+                })
+                .collect();
+            (
+                axum::http::StatusCode::OK,
+                axum::Json(serde_json::Value::Array(json)),
+            )
+        }
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+async fn api_edit_memory_fact(
+    State(state): State<AppState>,
+    AxumPath(line): AxumPath<usize>,
+    axum::Json(req): axum::Json<EditFactRequest>,
+) -> impl IntoResponse {
+    let Some(store) = &state.memory else {
+        return memory_unavailable();
+    };
+    let result = store.edit_fact(line, &req.expected, &req.text);
+    memory_written(&state, store, result)
+}
+
+async fn api_delete_memory_fact(
+    State(state): State<AppState>,
+    AxumPath(line): AxumPath<usize>,
+    Query(params): Query<DeleteFactParams>,
+) -> impl IntoResponse {
+    let Some(store) = &state.memory else {
+        return memory_unavailable();
+    };
+    let result = store.delete_fact(line, &params.expected);
+    memory_written(&state, store, result)
+}
+
 async fn ws_upgrade(
     ws: WebSocketUpgrade,
     Query(params): Query<WsParams>,
     State(state): State<AppState>,
 ) -> Response {
-    // Validate password
-    if !state.config.password.is_empty() {
-        let provided = params.password.as_deref().unwrap_or("");
-        if provided != state.config.password {
-            return ws
-                .on_upgrade(|mut socket| async move {
-                    let err = WsOutMsg {
-                        msg_type: "error".to_string(),
-                        content: Some("Authentication failed".to_string()),
-                        chat_id: None,
-                        timestamp: None,
-                        messages: None,
-                    };
-                    let _ = socket
-                        .send(Message::Text(serde_json::to_string(&err).unwrap().into()))
-                        .await;
-                    let _ = socket.close().await;
-                })
-                .into_response();
+    if let Some(token) = params.guest {
+        let pass = match state.guest_links.as_ref().map(|links| links.verify(&token)) {
+            Some(Ok(pass)) => pass,
+            Some(Err(e)) => {
+                warn!("Rejected guest link: {e}");
+                return reject_ws(ws, "This guest link is invalid or has expired");
+            }
+            None => return reject_ws(ws, "Guest links are disabled"),
+        };
+        // Watch links work whenever links can be verified; guest chats
+        // must be switched on
+        let guest_chats = state.config.guest_links.enabled && !state.config.password.is_empty();
+        if !pass.view_only && !guest_chats {
+            return reject_ws(ws, "Guest links are disabled");
         }
+        return ws
+            .on_upgrade(move |socket| handle_ws(socket, state, WsAuth::Guest(pass)))
+            .into_response();
+    }
+
+    let provided = params.password.as_deref().unwrap_or("");
+    let user = state.user_tokens.get(provided).cloned();
+
+    // Validate password (a user token also works)
+    if !state.config.password.is_empty() && user.is_none() && provided != state.config.password {
+        return reject_ws(ws, "Authentication failed");
     }
 
-    ws.on_upgrade(move |socket| handle_ws(socket, state))
+    ws.on_upgrade(move |socket| handle_ws(socket, state, WsAuth::Full { user }))
         .into_response()
 }
 
-async fn handle_ws(socket: WebSocket, state: AppState) {
+/// Accept the upgrade only to send `reason` and close.
+fn reject_ws(ws: WebSocketUpgrade, reason: &'static str) -> Response {
+    ws.on_upgrade(move |mut socket| async move {
+        let err = WsOutMsg {
+            msg_type: "error".to_string(),
+            content: Some(reason.to_string()),
+            chat_id: None,
+            timestamp: None,
+            messages: None,
+        };
+        let _ = socket
+            .send(Message::Text(serde_json::to_string(&err).unwrap().into()))
+            .await;
+        let _ = socket.close().await;
+    })
+    .into_response()
+}
+
+/// Store an OwnTracks ping and publish its geofence prompts; answers `[]` as OwnTracks expects.
+async fn api_location(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    axum::Json(body): axum::Json<serde_json::Value>,
+) -> Response {
+    let Some(ref locations) = state.locations else {
+        return (
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            axum::Json(serde_json::json!({"error": "location tracking is not enabled"})),
+        )
+            .into_response();
+    };
+    let Some(user) = location_user(&headers, &state.config.password, &state.user_tokens) else {
+        return (
+            axum::http::StatusCode::UNAUTHORIZED,
+            [(
+                axum::http::header::WWW_AUTHENTICATE,
+                "Basic realm=\"patina\"",
+            )],
+            axum::Json(serde_json::json!({"error": "authentication required"})),
+        )
+            .into_response();
+    };
+    let Some(fix) = location::parse_owntracks(&body, &user) else {
+        return axum::Json(serde_json::json!([])).into_response();
+    };
+    let transitions = match locations.store.record(&fix) {
+        Ok(transitions) => transitions,
+        Err(e) => {
+            error!("Couldn't store the location of {user}: {e}");
+            return (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    };
+    for transition in transitions {
+        info!("Location: {}", transition.describe());
+        for msg in location::geofence_messages(&locations.geofences, &transition) {
+            if let Err(e) = state.inbound_tx.send(msg).await {
+                warn!("Failed to send geofence message: {e}");
+            }
+        }
+    }
+    axum::Json(serde_json::json!([])).into_response()
+}
+
+/// Whose ping a location request is: the user whose web token it carries,
+/// or, with the password (or none configured), the Basic auth username or
+/// OwnTracks' `X-Limit-U` header, falling back to "owner". The token or
+/// password can be the Basic auth password or a bearer token. `None` when
+/// neither matches.
+fn location_user(
+    headers: &axum::http::HeaderMap,
+    password: &str,
+    user_tokens: &HashMap<String, String>,
+) -> Option<String> {
+    use base64::Engine;
+
+    let authorization = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let (username, provided) = match authorization.strip_prefix("Basic ") {
+        Some(encoded) => {
+            let decoded = base64::engine::general_purpose::STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .and_then(|d| String::from_utf8(d).ok())
+                .unwrap_or_default();
+            match decoded.split_once(':') {
+                Some((user, pass)) => (Some(user.to_string()), pass.to_string()),
+                None => (None, String::new()),
+            }
+        }
+        None => (
+            None,
+            authorization
+                .strip_prefix("Bearer ")
+                .unwrap_or_default()
+                .to_string(),
+        ),
+    };
+    if let Some(user) = user_tokens.get(&provided) {
+        return Some(user.clone());
+    }
+    if !password.is_empty() && provided != password {
+        return None;
+    }
+    let named = username.filter(|u| !u.trim().is_empty()).or_else(|| {
+        headers
+            .get("x-limit-u")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    });
+    Some(named.unwrap_or_else(|| "owner".to_string()))
+}
+
+/// Guard for /api routes: when a password is set, requests must send it as
+/// `Authorization: Bearer <password>`. User tokens and guest links don't
+/// grant API access.
+async fn require_password(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if state.config.password.is_empty() {
+        return next.run(req).await;
+    }
+    let provided = req
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided == Some(state.config.password.as_str()) {
+        next.run(req).await
+    } else {
+        (
+            axum::http::StatusCode::UNAUTHORIZED,
+            axum::Json(serde_json::json!({"error": "authentication required"})),
+        )
+            .into_response()
+    }
+}
+
+async fn handle_ws(socket: WebSocket, state: AppState, auth: WsAuth) {
     let conn_id = uuid::Uuid::new_v4().to_string();
     let short_conn = &conn_id[..8];
-    info!("WebSocket connected: conn={short_conn}");
+    let (user, guest) = match auth {
+        WsAuth::Full { user } => (user, None),
+        WsAuth::Guest(pass) => (None, Some(pass)),
+    };
+    let guest_chat = guest.as_ref().map(GuestPass::chat_id);
+    let view_only = guest.as_ref().is_some_and(|pass| pass.view_only);
+    match guest {
+        Some(ref pass) => info!(
+            "WebSocket connected: conn={short_conn} {}={} {}",
+            if pass.view_only { "viewer" } else { "guest" },
+            pass.id,
+            pass.label
+        ),
+        None => info!("WebSocket connected: conn={short_conn}"),
+    }
+    let name = match (&user, &guest) {
+        (Some(user), _) => user.clone(),
+        (None, Some(pass)) if !pass.label.is_empty() => pass.label.clone(),
+        (None, Some(pass)) if pass.view_only => "viewer".to_string(),
+        (None, Some(_)) => "guest".to_string(),
+        (None, None) => "owner".to_string(),
+    };
 
     let (ws_write, mut ws_read) = socket.split();
     let (tx, rx) = mpsc::unbounded_channel::<Message>();
 
-    state.connections.insert(conn_id.clone(), tx.clone());
+    state.connections.insert(
+        conn_id.clone(),
+        Connection {
+            tx: tx.clone(),
+            guest: guest.clone(),
+            name,
+            viewing: None,
+        },
+    );
 
     let write_conn_id = conn_id.clone();
     let write_handle = tokio::spawn(ws_write_loop(ws_write, rx, write_conn_id));
@@ -988,8 +2333,8 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
     // Send connected acknowledgment
     let connected = WsOutMsg {
         msg_type: "connected".to_string(),
-        content: None,
-        chat_id: None,
+        content: view_only.then(|| "view_only".to_string()),
+        chat_id: guest_chat.clone(),
         timestamp: None,
         messages: None,
     };
@@ -1009,23 +2354,72 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
 
         match msg {
             Message::Text(text) => {
-                let parsed: WsInMsg = match serde_json::from_str(&text) {
+                let mut parsed: WsInMsg = match serde_json::from_str(&text) {
                     Ok(m) => m,
                     Err(_) => continue,
                 };
 
+                // Guests can only read, write, and stop their own chat;
+                // viewers can only read it
+                if let (Some(pass), Some(chat_id)) = (&guest, &guest_chat) {
+                    if pass.expired() {
+                        let err = WsOutMsg {
+                            msg_type: "error".to_string(),
+                            content: Some("This guest link has expired".to_string()),
+                            chat_id: None,
+                            timestamp: None,
+                            messages: None,
+                        };
+                        if let Ok(json) = serde_json::to_string(&err) {
+                            let _ = tx.send(Message::Text(json.into()));
+                        }
+                        continue;
+                    }
+                    let allowed = if pass.view_only {
+                        parsed.msg_type == "get_history"
+                    } else {
+                        matches!(
+                            parsed.msg_type.as_str(),
+                            "get_history" | "message" | "stop" | "cancel"
+                        )
+                    };
+                    if !allowed {
+                        continue;
+                    }
+                    parsed.chat_id.clone_from(chat_id);
+                    parsed.persona.clear();
+                    parsed.files.clear();
+                }
+
                 match parsed.msg_type.as_str() {
                     "get_history" => {
                         if parsed.chat_id.is_empty() {
                             continue;
                         }
                         send_history(&state.sessions_dir, &parsed.chat_id, &tx);
+                        set_viewing(&state.connections, &conn_id, Some(&parsed.chat_id));
                     }
                     "message" => {
                         let chat_id = parsed.chat_id;
-                        if chat_id.is_empty() || parsed.content.trim().is_empty() {
+                        let media = match state.workspace {
+                            Some(ref ws) => resolve_uploads(ws, &chat_id, &parsed.files),
+                            None => Vec::new(),
+                        };
+                        if chat_id.is_empty()
+                            || (parsed.content.trim().is_empty() && media.is_empty())
+                        {
                             continue;
                         }
+                        set_viewing(&state.connections, &conn_id, Some(&chat_id));
+                        // Same form as Telegram attachments, so the agent knows
+                        // where to read them
+                        let mut content = parsed.content;
+                        for path in &media {
+                            if !content.is_empty() {
+                                content.push('\n');
+                            }
+                            content.push_str(&format!("[file: {path}]"));
+                        }
 
                         // Echo user message to other connected clients
                         broadcast_to_others(
@@ -1033,7 +2427,7 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                             &conn_id,
                             &WsOutMsg {
                                 msg_type: "user_message".to_string(),
-                                content: Some(parsed.content.clone()),
+                                content: Some(content.clone()),
                                 chat_id: Some(chat_id.clone()),
                                 timestamp: Some(chrono::Local::now().to_rfc3339()),
                                 messages: None,
@@ -1053,9 +2447,24 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                             },
                         );
 
-                        let sender_id = format!("web:{}", &chat_id[..chat_id.len().min(8)]);
+                        let sender_id = match guest {
+                            Some(ref pass) => format!("guest:{}", pass.id),
+                            None => format!("web:{}", &chat_id[..chat_id.len().min(8)]),
+                        };
 
                         let mut metadata = HashMap::new();
+                        if let Some(ref pass) = guest {
+                            metadata.insert(
+                                "guest".to_string(),
+                                serde_json::Value::String(pass.id.clone()),
+                            );
+                        }
+                        if let Some(ref user) = user {
+                            metadata.insert(
+                                "user".to_string(),
+                                serde_json::Value::String(user.clone()),
+                            );
+                        }
                         if !parsed.persona.is_empty() {
                             metadata.insert(
                                 "persona".to_string(),
@@ -1067,8 +2476,8 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                             channel: "web".to_string(),
                             sender_id,
                             chat_id,
-                            content: parsed.content,
-                            media: Vec::new(),
+                            content,
+                            media,
                             metadata,
                             timestamp: chrono::Local::now().to_rfc3339(),
                         };
@@ -1109,7 +2518,8 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
                             },
                         );
                     }
-                    "cancel" => {
+                    // "cancel" is the old name, kept for open tabs
+                    "stop" | "cancel" => {
                         if parsed.chat_id.is_empty() {
                             continue;
                         }
@@ -1193,6 +2603,7 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
     }
 
     // Cleanup
+    set_viewing(&state.connections, &conn_id, None);
     state.connections.remove(&conn_id);
     write_handle.abort();
     info!("WebSocket disconnected: conn={short_conn}");
@@ -1200,19 +2611,59 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
 
 /// Broadcast a message to all WebSocket connections except the sender.
 fn broadcast_to_others(
-    connections: &DashMap<String, WsSender>,
+    connections: &DashMap<String, Connection>,
     exclude_conn_id: &str,
     msg: &WsOutMsg,
 ) {
     if let Ok(json) = serde_json::to_string(msg) {
         for entry in connections.iter() {
-            if entry.key() != exclude_conn_id {
-                let _ = entry.value().send(Message::Text(json.clone().into()));
+            if entry.key() != exclude_conn_id && entry.value().sees(msg.chat_id.as_deref()) {
+                let _ = entry.value().tx.send(Message::Text(json.clone().into()));
             }
         }
     }
 }
 
+/// Record which chat a connection is looking at, and if that changed, tell
+/// everyone watching the old and new chats who else is there.
+fn set_viewing(connections: &DashMap<String, Connection>, conn_id: &str, chat_id: Option<&str>) {
+    let previous = match connections.get_mut(conn_id) {
+        Some(mut conn) if conn.viewing.as_deref() != chat_id => {
+            std::mem::replace(&mut conn.viewing, chat_id.map(str::to_string))
+        }
+        _ => return,
+    };
+    for chat in previous.iter().map(String::as_str).chain(chat_id) {
+        broadcast_presence(connections, chat);
+    }
+}
+
+/// Send each connection that can see `chat_id` the names of the *other*
+/// connections viewing it.
+fn broadcast_presence(connections: &DashMap<String, Connection>, chat_id: &str) {
+    let viewers: Vec<(String, String)> = connections
+        .iter()
+        .filter(|c| c.viewing.as_deref() == Some(chat_id) && c.sees(Some(chat_id)))
+        .map(|c| (c.key().clone(), c.name.clone()))
+        .collect();
+    for entry in connections.iter() {
+        if !entry.sees(Some(chat_id)) {
+            continue;
+        }
+        let others: Vec<&str> = viewers
+            .iter()
+            .filter(|(id, _)| id != entry.key())
+            .map(|(_, name)| name.as_str())
+            .collect();
+        let msg = serde_json::json!({
+            "type": "presence",
+            "chatId": chat_id,
+            "viewers": others,
+        });
+        let _ = entry.tx.send(Message::Text(msg.to_string().into()));
+    }
+}
+
 /// Send session history for a chat over a WS connection.
 fn send_history(
     sessions_dir: &std::path::Path,
@@ -1259,8 +2710,7 @@ fn load_session_history_by_key(
     sessions_dir: &std::path::Path,
     session_key: &str,
 ) -> Vec<HistoryMessage> {
-    let safe_key = session_key.replace(':', "_");
-    let path = sessions_dir.join(format!("{safe_key}.jsonl"));
+    let path = sessions_dir.join(format!("{}.jsonl", safe_file_name(session_key)));
 
     let file = match std::fs::File::open(&path) {
         Ok(f) => f,
@@ -1356,15 +2806,29 @@ fn list_web_sessions(sessions_dir: &std::path::Path) -> Vec<SessionInfo> {
             continue;
         }
 
+        // Saves append a fresh metadata line, so take the latest one
+        let metadata = read_metadata(&entry.path());
+        let updated_at = metadata
+            .as_ref()
+            .and_then(|m| m.get("updated_at"))
+            .and_then(|t| t.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let persona = metadata
+            .as_ref()
+            .and_then(|m| m.get("metadata"))
+            .and_then(|m| m.get("persona"))
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(str::to_string);
+
         let file = match std::fs::File::open(entry.path()) {
             Ok(f) => f,
             Err(_) => continue,
         };
         let reader = std::io::BufReader::new(file);
 
-        let mut updated_at = String::new();
         let mut title = String::new();
-        let mut persona = None;
 
         for line in reader.lines().flatten() {
             let line = line.trim().to_string();
@@ -1376,39 +2840,16 @@ fn list_web_sessions(sessions_dir: &std::path::Path) -> Vec<SessionInfo> {
                 Err(_) => continue,
             };
 
-            // Read metadata for updated_at and persona
-            if value.get("_type").and_then(|t| t.as_str()) == Some("metadata") {
-                if let Some(ts) = value.get("updated_at").and_then(|t| t.as_str()) {
-                    updated_at = ts.to_string();
-                }
-                if let Some(p) = value
-                    .get("metadata")
-                    .and_then(|m| m.get("persona"))
-                    .and_then(|p| p.as_str())
-                {
-                    if !p.is_empty() {
-                        persona = Some(p.to_string());
-                    }
-                }
-                continue;
-            }
-
             // Find first user message for title
-            if title.is_empty() {
-                if value.get("role").and_then(|r| r.as_str()) == Some("user") {
-                    if let Some(content) = value.get("content").and_then(|c| c.as_str()) {
-                        title = content.chars().take(50).collect();
-                        if content.len() > 50 {
-                            title.push_str("...");
-                        }
+            if value.get("role").and_then(|r| r.as_str()) == Some("user") {
+                if let Some(content) = value.get("content").and_then(|c| c.as_str()) {
+                    title = content.chars().take(50).collect();
+                    if content.len() > 50 {
+                        title.push_str("...");
                     }
+                    break;
                 }
             }
-
-            // Once we have both, stop reading
-            if !updated_at.is_empty() && !title.is_empty() {
-                break;
-            }
         }
 
         if title.is_empty() {
@@ -1431,18 +2872,10 @@ fn list_web_sessions(sessions_dir: &std::path::Path) -> Vec<SessionInfo> {
 /// Write an interrupt flag file for the given session key.
 /// The agent loop checks for this flag and breaks out of streaming/tool loops.
 fn write_interrupt_flag(session_key: &str) -> std::io::Result<()> {
-    let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-    let dir = home.join(".patina").join("interrupts");
+    let dir = patina_config::data_dir().join("interrupts");
     std::fs::create_dir_all(&dir)?;
-    let safe: String = session_key
-        .chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | ' ' => '_',
-            _ => c,
-        })
-        .collect();
     std::fs::write(
-        dir.join(format!("{safe}.flag")),
+        dir.join(format!("{}.flag", safe_file_name(session_key))),
         chrono::Utc::now().to_rfc3339(),
     )
 }
@@ -1488,6 +2921,7 @@ mod tests {
                 password: String::new(),
                 allow_from: vec![],
                 system_prompt_rules: None,
+                ..Default::default()
             },
             GatewayConfig::default(),
             test_sessions_dir(),
@@ -1508,6 +2942,7 @@ mod tests {
                 password: String::new(),
                 allow_from: vec!["web:abc12345".to_string()],
                 system_prompt_rules: None,
+                ..Default::default()
             },
             GatewayConfig::default(),
             test_sessions_dir(),
@@ -1606,6 +3041,7 @@ mod tests {
             password: String::new(),
             allow_from: vec![],
             system_prompt_rules: None,
+            ..Default::default()
         };
         assert!(config.password.is_empty());
     }
@@ -1691,5 +3127,104 @@ mod tests {
         assert_eq!(sessions[0].id, "abc-123");
         assert_eq!(sessions[0].title, "What is Rust?");
         assert_eq!(sessions[0].updated_at, "2026-01-01T12:00:00Z");
+
+        // An appended save's metadata line wins
+        let mut f = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writeln!(
+            f,
+            r#"{{"_type":"metadata","updated_at":"2026-01-02T08:00:00Z","metadata":{{"persona":"coder"}}}}"#
+        )
+        .unwrap();
+        drop(f);
+        let sessions = list_web_sessions(dir.path());
+        assert_eq!(sessions[0].title, "What is Rust?");
+        assert_eq!(sessions[0].updated_at, "2026-01-02T08:00:00Z");
+        assert_eq!(sessions[0].persona.as_deref(), Some("coder"));
+    }
+
+    #[test]
+    fn test_upload_file_names() {
+        assert_eq!(
+            upload_file_name("report.csv").as_deref(),
+            Some("report.csv")
+        );
+        assert_eq!(
+            upload_file_name("C:\\Users\\me\\Q3 sales (final).xlsx").as_deref(),
+            Some("Q3 sales _final_.xlsx")
+        );
+        assert_eq!(
+            upload_file_name("../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(upload_file_name(".env"), None);
+        assert_eq!(upload_file_name("dir/"), None);
+    }
+
+    #[test]
+    fn test_uploads_resolve_within_chat() {
+        let dir = TempDir::new().unwrap();
+        let uploads = uploads_dir(dir.path(), "abc-123");
+        std::fs::create_dir_all(&uploads).unwrap();
+        std::fs::write(uploads.join("data.csv"), "a,b\n1,2\n").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "no").unwrap();
+
+        assert_eq!(
+            unused_path(&uploads, "data.csv"),
+            uploads.join("data-1.csv")
+        );
+        assert_eq!(unused_path(&uploads, "new.csv"), uploads.join("new.csv"));
+
+        let names = [
+            "data.csv".to_string(),
+            "missing.csv".to_string(),
+            "../../secret.txt".to_string(),
+        ];
+        let paths = resolve_uploads(dir.path(), "abc-123", &names);
+        assert_eq!(
+            paths,
+            vec![uploads.join("data.csv").to_string_lossy().into_owned()]
+        );
+        assert!(resolve_uploads(dir.path(), "other", &names).is_empty());
+    }
+
+    #[test]
+    fn test_location_user_from_basic_auth() {
+        use base64::Engine;
+
+        let basic = |credentials: &str| {
+            let mut headers = axum::http::HeaderMap::new();
+            let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+            headers.insert(
+                axum::http::header::AUTHORIZATION,
+                format!("Basic {encoded}").parse().unwrap(),
+            );
+            headers
+        };
+        let tokens = HashMap::from([("ada-token".to_string(), "ada".to_string())]);
+
+        // A user's token names the user, whatever the username says
+        assert_eq!(
+            location_user(&basic("phone:ada-token"), "secret", &tokens).as_deref(),
+            Some("ada")
+        );
+        assert_eq!(
+            location_user(&basic("bob:secret"), "secret", &tokens).as_deref(),
+            Some("bob")
+        );
+        assert_eq!(location_user(&basic("bob:wrong"), "secret", &tokens), None);
+
+        let mut headers = axum::http::HeaderMap::new();
+        assert_eq!(
+            location_user(&headers, "", &tokens).as_deref(),
+            Some("owner")
+        );
+        headers.insert("X-Limit-U", "carol".parse().unwrap());
+        assert_eq!(
+            location_user(&headers, "", &tokens).as_deref(),
+            Some("carol")
+        );
     }
 }
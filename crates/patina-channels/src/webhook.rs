@@ -0,0 +1,272 @@
+//! Outbound-only webhook channel.
+//!
+//! POSTs cron job and subagent results as JSON to other systems, such as an
+//! n8n workflow or a Home Assistant automation. Like `notify`, a message's
+//! chat ID names the target in `channels.webhook.targets`, so a cron job
+//! created with `--channel webhook --to n8n` goes to the `n8n` target. With a
+//! `secret`, each request carries `X-Patina-Signature: sha256=<hex>`, the
+//! HMAC-SHA256 of the body, for the receiver to check.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use patina_config::{WebhookConfig, WebhookTarget};
+use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+
+use crate::base::{Channel, ChannelCapabilities};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying the body's signature.
+pub const SIGNATURE_HEADER: &str = "X-Patina-Signature";
+
+/// Message metadata copied into the JSON, with the field it becomes.
+const METADATA_FIELDS: &[(&str, &str)] = &[
+    ("cron_job_id", "cronJobId"),
+    ("cron_job_name", "cronJobName"),
+    ("subagent_id", "subagentId"),
+    ("task_id", "taskId"),
+    ("status", "status"),
+];
+
+/// Webhook channel for the targets in `channels.webhook`.
+pub struct WebhookChannel {
+    targets: HashMap<String, WebhookTarget>,
+    http: reqwest::Client,
+}
+
+impl WebhookChannel {
+    /// Create the channel, checking each target has a URL.
+    pub fn new(config: WebhookConfig) -> Result<Self, ChannelError> {
+        if config.targets.is_empty() {
+            return Err(ChannelError::NotConfigured {
+                channel: "Webhook".into(),
+                what: "any targets".into(),
+            });
+        }
+        for (name, target) in &config.targets {
+            if target.url.trim().is_empty() {
+                return Err(ChannelError::NotConfigured {
+                    channel: "Webhook".into(),
+                    what: format!("URL (url) for target '{name}'"),
+                });
+            }
+        }
+        Ok(Self {
+            targets: config.targets,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    fn target(&self, name: &str) -> Result<&WebhookTarget> {
+        self.targets.get(name).with_context(|| {
+            let mut names: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            format!(
+                "no webhook target '{name}' (configured: {})",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl Channel for WebhookChannel {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn start(&self, _inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+        let mut names: Vec<&str> = self.targets.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        info!("Webhook channel ready (targets: {})", names.join(", "));
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, msg: &OutboundMessage) -> Result<()> {
+        let target = self.target(&msg.chat_id)?;
+        let body = payload(&msg.chat_id, msg, Utc::now());
+        build_request(&self.http, target, &body)?
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("webhook target '{}' rejected the result", msg.chat_id))?;
+        debug!("Posted result to webhook '{}'", msg.chat_id);
+        Ok(())
+    }
+
+    /// Nobody writes in, so there's no one to allow.
+    fn is_allowed(&self, _sender_id: &str) -> bool {
+        false
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            // Passed on as written, for the receiver to render or not
+            supports_markdown: true,
+            supports_edit: false,
+            supports_attachments: false,
+            max_message_len: None,
+        }
+    }
+}
+
+/// The JSON posted for `msg`: what produced it, the text, and the job or
+/// subagent it came from.
+fn payload(target: &str, msg: &OutboundMessage, sent_at: DateTime<Utc>) -> Value {
+    let source = if msg.metadata.contains_key("cron_job_id") {
+        "cron"
+    } else if msg.metadata.contains_key("subagent_id") {
+        "subagent"
+    } else {
+        "patina"
+    };
+    let mut body = json!({
+        "target": target,
+        "source": source,
+        "content": msg.content,
+        "sentAt": sent_at.to_rfc3339(),
+    });
+    for (key, field) in METADATA_FIELDS {
+        if let Some(value) = msg.metadata.get(*key) {
+            body[*field] = value.clone();
+        }
+    }
+    body
+}
+
+/// The request that posts `body` to `target`, signed with its secret.
+fn build_request(
+    http: &reqwest::Client,
+    target: &WebhookTarget,
+    body: &Value,
+) -> Result<reqwest::RequestBuilder> {
+    let body = serde_json::to_vec(body)?;
+    let mut request = http
+        .post(target.url.trim())
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = target.secret.as_deref().filter(|s| !s.is_empty()) {
+        request = request.header(SIGNATURE_HEADER, signature(secret.as_bytes(), &body));
+    }
+    for (name, value) in &target.headers {
+        request = request.header(name, value);
+    }
+    Ok(request.body(body))
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `body` under `secret`.
+fn signature(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(metadata: &[(&str, &str)]) -> OutboundMessage {
+        OutboundMessage {
+            channel: "webhook".into(),
+            chat_id: "n8n".into(),
+            content: "Disk usage: 71%".into(),
+            reply_to: None,
+            metadata: metadata
+                .iter()
+                .map(|(k, v)| (k.to_string(), json!(v)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn payload_names_the_cron_job() {
+        let sent_at = DateTime::parse_from_rfc3339("2026-03-02T07:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let msg = message(&[
+            ("cron_job_id", "ab12cd34"),
+            ("cron_job_name", "disks"),
+            ("cron_cache_key", "internal"),
+        ]);
+        assert_eq!(
+            payload("n8n", &msg, sent_at),
+            json!({
+                "target": "n8n",
+                "source": "cron",
+                "content": "Disk usage: 71%",
+                "sentAt": "2026-03-02T07:00:00+00:00",
+                "cronJobId": "ab12cd34",
+                "cronJobName": "disks",
+            })
+        );
+        let msg = message(&[("subagent_id", "9f00aa11"), ("status", "completed")]);
+        let body = payload("n8n", &msg, sent_at);
+        assert_eq!(
+            (body["source"].as_str(), body["status"].as_str()),
+            (Some("subagent"), Some("completed"))
+        );
+    }
+
+    #[test]
+    fn requests_are_signed_with_the_secret() {
+        let http = reqwest::Client::new();
+        let mut target = WebhookTarget {
+            url: "https://n8n.example/webhook/patina".into(),
+            headers: HashMap::from([("X-Source".to_string(), "patina".to_string())]),
+            ..Default::default()
+        };
+        let body = json!({ "content": "hi" });
+        let request = build_request(&http, &target, &body)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(request.headers().get(SIGNATURE_HEADER).is_none());
+        assert_eq!(request.headers()["X-Source"], "patina");
+
+        target.secret = Some("s3cret".into());
+        let request = build_request(&http, &target, &body)
+            .unwrap()
+            .build()
+            .unwrap();
+        let sent = request.body().unwrap().as_bytes().unwrap();
+        assert_eq!(sent, br#"{"content":"hi"}"#);
+        let header = request.headers()[SIGNATURE_HEADER].to_str().unwrap();
+        let mut mac = HmacSha256::new_from_slice(b"s3cret").unwrap();
+        mac.update(sent);
+        let expected = mac.finalize().into_bytes();
+        let hex = header.strip_prefix("sha256=").unwrap();
+        assert_eq!(hex.len(), 64);
+        let bytes: Vec<u8> = (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        assert_eq!(bytes, expected.as_slice());
+
+        let err = WebhookChannel::new(WebhookConfig {
+            enabled: true,
+            targets: HashMap::from([("ha".to_string(), WebhookTarget::default())]),
+        })
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("URL (url) for target 'ha'"));
+    }
+}
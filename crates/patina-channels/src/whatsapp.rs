@@ -0,0 +1,869 @@
+//! WhatsApp channel using the WhatsApp Business Cloud API.
+//!
+//! Meta posts incoming messages to a webhook at `/whatsapp`, signed with the
+//! app secret (`X-Hub-Signature-256`); a `GET` on the same path answers the
+//! subscription check with `verifyToken`. Replies go out through the Graph
+//! API's `/{phone-number-id}/messages`.
+//!
+//! Voice notes and audio files are downloaded to `~/.patina/media/` and
+//! transcribed the same way as Telegram's. Images, videos, and documents are
+//! downloaded and referenced by path.
+//!
+//! WhatsApp only allows free-form messages within 24 hours of the user's last
+//! message. [`Windows`] records when each number last wrote, in
+//! `~/.patina/whatsapp/windows.json`. A reply outside that window is held
+//! there, `windowTemplate` is sent in its place, and held replies go out as
+//! soon as the user writes again.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tracing::{debug, error, info, warn};
+
+use patina_config::{WhatsAppConfig, WhatsAppTemplate};
+use patina_core::bus::{InboundMessage, OutboundMessage};
+use patina_core::error::ChannelError;
+use patina_transcribe::limits::MediaLimits;
+
+use crate::base::{Channel, ChannelCapabilities};
+use crate::voice::transcribe_media;
+use crate::whatsapp_markdown::markdown_to_whatsapp;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How long after the user's last message free-form replies are allowed.
+const WINDOW_SECS: i64 = 24 * 60 * 60;
+/// Treat the window as closed this much early, so a reply isn't sent just as
+/// it closes.
+const WINDOW_MARGIN_SECS: i64 = 60;
+/// Graph API error for a free-form message outside the window.
+const ERROR_OUTSIDE_WINDOW: i64 = 131047;
+
+/// WhatsApp channel using the Cloud API.
+pub struct WhatsAppChannel {
+    config: WhatsAppConfig,
+    graph: Arc<Graph>,
+    windows: Arc<Windows>,
+    transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
+    limits: MediaLimits,
+    shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+#[derive(Clone)]
+struct WhatsAppState {
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    allow_from: Arc<Vec<String>>,
+    app_secret: Arc<String>,
+    verify_token: Arc<String>,
+    graph: Arc<Graph>,
+    windows: Arc<Windows>,
+    transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
+    limits: MediaLimits,
+}
+
+impl WhatsAppChannel {
+    /// Create a new WhatsApp channel from config.
+    pub fn new(
+        config: WhatsAppConfig,
+        transcriber: Option<Arc<dyn patina_transcribe::Transcriber>>,
+    ) -> Result<Self, ChannelError> {
+        let required = [
+            (&config.phone_number_id, "phone number ID (phoneNumberId)"),
+            (&config.access_token, "access token (accessToken)"),
+            (&config.app_secret, "app secret (appSecret)"),
+            (&config.verify_token, "webhook verify token (verifyToken)"),
+        ];
+        if let Some((_, what)) = required.iter().find(|(value, _)| value.is_empty()) {
+            return Err(ChannelError::NotConfigured {
+                channel: "WhatsApp".into(),
+                what: (*what).into(),
+            });
+        }
+
+        let graph = Graph {
+            http: reqwest::Client::new(),
+            base: format!(
+                "https://graph.facebook.com/{}",
+                config.api_version.as_deref().unwrap_or("v21.0")
+            ),
+            phone_number_id: config.phone_number_id.clone(),
+            access_token: config.access_token.clone(),
+        };
+        let path = patina_config::data_dir()
+            .join("whatsapp")
+            .join("windows.json");
+        Ok(Self {
+            config,
+            graph: Arc::new(graph),
+            windows: Arc::new(Windows::load(path)),
+            transcriber,
+            limits: MediaLimits::default(),
+            shutdown_tx: Mutex::new(None),
+        })
+    }
+
+    /// Refuse voice and audio messages over these limits instead of downloading them.
+    pub fn with_media_limits(mut self, limits: MediaLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Hold a reply that can't be sent until the user writes again, sending
+    /// the window template the first time.
+    async fn hold(&self, to: &str, text: String) -> Result<()> {
+        let Some(ref template) = self.config.window_template else {
+            anyhow::bail!(
+                "{to} hasn't written in the last 24 hours, so WhatsApp only accepts a template message; set windowTemplate to send one"
+            );
+        };
+        if self.windows.hold(to, text) {
+            info!(
+                "Outside the 24-hour window for {to}; sending template '{}'",
+                template.name
+            );
+            self.graph.send_template(to, template).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Channel for WhatsAppChannel {
+    fn name(&self) -> &str {
+        "whatsapp"
+    }
+
+    async fn start(&self, inbound_tx: mpsc::Sender<InboundMessage>) -> Result<()> {
+        let state = WhatsAppState {
+            inbound_tx,
+            allow_from: Arc::new(self.config.allow_from.clone()),
+            app_secret: Arc::new(self.config.app_secret.clone()),
+            verify_token: Arc::new(self.config.verify_token.clone()),
+            graph: self.graph.clone(),
+            windows: self.windows.clone(),
+            transcriber: self.transcriber.clone(),
+            limits: self.limits,
+        };
+        let router = Router::new()
+            .route("/whatsapp", get(handle_verify).post(handle_webhook))
+            .with_state(state);
+
+        let listen = self.config.listen.as_deref().unwrap_or("0.0.0.0");
+        let port = self.config.port.unwrap_or(3979);
+        let addr: SocketAddr = format!("{listen}:{port}").parse().map_err(|e| {
+            anyhow::anyhow!("Invalid WhatsApp listen address '{listen}:{port}': {e}")
+        })?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("WhatsApp channel listening on http://{addr}/whatsapp");
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        *self.shutdown_tx.lock().await = Some(shutdown_tx);
+        tokio::spawn(async move {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            });
+            if let Err(e) = server.await {
+                error!("WhatsApp server error: {e}");
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<()> {
+        info!("Stopping WhatsApp channel...");
+        if let Some(tx) = self.shutdown_tx.lock().await.take() {
+            let _ = tx.send(());
+        }
+        Ok(())
+    }
+
+    async fn send(&self, msg: &OutboundMessage) -> Result<()> {
+        let text = markdown_to_whatsapp(&msg.content);
+        if !self
+            .windows
+            .is_open(&msg.chat_id, chrono::Utc::now().timestamp())
+        {
+            return self.hold(&msg.chat_id, text).await;
+        }
+        match self.graph.send_text(&msg.chat_id, &text).await {
+            Err(e)
+                if e.downcast_ref::<GraphError>().map(|g| g.code) == Some(ERROR_OUTSIDE_WINDOW) =>
+            {
+                self.hold(&msg.chat_id, text).await
+            }
+            result => result,
+        }
+    }
+
+    fn is_allowed(&self, sender_id: &str) -> bool {
+        is_sender_allowed(sender_id, &self.config.allow_from)
+    }
+
+    fn prompt_rules(&self) -> &str {
+        self.config.system_prompt_rules.as_deref().unwrap_or(
+            "No markdown tables or headers. Keep replies short; they're read on a phone.",
+        )
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: false,
+            supports_attachments: false,
+            max_message_len: Some(4096),
+        }
+    }
+}
+
+/// Answer Meta's subscription check.
+async fn handle_verify(
+    State(state): State<WhatsAppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let param = |name: &str| params.get(name).map(String::as_str);
+    if param("hub.mode") == Some("subscribe")
+        && param("hub.verify_token") == Some(state.verify_token.as_str())
+    {
+        info!("WhatsApp webhook subscription verified");
+        return param("hub.challenge")
+            .unwrap_or_default()
+            .to_string()
+            .into_response();
+    }
+    warn!("Rejected WhatsApp webhook verification with a wrong verify token");
+    StatusCode::FORBIDDEN.into_response()
+}
+
+/// Check the signature, acknowledge at once, and handle the messages in the
+/// background, since Meta resends a delivery that isn't answered quickly.
+async fn handle_webhook(
+    State(state): State<WhatsAppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let signature = headers
+        .get("x-hub-signature-256")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("sha256="));
+    if !signature.is_some_and(|s| verify_signature(state.app_secret.as_bytes(), &body, s)) {
+        warn!("Rejected WhatsApp webhook request with a bad signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+    let webhook: Webhook = match serde_json::from_slice(&body) {
+        Ok(webhook) => webhook,
+        Err(e) => {
+            warn!("Ignoring malformed WhatsApp webhook: {e}");
+            return StatusCode::OK;
+        }
+    };
+
+    for (message, name) in webhook.messages() {
+        let state = state.clone();
+        tokio::spawn(async move { handle_message(state, message, name).await });
+    }
+    StatusCode::OK
+}
+
+async fn handle_message(state: WhatsAppState, message: WaMessage, name: Option<String>) {
+    let from = message.from.clone();
+    if !is_sender_allowed(&from, &state.allow_from) {
+        warn!("Access denied for sender {from} on WhatsApp. Add to allowFrom to grant access.");
+        return;
+    }
+
+    // The user writing reopens the window; deliver what was held first
+    for text in state
+        .windows
+        .record_inbound(&from, chrono::Utc::now().timestamp())
+    {
+        if let Err(e) = state.graph.send_text(&from, &text).await {
+            warn!("Failed to deliver held WhatsApp reply to {from}: {e:#}");
+        }
+    }
+    if let Err(e) = state.graph.mark_read(&message.id).await {
+        debug!("Failed to mark WhatsApp message read: {e:#}");
+    }
+
+    let mut content_parts: Vec<String> = Vec::new();
+    let mut media_paths: Vec<String> = Vec::new();
+    if let Some(text) = message.text() {
+        content_parts.push(text);
+    }
+    if let Some((kind, media)) = message.media() {
+        let is_audio = kind == "voice" || kind == "audio";
+        match state
+            .graph
+            .download_media(media, kind, is_audio.then_some(&state.limits))
+            .await
+        {
+            Ok(Download::Saved(path)) => {
+                media_paths.push(path.clone());
+                if is_audio {
+                    content_parts.push(
+                        transcribe_media(
+                            state.transcriber.as_ref(),
+                            &path,
+                            kind,
+                            &format!("whatsapp:{from}"),
+                            &state.limits,
+                        )
+                        .await,
+                    );
+                } else {
+                    content_parts.push(format!("[{kind}: {path}]"));
+                }
+            }
+            Ok(Download::OverLimit(e)) => {
+                // Over-limit audio gets a refusal reply and never reaches the agent
+                info!("Refusing audio from {from}: {e}");
+                if let Err(e) = state.graph.send_text(&from, &e.refusal()).await {
+                    warn!("Failed to send audio limit refusal: {e:#}");
+                }
+                return;
+            }
+            Err(e) => {
+                error!("Failed to download WhatsApp {kind}: {e:#}");
+                content_parts.push(format!("[{kind}: download failed]"));
+            }
+        }
+        if let Some(caption) = media.caption.as_deref().filter(|c| !c.is_empty()) {
+            content_parts.push(caption.to_string());
+        }
+    }
+    if content_parts.is_empty() {
+        debug!("Ignoring WhatsApp {} message from {from}", message.kind);
+        return;
+    }
+
+    let content = content_parts.join("\n");
+    debug!(
+        "WhatsApp message from {from}: {}...",
+        content.chars().take(50).collect::<String>()
+    );
+    let mut metadata = HashMap::new();
+    metadata.insert("message_id".to_string(), Value::String(message.id));
+    if let Some(name) = name {
+        metadata.insert("user_name".to_string(), Value::String(name));
+    }
+    let inbound = InboundMessage {
+        channel: "whatsapp".to_string(),
+        sender_id: from.clone(),
+        chat_id: from,
+        content,
+        media: media_paths,
+        metadata,
+        timestamp: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+    };
+    if let Err(e) = state.inbound_tx.send(inbound).await {
+        error!("Failed to send inbound message: {e}");
+    }
+}
+
+/// Whether `signature` is the hex HMAC-SHA256 of `body` under the app secret.
+fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let signature = signature.trim();
+    if !signature.len().is_multiple_of(2) || !signature.is_ascii() {
+        return false;
+    }
+    let Ok(signature) = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+    else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Check a sender's phone number against allow_from, ignoring the `+`,
+/// spaces, and dashes people write numbers with.
+fn is_sender_allowed(sender_id: &str, allow_from: &[String]) -> bool {
+    let digits = |s: &str| -> String { s.chars().filter(char::is_ascii_digit).collect() };
+    allow_from.is_empty() || allow_from.iter().any(|a| digits(a) == digits(sender_id))
+}
+
+// --- Webhook payload ---
+
+#[derive(Debug, Deserialize)]
+struct Webhook {
+    #[serde(default)]
+    entry: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(default)]
+    changes: Vec<Change>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Change {
+    value: ChangeValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangeValue {
+    #[serde(default)]
+    contacts: Vec<Contact>,
+    /// Absent on delivery and read status updates.
+    #[serde(default)]
+    messages: Vec<WaMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Contact {
+    wa_id: String,
+    #[serde(default)]
+    profile: Option<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaMessage {
+    from: String,
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<TextBody>,
+    #[serde(default)]
+    audio: Option<Media>,
+    #[serde(default)]
+    image: Option<Media>,
+    #[serde(default)]
+    video: Option<Media>,
+    #[serde(default)]
+    document: Option<Media>,
+    /// Quick-reply button on a template message.
+    #[serde(default)]
+    button: Option<TextButton>,
+    /// Reply to an interactive button or list message.
+    #[serde(default)]
+    interactive: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextBody {
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextButton {
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Media {
+    id: String,
+    #[serde(default)]
+    mime_type: Option<String>,
+    #[serde(default)]
+    caption: Option<String>,
+    /// Set on audio recorded in WhatsApp as a voice note.
+    #[serde(default)]
+    voice: bool,
+}
+
+impl Webhook {
+    /// Every message in the delivery, with the sender's profile name.
+    fn messages(self) -> Vec<(WaMessage, Option<String>)> {
+        self.entry
+            .into_iter()
+            .flat_map(|entry| entry.changes)
+            .flat_map(|change| {
+                let names: HashMap<String, String> = change
+                    .value
+                    .contacts
+                    .into_iter()
+                    .filter_map(|c| Some((c.wa_id, c.profile?.name)))
+                    .collect();
+                change.value.messages.into_iter().map(move |m| {
+                    let name = names.get(&m.from).cloned();
+                    (m, name)
+                })
+            })
+            .collect()
+    }
+}
+
+impl WaMessage {
+    /// What the user typed or tapped.
+    fn text(&self) -> Option<String> {
+        if let Some(ref text) = self.text {
+            return Some(text.body.clone());
+        }
+        if let Some(ref button) = self.button {
+            return Some(button.text.clone());
+        }
+        let reply = self.interactive.as_ref()?;
+        ["button_reply", "list_reply"]
+            .iter()
+            .find_map(|kind| reply.get(kind)?.get("title")?.as_str())
+            .map(str::to_string)
+    }
+
+    /// The attached media and what to call it.
+    fn media(&self) -> Option<(&'static str, &Media)> {
+        if let Some(ref audio) = self.audio {
+            return Some((if audio.voice { "voice" } else { "audio" }, audio));
+        }
+        [
+            ("image", &self.image),
+            ("video", &self.video),
+            ("document", &self.document),
+        ]
+        .into_iter()
+        .find_map(|(kind, media)| Some((kind, media.as_ref()?)))
+    }
+}
+
+// --- Graph API ---
+
+/// The Cloud API endpoints the channel uses.
+struct Graph {
+    http: reqwest::Client,
+    base: String,
+    phone_number_id: String,
+    access_token: String,
+}
+
+/// An error the Graph API returned, with its numeric code.
+#[derive(Debug)]
+struct GraphError {
+    code: i64,
+    message: String,
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WhatsApp API error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+enum Download {
+    Saved(String),
+    OverLimit(patina_transcribe::limits::LimitExceeded),
+}
+
+impl Graph {
+    async fn send_text(&self, to: &str, text: &str) -> Result<()> {
+        self.post_message(json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "text",
+            "text": { "body": text, "preview_url": false },
+        }))
+        .await
+    }
+
+    async fn send_template(&self, to: &str, template: &WhatsAppTemplate) -> Result<()> {
+        self.post_message(json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "template",
+            "template": {
+                "name": template.name,
+                "language": { "code": template.language.as_deref().unwrap_or("en_US") },
+            },
+        }))
+        .await
+    }
+
+    async fn mark_read(&self, message_id: &str) -> Result<()> {
+        self.post_message(json!({
+            "messaging_product": "whatsapp",
+            "status": "read",
+            "message_id": message_id,
+        }))
+        .await
+    }
+
+    async fn post_message(&self, body: Value) -> Result<()> {
+        let response = self
+            .http
+            .post(format!("{}/{}/messages", self.base, self.phone_number_id))
+            .bearer_auth(&self.access_token)
+            .json(&body)
+            .send()
+            .await?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        let error = &body["error"];
+        Err(GraphError {
+            code: error["code"].as_i64().unwrap_or(status.as_u16().into()),
+            message: error["message"]
+                .as_str()
+                .unwrap_or(status.as_str())
+                .to_string(),
+        }
+        .into())
+    }
+
+    /// Download media to ~/.patina/media/, checking its size against
+    /// `limits` first when given.
+    async fn download_media(
+        &self,
+        media: &Media,
+        kind: &str,
+        limits: Option<&MediaLimits>,
+    ) -> Result<Download> {
+        #[derive(Deserialize)]
+        struct MediaInfo {
+            url: String,
+            #[serde(default)]
+            file_size: Option<u64>,
+        }
+        let info: MediaInfo = self
+            .http
+            .get(format!("{}/{}", self.base, media.id))
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(limits) = limits {
+            if let Err(e) = limits.check(None, info.file_size) {
+                return Ok(Download::OverLimit(e));
+            }
+        }
+
+        let bytes = self
+            .http
+            .get(&info.url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let media_dir = patina_config::data_dir().join("media");
+        std::fs::create_dir_all(&media_dir)?;
+        let short_id = &media.id[..media.id.len().min(16)];
+        let ext = extension(media.mime_type.as_deref());
+        let file_path = media_dir.join(format!("wa_{short_id}{ext}"));
+        tokio::fs::write(&file_path, &bytes)
+            .await
+            .with_context(|| format!("writing {}", file_path.display()))?;
+
+        debug!("Downloaded {kind} to {}", file_path.display());
+        Ok(Download::Saved(file_path.to_string_lossy().to_string()))
+    }
+}
+
+/// File extension for a WhatsApp media MIME type.
+fn extension(mime_type: Option<&str>) -> &'static str {
+    // Voice notes come as "audio/ogg; codecs=opus"
+    match mime_type.and_then(|m| m.split(';').next()).map(str::trim) {
+        Some("audio/ogg") => ".ogg",
+        Some("audio/mpeg") => ".mp3",
+        Some("audio/mp4") => ".m4a",
+        Some("audio/aac") => ".aac",
+        Some("audio/amr") => ".amr",
+        Some("image/jpeg") => ".jpg",
+        Some("image/png") => ".png",
+        Some("image/webp") => ".webp",
+        Some("video/mp4") => ".mp4",
+        Some("application/pdf") => ".pdf",
+        _ => "",
+    }
+}
+
+// --- 24-hour window ---
+
+/// When each number last wrote, and the replies waiting for it to write again.
+struct Windows {
+    path: PathBuf,
+    chats: std::sync::Mutex<HashMap<String, ChatWindow>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChatWindow {
+    /// Unix time of the user's last message.
+    last_inbound: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    held: Vec<String>,
+}
+
+impl Windows {
+    fn load(path: PathBuf) -> Self {
+        let chats = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            chats: std::sync::Mutex::new(chats),
+        }
+    }
+
+    fn is_open(&self, chat: &str, now: i64) -> bool {
+        let chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        chats
+            .get(chat)
+            .is_some_and(|w| now < w.last_inbound + WINDOW_SECS - WINDOW_MARGIN_SECS)
+    }
+
+    /// Record a message from the user and take the replies held for them.
+    fn record_inbound(&self, chat: &str, now: i64) -> Vec<String> {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let window = chats.entry(chat.to_string()).or_default();
+        window.last_inbound = now;
+        let held = std::mem::take(&mut window.held);
+        self.save(&chats);
+        held
+    }
+
+    /// Hold a reply until the user writes. True for the first one held,
+    /// when the template should be sent.
+    fn hold(&self, chat: &str, text: String) -> bool {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let window = chats.entry(chat.to_string()).or_default();
+        window.held.push(text);
+        let first = window.held.len() == 1;
+        self.save(&chats);
+        first
+    }
+
+    fn save(&self, chats: &HashMap<String, ChatWindow>) {
+        let saved = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                let json = serde_json::to_string_pretty(chats)?;
+                std::fs::write(&self.path, json)
+            });
+        if let Err(e) = saved {
+            warn!(
+                "Failed to save WhatsApp windows to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_messages_carry_names_and_media() {
+        let webhook: Webhook = serde_json::from_value(json!({
+            "object": "whatsapp_business_account",
+            "entry": [{
+                "id": "WABA_ID",
+                "changes": [{
+                    "field": "messages",
+                    "value": {
+                        "messaging_product": "whatsapp",
+                        "metadata": { "phone_number_id": "106540352242922" },
+                        "contacts": [{ "profile": { "name": "Mum" }, "wa_id": "447700900123" }],
+                        "messages": [
+                            {
+                                "from": "447700900123", "id": "wamid.1", "timestamp": "1700000000",
+                                "type": "audio",
+                                "audio": { "id": "987", "mime_type": "audio/ogg; codecs=opus", "voice": true }
+                            },
+                            {
+                                "from": "447700900123", "id": "wamid.2", "timestamp": "1700000001",
+                                "type": "interactive",
+                                "interactive": { "type": "button_reply", "button_reply": { "id": "yes", "title": "Yes please" } }
+                            }
+                        ]
+                    }
+                }]
+            }]
+        }))
+        .unwrap();
+        let messages = webhook.messages();
+        assert_eq!(messages.len(), 2);
+        let (voice, name) = &messages[0];
+        assert_eq!(name.as_deref(), Some("Mum"));
+        let (kind, media) = voice.media().unwrap();
+        assert_eq!(kind, "voice");
+        assert_eq!(extension(media.mime_type.as_deref()), ".ogg");
+        assert_eq!(messages[1].0.text().as_deref(), Some("Yes please"));
+    }
+
+    #[test]
+    fn status_updates_have_no_messages() {
+        let webhook: Webhook = serde_json::from_value(json!({
+            "entry": [{ "changes": [{ "value": { "statuses": [{ "id": "wamid.1", "status": "read" }] } }] }]
+        }))
+        .unwrap();
+        assert!(webhook.messages().is_empty());
+    }
+
+    #[test]
+    fn signature_is_hex_hmac_of_body() {
+        let body = br#"{"entry":[]}"#;
+        let mut mac = HmacSha256::new_from_slice(b"app-secret").unwrap();
+        mac.update(body);
+        let hex: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        assert!(verify_signature(b"app-secret", body, &hex));
+        assert!(!verify_signature(b"other", body, &hex));
+        assert!(!verify_signature(b"app-secret", body, "zz"));
+    }
+
+    #[test]
+    fn allowlist_ignores_number_formatting() {
+        let allow = vec!["+44 7700-900123".to_string()];
+        assert!(is_sender_allowed("447700900123", &allow));
+        assert!(!is_sender_allowed("447700900999", &allow));
+    }
+
+    #[test]
+    fn window_holds_replies_until_the_user_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("windows.json");
+        let windows = Windows::load(path.clone());
+        let now = 1_700_000_000;
+        assert!(!windows.is_open("447700900123", now));
+
+        windows.record_inbound("447700900123", now);
+        assert!(windows.is_open("447700900123", now + 60));
+        assert!(!windows.is_open("447700900123", now + WINDOW_SECS));
+
+        // Only the first held reply sends the template
+        assert!(windows.hold("447700900123", "one".into()));
+        assert!(!windows.hold("447700900123", "two".into()));
+
+        let reloaded = Windows::load(path);
+        let held = reloaded.record_inbound("447700900123", now + 2 * WINDOW_SECS);
+        assert_eq!(held, vec!["one", "two"]);
+        assert!(reloaded.is_open("447700900123", now + 2 * WINDOW_SECS));
+    }
+}
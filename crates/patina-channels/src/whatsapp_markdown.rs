@@ -0,0 +1,162 @@
+//! Markdown-to-WhatsApp converter.
+//!
+//! WhatsApp formats a handful of markers and shows everything else literally:
+//! - Bold: `*text*` (not `**text**`)
+//! - Italic: `_text_` (same)
+//! - Strikethrough: `~text~` (not `~~text~~`)
+//! - Code: `` `code` `` and ` ```code``` ` (same)
+//! - Links: bare URLs only — `[text](url)` becomes `text (url)`
+//! - No headers — converted to bold
+//! - No tables — converted to monospaced code blocks
+
+use regex::Regex;
+
+/// Convert markdown text to WhatsApp formatting.
+pub fn markdown_to_whatsapp(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+
+    let mut text = text.to_string();
+
+    // 1. Extract and protect code blocks (``` ... ```)
+    let mut code_blocks: Vec<String> = Vec::new();
+    let re_code_block = Regex::new(r"```[\w]*\n?([\s\S]*?)```").unwrap();
+    text = re_code_block
+        .replace_all(&text, |caps: &regex::Captures| {
+            let idx = code_blocks.len();
+            code_blocks.push(caps[1].trim_end_matches('\n').to_string());
+            format!("\x00CB{idx}\x00")
+        })
+        .into_owned();
+
+    // 2. Extract and protect inline code (` ... `)
+    let mut inline_codes: Vec<String> = Vec::new();
+    let re_inline = Regex::new(r"`([^`]+)`").unwrap();
+    text = re_inline
+        .replace_all(&text, |caps: &regex::Captures| {
+            let idx = inline_codes.len();
+            inline_codes.push(caps[1].to_string());
+            format!("\x00IC{idx}\x00")
+        })
+        .into_owned();
+
+    // 3. Tables → aligned columns, kept as code blocks
+    let re_table = Regex::new(r"(?m)(?:^\|.+\|$\n?)+").unwrap();
+    text = re_table
+        .replace_all(&text, |caps: &regex::Captures| {
+            let idx = code_blocks.len();
+            code_blocks.push(table_to_columns(caps[0].trim()));
+            let trailing = if caps[0].ends_with('\n') { "\n" } else { "" };
+            format!("\x00CB{idx}\x00{trailing}")
+        })
+        .into_owned();
+
+    // 4. Links [text](url) → text (url)
+    let re_links = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
+    text = re_links.replace_all(&text, "$1 ($2)").into_owned();
+
+    // 5. Bold **text** or __text__ → *text*
+    let re_bold_star = Regex::new(r"\*\*(.+?)\*\*").unwrap();
+    text = re_bold_star.replace_all(&text, "*$1*").into_owned();
+    let re_bold_under = Regex::new(r"__(.+?)__").unwrap();
+    text = re_bold_under.replace_all(&text, "*$1*").into_owned();
+
+    // 6. Strikethrough ~~text~~ → ~text~
+    let re_strike = Regex::new(r"~~(.+?)~~").unwrap();
+    text = re_strike.replace_all(&text, "~$1~").into_owned();
+
+    // 7. Headers # Title → *Title*
+    let re_headers = Regex::new(r"(?m)^#{1,6}\s+(.+)$").unwrap();
+    text = re_headers.replace_all(&text, "*$1*").into_owned();
+
+    // 8. Bullet lists * item → - item, so the marker isn't read as bold
+    let re_bullet = Regex::new(r"(?m)^(\s*)\*\s+").unwrap();
+    text = re_bullet.replace_all(&text, "$1- ").into_owned();
+
+    // 9. Restore inline code
+    for (i, code) in inline_codes.iter().enumerate() {
+        text = text.replace(&format!("\x00IC{i}\x00"), &format!("`{code}`"));
+    }
+
+    // 10. Restore code blocks and tables
+    for (i, code) in code_blocks.iter().enumerate() {
+        text = text.replace(&format!("\x00CB{i}\x00"), &format!("```{code}```"));
+    }
+
+    text
+}
+
+/// Lay out a markdown table as space-aligned columns.
+fn table_to_columns(table: &str) -> String {
+    let mut rows: Vec<Vec<String>> = table
+        .lines()
+        .map(|line| line.trim().trim_matches('|'))
+        .filter(|line| !line.chars().all(|c| "-: |".contains(c)))
+        .map(|line| line.split('|').map(|c| c.trim().to_string()).collect())
+        .collect();
+    let n_cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(n_cols, String::new());
+    }
+    let widths: Vec<usize> = (0..n_cols)
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, &w)| format!("{cell:<w$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(
+            markdown_to_whatsapp("**bold**, _italic_ and ~~gone~~"),
+            "*bold*, _italic_ and ~gone~"
+        );
+    }
+
+    #[test]
+    fn test_headers_and_bullets() {
+        assert_eq!(
+            markdown_to_whatsapp("## Today\n* milk\n  * oat"),
+            "*Today*\n- milk\n  - oat"
+        );
+    }
+
+    #[test]
+    fn test_links_show_the_url() {
+        assert_eq!(
+            markdown_to_whatsapp("See [the docs](https://example.com)."),
+            "See the docs (https://example.com)."
+        );
+    }
+
+    #[test]
+    fn test_code_is_left_alone() {
+        assert_eq!(
+            markdown_to_whatsapp("Run `**not bold**`:\n```sh\nls *.md\n```"),
+            "Run `**not bold**`:\n```ls *.md```"
+        );
+    }
+
+    #[test]
+    fn test_table_becomes_columns() {
+        assert_eq!(
+            markdown_to_whatsapp("| Item | Qty |\n|---|---|\n| apples | 3 |\nafter"),
+            "```Item    Qty\napples  3```\nafter"
+        );
+    }
+}
@@ -1,36 +1,56 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use patina_channels::control::{self, ControlRequest};
+use patina_channels::guest::{link_ttl, GuestLinks};
 use patina_channels::manager::ChannelManager;
+use patina_channels::notify::NotifyChannel;
 use patina_channels::slack::SlackChannel;
+use patina_channels::teams::TeamsChannel;
 use patina_channels::telegram::TelegramChannel;
-use patina_channels::web::WebChannel;
-use patina_config::{find_config_path, load_config, resolve_workspace};
-use patina_core::agent::subagent::SubagentManager;
-use patina_core::agent::{
-    AgentLoop, ConsolidationResult, ContextBuilder, MemoryIndex, ModelOverrides, ModelPool,
+use patina_channels::web::{Locations, StatusSources, WebChannel};
+use patina_channels::webhook::WebhookChannel;
+use patina_channels::whatsapp::WhatsAppChannel;
+use patina_config::{data_dir, find_config_path, load_config, resolve_workspace, MemoryAccess};
+use patina_core::agent::access::AccessControl;
+use patina_core::agent::batch::{self, BatchOptions};
+use patina_core::agent::consolidation_retries::ConsolidationRetries;
+use patina_core::agent::eval::{self, EvalSuite};
+use patina_core::agent::memory_approvals;
+use patina_core::agent::profile;
+use patina_core::agent::replay::{self, Recording};
+use patina_core::agent::{AgentLoop, ConsolidationResult, ConsolidationTask};
+use patina_core::builder::{self, Agent, AgentBuilder, ContextTools};
+use patina_core::bus::{take_next, InboundMessage, OutboundMessage};
+use patina_core::bus_journal::{journal_id, BusJournal};
+use patina_core::commands::{
+    AnswerCommand, CommandRegistry, ContextCommand, DraftCommand, ForgetCommand, HelpCommand,
+    LockdownCommand, MemoryCommand, NewSessionCommand, PersonaCommand, PlanCommand, ProjectCommand,
+    SetCommand, ShowCommand, StartCommand, StopCommand, TaskCommand,
 };
-use patina_core::bus::{InboundMessage, MessageBus, OutboundMessage};
-use patina_core::cron::CronService;
+use patina_core::compare::ComparisonStore;
+use patina_core::cron::{reply_metadata, CronService, DigestSources};
+use patina_core::dry_run::{plan_prompt, report, DryRun};
+use patina_core::error::{PatinaError, ProviderError};
+use patina_core::export::{export_session, ExportFormat};
+use patina_core::import;
+use patina_core::locale::Locales;
+use patina_core::lockdown::Lockdown;
+use patina_core::moderation::{Direction, Moderator, Verdict};
+use patina_core::ollama;
 use patina_core::persona::PersonaStore;
-use patina_core::session::SessionManager;
-use patina_core::task::TaskManager;
-use patina_core::tools::cron::CronTool;
-use patina_core::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
-use patina_core::tools::memory_search::MemorySearchTool;
+use patina_core::portable::{from_yaml, to_yaml};
+use patina_core::questions::Questions;
+use patina_core::session::{safe_file_name, SessionManager};
+use patina_core::standup::{self, STANDUP_CHANNEL};
+use patina_core::task::{Capture, TaskManager};
 use patina_core::tools::message::MessageTool;
-use patina_core::tools::shell::ExecTool;
-use patina_core::tools::spawn::SpawnTool;
-use patina_core::tools::task::TaskTool;
-use patina_core::tools::web::{WebFetchTool, WebSearchTool};
-use patina_core::tools::ToolRegistry;
-#[allow(deprecated)]
-use rig::client::completion::CompletionModelHandle;
-use rig::client::{CompletionClient, Nothing};
-use rig::providers::{anthropic, deepseek, gemini, groq, mistral, ollama, openai, openrouter};
+use patina_core::tools::transcribe::TranscribeTool;
+use patina_core::users::UserDirectory;
+use patina_core::watchdog::{job_key, Watchdog, HEARTBEAT};
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 use tokio::sync::Mutex;
@@ -41,15 +61,21 @@ fn render_markdown(text: &str) {
     skin.print_text(text);
 }
 
+/// Terminal attributes (termios) on Unix, the console input mode on Windows.
+#[cfg(unix)]
+type TerminalState = nix::sys::termios::Termios;
+#[cfg(windows)]
+type TerminalState = windows_sys::Win32::System::Console::CONSOLE_MODE;
+
 /// Save terminal attributes for later restoration.
 #[cfg(unix)]
-fn save_terminal_state() -> Option<nix::sys::termios::Termios> {
+fn save_terminal_state() -> Option<TerminalState> {
     nix::sys::termios::tcgetattr(std::io::stdin()).ok()
 }
 
 /// Restore previously saved terminal attributes.
 #[cfg(unix)]
-fn restore_terminal_state(saved: &nix::sys::termios::Termios) {
+fn restore_terminal_state(saved: &TerminalState) {
     let _ = nix::sys::termios::tcsetattr(
         std::io::stdin(),
         nix::sys::termios::SetArg::TCSADRAIN,
@@ -63,6 +89,39 @@ fn flush_pending_input() {
     let _ = nix::sys::termios::tcflush(std::io::stdin(), nix::sys::termios::FlushArg::TCIFLUSH);
 }
 
+/// Save the console input mode for later restoration. `None` when stdin
+/// isn't a console (piped input).
+#[cfg(windows)]
+fn save_terminal_state() -> Option<TerminalState> {
+    use windows_sys::Win32::System::Console::{GetConsoleMode, GetStdHandle, STD_INPUT_HANDLE};
+    let mut mode = 0;
+    // SAFETY: GetStdHandle has no preconditions; GetConsoleMode only writes `mode`
+    let ok = unsafe { GetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), &mut mode) };
+    (ok != 0).then_some(mode)
+}
+
+/// Restore a previously saved console input mode.
+#[cfg(windows)]
+fn restore_terminal_state(saved: &TerminalState) {
+    use windows_sys::Win32::System::Console::{GetStdHandle, SetConsoleMode, STD_INPUT_HANDLE};
+    // SAFETY: both calls take plain values; a stale handle just fails
+    unsafe {
+        SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), *saved);
+    }
+}
+
+/// Discard keystrokes typed while the agent was busy.
+#[cfg(windows)]
+fn flush_pending_input() {
+    use windows_sys::Win32::System::Console::{
+        FlushConsoleInputBuffer, GetStdHandle, STD_INPUT_HANDLE,
+    };
+    // SAFETY: fails harmlessly when stdin isn't a console
+    unsafe {
+        FlushConsoleInputBuffer(GetStdHandle(STD_INPUT_HANDLE));
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "patina", about = "Lightweight AI agent", version)]
 struct Cli {
@@ -82,9 +141,40 @@ enum Commands {
         #[arg(short, long)]
         message: Option<String>,
 
+        /// With --message, show what the agent would do without running
+        /// commands, changing files, sending messages, or spawning
+        #[arg(long, requires = "message")]
+        dry_run: bool,
+
         /// Session ID for conversation tracking
         #[arg(short, long, default_value = "cli:interactive")]
         session: String,
+
+        /// Speak instead of typing: press Enter on an empty line to record
+        #[arg(long, conflicts_with = "message")]
+        voice: bool,
+
+        /// Hands-free mode: wait for the wake word, then listen for a request
+        #[arg(long, conflicts_with_all = ["message", "voice"])]
+        wake_word: bool,
+    },
+    /// Ask one question, e.g. about what's on screen right now
+    Ask {
+        /// The question (with --screenshot, defaults to explaining the screen)
+        prompt: Vec<String>,
+
+        /// Capture the screen and attach it to the question
+        #[arg(long)]
+        screenshot: bool,
+
+        /// Model tier to answer with (default with --screenshot: the "vision"
+        /// tier when one is configured)
+        #[arg(short, long)]
+        tier: Option<String>,
+
+        /// Session ID for conversation tracking
+        #[arg(short, long, default_value = "cli:ask")]
+        session: String,
     },
     /// Start gateway with all enabled channels
     Serve,
@@ -93,6 +183,11 @@ enum Commands {
         /// Skip interactive prompts and write defaults
         #[arg(long)]
         non_interactive: bool,
+
+        /// Set up for Docker: keep state in ./patina-data and write a
+        /// docker-compose.yml next to it
+        #[arg(long)]
+        docker: bool,
     },
     /// Interrupt an active session run
     Interrupt {
@@ -107,11 +202,117 @@ enum Commands {
         #[command(subcommand)]
         action: CronCommands,
     },
+    /// Export and import the task board
+    Tasks {
+        #[command(subcommand)]
+        action: TaskCommands,
+    },
     /// Manage channels
     Channels {
         #[command(subcommand)]
         action: ChannelCommands,
     },
+    /// Work with saved conversation sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionCommands,
+    },
+    /// Work with long-term memory
+    Memory {
+        #[command(subcommand)]
+        action: MemoryCommands,
+    },
+    /// Import conversations exported from ChatGPT or Claude as sessions
+    Import {
+        /// The export zip, or the conversations.json from it
+        #[arg(long)]
+        from: PathBuf,
+        /// Consolidate the imported conversations into MEMORY.md and
+        /// HISTORY.md, oldest first
+        #[arg(long)]
+        consolidate: bool,
+    },
+    /// Show what a session's next prompt is made of, with token estimates
+    Context {
+        /// Session key (format: channel:chat_id)
+        #[arg(short, long, default_value = "cli:interactive")]
+        session: String,
+    },
+    /// Turn off exec, file writes, messages, and spawning everywhere, for
+    /// the running gateway too, until `patina lockdown off`
+    Lockdown {
+        /// on, off, or status
+        #[arg(default_value = "on")]
+        action: String,
+    },
+    /// Check that recurring cron jobs, the heartbeat, and the gateway are
+    /// running, exiting non-zero if something isn't
+    Watchdog {
+        /// Alert watchdog.channel/to if the gateway stopped checking in, and
+        /// send an all-clear once it's back. Meant for the system's cron
+        #[arg(long)]
+        alert: bool,
+    },
+    /// Replay a session debug log (logging.sessionLogs) through the current
+    /// agent with recorded model responses and tool results
+    Replay {
+        /// Session log, e.g. ~/.patina/logs/telegram_12345.jsonl
+        file: PathBuf,
+        /// Session key to replay as (default: the recorded one)
+        #[arg(short, long)]
+        session: Option<String>,
+    },
+    /// Run a YAML suite of prompts against model tiers and personas and
+    /// score the replies
+    Eval {
+        /// Suite file, e.g. evals/basics.yaml
+        file: PathBuf,
+        /// Tiers to run on, replacing the suite's list (repeatable)
+        #[arg(short, long)]
+        tier: Vec<String>,
+        /// Personas to run as, replacing the suite's list (repeatable;
+        /// "default" for none)
+        #[arg(short, long)]
+        persona: Vec<String>,
+    },
+    /// Run a JSON Lines file of prompts through the agent, writing a result
+    /// line for each
+    Batch {
+        /// Prompts, one per line: a JSON string, or an object with `prompt`
+        /// and optionally `id`, `tier`, and `persona`
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Where to write the results (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Model tier for prompts that don't name one
+        #[arg(short, long)]
+        tier: Option<String>,
+        /// Persona for prompts that don't name one
+        #[arg(short, long)]
+        persona: Option<String>,
+        /// How many prompts to run at once
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        /// Answer without tools
+        #[arg(long)]
+        no_tools: bool,
+    },
+    /// Print a guest link for the web chat (channels.web.guestLinks)
+    Share {
+        /// Watch this web chat read-only instead of starting a guest chat
+        #[arg(long)]
+        chat: Option<String>,
+        /// Minutes until the link expires (default: guestLinks.ttlMins)
+        #[arg(long)]
+        ttl: Option<u64>,
+        /// Note shown in the gateway log when the guest connects
+        #[arg(long, default_value = "")]
+        label: String,
+        /// Public base URL of the web UI (default: http://<gateway host:port>)
+        #[arg(long)]
+        url: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -133,10 +334,11 @@ enum CronCommands {
         /// Interval in seconds (recurring)
         #[arg(long)]
         every: Option<u64>,
-        /// Cron expression (e.g. "0 9 * * *")
+        /// Cron expression (e.g. "0 9 * * *"), run in `user.timezone`
         #[arg(long)]
         cron: Option<String>,
-        /// One-time execution at ISO datetime (e.g. "2025-06-01T09:00:00Z")
+        /// One-time execution at a date and time in `user.timezone` (e.g.
+        /// "2025-06-01 09:00"), or RFC 3339 with an offset
         #[arg(long)]
         at: Option<String>,
         /// Deliver result to a channel
@@ -148,6 +350,39 @@ enum CronCommands {
         /// Target chat_id for delivery
         #[arg(long)]
         to: Option<String>,
+        /// Payload kind: agent_turn, exec (shell command), digest (activity
+        /// summary since the last run, sent to --channel/--to), or message
+        /// (the message text, sent as-is to --channel/--to)
+        #[arg(long, default_value = "agent_turn")]
+        kind: String,
+        /// For agent_turn: resend the same day's reply instead of asking the
+        /// agent when the job runs again within this many seconds
+        #[arg(long)]
+        cache_ttl: Option<u64>,
+        /// For agent_turn: model tier to answer with (e.g. a cheap "fast"
+        /// tier for routine checks)
+        #[arg(long)]
+        tier: Option<String>,
+        /// For exec: deliver every run (always) or only failed ones (failure)
+        #[arg(long)]
+        deliver_on: Option<String>,
+        /// No schedule: only run when another job's --on-success or
+        /// --on-failure points here. `{{output}}` in the message is the
+        /// previous job's output
+        #[arg(long)]
+        chained: bool,
+        /// Job ID to run next when this one succeeds
+        #[arg(long)]
+        on_success: Option<String>,
+        /// Job ID to run next when this one fails or the condition isn't met
+        #[arg(long)]
+        on_failure: Option<String>,
+        /// Only count a run as a success if the output contains this text
+        #[arg(long)]
+        if_contains: Option<String>,
+        /// Only count a run as a success if the output matches this regex
+        #[arg(long)]
+        if_match: Option<String>,
     },
     /// Remove a job by ID
     Remove {
@@ -167,34 +402,132 @@ enum CronCommands {
         /// Job ID to run
         job_id: String,
     },
+    /// Show recent exec job runs with their output and exit codes
+    History {
+        /// Only this job's runs
+        job_id: Option<String>,
+        /// How many runs to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+        /// Show the full recorded stdout and stderr
+        #[arg(long)]
+        full: bool,
+    },
+    /// Write every job's definition as YAML, for editing or version control
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add and update jobs from a YAML file written by `cron export`
+    Import {
+        /// The YAML file
+        file: PathBuf,
+        /// Also remove jobs the file doesn't list
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TaskCommands {
+    /// Write the task board as YAML, for editing or version control
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add and update tasks from a YAML file written by `tasks export`
+    Import {
+        /// The YAML file
+        file: PathBuf,
+        /// Also remove tasks the file doesn't list
+        #[arg(long)]
+        replace: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum ChannelCommands {
     /// Show channel configuration and status
     Status,
+    /// Restart a channel in the running gateway, leaving the rest up
+    Restart {
+        /// Channel name (e.g. telegram)
+        name: String,
+    },
+    /// Stop a channel in the running gateway until it's enabled again
+    Disable {
+        /// Channel name (e.g. telegram)
+        name: String,
+    },
+    /// Start a channel that `channels disable` stopped
+    Enable {
+        /// Channel name (e.g. telegram)
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionCommands {
+    /// Export a conversation with timestamps and tool calls
+    Export {
+        /// Session key (format: channel:chat_id)
+        key: String,
+        /// Output format: md, html, or json
+        #[arg(short, long, default_value = "md")]
+        format: String,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemoryCommands {
+    /// Consolidate a session's older messages into MEMORY.md and HISTORY.md
+    Consolidate {
+        /// Session key (format: channel:chat_id)
+        #[arg(short, long)]
+        session: String,
+        /// Show the MEMORY.md diff and HISTORY.md entry without writing
+        #[arg(long)]
+        dry_run: bool,
+        /// Include the recent messages a background consolidation keeps
+        #[arg(long)]
+        all: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let base_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
-    let filter = format!("{base_filter},ort=error,ort::logging=error,patina_core::agent=debug");
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::new(filter))
-        .init();
-
     let cli = Cli::parse();
 
+    // The log format and span export are configured before logging starts.
+    // Span export only applies to the long-running commands.
+    let early_config = match cli.command {
+        Commands::Onboard { .. } => None,
+        _ => load_config(&cli.config.clone().unwrap_or_else(find_config_path)).ok(),
+    };
+    let telemetry = match cli.command {
+        Commands::Agent { .. } | Commands::Serve => early_config.as_ref().map(|c| &c.telemetry),
+        _ => None,
+    };
+    let _telemetry = init_tracing(early_config.as_ref().map(|c| &c.logging), telemetry);
+
     match cli.command {
-        Commands::Onboard { non_interactive } => {
-            return run_onboard(cli.config, non_interactive);
+        Commands::Onboard {
+            non_interactive,
+            docker,
+        } => {
+            return run_onboard(cli.config, non_interactive, docker);
         }
         Commands::Interrupt { session } => {
             return run_interrupt(&session);
         }
         Commands::Status => {
             let config_path = cli.config.unwrap_or_else(find_config_path);
-            return run_status(&config_path);
+            return run_status(&config_path).await;
         }
         Commands::Cron { action } => {
             let config_path = cli.config.unwrap_or_else(find_config_path);
@@ -204,7 +537,82 @@ async fn main() -> Result<()> {
         Commands::Channels { action } => {
             let config_path = cli.config.unwrap_or_else(find_config_path);
             let config = load_config(&config_path)?;
-            return run_channel_command(action, &config);
+            return run_channel_command(action, &config).await;
+        }
+        Commands::Sessions { action } => {
+            return run_session_command(action);
+        }
+        Commands::Tasks { action } => {
+            return run_task_command(action);
+        }
+        Commands::Memory { action } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_memory_command(&config, action).await;
+        }
+        Commands::Import { from, consolidate } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_import(&config, &from, consolidate).await;
+        }
+        Commands::Context { session } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_context(&config, &session).await;
+        }
+        Commands::Lockdown { action } => {
+            println!("{}", Lockdown::new().command(&action, "the command line"));
+            return Ok(());
+        }
+        Commands::Watchdog { alert } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_watchdog(&config, alert).await;
+        }
+        Commands::Replay { file, session } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_replay(&config, &file, session.as_deref()).await;
+        }
+        Commands::Eval {
+            file,
+            tier,
+            persona,
+        } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_eval(&config, &file, tier, persona).await;
+        }
+        Commands::Batch {
+            input,
+            output,
+            tier,
+            persona,
+            concurrency,
+            no_tools,
+        } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_batch(
+                &config,
+                &input,
+                output.as_deref(),
+                tier,
+                persona,
+                concurrency,
+                no_tools,
+            )
+            .await;
+        }
+        Commands::Share {
+            chat,
+            ttl,
+            label,
+            url,
+        } => {
+            let config_path = cli.config.unwrap_or_else(find_config_path);
+            let config = load_config(&config_path)?;
+            return run_share(&config, chat.as_deref(), ttl, &label, url.as_deref());
         }
         _ => {}
     }
@@ -217,10 +625,27 @@ async fn main() -> Result<()> {
     let workspace = resolve_workspace(&config.agents.defaults.workspace);
     std::fs::create_dir_all(&workspace)?;
 
+    prepare_ollama_models(&config).await?;
+
     match cli.command {
-        Commands::Agent { message, session } => {
-            let (agent_loop, context_tools, _cron_service, _bus, _task_manager) =
-                build_agent_loop(&config, &workspace)?;
+        Commands::Agent {
+            message,
+            dry_run,
+            session,
+            voice,
+            wake_word,
+        } => {
+            let Agent {
+                agent_loop,
+                context_tools,
+                personas,
+                dry_run: plans,
+                ..
+            } = AgentBuilder::new(config.clone())
+                .with_workspace(&workspace)
+                .build()?;
+            #[cfg(feature = "desktop")]
+            let agent_loop = with_desktop_tools(agent_loop);
 
             if let Some(msg) = message {
                 // Set context for the CLI session
@@ -231,11 +656,40 @@ async fn main() -> Result<()> {
                     ("cli", session.as_str())
                 };
                 context_tools.set_context(channel, chat_id).await;
-                run_single_message(agent_loop, &session, &msg).await?;
+                let mut agent_loop = agent_loop;
+                context_tools
+                    .set_locale(&agent_loop.locale_for(&session))
+                    .await;
+                run_single_message(agent_loop, &session, &msg, dry_run.then_some(&*plans)).await?;
+            } else if wake_word {
+                let voice = VoiceInput::new(&config).await?;
+                run_wake_word(
+                    agent_loop,
+                    context_tools,
+                    &session,
+                    voice,
+                    &config.transcription.voice_input.wake_word,
+                )
+                .await?;
             } else {
-                run_interactive(agent_loop, context_tools, &session).await?;
+                let voice = if voice {
+                    Some(VoiceInput::new(&config).await?)
+                } else {
+                    None
+                };
+                run_interactive(agent_loop, context_tools, personas, plans, &session, voice)
+                    .await?;
             }
         }
+        Commands::Ask {
+            prompt,
+            screenshot,
+            tier,
+            session,
+        } => {
+            let prompt = prompt.join(" ");
+            run_ask(&config, &workspace, &session, &prompt, screenshot, tier).await?;
+        }
         Commands::Serve => {
             run_gateway(&config, &workspace).await?;
         }
@@ -245,20 +699,110 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Flushes exported spans when dropped at the end of `main`.
+struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush telemetry: {e}");
+            }
+        }
+    }
+}
+
+/// Install the log subscriber (text or JSON per `logging.format`), plus an
+/// OTLP span exporter when `telemetry.enabled` is set and the binary was
+/// built with `otel`.
+fn init_tracing(
+    logging: Option<&patina_config::LoggingConfig>,
+    telemetry: Option<&patina_config::TelemetryConfig>,
+) -> TelemetryGuard {
+    use tracing_subscriber::prelude::*;
+
+    let base_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
+    let filter = format!("{base_filter},ort=error,ort::logging=error,patina_core::agent=debug");
+    let telemetry = telemetry.filter(|t| t.enabled);
+    let json = logging.is_some_and(|l| l.format == patina_config::LogFormat::Json);
+
+    #[cfg(feature = "otel")]
+    let (otel_layer, provider, otel_error) = match telemetry.map(otel_pipeline) {
+        Some(Ok((layer, provider))) => (Some(layer), Some(provider), None),
+        Some(Err(e)) => (None, None, Some(e)),
+        None => (None, None, None),
+    };
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<
+        Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync>,
+    > = None;
+
+    tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(tracing_subscriber::EnvFilter::new(filter))
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        // Feeds the web UI's log panel
+        .with(patina_core::log_stream::LogStreamLayer)
+        .init();
+
+    #[cfg(feature = "otel")]
+    {
+        match (otel_error, telemetry) {
+            (Some(e), _) => tracing::warn!("Telemetry disabled: {e}"),
+            (None, Some(t)) => tracing::info!("Exporting traces to {}", t.endpoint),
+            (None, None) => {}
+        }
+        TelemetryGuard { provider }
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        if telemetry.is_some() {
+            tracing::warn!(
+                "telemetry.enabled is set but this build has no OTLP exporter. \
+                 Rebuild with: cargo build --release --features otel"
+            );
+        }
+        TelemetryGuard {}
+    }
+}
+
+/// Build the OTLP/HTTP exporter and the tracing layer that feeds it.
+#[cfg(feature = "otel")]
+fn otel_pipeline(
+    config: &patina_config::TelemetryConfig,
+) -> Result<(
+    Box<dyn tracing_subscriber::Layer<tracing_subscriber::Registry> + Send + Sync>,
+    opentelemetry_sdk::trace::TracerProvider,
+)> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{WithExportConfig, WithHttpConfig};
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&config.endpoint)
+        .with_headers(config.headers.clone())
+        .build()?;
+    let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(opentelemetry_sdk::Resource::new([
+            opentelemetry::KeyValue::new("service.name", config.service_name.clone()),
+        ]))
+        .build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("patina"));
+    Ok((Box::new(layer), provider))
+}
+
 /// Create an interrupt flag for a session. Agent loops consume and clear this flag.
 fn run_interrupt(session: &str) -> Result<()> {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    let interrupts_dir = home.join(".patina").join("interrupts");
+    let interrupts_dir = data_dir().join("interrupts");
     std::fs::create_dir_all(&interrupts_dir)?;
 
-    let safe = session
-        .chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | ' ' => '_',
-            _ => c,
-        })
-        .collect::<String>();
-    let flag_path = interrupts_dir.join(format!("{safe}.flag"));
+    let flag_path = interrupts_dir.join(format!("{}.flag", safe_file_name(session)));
     std::fs::write(&flag_path, chrono::Utc::now().to_rfc3339())?;
 
     println!("Interrupt requested for session '{session}'.");
@@ -266,438 +810,407 @@ fn run_interrupt(session: &str) -> Result<()> {
     Ok(())
 }
 
-/// Resolve an API key from config, falling back to an environment variable.
-fn resolve_api_key(
-    provider_cfg: &Option<patina_config::ProviderConfig>,
-    env_var: &str,
-) -> Option<String> {
-    provider_cfg
-        .as_ref()
-        .and_then(|c| c.api_key.clone())
-        .filter(|k| !k.is_empty())
-        .or_else(|| std::env::var(env_var).ok().filter(|k| !k.is_empty()))
+/// Push-to-talk microphone input for `patina agent --voice`.
+struct VoiceInput {
+    transcriber: Box<dyn patina_transcribe::Transcriber>,
+    options: patina_transcribe::mic::RecordOptions,
 }
 
-/// Create a completion model for a specific provider + model combination.
-///
-/// Errors clearly if provider is unknown or has no API key.
-#[allow(deprecated)]
-fn create_model_for(
-    provider: &str,
-    model_name: &str,
-    config: &patina_config::Config,
-) -> Result<CompletionModelHandle<'static>> {
-    if provider.is_empty() {
-        anyhow::bail!(
-            "No provider configured.\n\
-             Valid providers: anthropic, openai, ollama, openrouter, deepseek, groq, gemini, mistral"
-        );
+impl VoiceInput {
+    async fn new(config: &patina_config::Config) -> Result<Self> {
+        if !patina_transcribe::mic::mic_available() {
+            anyhow::bail!("This build has no microphone support. Rebuild with `--features voice`.");
+        }
+        let transcriber = patina_transcribe::create_transcriber(
+            &config.transcription,
+            builder::transcription_keys(config),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Voice input needs transcription: {e}"))?;
+        Ok(Self {
+            transcriber,
+            options: patina_transcribe::mic::RecordOptions::from_config(
+                &config.transcription.voice_input,
+            ),
+        })
     }
 
-    if model_name.is_empty() {
-        anyhow::bail!("No model configured for provider '{provider}'.");
+    /// Record one utterance and transcribe it. `None` when nothing was said.
+    async fn listen(&self, session_key: &str) -> Result<Option<String>> {
+        let options = self.options.clone();
+        self.capture(session_key, move |path| {
+            patina_transcribe::mic::record_utterance(&options, path)
+        })
+        .await
     }
 
-    match provider {
-        "anthropic" => {
-            let key = resolve_api_key(&config.providers.anthropic, "ANTHROPIC_API_KEY")
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Anthropic provider selected but no API key found. \
-                     Set providers.anthropic.apiKey in config.json or ANTHROPIC_API_KEY env var."
-                    )
-                })?;
-            let client: anthropic::Client = anthropic::Client::builder()
-                .api_key(&key)
-                .build()
-                .map_err(|e| anyhow::anyhow!("Failed to create Anthropic client: {e}"))?;
-            let model = client.completion_model(model_name).with_prompt_caching();
-            tracing::info!(
-                "Using Anthropic provider with model '{model_name}' (prompt caching enabled)"
-            );
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "openai" => {
-            let key =
-                resolve_api_key(&config.providers.openai, "OPENAI_API_KEY").ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "OpenAI provider selected but no API key found. \
-                     Set providers.openai.apiKey in config.json or OPENAI_API_KEY env var."
-                    )
-                })?;
-            let mut builder = openai::CompletionsClient::builder().api_key(&key);
-            if let Some(ref base) = config
-                .providers
-                .openai
-                .as_ref()
-                .and_then(|c| c.api_base.clone())
-                .filter(|b| !b.is_empty())
-            {
-                builder = builder.base_url(base);
-                tracing::info!("Using OpenAI provider with custom base: {base}");
-            }
-            let client: openai::CompletionsClient = builder
-                .build()
-                .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using OpenAI provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "ollama" => {
-            let mut builder = ollama::Client::builder().api_key(Nothing);
-            if let Some(ref base) = config
-                .providers
-                .ollama
-                .as_ref()
-                .and_then(|c| c.api_base.clone())
-                .filter(|b| !b.is_empty())
-            {
-                builder = builder.base_url(base);
-            }
-            let client: ollama::Client = builder
-                .build()
-                .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using Ollama provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "openrouter" => {
-            let key = resolve_api_key(&config.providers.openrouter, "OPENROUTER_API_KEY")
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "OpenRouter provider selected but no API key found. \
-                     Set providers.openrouter.apiKey in config.json or OPENROUTER_API_KEY env var."
-                    )
-                })?;
-            let client: openrouter::Client = openrouter::Client::new(&key)
-                .map_err(|e| anyhow::anyhow!("Failed to create OpenRouter client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using OpenRouter provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "deepseek" => {
-            let key = resolve_api_key(&config.providers.deepseek, "DEEPSEEK_API_KEY").ok_or_else(
-                || {
-                    anyhow::anyhow!(
-                        "DeepSeek provider selected but no API key found. \
-                     Set providers.deepseek.apiKey in config.json or DEEPSEEK_API_KEY env var."
-                    )
-                },
-            )?;
-            let client: deepseek::Client = deepseek::Client::new(&key)
-                .map_err(|e| anyhow::anyhow!("Failed to create DeepSeek client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using DeepSeek provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
+    /// Wait for the wake word, then record and transcribe the request after it.
+    async fn listen_after_wake_word(
+        &self,
+        session_key: &str,
+        detector: Arc<std::sync::Mutex<patina_transcribe::wakeword::WakeWordDetector>>,
+    ) -> Result<Option<String>> {
+        let options = self.options.clone();
+        self.capture(session_key, move |path| {
+            let mut detector = detector
+                .lock()
+                .map_err(|_| anyhow::anyhow!("Wake-word detector poisoned"))?;
+            patina_transcribe::mic::record_after_wake_word(&mut detector, &options, path)
+        })
+        .await
+    }
 
-        "groq" => {
-            let key = resolve_api_key(&config.providers.groq, "GROQ_API_KEY").ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Groq provider selected but no API key found. \
-                     Set providers.groq.apiKey in config.json or GROQ_API_KEY env var."
-                )
-            })?;
-            let client: groq::Client = groq::Client::new(&key)
-                .map_err(|e| anyhow::anyhow!("Failed to create Groq client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using Groq provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "gemini" => {
-            let key =
-                resolve_api_key(&config.providers.gemini, "GEMINI_API_KEY").ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Gemini provider selected but no API key found. \
-                     Set providers.gemini.apiKey in config.json or GEMINI_API_KEY env var."
-                    )
-                })?;
-            let client: gemini::Client = gemini::Client::new(key)
-                .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using Gemini provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
-        }
-
-        "mistral" => {
-            let key =
-                resolve_api_key(&config.providers.mistral, "MISTRAL_API_KEY").ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Mistral provider selected but no API key found. \
-                     Set providers.mistral.apiKey in config.json or MISTRAL_API_KEY env var."
-                    )
-                })?;
-            let client: mistral::Client = mistral::Client::new(&key)
-                .map_err(|e| anyhow::anyhow!("Failed to create Mistral client: {e}"))?;
-            let model = client.completion_model(model_name);
-            tracing::info!("Using Mistral provider with model '{model_name}'");
-            Ok(CompletionModelHandle::new(Arc::new(model)))
+    /// Run a blocking recorder into a temp WAV and transcribe the result.
+    async fn capture<F>(&self, session_key: &str, record: F) -> Result<Option<String>>
+    where
+        F: FnOnce(&Path) -> Result<bool> + Send + 'static,
+    {
+        let path = std::env::temp_dir().join(format!("patina-voice-{}.wav", std::process::id()));
+        let output = path.clone();
+        let spoke = tokio::task::spawn_blocking(move || record(&output)).await??;
+        if !spoke {
+            return Ok(None);
         }
 
-        other => {
-            anyhow::bail!(
-                "Unknown provider '{other}'. \
-                 Valid providers: anthropic, openai, ollama, openrouter, deepseek, groq, gemini, mistral"
-            );
-        }
+        let options = patina_transcribe::TranscribeOptions {
+            language: None,
+            chat_key: Some(session_key.to_string()),
+        };
+        let result = self
+            .transcriber
+            .transcribe(&path.to_string_lossy(), &options)
+            .await;
+        let _ = std::fs::remove_file(&path);
+        let text = result?.text;
+        Ok(Some(text).filter(|t| !t.trim().is_empty()))
     }
 }
 
-/// Build a ModelPool from config.
+/// Pull any Ollama models the configured tiers need but the host doesn't
+/// have, and preload the default model when `keepAlive` is set.
 ///
-/// Uses `agents.models` if present, otherwise falls back to the legacy
-/// `agents.defaults.provider` + `agents.defaults.model` fields.
-#[allow(deprecated)]
-fn create_model_pool(config: &patina_config::Config) -> Result<ModelPool> {
-    let mut models = std::collections::HashMap::new();
-
-    if config.agents.models.is_empty() {
-        // Backwards compatibility: migrate legacy provider/model to "default" tier
-        let provider = &config.agents.defaults.provider;
-        let model_name = &config.agents.defaults.model;
-
-        if provider.is_empty() || model_name.is_empty() {
+/// An unreachable host is only a warning, since the first request reports it.
+async fn prepare_ollama_models(config: &patina_config::Config) -> Result<()> {
+    let tiers = builder::ollama_tiers(config);
+    if tiers.is_empty() {
+        return Ok(());
+    }
+    let provider = config.providers.ollama.clone().unwrap_or_default();
+    let client = builder::ollama_client(config);
+    let base = client.base_url().to_string();
+
+    let installed = match client.list_models().await {
+        Ok(models) => models,
+        Err(e) => {
+            tracing::warn!("Ollama at {base} is unreachable, skipping model check: {e}");
+            return Ok(());
+        }
+    };
+    let mut missing: Vec<&str> = tiers
+        .iter()
+        .map(|(_, model)| model.as_str())
+        .filter(|model| !installed.iter().any(|m| ollama::same_model(&m.name, model)))
+        .collect();
+    missing.sort_unstable();
+    missing.dedup();
+
+    for model in missing {
+        if !provider.pull_missing.unwrap_or(true) {
             anyhow::bail!(
-                "No models configured. Set agents.models in config.json with at least a \"default\" entry.\n\
-                 Example:\n  \
-                 \"models\": {{ \"default\": {{ \"provider\": \"ollama\", \"model\": \"llama3\" }} }}"
+                "Ollama model '{model}' is not installed on {base}. \
+                 Run `ollama pull {model}`, or set providers.ollama.pullMissing to true to pull it automatically."
             );
         }
-
-        tracing::warn!(
-            "Using legacy agents.defaults.provider/model config. \
-             Migrate to agents.models for multi-tier support."
-        );
-
-        let handle = create_model_for(provider, model_name, config)?;
-        models.insert(
-            "default".to_string(),
-            (handle, model_name.clone(), provider.clone()),
-        );
-    } else {
-        // Validate "default" tier exists
-        if !config.agents.models.contains_key("default") {
-            anyhow::bail!("config.agents.models must contain at least a \"default\" entry.");
+        let progress = std::io::IsTerminal::is_terminal(&std::io::stderr())
+            .then(|| terminal_pull_progress(model));
+        let result = client.pull(model, progress.clone()).await;
+        if progress.is_some() {
+            eprintln!();
         }
+        result.map_err(|e| anyhow::anyhow!("Failed to pull Ollama model '{model}': {e}"))?;
+    }
 
-        for (tier, model_ref) in &config.agents.models {
-            let handle = create_model_for(&model_ref.provider, &model_ref.model, config)
-                .map_err(|e| anyhow::anyhow!("Failed to create model for tier '{tier}': {e}"))?;
-            tracing::info!(
-                "Model tier '{tier}': {} / {}",
-                model_ref.provider,
-                model_ref.model
-            );
-            models.insert(
-                tier.clone(),
-                (handle, model_ref.model.clone(), model_ref.provider.clone()),
-            );
+    // Preloading can take a while for big models; don't wait for it
+    if let Some(keep_alive) = provider.keep_alive {
+        if let Some((_, model)) = tiers.into_iter().find(|(tier, _)| tier == "default") {
+            tokio::spawn(async move {
+                if let Err(e) = client.set_keep_alive(&model, &keep_alive).await {
+                    tracing::warn!("Failed to preload Ollama model '{model}': {e}");
+                }
+            });
         }
     }
-
-    Ok(ModelPool::new(models))
+    Ok(())
 }
 
-/// Holds context-aware tools that need set_context() called before each message.
-struct ContextTools {
-    message_tool: Arc<MessageTool>,
-    spawn_tool: Arc<SpawnTool>,
-    cron_tool: Arc<CronTool>,
-    task_tool: Arc<TaskTool>,
+/// Single-line pull progress on stderr for interactive runs.
+fn terminal_pull_progress(model: &str) -> ollama::PullProgressFn {
+    let model = model.to_string();
+    Arc::new(move |p: &ollama::PullProgress| {
+        use std::io::Write;
+        let line = match p.percent() {
+            Some(pct) => format!("{} {pct}%", p.status),
+            None => p.status.clone(),
+        };
+        eprint!("\r\x1b[2KPulling {model}: {line}");
+        let _ = std::io::stderr().flush();
+    })
 }
 
-impl ContextTools {
-    /// Update all context-aware tools with the current channel/chat_id.
-    async fn set_context(&self, channel: &str, chat_id: &str) {
-        self.message_tool.set_context(channel, chat_id).await;
-        self.spawn_tool.set_context(channel, chat_id).await;
-        self.cron_tool.set_context(channel, chat_id).await;
-        self.task_tool.set_context(channel, chat_id).await;
-    }
+/// Register clipboard and notification tools.
+///
+/// Only used for `patina agent` — desktop integration makes no sense for a gateway.
+#[cfg(feature = "desktop")]
+fn with_desktop_tools(mut agent_loop: AgentLoop) -> AgentLoop {
+    use patina_core::tools::desktop::{ClipboardTool, NotifyTool};
+    agent_loop.tools.register(Box::new(ClipboardTool::new()));
+    agent_loop.tools.register(Box::new(NotifyTool::new()));
+    agent_loop
 }
 
-#[allow(deprecated)]
-#[allow(clippy::type_complexity)]
-fn build_agent_loop(
-    config: &patina_config::Config,
-    workspace: &Path,
-) -> Result<(
-    AgentLoop,
-    ContextTools,
-    Arc<Mutex<CronService>>,
-    MessageBus,
-    Arc<Mutex<TaskManager>>,
-)> {
-    let defaults = &config.agents.defaults;
-    let model_pool = create_model_pool(config)?;
-
-    // Message bus
-    let bus = MessageBus::new(128);
-
-    // Sessions directory
-    let sessions_dir = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("sessions");
-    let sessions_dir_for_tasks = sessions_dir.clone();
-    let sessions = SessionManager::new(sessions_dir);
-
-    // Context builder (workspace + embedded builtin skills)
-    let context = ContextBuilder::new(workspace);
-
-    // Tool registry
-    let mut tools = ToolRegistry::new();
-    let allowed_dir: Option<PathBuf> = if config.tools.restrict_to_workspace {
-        Some(workspace.to_path_buf())
-    } else {
-        None
-    };
-    tools.register(Box::new(ReadFileTool::new(allowed_dir.clone())));
-    tools.register(Box::new(WriteFileTool::new(allowed_dir.clone())));
-    tools.register(Box::new(EditFileTool::new(allowed_dir.clone())));
-    tools.register(Box::new(ListDirTool::new(allowed_dir)));
-    tools.register(Box::new(ExecTool::new(
-        workspace.to_path_buf(),
-        config.tools.exec.timeout_secs,
-        config.tools.restrict_to_workspace,
-    )));
-
-    // Web tools
-    let brave_api_key = if config.tools.web.search.api_key.is_empty() {
-        std::env::var("BRAVE_API_KEY").unwrap_or_default()
-    } else {
-        config.tools.web.search.api_key.clone()
-    };
-    tools.register(Box::new(WebSearchTool::new(
-        brave_api_key,
-        config.tools.web.search.max_results,
-    )));
-    tools.register(Box::new(WebFetchTool::new(50_000)));
-
-    // Message tool
-    let message_tool = Arc::new(MessageTool::new(bus.outbound_tx.clone()));
-    tools.register(Box::new(ArcToolWrapper(message_tool.clone())));
-
-    // Usage tracker
-    let usage_db_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("usage.sqlite");
-    let usage_tracker = Arc::new(patina_core::usage::UsageTracker::new(&usage_db_path)?);
-
-    // Subagent manager + spawn tool
-    let mut subagent_manager = SubagentManager::new(
-        model_pool.clone(),
-        workspace.to_path_buf(),
-        bus.inbound_tx.clone(),
-        config.clone(),
-    );
-    subagent_manager.set_usage_tracker(usage_tracker.clone());
-    let subagent_manager = Arc::new(subagent_manager);
-    let subagent_manager_for_tasks = subagent_manager.clone();
-    let spawn_tool = Arc::new(SpawnTool::new(subagent_manager));
-    tools.register(Box::new(ArcToolWrapper(spawn_tool.clone())));
-
-    // Cron service + cron tool
-    let cron_store_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("cron")
-        .join("jobs.json");
-    let cron_service = Arc::new(Mutex::new(CronService::new(
-        cron_store_path,
-        bus.inbound_tx.clone(),
-    )));
-    let cron_tool = Arc::new(CronTool::new(cron_service.clone()));
-    tools.register(Box::new(ArcToolWrapper(cron_tool.clone())));
-
-    // Task manager + task tool
-    let task_store_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("tasks.json");
-    let task_manager = Arc::new(Mutex::new(TaskManager::load(&task_store_path)));
-    let task_tool = Arc::new(TaskTool::new(task_manager.clone()));
-    task_tool.set_subagent_manager(subagent_manager_for_tasks);
-    task_tool.set_sessions_dir(sessions_dir_for_tasks);
-    tools.register(Box::new(ArcToolWrapper(task_tool.clone())));
-
-    // Memory search index
-    let db_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("memory.sqlite");
-    let memory_index = Arc::new(MemoryIndex::new(workspace, &db_path)?);
-    if let Err(e) = memory_index.reindex() {
-        tracing::warn!("Initial memory reindex failed: {e}");
-    }
-    tools.register(Box::new(MemorySearchTool::new(memory_index.clone())));
-
-    let context_tools = ContextTools {
-        message_tool,
-        spawn_tool,
-        cron_tool,
-        task_tool,
-    };
-
-    let agent_loop = AgentLoop {
-        models: model_pool,
-        sessions,
-        context,
-        tools,
-        max_iterations: defaults.max_tool_iterations as usize,
-        temperature: defaults.temperature as f64,
-        max_tokens: defaults.max_tokens as u64,
-        memory_window: defaults.memory_window,
-        model_overrides: ModelOverrides::defaults(),
-        memory_index: Some(memory_index),
-        channel_rules: HashMap::new(),
-        usage_tracker: Some(usage_tracker.clone()),
-        stream_tx: None,
-    };
-
-    Ok((agent_loop, context_tools, cron_service, bus, task_manager))
+/// Forward local model download progress to the web UI as status events.
+fn model_download_progress(
+    outbound_tx: tokio::sync::broadcast::Sender<OutboundMessage>,
+) -> patina_transcribe::download::ProgressFn {
+    Arc::new(move |p: &patina_transcribe::download::DownloadProgress| {
+        // Every 5% keeps the event count well under the bus capacity
+        let content = match p.percent() {
+            _ if p.done => "connected".to_string(),
+            Some(pct) if pct % 5 == 0 => format!("downloading speech model: {} {pct}%", p.file),
+            _ => return,
+        };
+        let mut metadata = HashMap::new();
+        metadata.insert("event".to_string(), serde_json::json!("status"));
+        let _ = outbound_tx.send(OutboundMessage {
+            channel: "web".to_string(),
+            chat_id: String::new(),
+            content,
+            reply_to: None,
+            metadata,
+        });
+    })
 }
 
-/// Wrapper to register an `Arc<T: Tool>` in the ToolRegistry (which expects `Box<dyn Tool>`).
-struct ArcToolWrapper<T: patina_core::tools::Tool>(Arc<T>);
+/// Slash commands offered on chat channels.
+/// The gateway's background memory consolidations: at most one per
+/// session at a time, with failures retried on a backoff and reported to
+/// the owner (see `ConsolidationRetries`).
+struct Consolidations {
+    tx: tokio::sync::mpsc::Sender<(String, Result<ConsolidationResult>)>,
+    rx: tokio::sync::mpsc::Receiver<(String, Result<ConsolidationResult>)>,
+    running: HashSet<String>,
+    retries: ConsolidationRetries,
+    /// Where failure alerts go: `tools.askUser.ownerChat`, or with no
+    /// `users` configured, the chat itself.
+    owner_chat: Option<String>,
+    users_enabled: bool,
+}
 
-#[async_trait::async_trait]
-impl<T: patina_core::tools::Tool + 'static> patina_core::tools::Tool for ArcToolWrapper<T> {
-    fn name(&self) -> &str {
-        self.0.name()
-    }
-    fn description(&self) -> &str {
-        self.0.description()
-    }
-    fn parameters_schema(&self) -> serde_json::Value {
-        self.0.parameters_schema()
+impl Consolidations {
+    fn new(config: &patina_config::Config, users_enabled: bool) -> Self {
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        Self {
+            tx,
+            rx,
+            running: HashSet::new(),
+            retries: ConsolidationRetries::load(
+                data_dir().join("consolidation_retries.json"),
+                config.agents.defaults.consolidation_alert_after,
+            ),
+            owner_chat: config.tools.ask_user.owner_chat.clone(),
+            users_enabled,
+        }
     }
-    async fn execute(&self, params: serde_json::Value) -> anyhow::Result<String> {
-        self.0.execute(params).await
+
+    /// Consolidate `session_key` after a turn, unless it's already running
+    /// or waiting to retry.
+    fn start(&mut self, agent_loop: &AgentLoop, session_key: &str) {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        if self.running.contains(session_key) || self.retries.is_waiting(session_key, now_ms) {
+            return;
+        }
+        if let Some(task) = agent_loop.prepare_consolidation(session_key, false) {
+            self.spawn(agent_loop, task);
+        }
     }
-}
+
+    /// Retry the sessions whose wait is over.
+    fn retry_due(&mut self, agent_loop: &mut AgentLoop) {
+        for key in self.retries.due(chrono::Utc::now().timestamp_millis()) {
+            if self.running.contains(&key) {
+                continue;
+            }
+            // Loads the session if it has left the cache
+            if let Err(e) = agent_loop.sessions.get_existing(&key) {
+                tracing::warn!("Failed to load session '{key}' to retry consolidation: {e}");
+            }
+            match agent_loop.prepare_consolidation(&key, false) {
+                Some(task) => self.spawn(agent_loop, task),
+                // Consolidated some other way meanwhile, or cleared
+                None => self.retries.succeeded(&key),
+            }
+        }
+    }
+
+    /// How long until the next retry is due.
+    fn retry_wait(&self) -> Option<std::time::Duration> {
+        let wait_ms = self.retries.next_due_ms()? - chrono::Utc::now().timestamp_millis();
+        Some(std::time::Duration::from_millis(wait_ms.max(0) as u64))
+    }
+
+    fn spawn(&mut self, agent_loop: &AgentLoop, task: ConsolidationTask) {
+        self.running.insert(task.session_key.clone());
+        let models = agent_loop.models.clone();
+        let tracker = agent_loop.usage_tracker.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let outcome = AgentLoop::run_consolidation(&models, &task, tracker.as_ref()).await;
+            let _ = tx.send((task.session_key, outcome)).await;
+        });
+    }
+
+    /// Apply a finished consolidation, or record its failure and alert the
+    /// owner once it has failed too often.
+    fn finish(
+        &mut self,
+        agent_loop: &mut AgentLoop,
+        outbound_tx: &tokio::sync::broadcast::Sender<OutboundMessage>,
+        session_key: String,
+        outcome: Result<ConsolidationResult>,
+    ) {
+        self.running.remove(&session_key);
+        let error = match outcome {
+            Ok(result) => {
+                agent_loop.apply_consolidation(&result);
+                self.retries.succeeded(&session_key);
+                return;
+            }
+            Err(e) => e.to_string(),
+        };
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let Some(alert) = self.retries.failed(&session_key, &error, now_ms) else {
+            return;
+        };
+        let owner_chat = match &self.owner_chat {
+            Some(chat) => chat.as_str(),
+            None if !self.users_enabled => session_key.as_str(),
+            None => {
+                tracing::error!("{alert}");
+                return;
+            }
+        };
+        let Some((channel, chat_id)) = owner_chat.split_once(':') else {
+            tracing::error!("{alert}");
+            return;
+        };
+        if let Err(e) = outbound_tx.send(OutboundMessage {
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            content: alert,
+            reply_to: None,
+            metadata: HashMap::new(),
+        }) {
+            tracing::warn!("Failed to publish consolidation alert to bus: {e}");
+        }
+    }
+
+    /// Wait for the consolidations still running.
+    async fn finish_running(
+        &mut self,
+        agent_loop: &mut AgentLoop,
+        outbound_tx: &tokio::sync::broadcast::Sender<OutboundMessage>,
+    ) {
+        while !self.running.is_empty() {
+            let Some((session_key, outcome)) = self.rx.recv().await else {
+                break;
+            };
+            self.finish(agent_loop, outbound_tx, session_key, outcome);
+        }
+    }
+}
+
+fn gateway_commands(
+    persona_store: Arc<Mutex<PersonaStore>>,
+    task_manager: Arc<Mutex<TaskManager>>,
+    message_tool: Arc<MessageTool>,
+    questions: Option<Arc<Questions>>,
+    dry_run: Arc<DryRun>,
+) -> CommandRegistry {
+    const GREETING: &str = "Hi! I'm Patina.\n\nSend me a message and I'll respond.";
+    let mut commands = CommandRegistry::new();
+    commands.register(Box::new(NewSessionCommand));
+    commands.register(Box::new(ForgetCommand::new()));
+    commands.register(Box::new(SetCommand::new(persona_store.clone())));
+    commands.register(Box::new(ShowCommand::new(persona_store.clone())));
+    commands.register(Box::new(PersonaCommand::new(persona_store.clone())));
+    commands.register(Box::new(ContextCommand::new(persona_store.clone())));
+    commands.register(Box::new(TaskCommand::new(task_manager)));
+    commands.register(Box::new(StopCommand));
+    commands.register(Box::new(DraftCommand::new(message_tool)));
+    if let Some(questions) = questions {
+        commands.register(Box::new(AnswerCommand::new(questions)));
+    }
+    commands.register(Box::new(LockdownCommand::new()));
+    commands.register(Box::new(PlanCommand::new(dry_run)));
+    commands.register(Box::new(MemoryCommand));
+    commands.register(Box::new(ProjectCommand));
+    commands.register(Box::new(
+        HelpCommand::new(GREETING).with_personas(persona_store.clone()),
+    ));
+    commands.register(Box::new(StartCommand(
+        HelpCommand::new(GREETING).with_personas(persona_store),
+    )));
+    commands
+}
 
 /// Run the full gateway: channels + agent processing loop + cron + heartbeat.
 async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result<()> {
+    let started = std::time::Instant::now();
+    let started_at = chrono::Utc::now();
     tracing::info!("Starting gateway...");
 
-    let (mut agent_loop, context_tools, cron_service, mut bus, task_manager) =
-        build_agent_loop(config, workspace)?;
+    let Agent {
+        mut agent_loop,
+        context_tools,
+        cron: cron_service,
+        mut bus,
+        tasks: task_manager,
+        personas: persona_store,
+        questions,
+        subagents,
+        locations,
+        dry_run,
+        ..
+    } = AgentBuilder::new(config.clone())
+        .with_workspace(workspace)
+        .build()?;
+    tracing::info!("Agent loop built in {:.2?}", started.elapsed());
+    if let Some(state) = Lockdown::new().state() {
+        tracing::warn!(
+            "Lockdown has been on since {} (by {}): exec, writes, messages, and spawning are off",
+            state.since,
+            state.by
+        );
+    }
 
-    // Load persona store
-    let persona_store_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("personas.json");
-    let persona_store = Arc::new(tokio::sync::Mutex::new(PersonaStore::load(
-        &persona_store_path,
-    )));
+    let commands = gateway_commands(
+        persona_store.clone(),
+        task_manager.clone(),
+        context_tools.message_tool.clone(),
+        questions.clone(),
+        dry_run.clone(),
+    );
+
+    // Per-user roles: tools, commands, memory, and daily limits
+    let users = Arc::new(UserDirectory::new(&config.users, &config.roles));
+    let locales = Locales::new(&config.user, &config.users);
+    let guest_links = guest_links(config);
+    let access = (users.is_enabled() || guest_chats_enabled(config)).then(|| {
+        let access = Arc::new(AccessControl::new(users.clone()));
+        agent_loop.add_middleware(access.clone());
+        tracing::info!("User roles enabled for {} user(s)", config.users.len());
+        access
+    });
 
     // Wire persona store into task tool for auto_execute persona resolution
     context_tools
@@ -710,13 +1223,32 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
         cron.set_exec_context(
             bus.outbound_tx.clone(),
             workspace.to_path_buf(),
-            config.tools.exec.timeout_secs,
+            config.tools.exec.clone(),
         );
+        let patina_dir = data_dir();
+        cron.set_digest_sources(DigestSources {
+            sessions_dir: patina_dir.join("sessions"),
+            task_store: patina_dir.join("tasks.json"),
+            usage: agent_loop.usage_tracker.clone(),
+            pricing: config.agents.pricing.clone(),
+        });
         if let Err(e) = cron.start().await {
             tracing::warn!("Failed to start cron service: {e}");
         }
+        standup::sync_jobs(&mut cron, &config.standups);
     }
 
+    // The watchdog checks on cron jobs and the heartbeat
+    let watchdog = config.watchdog.enabled.then(|| {
+        Arc::new(Watchdog::new(
+            config.watchdog.clone(),
+            &config.heartbeat,
+            &data_dir(),
+            locales.for_user(None),
+        ))
+    });
+    let watchdog_handle = watchdog.clone().map(|w| w.spawn(bus.outbound_tx.clone()));
+
     // Start heartbeat if enabled
     let mut heartbeat_service: Option<patina_core::heartbeat::HeartbeatService> = None;
     if config.heartbeat.enabled {
@@ -724,7 +1256,24 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
             workspace.to_path_buf(),
             bus.inbound_tx.clone(),
             Some(config.heartbeat.interval_secs),
-        );
+        )
+        .with_model_tier(&config.heartbeat.model_tier);
+        if let Some(watchdog) = &watchdog {
+            heartbeat = heartbeat.with_watchdog(watchdog.clone());
+        }
+        let pickup = &config.heartbeat.task_pickup;
+        if pickup.enabled && !pickup.personas.is_empty() {
+            heartbeat = heartbeat.with_task_pickup(patina_core::heartbeat::TaskPickup::new(
+                pickup.clone(),
+                task_manager.clone(),
+                context_tools.task_tool.clone(),
+            ));
+            tracing::info!(
+                "Heartbeat task pickup enabled for {} (max {} at once)",
+                pickup.personas.join(", "),
+                pickup.max_concurrent
+            );
+        }
         heartbeat.start();
         tracing::info!("Heartbeat service started");
         heartbeat_service = Some(heartbeat);
@@ -733,23 +1282,72 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
     // Set up channel manager
     let outbound_rx = bus.outbound_tx.subscribe();
     let mut channel_manager = ChannelManager::new(outbound_rx);
+    channel_manager.set_outbound_config(config.gateway.outbound.clone());
+    let (control_handle, control_requests) = control::channel();
+
+    // Journal bus traffic so queued messages survive a crash
+    let journal = open_bus_journal(config);
+    if let Some(ref journal) = journal {
+        match bus.journal_inbound(journal.clone()) {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Replaying {n} unprocessed inbound message(s)"),
+            Err(e) => tracing::warn!("Failed to replay inbound journal: {e}"),
+        }
+        channel_manager.set_journal(journal.clone());
+    }
+
+    // Voice notes on Telegram and WhatsApp share one transcriber
+    let voice_transcriber = if config.channels.telegram.enabled || config.channels.whatsapp.enabled
+    {
+        let keys = builder::transcription_keys(config);
+        let progress = config
+            .channels
+            .web
+            .enabled
+            .then(|| model_download_progress(bus.outbound_tx.clone()));
+        // Loads (or downloads) the model in the background while the rest of
+        // the gateway starts; voice notes that arrive first wait for it
+        let mut transcriber: Arc<dyn patina_transcribe::Transcriber> =
+            Arc::new(patina_transcribe::BackgroundTranscriber::spawn(
+                config.transcription.clone(),
+                keys.clone(),
+                progress,
+            ));
+        if let Some(ref tracker) = agent_loop.usage_tracker {
+            transcriber = Arc::new(patina_core::usage::TrackedTranscriber::new(
+                transcriber,
+                tracker.clone(),
+            ));
+        }
+        // Share the backend with the transcribe tool instead of loading it twice
+        let allowed_dir = config
+            .tools
+            .restrict_to_workspace
+            .then(|| workspace.to_path_buf());
+        agent_loop.tools.register(Box::new(
+            TranscribeTool::new(
+                config.transcription.clone(),
+                keys,
+                workspace.to_path_buf(),
+                allowed_dir,
+                &config.tools.transcribe,
+            )
+            .with_transcriber(transcriber.clone()),
+        ));
+        Some(transcriber)
+    } else {
+        None
+    };
 
     // Register Telegram channel if enabled
     if config.channels.telegram.enabled {
-        let groq_key = resolve_api_key(&config.providers.groq, "GROQ_API_KEY");
-        let transcriber =
-            match patina_transcribe::create_transcriber(&config.transcription, groq_key).await {
-                Ok(t) => {
-                    tracing::info!("Voice transcription initialized");
-                    Some(Arc::from(t))
-                }
-                Err(e) => {
-                    tracing::warn!("Voice transcription unavailable: {e}");
-                    None
-                }
-            };
-        match TelegramChannel::new(config.channels.telegram.clone(), transcriber) {
+        match TelegramChannel::new(config.channels.telegram.clone(), voice_transcriber.clone()) {
             Ok(tg) => {
+                let tg = tg
+                    .with_media_limits(patina_transcribe::limits::MediaLimits::from_config(
+                        &config.transcription,
+                    ))
+                    .with_commands(commands.menu("telegram"));
                 channel_manager.register(Arc::new(tg)).await;
                 tracing::info!("Telegram channel registered");
             }
@@ -763,6 +1361,10 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
     if config.channels.slack.enabled {
         match SlackChannel::new(config.channels.slack.clone()) {
             Ok(sl) => {
+                let sl = sl
+                    .with_commands(commands.menu("slack"))
+                    .with_tasks(task_manager.clone())
+                    .with_sessions_dir(data_dir().join("sessions"));
                 channel_manager.register(Arc::new(sl)).await;
                 tracing::info!("Slack channel registered");
             }
@@ -772,13 +1374,65 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
         }
     }
 
+    // Register Teams channel if enabled
+    if config.channels.teams.enabled {
+        match TeamsChannel::new(config.channels.teams.clone()) {
+            Ok(teams) => {
+                channel_manager.register(Arc::new(teams)).await;
+                tracing::info!("Teams channel registered");
+            }
+            Err(e) => {
+                tracing::error!("Failed to create Teams channel: {e}");
+            }
+        }
+    }
+
+    // Register WhatsApp channel if enabled
+    if config.channels.whatsapp.enabled {
+        match WhatsAppChannel::new(config.channels.whatsapp.clone(), voice_transcriber) {
+            Ok(wa) => {
+                let wa = wa.with_media_limits(patina_transcribe::limits::MediaLimits::from_config(
+                    &config.transcription,
+                ));
+                channel_manager.register(Arc::new(wa)).await;
+                tracing::info!("WhatsApp channel registered");
+            }
+            Err(e) => {
+                tracing::error!("Failed to create WhatsApp channel: {e}");
+            }
+        }
+    }
+
+    // Register the outbound-only notify channel if enabled
+    if config.channels.notify.enabled {
+        match NotifyChannel::new(config.channels.notify.clone()) {
+            Ok(notify) => {
+                channel_manager.register(Arc::new(notify)).await;
+                tracing::info!("Notify channel registered");
+            }
+            Err(e) => {
+                tracing::error!("Failed to create notify channel: {e}");
+            }
+        }
+    }
+
+    // Register the outbound-only webhook channel if enabled
+    if config.channels.webhook.enabled {
+        match WebhookChannel::new(config.channels.webhook.clone()) {
+            Ok(webhook) => {
+                channel_manager.register(Arc::new(webhook)).await;
+                tracing::info!("Webhook channel registered");
+            }
+            Err(e) => {
+                tracing::error!("Failed to create webhook channel: {e}");
+            }
+        }
+    }
+
     // Register Web channel if enabled
     let mut web_channel_ref: Option<Arc<WebChannel>> = None;
     if config.channels.web.enabled {
-        let sessions_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".patina")
-            .join("sessions");
+        let sessions_dir = data_dir().join("sessions");
         match WebChannel::new(
             config.channels.web.clone(),
             config.gateway.clone(),
@@ -790,6 +1444,43 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
         ) {
             Ok(mut web) => {
                 web.set_task_manager(task_manager.clone());
+                web.set_user_tokens(users.web_tokens());
+                if let Some(links) = guest_links {
+                    web.set_guest_links(links);
+                }
+                web.set_memory(
+                    agent_loop.context.memory().clone(),
+                    agent_loop.memory_index.clone(),
+                );
+                web.set_workspace(workspace.to_path_buf());
+                let compare_db = data_dir().join("compare.sqlite");
+                match ComparisonStore::open(&compare_db) {
+                    Ok(store) => web.set_comparisons(Arc::new(store)),
+                    Err(e) => tracing::warn!(
+                        "A/B compare disabled, can't open {}: {e}",
+                        compare_db.display()
+                    ),
+                }
+                web.set_status_sources(StatusSources {
+                    cron: cron_service.clone(),
+                    subagents: subagents.clone(),
+                    transcription_mode: format!("{:?}", config.transcription.mode),
+                    transcription_chain: patina_transcribe::chain_order(
+                        &config.transcription,
+                        &builder::transcription_keys(config),
+                    )
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                    started_at,
+                });
+                web.set_control(control_handle.clone());
+                if let Some(ref store) = locations {
+                    web.set_locations(Locations {
+                        store: store.clone(),
+                        geofences: Arc::new(config.location.geofences.clone()),
+                    });
+                }
                 let web = Arc::new(web);
                 web_channel_ref = Some(web.clone());
                 channel_manager.register(web).await;
@@ -805,8 +1496,15 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
         }
     }
 
+    if locations.is_some() && web_channel_ref.is_none() {
+        tracing::warn!("location.enabled needs the web channel, which receives the pings");
+    }
+
     // Collect per-channel prompt rules and inject into agent loop
     agent_loop.channel_rules = channel_manager.prompt_rules().await;
+    context_tools
+        .message_tool
+        .set_channels(channel_manager.capabilities().await);
     if !agent_loop.channel_rules.is_empty() {
         tracing::info!(
             "Channel rules loaded for: {}",
@@ -829,10 +1527,41 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
     }
     channel_manager.start_all(bus.inbound_tx.clone()).await?;
 
-    tracing::info!("Gateway running. Press Ctrl-C to stop.");
+    // Let `patina channels restart|disable` act on the running channels
+    let channel_manager = Arc::new(tokio::sync::Mutex::new(channel_manager));
+    control::apply(control_requests, channel_manager.clone());
+    let control_socket = control::socket_path();
+    if let Err(e) = control::listen(&control_socket, control_handle).await {
+        tracing::warn!("Control socket disabled: {e}");
+    }
+
+    tracing::info!(
+        "Gateway running (started in {:.2?}). Press Ctrl-C to stop.",
+        started.elapsed()
+    );
+
+    let moderator = if config.moderation.enabled {
+        let api_key = config
+            .moderation
+            .provider
+            .as_ref()
+            .and_then(|p| p.api_key.clone())
+            .filter(|k| !k.is_empty())
+            .or_else(|| builder::resolve_api_key(&config.providers.openai, "OPENAI_API_KEY"));
+        Some(Moderator::new(&config.moderation, api_key)?)
+    } else {
+        None
+    };
+    // Streamed chunks would reach the browser before the reply is checked
+    let stream_web = !moderator
+        .as_ref()
+        .is_some_and(|m| m.covers("web", Direction::Outbound));
+    if !stream_web {
+        tracing::info!("Web streaming disabled: outbound moderation covers the web channel");
+    }
 
     // Set up streaming text chunk forwarder to web UI
-    if let Some(ref web_ch) = web_channel_ref {
+    if let Some(web_ch) = web_channel_ref.as_ref().filter(|_| stream_web) {
         let (stream_tx, mut stream_rx) =
             tokio::sync::mpsc::unbounded_channel::<patina_core::agent::r#loop::StreamChunk>();
         agent_loop.stream_tx = Some(stream_tx);
@@ -850,23 +1579,37 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
         });
     }
 
-    // Channel for background consolidation completions
-    let (consol_tx, mut consol_rx) = tokio::sync::mpsc::channel::<ConsolidationResult>(16);
+    // Background consolidations, and retries of failed ones
+    let mut consolidations = Consolidations::new(config, users.is_enabled());
 
     // Buffer for messages received while processing (from other sessions or slash commands)
     let mut pending: Vec<InboundMessage> = Vec::new();
+    // Journal entries for the messages the current turn is handling
+    let mut in_flight: Vec<i64> = Vec::new();
 
     // Main inbound processing loop
     loop {
+        // The previous turn is over, whichever way it ended
+        for id in in_flight.drain(..) {
+            if let Some(ref journal) = journal {
+                journal.complete(id);
+            }
+        }
+
         // Drain any completed background consolidations (non-blocking)
-        while let Ok(result) = consol_rx.try_recv() {
-            agent_loop.apply_consolidation(&result);
+        while let Ok((session_key, outcome)) = consolidations.rx.try_recv() {
+            consolidations.finish(&mut agent_loop, &bus.outbound_tx, session_key, outcome);
         }
+        consolidations.retry_due(&mut agent_loop);
 
-        // Take next message: from pending buffer first, then from channel
-        let msg = if let Some(buffered) = pending.pop() {
-            buffered
+        // Take the most urgent message that has arrived; wait if there's none
+        while let Ok(queued) = bus.inbound_rx.try_recv() {
+            pending.push(queued);
+        }
+        let mut msg = if let Some(next) = take_next(&mut pending) {
+            next
         } else {
+            let retry_wait = consolidations.retry_wait();
             tokio::select! {
                 msg = bus.inbound_rx.recv() => match msg {
                     Some(m) => m,
@@ -875,12 +1618,28 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                         break;
                     }
                 },
+                // Finished consolidations and due retries are handled at the top
+                Some((session_key, outcome)) = consolidations.rx.recv() => {
+                    consolidations.finish(&mut agent_loop, &bus.outbound_tx, session_key, outcome);
+                    continue;
+                }
+                _ = async {
+                    match retry_wait {
+                        Some(wait) => tokio::time::sleep(wait).await,
+                        None => std::future::pending().await,
+                    }
+                } => continue,
                 _ = tokio::signal::ctrl_c() => {
                     tracing::info!("Shutting down...");
                     break;
                 }
             }
         };
+        in_flight.extend(journal_id(&msg.metadata));
+        // Until a sender is resolved, turns use the `user` config's clock
+        agent_loop
+            .context
+            .set_user_locale(context_tools.default_locale.clone());
 
         {
             // System messages from subagents need special routing.
@@ -893,6 +1652,16 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                     } else {
                         ("cli".to_string(), msg.chat_id.clone())
                     };
+                // Heartbeat findings go to heartbeat.channel/to, if set
+                let heartbeat = msg.sender_id == "heartbeat";
+                let (reply_channel, reply_chat_id) = if heartbeat {
+                    (
+                        config.heartbeat.channel.clone(),
+                        config.heartbeat.to.clone(),
+                    )
+                } else {
+                    (origin_channel.clone(), origin_chat_id.clone())
+                };
 
                 let session_key = format!("{origin_channel}:{origin_chat_id}");
                 context_tools
@@ -908,9 +1677,17 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                             .get("status")
                             .and_then(|v| v.as_str())
                             .unwrap_or("completed");
+                        let picked_up = msg
+                            .metadata
+                            .get("pickup")
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false);
                         let mut tm = task_manager.lock().await;
                         let target_status = if status == "completed" {
                             patina_core::task::TaskStatus::Done
+                        } else if picked_up {
+                            // Don't let the next heartbeat retry it straight away
+                            patina_core::task::TaskStatus::Backlog
                         } else {
                             patina_core::task::TaskStatus::Todo
                         };
@@ -932,69 +1709,120 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                             session.add_message("system", &event);
                             let _ = agent_loop.sessions.save(&task_session_key);
                         }
+
+                        // Tasks picked up on a heartbeat report back to their owner
+                        let notify = |key: &str| msg.metadata.get(key).and_then(|v| v.as_str());
+                        if let (Some(channel), Some(to)) =
+                            (notify("notify_channel"), notify("notify_to"))
+                        {
+                            if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                                channel: channel.to_string(),
+                                chat_id: to.to_string(),
+                                content: msg.content.clone(),
+                                reply_to: None,
+                                metadata: HashMap::new(),
+                            }) {
+                                tracing::warn!("Failed to post task {task_id} result: {e}");
+                            }
+                        }
+                    }
+                }
+
+                // Subagent results for a webhook are posted as they are
+                if origin_channel == "webhook" {
+                    if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                        channel: origin_channel,
+                        chat_id: origin_chat_id,
+                        content: msg.content,
+                        reply_to: None,
+                        metadata: msg.metadata,
+                    }) {
+                        tracing::warn!("Failed to publish subagent result to webhook: {e}");
                     }
+                    continue;
                 }
 
                 // Prefix content with system sender info
                 let system_content = format!("[System: {}] {}", msg.sender_id, msg.content);
 
+                // Heartbeats and cron jobs can ask for a cheaper tier
+                let model_tier = msg.metadata.get("model_tier").and_then(|v| v.as_str());
                 let result = tokio::select! {
-                    res = agent_loop.process_message(&session_key, &system_content, None) => Some(res),
+                    res = agent_loop.process_message_with_persona(
+                        &session_key,
+                        &system_content,
+                        None,
+                        None,
+                        model_tier,
+                    ) => Some(res),
                     _ = tokio::signal::ctrl_c() => {
                         tracing::info!("Shutting down...");
                         None
                     }
                 };
+                // Heartbeat and cron turns tell the watchdog how they went
+                if let (Some(watchdog), Some(res)) = (&watchdog, &result) {
+                    let key = if heartbeat {
+                        Some(HEARTBEAT.to_string())
+                    } else {
+                        msg.metadata
+                            .get("cron_job_id")
+                            .and_then(|v| v.as_str())
+                            .map(job_key)
+                    };
+                    if let Some(key) = key {
+                        let outcome = res.as_ref().map(|_| ()).map_err(|e| e.to_string());
+                        watchdog.record(&key, outcome, chrono::Utc::now().timestamp_millis());
+                    }
+                }
                 match result {
                     Some(Ok((response, needs_consolidation))) => {
-                        if let Err(e) = bus.outbound_tx.send(OutboundMessage {
-                            channel: origin_channel,
-                            chat_id: origin_chat_id,
+                        let response = match moderator {
+                            Some(ref m) => {
+                                moderate_reply(
+                                    m,
+                                    &mut agent_loop,
+                                    &origin_channel,
+                                    &session_key,
+                                    response,
+                                )
+                                .await
+                            }
+                            None => response,
+                        };
+                        let quiet = heartbeat
+                            && (reply_channel.is_empty()
+                                || patina_core::heartbeat::is_heartbeat_ok(&response));
+                        if quiet {
+                            tracing::debug!("Heartbeat reply kept in its session");
+                        } else if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                            channel: reply_channel,
+                            chat_id: reply_chat_id,
                             content: response,
                             reply_to: None,
-                            metadata: msg.metadata.clone(),
+                            metadata: reply_metadata(&msg.metadata, true),
                         }) {
                             tracing::warn!(
                                 "Failed to publish outbound system response to bus: {e}"
                             );
                         }
                         if needs_consolidation {
-                            if let Some(task) =
-                                agent_loop.prepare_consolidation(&session_key, false)
-                            {
-                                let model = agent_loop.model_for_tier("consolidation");
-                                let tracker = agent_loop.usage_tracker.clone();
-                                let (_, cm_name, cm_provider) =
-                                    agent_loop.models.get("consolidation");
-                                let cm_name = cm_name.to_string();
-                                let cm_provider = cm_provider.to_string();
-                                let tx = consol_tx.clone();
-                                tokio::spawn(async move {
-                                    if let Some(result) = AgentLoop::run_consolidation(
-                                        &model,
-                                        &task,
-                                        tracker.as_ref(),
-                                        &cm_name,
-                                        &cm_provider,
-                                    )
-                                    .await
-                                    {
-                                        let _ = tx.send(result).await;
-                                    }
-                                });
-                            }
+                            consolidations.start(&agent_loop, &session_key);
                         }
                     }
+                    Some(Err(e)) if heartbeat && reply_channel.is_empty() => {
+                        tracing::error!("Error processing heartbeat: {e}");
+                    }
                     Some(Err(e)) => {
                         tracing::error!("Error processing system message: {e}");
                         if let Err(send_err) = bus.outbound_tx.send(OutboundMessage {
-                            channel: origin_channel,
-                            chat_id: origin_chat_id,
+                            channel: reply_channel,
+                            chat_id: reply_chat_id,
                             content: format!(
                                 "Background task completed but I couldn't process the result: {e}"
                             ),
                             reply_to: None,
-                            metadata: HashMap::new(),
+                            metadata: reply_metadata(&msg.metadata, false),
                         }) {
                             tracing::warn!(
                                     "Failed to publish outbound system-error response to bus: {send_err}"
@@ -1006,6 +1834,29 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                 continue;
             }
 
+            // Standups: run the configured persona and post to its chat
+            if msg.channel == STANDUP_CHANNEL {
+                let Some(standup_config) = config.standups.get(&msg.chat_id) else {
+                    tracing::warn!("No standup named '{}' in config", msg.chat_id);
+                    continue;
+                };
+                context_tools
+                    .set_context(&standup_config.channel, &standup_config.to)
+                    .await;
+                let reply = standup::run(
+                    &mut agent_loop,
+                    &msg.chat_id,
+                    standup_config,
+                    &persona_store,
+                    &task_manager,
+                )
+                .await;
+                if let Err(e) = bus.outbound_tx.send(reply) {
+                    tracing::warn!("Failed to publish standup to bus: {e}");
+                }
+                continue;
+            }
+
             // Task channel messages: route to task-scoped session with assignee persona.
             if msg.channel == "task" {
                 let task_id = msg.chat_id.clone();
@@ -1087,74 +1938,110 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
             context_tools.set_context(&msg.channel, &msg.chat_id).await;
 
             let session_key = msg.session_key();
+            let mut identity = users.resolve(&msg);
+
+            let refused_command = commands
+                .find(&msg.channel, &msg.content)
+                .map(|(command, _)| command.name().to_string())
+                .filter(|name| !users.policy(identity.role).allows_command(name));
+            if let Some(name) = refused_command {
+                if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                    channel: msg.channel.clone(),
+                    chat_id: msg.chat_id.clone(),
+                    content: format!("/{name} isn't available to you."),
+                    reply_to: None,
+                    metadata: msg.metadata.clone(),
+                }) {
+                    tracing::warn!("Failed to publish command response to bus: {e}");
+                }
+                continue;
+            }
+
+            // `/plan` runs as an ordinary turn, so personas and roles apply
+            let plan = commands
+                .find(&msg.channel, &msg.content)
+                .filter(|(command, args)| command.name() == "plan" && !args.is_empty())
+                .map(|(_, args)| plan_prompt(args));
+            let planning = plan.is_some();
+            if let Some(prompt) = plan {
+                msg.content = prompt;
+            }
 
             // Handle slash commands
-            let content = msg.content.trim();
-            if content == "/new" {
-                // Consolidate memory and start fresh
-                let session = match agent_loop.sessions.get_or_create_checked(&session_key) {
-                    Ok(s) => s,
-                    Err(e) => {
-                        tracing::error!("Failed to load session '{session_key}': {e}");
-                        if let Err(send_err) = bus.outbound_tx.send(OutboundMessage {
-                            channel: msg.channel.clone(),
-                            chat_id: msg.chat_id.clone(),
-                            content: format!(
-                                "I couldn't load your session state: {e}. \
-        Try checking session file permissions."
-                            ),
-                            reply_to: None,
-                            metadata: HashMap::new(),
-                        }) {
-                            tracing::warn!(
-                                "Failed to publish session-load error response: {send_err}"
-                            );
-                        }
-                        continue;
-                    }
-                };
-                let has_messages = !session.messages.is_empty();
-                if has_messages {
-                    agent_loop.consolidate_memory(&session_key, true).await;
+            if let Some(result) = commands
+                .dispatch(
+                    &msg.content,
+                    &mut agent_loop,
+                    &msg.channel,
+                    &session_key,
+                    Some(users.policy(identity.role)),
+                )
+                .await
+            {
+                let content = result.unwrap_or_else(|e| {
+                    tracing::error!("Command failed in session '{session_key}': {e}");
+                    format!("Sorry, I encountered an error: {e}")
+                });
+                // Buttons for channels that show them (Telegram's inline keyboard)
+                let mut metadata = msg.metadata.clone();
+                let choices = commands.choices(&msg.channel, &msg.content).await;
+                if !choices.is_empty() {
+                    let choices: Vec<_> = choices
+                        .into_iter()
+                        .map(|(label, input)| serde_json::json!({ "label": label, "input": input }))
+                        .collect();
+                    metadata.insert("choices".to_string(), serde_json::json!(choices));
+                }
+                if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                    channel: msg.channel.clone(),
+                    chat_id: msg.chat_id.clone(),
+                    content,
+                    reply_to: None,
+                    metadata,
+                }) {
+                    tracing::warn!("Failed to publish command response to bus: {e}");
                 }
-                let session = match agent_loop.sessions.get_or_create_checked(&session_key) {
-                    Ok(s) => s,
+                continue;
+            }
+
+            // Capture "todo: ..." messages straight onto the task board
+            if let Some(capture) = Capture::from_message(&msg.content, &config.tasks.capture_prefix)
+                .filter(|_| users.policy(identity.role).allows_tool("task"))
+            {
+                let content = match task_manager.lock().await.capture(&capture, &session_key) {
+                    Ok(task) => format!("Added task '{}' (ID: {})", task.title, task.id),
                     Err(e) => {
-                        tracing::error!(
-                            "Failed to reload session '{session_key}' before clear: {e}"
-                        );
-                        continue;
+                        tracing::error!("Task capture failed in session '{session_key}': {e}");
+                        format!("Sorry, I couldn't add that task: {e}")
                     }
                 };
-                session.clear();
-                if let Err(e) = agent_loop.sessions.save(&session_key) {
-                    tracing::warn!("Failed to save cleared session '{session_key}': {e}");
-                }
-                agent_loop.sessions.invalidate(&session_key);
-
                 if let Err(e) = bus.outbound_tx.send(OutboundMessage {
                     channel: msg.channel.clone(),
                     chat_id: msg.chat_id.clone(),
-                    content: "New session started. Previous conversation has been saved to memory."
-                        .to_string(),
+                    content,
                     reply_to: None,
                     metadata: msg.metadata.clone(),
                 }) {
-                    tracing::warn!("Failed to publish /new acknowledgement to bus: {e}");
+                    tracing::warn!("Failed to publish capture response to bus: {e}");
                 }
                 continue;
             }
 
-            if content == "/help" || content == "/start" {
+            if !users.take_message(&identity) {
+                tracing::info!(
+                    "Daily message limit reached for {}",
+                    identity.display_name()
+                );
                 if let Err(e) = bus.outbound_tx.send(OutboundMessage {
-                        channel: msg.channel.clone(),
-                        chat_id: msg.chat_id.clone(),
-                        content: "Hi! I'm Patina.\n\nSend me a message and I'll respond.\n\nCommands:\n/new - Start a new conversation\n/help - Show this help".to_string(),
-                        reply_to: None,
-                        metadata: msg.metadata.clone(),
-                    }) {
-                        tracing::warn!("Failed to publish help response to bus: {e}");
-                    }
+                    channel: msg.channel.clone(),
+                    chat_id: msg.chat_id.clone(),
+                    content: "You've reached today's message limit. Try again tomorrow."
+                        .to_string(),
+                    reply_to: None,
+                    metadata: msg.metadata.clone(),
+                }) {
+                    tracing::warn!("Failed to publish limit response to bus: {e}");
+                }
                 continue;
             }
 
@@ -1169,6 +2056,8 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
             let mut kept = Vec::new();
             for queued in pending.drain(..) {
                 if queued.session_key() == session_key && !queued.content.trim().starts_with('/') {
+                    identity = identity.least_trusted(users.resolve(&queued));
+                    in_flight.extend(journal_id(&queued.metadata));
                     content_parts.push(queued.content);
                     combined_media.extend(queued.media);
                     last_metadata = queued.metadata;
@@ -1181,6 +2070,8 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
             // Non-blocking drain of inbound channel for same-session messages
             while let Ok(extra) = bus.inbound_rx.try_recv() {
                 if extra.session_key() == session_key && !extra.content.trim().starts_with('/') {
+                    identity = identity.least_trusted(users.resolve(&extra));
+                    in_flight.extend(journal_id(&extra.metadata));
                     content_parts.push(extra.content);
                     combined_media.extend(extra.media);
                     last_metadata = extra.metadata;
@@ -1197,10 +2088,24 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                 );
             }
 
+            if planning {
+                dry_run.begin(&session_key);
+            }
+
             // === Layer 2: Active cancellation via pinned select loop ===
             // Process with ability to cancel and restart if new same-session messages arrive.
+            let mut inbound_blocked = false;
             let result = 'coalesce: loop {
-                let combined = content_parts.join("\n\n");
+                let mut combined = content_parts.join("\n\n");
+                if let Some(ref m) = moderator {
+                    let verdict = m.check(&msg.channel, Direction::Inbound, &combined).await;
+                    log_moderation(&agent_loop, &session_key, Direction::Inbound, &verdict);
+                    if verdict.is_blocked() {
+                        inbound_blocked = true;
+                        break 'coalesce Some(Ok((verdict.text, false)));
+                    }
+                    combined = verdict.text;
+                }
                 let media_snapshot: Vec<String> = combined_media.clone();
                 let media_opt = if media_snapshot.is_empty() {
                     None
@@ -1257,12 +2162,27 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                     }
                 };
 
+                agent_loop
+                    .context
+                    .set_user_locale(locales.for_user(identity.user.as_deref()));
+                let locale = agent_loop.locale_for(&session_key);
+                context_tools.set_locale(&locale).await;
+
+                if let Some(ref access) = access {
+                    access.begin_turn(&session_key, identity.clone());
+                }
+                // A cron job's own tier wins over the chat's persona
+                let model_tier = last_metadata
+                    .get("model_tier")
+                    .and_then(|v| v.as_str())
+                    .map(String::from)
+                    .or(persona_tier);
                 let process_fut = agent_loop.process_message_with_persona(
                     &session_key,
                     &combined,
                     media_opt,
                     preamble_override.as_deref(),
-                    persona_tier.as_deref(),
+                    model_tier.as_deref(),
                 );
                 tokio::pin!(process_fut);
 
@@ -1272,6 +2192,71 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                         res = &mut process_fut => break Some(res),
                         new_msg = bus.inbound_rx.recv() => {
                             match new_msg {
+                                // `/stop` ends this turn with what it has so far
+                                Some(m)
+                                    if m.session_key() == session_key
+                                        && commands
+                                            .find(&m.channel, &m.content)
+                                            .is_some_and(|(c, _)| c.name() == "stop") =>
+                                {
+                                    tracing::info!("Stopping in-flight turn for '{}'", session_key);
+                                    if let Err(e) = AgentLoop::request_interrupt(&session_key) {
+                                        tracing::warn!("Failed to request interrupt: {e}");
+                                    }
+                                    in_flight.extend(journal_id(&m.metadata));
+                                }
+                                // A turn waiting in `ask_user` needs `/answer` to get through
+                                Some(m)
+                                    if questions.is_some()
+                                        && commands
+                                            .find(&m.channel, &m.content)
+                                            .is_some_and(|(c, _)| c.name() == "answer")
+                                        && users
+                                            .policy(users.resolve(&m).role)
+                                            .allows_command("answer") =>
+                                {
+                                    let args = commands
+                                        .find(&m.channel, &m.content)
+                                        .map_or("", |(_, args)| args);
+                                    let reply = questions
+                                        .as_ref()
+                                        .map(|q| q.answer(args))
+                                        .unwrap_or_default();
+                                    in_flight.extend(journal_id(&m.metadata));
+                                    if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                                        channel: m.channel.clone(),
+                                        chat_id: m.chat_id.clone(),
+                                        content: reply,
+                                        reply_to: None,
+                                        metadata: m.metadata.clone(),
+                                    }) {
+                                        tracing::warn!("Failed to publish answer reply to bus: {e}");
+                                    }
+                                }
+                                // The panic switch can't wait for the turn it's meant to stop
+                                Some(m)
+                                    if commands
+                                        .find(&m.channel, &m.content)
+                                        .is_some_and(|(c, _)| c.name() == "lockdown")
+                                        && users
+                                            .policy(users.resolve(&m).role)
+                                            .allows_command("lockdown") =>
+                                {
+                                    let args = commands
+                                        .find(&m.channel, &m.content)
+                                        .map_or("", |(_, args)| args);
+                                    let reply = Lockdown::new().command(args, &m.session_key());
+                                    in_flight.extend(journal_id(&m.metadata));
+                                    if let Err(e) = bus.outbound_tx.send(OutboundMessage {
+                                        channel: m.channel.clone(),
+                                        chat_id: m.chat_id.clone(),
+                                        content: reply,
+                                        reply_to: None,
+                                        metadata: m.metadata.clone(),
+                                    }) {
+                                        tracing::warn!("Failed to publish lockdown reply to bus: {e}");
+                                    }
+                                }
                                 Some(m)
                                     if m.session_key() == session_key
                                         && !m.content.trim().starts_with('/') =>
@@ -1280,6 +2265,8 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                                         "Cancelling in-flight for '{}', coalescing new message",
                                         session_key
                                     );
+                                    identity = identity.least_trusted(users.resolve(&m));
+                                    in_flight.extend(journal_id(&m.metadata));
                                     content_parts.push(m.content);
                                     combined_media.extend(m.media);
                                     last_metadata = m.metadata;
@@ -1301,10 +2288,25 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
 
                 break inner_result;
             };
+            // A stop that arrived as the turn was finishing would cut the
+            // next one short
+            AgentLoop::consume_interrupt(&session_key);
+            let planned = planning.then(|| dry_run.end(&session_key));
 
             // === Handle result ===
             match result {
                 Some(Ok((response, needs_consolidation))) => {
+                    let response = match planned {
+                        Some(ref planned) => report(&response, planned),
+                        None => response,
+                    };
+                    let response = match moderator {
+                        Some(ref m) if !inbound_blocked => {
+                            moderate_reply(m, &mut agent_loop, &msg.channel, &session_key, response)
+                                .await
+                        }
+                        _ => response,
+                    };
                     if let Err(e) = bus.outbound_tx.send(OutboundMessage {
                         channel: msg.channel.clone(),
                         chat_id: msg.chat_id.clone(),
@@ -1314,28 +2316,11 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                     }) {
                         tracing::warn!("Failed to publish outbound response to bus: {e}");
                     }
-                    if needs_consolidation {
-                        if let Some(task) = agent_loop.prepare_consolidation(&session_key, false) {
-                            let model = agent_loop.model_for_tier("consolidation");
-                            let tracker = agent_loop.usage_tracker.clone();
-                            let (_, cm_name, cm_provider) = agent_loop.models.get("consolidation");
-                            let cm_name = cm_name.to_string();
-                            let cm_provider = cm_provider.to_string();
-                            let tx = consol_tx.clone();
-                            tokio::spawn(async move {
-                                if let Some(result) = AgentLoop::run_consolidation(
-                                    &model,
-                                    &task,
-                                    tracker.as_ref(),
-                                    &cm_name,
-                                    &cm_provider,
-                                )
-                                .await
-                                {
-                                    let _ = tx.send(result).await;
-                                }
-                            });
-                        }
+                    // Only roles with full memory access write their chats to memory
+                    if needs_consolidation
+                        && users.policy(identity.role).memory == MemoryAccess::Full
+                    {
+                        consolidations.start(&agent_loop, &session_key);
                     }
                 }
                 Some(Err(e)) => {
@@ -1343,7 +2328,7 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
                     if let Err(send_err) = bus.outbound_tx.send(OutboundMessage {
                         channel: msg.channel.clone(),
                         chat_id: msg.chat_id.clone(),
-                        content: format!("Sorry, I encountered an error: {e}"),
+                        content: error_reply(&e),
                         reply_to: None,
                         metadata: HashMap::new(),
                     }) {
@@ -1358,48 +2343,207 @@ async fn run_gateway(config: &patina_config::Config, workspace: &Path) -> Result
     }
 
     // Clean shutdown
-    channel_manager.stop_all().await?;
+    channel_manager.lock().await.stop_all().await?;
+    let _ = std::fs::remove_file(&control_socket);
     if let Some(ref mut heartbeat) = heartbeat_service {
         heartbeat.stop();
     }
+    if let Some(handle) = watchdog_handle {
+        handle.abort();
+    }
     {
         let mut cron = cron_service.lock().await;
         cron.stop();
     }
 
     // Wait for any in-flight background consolidations to finish
-    drop(consol_tx);
-    while let Some(result) = consol_rx.recv().await {
-        agent_loop.apply_consolidation(&result);
-    }
+    consolidations
+        .finish_running(&mut agent_loop, &bus.outbound_tx)
+        .await;
 
     tracing::info!("Gateway stopped");
 
     Ok(())
 }
 
-async fn run_single_message(
-    mut agent_loop: AgentLoop,
+/// Report a moderation hit in the terminal log and the session debug log.
+fn log_moderation(
+    agent_loop: &AgentLoop,
     session_key: &str,
-    message: &str,
-) -> Result<()> {
-    let (response, needs_consolidation) = agent_loop
-        .process_message(session_key, message, None)
-        .await?;
-    render_markdown(&response);
-    if needs_consolidation {
-        agent_loop.consolidate_memory(session_key, false).await;
+    direction: Direction,
+    verdict: &Verdict,
+) {
+    let Some(action) = verdict.action else {
+        return;
+    };
+    let direction = match direction {
+        Direction::Inbound => "inbound",
+        Direction::Outbound => "outbound",
+    };
+    tracing::warn!(
+        "Moderation {action:?} on {direction} message in '{session_key}': {}",
+        verdict.reasons.join(", ")
+    );
+    if let Some(ref log) = agent_loop.session_log {
+        log.log(
+            session_key,
+            "moderation",
+            serde_json::json!({
+                "direction": direction,
+                "action": action,
+                "reasons": verdict.reasons,
+            }),
+        );
     }
-    Ok(())
 }
 
-async fn run_interactive(
-    mut agent_loop: AgentLoop,
-    context_tools: ContextTools,
+/// Moderate a reply before it is sent. A rewritten or blocked reply also
+/// replaces the saved assistant message so the original doesn't come back
+/// with the chat history.
+async fn moderate_reply(
+    moderator: &Moderator,
+    agent_loop: &mut AgentLoop,
+    channel: &str,
     session_key: &str,
+    response: String,
+) -> String {
+    let verdict = moderator
+        .check(channel, Direction::Outbound, &response)
+        .await;
+    log_moderation(agent_loop, session_key, Direction::Outbound, &verdict);
+    if !verdict.changed() {
+        return response;
+    }
+    if let Ok(session) = agent_loop.sessions.get_or_create_checked(session_key) {
+        if let Some(last) = session
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == "assistant")
+        {
+            last.content.clone_from(&verdict.text);
+        }
+        let _ = agent_loop.sessions.save(session_key);
+    }
+    verdict.text
+}
+
+/// Answer one message, or with `dry_run`, plan it (see `/plan`).
+async fn run_single_message(
+    mut agent_loop: AgentLoop,
+    session_key: &str,
+    message: &str,
+    dry_run: Option<&DryRun>,
+) -> Result<()> {
+    let prompt = match dry_run {
+        Some(dry_run) => {
+            dry_run.begin(session_key);
+            plan_prompt(message)
+        }
+        None => message.to_string(),
+    };
+    let result = agent_loop.process_message(session_key, &prompt, None).await;
+    let planned = dry_run.map(|d| d.end(session_key));
+    let (response, needs_consolidation) = result?;
+    match planned {
+        Some(planned) => render_markdown(&report(&response, &planned)),
+        None => render_markdown(&response),
+    }
+    if needs_consolidation {
+        agent_loop.consolidate_memory(session_key, false).await;
+    }
+    Ok(())
+}
+
+/// Prompt for `patina ask --screenshot` without a question.
+const SCREENSHOT_PROMPT: &str = "What's on my screen? If it shows an error, explain what it \
+means and how to fix it.";
+
+/// `patina ask`: answer one question, attaching a screenshot taken first
+/// with `--screenshot`.
+async fn run_ask(
+    config: &patina_config::Config,
+    workspace: &Path,
+    session_key: &str,
+    prompt: &str,
+    screenshot: bool,
+    tier: Option<String>,
+) -> Result<()> {
+    let prompt = match prompt.trim() {
+        "" if screenshot => SCREENSHOT_PROMPT,
+        "" => anyhow::bail!("Nothing to ask: give a question, or --screenshot"),
+        prompt => prompt,
+    };
+    let media = if screenshot {
+        vec![take_screenshot().await?]
+    } else {
+        Vec::new()
+    };
+    let tier = tier.or_else(|| {
+        (screenshot && config.agents.models.contains_key("vision")).then(|| "vision".to_string())
+    });
+
+    let Agent {
+        agent_loop,
+        context_tools,
+        ..
+    } = AgentBuilder::new(config.clone())
+        .with_workspace(workspace)
+        .build()?;
+    #[cfg(feature = "desktop")]
+    let mut agent_loop = with_desktop_tools(agent_loop);
+    #[cfg(not(feature = "desktop"))]
+    let mut agent_loop = agent_loop;
+
+    let (channel, chat_id) = session_key.split_once(':').unwrap_or(("cli", session_key));
+    context_tools.set_context(channel, chat_id).await;
+    context_tools
+        .set_locale(&agent_loop.locale_for(session_key))
+        .await;
+    let (response, needs_consolidation) = agent_loop
+        .process_message_with_persona(
+            session_key,
+            prompt,
+            (!media.is_empty()).then_some(media.as_slice()),
+            None,
+            tier.as_deref(),
+        )
+        .await?;
+    render_markdown(&response);
+    if needs_consolidation {
+        agent_loop.consolidate_memory(session_key, false).await;
+    }
+    Ok(())
+}
+
+/// Capture the screen into the media folder, returning the file's path.
+#[cfg(feature = "desktop")]
+async fn take_screenshot() -> Result<String> {
+    let media_dir = data_dir().join("media");
+    std::fs::create_dir_all(&media_dir)?;
+    let path = media_dir.join(format!(
+        "screenshot-{}.png",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    patina_core::tools::desktop::capture_screen(&path).await?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(not(feature = "desktop"))]
+async fn take_screenshot() -> Result<String> {
+    anyhow::bail!("--screenshot needs a build with the `desktop` feature")
+}
+
+async fn run_interactive(
+    mut agent_loop: AgentLoop,
+    context_tools: ContextTools,
+    personas: Arc<Mutex<PersonaStore>>,
+    dry_run: Arc<DryRun>,
+    session_key: &str,
+    voice: Option<VoiceInput>,
 ) -> Result<()> {
     // Save terminal state for restoration on exit
-    #[cfg(unix)]
+    #[cfg(any(unix, windows))]
     let saved_term = save_terminal_state();
 
     // Set initial context from the session key
@@ -1410,31 +2554,74 @@ async fn run_interactive(
         ("cli", session_key)
     };
     context_tools.set_context(channel, chat_id).await;
-    let history_dir = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("history");
+    context_tools
+        .set_locale(&agent_loop.locale_for(session_key))
+        .await;
+    let history_dir = data_dir().join("history");
     std::fs::create_dir_all(&history_dir)?;
     let history_path = history_dir.join("cli_history");
 
     let mut rl = DefaultEditor::new()?;
     let _ = rl.load_history(&history_path);
 
+    let mut footer = Vec::new();
+    if voice.is_some() {
+        footer.push("Enter - Speak a message (empty line)".to_string());
+    }
+    footer.push(format!(
+        "interrupt (external): `patina interrupt --session {session_key}`"
+    ));
+    footer.push("/quit - Exit".to_string());
+    let mut commands = CommandRegistry::new();
+    commands.register(Box::new(NewSessionCommand));
+    commands.register(Box::new(ForgetCommand::new()));
+    commands.register(Box::new(ContextCommand::new(personas)));
+    commands.register(Box::new(PlanCommand::new(dry_run)));
+    commands.register(Box::new(
+        HelpCommand::new("").with_footer(footer.join("\n")),
+    ));
+
     println!("patina interactive mode (type /help for commands, Ctrl-D to quit)");
+    if voice.is_some() {
+        println!("Voice input on: press Enter on an empty line and speak");
+    }
     println!();
 
     let result = loop {
         // Flush any pending input before reading
-        #[cfg(unix)]
+        #[cfg(any(unix, windows))]
         flush_pending_input();
 
         let readline = rl.readline("you> ");
         match readline {
             Ok(line) => {
-                let input = line.trim();
-                if input.is_empty() {
-                    continue;
-                }
+                let line = line.trim();
+                let transcript;
+                let input = if line.is_empty() {
+                    let Some(ref voice) = voice else {
+                        continue;
+                    };
+                    println!("Listening... (pause to finish)");
+                    match voice.listen(session_key).await {
+                        Ok(Some(text)) => {
+                            println!("you (voice)> {text}");
+                            transcript = text;
+                            transcript.as_str()
+                        }
+                        Ok(None) => {
+                            println!("(no speech heard)");
+                            println!();
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!("Voice input failed: {e}");
+                            println!();
+                            continue;
+                        }
+                    }
+                } else {
+                    line
+                };
 
                 let _ = rl.add_history_entry(input);
 
@@ -1444,76 +2631,19 @@ async fn run_interactive(
                 }
 
                 // Handle slash commands
-                match input {
-                    "/help" => {
-                        println!("Commands:");
-                        println!("  /new   - Start a new conversation (consolidates memory)");
-                        println!("  /help  - Show this help");
-                        println!(
-                            "  interrupt (external): `patina interrupt --session {session_key}`"
-                        );
-                        println!("  /quit  - Exit");
-                        println!();
-                        continue;
+                if let Some(result) = commands
+                    .dispatch(input, &mut agent_loop, "cli", session_key, None)
+                    .await
+                {
+                    match result {
+                        Ok(reply) => println!("{reply}"),
+                        Err(e) => eprintln!("Error: {e}"),
                     }
-                    "/new" => {
-                        // Consolidate current session before clearing
-                        let session = match agent_loop.sessions.get_or_create_checked(session_key) {
-                            Ok(s) => s,
-                            Err(e) => {
-                                tracing::error!("Failed to load session '{session_key}': {e}");
-                                println!(
-                                    "Could not load session state: {e}\nCheck session file permissions and try again."
-                                );
-                                println!();
-                                continue;
-                            }
-                        };
-                        let has_messages = !session.messages.is_empty();
-
-                        if has_messages {
-                            println!("Consolidating memory...");
-                            agent_loop.consolidate_memory(session_key, true).await;
-                        }
-
-                        let session = match agent_loop.sessions.get_or_create_checked(session_key) {
-                            Ok(s) => s,
-                            Err(e) => {
-                                tracing::error!(
-                                    "Failed to reload session '{session_key}' before clear: {e}"
-                                );
-                                println!("Could not reset session: {e}");
-                                println!();
-                                continue;
-                            }
-                        };
-                        session.clear();
-                        if let Err(e) = agent_loop.sessions.save(session_key) {
-                            tracing::warn!("Failed to save cleared session '{session_key}': {e}");
-                        }
-                        agent_loop.sessions.invalidate(session_key);
-                        println!("New session started.");
-                        println!();
-                        continue;
-                    }
-                    _ => {}
+                    println!();
+                    continue;
                 }
 
-                // Process message
-                match agent_loop.process_message(session_key, input, None).await {
-                    Ok((response, needs_consolidation)) => {
-                        println!();
-                        render_markdown(&response);
-                        println!();
-                        if needs_consolidation {
-                            agent_loop.consolidate_memory(session_key, false).await;
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error: {e}");
-                        println!();
-                    }
-                }
+                respond(&mut agent_loop, session_key, input).await;
             }
             Err(ReadlineError::Interrupted) => {
                 println!("^C");
@@ -1533,7 +2663,7 @@ async fn run_interactive(
     let _ = rl.save_history(&history_path);
 
     // Restore terminal state on exit
-    #[cfg(unix)]
+    #[cfg(any(unix, windows))]
     if let Some(ref saved) = saved_term {
         restore_terminal_state(saved);
     }
@@ -1541,6 +2671,104 @@ async fn run_interactive(
     result
 }
 
+/// User-facing reply for a failed agent turn. Provider timeouts, rate limits,
+/// and auth failures get their own wording so a slow model or a bad key isn't
+/// reported as a generic failure.
+fn error_reply(e: &PatinaError) -> String {
+    match e {
+        PatinaError::Provider(ProviderError::Timeout { provider, secs }) => format!(
+            "Sorry, the {provider} model didn't respond within {secs}s, so I gave up on this message. \
+             It may be overloaded or unreachable; please try again shortly."
+        ),
+        PatinaError::Provider(ProviderError::RateLimited { provider, .. }) => format!(
+            "Sorry, {provider} is rate limiting requests right now. Please try again in a minute."
+        ),
+        PatinaError::Provider(ProviderError::Auth { provider, .. }) => format!(
+            "Sorry, {provider} rejected the API key, so I can't answer. \
+             Check the provider's key in the config."
+        ),
+        _ => format!("Sorry, I encountered an error: {e}"),
+    }
+}
+
+/// Run one message through the agent and print the reply.
+async fn respond(agent_loop: &mut AgentLoop, session_key: &str, input: &str) {
+    match agent_loop.process_message(session_key, input, None).await {
+        Ok((response, needs_consolidation)) => {
+            println!();
+            render_markdown(&response);
+            println!();
+            if needs_consolidation {
+                agent_loop.consolidate_memory(session_key, false).await;
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            println!();
+        }
+    }
+}
+
+/// Hands-free loop for `patina agent --wake-word`: wait for the wake word,
+/// record the request that follows, and answer it. Runs until Ctrl-C.
+async fn run_wake_word(
+    mut agent_loop: AgentLoop,
+    context_tools: ContextTools,
+    session_key: &str,
+    voice: VoiceInput,
+    config: &patina_config::WakeWordConfig,
+) -> Result<()> {
+    use patina_transcribe::wakeword::{self, WakeWordDetector};
+
+    if !wakeword::wakeword_available() {
+        anyhow::bail!("This build has no wake-word support. Rebuild with `--features wakeword`.");
+    }
+    let models = wakeword::ensure_models(config, None).await?;
+    let detector = Arc::new(std::sync::Mutex::new(WakeWordDetector::load(
+        &models,
+        config.threshold,
+    )?));
+
+    let parts: Vec<&str> = session_key.splitn(2, ':').collect();
+    let (channel, chat_id) = if parts.len() == 2 {
+        (parts[0], parts[1])
+    } else {
+        ("cli", session_key)
+    };
+    context_tools.set_context(channel, chat_id).await;
+    context_tools
+        .set_locale(&agent_loop.locale_for(session_key))
+        .await;
+
+    println!(
+        "patina hands-free mode: say \"{}\" followed by your request (Ctrl-C to quit)",
+        models.phrase
+    );
+    println!();
+
+    loop {
+        let heard = tokio::select! {
+            heard = voice.listen_after_wake_word(session_key, detector.clone()) => heard,
+            _ = tokio::signal::ctrl_c() => {
+                println!("Goodbye!");
+                return Ok(());
+            }
+        };
+        match heard {
+            Ok(Some(text)) => {
+                println!("you (voice)> {text}");
+                respond(&mut agent_loop, session_key, &text).await;
+            }
+            Ok(None) => println!("(no request heard)"),
+            Err(e) => {
+                eprintln!("Voice input failed: {e}");
+                // Don't spin if the microphone keeps failing
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
 fn prompt_with_default(prompt: &str, default: &str) -> Result<String> {
     use std::io::{self, Write};
     print!("{prompt} [{default}]: ");
@@ -1571,9 +2799,13 @@ fn prompt_yes_no(prompt: &str, default_yes: bool) -> Result<bool> {
 }
 
 /// Initialize configuration and workspace with templates.
-fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool) -> Result<()> {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    let patina_dir = home.join(".patina");
+fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool, docker: bool) -> Result<()> {
+    // For Docker, ./patina-data is mounted as the container's PATINA_HOME
+    let patina_dir = if docker {
+        PathBuf::from(DOCKER_DATA_DIR)
+    } else {
+        data_dir()
+    };
     std::fs::create_dir_all(&patina_dir)?;
 
     // Config file
@@ -1583,10 +2815,15 @@ fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool) -> Result<()>
         println!("To reset, delete it and run `patina onboard` again.");
     } else {
         let mut cfg = patina_config::Config::default();
+        // The web UI is how you reach a container, and what its healthcheck probes
+        cfg.channels.web.enabled = docker;
         if !non_interactive {
             println!("Interactive setup");
-            cfg.agents.defaults.workspace =
-                prompt_with_default("Workspace path", &cfg.agents.defaults.workspace)?;
+            // In a container the workspace stays in the data dir
+            if !docker {
+                cfg.agents.defaults.workspace =
+                    prompt_with_default("Workspace path", &cfg.agents.defaults.workspace)?;
+            }
             cfg.agents.defaults.model =
                 prompt_with_default("Default model", &cfg.agents.defaults.model)?;
 
@@ -1606,17 +2843,22 @@ fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool) -> Result<()>
                     prompt_with_default("Slack bot token (xoxb-*)", &cfg.channels.slack.bot_token)?;
             }
 
-            let enable_web = prompt_yes_no("Enable Web chat channel?", false)?;
+            let enable_web = prompt_yes_no("Enable Web chat channel?", docker)?;
             cfg.channels.web.enabled = enable_web;
             if enable_web {
                 let pw = prompt_with_default("Web chat password (empty for open access)", "")?;
                 cfg.channels.web.password = pw;
             }
 
-            let mode = prompt_with_default("Transcription mode (auto/local/groq)", "auto")?;
+            let mode = prompt_with_default(
+                "Transcription mode (auto/local/groq/openai/deepgram)",
+                "auto",
+            )?;
             cfg.transcription.mode = match mode.to_lowercase().as_str() {
                 "local" => patina_config::TranscriptionMode::Local,
                 "groq" => patina_config::TranscriptionMode::Groq,
+                "openai" => patina_config::TranscriptionMode::OpenAi,
+                "deepgram" => patina_config::TranscriptionMode::Deepgram,
                 _ => patina_config::TranscriptionMode::Auto,
             };
         }
@@ -1628,7 +2870,11 @@ fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool) -> Result<()>
 
     // Workspace
     let config = load_config(&config_path)?;
-    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let workspace = if docker {
+        patina_dir.join("workspace")
+    } else {
+        resolve_workspace(&config.agents.defaults.workspace)
+    };
     std::fs::create_dir_all(&workspace)?;
     println!("Workspace: {}", workspace.display());
 
@@ -1680,27 +2926,86 @@ fn run_onboard(config_arg: Option<PathBuf>, non_interactive: bool) -> Result<()>
     std::fs::create_dir_all(&skills_dir)?;
     println!("  Created skills/");
 
+    if docker {
+        let compose = Path::new("docker-compose.yml");
+        if compose.exists() {
+            println!("docker-compose.yml already exists, leaving it alone");
+        } else {
+            std::fs::write(compose, docker_compose(&config))?;
+            println!("Created docker-compose.yml");
+        }
+    }
+
     println!();
     println!("Setup complete! Next steps:");
     println!(
         "  1. Edit {} to configure your LLM provider",
         config_path.display()
     );
-    println!("  2. Run `patina agent` to start chatting");
+    if docker {
+        println!("  2. Build the image from a patina checkout: `docker build -t patina .`");
+        println!("  3. Run `docker compose up -d` here");
+        println!("     (services on this machine, like Ollama, are at host.docker.internal)");
+    } else {
+        println!("  2. Run `patina agent` to start chatting");
+    }
     println!();
     println!("Voice transcription notes:");
     println!(
         "  - Local model files auto-download on first use when transcription.autoDownload=true."
     );
-    println!("  - ffmpeg must be installed for local transcription audio conversion.");
-    println!("  - Manual model setup (optional): ~/.patina/models/parakeet-tdt");
+    println!(
+        "  - Local transcription decodes audio natively; ffmpeg is only needed for exotic formats."
+    );
+    println!(
+        "  - Manual model setup (optional): {}",
+        patina_dir.join("models/parakeet-tdt").display()
+    );
     println!();
 
     Ok(())
 }
 
+/// Host directory `patina onboard --docker` mounts as the container's data dir.
+const DOCKER_DATA_DIR: &str = "patina-data";
+
+/// docker-compose.yml for `patina onboard --docker`. The healthcheck needs the
+/// web channel, which serves `/health`.
+fn docker_compose(config: &patina_config::Config) -> String {
+    let port = config.gateway.port;
+    let mut yaml = format!(
+        r#"services:
+  patina:
+    image: patina:latest
+    command: ["serve"]
+    restart: unless-stopped
+    environment:
+      PATINA_HOME: /data
+      HOST: 0.0.0.0
+      PORT: "{port}"
+    ports:
+      - "{port}:{port}"
+    volumes:
+      - ./{DOCKER_DATA_DIR}:/data
+    extra_hosts:
+      - "host.docker.internal:host-gateway"
+"#
+    );
+    if config.channels.web.enabled {
+        yaml.push_str(&format!(
+            r#"    healthcheck:
+      test: ["CMD", "curl", "-fsS", "http://localhost:{port}/health"]
+      interval: 30s
+      timeout: 5s
+      retries: 3
+"#
+        ));
+    }
+    yaml
+}
+
 /// Show system status and configuration summary.
-fn run_status(config_path: &Path) -> Result<()> {
+async fn run_status(config_path: &Path) -> Result<()> {
     println!("patina status");
     println!();
 
@@ -1744,6 +3049,45 @@ fn run_status(config_path: &Path) -> Result<()> {
     );
     println!();
 
+    // Ollama
+    let tiers = builder::ollama_tiers(&config);
+    if !tiers.is_empty() {
+        let client = builder::ollama_client(&config);
+        match client.list_models().await {
+            Ok(installed) => {
+                let loaded = client.loaded_models().await.unwrap_or_default();
+                let pulls = config
+                    .providers
+                    .ollama
+                    .as_ref()
+                    .and_then(|c| c.pull_missing)
+                    .unwrap_or(true);
+                let missing = if pulls {
+                    "missing — pulled on next start"
+                } else {
+                    "missing — run `ollama pull`"
+                };
+                println!(
+                    "  Ollama:    {} (reachable, {} installed)",
+                    client.base_url(),
+                    installed.len()
+                );
+                for (tier, model) in &tiers {
+                    let state = if !installed.iter().any(|m| ollama::same_model(&m.name, model)) {
+                        missing
+                    } else if loaded.iter().any(|m| ollama::same_model(&m.name, model)) {
+                        "installed, loaded"
+                    } else {
+                        "installed"
+                    };
+                    println!("    {tier}: {model} ({state})");
+                }
+            }
+            Err(e) => println!("  Ollama:    {} (unreachable: {e})", client.base_url()),
+        }
+        println!();
+    }
+
     // Tools
     let brave_key = if config.tools.web.search.api_key.is_empty() {
         std::env::var("BRAVE_API_KEY").unwrap_or_default()
@@ -1774,8 +3118,8 @@ fn run_status(config_path: &Path) -> Result<()> {
     println!("  Transcription:");
     println!("    Mode: {:?}", config.transcription.mode);
     let model_path = config.transcription.model_path.clone().unwrap_or_else(|| {
-        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-        home.join(".patina/models/parakeet-tdt")
+        data_dir()
+            .join("models/parakeet-tdt")
             .to_string_lossy()
             .to_string()
     });
@@ -1789,21 +3133,31 @@ fn run_status(config_path: &Path) -> Result<()> {
         }
     );
     println!(
-        "    ffmpeg: {}",
-        if patina_transcribe::audio::ffmpeg_available() {
-            "available"
-        } else {
-            "not found"
-        }
+        "    Audio decoding: {}",
+        patina_transcribe::audio::conversion_backend()
     );
-    println!(
-        "    Execution provider: {}",
-        config
-            .transcription
-            .execution_provider
-            .as_deref()
-            .unwrap_or("cpu")
+    let selection = patina_transcribe::provider::select_provider(
+        config.transcription.execution_provider.as_deref(),
     );
+    match selection.fallback_reason {
+        Some(ref reason) => println!(
+            "    Execution provider: {} -> {} ({reason})",
+            selection.requested,
+            selection.selected.as_str()
+        ),
+        None => println!("    Execution provider: {}", selection.selected.as_str()),
+    }
+    if let Some(loaded) = patina_transcribe::provider::last_loaded() {
+        println!(
+            "    Last model load: {} (pid {}, {})",
+            loaded.loaded.as_str(),
+            loaded.pid,
+            loaded.loaded_at
+        );
+        if let Some(reason) = loaded.fallback_reason {
+            println!("      Fallback: {reason}");
+        }
+    }
     println!(
         "    Auto download: {}",
         if config.transcription.auto_download {
@@ -1817,24 +3171,59 @@ fn run_status(config_path: &Path) -> Result<()> {
             println!("    Model URL: {url}");
         }
     }
+    let chain = patina_transcribe::chain_order(
+        &config.transcription,
+        &builder::transcription_keys(&config),
+    );
+    println!(
+        "    Chain: {}",
+        if chain.is_empty() {
+            "none (no API keys)".to_string()
+        } else {
+            chain.join(" -> ")
+        }
+    );
+    println!(
+        "    Language: {}",
+        config
+            .transcription
+            .language
+            .as_deref()
+            .unwrap_or("auto-detect")
+    );
+    let limits = patina_transcribe::limits::MediaLimits::from_config(&config.transcription);
+    println!(
+        "    Voice limits: {}, {}",
+        limits
+            .max_duration_secs
+            .map(|s| format!("{s}s"))
+            .unwrap_or_else(|| "no length limit".to_string()),
+        limits
+            .max_bytes
+            .map(|b| format!("{} MB", b / (1024 * 1024)))
+            .unwrap_or_else(|| "no size limit".to_string())
+    );
+    if !config.transcription.chat_languages.is_empty() {
+        println!(
+            "    Per-chat languages: {}",
+            config.transcription.chat_languages.len()
+        );
+    }
 
     Ok(())
 }
 
 /// Handle cron CLI subcommands.
 async fn run_cron_command(action: CronCommands, config: &patina_config::Config) -> Result<()> {
-    use patina_core::cron::{CronSchedule, ScheduleKind};
+    use patina_core::cron::{CronSchedule, DeliverOn, OutputCondition, ScheduleKind};
 
-    let store_path = dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".patina")
-        .join("cron")
-        .join("jobs.json");
+    let store_path = data_dir().join("cron").join("jobs.json");
 
     // Create a dummy inbound_tx — CLI cron commands don't send messages
     let (inbound_tx, _inbound_rx) = tokio::sync::mpsc::channel(1);
     let mut cron_service = CronService::new(store_path, inbound_tx);
     cron_service.start().await?;
+    let locale = Locales::new(&config.user, &config.users).for_user(None);
 
     match action {
         CronCommands::List { all } => {
@@ -1862,16 +3251,12 @@ async fn run_cron_command(action: CronCommands, config: &patina_config::Config)
                     }
                     ScheduleKind::Cron => job.schedule.expr.clone().unwrap_or_else(|| "?".into()),
                     ScheduleKind::At => match job.schedule.at_ms {
-                        Some(ms) => chrono::DateTime::from_timestamp_millis(ms)
-                            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
-                            .unwrap_or_else(|| "?".into()),
-                        None => "?".into(),
+                        Some(ms) => locale.format_ms(ms),
+                        None => "when chained".into(),
                     },
                 };
                 let next_run = match job.state.next_run_at_ms {
-                    Some(ms) => chrono::DateTime::from_timestamp_millis(ms)
-                        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
-                        .unwrap_or_else(|| "—".into()),
+                    Some(ms) => locale.format_ms(ms),
                     None => "—".into(),
                 };
                 println!(
@@ -1893,7 +3278,43 @@ async fn run_cron_command(action: CronCommands, config: &patina_config::Config)
             deliver,
             channel,
             to,
+            kind,
+            cache_ttl,
+            tier,
+            deliver_on,
+            chained,
+            on_success,
+            on_failure,
+            if_contains,
+            if_match,
         } => {
+            if !matches!(kind.as_str(), "agent_turn" | "exec" | "digest" | "message") {
+                anyhow::bail!("Unknown kind '{kind}' (use agent_turn, exec, digest, or message)");
+            }
+            if cache_ttl.is_some() && kind != "agent_turn" {
+                anyhow::bail!("--cache-ttl only applies to agent_turn jobs");
+            }
+            if tier.is_some() && kind != "agent_turn" {
+                anyhow::bail!("--tier only applies to agent_turn jobs");
+            }
+            let deliver_on = match deliver_on.as_deref() {
+                None | Some("always") => DeliverOn::Always,
+                Some("failure") if kind == "exec" => DeliverOn::Failure,
+                Some("failure") => anyhow::bail!("--deliver-on only applies to exec jobs"),
+                Some(other) => {
+                    anyhow::bail!("Unknown --deliver-on '{other}' (use always or failure)")
+                }
+            };
+            if (if_contains.is_some() || if_match.is_some())
+                && on_success.is_none()
+                && on_failure.is_none()
+            {
+                anyhow::bail!("--if-contains and --if-match need --on-success or --on-failure");
+            }
+            if matches!(kind.as_str(), "digest" | "message") && (channel.is_none() || to.is_none())
+            {
+                anyhow::bail!("{kind} jobs need --channel and --to");
+            }
             let (schedule, delete_after_run) = if let Some(secs) = every {
                 (
                     CronSchedule {
@@ -1912,16 +3333,14 @@ async fn run_cron_command(action: CronCommands, config: &patina_config::Config)
                         at_ms: None,
                         every_ms: None,
                         expr: Some(expr),
-                        tz: None,
+                        tz: locale.tz_name(),
                     },
                     false,
                 )
             } else if let Some(at_str) = at {
-                let dt = chrono::DateTime::parse_from_rfc3339(&at_str).map_err(|e| {
-                    anyhow::anyhow!(
-                        "Invalid datetime (use RFC3339 format, e.g. 2025-06-01T09:00:00Z): {e}"
-                    )
-                })?;
+                let dt = locale
+                    .parse(&at_str)
+                    .map_err(|e| anyhow::anyhow!("Invalid --at: {e}"))?;
                 (
                     CronSchedule {
                         kind: ScheduleKind::At,
@@ -1932,20 +3351,52 @@ async fn run_cron_command(action: CronCommands, config: &patina_config::Config)
                     },
                     true,
                 )
+            } else if chained {
+                (
+                    CronSchedule {
+                        kind: ScheduleKind::At,
+                        at_ms: None,
+                        every_ms: None,
+                        expr: None,
+                        tz: None,
+                    },
+                    false,
+                )
             } else {
-                anyhow::bail!("Must specify one of --every, --cron, or --at");
+                anyhow::bail!("Must specify one of --every, --cron, --at, or --chained");
             };
 
             let job = cron_service.add_job(
                 &name,
                 schedule,
                 &message,
-                "agent_turn",
+                &kind,
                 deliver,
                 channel,
                 to,
                 delete_after_run,
             )?;
+            if cache_ttl.is_some() {
+                cron_service.set_cache_ttl(&job.id, cache_ttl);
+            }
+            if tier.is_some() {
+                cron_service.set_model_tier(&job.id, tier);
+            }
+            if !deliver_on.is_always() {
+                cron_service.set_deliver_on(&job.id, deliver_on);
+            }
+            if on_success.is_some() || on_failure.is_some() {
+                let condition = OutputCondition {
+                    contains: if_contains,
+                    regex: if_match,
+                };
+                if let Err(e) =
+                    cron_service.set_chain(&job.id, on_success, on_failure, Some(condition))
+                {
+                    cron_service.remove_job(&job.id);
+                    return Err(e);
+                }
+            }
             println!("Added job '{}' (id: {})", job.name, job.id);
         }
         CronCommands::Remove { job_id } => {
@@ -1989,14 +3440,628 @@ async fn run_cron_command(action: CronCommands, config: &patina_config::Config)
                 }
             }
         }
+        CronCommands::History {
+            job_id,
+            limit,
+            full,
+        } => {
+            let runs = cron_service.runs(job_id.as_deref(), limit);
+            if runs.is_empty() {
+                println!("No recorded runs.");
+                return Ok(());
+            }
+            for run in &runs {
+                let exit = run
+                    .exit_code
+                    .map(|c| format!("exit {c}"))
+                    .unwrap_or_else(|| "no exit code".into());
+                println!(
+                    "{}  {} ({})  {}  {exit}, {:.1}s",
+                    locale.format_ms(run.started_at_ms),
+                    run.job_name,
+                    run.job_id,
+                    run.status,
+                    run.duration_ms as f64 / 1000.0
+                );
+                if let Some(error) = &run.error {
+                    println!("  error: {error}");
+                }
+                for (label, text) in [("stdout", &run.stdout), ("stderr", &run.stderr)] {
+                    let text = text.trim_end();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let lines: Vec<&str> = text.lines().collect();
+                    let shown = if full {
+                        &lines[..]
+                    } else {
+                        &lines[lines.len().saturating_sub(5)..]
+                    };
+                    println!("  {label}:");
+                    if shown.len() < lines.len() {
+                        println!("    ... ({} earlier lines)", lines.len() - shown.len());
+                    }
+                    for line in shown {
+                        println!("    {line}");
+                    }
+                }
+            }
+        }
+        CronCommands::Export { output } => {
+            let file = cron_service.export_jobs();
+            let yaml = to_yaml(
+                "patina cron jobs. Load with `patina cron import <file>`.",
+                &file,
+            )?;
+            write_export(&yaml, output.as_deref(), file.jobs.len(), "jobs")?;
+        }
+        CronCommands::Import { file, replace } => {
+            let text = std::fs::read_to_string(&file)?;
+            let imported = cron_service.import_jobs(from_yaml(&text)?, replace)?;
+            println!("Imported {}: {imported}", file.display());
+        }
     }
 
     let _ = config; // suppress unused warning
     Ok(())
 }
 
+/// Handle task board CLI subcommands.
+fn run_task_command(action: TaskCommands) -> Result<()> {
+    let mut tasks = TaskManager::load(&data_dir().join("tasks.json"));
+    match action {
+        TaskCommands::Export { output } => {
+            let store = tasks.export();
+            let yaml = to_yaml(
+                "patina task board. Load with `patina tasks import <file>`.",
+                &store,
+            )?;
+            write_export(&yaml, output.as_deref(), store.tasks.len(), "tasks")?;
+        }
+        TaskCommands::Import { file, replace } => {
+            let text = std::fs::read_to_string(&file)?;
+            let imported = tasks.import(from_yaml(&text)?, replace)?;
+            println!("Imported {}: {imported}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Print `yaml`, or write it to `output` and say how many `what` it holds.
+fn write_export(yaml: &str, output: Option<&Path>, count: usize, what: &str) -> Result<()> {
+    match output {
+        Some(path) => {
+            std::fs::write(path, yaml)?;
+            println!("Exported {count} {what} to {}", path.display());
+        }
+        None => print!("{yaml}"),
+    }
+    Ok(())
+}
+
+/// Handle session CLI subcommands.
+/// Replay a recorded session through a freshly built agent loop. Models and
+/// tools are replaced by the recording, and history goes to a scratch
+/// sessions directory, so nothing is sent or changed. Fails if any turn
+/// diverges from the recording.
+async fn run_eval(
+    config: &patina_config::Config,
+    file: &Path,
+    tiers: Vec<String>,
+    personas: Vec<String>,
+) -> Result<()> {
+    let mut suite = EvalSuite::load(file)?;
+    if !tiers.is_empty() {
+        suite.tiers = tiers;
+    }
+    if !personas.is_empty() {
+        suite.personas = personas;
+    }
+
+    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let Agent {
+        mut agent_loop,
+        personas: persona_store,
+        ..
+    } = AgentBuilder::new(config.clone())
+        .with_workspace(&workspace)
+        .build()?;
+    // Keep eval sessions out of the real history and memory index; usage
+    // is still recorded, since the calls are real
+    let run_id = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let scratch = std::env::temp_dir().join(format!("patina-eval-{}", std::process::id()));
+    agent_loop.sessions = SessionManager::new(scratch.clone());
+    agent_loop.memory_index = None;
+
+    let runs = suite.cases.len() * suite.personas.len() * suite.tiers.len().max(1);
+    println!(
+        "Running '{}': {} case(s), {runs} run(s)\n",
+        suite.name,
+        suite.cases.len()
+    );
+    let personas = persona_store.lock().await;
+    let results = eval::run_suite(
+        &mut agent_loop,
+        &suite,
+        &personas,
+        &config.agents.pricing,
+        &run_id,
+    )
+    .await;
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    println!("{}", eval::render_report(&results));
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        anyhow::bail!("{failed} run(s) failed");
+    }
+    Ok(())
+}
+
+/// `patina batch`: answer every prompt in `input`, `concurrency` at a time,
+/// writing each result as a JSON line as soon as it's done.
+async fn run_batch(
+    config: &patina_config::Config,
+    input: &Path,
+    output: Option<&Path>,
+    tier: Option<String>,
+    persona: Option<String>,
+    concurrency: usize,
+    no_tools: bool,
+) -> Result<()> {
+    use std::io::Write;
+
+    let text = std::fs::read_to_string(input)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", input.display()))?;
+    let items = batch::parse_items(&text)?;
+
+    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let Agent {
+        agent_loop,
+        personas: persona_store,
+        subagents,
+        ..
+    } = AgentBuilder::new(config.clone())
+        .with_workspace(&workspace)
+        .build()?;
+    if let Some(tier) = &tier {
+        let tiers = agent_loop.models.tiers();
+        if !tiers.contains(&tier.as_str()) {
+            anyhow::bail!(
+                "Unknown model tier '{tier}'. Available: {}",
+                tiers.join(", ")
+            );
+        }
+    }
+    let personas = persona_store.lock().await;
+    if let Some(persona) = persona.as_deref() {
+        if persona != eval::DEFAULT_PERSONA && personas.get(persona).is_none() {
+            anyhow::bail!("Unknown persona '{persona}'");
+        }
+    }
+
+    // Like eval, keep batch sessions out of the real history; usage is
+    // still recorded
+    let run_id = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let scratch = std::env::temp_dir().join(format!("patina-batch-{}", std::process::id()));
+    let concurrency = concurrency.max(1);
+    let workers = (0..concurrency)
+        .map(|_| subagents.batch_loop(scratch.clone(), !no_tools))
+        .collect();
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path)
+                .map_err(|e| anyhow::anyhow!("Failed to create {}: {e}", path.display()))?,
+        )),
+        None => Box::new(std::io::stdout()),
+    };
+    let writer = std::sync::Mutex::new(writer);
+    let total = items.len();
+    eprintln!("Running {total} prompt(s), {concurrency} at a time");
+    let options = BatchOptions {
+        tier: tier.as_deref(),
+        persona: persona.as_deref(),
+        run_id: &run_id,
+    };
+    let results = batch::run_batch(
+        workers,
+        items,
+        options,
+        &personas,
+        &config.agents.pricing,
+        |result| {
+            let line = serde_json::to_string(result).unwrap_or_default();
+            let mut writer = writer.lock().unwrap();
+            if let Err(e) = writeln!(writer, "{line}").and_then(|_| writer.flush()) {
+                tracing::warn!("Failed to write the result for line {}: {e}", result.line);
+            }
+        },
+    )
+    .await;
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let tokens: u64 = results.iter().map(|r| r.tokens).sum();
+    let cost = match results.iter().filter_map(|r| r.cost).reduce(|a, b| a + b) {
+        Some(cost) => format!(", ${cost:.4}"),
+        None => String::new(),
+    };
+    eprintln!(
+        "{} of {total} answered, {failed} failed, {tokens} tokens{cost}",
+        total - failed
+    );
+    if failed > 0 {
+        anyhow::bail!("{failed} prompt(s) failed");
+    }
+    Ok(())
+}
+
+async fn run_context(config: &patina_config::Config, session_key: &str) -> Result<()> {
+    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let Agent {
+        mut agent_loop,
+        personas,
+        ..
+    } = AgentBuilder::new(config.clone())
+        .with_workspace(&workspace)
+        .build()?;
+    let personas = personas.lock().await;
+    let report = profile::profile(&mut agent_loop, session_key, &personas)?;
+    println!("{}", profile::render_report(&report));
+    Ok(())
+}
+
+/// Handle memory CLI subcommands.
+async fn run_memory_command(config: &patina_config::Config, action: MemoryCommands) -> Result<()> {
+    match action {
+        MemoryCommands::Consolidate {
+            session,
+            dry_run,
+            all,
+        } => {
+            let workspace = resolve_workspace(&config.agents.defaults.workspace);
+            let Agent { mut agent_loop, .. } = AgentBuilder::new(config.clone())
+                .with_workspace(&workspace)
+                .build()?;
+            if agent_loop.sessions.get_existing(&session)?.is_none() {
+                anyhow::bail!("Session '{session}' not found");
+            }
+            let Some(task) = agent_loop.prepare_consolidation(&session, all) else {
+                println!(
+                    "Nothing to consolidate in '{session}'.{}",
+                    if all {
+                        ""
+                    } else {
+                        " Add --all to include the recent messages."
+                    }
+                );
+                return Ok(());
+            };
+            let tracker = agent_loop.usage_tracker.clone();
+
+            if dry_run {
+                let proposal =
+                    AgentLoop::propose_consolidation(&agent_loop.models, &task, tracker.as_ref())
+                        .await?;
+                println!(
+                    "Dry run: {} messages from '{session}'. Nothing was written.{}\n",
+                    task.messages,
+                    if task.approvals.is_some() {
+                        " On this channel the update would wait for approval."
+                    } else {
+                        ""
+                    }
+                );
+                println!(
+                    "{}",
+                    memory_approvals::preview(
+                        proposal.history_entry.as_deref(),
+                        &task.current_memory,
+                        proposal.memory_update.as_deref(),
+                        usize::MAX,
+                    )
+                );
+                return Ok(());
+            }
+
+            let result =
+                AgentLoop::run_consolidation(&agent_loop.models, &task, tracker.as_ref()).await?;
+            agent_loop.apply_consolidation(&result);
+            match &result.held {
+                Some(held) => println!(
+                    "Consolidated {} messages from '{session}'. The update is waiting for \
+                     approval as {}; review it with /memory.",
+                    result.messages, held.id
+                ),
+                None => {
+                    println!(
+                        "Consolidated {} messages from '{session}'.",
+                        result.messages
+                    );
+                    for fact in &result.new_facts {
+                        println!("+ {fact}");
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Save an export's conversations as sessions, skipping ones imported
+/// before, then optionally consolidate them. Consolidation skips whatever an
+/// earlier run already covered, so after a failure it can be run again.
+async fn run_import(config: &patina_config::Config, from: &Path, consolidate: bool) -> Result<()> {
+    let conversations = import::read_export(from)?;
+    let mut sessions = SessionManager::new(data_dir().join("sessions"));
+    let mut keys = Vec::with_capacity(conversations.len());
+    let (mut imported, mut messages) = (0, 0);
+    for conversation in conversations {
+        let key = conversation.session_key();
+        keys.push(key.clone());
+        if sessions.load(&key)?.is_some() {
+            continue;
+        }
+        messages += conversation.messages.len();
+        *sessions.get_or_create(&key) = conversation.into_session();
+        sessions.save(&key)?;
+        imported += 1;
+    }
+    println!(
+        "Imported {imported} conversation(s) ({messages} messages); {} already imported.",
+        keys.len() - imported
+    );
+    if !consolidate {
+        if imported > 0 {
+            println!("Run again with --consolidate to add them to long-term memory.");
+        }
+        return Ok(());
+    }
+
+    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let Agent { mut agent_loop, .. } = AgentBuilder::new(config.clone())
+        .with_workspace(&workspace)
+        .build()?;
+    let tracker = agent_loop.usage_tracker.clone();
+    for (i, key) in keys.iter().enumerate() {
+        if agent_loop.sessions.get_existing(key)?.is_none() {
+            continue;
+        }
+        let Some(task) = agent_loop.prepare_consolidation(key, true) else {
+            continue;
+        };
+        println!("[{}/{}] Consolidating '{key}'", i + 1, keys.len());
+        let result = AgentLoop::run_consolidation(&agent_loop.models, &task, tracker.as_ref())
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Consolidating '{key}' failed: {e}. Run the import again with --consolidate to continue."
+                )
+            })?;
+        agent_loop.apply_consolidation(&result);
+        if let Some(held) = &result.held {
+            println!("  The update is waiting for approval as {}", held.id);
+        }
+        for fact in &result.new_facts {
+            println!("  + {fact}");
+        }
+    }
+    Ok(())
+}
+
+async fn run_replay(
+    config: &patina_config::Config,
+    file: &Path,
+    session: Option<&str>,
+) -> Result<()> {
+    let recording = Recording::load(file)?;
+    let session_key = session
+        .map(str::to_string)
+        .or_else(|| {
+            recording
+                .turns
+                .first()
+                .map(|t| t.session_key.clone())
+                .filter(|k| !k.is_empty())
+        })
+        .unwrap_or_else(|| "replay:session".to_string());
+
+    let workspace = resolve_workspace(&config.agents.defaults.workspace);
+    let Agent { mut agent_loop, .. } = AgentBuilder::new(config.clone())
+        .with_workspace(&workspace)
+        .build()?;
+    let scratch = std::env::temp_dir().join(format!("patina-replay-{}", std::process::id()));
+    agent_loop.sessions = SessionManager::new(scratch.clone());
+    agent_loop.memory_index = None;
+    agent_loop.usage_tracker = None;
+    agent_loop.session_log = None;
+
+    println!(
+        "Replaying {} turn(s) from {} as {session_key}\n",
+        recording.turns.len(),
+        file.display()
+    );
+    let reports = replay::replay(&mut agent_loop, &recording, &session_key).await;
+    let _ = std::fs::remove_dir_all(&scratch);
+
+    println!("{}", replay::render_report(&reports));
+    let diverged = reports.iter().filter(|r| !r.passed()).count();
+    if diverged > 0 {
+        anyhow::bail!("{diverged} turn(s) diverged from the recording");
+    }
+    Ok(())
+}
+
+/// Open the bus journal if `gateway.journal` is enabled, dropping finished
+/// entries past their retention.
+fn open_bus_journal(config: &patina_config::Config) -> Option<Arc<BusJournal>> {
+    let cfg = &config.gateway.journal;
+    if !cfg.enabled {
+        return None;
+    }
+    let path = match cfg.path {
+        Some(ref p) => resolve_workspace(p),
+        None => data_dir().join("bus.db"),
+    };
+    match BusJournal::open(&path) {
+        Ok(journal) => {
+            if let Err(e) = journal.prune(chrono::Duration::hours(cfg.retain_hours as i64)) {
+                tracing::warn!("Failed to prune bus journal: {e}");
+            }
+            tracing::info!("Bus journal at {}", path.display());
+            Some(Arc::new(journal))
+        }
+        Err(e) => {
+            tracing::warn!("Bus journal disabled, can't open {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Link signer for the web channel. Watch links for existing chats always
+/// work; guest chats also need `guest_chats_enabled`.
+fn guest_links(config: &patina_config::Config) -> Option<GuestLinks> {
+    let web = &config.channels.web;
+    if !web.enabled {
+        return None;
+    }
+    if web.guest_links.enabled && web.password.is_empty() {
+        tracing::warn!("channels.web.guestLinks needs channels.web.password, ignoring it");
+    }
+    match GuestLinks::from_config(&web.guest_links) {
+        Ok(links) => Some(links),
+        Err(e) => {
+            tracing::warn!("Guest and share links disabled: {e}");
+            None
+        }
+    }
+}
+
+/// List what the watchdog finds. With `alert`, report a dead gateway
+/// through a `notify` or `webhook` target, which work without it.
+async fn run_watchdog(config: &patina_config::Config, alert: bool) -> Result<()> {
+    use patina_channels::base::Channel;
+
+    let locale = Locales::new(&config.user, &config.users).for_user(None);
+    let watchdog = Watchdog::new(
+        config.watchdog.clone(),
+        &config.heartbeat,
+        &data_dir(),
+        locale,
+    );
+    if !config.watchdog.enabled {
+        println!("The watchdog is off (watchdog.enabled), so the gateway doesn't check in.");
+    }
+    let now_ms = chrono::Utc::now().timestamp_millis();
+
+    if alert {
+        let Some((channel, to)) = watchdog.target() else {
+            anyhow::bail!("Set watchdog.channel and watchdog.to to send alerts");
+        };
+        let sink: Box<dyn Channel> = match channel {
+            "notify" => Box::new(NotifyChannel::new(config.channels.notify.clone())?),
+            "webhook" => Box::new(WebhookChannel::new(config.channels.webhook.clone())?),
+            other => anyhow::bail!(
+                "Without the gateway, alerts can only go to notify or webhook, not {other}"
+            ),
+        };
+        for content in watchdog.check_gateway(now_ms) {
+            println!("Sending: {content}");
+            sink.send(&OutboundMessage {
+                channel: channel.to_string(),
+                chat_id: to.to_string(),
+                content,
+                reply_to: None,
+                metadata: HashMap::new(),
+            })
+            .await?;
+        }
+    }
+
+    let problems = watchdog.problems(now_ms);
+    if problems.is_empty() {
+        println!("Everything is running.");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("- {}", problem.message);
+    }
+    anyhow::bail!("{} problem(s) found", problems.len());
+}
+
+fn guest_chats_enabled(config: &patina_config::Config) -> bool {
+    let web = &config.channels.web;
+    web.enabled && web.guest_links.enabled && !web.password.is_empty()
+}
+
+fn run_share(
+    config: &patina_config::Config,
+    chat: Option<&str>,
+    ttl: Option<u64>,
+    label: &str,
+    url: Option<&str>,
+) -> Result<()> {
+    let web = &config.channels.web;
+    if chat.is_none() && !web.guest_links.enabled {
+        anyhow::bail!("Guest links are off. Set channels.web.guestLinks.enabled in your config.");
+    }
+    if chat.is_none() && web.password.is_empty() {
+        anyhow::bail!("Guest links need channels.web.password to be set.");
+    }
+    let ttl = link_ttl(ttl.unwrap_or(web.guest_links.ttl_mins))?;
+    let links = GuestLinks::from_config(&web.guest_links)?;
+    let token = match chat {
+        // Accept a session key ("web:<id>") as well as a bare chat id
+        Some(chat) => links.issue_view(chat.strip_prefix("web:").unwrap_or(chat), label, ttl),
+        None => links.issue(label, ttl),
+    };
+    let base = url
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("http://{}:{}", config.gateway.host, config.gateway.port));
+    println!("{}/?guest={token}", base.trim_end_matches('/'));
+    println!(
+        "Expires {}",
+        (chrono::Local::now() + ttl).format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+fn run_session_command(action: SessionCommands) -> Result<()> {
+    match action {
+        SessionCommands::Export {
+            key,
+            format,
+            output,
+        } => {
+            let format: ExportFormat = format.parse()?;
+            let sessions_dir = data_dir().join("sessions");
+            let sessions = SessionManager::new(sessions_dir);
+            let Some(session) = sessions.load(&key)? else {
+                anyhow::bail!("Session '{key}' not found");
+            };
+            let rendered = export_session(&session, format);
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, rendered)?;
+                    println!(
+                        "Exported {} messages to {}",
+                        session.messages.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{rendered}"),
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Handle channel CLI subcommands.
-fn run_channel_command(action: ChannelCommands, config: &patina_config::Config) -> Result<()> {
+async fn run_channel_command(
+    action: ChannelCommands,
+    config: &patina_config::Config,
+) -> Result<()> {
     match action {
         ChannelCommands::Status => {
             println!("Channels:");
@@ -2065,6 +4130,127 @@ fn run_channel_command(action: ChannelCommands, config: &patina_config::Config)
                 }
             }
 
+            // Teams
+            let teams = &config.channels.teams;
+            println!();
+            println!("  Teams:");
+            println!("    Enabled: {}", teams.enabled);
+            if teams.enabled {
+                let endpoint = format!(
+                    "{}:{}/api/messages",
+                    teams.listen.as_deref().unwrap_or("0.0.0.0"),
+                    teams.port.unwrap_or(3978)
+                );
+                match teams.mode {
+                    patina_config::TeamsMode::Bot => {
+                        let app_display = if teams.app_id.is_empty() {
+                            "(not set)"
+                        } else {
+                            teams.app_id.as_str()
+                        };
+                        println!("    Mode:     bot");
+                        println!("    App ID:   {app_display}");
+                    }
+                    patina_config::TeamsMode::OutgoingWebhook => {
+                        println!("    Mode:     outgoing webhook");
+                        println!(
+                            "    Late replies: {}",
+                            if teams.incoming_webhook_url.is_some() {
+                                "incoming webhook"
+                            } else {
+                                "dropped (no incomingWebhookUrl)"
+                            }
+                        );
+                    }
+                }
+                println!("    Endpoint: {endpoint}");
+                if teams.allow_from.is_empty() {
+                    println!("    Access:   open (no allowFrom configured)");
+                } else {
+                    println!(
+                        "    Access:   restricted to {} user(s)",
+                        teams.allow_from.len()
+                    );
+                }
+            }
+
+            // WhatsApp
+            let wa = &config.channels.whatsapp;
+            println!();
+            println!("  WhatsApp:");
+            println!("    Enabled: {}", wa.enabled);
+            if wa.enabled {
+                let number_display = if wa.phone_number_id.is_empty() {
+                    "(not set)"
+                } else {
+                    wa.phone_number_id.as_str()
+                };
+                println!("    Phone number ID: {number_display}");
+                println!(
+                    "    Webhook:  {}:{}/whatsapp",
+                    wa.listen.as_deref().unwrap_or("0.0.0.0"),
+                    wa.port.unwrap_or(3979)
+                );
+                match wa.window_template {
+                    Some(ref t) => {
+                        println!("    Template: {} (outside the 24-hour window)", t.name)
+                    }
+                    None => println!("    Template: (none; replies after 24 hours fail)"),
+                }
+                if wa.allow_from.is_empty() {
+                    println!("    Access:   open (no allowFrom configured)");
+                } else {
+                    println!(
+                        "    Access:   restricted to {} number(s)",
+                        wa.allow_from.len()
+                    );
+                }
+            }
+
+            // Notify
+            let notify = &config.channels.notify;
+            println!();
+            println!("  Notify:");
+            println!("    Enabled: {}", notify.enabled);
+            if notify.enabled {
+                let mut names: Vec<&String> = notify.targets.keys().collect();
+                names.sort();
+                if names.is_empty() {
+                    println!("    Targets: (none configured)");
+                }
+                for name in names {
+                    let service = match notify.targets[name].service {
+                        patina_config::NotifyService::Ntfy => "ntfy",
+                        patina_config::NotifyService::Pushover => "Pushover",
+                        patina_config::NotifyService::Gotify => "Gotify",
+                        patina_config::NotifyService::Webhook => "webhook",
+                    };
+                    println!("    Target:  {name} ({service})");
+                }
+            }
+
+            // Webhook
+            let webhook = &config.channels.webhook;
+            println!();
+            println!("  Webhook:");
+            println!("    Enabled: {}", webhook.enabled);
+            if webhook.enabled {
+                let mut names: Vec<&String> = webhook.targets.keys().collect();
+                names.sort();
+                if names.is_empty() {
+                    println!("    Targets: (none configured)");
+                }
+                for name in names {
+                    let target = &webhook.targets[name];
+                    let signed = if target.secret.as_deref().is_some_and(|s| !s.is_empty()) {
+                        "signed"
+                    } else {
+                        "unsigned"
+                    };
+                    println!("    Target:  {name} ({}, {signed})", target.url);
+                }
+            }
+
             // Web
             let web = &config.channels.web;
             println!();
@@ -2085,7 +4271,26 @@ fn run_channel_command(action: ChannelCommands, config: &patina_config::Config)
                 );
             }
         }
+        ChannelCommands::Restart { name } => {
+            send_control(ControlRequest::RestartChannel { channel: name }).await?;
+        }
+        ChannelCommands::Disable { name } => {
+            send_control(ControlRequest::DisableChannel { channel: name }).await?;
+        }
+        ChannelCommands::Enable { name } => {
+            send_control(ControlRequest::EnableChannel { channel: name }).await?;
+        }
     }
 
     Ok(())
 }
+
+/// Send a request to the running gateway's control socket and print the reply.
+async fn send_control(request: ControlRequest) -> Result<()> {
+    let reply = control::request(&control::socket_path(), &request).await?;
+    if !reply.ok {
+        anyhow::bail!("{}", reply.message);
+    }
+    println!("{}", reply.message);
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+/// Loading or saving the config file failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config '{}'", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The file isn't valid JSON or doesn't match the schema; the source
+    /// has the line and column.
+    #[error("failed to parse config '{}'", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("failed to write config '{}'", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -1,8 +1,20 @@
+pub mod error;
 pub mod loader;
 pub mod schema;
 
-pub use loader::{find_config_path, load_config, resolve_workspace, save_config};
+pub use error::ConfigError;
+pub use loader::{data_dir, find_config_path, load_config, resolve_workspace, save_config};
 pub use schema::{
-    Config, GatewayConfig, HeartbeatConfig, ModelRef, ProviderConfig, SlackConfig, TelegramConfig,
-    TelegramMode, TranscriptionConfig, TranscriptionMode, WebConfig,
+    AskUserConfig, CitationsConfig, Config, DriveSourceConfig, EmbeddingConfig, ExecToolConfig,
+    FsyncPolicy, GatewayConfig, GeofenceConfig, GeofenceTransition, GuestLinksConfig,
+    HeartbeatConfig, JournalConfig, KnowledgeConfig, LanguageConfig, LlamaCppConfig,
+    LocationConfig, LogFormat, LoggingConfig, MemoryAccess, MessageToolConfig, ModelRef,
+    ModerationAction, ModerationConfig, ModerationDirection, ModerationProviderConfig,
+    ModerationRule, NotifyConfig, NotifyService, NotifyTarget, NotionSourceConfig, ObsidianConfig,
+    OutboundConfig, OutboundQueueOverride, OverflowPolicy, PlaceConfig, ProviderConfig, Role,
+    RolePolicy, RolesConfig, SessionsConfig, SlackConfig, StandupConfig, TaskPickupConfig,
+    TasksConfig, TeamsConfig, TeamsMode, TelegramConfig, TelegramMode, TelemetryConfig,
+    TranscribeToolConfig, TranscriptPostProcessConfig, TranscriptionConfig, TranscriptionMode,
+    UserConfig, UserPrefsConfig, VoiceInputConfig, WakeWordConfig, WatchdogConfig, WebConfig,
+    WebhookConfig, WebhookTarget, WhatsAppConfig, WhatsAppTemplate,
 };
@@ -1,11 +1,25 @@
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use crate::{Config, ConfigError};
 
-use crate::Config;
+/// Where patina keeps its state: `$PATINA_HOME`, or ~/.patina.
+pub fn data_dir() -> PathBuf {
+    match std::env::var_os("PATINA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".patina"),
+    }
+}
 
-/// Resolve workspace path, expanding ~ to home directory.
+/// Resolve workspace path, expanding ~ to home directory. Paths under
+/// ~/.patina follow [`data_dir`], so the default workspace moves with it.
 pub fn resolve_workspace(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/.patina") {
+        if rest.is_empty() || rest.starts_with('/') {
+            return data_dir().join(rest.trim_start_matches('/'));
+        }
+    }
     if path.starts_with("~/") || path == "~" {
         if let Some(home) = dirs::home_dir() {
             return home.join(path.strip_prefix("~/").unwrap_or(""));
@@ -22,45 +36,56 @@ pub fn find_config_path() -> PathBuf {
         return local.to_path_buf();
     }
 
-    // 2. ~/.patina/config.json
-    if let Some(home) = dirs::home_dir() {
-        let home_config = home.join(".patina").join("config.json");
-        if home_config.exists() {
-            return home_config;
-        }
-    }
-
-    // Default: ~/.patina/config.json (will use defaults if missing)
-    dirs::home_dir()
-        .map(|h| h.join(".patina").join("config.json"))
-        .unwrap_or_else(|| PathBuf::from("config.json"))
+    // 2. The data dir; used even if missing (defaults apply)
+    data_dir().join("config.json")
 }
 
 /// Load configuration from a JSON file.
-pub fn load_config(path: &Path) -> Result<Config> {
+pub fn load_config(path: &Path) -> Result<Config, ConfigError> {
     if path.exists() {
-        let contents = std::fs::read_to_string(path)
-            .with_context(|| format!("failed to read config '{}'", path.display()))?;
-        let config: Config = serde_json::from_str(&contents)
-            .with_context(|| format!("failed to parse config '{}'", path.display()))?;
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut config: Config =
+            serde_json::from_str(&contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        apply_env(&mut config);
         Ok(config)
     } else {
-        Ok(Config::default())
+        let mut config = Config::default();
+        apply_env(&mut config);
+        Ok(config)
+    }
+}
+
+/// Let `HOST` and `PORT` override the gateway address, as container
+/// platforms expect. `HOST` only counts if it is an IP address, since some
+/// shells set it to the machine name.
+fn apply_env(config: &mut Config) {
+    if let Ok(host) = std::env::var("HOST") {
+        if host.parse::<std::net::IpAddr>().is_ok() {
+            config.gateway.host = host;
+        }
+    }
+    if let Some(port) = std::env::var("PORT").ok().and_then(|p| p.parse().ok()) {
+        config.gateway.port = port;
     }
 }
 
 /// Save configuration to a JSON file.
-pub fn save_config(path: &Path, config: &Config) -> Result<()> {
-    let contents = serde_json::to_string_pretty(config)?;
+pub fn save_config(path: &Path, config: &Config) -> Result<(), ConfigError> {
+    let write_error = |source| ConfigError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    let contents =
+        serde_json::to_string_pretty(config).map_err(|e| write_error(std::io::Error::other(e)))?;
     if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).with_context(|| {
-            format!(
-                "failed to create config directory '{}'",
-                parent.to_string_lossy()
-            )
-        })?;
+        std::fs::create_dir_all(parent).map_err(write_error)?;
     }
-    std::fs::write(path, contents)
-        .with_context(|| format!("failed to write config '{}'", path.display()))?;
+    std::fs::write(path, contents).map_err(write_error)?;
     Ok(())
 }
@@ -0,0 +1,143 @@
+//! Role-based tool access, enforced as [`Middleware`].
+//!
+//! The gateway resolves each message's sender with
+//! [`UserDirectory::resolve`](crate::users::UserDirectory::resolve) and calls
+//! [`AccessControl::begin_turn`] before running the turn. Tools the role may
+//! not use are removed from the request and refused if called anyway.
+//! Sessions without a registered turn (subagents, internal jobs) are
+//! unrestricted.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use patina_config::RolePolicy;
+use rig::completion::CompletionRequest;
+
+use crate::agent::middleware::{HookContext, Middleware, ToolDecision};
+use crate::users::{Identity, UserDirectory};
+
+pub struct AccessControl {
+    directory: Arc<UserDirectory>,
+    turns: Mutex<HashMap<String, Identity>>,
+}
+
+impl AccessControl {
+    pub fn new(directory: Arc<UserDirectory>) -> Self {
+        Self {
+            directory,
+            turns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record who the next turn in `session_key` is for.
+    pub fn begin_turn(&self, session_key: &str, identity: Identity) {
+        self.lock().insert(session_key.to_string(), identity);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Identity>> {
+        match self.turns.lock() {
+            Ok(g) => g,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    fn policy_for(&self, session_key: &str) -> Option<(Identity, RolePolicy)> {
+        let identity = self.lock().get(session_key).cloned()?;
+        let policy = self.directory.policy(identity.role).clone();
+        Some((identity, policy))
+    }
+}
+
+#[async_trait]
+impl Middleware for AccessControl {
+    async fn before_completion(
+        &self,
+        ctx: &HookContext<'_>,
+        request: &mut CompletionRequest,
+    ) -> Result<()> {
+        if let Some((_, policy)) = self.policy_for(ctx.session_key) {
+            request.tools.retain(|t| policy.allows_tool(&t.name));
+        }
+        Ok(())
+    }
+
+    async fn before_tool(
+        &self,
+        ctx: &HookContext<'_>,
+        tool: &str,
+        _args: &mut serde_json::Value,
+    ) -> Result<ToolDecision> {
+        match self.policy_for(ctx.session_key) {
+            Some((identity, policy)) if !policy.allows_tool(tool) => {
+                tracing::warn!(
+                    "Refused {tool} for {} ({:?}) in '{}'",
+                    identity.display_name(),
+                    identity.role,
+                    ctx.session_key
+                );
+                Ok(ToolDecision::Skip(format!(
+                    "Error: the {tool} tool is not available to this user"
+                )))
+            }
+            _ => Ok(ToolDecision::Run),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use patina_config::{Role, RolesConfig, UserConfig};
+
+    fn ctx(session_key: &str) -> HookContext<'_> {
+        HookContext {
+            session_key,
+            agent: "default",
+            model: "m",
+            provider: "p",
+            iteration: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tools_refused_by_role() {
+        let users = HashMap::from([(
+            "pal".to_string(),
+            UserConfig {
+                role: Role::Member,
+                identities: vec!["telegram:42".into()],
+                ..Default::default()
+            },
+        )]);
+        let directory = Arc::new(UserDirectory::new(&users, &RolesConfig::default()));
+        let access = AccessControl::new(directory);
+        access.begin_turn(
+            "telegram:42",
+            Identity {
+                user: Some("pal".into()),
+                role: Role::Member,
+                key: "pal".into(),
+            },
+        );
+
+        let mut args = serde_json::json!({});
+        let decision = access
+            .before_tool(&ctx("telegram:42"), "exec", &mut args)
+            .await
+            .unwrap();
+        assert!(matches!(decision, ToolDecision::Skip(ref r) if r.starts_with("Error:")));
+        let decision = access
+            .before_tool(&ctx("telegram:42"), "web_search", &mut args)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+        // No registered turn: unrestricted
+        let decision = access
+            .before_tool(&ctx("subagent:1"), "exec", &mut args)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+    }
+}
@@ -0,0 +1,318 @@
+//! Run a file of prompts through the agent, for periodic jobs like
+//! classifying or summarizing on the same models, usage tracking, and
+//! middleware as chat.
+//!
+//! The input is JSON Lines with one prompt per line, either a JSON string or
+//! an object where only `prompt` is required:
+//!
+//! ```json
+//! "Summarize: ..."
+//! {"id": "ticket-41", "prompt": "Classify: ...", "tier": "fast", "persona": "analyst"}
+//! ```
+//!
+//! [`run_batch`] hands the prompts out to its workers in order, each answered
+//! in a fresh session, and reports every [`BatchResult`] as it finishes, with
+//! tokens and estimated cost from the usage tracker.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use patina_config::schema::ModelPricing;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::agent::eval::{session_usage, DEFAULT_PERSONA};
+use crate::agent::r#loop::AgentLoop;
+use crate::persona::PersonaStore;
+
+/// One prompt from the input file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    /// Line number in the input, from 1.
+    pub line: usize,
+    /// The item's `id`, or its line number.
+    pub id: Value,
+    pub prompt: String,
+    pub tier: Option<String>,
+    pub persona: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawItem {
+    id: Option<Value>,
+    prompt: String,
+    tier: Option<String>,
+    persona: Option<String>,
+}
+
+/// Parse JSON Lines input, skipping blank lines.
+pub fn parse_items(text: &str) -> Result<Vec<BatchItem>> {
+    let mut items = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let value: Value =
+            serde_json::from_str(raw).with_context(|| format!("Line {line}: invalid JSON"))?;
+        let item = match value {
+            Value::String(prompt) => RawItem {
+                id: None,
+                prompt,
+                tier: None,
+                persona: None,
+            },
+            Value::Object(_) => serde_json::from_value(value)
+                .with_context(|| format!("Line {line}: not a batch item"))?,
+            _ => anyhow::bail!("Line {line}: expected a prompt string or an object"),
+        };
+        if item.prompt.trim().is_empty() {
+            anyhow::bail!("Line {line}: empty prompt");
+        }
+        items.push(BatchItem {
+            line,
+            id: item.id.unwrap_or_else(|| Value::from(line)),
+            prompt: item.prompt,
+            tier: item.tier.filter(|t| !t.is_empty()),
+            persona: item.persona.filter(|p| !p.is_empty()),
+        });
+    }
+    if items.is_empty() {
+        anyhow::bail!("No prompts in the input");
+    }
+    Ok(items)
+}
+
+/// One line of output.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub id: Value,
+    pub line: usize,
+    pub tier: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    pub latency_ms: u64,
+}
+
+/// Defaults for items that don't set their own tier or persona.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchOptions<'a> {
+    /// Tier for items without one. Otherwise the persona's tier, or the
+    /// default tier.
+    pub tier: Option<&'a str>,
+    /// Persona key for items without one; `default` means no persona.
+    pub persona: Option<&'a str>,
+    /// Sessions are named `batch:<run_id>:<line>`, so the workers should use
+    /// a scratch session directory.
+    pub run_id: &'a str,
+}
+
+/// Answer every item, running as many at once as there are `workers`.
+/// `on_result` sees each result as it finishes; the returned results are in
+/// input order.
+pub async fn run_batch(
+    workers: Vec<AgentLoop>,
+    items: Vec<BatchItem>,
+    options: BatchOptions<'_>,
+    personas: &PersonaStore,
+    pricing: &HashMap<String, ModelPricing>,
+    on_result: impl Fn(&BatchResult),
+) -> Vec<BatchResult> {
+    let queue = Mutex::new(VecDeque::from(items));
+    let results = Mutex::new(Vec::new());
+    let work = workers.into_iter().map(|mut agent_loop| {
+        let (queue, results, on_result) = (&queue, &results, &on_result);
+        async move {
+            loop {
+                let Some(item) = queue.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let result = run_item(&mut agent_loop, &item, options, personas, pricing).await;
+                on_result(&result);
+                results.lock().unwrap().push(result);
+            }
+        }
+    });
+    futures::future::join_all(work).await;
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|r| r.line);
+    results
+}
+
+async fn run_item(
+    agent_loop: &mut AgentLoop,
+    item: &BatchItem,
+    options: BatchOptions<'_>,
+    personas: &PersonaStore,
+    pricing: &HashMap<String, ModelPricing>,
+) -> BatchResult {
+    let persona_key = item
+        .persona
+        .as_deref()
+        .or(options.persona)
+        .filter(|p| *p != DEFAULT_PERSONA);
+    let mut result = BatchResult {
+        id: item.id.clone(),
+        line: item.line,
+        persona: persona_key.map(String::from),
+        ..Default::default()
+    };
+    let (preamble, persona_tier) = match persona_key {
+        None => (None, None),
+        Some(key) => match personas.get(key) {
+            Some(p) => (
+                Some(p.preamble.clone()).filter(|s| !s.is_empty()),
+                Some(p.model_tier.clone()).filter(|s| !s.is_empty()),
+            ),
+            None => {
+                result.tier = "default".to_string();
+                result.error = Some(format!("persona '{key}' doesn't exist"));
+                return result;
+            }
+        },
+    };
+    let tier = item
+        .tier
+        .clone()
+        .or(options.tier.map(String::from))
+        .or(persona_tier);
+    result.tier = tier.clone().unwrap_or_else(|| "default".to_string());
+
+    let session_key = format!("batch:{}:{}", options.run_id, item.line);
+    let started = Instant::now();
+    match agent_loop
+        .process_message_with_persona(
+            &session_key,
+            &item.prompt,
+            None,
+            preamble.as_deref(),
+            tier.as_deref(),
+        )
+        .await
+    {
+        Ok((response, _)) => result.response = Some(response),
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result.latency_ms = started.elapsed().as_millis() as u64;
+    (result.tokens, result.cost) = session_usage(agent_loop, &session_key, pricing);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::r#loop::ModelOverrides;
+    use crate::agent::replay::{MockCompletionModel, RecordedResponse};
+    use crate::agent::ContextBuilder;
+    use crate::session::SessionManager;
+    use crate::tools::ToolRegistry;
+
+    #[test]
+    fn parses_strings_and_objects() {
+        let text = "\"Summarize this\"\n\n\
+                    {\"id\": \"t-1\", \"prompt\": \"Classify\", \"tier\": \"fast\"}\n\
+                    {\"prompt\": \"Translate\", \"persona\": \"\"}\n";
+        let items = parse_items(text).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].id, Value::from(1));
+        assert_eq!(items[0].prompt, "Summarize this");
+        assert_eq!(items[1].id, Value::from("t-1"));
+        assert_eq!(items[1].tier.as_deref(), Some("fast"));
+        assert_eq!((items[2].line, items[2].persona.clone()), (4, None));
+
+        let err = parse_items("\"ok\"\n{\"id\": 2}").unwrap_err();
+        assert!(err.to_string().starts_with("Line 2"));
+        assert!(parse_items("\n  \n").is_err());
+        assert!(parse_items("[1]").is_err());
+    }
+
+    #[tokio::test]
+    async fn runs_items_across_workers() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = MockCompletionModel::new([
+            RecordedResponse {
+                text: "ham".into(),
+                ..Default::default()
+            },
+            RecordedResponse {
+                text: "ham".into(),
+                ..Default::default()
+            },
+        ]);
+        let workers = (0..2)
+            .map(|_| AgentLoop {
+                models: model.clone().into_pool(),
+                sessions: SessionManager::new(dir.path().join("sessions")),
+                context: ContextBuilder::new(dir.path()),
+                tools: ToolRegistry::new(),
+                max_iterations: 5,
+                temperature: 0.7,
+                max_tokens: 1024,
+                memory_window: 20,
+                model_overrides: ModelOverrides::defaults(),
+                memory_index: None,
+                channel_rules: HashMap::new(),
+                usage_tracker: None,
+                session_log: None,
+                stream_tx: None,
+                middleware: Vec::new(),
+                memory_approvals: None,
+            })
+            .collect();
+        let items = parse_items(
+            "\"Spam or ham: lunch?\"\n\
+             {\"prompt\": \"Spam or ham: hi\", \"persona\": \"ghost\"}\n\
+             \"Spam or ham: dinner?\"",
+        )
+        .unwrap();
+        let personas = PersonaStore::load(&dir.path().join("personas.json"));
+        let seen = Mutex::new(0);
+        let options = BatchOptions {
+            run_id: "t",
+            ..Default::default()
+        };
+
+        let results = run_batch(workers, items, options, &personas, &HashMap::new(), |_| {
+            *seen.lock().unwrap() += 1
+        })
+        .await;
+        assert_eq!(*seen.lock().unwrap(), 3);
+        let lines: Vec<usize> = results.iter().map(|r| r.line).collect();
+        assert_eq!(lines, [1, 2, 3]);
+        assert_eq!(results[0].response.as_deref(), Some("ham"));
+        assert_eq!(
+            results[1].error.as_deref(),
+            Some("persona 'ghost' doesn't exist")
+        );
+        assert_eq!(results[2].response.as_deref(), Some("ham"));
+        assert_eq!(results[2].tier, "default");
+    }
+
+    #[test]
+    fn results_skip_empty_fields() {
+        let result = BatchResult {
+            id: Value::from("t-1"),
+            line: 2,
+            tier: "fast".to_string(),
+            response: Some("spam".to_string()),
+            tokens: 42,
+            latency_ms: 810,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"id":"t-1","line":2,"tier":"fast","response":"spam","tokens":42,"latencyMs":810}"#
+        );
+    }
+}
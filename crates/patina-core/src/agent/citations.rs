@@ -0,0 +1,347 @@
+//! Footnote citations for replies that used retrieved context.
+//!
+//! [`Citations`] is registered as middleware on the main loop. It reads the
+//! results of `memory_search`, `knowledge_search`, `web_search`,
+//! `web_fetch`, and `web_crawl` during a turn and, when the model gives its
+//! final reply, appends a numbered list of the sources behind it. Passages
+//! and fetched pages were read into the model's context, so they're listed;
+//! web search hits are only listed when the reply links them or the model
+//! went on to fetch them, since most of a results page goes unused.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use patina_config::CitationsConfig;
+use rig::completion::CompletionRequest;
+use rig::message::ToolCall;
+
+use crate::agent::middleware::{HookContext, Middleware};
+
+/// Where a source came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    Memory,
+    Knowledge,
+    Web,
+}
+
+impl SourceKind {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Memory => "Memory",
+            Self::Knowledge => "Knowledge",
+            Self::Web => "Web",
+        }
+    }
+}
+
+/// One document, file, or page a turn retrieved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub kind: SourceKind,
+    /// File path relative to the workspace or knowledge folder, or a URL.
+    pub location: String,
+    /// Line ranges of the passages used, in the order they were retrieved.
+    pub lines: Vec<(usize, usize)>,
+    /// A search hit the model only saw a snippet of.
+    pub search_hit: bool,
+}
+
+impl Source {
+    fn new(kind: SourceKind, location: &str) -> Self {
+        Self {
+            kind,
+            location: location.to_string(),
+            lines: Vec::new(),
+            search_hit: false,
+        }
+    }
+
+    fn passage(kind: SourceKind, location: &str, start: usize, end: usize) -> Self {
+        Self {
+            lines: vec![(start, end)],
+            ..Self::new(kind, location)
+        }
+    }
+}
+
+/// The sources in a retrieval tool's result. Other tools, errors, and
+/// empty results give none.
+pub fn sources_in(tool: &str, result: &str) -> Vec<Source> {
+    match tool {
+        "memory_search" => result
+            .lines()
+            .filter_map(|line| line.strip_prefix("**File:** "))
+            .filter_map(|rest| {
+                let (path, start, end) = split_lines(rest, ")")?;
+                Some(Source::passage(SourceKind::Memory, path, start, end))
+            })
+            .collect(),
+        "knowledge_search" => result
+            .lines()
+            .filter_map(|line| {
+                let rest = line.strip_prefix('[')?;
+                let (n, rest) = rest.split_once("] ")?;
+                n.parse::<usize>().ok()?;
+                let (source, start, end) = split_lines(rest, ", score:")?;
+                Some(Source::passage(SourceKind::Knowledge, source, start, end))
+            })
+            .collect(),
+        "web_search" => result
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+            .map(|url| Source {
+                search_hit: true,
+                ..Source::new(SourceKind::Web, url)
+            })
+            .collect(),
+        "web_fetch" => {
+            let Ok(json) = serde_json::from_str::<serde_json::Value>(result) else {
+                return Vec::new();
+            };
+            if json.get("error").is_some() {
+                return Vec::new();
+            }
+            json.get("finalUrl")
+                .or_else(|| json.get("url"))
+                .and_then(|u| u.as_str())
+                .map(|url| vec![Source::new(SourceKind::Web, url)])
+                .unwrap_or_default()
+        }
+        "web_crawl" => serde_json::from_str::<serde_json::Value>(result)
+            .ok()
+            .and_then(|json| json.get("pages")?.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|page| page.get("url")?.as_str())
+            .map(|url| Source::new(SourceKind::Web, url))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Split `"<location> (lines <a>-<b><terminator>"`.
+fn split_lines<'a>(text: &'a str, terminator: &str) -> Option<(&'a str, usize, usize)> {
+    let (location, rest) = text.rsplit_once(" (lines ")?;
+    let (range, _) = rest.split_once(terminator)?;
+    let (start, end) = range.split_once('-')?;
+    Some((location, start.parse().ok()?, end.parse().ok()?))
+}
+
+/// The sources that informed `reply`, merged by location in the order they
+/// were first retrieved and capped at `max`.
+pub fn cited(retrieved: &[Source], reply: &str, max: usize) -> Vec<Source> {
+    let mut sources: Vec<Source> = Vec::new();
+    for source in retrieved {
+        match sources
+            .iter_mut()
+            .find(|s| s.kind == source.kind && s.location == source.location)
+        {
+            Some(existing) => {
+                for range in &source.lines {
+                    if !existing.lines.contains(range) {
+                        existing.lines.push(*range);
+                    }
+                }
+                existing.search_hit &= source.search_hit;
+            }
+            None => sources.push(source.clone()),
+        }
+    }
+    sources.retain(|s| !s.search_hit || reply.contains(s.location.as_str()));
+    sources.truncate(max);
+    sources
+}
+
+/// A footnote block listing `sources`, to append to a reply.
+pub fn footnotes(sources: &[Source]) -> String {
+    let mut out = String::from("\n\nSources:");
+    for (i, source) in sources.iter().enumerate() {
+        out.push_str(&format!(
+            "\n[{}] {}: {}",
+            i + 1,
+            source.kind.label(),
+            source.location
+        ));
+        if !source.lines.is_empty() {
+            let ranges: Vec<String> = source
+                .lines
+                .iter()
+                .map(|(start, end)| format!("{start}-{end}"))
+                .collect();
+            out.push_str(&format!(", lines {}", ranges.join(", ")));
+        }
+    }
+    out
+}
+
+/// Middleware that appends source footnotes to replies on the configured
+/// channels.
+pub struct Citations {
+    config: CitationsConfig,
+    turns: Mutex<HashMap<String, Vec<Source>>>,
+}
+
+impl Citations {
+    pub fn new(config: &CitationsConfig) -> Self {
+        Self {
+            config: config.clone(),
+            turns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether replies in `session_key` ("channel:chat_id") get footnotes.
+    fn covers(&self, session_key: &str) -> bool {
+        let channel = session_key
+            .split_once(':')
+            .map_or(session_key, |(ch, _)| ch);
+        self.config.channels.is_empty() || self.config.channels.iter().any(|c| c == channel)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<Source>>> {
+        match self.turns.lock() {
+            Ok(g) => g,
+            Err(e) => e.into_inner(),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for Citations {
+    async fn before_completion(
+        &self,
+        ctx: &HookContext<'_>,
+        _request: &mut CompletionRequest,
+    ) -> Result<()> {
+        // A new turn starts with nothing retrieved
+        if ctx.iteration == 1 {
+            self.lock().remove(ctx.session_key);
+        }
+        Ok(())
+    }
+
+    async fn after_completion(
+        &self,
+        ctx: &HookContext<'_>,
+        text: &mut String,
+        tool_calls: &mut Vec<ToolCall>,
+    ) -> Result<()> {
+        if !tool_calls.is_empty() {
+            return Ok(());
+        }
+        let Some(retrieved) = self.lock().remove(ctx.session_key) else {
+            return Ok(());
+        };
+        let sources = cited(&retrieved, text, self.config.max_sources);
+        if !sources.is_empty() && !text.trim().is_empty() {
+            tracing::debug!(
+                "Citing {} source(s) in '{}'",
+                sources.len(),
+                ctx.session_key
+            );
+            text.push_str(&footnotes(&sources));
+        }
+        Ok(())
+    }
+
+    async fn after_tool(
+        &self,
+        ctx: &HookContext<'_>,
+        tool: &str,
+        result: &mut String,
+    ) -> Result<()> {
+        if !self.covers(ctx.session_key) {
+            return Ok(());
+        }
+        let sources = sources_in(tool, result);
+        if !sources.is_empty() {
+            self.lock()
+                .entry(ctx.session_key.to_string())
+                .or_default()
+                .extend(sources);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_from_tool_results() {
+        let memory = "## Result 1 (score: 1.20)\n**File:** memory/MEMORY.md (lines 3-10)\n\nlikes tea\n\
+                      \n---\n## Result 2 (score: 0.80)\n**File:** memory/HISTORY.md (lines 40-52)\n\ntea party";
+        let sources = sources_in("memory_search", memory);
+        assert_eq!(
+            sources,
+            vec![
+                Source::passage(SourceKind::Memory, "memory/MEMORY.md", 3, 10),
+                Source::passage(SourceKind::Memory, "memory/HISTORY.md", 40, 52),
+            ]
+        );
+
+        let knowledge = "[1] handbook/leave.md (lines 4-30, score: 0.91)\n\nTwenty days.\n\
+                         \n---\n[2] https://example.com/faq (lines 1-12, score: 0.70)\n\n[3] is not a header";
+        let sources = sources_in("knowledge_search", knowledge);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].location, "handbook/leave.md");
+        assert_eq!(sources[1].lines, vec![(1, 12)]);
+
+        let search = "Results for: tea\n\n1. Tea\n   https://example.com/tea\n   All about tea";
+        let sources = sources_in("web_search", search);
+        assert_eq!(sources[0].location, "https://example.com/tea");
+        assert!(sources[0].search_hit);
+
+        let fetch = r#"{"url": "http://a.test", "finalUrl": "https://a.test/", "text": "hi"}"#;
+        assert_eq!(
+            sources_in("web_fetch", fetch)[0].location,
+            "https://a.test/"
+        );
+        assert!(sources_in("web_fetch", r#"{"error": "timed out"}"#).is_empty());
+        assert!(sources_in("memory_search", "No results found.").is_empty());
+        assert!(sources_in("calc", "**File:** x (lines 1-2)").is_empty());
+    }
+
+    #[test]
+    fn test_cited_merges_and_filters() {
+        let retrieved = vec![
+            Source::passage(SourceKind::Memory, "memory/MEMORY.md", 3, 10),
+            Source {
+                search_hit: true,
+                ..Source::new(SourceKind::Web, "https://unused.test")
+            },
+            Source {
+                search_hit: true,
+                ..Source::new(SourceKind::Web, "https://linked.test")
+            },
+            Source::passage(SourceKind::Memory, "memory/MEMORY.md", 20, 25),
+            Source {
+                search_hit: true,
+                ..Source::new(SourceKind::Web, "https://fetched.test")
+            },
+            Source::new(SourceKind::Web, "https://fetched.test"),
+        ];
+        let reply = "You like tea (see https://linked.test).";
+        let sources = cited(&retrieved, reply, 5);
+        let locations: Vec<&str> = sources.iter().map(|s| s.location.as_str()).collect();
+        assert_eq!(
+            locations,
+            [
+                "memory/MEMORY.md",
+                "https://linked.test",
+                "https://fetched.test"
+            ]
+        );
+        assert_eq!(sources[0].lines, vec![(3, 10), (20, 25)]);
+        assert_eq!(cited(&retrieved, reply, 1).len(), 1);
+
+        assert_eq!(
+            footnotes(&sources[..2]),
+            "\n\nSources:\n[1] Memory: memory/MEMORY.md, lines 3-10, 20-25\n[2] Web: https://linked.test"
+        );
+    }
+}
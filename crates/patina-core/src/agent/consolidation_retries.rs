@@ -0,0 +1,173 @@
+//! Retries for memory consolidations that failed.
+//!
+//! A failed consolidation leaves the session's messages unconsolidated, so
+//! nothing is lost, but memory stops updating until one succeeds. The
+//! gateway records each failure here and retries with backoff instead of
+//! on every turn, even once the chat goes quiet. After
+//! `agents.defaults.consolidationAlertAfter` failures in a row it tells the
+//! owner. The state is kept in `~/.patina/consolidation_retries.json` so
+//! retries survive restarts.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Wait before the first retry, doubled after each failure.
+const FIRST_RETRY_MS: i64 = 60 * 1000;
+/// Longest wait between retries.
+const MAX_RETRY_MS: i64 = 6 * 60 * 60 * 1000;
+
+/// One session's failures.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryState {
+    /// Failures in a row.
+    pub failures: u32,
+    pub next_attempt_ms: i64,
+    pub last_error: String,
+}
+
+/// Sessions waiting to retry consolidation.
+pub struct ConsolidationRetries {
+    path: PathBuf,
+    alert_after: u32,
+    sessions: BTreeMap<String, RetryState>,
+}
+
+impl ConsolidationRetries {
+    /// Load the retries in `path`, alerting after `alert_after` failures
+    /// (0 never alerts).
+    pub fn load(path: PathBuf, alert_after: u32) -> Self {
+        let sessions = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable {}: {e}", path.display());
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            path,
+            alert_after,
+            sessions,
+        }
+    }
+
+    pub fn get(&self, session_key: &str) -> Option<&RetryState> {
+        self.sessions.get(session_key)
+    }
+
+    /// Whether `session_key` failed recently and shouldn't be tried before
+    /// its next retry.
+    pub fn is_waiting(&self, session_key: &str, now_ms: i64) -> bool {
+        self.sessions
+            .get(session_key)
+            .is_some_and(|s| now_ms < s.next_attempt_ms)
+    }
+
+    /// Sessions whose retry is due.
+    pub fn due(&self, now_ms: i64) -> Vec<String> {
+        self.sessions
+            .iter()
+            .filter(|(_, s)| s.next_attempt_ms <= now_ms)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// When the next retry is due, if any are waiting.
+    pub fn next_due_ms(&self) -> Option<i64> {
+        self.sessions.values().map(|s| s.next_attempt_ms).min()
+    }
+
+    /// Record a failure and schedule the next try. Returns an alert for
+    /// the owner when this failure reaches the alert threshold.
+    pub fn failed(&mut self, session_key: &str, error: &str, now_ms: i64) -> Option<String> {
+        let state = self
+            .sessions
+            .entry(session_key.to_string())
+            .or_insert(RetryState {
+                failures: 0,
+                next_attempt_ms: now_ms,
+                last_error: String::new(),
+            });
+        state.failures += 1;
+        let wait = FIRST_RETRY_MS
+            .saturating_mul(1 << (state.failures - 1).min(20))
+            .min(MAX_RETRY_MS);
+        state.next_attempt_ms = now_ms + wait;
+        state.last_error = error.to_string();
+        let failures = state.failures;
+        warn!(
+            "Memory consolidation of '{session_key}' failed ({failures} in a row), \
+             retrying in {}s: {error}",
+            wait / 1000
+        );
+        self.save();
+
+        (self.alert_after > 0 && failures == self.alert_after).then(|| {
+            format!(
+                "Memory for {session_key} hasn't been saved: consolidation failed {failures} \
+                 times in a row. The last error was: {error}\n\n\
+                 Nothing is lost; the messages stay in the session and it keeps retrying, \
+                 up to every {} hours. Check the consolidation model.",
+                MAX_RETRY_MS / (60 * 60 * 1000)
+            )
+        })
+    }
+
+    /// Forget `session_key`'s failures, after it consolidated or had
+    /// nothing left to.
+    pub fn succeeded(&mut self, session_key: &str) {
+        if self.sessions.remove(session_key).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let json = match serde_json::to_string_pretty(&self.sessions) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize consolidation retries: {e}");
+                return;
+            }
+        };
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(&self.path, json) {
+            warn!("Failed to save {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backs_off_alerts_once_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("retries.json");
+        let mut retries = ConsolidationRetries::load(path.clone(), 2);
+        let key = "telegram:42";
+
+        assert!(retries.failed(key, "timed out", 0).is_none());
+        assert_eq!(retries.get(key).unwrap().next_attempt_ms, FIRST_RETRY_MS);
+        assert!(retries.is_waiting(key, FIRST_RETRY_MS - 1));
+        assert!(retries.due(FIRST_RETRY_MS - 1).is_empty());
+        assert_eq!(retries.due(FIRST_RETRY_MS), vec![key.to_string()]);
+
+        let alert = retries.failed(key, "bad JSON", FIRST_RETRY_MS).unwrap();
+        assert!(alert.contains("telegram:42") && alert.contains("bad JSON"));
+        assert_eq!(retries.next_due_ms(), Some(3 * FIRST_RETRY_MS));
+        assert!(retries.failed(key, "bad JSON", 0).is_none());
+
+        let reloaded = ConsolidationRetries::load(path.clone(), 2);
+        assert_eq!(reloaded.get(key).unwrap().failures, 3);
+
+        retries.succeeded(key);
+        assert!(!retries.is_waiting(key, 0));
+        assert!(ConsolidationRetries::load(path, 2).get(key).is_none());
+    }
+}
@@ -1,22 +1,71 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::Result;
 use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use patina_config::{Config, LanguageConfig};
 
-use crate::agent::memory::MemoryStore;
+use crate::agent::memory::{CurrentProject, MemoryStore, PROJECTS_DIR};
+use crate::agent::obsidian::Vault;
 use crate::agent::skills::SkillsLoader;
+use crate::agent::template::{self, TemplateVars};
+use crate::locale::UserLocale;
 use crate::session::Message;
+use crate::tools::filesystem::resolve_path;
 
 /// Bootstrap files loaded into the system prompt.
 const BOOTSTRAP_FILES: &[&str] = &["AGENTS.md", "SOUL.md", "USER.md", "TOOLS.md", "IDENTITY.md"];
 
+/// Most directories a session remembers working in.
+const MAX_WORKING_DIRS: usize = 5;
+
+/// A labelled part of the system prompt. `text` starts with whatever
+/// separates it from the part before, so the parts concatenate to the prompt.
+pub struct PromptSection {
+    pub label: String,
+    pub text: String,
+}
+
+impl PromptSection {
+    fn new(label: &str, text: String) -> Self {
+        Self {
+            label: label.to_string(),
+            text,
+        }
+    }
+}
+
 /// Builds the system prompt and message list for LLM calls.
 pub struct ContextBuilder {
     workspace: PathBuf,
     memory: MemoryStore,
+    /// The Obsidian vault memory is kept in, if any.
+    vault: Option<Arc<Vault>>,
     skills: SkillsLoader,
     /// Optional override for the system prompt (used by subagents).
     preamble_override: Option<String>,
+    language: LanguageConfig,
+    /// Language for the next reply, pinned or detected for the session.
+    reply_language: Option<String>,
+    /// Persona answering the next reply, for the runtime facts.
+    persona: Option<String>,
+    /// Project the session is attached to, shared with memory_search.
+    project: CurrentProject,
+    /// Timezone and locale of whoever the host says is talking.
+    user_locale: UserLocale,
+    /// The session's timezone and locale for the next prompt, with `/set`
+    /// overrides applied. Falls back to `user_locale`.
+    locale: Option<UserLocale>,
+    /// When the agent started, for the runtime facts.
+    started: DateTime<Utc>,
+    /// The config for `{{config:...}}` in prompt files, secrets removed.
+    config_vars: serde_json::Value,
+    /// Secrets `{{env:...}}` must not put in the prompt.
+    secrets: Vec<String>,
+    /// Workspace-relative directories the session last worked in, most
+    /// recent first, for nested AGENTS.md files.
+    working_dirs: Vec<PathBuf>,
 }
 
 impl ContextBuilder {
@@ -24,8 +73,19 @@ impl ContextBuilder {
         Self {
             workspace: workspace.to_path_buf(),
             memory: MemoryStore::new(workspace),
+            vault: None,
             skills: SkillsLoader::new(workspace),
             preamble_override: None,
+            language: LanguageConfig::default(),
+            reply_language: None,
+            persona: None,
+            project: CurrentProject::default(),
+            user_locale: UserLocale::default(),
+            locale: None,
+            started: Utc::now(),
+            config_vars: serde_json::Value::Null,
+            secrets: Vec::new(),
+            working_dirs: Vec::new(),
         }
     }
 
@@ -34,67 +94,288 @@ impl ContextBuilder {
         Self {
             workspace: workspace.to_path_buf(),
             memory: MemoryStore::new(workspace),
+            vault: None,
             skills: SkillsLoader::new(workspace),
             preamble_override: Some(preamble),
+            language: LanguageConfig::default(),
+            reply_language: None,
+            persona: None,
+            project: CurrentProject::default(),
+            user_locale: UserLocale::default(),
+            locale: None,
+            started: Utc::now(),
+            config_vars: serde_json::Value::Null,
+            secrets: Vec::new(),
+            working_dirs: Vec::new(),
         }
     }
 
+    /// Use `config` for reply-language detection and its fallback.
+    pub fn with_language(mut self, config: LanguageConfig) -> Self {
+        self.language = config;
+        self
+    }
+
+    /// Let prompt files read `config` through `{{config:...}}`.
+    pub fn with_template_config(mut self, config: &Config) -> Self {
+        self.config_vars = template::config_json(config);
+        self.secrets = config.secrets();
+        self
+    }
+
+    /// Keep memory in `vault` instead of the workspace's `memory/`.
+    pub fn with_vault(mut self, vault: Option<Arc<Vault>>) -> Self {
+        if let Some(ref vault) = vault {
+            self.memory = MemoryStore::in_dir(vault.memory_dir());
+        }
+        self.vault = vault;
+        self
+    }
+
     /// Set or clear the preamble override (for persona support).
     pub fn set_preamble_override(&mut self, preamble: Option<String>) {
         self.preamble_override = preamble;
     }
 
+    /// Set or clear the language for the next reply (per-session language).
+    pub fn set_reply_language(&mut self, language: Option<String>) {
+        self.reply_language = language;
+    }
+
+    /// Set or clear the persona named in the next prompt's runtime facts.
+    pub fn set_persona(&mut self, persona: Option<String>) {
+        self.persona = persona;
+    }
+
+    /// Set or clear the project the next prompt and memory searches are for.
+    pub fn set_project(&mut self, project: Option<String>) {
+        self.project.set(project);
+    }
+
+    /// The project set with [`ContextBuilder::set_project`], shared so
+    /// `memory_search` follows it.
+    pub fn current_project(&self) -> CurrentProject {
+        self.project.clone()
+    }
+
+    /// Set the timezone and locale of the person being talked to, from the
+    /// config. Sessions' `/set timezone` and `/set locale` apply on top.
+    pub fn set_user_locale(&mut self, locale: UserLocale) {
+        self.user_locale = locale;
+    }
+
+    pub fn user_locale(&self) -> &UserLocale {
+        &self.user_locale
+    }
+
+    /// Set or clear the session's timezone and locale for the next prompt.
+    pub fn set_locale(&mut self, locale: Option<UserLocale>) {
+        self.locale = locale;
+    }
+
+    /// Set the directories the next prompt takes nested AGENTS.md files from.
+    pub fn set_working_dirs(&mut self, dirs: Vec<PathBuf>) {
+        self.working_dirs = dirs;
+    }
+
+    pub fn language_config(&self) -> &LanguageConfig {
+        &self.language
+    }
+
     /// Access the memory store for consolidation.
     pub fn memory(&self) -> &MemoryStore {
         &self.memory
     }
 
+    pub fn vault(&self) -> Option<&Arc<Vault>> {
+        self.vault.as_ref()
+    }
+
+    /// The folder of the shared MEMORY.md and HISTORY.md.
+    fn memory_dir(&self) -> &Path {
+        self.memory
+            .memory_path()
+            .parent()
+            .unwrap_or(&self.workspace)
+    }
+
+    /// Projects with memory, sorted.
+    pub fn projects(&self) -> Vec<String> {
+        crate::agent::memory::projects(self.memory_dir())
+    }
+
+    /// The memory of `project`, or the shared memory for `None`.
+    pub fn memory_for(&self, project: Option<&str>) -> MemoryStore {
+        MemoryStore::for_project(self.memory_dir(), project)
+    }
+
+    /// The folder holding project memory, relative to the workspace when
+    /// it's inside it, as memory search results show paths.
+    pub fn projects_dir(&self) -> String {
+        let dir = self.memory_dir().join(PROJECTS_DIR);
+        dir.strip_prefix(&self.workspace)
+            .unwrap_or(&dir)
+            .to_string_lossy()
+            .into_owned()
+    }
+
     /// Build the full system prompt from identity, bootstrap files, skills, and memory.
     pub fn build_system_prompt(&self) -> Result<String> {
+        self.system_prompt(None)
+    }
+
+    /// The system prompt with templates expanded for `session`
+    /// (channel, chat_id).
+    fn system_prompt(&self, session: Option<(&str, &str)>) -> Result<String> {
+        Ok(self
+            .system_prompt_sections(session)
+            .into_iter()
+            .map(|s| s.text)
+            .collect())
+    }
+
+    /// The system prompt `build_messages` would send for `session`, in
+    /// labelled parts, for `/context`.
+    pub fn prompt_sections(
+        &self,
+        session: Option<(&str, &str)>,
+        channel_rules: Option<&str>,
+    ) -> Vec<PromptSection> {
+        let mut sections = self.system_prompt_sections(session);
+        sections.extend(self.prompt_suffix(session, channel_rules));
+        sections
+    }
+
+    fn system_prompt_sections(&self, session: Option<(&str, &str)>) -> Vec<PromptSection> {
         // If a preamble override is set, use it directly (for subagents)
         if let Some(ref preamble) = self.preamble_override {
-            return Ok(preamble.clone());
+            return vec![PromptSection::new(
+                "Persona or custom prompt",
+                preamble.clone(),
+            )];
         }
 
-        let mut parts = Vec::new();
+        const PART: &str = "\n\n---\n\n";
+        let vars = self.template_vars(session);
 
         // Core identity
-        parts.push(self.get_identity());
+        let mut sections = vec![PromptSection::new("Identity", self.get_identity())];
 
         // Bootstrap files
-        let bootstrap = self.load_bootstrap_files();
-        if !bootstrap.is_empty() {
-            parts.push(bootstrap);
+        for (i, (filename, content)) in self.bootstrap_files(&vars).into_iter().enumerate() {
+            let sep = if i == 0 { PART } else { "\n\n" };
+            sections.push(PromptSection::new(
+                filename,
+                format!("{sep}## {filename}\n\n{content}"),
+            ));
         }
 
         // Memory is indexed by memory_search — not injected into context.
         // The agent can recall facts on demand via the memory_search tool.
 
         // Always-loaded skills (full content)
-        let always_skills = self.skills.get_always_skills();
-        if !always_skills.is_empty() {
-            let always_content = self.skills.load_skills_for_context(&always_skills);
-            if !always_content.is_empty() {
-                parts.push(format!("# Active Skills\n\n{always_content}"));
+        let mut heading = "# Active Skills\n\n";
+        for name in self.skills.get_always_skills() {
+            let content = template::expand(
+                &self
+                    .skills
+                    .load_skills_for_context(std::slice::from_ref(&name)),
+                &vars,
+            );
+            if content.is_empty() {
+                continue;
             }
+            sections.push(PromptSection::new(
+                &format!("Skill: {name}"),
+                format!("{PART}{heading}{content}"),
+            ));
+            heading = "";
         }
 
         // Skills summary (progressive loading — agent uses read_file to load full content)
         let skills_summary = self.skills.build_skills_summary();
         if !skills_summary.is_empty() {
-            parts.push(format!(
-                "# Skills\n\n\
-                 Use read_file to load a skill's SKILL.md before using it.\n\n\
-                 {skills_summary}"
+            sections.push(PromptSection::new(
+                "Skills summary",
+                format!(
+                    "{PART}# Skills\n\n\
+                     Use read_file to load a skill's SKILL.md before using it.\n\n\
+                     {skills_summary}"
+                ),
             ));
         }
 
-        Ok(parts.join("\n\n---\n\n"))
+        // Nested AGENTS.md files, after the rest so moving to another
+        // directory keeps the prefix cached
+        let nested = self.agents_files_for(&self.working_dirs);
+        if !nested.is_empty() {
+            sections.push(PromptSection::new(
+                "Project instructions",
+                format!(
+                    "{PART}# Project Instructions\n\n\
+                     From AGENTS.md files in directories you've been working in, nearest first. \
+                     They add to the AGENTS.md above and win where they disagree.\n\n{}",
+                    self.nested_instructions(&nested, session)
+                ),
+            ));
+        }
+
+        sections
+    }
+
+    /// Sections `build_messages` appends after the system prompt, persona
+    /// preambles included.
+    fn prompt_suffix(
+        &self,
+        session: Option<(&str, &str)>,
+        channel_rules: Option<&str>,
+    ) -> Vec<PromptSection> {
+        let mut sections = Vec::new();
+        if let Some(rules) = channel_rules.filter(|r| !r.is_empty()) {
+            sections.push(PromptSection::new(
+                "Channel rules",
+                format!("\n\n## Channel Rules\n{rules}"),
+            ));
+        }
+        if let Some(lang) = self
+            .reply_language
+            .as_ref()
+            .or(self.language.default.as_ref())
+        {
+            sections.push(PromptSection::new(
+                "Language",
+                format!(
+                    "\n\n## Language\nReply in {lang}, whatever language earlier messages, \
+                     memory, or tool results are in. Keep code, commands, and quoted text as they are."
+                ),
+            ));
+        }
+        if let Some(project) = self.project.get() {
+            sections.push(PromptSection::new(
+                "Project",
+                format!(
+                    "\n\n## Project\nThis conversation is about the {project} project. Its \
+                     memory is in {}/{project}/, and memory_search shows matches \
+                     from there first.",
+                    self.projects_dir()
+                ),
+            ));
+        }
+        // Last, so the rest of the prompt stays a stable, cacheable prefix
+        sections.push(PromptSection::new(
+            "Runtime facts",
+            runtime_facts(
+                Utc::now(),
+                self.started,
+                self.locale.as_ref().unwrap_or(&self.user_locale),
+                session,
+                self.persona.as_deref(),
+            ),
+        ));
+        sections
     }
 
     fn get_identity(&self) -> String {
-        let now = chrono::Local::now().format("%Y-%m-%d %H:%M (%A)");
-        let tz = chrono::Local::now().format("%Z");
         let workspace_path = self
             .workspace
             .canonicalize()
@@ -127,25 +408,90 @@ You are Patina, a helpful AI assistant.
 When writing scripts, prefer Python (run with `uv run`) over bash unless it's a simple one-liner.
 
 ## Environment
-- Time: {now} ({tz})
 - OS: {os} {arch}
 - Workspace: {workspace_path}"#
         )
     }
 
-    fn load_bootstrap_files(&self) -> String {
-        let mut parts = Vec::new();
+    /// The workspace directory a tool call works in, relative to the
+    /// workspace: a file tool's `path` (its directory, for a file) or
+    /// `exec`'s `working_dir`. `None` outside the workspace.
+    pub fn tool_dir(&self, tool: &str, args: &serde_json::Value) -> Option<PathBuf> {
+        let key = match tool {
+            "read_file" | "write_file" | "edit_file" | "list_dir" => "path",
+            "exec" => "working_dir",
+            _ => return None,
+        };
+        let path = resolve_path(args.get(key)?.as_str()?, None).ok()?;
+        let dir = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent()?
+        };
+        let root = self.workspace.canonicalize().ok()?;
+        dir.strip_prefix(&root).ok().map(Path::to_path_buf)
+    }
 
-        for filename in BOOTSTRAP_FILES {
-            let file_path = self.workspace.join(filename);
-            if file_path.exists() {
-                if let Ok(content) = std::fs::read_to_string(&file_path) {
-                    parts.push(format!("## {filename}\n\n{content}"));
+    /// AGENTS.md files in `dirs` and their parents below the workspace root,
+    /// nearest first, each once. The root's own is a bootstrap file.
+    pub fn agents_files_for(&self, dirs: &[PathBuf]) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for dir in dirs {
+            for ancestor in dir.ancestors() {
+                if ancestor.as_os_str().is_empty() {
+                    break;
+                }
+                let file = ancestor.join("AGENTS.md");
+                if !files.contains(&file) && self.workspace.join(&file).is_file() {
+                    files.push(file);
                 }
             }
         }
+        files
+    }
 
-        parts.join("\n\n")
+    /// Nested AGENTS.md `files` with their templates expanded, each under
+    /// its workspace path.
+    pub fn nested_instructions(&self, files: &[PathBuf], session: Option<(&str, &str)>) -> String {
+        let vars = self.template_vars(session);
+        files
+            .iter()
+            .filter_map(|file| {
+                let content = std::fs::read_to_string(self.workspace.join(file)).ok()?;
+                Some(format!(
+                    "## {}\n\n{}",
+                    file.display(),
+                    template::expand(content.trim_end(), &vars)
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Values for `{{...}}` in prompt files. Only the date changes within a
+    /// session, so the expanded prompt still caches.
+    fn template_vars(&self, session: Option<(&str, &str)>) -> TemplateVars {
+        let locale = self.locale.as_ref().unwrap_or(&self.user_locale);
+        TemplateVars {
+            date: locale.localize(Utc::now()).format("%Y-%m-%d").to_string(),
+            channel: session.map(|(c, _)| c.to_string()),
+            chat_id: session.map(|(_, id)| id.to_string()),
+            persona: self.persona.clone(),
+            workspace: self.workspace.clone(),
+            config: self.config_vars.clone(),
+            secrets: self.secrets.clone(),
+        }
+    }
+
+    /// Bootstrap files in the workspace, with templates expanded.
+    fn bootstrap_files(&self, vars: &TemplateVars) -> Vec<(&'static str, String)> {
+        BOOTSTRAP_FILES
+            .iter()
+            .filter_map(|filename| {
+                let content = std::fs::read_to_string(self.workspace.join(filename)).ok()?;
+                Some((*filename, template::expand(&content, vars)))
+            })
+            .collect()
     }
 
     /// Build user message content with optional base64-encoded images.
@@ -205,16 +551,10 @@ When writing scripts, prefer Python (run with `uv run`) over bash unless it's a
         let mut messages = Vec::new();
 
         // System prompt
-        let mut system_prompt = self.build_system_prompt()?;
-        if let (Some(ch), Some(cid)) = (channel, chat_id) {
-            system_prompt.push_str(&format!(
-                "\n\n## Current Session\nChannel: {ch}\nChat ID: {cid}"
-            ));
-        }
-        if let Some(rules) = channel_rules {
-            if !rules.is_empty() {
-                system_prompt.push_str(&format!("\n\n## Channel Rules\n{rules}"));
-            }
+        let session = channel.zip(chat_id);
+        let mut system_prompt = self.system_prompt(session)?;
+        for section in self.prompt_suffix(session, channel_rules) {
+            system_prompt.push_str(&section.text);
         }
         messages.push(serde_json::json!({
             "role": "system",
@@ -245,3 +585,171 @@ When writing scripts, prefer Python (run with `uv run`) over bash unless it's a
         Ok(messages)
     }
 }
+
+/// Put `dir` first in a session's working directories. The workspace root
+/// isn't kept, since its AGENTS.md is always loaded.
+pub fn remember_working_dir(dirs: &mut Vec<PathBuf>, dir: PathBuf) {
+    if dir.as_os_str().is_empty() {
+        return;
+    }
+    dirs.retain(|d| *d != dir);
+    dirs.insert(0, dir);
+    dirs.truncate(MAX_WORKING_DIRS);
+}
+
+/// The "Runtime Facts" section: the date and time on the user's clock,
+/// where the message came from, who's answering, and how long the agent has
+/// been up. Added to every prompt, persona preambles included, so the model
+/// never has to guess today's date.
+fn runtime_facts(
+    now: DateTime<Utc>,
+    started: DateTime<Utc>,
+    locale: &UserLocale,
+    session: Option<(&str, &str)>,
+    persona: Option<&str>,
+) -> String {
+    let local_now = locale.localize(now);
+    let zone = locale
+        .tz_name()
+        .map(|name| format!("{name}, "))
+        .unwrap_or_default();
+    let mut out = format!(
+        "\n\n## Runtime Facts\nCurrent time: {} ({zone}UTC{})",
+        local_now.format("%Y-%m-%d %H:%M, %A"),
+        local_now.format("%:z")
+    );
+    if let Some(tag) = &locale.locale {
+        out.push_str(&format!(
+            "\nLocale: {tag} (write dates, times, numbers, and units the way it does)"
+        ));
+    }
+    if let Some((channel, chat_id)) = session {
+        out.push_str(&format!("\nChannel: {channel}\nChat ID: {chat_id}"));
+    }
+    if let Some(persona) = persona {
+        out.push_str(&format!("\nPersona: {persona}"));
+    }
+    out.push_str(&format!(
+        "\nRunning since: {} ({})",
+        locale.localize(started).format("%Y-%m-%d %H:%M"),
+        format_uptime(now.signed_duration_since(started))
+    ));
+    out.push_str(
+        "\nUse the current time above for anything date-related, like scheduling \
+         reminders; don't guess it.",
+    );
+    out
+}
+
+/// "2d 5h", "3h 12m", or "4m".
+fn format_uptime(d: chrono::Duration) -> String {
+    let mins = d.num_minutes().max(0);
+    let (days, hours, mins) = (mins / 1440, mins % 1440 / 60, mins % 60);
+    match (days, hours) {
+        (0, 0) => format!("{mins}m"),
+        (0, _) => format!("{hours}h {mins}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_nested_agents_files_nearest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path();
+        std::fs::create_dir_all(ws.join("projects/foo/src")).unwrap();
+        std::fs::write(ws.join("AGENTS.md"), "Everywhere").unwrap();
+        std::fs::write(ws.join("projects/AGENTS.md"), "All projects").unwrap();
+        std::fs::write(ws.join("projects/foo/AGENTS.md"), "Foo, on {{channel}}\n").unwrap();
+        std::fs::write(ws.join("projects/foo/src/main.rs"), "").unwrap();
+        let context = ContextBuilder::new(ws);
+
+        let file = ws.join("projects/foo/src/main.rs");
+        let dir = context
+            .tool_dir("read_file", &serde_json::json!({ "path": file }))
+            .unwrap();
+        assert_eq!(dir, Path::new("projects/foo/src"));
+        assert_eq!(
+            context.tool_dir("web_fetch", &serde_json::json!({ "path": file })),
+            None
+        );
+        assert_eq!(
+            context.tool_dir("list_dir", &serde_json::json!({ "path": "/" })),
+            None
+        );
+
+        let files = context.agents_files_for(&[dir, PathBuf::from("projects")]);
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("projects/foo/AGENTS.md"),
+                PathBuf::from("projects/AGENTS.md")
+            ]
+        );
+        assert_eq!(
+            context.nested_instructions(&files[..1], Some(("telegram", "1"))),
+            "## projects/foo/AGENTS.md\n\nFoo, on telegram"
+        );
+
+        let mut dirs = vec![PathBuf::from("a"), PathBuf::from("b")];
+        remember_working_dir(&mut dirs, PathBuf::from("b"));
+        remember_working_dir(&mut dirs, PathBuf::new());
+        assert_eq!(dirs, [PathBuf::from("b"), PathBuf::from("a")]);
+    }
+
+    #[test]
+    fn test_prompt_sections_make_up_the_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("AGENTS.md"), "Be brief").unwrap();
+        std::fs::write(dir.path().join("SOUL.md"), "Warm").unwrap();
+        let context = ContextBuilder::new(dir.path());
+
+        let sections = context.prompt_sections(None, Some("No tables"));
+        let labels: Vec<&str> = sections.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels[..3], ["Identity", "AGENTS.md", "SOUL.md"]);
+        assert_eq!(
+            labels[labels.len() - 2..],
+            ["Channel rules", "Runtime facts"]
+        );
+
+        let prompt = context.build_system_prompt().unwrap();
+        assert!(prompt.contains("\n\n---\n\n## AGENTS.md\n\nBe brief\n\n## SOUL.md\n\nWarm"));
+        let joined: String = sections.iter().map(|s| s.text.as_str()).collect();
+        assert!(joined.starts_with(&prompt));
+    }
+
+    #[test]
+    fn test_runtime_facts() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 6, 12, 5, 0).unwrap();
+        let started = Utc.with_ymd_and_hms(2026, 3, 4, 7, 0, 0).unwrap();
+        let cairo = UserLocale::new(Some("Africa/Cairo"), None);
+        assert_eq!(
+            runtime_facts(
+                now,
+                started,
+                &cairo,
+                Some(("telegram", "42")),
+                Some("coder")
+            ),
+            "\n\n## Runtime Facts\n\
+             Current time: 2026-03-06 14:05, Friday (Africa/Cairo, UTC+02:00)\n\
+             Channel: telegram\n\
+             Chat ID: 42\n\
+             Persona: coder\n\
+             Running since: 2026-03-04 09:00 (2d 5h)\n\
+             Use the current time above for anything date-related, like scheduling \
+             reminders; don't guess it."
+        );
+        let tokyo = UserLocale::new(Some("Asia/Tokyo"), Some("ja-JP"));
+        let facts = runtime_facts(now, now, &tokyo, None, None);
+        assert!(facts.contains("Current time: 2026-03-06 21:05, Friday (Asia/Tokyo, UTC+09:00)"));
+        assert!(facts.contains("\nLocale: ja-JP"));
+        assert!(!facts.contains("Channel:"));
+        assert!(facts.contains("(0m)"));
+        assert_eq!(format_uptime(chrono::Duration::minutes(192)), "3h 12m");
+    }
+}
@@ -0,0 +1,582 @@
+//! Score prompts, personas, and model tiers against a suite of test cases.
+//!
+//! A suite is a YAML file listing prompts and what a good reply looks like:
+//!
+//! ```yaml
+//! name: assistant basics
+//! tiers: [default, fast]
+//! personas: [default, analyst]
+//! cases:
+//!   - name: capital
+//!     prompt: What's the capital of France?
+//!     assert:
+//!       - contains: Paris
+//!       - notContains: "I'm not sure"
+//!       - regex: "(?i)^paris"
+//!       - judge: Answers in one short sentence
+//! ```
+//!
+//! Every case runs once per tier and persona, each in a fresh session, and
+//! [`run_suite`] reports which assertions held along with latency, tokens,
+//! and estimated cost. `judge` assertions ask the suite's `judgeTier` model
+//! for a PASS/FAIL verdict on the reply.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use patina_config::schema::ModelPricing;
+use regex::Regex;
+use rig::completion::{CompletionModel, CompletionRequest};
+use rig::message::{AssistantContent, Message, Text, UserContent};
+use rig::OneOrMany;
+use serde::Deserialize;
+
+use crate::agent::r#loop::AgentLoop;
+use crate::persona::PersonaStore;
+use crate::usage::{UsageFilter, UsageRecord};
+
+/// Persona name that runs a case without a persona.
+pub const DEFAULT_PERSONA: &str = "default";
+
+const JUDGE_PREAMBLE: &str = "\
+You grade an AI assistant's reply against one criterion. \
+Answer PASS or FAIL on the first line, then one sentence explaining why.";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EvalSuite {
+    pub name: String,
+    /// Model tiers to run every case on. Empty uses each persona's tier, or
+    /// the default tier.
+    pub tiers: Vec<String>,
+    /// Persona keys to run every case as; `default` means no persona.
+    pub personas: Vec<String>,
+    /// Tier that grades `judge` assertions.
+    pub judge_tier: String,
+    pub cases: Vec<EvalCase>,
+}
+
+impl Default for EvalSuite {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            tiers: Vec::new(),
+            personas: vec![DEFAULT_PERSONA.to_string()],
+            judge_tier: "default".to_string(),
+            cases: Vec::new(),
+        }
+    }
+}
+
+impl EvalSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut suite = Self::parse(&text)?;
+        if suite.name.is_empty() {
+            suite.name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+        Ok(suite)
+    }
+
+    /// Parse a suite, checking that every regex compiles.
+    pub fn parse(text: &str) -> Result<Self> {
+        let suite: Self = serde_yaml::from_str(text).context("Invalid eval suite")?;
+        if suite.cases.is_empty() {
+            anyhow::bail!("Eval suite has no cases");
+        }
+        for case in &suite.cases {
+            for assertion in &case.assert {
+                if let Assertion::Regex(pattern) = assertion {
+                    Regex::new(pattern)
+                        .with_context(|| format!("Case '{}': invalid regex", case.name))?;
+                }
+            }
+        }
+        Ok(suite)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EvalCase {
+    pub name: String,
+    pub prompt: String,
+    /// One-key maps (`- contains: Paris`) rather than YAML tags.
+    #[serde(with = "serde_yaml::with::singleton_map_recursive")]
+    pub assert: Vec<Assertion>,
+}
+
+/// A check on a reply. `contains` and `notContains` ignore case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Assertion {
+    Contains(String),
+    NotContains(String),
+    Regex(String),
+    /// A criterion for the judge model.
+    Judge(String),
+}
+
+impl std::fmt::Display for Assertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Contains(s) => write!(f, "contains {s:?}"),
+            Self::NotContains(s) => write!(f, "doesn't contain {s:?}"),
+            Self::Regex(s) => write!(f, "matches /{s}/"),
+            Self::Judge(s) => write!(f, "judge: {s}"),
+        }
+    }
+}
+
+/// One assertion's outcome.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub assertion: String,
+    pub passed: bool,
+    /// The judge's reasoning, or why the check couldn't run.
+    pub note: Option<String>,
+}
+
+/// One case run on one tier as one persona.
+#[derive(Debug, Clone, Default)]
+pub struct CaseResult {
+    pub case: String,
+    pub tier: String,
+    pub persona: String,
+    pub response: String,
+    pub error: Option<String>,
+    pub checks: Vec<CheckResult>,
+    pub latency: Duration,
+    pub tokens: u64,
+    pub cost: Option<f64>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Run every case in `suite` on each of its tiers and personas. Sessions
+/// are named `eval:<run_id>:...`, so the loop should use a scratch session
+/// directory; token counts and cost come from the loop's usage tracker.
+pub async fn run_suite(
+    agent_loop: &mut AgentLoop,
+    suite: &EvalSuite,
+    personas: &PersonaStore,
+    pricing: &HashMap<String, ModelPricing>,
+    run_id: &str,
+) -> Vec<CaseResult> {
+    let tiers: Vec<Option<&str>> = if suite.tiers.is_empty() {
+        vec![None]
+    } else {
+        suite.tiers.iter().map(|t| Some(t.as_str())).collect()
+    };
+
+    let mut results = Vec::new();
+    for persona_key in &suite.personas {
+        let persona = if persona_key == DEFAULT_PERSONA {
+            Some((None, None))
+        } else {
+            personas.get(persona_key).map(|p| {
+                (
+                    Some(p.preamble.clone()).filter(|s| !s.is_empty()),
+                    Some(p.model_tier.clone()).filter(|s| !s.is_empty()),
+                )
+            })
+        };
+        for tier in &tiers {
+            for (i, case) in suite.cases.iter().enumerate() {
+                let mut result = CaseResult {
+                    case: if case.name.is_empty() {
+                        format!("case {}", i + 1)
+                    } else {
+                        case.name.clone()
+                    },
+                    tier: tier.unwrap_or("default").to_string(),
+                    persona: persona_key.clone(),
+                    ..Default::default()
+                };
+                let Some((preamble, persona_tier)) = &persona else {
+                    result.error = Some(format!("persona '{persona_key}' doesn't exist"));
+                    results.push(result);
+                    continue;
+                };
+                let tier = tier.map(str::to_string).or(persona_tier.clone());
+                if let Some(tier) = &tier {
+                    result.tier.clone_from(tier);
+                }
+
+                let session_key = format!("eval:{run_id}:{persona_key}:{}:{i}", result.tier);
+                run_case(
+                    agent_loop,
+                    suite,
+                    case,
+                    &session_key,
+                    preamble.as_deref(),
+                    tier.as_deref(),
+                    &mut result,
+                )
+                .await;
+
+                (result.tokens, result.cost) = session_usage(agent_loop, &session_key, pricing);
+                results.push(result);
+            }
+        }
+    }
+    results
+}
+
+/// Tokens and estimated cost the loop's usage tracker recorded for
+/// `session_key`. Zero and `None` without a tracker.
+pub(crate) fn session_usage(
+    agent_loop: &AgentLoop,
+    session_key: &str,
+    pricing: &HashMap<String, ModelPricing>,
+) -> (u64, Option<f64>) {
+    let Some(tracker) = &agent_loop.usage_tracker else {
+        return (0, None);
+    };
+    let filter = UsageFilter {
+        session: Some(session_key.to_string()),
+        group_by: Some("session".to_string()),
+        ..Default::default()
+    };
+    match tracker.query_summary_with_cost(&filter, pricing) {
+        Ok(rows) => (
+            rows.iter().map(|r| r.total_tokens).sum(),
+            rows.iter()
+                .filter_map(|r| r.estimated_cost)
+                .reduce(|a, b| a + b),
+        ),
+        Err(_) => (0, None),
+    }
+}
+
+/// Send one case's prompt and check the reply.
+async fn run_case(
+    agent_loop: &mut AgentLoop,
+    suite: &EvalSuite,
+    case: &EvalCase,
+    session_key: &str,
+    preamble: Option<&str>,
+    tier: Option<&str>,
+    result: &mut CaseResult,
+) {
+    let started = Instant::now();
+    let outcome = agent_loop
+        .process_message_with_persona(session_key, &case.prompt, None, preamble, tier)
+        .await;
+    result.latency = started.elapsed();
+
+    let response = match outcome {
+        Ok((response, _)) => response,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return;
+        }
+    };
+    for assertion in &case.assert {
+        let check = match assertion {
+            Assertion::Judge(criterion) => {
+                let question = format!(
+                    "The user asked:\n{}\n\nThe assistant replied:\n{response}\n\n\
+                     Criterion: {criterion}",
+                    case.prompt
+                );
+                judge(agent_loop, &suite.judge_tier, session_key, &question).await
+            }
+            other => check_text(other, &response),
+        };
+        result.checks.push(CheckResult {
+            assertion: assertion.to_string(),
+            ..check
+        });
+    }
+    result.response = response;
+}
+
+/// Check a reply against a text assertion. Judge assertions need the
+/// model, so they go through [`judge`] instead.
+fn check_text(assertion: &Assertion, response: &str) -> CheckResult {
+    let (passed, note) = match assertion {
+        Assertion::Contains(s) => (response.to_lowercase().contains(&s.to_lowercase()), None),
+        Assertion::NotContains(s) => (!response.to_lowercase().contains(&s.to_lowercase()), None),
+        // Patterns were checked when the suite was parsed
+        Assertion::Regex(pattern) => match Regex::new(pattern) {
+            Ok(re) => (re.is_match(response), None),
+            Err(e) => (false, Some(e.to_string())),
+        },
+        Assertion::Judge(_) => (false, Some("needs the judge model".to_string())),
+    };
+    CheckResult {
+        assertion: assertion.to_string(),
+        passed,
+        note,
+    }
+}
+
+/// Ask the judge tier for a verdict on `question`: the prompt, the reply,
+/// and the criterion.
+#[allow(deprecated)]
+async fn judge(
+    agent_loop: &AgentLoop,
+    tier: &str,
+    session_key: &str,
+    question: &str,
+) -> CheckResult {
+    let fail = |note: String| CheckResult {
+        assertion: String::new(),
+        passed: false,
+        note: Some(note),
+    };
+    let (model, model_name, provider) = agent_loop.models.get(tier);
+    let request = CompletionRequest {
+        preamble: Some(JUDGE_PREAMBLE.to_string()),
+        chat_history: OneOrMany::one(Message::User {
+            content: OneOrMany::one(UserContent::Text(Text {
+                text: question.to_string(),
+            })),
+        }),
+        documents: Vec::new(),
+        tools: Vec::new(),
+        temperature: Some(0.0),
+        max_tokens: None,
+        tool_choice: None,
+        additional_params: agent_loop.models.request_params(provider),
+    };
+
+    agent_loop.models.throttle(provider, &request).await;
+    let limit = agent_loop.models.timeout(provider);
+    let reply = match tokio::time::timeout(limit, model.completion(request)).await {
+        Ok(Ok(r)) => r,
+        Ok(Err(e)) => return fail(format!("judge failed: {e}")),
+        Err(_) => return fail(format!("judge timed out after {}s", limit.as_secs())),
+    };
+    let text: String = reply
+        .choice
+        .iter()
+        .filter_map(|c| match c {
+            AssistantContent::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    if let Some(tracker) = &agent_loop.usage_tracker {
+        let mut record = UsageRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_key: format!("{session_key}:judge"),
+            model: model_name.to_string(),
+            provider: provider.to_string(),
+            agent: "eval-judge".to_string(),
+            input_tokens: reply.usage.input_tokens,
+            output_tokens: reply.usage.output_tokens,
+            total_tokens: reply.usage.total_tokens,
+            cached_input_tokens: reply.usage.cached_input_tokens,
+            call_type: "eval_judge".to_string(),
+            ..Default::default()
+        };
+        record.fill_estimates(&format!("{JUDGE_PREAMBLE}\n{question}"), &text);
+        tracker.record(&record);
+    }
+
+    match parse_verdict(&text) {
+        Some((passed, reason)) => CheckResult {
+            assertion: String::new(),
+            passed,
+            note: Some(reason).filter(|r| !r.is_empty()),
+        },
+        None => fail(format!("judge gave no verdict: {}", text.trim())),
+    }
+}
+
+/// Read "PASS"/"FAIL" from the start of the judge's reply, with the rest as
+/// the reason.
+fn parse_verdict(text: &str) -> Option<(bool, String)> {
+    let text = text.trim().trim_start_matches(['*', '#', ' ']);
+    let upper = text.to_uppercase();
+    let passed = if upper.starts_with("PASS") {
+        true
+    } else if upper.starts_with("FAIL") {
+        false
+    } else {
+        return None;
+    };
+    let reason = text
+        .get(4..)
+        .unwrap_or_default()
+        .trim_start_matches(['*', ':', '.', '-', '—', ' '])
+        .trim()
+        .to_string();
+    Some((passed, reason))
+}
+
+/// Human-readable results, with a line per tier and persona at the end.
+pub fn render_report(results: &[CaseResult]) -> String {
+    let mut out = String::new();
+    for r in results {
+        let status = if r.passed() { "pass" } else { "FAIL" };
+        out.push_str(&format!(
+            "[{status}] {} · {} · {} — {}\n",
+            r.case,
+            r.tier,
+            r.persona,
+            usage_line(r.latency, r.tokens, r.cost)
+        ));
+        if let Some(ref e) = r.error {
+            out.push_str(&format!("  error: {e}\n"));
+        }
+        for check in r.checks.iter().filter(|c| !c.passed) {
+            match &check.note {
+                Some(note) => out.push_str(&format!("  ✗ {} ({note})\n", check.assertion)),
+                None => out.push_str(&format!("  ✗ {}\n", check.assertion)),
+            }
+        }
+    }
+
+    // Totals per tier and persona, in the order they ran
+    let mut groups: Vec<((&str, &str), Vec<&CaseResult>)> = Vec::new();
+    for r in results {
+        let key = (r.tier.as_str(), r.persona.as_str());
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, rs)) => rs.push(r),
+            None => groups.push((key, vec![r])),
+        }
+    }
+    out.push('\n');
+    for ((tier, persona), rs) in &groups {
+        let passed = rs.iter().filter(|r| r.passed()).count();
+        let latency = rs.iter().map(|r| r.latency).sum::<Duration>() / rs.len() as u32;
+        let tokens = rs.iter().map(|r| r.tokens).sum();
+        let cost = rs.iter().filter_map(|r| r.cost).reduce(|a, b| a + b);
+        out.push_str(&format!(
+            "{tier} · {persona}: {passed}/{} passed, {:.1}s avg, {} total\n",
+            rs.len(),
+            latency.as_secs_f64(),
+            spend(tokens, cost)
+        ));
+    }
+    let passed = results.iter().filter(|r| r.passed()).count();
+    out.push_str(&format!("\n{passed}/{} runs passed", results.len()));
+    out
+}
+
+fn usage_line(latency: Duration, tokens: u64, cost: Option<f64>) -> String {
+    format!("{:.1}s, {}", latency.as_secs_f64(), spend(tokens, cost))
+}
+
+fn spend(tokens: u64, cost: Option<f64>) -> String {
+    match cost {
+        Some(cost) => format!("{tokens} tokens, ${cost:.4}"),
+        None => format!("{tokens} tokens"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::model_pool::ModelPool;
+    use crate::agent::r#loop::ModelOverrides;
+    use crate::agent::replay::{MockCompletionModel, RecordedResponse};
+    use crate::agent::ContextBuilder;
+    use crate::session::SessionManager;
+    use crate::tools::ToolRegistry;
+
+    const SUITE: &str = r#"
+name: basics
+cases:
+  - name: capital
+    prompt: What's the capital of France?
+    assert:
+      - contains: paris
+      - notContains: "not sure"
+      - regex: "^The capital"
+      - judge: Answers in one sentence
+"#;
+
+    fn test_loop(dir: &Path, models: ModelPool) -> AgentLoop {
+        AgentLoop {
+            models,
+            sessions: SessionManager::new(dir.join("sessions")),
+            context: ContextBuilder::new(dir),
+            tools: ToolRegistry::new(),
+            max_iterations: 5,
+            temperature: 0.7,
+            max_tokens: 1024,
+            memory_window: 20,
+            model_overrides: ModelOverrides::defaults(),
+            memory_index: None,
+            channel_rules: HashMap::new(),
+            usage_tracker: None,
+            session_log: None,
+            stream_tx: None,
+            middleware: Vec::new(),
+            memory_approvals: None,
+        }
+    }
+
+    #[test]
+    fn parses_suites() {
+        let suite = EvalSuite::parse(SUITE).unwrap();
+        assert_eq!(suite.personas, [DEFAULT_PERSONA]);
+        assert_eq!(suite.cases[0].assert.len(), 4);
+        assert!(matches!(&suite.cases[0].assert[1], Assertion::NotContains(s) if s == "not sure"));
+
+        assert!(EvalSuite::parse("cases: []").is_err());
+        let bad_regex = "cases:\n  - prompt: hi\n    assert:\n      - regex: \"(\"";
+        assert!(EvalSuite::parse(bad_regex).is_err());
+    }
+
+    #[test]
+    fn reads_verdicts() {
+        assert_eq!(
+            parse_verdict("PASS\nOne sentence."),
+            Some((true, "One sentence.".to_string()))
+        );
+        assert_eq!(
+            parse_verdict("**FAIL**: rambles"),
+            Some((false, "rambles".to_string()))
+        );
+        assert_eq!(parse_verdict("It depends"), None);
+    }
+
+    #[tokio::test]
+    async fn runs_and_scores_a_suite() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = MockCompletionModel::new([
+            RecordedResponse {
+                text: "The capital of France is Paris.".into(),
+                ..Default::default()
+            },
+            RecordedResponse {
+                text: "FAIL - it is one sentence, but the grader is strict".into(),
+                ..Default::default()
+            },
+        ]);
+        let mut agent_loop = test_loop(dir.path(), model.clone().into_pool());
+        let suite = EvalSuite::parse(SUITE).unwrap();
+        let personas = PersonaStore::load(&dir.path().join("personas.json"));
+
+        let results = run_suite(&mut agent_loop, &suite, &personas, &HashMap::new(), "t").await;
+        assert_eq!(results.len(), 1);
+        let r = &results[0];
+        assert_eq!(
+            (r.tier.as_str(), r.persona.as_str()),
+            ("default", "default")
+        );
+        let passed: Vec<bool> = r.checks.iter().map(|c| c.passed).collect();
+        assert_eq!(passed, [true, true, true, false]);
+        assert!(!r.passed());
+
+        let report = render_report(&results);
+        assert!(report.starts_with("[FAIL] capital · default · default"));
+        assert!(report.contains("✗ judge: Answers in one sentence (it is one sentence"));
+        assert!(report.ends_with("0/1 runs passed"));
+    }
+}
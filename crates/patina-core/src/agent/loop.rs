@@ -1,20 +1,30 @@
+use std::future::Future;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-#[allow(deprecated)]
-use rig::client::completion::CompletionModelHandle;
 use rig::completion::{CompletionModel, CompletionRequest, GetTokenUsage, Message, ToolDefinition};
 use rig::message::{
-    AssistantContent, Reasoning, Text, ToolCall, ToolResult, ToolResultContent, UserContent,
+    AssistantContent, Reasoning, Text, ToolCall, ToolChoice, ToolResult, ToolResultContent,
+    UserContent,
 };
 use rig::OneOrMany;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
 
-use crate::agent::context::ContextBuilder;
+use crate::agent::context::{remember_working_dir, ContextBuilder};
+use crate::agent::memory_approvals::{MemoryApprovals, PendingMemory};
 use crate::agent::memory_index::MemoryIndex;
+use crate::agent::middleware::{HookContext, Middleware, ToolDecision};
 use crate::agent::model_pool::ModelPool;
-use crate::session::SessionManager;
+use crate::agent::obsidian::{note_mentions, Vault, LINK_INSTRUCTIONS};
+use crate::agent::settings::{
+    session_project, SessionSettings, DETECTED_LANGUAGE_KEY, PERSONA_KEY, WORKING_DIRS_KEY,
+};
+use crate::error::{PatinaError, ProviderError};
+use crate::locale::UserLocale;
+use crate::session::{safe_file_name, SessionManager};
+use crate::session_log::SessionLogger;
 use crate::tools::ToolRegistry;
 use crate::usage::{UsageRecord, UsageTracker};
 
@@ -35,16 +45,41 @@ fn floor_char_boundary(s: &str, max: usize) -> usize {
 pub struct ConsolidationTask {
     pub session_key: String,
     pub end: usize,
+    /// Number of messages being archived.
+    pub messages: usize,
     pub conversation: String,
     pub current_memory: String,
     pub memory_path: PathBuf,
     pub history_path: PathBuf,
+    /// Set when the update has to wait for the owner's approval.
+    pub approvals: Option<Arc<MemoryApprovals>>,
+    /// Set when memory is kept in an Obsidian vault, whose linked pages
+    /// get the history entry too.
+    pub vault: Option<Arc<Vault>>,
+}
+
+/// What a consolidation would write.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsolidationProposal {
+    /// The summary to append to HISTORY.md.
+    pub history_entry: Option<String>,
+    /// The complete new MEMORY.md.
+    pub memory_update: Option<String>,
 }
 
 /// Result of a successful consolidation, used to update session state.
 pub struct ConsolidationResult {
     pub session_key: String,
     pub end: usize,
+    /// Number of messages archived.
+    pub messages: usize,
+    /// The summary appended to HISTORY.md.
+    pub history_entry: Option<String>,
+    /// MEMORY.md facts this consolidation added or reworded.
+    pub new_facts: Vec<String>,
+    /// Set when nothing was written because the update is waiting for the
+    /// owner's approval.
+    pub held: Option<PendingMemory>,
 }
 
 /// Per-model parameter overrides keyed by substring pattern.
@@ -84,6 +119,21 @@ impl ModelOverrides {
     }
 }
 
+/// Run one step of an LLM call, failing with [`ProviderError::Timeout`] if
+/// it takes longer than `limit`.
+async fn llm_timeout<T>(
+    provider: &str,
+    limit: Duration,
+    fut: impl Future<Output = T>,
+) -> Result<T, ProviderError> {
+    tokio::time::timeout(limit, fut)
+        .await
+        .map_err(|_| ProviderError::Timeout {
+            provider: provider.to_string(),
+            secs: limit.as_secs(),
+        })
+}
+
 /// Core agent processing loop.
 ///
 /// Uses rig's CompletionModel directly for LLM calls but runs its own
@@ -110,27 +160,56 @@ pub struct AgentLoop {
     pub channel_rules: std::collections::HashMap<String, String>,
     /// Optional usage tracker for recording LLM API token consumption.
     pub usage_tracker: Option<Arc<UsageTracker>>,
+    /// Optional per-session debug log of prompts, responses, and tool I/O.
+    pub session_log: Option<Arc<SessionLogger>>,
     /// Optional sender for streaming text chunks to the UI.
     pub stream_tx: Option<tokio::sync::mpsc::UnboundedSender<StreamChunk>>,
+    /// Hooks run around each completion and tool call, in order.
+    pub middleware: Vec<Arc<dyn Middleware>>,
+    /// Holds memory updates from `agents.defaults.memoryApprovalChannels`
+    /// for the owner's approval.
+    pub memory_approvals: Option<Arc<MemoryApprovals>>,
 }
 
 #[allow(deprecated)]
 impl AgentLoop {
     fn interrupt_flag_path(session_key: &str) -> std::path::PathBuf {
-        let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-        let safe = session_key
-            .chars()
-            .map(|c| match c {
-                '/' | '\\' | ':' | ' ' => '_',
-                _ => c,
-            })
-            .collect::<String>();
-        home.join(".patina")
+        patina_config::data_dir()
             .join("interrupts")
-            .join(format!("{safe}.flag"))
+            .join(format!("{}.flag", safe_file_name(session_key)))
+    }
+
+    /// The timezone and locale for turns in `session_key`: the user's from
+    /// [`ContextBuilder::set_user_locale`] with the session's `/set timezone`
+    /// and `/set locale` on top. Hosts pass it to
+    /// [`ContextTools::set_locale`](crate::builder::ContextTools::set_locale).
+    pub fn locale_for(&mut self, session_key: &str) -> UserLocale {
+        let settings = self
+            .sessions
+            .get_or_create_checked(session_key)
+            .map(|s| SessionSettings::from_metadata(&s.metadata))
+            .unwrap_or_default();
+        settings.locale(self.context.user_locale())
     }
 
-    fn consume_interrupt(session_key: &str) -> bool {
+    /// Register middleware to run after any already registered.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Ask the turn running in `session_key` to stop. It notices at the next
+    /// tool call or streamed chunk and replies with what it got done.
+    pub fn request_interrupt(session_key: &str) -> std::io::Result<()> {
+        let flag = Self::interrupt_flag_path(session_key);
+        if let Some(dir) = flag.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&flag, chrono::Utc::now().to_rfc3339())
+    }
+
+    /// Clear a pending interrupt for `session_key`, returning whether there
+    /// was one.
+    pub fn consume_interrupt(session_key: &str) -> bool {
         let flag = Self::interrupt_flag_path(session_key);
         if flag.exists() {
             if let Err(e) = std::fs::remove_file(&flag) {
@@ -148,7 +227,7 @@ impl AgentLoop {
         session_key: &str,
         user_message: &str,
         media: Option<&[String]>,
-    ) -> Result<(String, bool)> {
+    ) -> Result<(String, bool), PatinaError> {
         self.process_message_with_persona(session_key, user_message, media, None, None)
             .await
     }
@@ -157,6 +236,12 @@ impl AgentLoop {
     ///
     /// - `preamble_override`: replaces the default system prompt when `Some`
     /// - `model_tier`: selects a model tier (falls back to "default" when `None`)
+    ///
+    /// Session settings from `/set` take precedence over both.
+    ///
+    /// Failures come back as a [`PatinaError`], so callers can tell a
+    /// provider timeout or rate limit from other errors.
+    #[tracing::instrument(name = "turn", skip_all, fields(session = session_key))]
     pub async fn process_message_with_persona(
         &mut self,
         session_key: &str,
@@ -164,17 +249,25 @@ impl AgentLoop {
         media: Option<&[String]>,
         preamble_override: Option<&str>,
         model_tier: Option<&str>,
-    ) -> Result<(String, bool)> {
+    ) -> Result<(String, bool), PatinaError> {
         if Self::consume_interrupt(session_key) {
             return Ok(("Interrupted before processing.".to_string(), false));
         }
 
-        // Apply preamble override if provided
-        self.context
-            .set_preamble_override(preamble_override.map(|s| s.to_string()));
-
         let session = self.sessions.get_or_create_checked(session_key)?;
-        let history = session.get_history(self.memory_window);
+        if self.context.language_config().auto_detect {
+            // Saved with the session, so a short "ok" keeps the last language
+            if let Some(lang) = crate::language::detect(user_message) {
+                session
+                    .metadata
+                    .insert(DETECTED_LANGUAGE_KEY.to_string(), lang.into());
+            }
+        }
+        let (settings, mut working_dirs) = self.prepare_context(session_key, preamble_override)?;
+        let history = self
+            .sessions
+            .get_or_create_checked(session_key)?
+            .get_history(self.memory_window);
 
         // Parse session_key ("channel:chat_id") to extract channel info
         let (channel, chat_id) = session_key
@@ -186,17 +279,19 @@ impl AgentLoop {
             .map(|s| s.as_str());
 
         // Build messages for context
-        let messages_json = self.context.build_messages(
-            &history,
-            user_message,
-            channel,
-            chat_id,
-            media,
-            channel_rules,
-        )?;
+        let messages_json = info_span!("context_build").in_scope(|| {
+            self.context.build_messages(
+                &history,
+                user_message,
+                channel,
+                chat_id,
+                media,
+                channel_rules,
+            )
+        })?;
 
         // Clear preamble override so subsequent calls use defaults
-        self.context.set_preamble_override(None);
+        self.reset_context();
 
         // Log context summary
         {
@@ -315,7 +410,23 @@ impl AgentLoop {
         self.sessions.save(session_key)?;
 
         // Run the agent loop with tool calling
-        let tier = model_tier.unwrap_or("default");
+        let tier = settings
+            .model
+            .as_deref()
+            .or(model_tier)
+            .unwrap_or("default");
+        if let Some(ref log) = self.session_log {
+            log.log(
+                session_key,
+                "turn",
+                serde_json::json!({
+                    "session": session_key,
+                    "user_message": user_message,
+                    "tier": tier,
+                    "agent": agent_name,
+                }),
+            );
+        }
         let (response, tools_used, reasoning) = self
             .run_loop(
                 session_key,
@@ -324,12 +435,18 @@ impl AgentLoop {
                 prompt,
                 &tool_defs,
                 tier,
+                settings.temperature.unwrap_or(self.temperature),
                 &agent_name,
+                &mut working_dirs,
             )
             .await?;
 
         // Save assistant response
         let session = self.sessions.get_or_create_checked(session_key)?;
+        session.metadata.insert(
+            WORKING_DIRS_KEY.to_string(),
+            serde_json::json!(working_dirs),
+        );
         session.add_message_full("assistant", &response, tools_used, reasoning);
         self.sessions.save(session_key)?;
 
@@ -357,6 +474,55 @@ impl AgentLoop {
         Ok((response, needs_consolidation))
     }
 
+    /// Set up the context builder for `session_key`'s next prompt: its
+    /// language, locale, persona, working directories, and the preamble
+    /// (`/set prompt`, else `preamble_override`). Returns the session's
+    /// settings and working directories. Undo with [`Self::reset_context`].
+    pub(crate) fn prepare_context(
+        &mut self,
+        session_key: &str,
+        preamble_override: Option<&str>,
+    ) -> Result<(SessionSettings, Vec<PathBuf>), PatinaError> {
+        let session = self.sessions.get_or_create_checked(session_key)?;
+        let settings = SessionSettings::from_metadata(&session.metadata);
+        self.context
+            .set_reply_language(settings.reply_language(&session.metadata));
+        self.context
+            .set_locale(Some(settings.locale(self.context.user_locale())));
+        self.context.set_persona(
+            session
+                .metadata
+                .get(PERSONA_KEY)
+                .and_then(|v| v.as_str())
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+        );
+        self.context.set_project(session_project(&session.metadata));
+        let working_dirs: Vec<PathBuf> = session
+            .metadata
+            .get(WORKING_DIRS_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        self.context.set_working_dirs(working_dirs.clone());
+        self.context.set_preamble_override(
+            settings
+                .system_prompt
+                .clone()
+                .or_else(|| preamble_override.map(|s| s.to_string())),
+        );
+        Ok((settings, working_dirs))
+    }
+
+    /// Clear what [`Self::prepare_context`] set, so other callers get defaults.
+    pub(crate) fn reset_context(&mut self) {
+        self.context.set_preamble_override(None);
+        self.context.set_reply_language(None);
+        self.context.set_persona(None);
+        self.context.set_project(None);
+        self.context.set_locale(None);
+        self.context.set_working_dirs(Vec::new());
+    }
+
     /// Snapshot session data for consolidation without borrowing mutably.
     /// Returns `None` if there's nothing to consolidate.
     pub fn prepare_consolidation(
@@ -400,34 +566,98 @@ impl AgentLoop {
             conversation.push_str(&format!("[{ts}] {role}{tools_info}: {}\n", msg.content));
         }
 
-        let current_memory = self.context.memory().read_long_term().unwrap_or_default();
-        let memory_store = self.context.memory();
+        // A project's conversations go into its own memory
+        let memory_store = self
+            .context
+            .memory_for(session_project(&session.metadata).as_deref());
+        let current_memory = memory_store.read_long_term().unwrap_or_default();
 
         Some(ConsolidationTask {
             session_key: session_key.to_string(),
             end,
+            messages: messages_to_process.len(),
             conversation,
             current_memory,
             memory_path: memory_store.memory_path().to_path_buf(),
             history_path: memory_store.history_path().to_path_buf(),
+            approvals: self
+                .memory_approvals
+                .clone()
+                .filter(|a| a.applies_to(session_key)),
+            vault: self.context.vault().cloned(),
         })
     }
 
-    /// Run the consolidation LLM call and write memory files.
+    /// Run the consolidation LLM call and write memory files, or hold the
+    /// update for approval when `task.approvals` is set.
     /// This is a static method that doesn't need `self`.
+    ///
+    /// Fails, with nothing written to HISTORY.md, when the model call
+    /// fails, its reply can't be used, or MEMORY.md can't be written;
+    /// the caller should leave the messages unconsolidated and retry.
     pub async fn run_consolidation(
-        model: &CompletionModelHandle<'static>,
+        models: &ModelPool,
         task: &ConsolidationTask,
         usage_tracker: Option<&Arc<UsageTracker>>,
-        model_name: &str,
-        provider_name: &str,
-    ) -> Option<ConsolidationResult> {
+    ) -> Result<ConsolidationResult> {
+        let proposal = Self::propose_consolidation(models, task, usage_tracker).await?;
+        let new_facts = proposal
+            .memory_update
+            .as_deref()
+            .map(|update| crate::agent::memory::new_facts(&task.current_memory, update))
+            .unwrap_or_default();
+
+        let held = match &task.approvals {
+            Some(approvals) => Some(approvals.hold(PendingMemory {
+                session_key: task.session_key.clone(),
+                messages: task.messages,
+                history_entry: proposal.history_entry.clone(),
+                memory_update: proposal.memory_update.clone(),
+                base_memory: task.current_memory.clone(),
+                memory_path: task.memory_path.clone(),
+                history_path: task.history_path.clone(),
+                ..Default::default()
+            })?),
+            None => {
+                crate::agent::memory::save_consolidation(
+                    &task.memory_path,
+                    &task.history_path,
+                    proposal.memory_update.as_deref(),
+                    proposal.history_entry.as_deref(),
+                )?;
+                info!("Memory consolidation: saved memory and history");
+                if let (Some(vault), Some(entry)) = (&task.vault, &proposal.history_entry) {
+                    note_mentions(vault, entry);
+                }
+                None
+            }
+        };
+
+        Ok(ConsolidationResult {
+            session_key: task.session_key.clone(),
+            end: task.end,
+            messages: task.messages,
+            history_entry: proposal.history_entry,
+            new_facts,
+            held,
+        })
+    }
+
+    /// Run the consolidation LLM call without writing anything, for
+    /// [`AgentLoop::run_consolidation`] and dry runs.
+    #[tracing::instrument(name = "consolidation", skip_all, fields(session = %task.session_key))]
+    pub async fn propose_consolidation(
+        models: &ModelPool,
+        task: &ConsolidationTask,
+        usage_tracker: Option<&Arc<UsageTracker>>,
+    ) -> Result<ConsolidationProposal> {
+        let (model, model_name, provider_name) = models.get("consolidation");
         let prompt = format!(
             r#"You are a memory consolidation agent. Process this conversation and return a JSON object with exactly two keys:
 
 1. "history_entry": A paragraph (2-5 sentences) summarizing the key events/decisions/topics. Start with a timestamp like [YYYY-MM-DD HH:MM]. Include enough detail to be useful when found by grep search later.
 
-2. "memory_update": The updated long-term memory content. Add any new facts: user location, preferences, personal info, habits, project context, technical decisions, tools/services used. If nothing new, return the existing content unchanged.
+2. "memory_update": The updated long-term memory content. Add any new facts: user location, preferences, personal info, habits, project context, technical decisions, tools/services used. If nothing new, return the existing content unchanged.{}
 
 ## Current Long-term Memory
 {}
@@ -435,34 +665,86 @@ impl AgentLoop {
 ## Conversation to Process
 {}
 
-Respond with ONLY valid JSON, no markdown fences."#,
-            task.current_memory, task.conversation
+Save them with the {SAVE_MEMORY_TOOL} tool if you have it. Otherwise respond with ONLY valid JSON, no markdown fences."#,
+            if task.vault.is_some() {
+                format!("\n\n{LINK_INSTRUCTIONS}")
+            } else {
+                String::new()
+            },
+            task.current_memory,
+            task.conversation
         );
 
-        let request = CompletionRequest {
-            preamble: None,
-            chat_history: OneOrMany::one(Message::User {
-                content: OneOrMany::one(UserContent::Text(Text { text: prompt })),
-            }),
-            documents: Vec::new(),
-            tools: Vec::new(),
-            temperature: Some(0.3),
-            max_tokens: Some(2048),
-            tool_choice: None,
-            additional_params: None,
+        // Constrain the reply to the schema: Ollama takes it as `format`,
+        // other providers get a tool they're required to call
+        let request = |structured: bool| {
+            let mut params = models.request_params(provider_name);
+            let mut tools = Vec::new();
+            let mut tool_choice = None;
+            if structured && provider_name == "ollama" {
+                let mut ollama = params.unwrap_or_else(|| serde_json::json!({}));
+                ollama["format"] = consolidation_schema();
+                params = Some(ollama);
+            } else if structured {
+                tools.push(ToolDefinition {
+                    name: SAVE_MEMORY_TOOL.to_string(),
+                    description: "Save the history entry and the updated long-term memory."
+                        .to_string(),
+                    parameters: consolidation_schema(),
+                });
+                tool_choice = Some(ToolChoice::Required);
+            }
+            CompletionRequest {
+                preamble: None,
+                chat_history: OneOrMany::one(Message::User {
+                    content: OneOrMany::one(UserContent::Text(Text {
+                        text: prompt.clone(),
+                    })),
+                }),
+                documents: Vec::new(),
+                tools,
+                temperature: Some(0.3),
+                max_tokens: Some(2048),
+                tool_choice,
+                additional_params: params,
+            }
         };
 
-        let response = match model.completion(request).await {
-            Ok(r) => r,
+        let timeout = models.timeout(provider_name);
+        let send = |request: CompletionRequest| async move {
+            models.throttle(provider_name, &request).await;
+            llm_timeout(provider_name, timeout, model.completion(request)).await
+        };
+        // Not every model takes tools or a schema; those get the plain
+        // prompt. A timeout isn't retried.
+        let response = match send(request(true)).await? {
+            Ok(response) => response,
             Err(e) => {
-                warn!("Memory consolidation LLM call failed: {e}");
-                return None;
+                warn!(
+                    "Memory consolidation: structured request to {provider_name} failed, \
+                     retrying without a schema: {e}"
+                );
+                send(request(false))
+                    .await?
+                    .map_err(|e| anyhow::anyhow!("the model call failed: {e}"))?
             }
         };
 
+        let mut response_text = String::new();
+        let mut arguments = None;
+        for content in response.choice.iter() {
+            match content {
+                AssistantContent::Text(t) => response_text.push_str(&t.text),
+                AssistantContent::ToolCall(tc) if tc.function.name == SAVE_MEMORY_TOOL => {
+                    arguments = Some(tc.function.arguments.clone());
+                }
+                _ => {}
+            }
+        }
+
         // Record consolidation usage
         if let Some(tracker) = usage_tracker {
-            tracker.record(&UsageRecord {
+            let mut record = UsageRecord {
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 session_key: task.session_key.clone(),
                 model: model_name.to_string(),
@@ -473,28 +755,22 @@ Respond with ONLY valid JSON, no markdown fences."#,
                 total_tokens: response.usage.total_tokens,
                 cached_input_tokens: response.usage.cached_input_tokens,
                 call_type: "consolidation".to_string(),
-            });
+                ..Default::default()
+            };
+            let output = match &arguments {
+                Some(args) => args.to_string(),
+                None => response_text.clone(),
+            };
+            record.fill_estimates(&prompt, &output);
+            tracker.record(&record);
         }
 
-        let response_text: String = response
-            .choice
-            .iter()
-            .filter_map(|c| match c {
-                AssistantContent::Text(t) => Some(t.text.clone()),
-                _ => None,
-            })
-            .collect();
-
-        debug!("Memory consolidation LLM response: {}", response_text);
-
-        let json_str = strip_markdown_fences(&response_text);
-
         debug!(
-            "Memory consolidation JSON after fence stripping: {}",
-            json_str
+            "Memory consolidation LLM response: {} (tool call: {:?})",
+            response_text, arguments
         );
 
-        let parsed: serde_json::Value = match serde_json::from_str(json_str) {
+        let parsed = match parse_consolidation_reply(&response_text, arguments.as_ref()) {
             Ok(v) => v,
             Err(e) => {
                 warn!(
@@ -506,63 +782,56 @@ Respond with ONLY valid JSON, no markdown fences."#,
                         &response_text
                     }
                 );
-                return None;
+                anyhow::bail!("the reply wasn't valid JSON: {e}");
             }
         };
-
-        // Write memory files directly using paths from the task
-        if let Some(entry) = parsed.get("history_entry").and_then(|e| e.as_str()) {
-            if let Some(parent) = task.history_path.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
-            match std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&task.history_path)
-            {
-                Ok(mut file) => {
-                    use std::io::Write;
-                    if let Err(e) = writeln!(file, "\n{entry}") {
-                        warn!("Failed to append history: {e}");
-                    } else {
-                        info!("Memory consolidation: appended history entry");
-                    }
-                }
-                Err(e) => warn!("Failed to open history file: {e}"),
-            }
-        }
-
-        if let Some(update) = parsed.get("memory_update").and_then(|u| u.as_str()) {
-            if let Some(parent) = task.memory_path.parent() {
-                let _ = std::fs::create_dir_all(parent);
-            }
-            match std::fs::write(&task.memory_path, update) {
-                Ok(()) => info!("Memory consolidation: updated long-term memory"),
-                Err(e) => warn!("Failed to update memory: {e}"),
-            }
+        let history_entry = parsed
+            .get("history_entry")
+            .and_then(|e| e.as_str())
+            .map(str::to_string);
+        let memory_update = parsed.get("memory_update").and_then(|u| u.as_str());
+        if history_entry.is_none() && memory_update.is_none() {
+            anyhow::bail!("the reply had neither history_entry nor memory_update");
         }
 
-        Some(ConsolidationResult {
-            session_key: task.session_key.clone(),
-            end: task.end,
+        Ok(ConsolidationProposal {
+            history_entry,
+            memory_update: memory_update.map(str::to_string),
         })
     }
 
     /// Apply a completed consolidation result to update session state.
     pub fn apply_consolidation(&mut self, result: &ConsolidationResult) {
-        if let Some(session) = self.sessions.sessions.get_mut(&result.session_key) {
-            session.last_consolidated = result.end;
-            if let Err(e) = self.sessions.save(&result.session_key) {
-                warn!(
-                    "Failed to persist session '{}' after consolidation: {e}",
-                    result.session_key
+        // Reloads the session if it was evicted from the cache meanwhile
+        match self.sessions.get_existing(&result.session_key) {
+            // A `/new` or a consolidation that finished first may have
+            // moved past this one; never move the mark back
+            Ok(Some(session))
+                if result.end <= session.last_consolidated
+                    || result.end > session.messages.len() =>
+            {
+                debug!(
+                    "Ignoring stale consolidation of '{}' up to {}",
+                    result.session_key, result.end
                 );
             }
-        } else {
-            warn!(
+            Ok(Some(session)) => {
+                session.last_consolidated = result.end;
+                if let Err(e) = self.sessions.save(&result.session_key) {
+                    warn!(
+                        "Failed to persist session '{}' after consolidation: {e}",
+                        result.session_key
+                    );
+                }
+            }
+            Ok(None) => warn!(
                 "Session '{}' no longer exists after consolidation",
                 result.session_key
-            );
+            ),
+            Err(e) => warn!(
+                "Failed to load session '{}' after consolidation: {e}",
+                result.session_key
+            ),
         }
 
         // Reindex memory after consolidation writes new content
@@ -573,38 +842,35 @@ Respond with ONLY valid JSON, no markdown fences."#,
         }
     }
 
-    /// Get the model handle for a given tier (cloned for use in spawned tasks).
-    pub fn model_for_tier(&self, tier: &str) -> CompletionModelHandle<'static> {
-        let (model, _, _) = self.models.get(tier);
-        model.clone()
-    }
-
     /// Consolidate old messages synchronously (convenience wrapper).
     /// Used by `/new` command and CLI interactive mode where blocking is acceptable.
-    pub async fn consolidate_memory(&mut self, session_key: &str, archive_all: bool) {
-        let task = match self.prepare_consolidation(session_key, archive_all) {
-            Some(t) => t,
-            None => return,
-        };
-        let (model, model_name, provider_name) = self.models.get("consolidation");
-        let model_name = model_name.to_string();
-        let provider_name = provider_name.to_string();
-        if let Some(result) = Self::run_consolidation(
-            model,
-            &task,
-            self.usage_tracker.as_ref(),
-            &model_name,
-            &provider_name,
-        )
-        .await
-        {
-            self.apply_consolidation(&result);
+    ///
+    /// Returns what was archived, or `None` if there was nothing new to
+    /// consolidate or the consolidation call failed.
+    pub async fn consolidate_memory(
+        &mut self,
+        session_key: &str,
+        archive_all: bool,
+    ) -> Option<ConsolidationResult> {
+        let task = self.prepare_consolidation(session_key, archive_all)?;
+        match Self::run_consolidation(&self.models, &task, self.usage_tracker.as_ref()).await {
+            Ok(result) => {
+                self.apply_consolidation(&result);
+                Some(result)
+            }
+            Err(e) => {
+                warn!("Memory consolidation of '{session_key}' failed: {e}");
+                None
+            }
         }
     }
 
     /// Run the LLM <> tool loop until the model produces a text response or max iterations.
     ///
-    /// Returns (response_text, tools_used, reasoning_content).
+    /// Returns (response_text, tools_used, reasoning_content). Directories
+    /// the tools work in are added to `working_dirs`, and the first call in
+    /// one with an AGENTS.md not yet in the prompt gets it with its result.
+    #[allow(clippy::too_many_arguments)]
     async fn run_loop(
         &self,
         session_key: &str,
@@ -613,21 +879,26 @@ Respond with ONLY valid JSON, no markdown fences."#,
         prompt: Message,
         tool_defs: &[ToolDefinition],
         tier: &str,
+        temperature: f64,
         agent_name: &str,
+        working_dirs: &mut Vec<PathBuf>,
     ) -> Result<(String, Vec<String>, Option<String>)> {
         let (model, model_name, provider_name) = self.models.get(tier);
         let model_name = model_name.to_string();
         let provider_name = provider_name.to_string();
+        let timeout = self.models.timeout(&provider_name);
         let mut tools_used = Vec::new();
         let mut current_prompt = prompt;
         let mut accumulated_reasoning = String::new();
         let mut consecutive_errors: usize = 0;
         const MAX_CONSECUTIVE_ERRORS: usize = 3;
+        let session = session_key.split_once(':');
+        let mut instructions_seen = self.context.agents_files_for(working_dirs);
 
         for iteration in 0..self.max_iterations {
             if Self::consume_interrupt(session_key) {
                 return Ok((
-                    "Interrupted.".to_string(),
+                    stopped_reply("", &tools_used),
                     tools_used,
                     if accumulated_reasoning.is_empty() {
                         None
@@ -645,11 +916,11 @@ Respond with ONLY valid JSON, no markdown fences."#,
             let (effective_temp, effective_max_tokens) =
                 if let Some(overrides) = self.model_overrides.find(&model_name) {
                     (
-                        overrides.temperature.unwrap_or(self.temperature),
+                        overrides.temperature.unwrap_or(temperature),
                         overrides.max_tokens.unwrap_or(self.max_tokens),
                     )
                 } else {
-                    (self.temperature, self.max_tokens)
+                    (temperature, self.max_tokens)
                 };
 
             debug!(
@@ -659,7 +930,7 @@ Respond with ONLY valid JSON, no markdown fences."#,
                 all_messages.len()
             );
 
-            let request = CompletionRequest {
+            let mut request = CompletionRequest {
                 preamble: Some(system_prompt.to_string()),
                 chat_history: OneOrMany::many(all_messages.clone())
                     .unwrap_or_else(|_| OneOrMany::one(current_prompt.clone())),
@@ -668,25 +939,78 @@ Respond with ONLY valid JSON, no markdown fences."#,
                 temperature: Some(effective_temp),
                 max_tokens: Some(effective_max_tokens),
                 tool_choice: None,
-                additional_params: None,
+                additional_params: self.models.request_params(&provider_name),
+            };
+
+            let hook_ctx = HookContext {
+                session_key,
+                agent: agent_name,
+                model: &model_name,
+                provider: &provider_name,
+                iteration: iteration + 1,
             };
+            for m in &self.middleware {
+                m.before_completion(&hook_ctx, &mut request).await?;
+            }
+            let queued = self.models.throttle(&provider_name, &request).await;
+            if !queued.is_zero() {
+                info!("Waited {queued:.2?} for {provider_name}'s rate limit");
+            }
+
+            if let Some(ref log) = self.session_log {
+                // The full context on the first call; after that only what's
+                // new (the tool results being sent back).
+                let mut data = serde_json::json!({
+                    "iteration": iteration + 1,
+                    "model": model_name,
+                    "provider": provider_name,
+                    "temperature": request.temperature,
+                    "max_tokens": request.max_tokens,
+                });
+                if !queued.is_zero() {
+                    data["queued_ms"] = serde_json::json!(queued.as_millis() as u64);
+                }
+                if iteration == 0 {
+                    data["system_prompt"] = serde_json::json!(request.preamble);
+                    data["messages"] = serde_json::json!(request.chat_history);
+                    data["tools"] = request.tools.iter().map(|t| t.name.clone()).collect();
+                } else {
+                    data["messages"] = serde_json::json!([request.chat_history.iter().last()]);
+                }
+                log.log(session_key, "request", data);
+            }
 
             let llm_start = std::time::Instant::now();
+            let completion_span = info_span!(
+                "completion",
+                iteration = iteration + 1,
+                model = %model_name,
+                provider = %provider_name,
+                queued_ms = queued.as_millis() as u64,
+                input_tokens = tracing::field::Empty,
+                output_tokens = tracing::field::Empty,
+            );
 
-            // Use streaming to get text chunks in real-time
-            let mut stream = model
-                .stream(request)
-                .await
-                .map_err(|e| anyhow::anyhow!("LLM stream error: {e}"))?;
+            // Use streaming to get text chunks in real-time. The timeout covers
+            // the wait for the stream to open and each gap between chunks, so a
+            // hung provider can't stall the loop but long generations still finish.
+            let mut stream = llm_timeout(&provider_name, timeout, model.stream(request))
+                .instrument(completion_span.clone())
+                .await?
+                .map_err(|e| ProviderError::classify(&provider_name, e))?;
 
             let mut has_tool_calls = false;
             let mut text_content = String::new();
             let mut tool_calls_to_execute: Vec<ToolCall> = Vec::new();
             let mut interrupted_during_stream = false;
+            let mut reported_usage = None;
 
             use futures::StreamExt;
             let mut chunk_count: usize = 0;
-            while let Some(chunk) = stream.next().await {
+            while let Some(chunk) = llm_timeout(&provider_name, timeout, stream.next())
+                .instrument(completion_span.clone())
+                .await?
+            {
                 match chunk {
                     Ok(rig::streaming::StreamedAssistantContent::Text(t)) => {
                         text_content.push_str(&t.text);
@@ -729,22 +1053,8 @@ Respond with ONLY valid JSON, no markdown fences."#,
                         accumulated_reasoning.push_str(&reasoning);
                     }
                     Ok(rig::streaming::StreamedAssistantContent::Final(ref resp)) => {
-                        // Extract usage from the final response
-                        if let Some(usage) = resp.token_usage() {
-                            if let Some(ref tracker) = self.usage_tracker {
-                                tracker.record(&UsageRecord {
-                                    timestamp: chrono::Utc::now().to_rfc3339(),
-                                    session_key: session_key.to_string(),
-                                    model: model_name.clone(),
-                                    provider: provider_name.clone(),
-                                    agent: agent_name.to_string(),
-                                    input_tokens: usage.input_tokens,
-                                    output_tokens: usage.output_tokens,
-                                    total_tokens: usage.total_tokens,
-                                    cached_input_tokens: usage.cached_input_tokens,
-                                    call_type: "chat".to_string(),
-                                });
-                            }
+                        reported_usage = resp.token_usage();
+                        if let Some(ref usage) = reported_usage {
                             if usage.cached_input_tokens > 0 {
                                 debug!(
                                     "Prompt cache hit: {} cached input tokens",
@@ -755,13 +1065,84 @@ Respond with ONLY valid JSON, no markdown fences."#,
                     }
                     Ok(_) => {} // ToolCallDelta — ignore partial tool call updates
                     Err(e) => {
-                        return Err(anyhow::anyhow!("LLM stream error: {e}"));
+                        return Err(ProviderError::classify(&provider_name, e).into());
                     }
                 }
             }
 
             let llm_elapsed = llm_start.elapsed();
 
+            let mut assistant_content = stream.choice.clone();
+            if !self.middleware.is_empty() && !interrupted_during_stream {
+                for m in &self.middleware {
+                    m.after_completion(&hook_ctx, &mut text_content, &mut tool_calls_to_execute)
+                        .await?;
+                }
+                has_tool_calls = !tool_calls_to_execute.is_empty();
+                assistant_content =
+                    rebuild_choice(&assistant_content, &text_content, &tool_calls_to_execute);
+            }
+
+            let mut record = UsageRecord {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                session_key: session_key.to_string(),
+                model: model_name.clone(),
+                provider: provider_name.clone(),
+                agent: agent_name.to_string(),
+                input_tokens: reported_usage.as_ref().map_or(0, |u| u.input_tokens),
+                output_tokens: reported_usage.as_ref().map_or(0, |u| u.output_tokens),
+                total_tokens: reported_usage.as_ref().map_or(0, |u| u.total_tokens),
+                cached_input_tokens: reported_usage.as_ref().map_or(0, |u| u.cached_input_tokens),
+                call_type: "chat".to_string(),
+                ..Default::default()
+            };
+            if record.input_tokens == 0 || record.output_tokens == 0 {
+                let prompt = format!(
+                    "{system_prompt}{}{}",
+                    serde_json::to_string(&all_messages).unwrap_or_default(),
+                    serde_json::to_string(tool_defs).unwrap_or_default()
+                );
+                let mut output = text_content.clone();
+                for tc in &tool_calls_to_execute {
+                    output.push_str(&tc.function.name);
+                    output.push_str(&tc.function.arguments.to_string());
+                }
+                if record.fill_estimates(&prompt, &output) {
+                    debug!("{provider_name} reported no token usage; recording an estimate");
+                }
+            }
+            completion_span.record("input_tokens", record.input_tokens);
+            completion_span.record("output_tokens", record.output_tokens);
+            if let Some(ref tracker) = self.usage_tracker {
+                tracker.record(&record);
+            }
+            drop(completion_span);
+
+            if let Some(ref log) = self.session_log {
+                let tool_calls: Vec<serde_json::Value> = tool_calls_to_execute
+                    .iter()
+                    .map(|tc| {
+                        serde_json::json!({
+                            "name": tc.function.name,
+                            "arguments": tc.function.arguments,
+                        })
+                    })
+                    .collect();
+                log.log(
+                    session_key,
+                    "response",
+                    serde_json::json!({
+                        "iteration": iteration + 1,
+                        "text": text_content,
+                        "tool_calls": tool_calls,
+                        "input_tokens": record.input_tokens,
+                        "output_tokens": record.output_tokens,
+                        "elapsed_ms": llm_elapsed.as_millis() as u64,
+                        "interrupted": interrupted_during_stream,
+                    }),
+                );
+            }
+
             let reasoning = if accumulated_reasoning.is_empty() {
                 None
             } else {
@@ -770,10 +1151,11 @@ Respond with ONLY valid JSON, no markdown fences."#,
 
             // If interrupted during streaming, return whatever text we have so far
             if interrupted_during_stream {
-                if text_content.is_empty() {
-                    text_content = "Interrupted.".into();
-                }
-                return Ok((text_content, tools_used, reasoning));
+                return Ok((
+                    stopped_reply(&text_content, &tools_used),
+                    tools_used,
+                    reasoning,
+                ));
             }
 
             if !has_tool_calls {
@@ -804,7 +1186,7 @@ Respond with ONLY valid JSON, no markdown fences."#,
             chat_history.push(current_prompt);
             chat_history.push(Message::Assistant {
                 id: None,
-                content: stream.choice.clone(),
+                content: assistant_content,
             });
 
             // Execute each tool call
@@ -814,7 +1196,7 @@ Respond with ONLY valid JSON, no markdown fences."#,
             for tc in &tool_calls_to_execute {
                 if Self::consume_interrupt(session_key) {
                     return Ok((
-                        "Interrupted.".to_string(),
+                        stopped_reply("", &tools_used),
                         tools_used,
                         if accumulated_reasoning.is_empty() {
                             None
@@ -825,9 +1207,17 @@ Respond with ONLY valid JSON, no markdown fences."#,
                 }
 
                 let tool_name = &tc.function.name;
-                let tool_args = &tc.function.arguments;
+                let mut tool_args = tc.function.arguments.clone();
                 tools_used.push(tool_name.clone());
 
+                let mut decision = ToolDecision::Run;
+                for m in &self.middleware {
+                    decision = m.before_tool(&hook_ctx, tool_name, &mut tool_args).await?;
+                    if decision != ToolDecision::Run {
+                        break;
+                    }
+                }
+
                 let args_preview = tool_args.to_string();
                 let preview = if args_preview.len() > 200 {
                     let end = floor_char_boundary(&args_preview, 200);
@@ -841,7 +1231,27 @@ Respond with ONLY valid JSON, no markdown fences."#,
                     self.max_iterations
                 );
 
-                let result = match self.tools.execute(tool_name, tool_args.clone()).await {
+                let tool_start = std::time::Instant::now();
+                let (outcome, skipped) = match decision {
+                    ToolDecision::Run => (
+                        self.tools
+                            .execute(tool_name, tool_args.clone())
+                            .instrument(info_span!("tool_call", tool = %tool_name))
+                            .await,
+                        false,
+                    ),
+                    ToolDecision::Skip(reason) => {
+                        info!("Tool call {tool_name} skipped by middleware");
+                        (Ok(reason), true)
+                    }
+                };
+                let outcome_ok = matches!(&outcome, Ok(r) if !r.starts_with("Error"));
+                let mut result = match outcome {
+                    // A skipped call counts as failed for the circuit breaker
+                    Ok(r) if skipped => {
+                        last_error.clone_from(&r);
+                        r
+                    }
                     Ok(r) => {
                         if r.starts_with("Error executing ") {
                             last_error.clone_from(&r);
@@ -865,6 +1275,44 @@ Respond with ONLY valid JSON, no markdown fences."#,
                 };
                 debug!("Tool result [{tool_name}]: {result_preview}");
 
+                if let Some(dir) = self
+                    .context
+                    .tool_dir(tool_name, &tool_args)
+                    .filter(|_| !skipped && outcome_ok)
+                {
+                    let new: Vec<PathBuf> = self
+                        .context
+                        .agents_files_for(std::slice::from_ref(&dir))
+                        .into_iter()
+                        .filter(|f| !instructions_seen.contains(f))
+                        .collect();
+                    if !new.is_empty() {
+                        result.push_str(&format!(
+                            "\n\n[Instructions for this directory]\n{}",
+                            self.context.nested_instructions(&new, session)
+                        ));
+                        instructions_seen.extend(new);
+                    }
+                    remember_working_dir(working_dirs, dir);
+                }
+
+                for m in &self.middleware {
+                    m.after_tool(&hook_ctx, tool_name, &mut result).await?;
+                }
+
+                if let Some(ref log) = self.session_log {
+                    log.log(
+                        session_key,
+                        "tool",
+                        serde_json::json!({
+                            "name": tool_name,
+                            "arguments": tool_args,
+                            "result": result,
+                            "elapsed_ms": tool_start.elapsed().as_millis() as u64,
+                        }),
+                    );
+                }
+
                 tool_results.push(UserContent::ToolResult(ToolResult {
                     id: tc.id.clone(),
                     call_id: tc.call_id.clone(),
@@ -928,6 +1376,88 @@ Respond with ONLY valid JSON, no markdown fences."#,
     }
 }
 
+/// The reply for a turn stopped by an interrupt: the text streamed so far
+/// and the tools it ran, so the user knows how far it got.
+fn stopped_reply(partial: &str, tools_used: &[String]) -> String {
+    let mut tools: Vec<&str> = Vec::new();
+    for tool in tools_used {
+        if !tools.contains(&tool.as_str()) {
+            tools.push(tool);
+        }
+    }
+    let note = match tools_used.len() {
+        0 => "Stopped.".to_string(),
+        n => format!(
+            "Stopped after {n} tool call{} ({}).",
+            if n == 1 { "" } else { "s" },
+            tools.join(", ")
+        ),
+    };
+    let partial = partial.trim_end();
+    if partial.is_empty() {
+        note
+    } else {
+        format!("{partial}\n\n[{note}]")
+    }
+}
+
+/// Rebuild an assistant message after middleware edited its text or tool
+/// calls, keeping any other parts (e.g. reasoning) from the original.
+fn rebuild_choice(
+    original: &OneOrMany<AssistantContent>,
+    text: &str,
+    tool_calls: &[ToolCall],
+) -> OneOrMany<AssistantContent> {
+    let mut parts: Vec<AssistantContent> = original
+        .iter()
+        .filter(|c| !matches!(c, AssistantContent::Text(_) | AssistantContent::ToolCall(_)))
+        .cloned()
+        .collect();
+    if !text.is_empty() {
+        parts.push(AssistantContent::Text(Text {
+            text: text.to_string(),
+        }));
+    }
+    parts.extend(tool_calls.iter().cloned().map(AssistantContent::ToolCall));
+    OneOrMany::many(parts).unwrap_or_else(|_| original.clone())
+}
+
+/// Tool the consolidation model is made to call with its reply.
+const SAVE_MEMORY_TOOL: &str = "save_memory";
+
+/// Schema of a consolidation reply, for the forced tool call or Ollama's
+/// `format`.
+fn consolidation_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "history_entry": {
+                "type": "string",
+                "description": "2-5 sentences summarizing the conversation, starting with [YYYY-MM-DD HH:MM]"
+            },
+            "memory_update": {
+                "type": "string",
+                "description": "The complete updated long-term memory"
+            }
+        },
+        "required": ["history_entry", "memory_update"]
+    })
+}
+
+/// The JSON object in a consolidation reply: the `save_memory` call's
+/// arguments when the model made one, else the text without fences.
+fn parse_consolidation_reply(
+    text: &str,
+    arguments: Option<&serde_json::Value>,
+) -> serde_json::Result<serde_json::Value> {
+    match arguments {
+        // Some providers pass the arguments through as a JSON string
+        Some(serde_json::Value::String(args)) => serde_json::from_str(args),
+        Some(args) => Ok(args.clone()),
+        None => serde_json::from_str(strip_markdown_fences(text)),
+    }
+}
+
 /// Strip markdown code fences from an LLM response to extract raw content.
 /// Handles ```json, ```, and plain text (no fences).
 fn strip_markdown_fences(text: &str) -> &str {
@@ -946,6 +1476,60 @@ fn strip_markdown_fences(text: &str) -> &str {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_llm_timeout() {
+        let ok = llm_timeout("ollama", Duration::from_secs(1), async { 42 }).await;
+        assert_eq!(ok.unwrap(), 42);
+
+        let err = llm_timeout(
+            "ollama",
+            Duration::from_millis(10),
+            std::future::pending::<()>(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ProviderError::Timeout { ref provider, secs: 0 } if provider == "ollama"
+        ));
+    }
+
+    #[test]
+    fn test_stopped_reply_reports_progress() {
+        assert_eq!(stopped_reply("", &[]), "Stopped.");
+        let tools = vec![
+            "web_search".to_string(),
+            "web_fetch".into(),
+            "web_search".into(),
+        ];
+        assert_eq!(
+            stopped_reply("", &tools),
+            "Stopped after 3 tool calls (web_search, web_fetch)."
+        );
+        assert_eq!(
+            stopped_reply("The first two results say \n", &tools[..1]),
+            "The first two results say\n\n[Stopped after 1 tool call (web_search).]"
+        );
+    }
+
+    #[test]
+    fn test_parse_consolidation_reply_prefers_the_tool_call() {
+        let args =
+            serde_json::json!({"history_entry": "[2026-01-01 10:00] Hi", "memory_update": "x"});
+        let parsed = parse_consolidation_reply("ignored", Some(&args)).unwrap();
+        assert_eq!(parsed, args);
+
+        let as_string = serde_json::Value::String(args.to_string());
+        assert_eq!(
+            parse_consolidation_reply("", Some(&as_string)).unwrap(),
+            args
+        );
+
+        let fenced = format!("```json\n{args}\n```");
+        assert_eq!(parse_consolidation_reply(&fenced, None).unwrap(), args);
+        assert!(parse_consolidation_reply("Sure! Here's the JSON", None).is_err());
+    }
+
     #[test]
     fn test_strip_markdown_fences_json() {
         let input = "```json\n{\"key\": \"value\"}\n```";
@@ -1,6 +1,23 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use anyhow::Result;
+use serde::Serialize;
+
+/// One line of MEMORY.md, as written by consolidation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemoryFact {
+    /// 1-based line number in MEMORY.md.
+    pub line: usize,
+    /// The line without indentation or a list marker.
+    pub text: String,
+    /// The nearest heading above the line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+}
+
+/// Where project memory lives, relative to the memory directory.
+pub const PROJECTS_DIR: &str = "projects";
 
 /// Two-layer memory system: MEMORY.md (facts) + HISTORY.md (event log).
 #[derive(Clone)]
@@ -11,9 +28,24 @@ pub struct MemoryStore {
 
 impl MemoryStore {
     pub fn new(workspace: &std::path::Path) -> Self {
+        Self::in_dir(&workspace.join("memory"))
+    }
+
+    /// The memory of `project`, under `projects/<project>/` in the memory
+    /// directory (`memory/` in the workspace, or the Obsidian vault's
+    /// folder), or the shared memory for `None`.
+    pub fn for_project(memory_dir: &Path, project: Option<&str>) -> Self {
+        match project {
+            Some(project) => Self::in_dir(&memory_dir.join(PROJECTS_DIR).join(project)),
+            None => Self::in_dir(memory_dir),
+        }
+    }
+
+    /// The memory with MEMORY.md and HISTORY.md directly in `dir`.
+    pub fn in_dir(dir: &Path) -> Self {
         Self {
-            memory_path: workspace.join("memory").join("MEMORY.md"),
-            history_path: workspace.join("memory").join("HISTORY.md"),
+            memory_path: dir.join("MEMORY.md"),
+            history_path: dir.join("HISTORY.md"),
         }
     }
 
@@ -40,6 +72,126 @@ impl MemoryStore {
         Ok(std::fs::write(&self.memory_path, content)?)
     }
 
+    /// Split MEMORY.md into facts: every non-blank line that isn't a heading.
+    pub fn facts(&self) -> Result<Vec<MemoryFact>> {
+        Ok(parse_facts(&self.read_long_term()?))
+    }
+
+    /// Replace the fact on `line`, keeping its indentation and list marker.
+    ///
+    /// `expected` is the fact's current text. Returns `false` without writing
+    /// if the line no longer holds it (e.g. consolidation rewrote the file).
+    pub fn edit_fact(&self, line: usize, expected: &str, text: &str) -> Result<bool> {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            return self.delete_fact(line, expected);
+        }
+        self.rewrite_fact(line, expected, |prefix| Some(format!("{prefix}{text}")))
+    }
+
+    /// Remove the fact on `line`. See [`MemoryStore::edit_fact`] for `expected`.
+    pub fn delete_fact(&self, line: usize, expected: &str) -> Result<bool> {
+        self.rewrite_fact(line, expected, |_| None)
+    }
+
+    /// Facts that mention every word of `topic` (case-insensitive).
+    pub fn facts_matching(&self, topic: &str) -> Result<Vec<MemoryFact>> {
+        Ok(self
+            .facts()?
+            .into_iter()
+            .filter(|f| mentions(&f.text, topic))
+            .collect())
+    }
+
+    /// HISTORY.md entries (blank-line separated) that mention every word of
+    /// `topic`.
+    pub fn history_matching(&self, topic: &str) -> Result<Vec<String>> {
+        Ok(self
+            .history_entries()?
+            .into_iter()
+            .filter(|e| mentions(e, topic))
+            .collect())
+    }
+
+    /// HISTORY.md entries dated `since` or later, oldest first. Entries
+    /// without a leading `[YYYY-MM-DD ...]` timestamp are skipped.
+    pub fn history_since(&self, since: chrono::NaiveDate) -> Result<Vec<String>> {
+        Ok(self
+            .history_entries()?
+            .into_iter()
+            .filter(|e| {
+                e.strip_prefix('[')
+                    .and_then(|e| e.get(..10))
+                    .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                    .is_some_and(|date| date >= since)
+            })
+            .collect())
+    }
+
+    /// Remove these entries from HISTORY.md. Returns how many were removed.
+    pub fn remove_history(&self, entries: &[String]) -> Result<usize> {
+        let all = self.history_entries()?;
+        let kept: Vec<&String> = all.iter().filter(|e| !entries.contains(e)).collect();
+        let removed = all.len() - kept.len();
+        if removed > 0 {
+            let content: String = kept.iter().map(|e| format!("\n{e}\n")).collect();
+            std::fs::write(&self.history_path, content)?;
+        }
+        Ok(removed)
+    }
+
+    fn history_entries(&self) -> Result<Vec<String>> {
+        if !self.history_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.history_path)?;
+        let mut entries = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for line in content.lines() {
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    entries.push(current.join("\n"));
+                    current.clear();
+                }
+            } else {
+                current.push(line);
+            }
+        }
+        if !current.is_empty() {
+            entries.push(current.join("\n"));
+        }
+        Ok(entries)
+    }
+
+    fn rewrite_fact(
+        &self,
+        line: usize,
+        expected: &str,
+        replace: impl FnOnce(&str) -> Option<String>,
+    ) -> Result<bool> {
+        let content = self.read_long_term()?;
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let Some(current) = line.checked_sub(1).and_then(|i| lines.get(i)) else {
+            return Ok(false);
+        };
+        let (prefix, text) = split_marker(current);
+        if text != expected.trim() || current.trim_start().starts_with('#') {
+            return Ok(false);
+        }
+        match replace(prefix) {
+            Some(new) => lines[line - 1] = new,
+            None => {
+                lines.remove(line - 1);
+            }
+        }
+        let mut updated = lines.join("\n");
+        if content.ends_with('\n') {
+            updated.push('\n');
+        }
+        self.write_long_term(&updated)?;
+        Ok(true)
+    }
+
     pub fn append_history(&self, entry: &str) -> Result<()> {
         use std::io::Write;
         if let Some(parent) = self.history_path.parent() {
@@ -54,6 +206,173 @@ impl MemoryStore {
     }
 }
 
+/// Facts in MEMORY.md `content`: every non-blank line that isn't a heading.
+fn parse_facts(content: &str) -> Vec<MemoryFact> {
+    let mut section = None;
+    let mut facts = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            section = Some(heading.trim_start_matches('#').trim().to_string());
+        } else if !trimmed.is_empty() {
+            facts.push(MemoryFact {
+                line: i + 1,
+                text: split_marker(line).1.to_string(),
+                section: section.clone(),
+            });
+        }
+    }
+    facts
+}
+
+/// Facts in `after` that weren't in `before` (new or reworded), in order.
+pub fn new_facts(before: &str, after: &str) -> Vec<String> {
+    let known: Vec<String> = parse_facts(before).into_iter().map(|f| f.text).collect();
+    parse_facts(after)
+        .into_iter()
+        .map(|f| f.text)
+        .filter(|text| !known.contains(text))
+        .collect()
+}
+
+/// A project name as typed, lowercased, if it's usable as a directory:
+/// 1-40 letters, digits, `-`, or `_`.
+pub fn project_name(input: &str) -> Option<String> {
+    let name = input.trim().to_lowercase();
+    let valid = (1..=40).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(name)
+}
+
+/// Projects with memory in `memory_dir`, sorted.
+pub fn projects(memory_dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(memory_dir.join(PROJECTS_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// The project the session being answered is attached to. The agent loop
+/// sets it for each turn and `memory_search` reads it.
+#[derive(Clone, Default)]
+pub struct CurrentProject(Arc<RwLock<Option<String>>>);
+
+impl CurrentProject {
+    pub fn get(&self) -> Option<String> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set(&self, project: Option<String>) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = project;
+    }
+}
+
+/// Write a consolidation: `memory_update` replaces MEMORY.md, then
+/// `history_entry` is appended to HISTORY.md. MEMORY.md goes first because
+/// rewriting it is harmless on a retry; appending the entry twice isn't.
+pub fn save_consolidation(
+    memory_path: &Path,
+    history_path: &Path,
+    memory_update: Option<&str>,
+    history_entry: Option<&str>,
+) -> Result<()> {
+    if let Some(update) = memory_update {
+        if let Some(parent) = memory_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        std::fs::write(memory_path, update)
+            .map_err(|e| anyhow::anyhow!("couldn't write MEMORY.md: {e}"))?;
+    }
+    if let Some(entry) = history_entry {
+        if let Some(parent) = history_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history_path)
+            .and_then(|mut file| {
+                use std::io::Write;
+                writeln!(file, "\n{entry}")
+            })
+            .map_err(|e| anyhow::anyhow!("couldn't append to HISTORY.md: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Line diff from `before` to `after`: removed lines as "- line" and added
+/// ones as "+ line", in file order. Unchanged and blank lines are left out.
+pub fn diff_lines(before: &str, after: &str) -> Vec<String> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+    // common[i][j]: longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            if !old[i].trim().is_empty() {
+                diff.push(format!("- {}", old[i]));
+            }
+            i += 1;
+        } else {
+            if !new[j].trim().is_empty() {
+                diff.push(format!("+ {}", new[j]));
+            }
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Whether `text` contains every whitespace-separated word of `topic`.
+fn mentions(text: &str, topic: &str) -> bool {
+    let text = text.to_lowercase();
+    let mut words = topic.split_whitespace().peekable();
+    words.peek().is_some() && words.all(|w| text.contains(&w.to_lowercase()))
+}
+
+/// Split a line into its indentation plus list marker, and the text after it.
+fn split_marker(line: &str) -> (&str, &str) {
+    let body = line.trim_start();
+    let indent = line.len() - body.len();
+    let marker = ["- [ ] ", "- [x] ", "- ", "* ", "+ "]
+        .iter()
+        .find(|m| body.starts_with(*m))
+        .map_or_else(|| ordered_marker(body), |m| m.len());
+    (&line[..indent + marker], line[indent + marker..].trim_end())
+}
+
+/// Length of an ordered list marker such as "12. ", or 0.
+fn ordered_marker(body: &str) -> usize {
+    let digits = body.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && body[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,6 +446,149 @@ mod tests {
         assert_eq!(content, "new content");
     }
 
+    #[test]
+    fn test_facts_track_lines_and_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::new(dir.path());
+        store
+            .write_long_term("# Memory\n\n## Preferences\n- Likes Rust\n  * Uses vim\n\n1. Lives in Oslo\nNo marker\n")
+            .unwrap();
+
+        let facts = store.facts().unwrap();
+        let texts: Vec<(usize, &str)> = facts.iter().map(|f| (f.line, f.text.as_str())).collect();
+        assert_eq!(
+            texts,
+            [
+                (4, "Likes Rust"),
+                (5, "Uses vim"),
+                (7, "Lives in Oslo"),
+                (8, "No marker")
+            ]
+        );
+        assert_eq!(facts[0].section.as_deref(), Some("Preferences"));
+    }
+
+    #[test]
+    fn test_edit_and_delete_fact() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::new(dir.path());
+        store
+            .write_long_term("## Location\n  - Lives in Oslo\n- Has a cat\n")
+            .unwrap();
+
+        assert!(store
+            .edit_fact(2, "Lives in Oslo", "Lives in\nBergen")
+            .unwrap());
+        assert_eq!(
+            store.read_long_term().unwrap(),
+            "## Location\n  - Lives in Bergen\n- Has a cat\n"
+        );
+
+        // Stale text, headings, and missing lines are left alone
+        assert!(!store.delete_fact(2, "Lives in Oslo").unwrap());
+        assert!(!store.delete_fact(1, "Location").unwrap());
+        assert!(!store.delete_fact(9, "Has a cat").unwrap());
+
+        assert!(store.delete_fact(3, "Has a cat").unwrap());
+        assert_eq!(
+            store.read_long_term().unwrap(),
+            "## Location\n  - Lives in Bergen\n"
+        );
+    }
+
+    #[test]
+    fn test_matching_facts_and_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::new(dir.path());
+        store
+            .write_long_term("- Partner is Sam\n- Works at Acme\n- Sam likes hiking\n")
+            .unwrap();
+        store
+            .append_history("[2026-01-02 10:00] Planned a birthday dinner for sam.")
+            .unwrap();
+        store
+            .append_history("[2026-01-03 09:00] Debugged a build.\nFixed CI.")
+            .unwrap();
+
+        let facts = store.facts_matching("SAM").unwrap();
+        assert_eq!(facts.len(), 2);
+        assert_eq!(store.facts_matching("sam hiking").unwrap().len(), 1);
+        assert!(store.facts_matching("  ").unwrap().is_empty());
+
+        let history = store.history_matching("sam").unwrap();
+        assert_eq!(
+            history,
+            ["[2026-01-02 10:00] Planned a birthday dinner for sam."]
+        );
+        assert_eq!(store.remove_history(&history).unwrap(), 1);
+        assert!(store.history_matching("sam").unwrap().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(store.history_path()).unwrap(),
+            "\n[2026-01-03 09:00] Debugged a build.\nFixed CI.\n"
+        );
+    }
+
+    #[test]
+    fn test_history_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::new(dir.path());
+        store
+            .append_history("[2026-03-01 09:00] Old news.")
+            .unwrap();
+        store.append_history("Undated entry.").unwrap();
+        store
+            .append_history("[2026-03-09 18:30] Shipped v2.")
+            .unwrap();
+        let since = chrono::NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        assert_eq!(
+            store.history_since(since).unwrap(),
+            ["[2026-03-09 18:30] Shipped v2."]
+        );
+    }
+
+    #[test]
+    fn test_new_facts() {
+        let before = "# User\n- Lives in Lisbon\n- Likes tea\n";
+        let after = "# User\n- Lives in Lisbon\n- Likes green tea\n\n# Projects\n1. patina\n";
+        assert_eq!(new_facts(before, after), ["Likes green tea", "patina"]);
+        assert!(new_facts(after, after).is_empty());
+    }
+
+    #[test]
+    fn test_project_memory_is_kept_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory_dir = dir.path().join("memory");
+        let work = MemoryStore::for_project(&memory_dir, Some("acme"));
+        assert_eq!(
+            work.memory_path(),
+            dir.path().join("memory/projects/acme/MEMORY.md")
+        );
+        work.write_long_term("- Deploys on Fridays\n").unwrap();
+        assert_eq!(MemoryStore::new(dir.path()).read_long_term().unwrap(), "");
+        assert_eq!(projects(&memory_dir), ["acme"]);
+
+        assert_eq!(project_name(" Acme-2 ").as_deref(), Some("acme-2"));
+        assert_eq!(project_name("../etc"), None);
+        assert_eq!(project_name(""), None);
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let before = "# User\n- Lives in Lisbon\n- Likes tea\n";
+        let after = "# User\n- Lives in Lisbon\n- Likes green tea\n\n# Projects\n1. patina\n";
+        assert_eq!(
+            diff_lines(before, after),
+            [
+                "- - Likes tea",
+                "+ - Likes green tea",
+                "+ # Projects",
+                "+ 1. patina"
+            ]
+        );
+        assert!(diff_lines(before, before).is_empty());
+        assert_eq!(diff_lines("", "- New"), ["+ - New"]);
+    }
+
     #[test]
     fn test_unicode_content() {
         let dir = tempfile::tempdir().unwrap();
@@ -0,0 +1,310 @@
+//! Memory updates held until the owner approves them.
+//!
+//! With `agents.defaults.memoryApprovalChannels` set, consolidating a
+//! conversation on one of those channels writes nothing. The proposed
+//! MEMORY.md and HISTORY.md changes are saved as a [`PendingMemory`] and
+//! shown to the owner in `tools.askUser.ownerChat`, who saves or drops them
+//! with `/memory`. They're kept in `~/.patina/memory_approvals.json`, so
+//! they survive a restart.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::agent::memory::{diff_lines, save_consolidation};
+use crate::agent::obsidian::{note_mentions, Vault};
+use crate::bus::OutboundMessage;
+
+/// Most MEMORY.md diff lines in a review notice.
+const MAX_NOTICE_LINES: usize = 30;
+
+/// A consolidation waiting for approval.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingMemory {
+    pub id: String,
+    pub session_key: String,
+    /// Number of messages it covers.
+    pub messages: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_entry: Option<String>,
+    /// The complete new MEMORY.md.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_update: Option<String>,
+    /// MEMORY.md when the update was proposed.
+    pub base_memory: String,
+    pub memory_path: PathBuf,
+    pub history_path: PathBuf,
+    pub created_at_ms: i64,
+}
+
+impl PendingMemory {
+    /// What the update would change, with at most `max_lines` lines of the
+    /// MEMORY.md diff.
+    pub fn preview(&self, max_lines: usize) -> String {
+        preview(
+            self.history_entry.as_deref(),
+            &self.base_memory,
+            self.memory_update.as_deref(),
+            max_lines,
+        )
+    }
+
+    /// The message that asks the owner to review it.
+    pub fn review_notice(&self) -> String {
+        format!(
+            "Memory update {id} from {} ({} messages) is waiting for approval.\n\n{}\n\n\
+             /memory approve {id} saves it, and /memory reject {id} drops it.",
+            self.session_key,
+            self.messages,
+            self.preview(MAX_NOTICE_LINES),
+            id = self.id
+        )
+    }
+}
+
+/// A proposed consolidation as text: the HISTORY.md entry, then the
+/// MEMORY.md diff from `before`.
+pub fn preview(
+    history_entry: Option<&str>,
+    before: &str,
+    memory_update: Option<&str>,
+    max_lines: usize,
+) -> String {
+    let mut out = match history_entry {
+        Some(entry) => format!("HISTORY.md entry:\n{}", entry.trim()),
+        None => "No HISTORY.md entry.".to_string(),
+    };
+    let diff = memory_update.map_or_else(Vec::new, |after| diff_lines(before, after));
+    if diff.is_empty() {
+        out.push_str("\n\nNo changes to MEMORY.md.");
+        return out;
+    }
+    out.push_str("\n\nMEMORY.md changes:");
+    for line in diff.iter().take(max_lines) {
+        out.push('\n');
+        out.push_str(line);
+    }
+    if diff.len() > max_lines {
+        out.push_str(&format!("\n...and {} more lines", diff.len() - max_lines));
+    }
+    out
+}
+
+/// The approval policy and the file of waiting updates. Like
+/// [`DraftStore`](crate::drafts::DraftStore), the file is re-read before
+/// every change.
+pub struct MemoryApprovals {
+    path: PathBuf,
+    channels: Vec<String>,
+    /// Where review notices go, as `channel:chat_id`.
+    review: Option<(String, broadcast::Sender<OutboundMessage>)>,
+    /// Obsidian vault whose linked pages get approved history entries.
+    vault: Option<Arc<Vault>>,
+    /// Held around each read-modify-write of the file.
+    lock: Mutex<()>,
+}
+
+impl MemoryApprovals {
+    /// Hold updates from `channels` (`"*"` for all) in `path`.
+    pub fn new(path: &Path, channels: Vec<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            channels,
+            review: None,
+            vault: None,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Show each held update to the owner in `review_chat`
+    /// (`channel:chat_id`). Without it they only see them with `/memory`.
+    pub fn with_review(
+        mut self,
+        review_chat: Option<&str>,
+        outbound_tx: broadcast::Sender<OutboundMessage>,
+    ) -> Self {
+        self.review = review_chat
+            .filter(|r| r.contains(':'))
+            .map(|r| (r.to_string(), outbound_tx));
+        self
+    }
+
+    /// Add approved history entries to the vault pages they link.
+    pub fn with_vault(mut self, vault: Option<Arc<Vault>>) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    /// Whether consolidating `session_key` has to wait for approval.
+    pub fn applies_to(&self, session_key: &str) -> bool {
+        let channel = session_key.split(':').next().unwrap_or_default();
+        self.channels.iter().any(|c| c == "*" || c == channel)
+    }
+
+    /// Waiting updates, oldest first.
+    pub fn list(&self) -> Vec<PendingMemory> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)
+                .unwrap_or_else(|e| {
+                    warn!(
+                        "Failed to parse memory approvals {}: {e}",
+                        self.path.display()
+                    );
+                    Vec::new()
+                }),
+            _ => Vec::new(),
+        }
+    }
+
+    fn save(&self, pending: &[PendingMemory]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(pending)?)?;
+        Ok(())
+    }
+
+    /// Save an update to wait for approval. The id and creation time are
+    /// assigned here.
+    pub fn hold(&self, mut update: PendingMemory) -> Result<PendingMemory> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pending = self.list();
+        update.id = uuid::Uuid::new_v4().to_string()[..8].to_string();
+        update.created_at_ms = chrono::Utc::now().timestamp_millis();
+        pending.push(update.clone());
+        self.save(&pending)?;
+        info!(
+            "Held memory update {} from '{}' for approval",
+            update.id, update.session_key
+        );
+        if let Some((review_chat, outbound_tx)) = &self.review {
+            let (channel, chat_id) = review_chat.split_once(':').unwrap_or_default();
+            if let Err(e) = outbound_tx.send(OutboundMessage {
+                channel: channel.to_string(),
+                chat_id: chat_id.to_string(),
+                content: update.review_notice(),
+                reply_to: None,
+                metadata: HashMap::new(),
+            }) {
+                warn!("Failed to publish memory review notice to bus: {e}");
+            }
+        }
+        Ok(update)
+    }
+
+    /// Write a waiting update to MEMORY.md and HISTORY.md. If MEMORY.md
+    /// changed since the update was proposed, it's only written with
+    /// `anyway`, since it would undo those changes. Returns the reply, or
+    /// `None` if there's no such update.
+    pub fn approve(&self, id: &str, anyway: bool) -> Result<Option<String>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pending = self.list();
+        let Some(pos) = pending.iter().position(|p| p.id == id) else {
+            return Ok(None);
+        };
+        let update = &pending[pos];
+        let current = std::fs::read_to_string(&update.memory_path).unwrap_or_default();
+        if update.memory_update.is_some() && current != update.base_memory && !anyway {
+            return Ok(Some(format!(
+                "MEMORY.md has changed since update {id} was proposed, and saving it would \
+                 undo those changes. Send /memory approve {id} anyway to save it regardless, \
+                 or /memory reject {id}."
+            )));
+        }
+        save_consolidation(
+            &update.memory_path,
+            &update.history_path,
+            update.memory_update.as_deref(),
+            update.history_entry.as_deref(),
+        )?;
+        if let (Some(vault), Some(entry)) = (&self.vault, &update.history_entry) {
+            note_mentions(vault, entry);
+        }
+        let update = pending.remove(pos);
+        self.save(&pending)?;
+        info!(
+            "Saved memory update {id} from '{}' after approval",
+            update.session_key
+        );
+        Ok(Some(format!(
+            "Saved memory update {id} from {}.",
+            update.session_key
+        )))
+    }
+
+    /// Drop a waiting update without writing it.
+    pub fn reject(&self, id: &str) -> Result<Option<PendingMemory>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pending = self.list();
+        let Some(pos) = pending.iter().position(|p| p.id == id) else {
+            return Ok(None);
+        };
+        let update = pending.remove(pos);
+        self.save(&pending)?;
+        Ok(Some(update))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_held_update_is_written_on_approval() {
+        let dir = tempfile::tempdir().unwrap();
+        let (outbound_tx, mut outbound_rx) = broadcast::channel(4);
+        let approvals =
+            MemoryApprovals::new(&dir.path().join("approvals.json"), vec!["discord".into()])
+                .with_review(Some("telegram:1"), outbound_tx);
+        assert!(approvals.applies_to("discord:42") && !approvals.applies_to("telegram:1"));
+
+        let memory_path = dir.path().join("MEMORY.md");
+        let history_path = dir.path().join("HISTORY.md");
+        std::fs::write(&memory_path, "- Likes tea\n").unwrap();
+        let update = approvals
+            .hold(PendingMemory {
+                session_key: "discord:42".into(),
+                messages: 6,
+                history_entry: Some("[2026-05-01 09:00] Talked about tea.".into()),
+                memory_update: Some("- Likes green tea\n".into()),
+                base_memory: "- Likes tea\n".into(),
+                memory_path: memory_path.clone(),
+                history_path: history_path.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+        let notice = outbound_rx.try_recv().unwrap();
+        assert_eq!(notice.chat_id, "1");
+        assert!(notice
+            .content
+            .contains("- - Likes tea\n+ - Likes green tea"));
+        assert_eq!(approvals.list(), std::slice::from_ref(&update));
+
+        // Someone else changed MEMORY.md meanwhile
+        std::fs::write(&memory_path, "- Likes tea\n- Has a cat\n").unwrap();
+        let reply = approvals.approve(&update.id, false).unwrap().unwrap();
+        assert!(reply.starts_with("MEMORY.md has changed"));
+        assert!(!history_path.exists());
+
+        let reply = approvals.approve(&update.id, true).unwrap().unwrap();
+        assert_eq!(
+            reply,
+            format!("Saved memory update {} from discord:42.", update.id)
+        );
+        assert_eq!(
+            std::fs::read_to_string(&memory_path).unwrap(),
+            "- Likes green tea\n"
+        );
+        assert!(std::fs::read_to_string(&history_path)
+            .unwrap()
+            .contains("Talked about tea."));
+        assert!(approvals.list().is_empty());
+        assert_eq!(approvals.reject(&update.id).unwrap(), None);
+    }
+}
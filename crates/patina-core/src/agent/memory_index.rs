@@ -1,16 +1,92 @@
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
 use rusqlite::Connection;
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use tracing::{debug, info, warn};
 
+use crate::agent::obsidian::Vault;
+use crate::db::{Db, Migration, Step};
+
+/// The index schema.
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "files, chunks, and full-text search",
+    up: Step::Sql(
+        "CREATE TABLE IF NOT EXISTS files (
+            path TEXT PRIMARY KEY,
+            hash TEXT NOT NULL,
+            mtime INTEGER NOT NULL,
+            size INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS chunks (
+            id TEXT PRIMARY KEY,
+            path TEXT NOT NULL,
+            start_line INTEGER NOT NULL,
+            end_line INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            text TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_chunks_path ON chunks(path);
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+            text,
+            content=chunks,
+            content_rowid=rowid
+        );
+
+        -- Triggers to keep FTS in sync with chunks table
+        CREATE TRIGGER IF NOT EXISTS chunks_ai AFTER INSERT ON chunks BEGIN
+            INSERT INTO chunks_fts(rowid, text)
+            VALUES (new.rowid, new.text);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS chunks_ad AFTER DELETE ON chunks BEGIN
+            INSERT INTO chunks_fts(chunks_fts, rowid, text)
+            VALUES ('delete', old.rowid, old.text);
+        END;
+
+        -- Only text changes touch FTS; reindexing shifts line numbers often
+        DROP TRIGGER IF EXISTS chunks_au;
+        CREATE TRIGGER chunks_au AFTER UPDATE OF text ON chunks BEGIN
+            INSERT INTO chunks_fts(chunks_fts, rowid, text)
+            VALUES ('delete', old.rowid, old.text);
+            INSERT INTO chunks_fts(rowid, text)
+            VALUES (new.rowid, new.text);
+        END;",
+    ),
+}];
+
 /// A chunk of text from an indexed file.
-struct Chunk {
-    text: String,
-    start_line: usize,
-    end_line: usize,
+pub(crate) struct Chunk {
+    pub(crate) text: String,
+    pub(crate) start_line: usize,
+    pub(crate) end_line: usize,
+}
+
+/// A file's row in the `files` table.
+struct IndexedFile {
+    hash: String,
+    /// Modification time in nanoseconds since the epoch.
+    mtime: i64,
+    size: i64,
+}
+
+/// What a reindex found for one workspace file.
+enum Scanned {
+    /// Size and mtime match the index, so it wasn't read.
+    Unchanged,
+    /// Same content as indexed, under a new mtime or size.
+    Touched(IndexedFile),
+    /// New or changed content, chunked, with each chunk's hash.
+    Changed(IndexedFile, Vec<(Chunk, String)>),
+    Failed,
 }
 
 /// A search result from the FTS5 index.
@@ -22,216 +98,249 @@ pub struct SearchResult {
     pub score: f64,
 }
 
+/// How much the index holds.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub files: u64,
+    pub chunks: u64,
+    /// Total size of the indexed files.
+    pub bytes: u64,
+}
+
 /// Full-text search index over workspace markdown files using SQLite FTS5.
 ///
 /// The index is a regenerable cache — flat files remain the source of truth.
 /// Database is stored at `~/.patina/memory.sqlite` (safe to delete).
 pub struct MemoryIndex {
-    conn: Mutex<Connection>,
+    db: Db,
     workspace: PathBuf,
+    /// Obsidian vault indexed along with the workspace.
+    vault: Option<Arc<Vault>>,
 }
 
 impl MemoryIndex {
     /// Open (or create) the memory index database.
     pub fn new(workspace: &Path, db_path: &Path) -> Result<Self> {
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
-        let conn = Connection::open(db_path)?;
-        conn.execute_batch("PRAGMA journal_mode=WAL;")?;
-
-        // Create schema
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS files (
-                path TEXT PRIMARY KEY,
-                hash TEXT NOT NULL,
-                mtime INTEGER NOT NULL,
-                size INTEGER NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS chunks (
-                id TEXT PRIMARY KEY,
-                path TEXT NOT NULL,
-                start_line INTEGER NOT NULL,
-                end_line INTEGER NOT NULL,
-                hash TEXT NOT NULL,
-                text TEXT NOT NULL,
-                updated_at INTEGER NOT NULL
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_chunks_path ON chunks(path);
-
-            CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
-                text,
-                content=chunks,
-                content_rowid=rowid
-            );
-
-            -- Triggers to keep FTS in sync with chunks table
-            CREATE TRIGGER IF NOT EXISTS chunks_ai AFTER INSERT ON chunks BEGIN
-                INSERT INTO chunks_fts(rowid, text)
-                VALUES (new.rowid, new.text);
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS chunks_ad AFTER DELETE ON chunks BEGIN
-                INSERT INTO chunks_fts(chunks_fts, rowid, text)
-                VALUES ('delete', old.rowid, old.text);
-            END;
-
-            CREATE TRIGGER IF NOT EXISTS chunks_au AFTER UPDATE ON chunks BEGIN
-                INSERT INTO chunks_fts(chunks_fts, rowid, text)
-                VALUES ('delete', old.rowid, old.text);
-                INSERT INTO chunks_fts(rowid, text)
-                VALUES (new.rowid, new.text);
-            END;",
-        )?;
-
         Ok(Self {
-            conn: Mutex::new(conn),
+            db: Db::open(db_path, MIGRATIONS)?,
             workspace: workspace.to_path_buf(),
+            vault: None,
         })
     }
 
-    fn lock_conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
-        self.conn
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Lock poisoned: {e}"))
-    }
-
     /// Reindex all markdown files in the workspace.
-    /// Skips unchanged files (by SHA-256 hash). Removes stale entries.
+    ///
+    /// Files whose size and mtime match the index aren't read. The rest are
+    /// read, hashed, and chunked in parallel; within a changed file only the
+    /// chunks whose text changed are rewritten. All writes go in one
+    /// transaction. Removes stale entries.
     pub fn reindex(&self) -> Result<()> {
-        let pattern = self.workspace.join("**/*.md").to_string_lossy().to_string();
-
-        let paths: Vec<PathBuf> = glob::glob(&pattern)
-            .map_err(|e| anyhow::anyhow!("Invalid glob pattern: {e}"))?
-            .filter_map(|entry| entry.ok())
-            .filter(|p| p.is_file())
+        let started = Instant::now();
+        let files: Vec<(String, PathBuf)> = self
+            .markdown_files()?
+            .into_iter()
+            .map(|path| {
+                let rel_path = path
+                    .strip_prefix(&self.workspace)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .to_string();
+                (rel_path, path)
+            })
             .collect();
 
-        let conn = self.lock_conn()?;
-
-        let mut indexed_paths: Vec<String> = Vec::new();
-        let mut changed = 0usize;
-        let mut skipped = 0usize;
-
-        for path in &paths {
-            let rel_path = path
-                .strip_prefix(&self.workspace)
-                .unwrap_or(path)
-                .to_string_lossy()
-                .to_string();
-
-            indexed_paths.push(rel_path.clone());
+        let known: HashMap<String, IndexedFile> = {
+            let conn = self.db.conn()?;
+            let mut stmt = conn.prepare("SELECT path, hash, mtime, size FROM files")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    IndexedFile {
+                        hash: row.get(1)?,
+                        mtime: row.get(2)?,
+                        size: row.get(3)?,
+                    },
+                ))
+            })?;
+            rows.filter_map(|r| r.ok()).collect()
+        };
+
+        // Read, hash, and chunk without holding the connection
+        let scanned = parallel_map(&files, |(rel_path, path)| {
+            scan_file(path, known.get(rel_path))
+        });
 
-            let content = match std::fs::read_to_string(path) {
-                Ok(c) => c,
-                Err(e) => {
-                    warn!("Failed to read {}: {e}", path.display());
-                    continue;
+        let (changed, skipped, chunks_written) = self.db.write(|tx| {
+            let (mut changed, mut skipped, mut chunks_written) = (0usize, 0usize, 0usize);
+            for ((rel_path, _), scan) in files.iter().zip(scanned) {
+                match scan {
+                    Scanned::Unchanged => skipped += 1,
+                    Scanned::Touched(file) => {
+                        Self::record_file(tx, rel_path, &file)?;
+                        skipped += 1;
+                    }
+                    Scanned::Changed(file, chunks) => {
+                        chunks_written += Self::index_file(tx, rel_path, &file, &chunks)?;
+                        changed += 1;
+                    }
+                    Scanned::Failed => {}
                 }
-            };
-
-            let hash = hex_sha256(&content);
-            let meta = std::fs::metadata(path).ok();
-            let mtime = meta
-                .as_ref()
-                .and_then(|m| m.modified().ok())
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0);
-            let size = meta.map(|m| m.len() as i64).unwrap_or(0);
-
-            // Check if file is unchanged
-            let existing_hash: Option<String> = conn
-                .query_row(
-                    "SELECT hash FROM files WHERE path = ?1",
-                    [&rel_path],
-                    |row| row.get(0),
-                )
-                .ok();
+            }
 
-            if existing_hash.as_deref() == Some(&hash) {
-                skipped += 1;
-                continue;
+            // Remove stale entries (files that no longer exist)
+            let present: HashSet<&str> = files.iter().map(|(rel, _)| rel.as_str()).collect();
+            for db_path in known.keys() {
+                if !present.contains(db_path.as_str()) {
+                    Self::remove_file(tx, db_path)?;
+                    debug!("Removed stale index entry: {db_path}");
+                }
             }
+            Ok((changed, skipped, chunks_written))
+        })?;
 
-            // File is new or changed — reindex it
-            Self::index_file(&conn, &rel_path, &content, &hash, mtime, size)?;
-            changed += 1;
-        }
+        info!(
+            "Memory reindex: {changed} changed ({chunks_written} chunks written), {skipped} unchanged, {} total files in {:.0?}",
+            files.len(),
+            started.elapsed()
+        );
 
-        // Remove stale entries (files that no longer exist)
-        let mut stmt = conn.prepare("SELECT path FROM files")?;
-        let db_paths: Vec<String> = stmt
-            .query_map([], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
+        Ok(())
+    }
 
-        for db_path in &db_paths {
-            if !indexed_paths.contains(db_path) {
-                Self::remove_file(&conn, db_path)?;
-                debug!("Removed stale index entry: {db_path}");
+    /// Index the notes in `vault` too, leaving out the files Obsidian
+    /// excludes.
+    pub fn with_vault(mut self, vault: Option<Arc<Vault>>) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    /// Markdown files in the workspace and the vault, with each top-level
+    /// workspace directory walked on its own thread.
+    fn markdown_files(&self) -> Result<Vec<PathBuf>> {
+        let mut roots = Vec::new();
+        let mut paths = Vec::new();
+        let entries = match std::fs::read_dir(&self.workspace) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(paths),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                roots.push(path);
+            } else if path.extension().is_some_and(|e| e == "md") {
+                paths.push(path);
             }
         }
 
-        info!(
-            "Memory reindex: {changed} changed, {skipped} unchanged, {} total files",
-            indexed_paths.len()
-        );
+        let walked = parallel_map(&roots, |root| {
+            let pattern = Path::new(&glob::Pattern::escape(&root.to_string_lossy()))
+                .join("**/*.md")
+                .to_string_lossy()
+                .to_string();
+            match glob::glob(&pattern) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|p| p.is_file())
+                    .collect(),
+                Err(e) => {
+                    warn!("Invalid glob pattern {pattern}: {e}");
+                    Vec::new()
+                }
+            }
+        });
+        paths.extend(walked.into_iter().flatten());
+        if let Some(ref vault) = self.vault {
+            // The workspace may be in the vault, or the vault in it
+            paths.retain(|p| !vault.is_excluded(p));
+            paths.extend(vault.markdown_files());
+        }
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
 
+    /// Store a file's hash, mtime, and size.
+    fn record_file(conn: &Connection, rel_path: &str, file: &IndexedFile) -> Result<()> {
+        conn.prepare_cached(
+            "INSERT OR REPLACE INTO files (path, hash, mtime, size) VALUES (?1, ?2, ?3, ?4)",
+        )?
+        .execute(rusqlite::params![
+            rel_path, file.hash, file.mtime, file.size
+        ])?;
         Ok(())
     }
 
-    /// Index a single file: chunk its content and insert into the database.
+    /// Bring a changed file's chunks up to date. Chunks whose text is
+    /// already indexed keep their rows (only their line numbers move); the
+    /// rest are inserted and leftovers deleted. Returns the chunks inserted.
     fn index_file(
         conn: &Connection,
         rel_path: &str,
-        content: &str,
-        hash: &str,
-        mtime: i64,
-        size: i64,
-    ) -> Result<()> {
+        file: &IndexedFile,
+        chunks: &[(Chunk, String)],
+    ) -> Result<usize> {
         let now = chrono::Utc::now().timestamp();
 
-        // Remove old chunks for this file
-        conn.execute("DELETE FROM chunks WHERE path = ?1", [rel_path])?;
-
-        // Update file record
-        conn.execute(
-            "INSERT OR REPLACE INTO files (path, hash, mtime, size) VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![rel_path, hash, mtime, size],
-        )?;
-
-        // Chunk and insert
-        let chunks = chunk_text(content);
-        let mut stmt = conn.prepare(
-            "INSERT INTO chunks (id, path, start_line, end_line, hash, text, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        )?;
+        let mut existing: HashMap<String, Vec<(String, i64, i64)>> = HashMap::new();
+        {
+            let mut stmt = conn.prepare_cached(
+                "SELECT id, hash, start_line, end_line FROM chunks WHERE path = ?1",
+            )?;
+            let rows = stmt.query_map([rel_path], |row| {
+                Ok((
+                    row.get::<_, String>(1)?,
+                    (row.get(0)?, row.get(2)?, row.get(3)?),
+                ))
+            })?;
+            for (hash, chunk) in rows.filter_map(|r| r.ok()) {
+                existing.entry(hash).or_default().push(chunk);
+            }
+        }
 
-        for chunk in &chunks {
-            let id = uuid::Uuid::new_v4().to_string();
-            let chunk_hash = hex_sha256(&chunk.text);
-            stmt.execute(rusqlite::params![
-                id,
-                rel_path,
-                chunk.start_line as i64,
-                chunk.end_line as i64,
-                chunk_hash,
-                chunk.text,
-                now,
-            ])?;
+        let mut inserted = 0;
+        for (chunk, chunk_hash) in chunks {
+            let (start, end) = (chunk.start_line as i64, chunk.end_line as i64);
+            match existing.get_mut(chunk_hash).and_then(Vec::pop) {
+                Some((id, old_start, old_end)) => {
+                    if (old_start, old_end) != (start, end) {
+                        conn.prepare_cached(
+                            "UPDATE chunks SET start_line = ?2, end_line = ?3 WHERE id = ?1",
+                        )?
+                        .execute(rusqlite::params![id, start, end])?;
+                    }
+                }
+                None => {
+                    conn.prepare_cached(
+                        "INSERT INTO chunks (id, path, start_line, end_line, hash, text, updated_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    )?
+                    .execute(rusqlite::params![
+                        uuid::Uuid::new_v4().to_string(),
+                        rel_path,
+                        start,
+                        end,
+                        chunk_hash,
+                        chunk.text,
+                        now,
+                    ])?;
+                    inserted += 1;
+                }
+            }
+        }
+        for (id, _, _) in existing.into_values().flatten() {
+            conn.prepare_cached("DELETE FROM chunks WHERE id = ?1")?
+                .execute([id])?;
         }
+        Self::record_file(conn, rel_path, file)?;
 
         debug!(
-            "Indexed {rel_path}: {} chunks ({} chars)",
-            chunks.len(),
-            content.len()
+            "Indexed {rel_path}: {} chunks, {inserted} new",
+            chunks.len()
         );
 
-        Ok(())
+        Ok(inserted)
     }
 
     /// Remove all index entries for a file.
@@ -243,6 +352,18 @@ impl MemoryIndex {
 
     /// Search the index using FTS5 full-text search with BM25 ranking.
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_preferring(query, limit, "")
+    }
+
+    /// Like [`MemoryIndex::search`], but matches in files under the
+    /// workspace-relative `prefix` (e.g. a project's memory directory) come
+    /// first.
+    pub fn search_preferring(
+        &self,
+        query: &str,
+        limit: usize,
+        prefix: &str,
+    ) -> Result<Vec<SearchResult>> {
         let query = query.trim();
         if query.is_empty() {
             return Ok(Vec::new());
@@ -253,19 +374,19 @@ impl MemoryIndex {
             return Ok(Vec::new());
         }
 
-        let conn = self.lock_conn()?;
+        let conn = self.db.conn()?;
 
         let mut stmt = conn.prepare(
             "SELECT c.path, c.start_line, c.end_line, c.text, rank
              FROM chunks_fts
              JOIN chunks c ON chunks_fts.rowid = c.rowid
              WHERE chunks_fts MATCH ?1
-             ORDER BY rank
+             ORDER BY substr(c.path, 1, length(?3)) = ?3 DESC, rank
              LIMIT ?2",
         )?;
 
         let results = stmt
-            .query_map(rusqlite::params![fts_query, limit as i64], |row| {
+            .query_map(rusqlite::params![fts_query, limit as i64, prefix], |row| {
                 Ok(SearchResult {
                     path: row.get(0)?,
                     start_line: row.get::<_, i64>(1)? as usize,
@@ -281,10 +402,28 @@ impl MemoryIndex {
         Ok(results)
     }
 
+    /// Indexed files, chunks, and bytes, as of the last reindex.
+    pub fn stats(&self) -> Result<IndexStats> {
+        self.db.read(|conn| {
+            let (files, bytes): (i64, i64) = conn.query_row(
+                "SELECT count(*), coalesce(sum(size), 0) FROM files",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let chunks: i64 =
+                conn.query_row("SELECT count(*) FROM chunks", [], |row| row.get(0))?;
+            Ok(IndexStats {
+                files: files as u64,
+                chunks: chunks as u64,
+                bytes: bytes as u64,
+            })
+        })
+    }
+
     /// Get chunk count (for testing).
     #[cfg(test)]
     fn chunk_count(&self) -> i64 {
-        let conn = self.lock_conn().unwrap();
+        let conn = self.db.conn().unwrap();
         conn.query_row("SELECT count(*) FROM chunks", [], |row| row.get(0))
             .unwrap()
     }
@@ -303,9 +442,71 @@ fn build_fts_query(query: &str) -> String {
         .join(" ")
 }
 
+/// Read, hash, and chunk a file unless its size and mtime show it's
+/// unchanged since it was indexed.
+fn scan_file(path: &Path, known: Option<&IndexedFile>) -> Scanned {
+    let meta = match std::fs::metadata(path) {
+        Ok(m) => m,
+        Err(e) => {
+            warn!("Failed to stat {}: {e}", path.display());
+            return Scanned::Failed;
+        }
+    };
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0);
+    let size = meta.len() as i64;
+    if known.is_some_and(|k| k.mtime == mtime && k.size == size) {
+        return Scanned::Unchanged;
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("Failed to read {}: {e}", path.display());
+            return Scanned::Failed;
+        }
+    };
+    let file = IndexedFile {
+        hash: hex_sha256(&content),
+        mtime,
+        size,
+    };
+    if known.is_some_and(|k| k.hash == file.hash) {
+        return Scanned::Touched(file);
+    }
+    let chunks = chunk_text(&content)
+        .into_iter()
+        .map(|chunk| {
+            let hash = hex_sha256(&chunk.text);
+            (chunk, hash)
+        })
+        .collect();
+    Scanned::Changed(file, chunks)
+}
+
+/// Map `f` over `items` on a few threads, keeping order.
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get().min(8));
+    let per_thread = items.len().div_ceil(threads).max(1);
+    std::thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(per_thread)
+            .map(|batch| s.spawn(|| batch.iter().map(&f).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
 /// Split text into overlapping chunks on line boundaries.
 /// Target: ~1600 chars per chunk, ~320 chars overlap.
-fn chunk_text(text: &str) -> Vec<Chunk> {
+pub(crate) fn chunk_text(text: &str) -> Vec<Chunk> {
     const TARGET_SIZE: usize = 1600;
     const OVERLAP: usize = 320;
 
@@ -365,7 +566,7 @@ fn chunk_text(text: &str) -> Vec<Chunk> {
 }
 
 /// Compute the hex-encoded SHA-256 of a string.
-fn hex_sha256(s: &str) -> String {
+pub(crate) fn hex_sha256(s: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(s.as_bytes());
     format!("{:x}", hasher.finalize())
@@ -465,7 +666,7 @@ mod tests {
         let db_path = dir.path().join("test.sqlite");
         let index = MemoryIndex::new(dir.path(), &db_path).unwrap();
 
-        let conn = index.lock_conn().unwrap();
+        let conn = index.db.conn().unwrap();
         let count: i64 = conn
             .query_row(
                 "SELECT count(*) FROM sqlite_master WHERE type='table' AND name IN ('files', 'chunks')",
@@ -495,6 +696,24 @@ mod tests {
         assert!(!results.is_empty(), "Should find Rust/Python reference");
     }
 
+    #[test]
+    fn test_search_prefers_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        setup_workspace(dir.path());
+        let project = dir.path().join("memory/projects/acme");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("MEMORY.md"), "Acme's storage is Postgres.\n").unwrap();
+
+        let index = MemoryIndex::new(dir.path(), &dir.path().join("memory.sqlite")).unwrap();
+        index.reindex().unwrap();
+        let results = index
+            .search_preferring("storage", 5, "memory/projects/acme/")
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].path, "memory/projects/acme/MEMORY.md");
+        assert_eq!(results[1].path, "memory/HISTORY.md");
+    }
+
     #[test]
     fn test_reindex_skips_unchanged() {
         let dir = tempfile::tempdir().unwrap();
@@ -510,6 +729,11 @@ mod tests {
         index.reindex().unwrap();
         let count2 = index.chunk_count();
         assert_eq!(count1, count2);
+
+        let stats = index.stats().unwrap();
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.chunks, count2 as u64);
+        assert!(stats.bytes > 0);
     }
 
     #[test]
@@ -559,6 +783,50 @@ mod tests {
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_reindex_rewrites_only_changed_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes/deep");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(dir.path().join("TOP.md"), "Top level note about kayaks.\n").unwrap();
+        let lines: Vec<String> = (0..100)
+            .map(|i| format!("Line {i} of a long note, padded out so it spans several chunks."))
+            .collect();
+        std::fs::write(notes.join("long.md"), lines.join("\n")).unwrap();
+
+        let db_path = dir.path().join("memory.sqlite");
+        let index = MemoryIndex::new(dir.path(), &db_path).unwrap();
+        index.reindex().unwrap();
+        assert_eq!(index.search("kayaks", 5).unwrap().len(), 1);
+
+        let ids = |index: &MemoryIndex| -> Vec<String> {
+            let conn = index.db.conn().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT id FROM chunks WHERE path LIKE '%long.md' ORDER BY start_line")
+                .unwrap();
+            stmt.query_map([], |row| row.get(0))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        let before = ids(&index);
+        assert!(before.len() > 2);
+
+        // Prepending a line shifts every chunk but only changes the first
+        std::fs::write(
+            notes.join("long.md"),
+            format!("A new opening line about canoes.\n{}", lines.join("\n")),
+        )
+        .unwrap();
+        index.reindex().unwrap();
+        let after = ids(&index);
+        assert_ne!(after[0], before[0]);
+        assert_eq!(after[after.len() - 1], before[before.len() - 1]);
+        assert_eq!(index.search("canoes", 5).unwrap().len(), 1);
+        let last = index.search("Line 99", 1).unwrap();
+        assert_eq!(last[0].end_line, 101);
+    }
+
     #[test]
     fn test_reindex_detects_changes() {
         let dir = tempfile::tempdir().unwrap();
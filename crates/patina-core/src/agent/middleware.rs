@@ -0,0 +1,212 @@
+//! Hooks around the agent loop's completions and tool calls.
+//!
+//! Register a [`Middleware`] with [`AgentLoop::add_middleware`](crate::agent::AgentLoop::add_middleware)
+//! to add policy, logging, or content filtering without changing the loop.
+//! Middleware runs in registration order, and every hook has a no-op
+//! default, so implementors only override what they need.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rig::completion::CompletionRequest;
+use rig::message::ToolCall;
+
+/// Where in the loop a hook is running.
+#[derive(Debug, Clone, Copy)]
+pub struct HookContext<'a> {
+    pub session_key: &'a str,
+    /// Persona name, or "default".
+    pub agent: &'a str,
+    pub model: &'a str,
+    pub provider: &'a str,
+    /// 1-based LLM round within the current turn.
+    pub iteration: usize,
+}
+
+/// What to do with a tool call after [`Middleware::before_tool`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolDecision {
+    Run,
+    /// Don't run the tool; give the model this text as its result.
+    Skip(String),
+}
+
+/// Hooks called by `AgentLoop` on every turn. Returning an error from any
+/// hook aborts the turn with that error.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Inspect or edit a request before it is sent to the model.
+    async fn before_completion(
+        &self,
+        _ctx: &HookContext<'_>,
+        _request: &mut CompletionRequest,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Inspect or edit the model's reply and tool calls once streaming has
+    /// finished. Streamed text has already reached the UI by then; edits
+    /// change what is saved to the session and returned.
+    async fn after_completion(
+        &self,
+        _ctx: &HookContext<'_>,
+        _text: &mut String,
+        _tool_calls: &mut Vec<ToolCall>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Inspect or edit a tool call's arguments, or skip the call.
+    async fn before_tool(
+        &self,
+        _ctx: &HookContext<'_>,
+        _tool: &str,
+        _args: &mut serde_json::Value,
+    ) -> Result<ToolDecision> {
+        Ok(ToolDecision::Run)
+    }
+
+    /// Inspect or edit a tool's result before the model sees it. Also runs
+    /// for calls skipped by `before_tool`.
+    async fn after_tool(
+        &self,
+        _ctx: &HookContext<'_>,
+        _tool: &str,
+        _result: &mut String,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::agent::model_pool::ModelPool;
+    use crate::agent::r#loop::{AgentLoop, ModelOverrides};
+    use crate::agent::replay::{MockCompletionModel, RecordedResponse};
+    use crate::agent::ContextBuilder;
+    use crate::session::SessionManager;
+    use crate::tools::calc::CalcTool;
+    use crate::tools::ToolRegistry;
+
+    #[derive(Default)]
+    struct Policy {
+        requests: Mutex<usize>,
+        tool_results: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Policy {
+        async fn before_completion(
+            &self,
+            ctx: &HookContext<'_>,
+            request: &mut CompletionRequest,
+        ) -> Result<()> {
+            assert_eq!(ctx.session_key, "cli:test");
+            assert!(request.preamble.is_some());
+            *self.requests.lock().unwrap() += 1;
+            Ok(())
+        }
+
+        async fn after_completion(
+            &self,
+            _ctx: &HookContext<'_>,
+            text: &mut String,
+            _tool_calls: &mut Vec<ToolCall>,
+        ) -> Result<()> {
+            *text = text.replace("secret", "***");
+            Ok(())
+        }
+
+        async fn before_tool(
+            &self,
+            _ctx: &HookContext<'_>,
+            tool: &str,
+            args: &mut serde_json::Value,
+        ) -> Result<ToolDecision> {
+            if tool == "exec" {
+                return Ok(ToolDecision::Skip("Error: exec is disabled".into()));
+            }
+            args["expression"] = serde_json::json!("3+3");
+            Ok(ToolDecision::Run)
+        }
+
+        async fn after_tool(
+            &self,
+            _ctx: &HookContext<'_>,
+            tool: &str,
+            result: &mut String,
+        ) -> Result<()> {
+            self.tool_results
+                .lock()
+                .unwrap()
+                .push((tool.to_string(), result.clone()));
+            Ok(())
+        }
+    }
+
+    fn test_loop(dir: &std::path::Path, models: ModelPool) -> AgentLoop {
+        let mut tools = ToolRegistry::new();
+        tools.register(Box::new(CalcTool::new()));
+        AgentLoop {
+            models,
+            sessions: SessionManager::new(dir.join("sessions")),
+            context: ContextBuilder::new(dir),
+            tools,
+            max_iterations: 5,
+            temperature: 0.7,
+            max_tokens: 1024,
+            memory_window: 20,
+            model_overrides: ModelOverrides::defaults(),
+            memory_index: None,
+            channel_rules: HashMap::new(),
+            usage_tracker: None,
+            session_log: None,
+            stream_tx: None,
+            middleware: Vec::new(),
+            memory_approvals: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = MockCompletionModel::new([
+            RecordedResponse {
+                tool_calls: vec![
+                    (
+                        "calc".into(),
+                        serde_json::json!({"action": "eval", "expression": "2+2"}),
+                    ),
+                    ("exec".into(), serde_json::json!({"command": "rm -rf /"})),
+                ],
+                ..Default::default()
+            },
+            RecordedResponse {
+                text: "The secret answer is 6.".into(),
+                ..Default::default()
+            },
+        ]);
+        let mut agent_loop = test_loop(dir.path(), model.clone().into_pool());
+        let policy = Arc::new(Policy::default());
+        agent_loop.add_middleware(policy.clone());
+
+        let (response, _) = agent_loop
+            .process_message("cli:test", "what's 2+2?", None)
+            .await
+            .unwrap();
+
+        assert_eq!(response, "The *** answer is 6.");
+        assert_eq!(*policy.requests.lock().unwrap(), 2);
+        let results = policy.tool_results.lock().unwrap();
+        assert_eq!(results[0].0, "calc");
+        assert!(results[0].1.contains('6'), "{}", results[0].1);
+        assert_eq!(
+            results[1],
+            ("exec".to_string(), "Error: exec is disabled".to_string())
+        );
+        assert_eq!(model.remaining(), 0);
+    }
+}
@@ -1,14 +1,37 @@
+pub mod access;
+pub mod batch;
+pub mod citations;
+pub mod consolidation_retries;
 pub mod context;
+pub mod eval;
 pub mod r#loop;
 pub mod memory;
+pub mod memory_approvals;
 pub mod memory_index;
+pub mod middleware;
 pub mod model_pool;
+pub mod obsidian;
+pub mod profile;
+pub mod rate_limit;
+pub mod replay;
+pub mod runs;
+pub mod settings;
 pub mod skills;
 pub mod subagent;
+pub mod template;
 
+pub use access::AccessControl;
+pub use citations::Citations;
 pub use context::ContextBuilder;
-pub use memory::MemoryStore;
-pub use memory_index::MemoryIndex;
+pub use memory::{MemoryFact, MemoryStore};
+pub use memory_approvals::MemoryApprovals;
+pub use memory_index::{IndexStats, MemoryIndex};
+pub use middleware::{HookContext, Middleware, ToolDecision};
 pub use model_pool::ModelPool;
-pub use r#loop::{AgentLoop, ConsolidationResult, ConsolidationTask, ModelOverrides, StreamChunk};
+pub use r#loop::{
+    AgentLoop, ConsolidationProposal, ConsolidationResult, ConsolidationTask, ModelOverrides,
+    StreamChunk,
+};
+pub use replay::{MockCompletionModel, Recording};
+pub use settings::{SessionSettings, SettingsCommand};
 pub use skills::SkillsLoader;
@@ -1,7 +1,15 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[allow(deprecated)]
 use rig::client::completion::CompletionModelHandle;
+use rig::completion::CompletionRequest;
+
+use crate::agent::rate_limit::{request_tokens, RateLimit, RateLimiter};
+
+/// Request timeout for providers without `timeoutSecs` configured.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
 
 /// Pool of named model tiers (e.g. "default", "coding", "consolidation").
 ///
@@ -12,6 +20,12 @@ use rig::client::completion::CompletionModelHandle;
 pub struct ModelPool {
     /// (model_handle, model_name, provider_name)
     models: HashMap<String, (CompletionModelHandle<'static>, String, String)>,
+    /// Per-provider request timeouts, keyed by provider name.
+    timeouts: HashMap<String, Duration>,
+    /// Per-provider extra request fields (e.g. Ollama's `keep_alive`).
+    request_params: HashMap<String, serde_json::Value>,
+    /// Per-provider rate limiters, shared by every clone of the pool.
+    limiters: HashMap<String, Arc<RateLimiter>>,
 }
 
 #[allow(deprecated)]
@@ -22,7 +36,59 @@ impl ModelPool {
             models.contains_key("default"),
             "ModelPool must contain a \"default\" tier"
         );
-        Self { models }
+        Self {
+            models,
+            timeouts: HashMap::new(),
+            request_params: HashMap::new(),
+            limiters: HashMap::new(),
+        }
+    }
+
+    /// Set per-provider request timeouts. Providers not listed use
+    /// [`DEFAULT_REQUEST_TIMEOUT`].
+    pub fn with_timeouts(mut self, timeouts: HashMap<String, Duration>) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Set per-provider extra request fields, sent as rig's `additional_params`.
+    pub fn with_request_params(mut self, params: HashMap<String, serde_json::Value>) -> Self {
+        self.request_params = params;
+        self
+    }
+
+    /// Set per-provider rate limits. Providers not listed aren't limited.
+    pub fn with_rate_limits(mut self, limits: HashMap<String, RateLimit>) -> Self {
+        self.limiters = limits
+            .into_iter()
+            .map(|(provider, limit)| {
+                let limiter = Arc::new(RateLimiter::new(&provider, limit));
+                (provider, limiter)
+            })
+            .collect();
+        self
+    }
+
+    /// Wait until `request` fits in the provider's rate limits, returning
+    /// how long it was queued. Call right before sending it.
+    pub async fn throttle(&self, provider: &str, request: &CompletionRequest) -> Duration {
+        match self.limiters.get(provider) {
+            Some(limiter) => limiter.acquire(request_tokens(request)).await,
+            None => Duration::ZERO,
+        }
+    }
+
+    /// Extra request fields for a provider, if any.
+    pub fn request_params(&self, provider: &str) -> Option<serde_json::Value> {
+        self.request_params.get(provider).cloned()
+    }
+
+    /// Request timeout for a provider.
+    pub fn timeout(&self, provider: &str) -> Duration {
+        self.timeouts
+            .get(provider)
+            .copied()
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
     }
 
     /// Get a specific tier. Falls back to "default" if the tier is not found.
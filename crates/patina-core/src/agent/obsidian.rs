@@ -0,0 +1,337 @@
+//! Memory kept in an Obsidian vault.
+//!
+//! With `obsidian.enabled`, MEMORY.md, HISTORY.md, and project memory live
+//! in `obsidian.folder` inside the vault instead of the workspace, and the
+//! memory index covers the whole vault except what Obsidian ignores itself:
+//! dot folders like `.obsidian` and `.trash`, and the "Excluded files" in
+//! `.obsidian/app.json`. Consolidation is asked to write people, places, and
+//! projects as `[[wiki links]]`, and each history entry is added to the page
+//! of everything it links, creating the page in `obsidian.entityFolder` when
+//! the vault doesn't have one yet.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use patina_config::{resolve_workspace, ObsidianConfig};
+use regex::Regex;
+use tracing::{debug, warn};
+
+/// Added to the consolidation prompt for a vault.
+pub const LINK_INSTRUCTIONS: &str = "Memory is kept in an Obsidian vault. In both the history \
+entry and the memory, write the names of people, places, organizations, and projects as wiki \
+links, like [[Ada Lovelace]], using the same name every time (reuse the links already in the \
+current memory). Don't link anything else.";
+
+/// One of Obsidian's "Excluded files" filters.
+enum Exclusion {
+    /// A vault-relative path the excluded files start with.
+    Prefix(String),
+    /// A regex, written in slashes, matched against the vault-relative path.
+    Pattern(Regex),
+}
+
+/// An Obsidian vault holding the agent's memory.
+pub struct Vault {
+    root: PathBuf,
+    memory_dir: PathBuf,
+    entity_dir: PathBuf,
+    exclusions: Vec<Exclusion>,
+}
+
+impl Vault {
+    /// Open the vault in `config`, reading its excluded files.
+    pub fn open(config: &ObsidianConfig) -> Result<Self> {
+        let root = resolve_workspace(&config.vault);
+        if config.vault.trim().is_empty() || !root.is_dir() {
+            anyhow::bail!("vault folder '{}' doesn't exist", root.display());
+        }
+        let exclusions = match std::fs::read_to_string(root.join(".obsidian/app.json")) {
+            Ok(app) => parse_exclusions(&app),
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            memory_dir: root.join(config.folder.trim_matches('/')),
+            entity_dir: root.join(config.entity_folder.trim_matches('/')),
+            root,
+            exclusions,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The folder holding MEMORY.md, HISTORY.md, and project memory.
+    pub fn memory_dir(&self) -> &Path {
+        &self.memory_dir
+    }
+
+    /// Whether Obsidian leaves `path` out: it's in a dot folder or matches
+    /// an excluded-files filter. Paths outside the vault never are.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if rel
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return true;
+        }
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        self.exclusions.iter().any(|exclusion| match exclusion {
+            Exclusion::Prefix(prefix) => rel.starts_with(prefix.as_str()),
+            Exclusion::Pattern(re) => re.is_match(&rel),
+        })
+    }
+
+    /// Markdown files in the vault that Obsidian doesn't exclude, sorted.
+    pub fn markdown_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for path in entries.flatten().map(|e| e.path()) {
+                if self.is_excluded(&path) {
+                    continue;
+                }
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|e| e == "md") {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    /// Add a history entry to the page of everything it links, creating
+    /// missing pages. Returns the names of the pages written.
+    pub fn record_mentions(&self, entry: &str) -> Result<Vec<String>> {
+        let links = wiki_links(entry);
+        if links.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pages = self.markdown_files();
+        let line = entry.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut written = Vec::new();
+        for name in links {
+            let Some(path) = self.page_for(&name, &pages) else {
+                warn!("Not writing an Obsidian page for the link [[{name}]]");
+                continue;
+            };
+            let existing = std::fs::read_to_string(&path).unwrap_or_default();
+            let mut text = String::new();
+            if existing.is_empty() {
+                text.push_str(&format!("# {}\n\n", page_title(&name)));
+            } else if !existing.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("- {line}\n"));
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .with_context(|| format!("couldn't write {}", path.display()))?;
+            written.push(name);
+        }
+        Ok(written)
+    }
+
+    /// The page `[[name]]` opens in Obsidian, among `pages`: the one with
+    /// that file name (or vault path, for `[[Folder/Name]]`), compared
+    /// without case. A new page's path otherwise, unless the name can't be
+    /// a file name.
+    fn page_for(&self, name: &str, pages: &[PathBuf]) -> Option<PathBuf> {
+        let valid = !name.starts_with('.')
+            && !name.split('/').any(|part| part.is_empty() || part == "..")
+            && !name
+                .chars()
+                .any(|c| c.is_control() || "\\:*?\"<>|".contains(c));
+        if !valid {
+            return None;
+        }
+        let wanted = name.to_lowercase();
+        let found = pages.iter().find(|page| {
+            let rel = page.strip_prefix(&self.root).unwrap_or(page);
+            let rel = rel.with_extension("").to_string_lossy().replace('\\', "/");
+            let rel = rel.to_lowercase();
+            if name.contains('/') {
+                rel == wanted || rel.ends_with(&format!("/{wanted}"))
+            } else {
+                rel.rsplit('/').next() == Some(wanted.as_str())
+            }
+        });
+        match found {
+            // Not the memory files themselves
+            Some(page) if page.parent() == Some(self.memory_dir.as_path()) => {
+                let file = page.file_name().unwrap_or_default();
+                (file != "MEMORY.md" && file != "HISTORY.md").then(|| page.clone())
+            }
+            Some(page) => Some(page.clone()),
+            None if name.contains('/') => Some(self.root.join(format!("{name}.md"))),
+            None => Some(self.entity_dir.join(format!("{name}.md"))),
+        }
+    }
+}
+
+/// [`Vault::record_mentions`], logging failures instead of returning them:
+/// by then the entry is already in HISTORY.md.
+pub fn note_mentions(vault: &Vault, entry: &str) {
+    match vault.record_mentions(entry) {
+        Ok(pages) if !pages.is_empty() => {
+            debug!(
+                "Added the history entry to Obsidian pages: {}",
+                pages.join(", ")
+            )
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Couldn't add the history entry to its Obsidian pages: {e}"),
+    }
+}
+
+/// The pages `text` links to with `[[Name]]`, `[[Name|shown]]`, or
+/// `[[Name#Heading]]`, once each in order. Embeds (`![[...]]`) aren't links.
+pub fn wiki_links(text: &str) -> Vec<String> {
+    let re = Regex::new(r"(!?)\[\[([^\[\]|#\n]+)(?:[|#][^\[\]\n]*)?\]\]").unwrap();
+    let mut links: Vec<String> = Vec::new();
+    for caps in re.captures_iter(text) {
+        let name = caps[2].trim();
+        if caps[1].is_empty()
+            && !name.is_empty()
+            && !links.iter().any(|l| l.eq_ignore_ascii_case(name))
+        {
+            links.push(name.to_string());
+        }
+    }
+    links
+}
+
+fn page_title(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+/// The `userIgnoreFilters` of `.obsidian/app.json`.
+fn parse_exclusions(app_json: &str) -> Vec<Exclusion> {
+    let Ok(app) = serde_json::from_str::<serde_json::Value>(app_json) else {
+        return Vec::new();
+    };
+    let filters = app["userIgnoreFilters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    filters
+        .iter()
+        .filter_map(|f| f.as_str())
+        .filter(|f| !f.is_empty())
+        .filter_map(|filter| {
+            match filter
+                .strip_prefix('/')
+                .and_then(|f| f.strip_suffix('/'))
+                .filter(|f| !f.is_empty())
+            {
+                Some(pattern) => match Regex::new(pattern) {
+                    Ok(re) => Some(Exclusion::Pattern(re)),
+                    Err(e) => {
+                        warn!("Ignoring Obsidian excluded-files filter {filter}: {e}");
+                        None
+                    }
+                },
+                None => Some(Exclusion::Prefix(filter.to_string())),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault(dir: &Path) -> Vault {
+        std::fs::create_dir_all(dir.join(".obsidian")).unwrap();
+        std::fs::write(
+            dir.join(".obsidian/app.json"),
+            r#"{"userIgnoreFilters": ["Archive/", "/\\.excalidraw\\.md$/"]}"#,
+        )
+        .unwrap();
+        Vault::open(&ObsidianConfig {
+            enabled: true,
+            vault: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn links_are_parsed_once_each() {
+        let text = "Met [[Ada Lovelace]] and [[ada lovelace|Ada]] at [[London#Soho]]. \
+                    ![[photo.png]] [[ ]] [[People/Bob]]";
+        assert_eq!(wiki_links(text), ["Ada Lovelace", "London", "People/Bob"]);
+    }
+
+    #[test]
+    fn excluded_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = vault(dir.path());
+        for file in [
+            "Notes/keep.md",
+            "Archive/old.md",
+            "Drawings/sketch.excalidraw.md",
+            ".trash/gone.md",
+            "Patina/MEMORY.md",
+        ] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "x").unwrap();
+        }
+        let files: Vec<String> = vault
+            .markdown_files()
+            .iter()
+            .map(|p| {
+                p.strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        assert_eq!(files, ["Notes/keep.md", "Patina/MEMORY.md"]);
+        assert!(vault.is_excluded(&dir.path().join(".obsidian/workspace.md")));
+        assert!(!vault.is_excluded(Path::new("/elsewhere/Archive/x.md")));
+        assert_eq!(vault.memory_dir(), dir.path().join("Patina"));
+    }
+
+    #[test]
+    fn mentions_go_to_existing_or_new_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = vault(dir.path());
+        std::fs::create_dir_all(dir.path().join("People")).unwrap();
+        std::fs::write(dir.path().join("People/Ada Lovelace.md"), "Mathematician").unwrap();
+
+        let entry = "[2026-03-01 10:00] Planned a visit to\n[[London]] with [[ada lovelace]].";
+        let written = vault.record_mentions(entry).unwrap();
+        assert_eq!(written, ["London", "ada lovelace"]);
+
+        let line = "- [2026-03-01 10:00] Planned a visit to [[London]] with [[ada lovelace]].\n";
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("People/Ada Lovelace.md")).unwrap(),
+            format!("Mathematician\n{line}")
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("Patina/Entities/London.md")).unwrap(),
+            format!("# London\n\n{line}")
+        );
+        // Names that can't be file names are skipped
+        assert!(vault
+            .record_mentions("[[../etc/passwd]]")
+            .unwrap()
+            .is_empty());
+    }
+}
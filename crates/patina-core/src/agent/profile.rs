@@ -0,0 +1,212 @@
+//! What the next prompt for a session is made of, for `/context` and
+//! `patina context`: each part of the system prompt, the history, and the
+//! tool schemas, with token estimates. For when replies cost more than
+//! expected, or the agent seems to have forgotten something.
+
+use crate::agent::settings::PERSONA_KEY;
+use crate::agent::AgentLoop;
+use crate::error::PatinaError;
+use crate::persona::PersonaStore;
+use crate::usage::estimate_tokens;
+
+/// Tool schemas listed by name; the rest are summed.
+const LISTED_TOOLS: usize = 5;
+
+/// Token estimates for one session's next prompt.
+#[derive(Debug, Clone, Default)]
+pub struct ContextReport {
+    pub session_key: String,
+    pub model: String,
+    /// Each part of the system prompt, in order, with its tokens.
+    pub system: Vec<(String, u64)>,
+    /// Messages sent as history, and their tokens.
+    pub history_sent: usize,
+    pub history_tokens: u64,
+    /// Messages in the session, and how many of the oldest are saved to
+    /// memory.
+    pub messages: usize,
+    pub consolidated: usize,
+    /// Each tool's schema with its tokens, largest first.
+    pub tools: Vec<(String, u64)>,
+}
+
+impl ContextReport {
+    pub fn system_tokens(&self) -> u64 {
+        self.system.iter().map(|(_, t)| t).sum()
+    }
+
+    pub fn tool_tokens(&self) -> u64 {
+        self.tools.iter().map(|(_, t)| t).sum()
+    }
+
+    /// Everything sent before the next user message.
+    pub fn total(&self) -> u64 {
+        self.system_tokens() + self.history_tokens + self.tool_tokens()
+    }
+}
+
+/// Measure the prompt `agent_loop` would send for `session_key`'s next
+/// message, with the session's persona from `personas`.
+pub fn profile(
+    agent_loop: &mut AgentLoop,
+    session_key: &str,
+    personas: &PersonaStore,
+) -> Result<ContextReport, PatinaError> {
+    let persona = agent_loop
+        .sessions
+        .get_or_create_checked(session_key)?
+        .metadata
+        .get(PERSONA_KEY)
+        .and_then(|v| v.as_str())
+        .and_then(|key| personas.get(key));
+    let preamble = persona
+        .map(|p| p.preamble.clone())
+        .filter(|p| !p.is_empty());
+    let persona_tier = persona
+        .map(|p| p.model_tier.clone())
+        .filter(|t| !t.is_empty());
+
+    let (settings, _) = agent_loop.prepare_context(session_key, preamble.as_deref())?;
+    let session = session_key.split_once(':');
+    let channel_rules = session
+        .and_then(|(channel, _)| agent_loop.channel_rules.get(channel))
+        .map(String::as_str);
+    let system = agent_loop
+        .context
+        .prompt_sections(session, channel_rules)
+        .into_iter()
+        .map(|s| (s.label, estimate_tokens(&s.text)))
+        .collect();
+    agent_loop.reset_context();
+
+    let tier = settings
+        .model
+        .as_deref()
+        .or(persona_tier.as_deref())
+        .unwrap_or("default");
+    let model = agent_loop.models.get(tier).1.to_string();
+
+    let mut tools: Vec<(String, u64)> = agent_loop
+        .tools
+        .list()
+        .iter()
+        .map(|t| {
+            let schema = format!("{}{}{}", t.name(), t.description(), t.parameters_schema());
+            (t.name().to_string(), estimate_tokens(&schema))
+        })
+        .collect();
+    tools.sort_by_key(|(_, tokens)| std::cmp::Reverse(*tokens));
+
+    let session = agent_loop.sessions.get_or_create_checked(session_key)?;
+    let history = session.get_history(agent_loop.memory_window);
+    let history_tokens = history
+        .iter()
+        .map(|m| {
+            estimate_tokens(&m.content) + m.reasoning_content.as_deref().map_or(0, estimate_tokens)
+        })
+        .sum();
+
+    Ok(ContextReport {
+        session_key: session_key.to_string(),
+        model,
+        system,
+        history_sent: history.len(),
+        history_tokens,
+        messages: session.messages.len(),
+        consolidated: session.last_consolidated,
+        tools,
+    })
+}
+
+/// The report as text for a chat or the terminal.
+pub fn render_report(report: &ContextReport) -> String {
+    let mut out = format!(
+        "Context for {} on {}: about {} tokens before the next message.\n",
+        report.session_key,
+        report.model,
+        report.total()
+    );
+
+    out.push_str(&format!("\nSystem prompt: {}\n", report.system_tokens()));
+    for (label, tokens) in &report.system {
+        out.push_str(&format!("  {label}: {tokens}\n"));
+    }
+
+    out.push_str(&format!(
+        "\nHistory: {} (the last {} of {} messages)\n",
+        report.history_tokens, report.history_sent, report.messages
+    ));
+    let unsent = report.messages - report.history_sent;
+    if unsent > 0 {
+        let saved = report.consolidated.min(unsent);
+        out.push_str(&format!("  {unsent} older messages aren't sent."));
+        if saved > 0 {
+            out.push_str(&format!(
+                " {saved} are saved to memory, where memory_search finds them."
+            ));
+        }
+        if unsent > saved {
+            out.push_str(&format!(
+                " {} aren't yet, and will be at the next consolidation.",
+                unsent - saved
+            ));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&format!(
+        "\nTool schemas: {} ({} tools)\n",
+        report.tool_tokens(),
+        report.tools.len()
+    ));
+    for (name, tokens) in report.tools.iter().take(LISTED_TOOLS) {
+        out.push_str(&format!("  {name}: {tokens}\n"));
+    }
+    if report.tools.len() > LISTED_TOOLS {
+        let rest = &report.tools[LISTED_TOOLS..];
+        out.push_str(&format!(
+            "  {} others: {}\n",
+            rest.len(),
+            rest.iter().map(|(_, t)| t).sum::<u64>()
+        ));
+    }
+
+    out.push_str(
+        "\nMemory isn't in the prompt; memory_search looks it up when needed. \
+         Counts are estimates.",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_report_explains_unsent_history() {
+        let report = ContextReport {
+            session_key: "telegram:42".into(),
+            model: "gpt-4o".into(),
+            system: vec![("Identity".into(), 400), ("AGENTS.md".into(), 100)],
+            history_sent: 40,
+            history_tokens: 2000,
+            messages: 100,
+            consolidated: 50,
+            tools: (1..=7).map(|i| (format!("tool{i}"), 10)).collect(),
+        };
+        assert_eq!(report.total(), 2570);
+
+        let text = render_report(&report);
+        assert!(text.starts_with(
+            "Context for telegram:42 on gpt-4o: about 2570 tokens before the next message.\n\n\
+             System prompt: 500\n  Identity: 400\n  AGENTS.md: 100\n"
+        ));
+        assert!(text.contains(
+            "History: 2000 (the last 40 of 100 messages)\n  60 older messages aren't sent. \
+             50 are saved to memory, where memory_search finds them. 10 aren't yet, \
+             and will be at the next consolidation.\n"
+        ));
+        assert!(text.contains("Tool schemas: 70 (7 tools)\n"));
+        assert!(text.contains("  2 others: 20\n"));
+    }
+}
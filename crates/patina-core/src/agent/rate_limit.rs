@@ -0,0 +1,227 @@
+//! Client-side rate limits per provider.
+//!
+//! The main loop, subagents, consolidation, and the other model callers all
+//! share a provider's limits, so a busy moment can trip its 429s. With
+//! `requestsPerMinute` or `tokensPerMinute` set on a provider, every request
+//! to it waits in [`RateLimiter::acquire`] until it fits in the last
+//! minute's budget. Requests go out in the order they arrived. Time spent
+//! waiting is counted process-wide for `/api/metrics`.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use rig::completion::CompletionRequest;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::usage::estimate_tokens;
+
+/// The window the limits are counted over.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// A provider's limits. `None` (or 0) leaves that one unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u64>,
+}
+
+impl RateLimit {
+    pub fn from_config(config: &patina_config::ProviderConfig) -> Option<Self> {
+        let limit = Self {
+            requests_per_minute: config.requests_per_minute.filter(|&n| n > 0),
+            tokens_per_minute: config.tokens_per_minute.filter(|&n| n > 0),
+        };
+        (limit != Self::default()).then_some(limit)
+    }
+}
+
+/// Snapshot of one provider's queue.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// Requests waiting for room right now.
+    pub queued: usize,
+    pub requests: u64,
+    /// Requests that had to wait at all.
+    pub delayed: u64,
+    /// Time all requests spent waiting, in milliseconds.
+    pub waited_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    queued: AtomicUsize,
+    requests: AtomicU64,
+    delayed: AtomicU64,
+    waited_ms: AtomicU64,
+    max_wait_ms: AtomicU64,
+}
+
+fn registry() -> &'static Mutex<BTreeMap<String, Arc<Counters>>> {
+    static PROVIDERS: OnceLock<Mutex<BTreeMap<String, Arc<Counters>>>> = OnceLock::new();
+    PROVIDERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Queue stats for every rate-limited provider, by name.
+pub fn queue_stats() -> BTreeMap<String, QueueStats> {
+    let providers = registry().lock().unwrap_or_else(|e| e.into_inner());
+    providers
+        .iter()
+        .map(|(name, c)| {
+            let stats = QueueStats {
+                queued: c.queued.load(Ordering::Relaxed),
+                requests: c.requests.load(Ordering::Relaxed),
+                delayed: c.delayed.load(Ordering::Relaxed),
+                waited_ms: c.waited_ms.load(Ordering::Relaxed),
+                max_wait_ms: c.max_wait_ms.load(Ordering::Relaxed),
+            };
+            (name.clone(), stats)
+        })
+        .collect()
+}
+
+/// Estimated input tokens for a request: the system prompt, history, and
+/// tool schemas.
+pub fn request_tokens(request: &CompletionRequest) -> u64 {
+    let history = serde_json::to_string(&request.chat_history).unwrap_or_default();
+    let tools = serde_json::to_string(&request.tools).unwrap_or_default();
+    request.preamble.as_deref().map_or(0, estimate_tokens)
+        + estimate_tokens(&history)
+        + estimate_tokens(&tools)
+}
+
+/// The limiter for one provider, shared by every tier that uses it.
+pub struct RateLimiter {
+    provider: String,
+    limit: RateLimit,
+    /// Held by the request at the front of the queue while it waits, so
+    /// the rest wait behind it in order.
+    turn: tokio::sync::Mutex<()>,
+    /// Requests sent within the window, with their estimated tokens.
+    sent: Mutex<VecDeque<(Instant, u64)>>,
+    counters: Arc<Counters>,
+}
+
+impl RateLimiter {
+    pub fn new(provider: &str, limit: RateLimit) -> Self {
+        let counters = registry()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(provider.to_string())
+            .or_default()
+            .clone();
+        Self {
+            provider: provider.to_string(),
+            limit,
+            turn: tokio::sync::Mutex::new(()),
+            sent: Mutex::new(VecDeque::new()),
+            counters,
+        }
+    }
+
+    /// Wait until a request of about `tokens` input tokens fits, and count
+    /// it as sent. Returns how long it waited.
+    pub async fn acquire(&self, tokens: u64) -> Duration {
+        let started = Instant::now();
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        let _turn = self.turn.lock().await;
+        while let Some(wait) = self.wait_for(tokens, Instant::now()) {
+            debug!(
+                "Holding a {}-token request to {} for {wait:.2?}",
+                tokens, self.provider
+            );
+            tokio::time::sleep(wait).await;
+        }
+        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+
+        let waited = started.elapsed();
+        let waited_ms = waited.as_millis() as u64;
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+        if waited_ms > 0 {
+            self.counters.delayed.fetch_add(1, Ordering::Relaxed);
+            self.counters
+                .waited_ms
+                .fetch_add(waited_ms, Ordering::Relaxed);
+            self.counters
+                .max_wait_ms
+                .fetch_max(waited_ms, Ordering::Relaxed);
+        }
+        waited
+    }
+
+    /// How long to wait before checking again, or `None` when the request
+    /// fits at `now` (it's then counted as sent).
+    fn wait_for(&self, tokens: u64, now: Instant) -> Option<Duration> {
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        while sent
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= WINDOW)
+        {
+            sent.pop_front();
+        }
+        let Some(&(oldest, _)) = sent.front() else {
+            // A request bigger than the whole budget still goes, on its own
+            sent.push_back((now, tokens));
+            return None;
+        };
+
+        let too_many = self
+            .limit
+            .requests_per_minute
+            .is_some_and(|n| sent.len() >= n as usize);
+        let used: u64 = sent.iter().map(|(_, t)| t).sum();
+        let too_big = self
+            .limit
+            .tokens_per_minute
+            .is_some_and(|n| used + tokens > n);
+        if too_many || too_big {
+            return Some(WINDOW - now.duration_since(oldest));
+        }
+        sent.push_back((now, tokens));
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_for_the_oldest_request_to_leave_the_window() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let requests = RateLimiter::new(
+            "test-requests",
+            RateLimit {
+                requests_per_minute: Some(2),
+                tokens_per_minute: None,
+            },
+        );
+        assert_eq!(requests.wait_for(100, at(0)), None);
+        assert_eq!(requests.wait_for(100, at(10)), None);
+        assert_eq!(
+            requests.wait_for(100, at(20)),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(requests.wait_for(100, at(60)), None);
+
+        let tokens = RateLimiter::new(
+            "test-tokens",
+            RateLimit {
+                requests_per_minute: None,
+                tokens_per_minute: Some(1000),
+            },
+        );
+        assert_eq!(tokens.wait_for(600, at(0)), None);
+        assert_eq!(tokens.wait_for(500, at(10)), Some(Duration::from_secs(50)));
+        assert_eq!(tokens.wait_for(500, at(60)), None);
+        assert_eq!(tokens.wait_for(5000, at(61)), Some(Duration::from_secs(59)));
+        // Bigger than the whole budget, but alone in the window
+        assert_eq!(tokens.wait_for(5000, at(120)), None);
+    }
+}
@@ -0,0 +1,597 @@
+//! Replay recorded sessions through the current agent loop, to catch
+//! regressions in context building, tool schemas, and the loop itself.
+//!
+//! A recording is a session debug log written with `logging.sessionLogs`
+//! (see [`crate::session_log`]). [`MockCompletionModel`] answers each
+//! completion request with the next recorded model response, and every tool
+//! is swapped for a stand-in that returns the recorded result, so a replay
+//! never calls a provider or runs a real tool.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+#[allow(deprecated)]
+use rig::client::completion::CompletionModelHandle;
+use rig::completion::{
+    CompletionError, CompletionModel, CompletionRequest, CompletionResponse, GetTokenUsage,
+    ToolDefinition, Usage,
+};
+use rig::message::{AssistantContent, Text};
+use rig::streaming::{RawStreamingChoice, RawStreamingToolCall, StreamingCompletionResponse};
+use rig::OneOrMany;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::model_pool::ModelPool;
+use crate::agent::r#loop::AgentLoop;
+use crate::tools::{validate_params, Tool};
+
+/// One recorded model response.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedResponse {
+    pub text: String,
+    /// (tool name, arguments) in call order.
+    pub tool_calls: Vec<(String, serde_json::Value)>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// One user message and everything the agent did to answer it.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedTurn {
+    pub session_key: String,
+    pub user_message: String,
+    pub tier: Option<String>,
+    pub system_prompt: String,
+    /// Tool names offered to the model.
+    pub tools: Vec<String>,
+    pub responses: Vec<RecordedResponse>,
+    /// (tool name, result) in call order.
+    pub tool_results: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub turns: Vec<RecordedTurn>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text)
+    }
+
+    /// Parse session debug log lines. Events before the first `turn` are
+    /// skipped.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut turns: Vec<RecordedTurn> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: serde_json::Value = serde_json::from_str(line)
+                .with_context(|| format!("Line {}: invalid JSON", i + 1))?;
+            let field = |key: &str| event[key].as_str().unwrap_or("").to_string();
+
+            if event["event"] == "turn" {
+                turns.push(RecordedTurn {
+                    session_key: field("session"),
+                    user_message: field("user_message"),
+                    tier: event["tier"].as_str().map(String::from),
+                    ..Default::default()
+                });
+                continue;
+            }
+            let Some(turn) = turns.last_mut() else {
+                continue;
+            };
+            match event["event"].as_str() {
+                Some("request") if event["iteration"] == 1 => {
+                    turn.system_prompt = field("system_prompt");
+                    turn.tools = event["tools"]
+                        .as_array()
+                        .map(|tools| {
+                            tools
+                                .iter()
+                                .filter_map(|t| t.as_str().map(String::from))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                }
+                Some("response") => turn.responses.push(RecordedResponse {
+                    text: field("text"),
+                    tool_calls: event["tool_calls"]
+                        .as_array()
+                        .map(|calls| {
+                            calls
+                                .iter()
+                                .map(|c| {
+                                    let name = c["name"].as_str().unwrap_or("").to_string();
+                                    (name, c["arguments"].clone())
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    input_tokens: event["input_tokens"].as_u64().unwrap_or(0),
+                    output_tokens: event["output_tokens"].as_u64().unwrap_or(0),
+                }),
+                Some("tool") => turn.tool_results.push((field("name"), field("result"))),
+                _ => {}
+            }
+        }
+        if turns.is_empty() {
+            bail!("No turns found; record a session with logging.sessionLogs enabled");
+        }
+        Ok(Self { turns })
+    }
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What the agent sent in one completion request.
+#[derive(Debug, Clone)]
+pub struct SeenRequest {
+    pub preamble: Option<String>,
+    pub tools: Vec<ToolDefinition>,
+    pub messages: usize,
+}
+
+#[derive(Default)]
+struct MockState {
+    responses: VecDeque<RecordedResponse>,
+    requests: Vec<SeenRequest>,
+}
+
+/// Completion model that answers with queued responses in order and keeps
+/// the requests it was sent. Clones share the queue.
+#[derive(Clone, Default)]
+pub struct MockCompletionModel {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockCompletionModel {
+    pub fn new(responses: impl IntoIterator<Item = RecordedResponse>) -> Self {
+        let model = Self::default();
+        model.push(responses);
+        model
+    }
+
+    /// Queue more responses.
+    pub fn push(&self, responses: impl IntoIterator<Item = RecordedResponse>) {
+        lock(&self.state).responses.extend(responses);
+    }
+
+    /// Responses not yet used.
+    pub fn remaining(&self) -> usize {
+        lock(&self.state).responses.len()
+    }
+
+    /// Drop any unused responses.
+    pub fn clear(&self) {
+        lock(&self.state).responses.clear();
+    }
+
+    /// Requests received since the last call.
+    pub fn take_requests(&self) -> Vec<SeenRequest> {
+        std::mem::take(&mut lock(&self.state).requests)
+    }
+
+    /// A pool whose only tier ("default") is this model.
+    #[allow(deprecated)]
+    pub fn into_pool(self) -> ModelPool {
+        let mut models = HashMap::new();
+        models.insert(
+            "default".to_string(),
+            (
+                CompletionModelHandle::new(Arc::new(self)),
+                "replay".to_string(),
+                "replay".to_string(),
+            ),
+        );
+        ModelPool::new(models)
+    }
+
+    fn next(&self, request: &CompletionRequest) -> Result<RecordedResponse, CompletionError> {
+        let mut state = lock(&self.state);
+        state.requests.push(SeenRequest {
+            preamble: request.preamble.clone(),
+            tools: request.tools.clone(),
+            messages: request.chat_history.len(),
+        });
+        state.responses.pop_front().ok_or_else(|| {
+            CompletionError::ProviderError(
+                "replay: the agent made more completion requests than were recorded".into(),
+            )
+        })
+    }
+}
+
+/// Token counts carried over from the recording.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockResponse {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl GetTokenUsage for MockResponse {
+    fn token_usage(&self) -> Option<Usage> {
+        let mut usage = Usage::new();
+        usage.input_tokens = self.input_tokens;
+        usage.output_tokens = self.output_tokens;
+        usage.total_tokens = self.input_tokens + self.output_tokens;
+        Some(usage)
+    }
+}
+
+fn tool_call_id(i: usize) -> String {
+    format!("replay_call_{i}")
+}
+
+impl CompletionModel for MockCompletionModel {
+    type Response = MockResponse;
+    type StreamingResponse = MockResponse;
+    type Client = MockCompletionModel;
+
+    fn make(client: &Self::Client, _model: impl Into<String>) -> Self {
+        client.clone()
+    }
+
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> std::result::Result<CompletionResponse<MockResponse>, CompletionError> {
+        let recorded = self.next(&request)?;
+        let usage = MockResponse {
+            input_tokens: recorded.input_tokens,
+            output_tokens: recorded.output_tokens,
+        };
+        let mut choice = Vec::new();
+        if !recorded.text.is_empty() {
+            choice.push(AssistantContent::Text(Text {
+                text: recorded.text,
+            }));
+        }
+        for (i, (name, args)) in recorded.tool_calls.into_iter().enumerate() {
+            choice.push(AssistantContent::tool_call(tool_call_id(i), name, args));
+        }
+        let choice = OneOrMany::many(choice).unwrap_or_else(|_| {
+            OneOrMany::one(AssistantContent::Text(Text {
+                text: String::new(),
+            }))
+        });
+        Ok(CompletionResponse {
+            choice,
+            usage: usage.token_usage().unwrap_or_default(),
+            raw_response: usage,
+        })
+    }
+
+    async fn stream(
+        &self,
+        request: CompletionRequest,
+    ) -> std::result::Result<StreamingCompletionResponse<MockResponse>, CompletionError> {
+        let recorded = self.next(&request)?;
+        let mut items = Vec::new();
+        if !recorded.text.is_empty() {
+            items.push(Ok(RawStreamingChoice::Message(recorded.text)));
+        }
+        for (i, (name, args)) in recorded.tool_calls.into_iter().enumerate() {
+            items.push(Ok(RawStreamingChoice::ToolCall(RawStreamingToolCall::new(
+                tool_call_id(i),
+                name,
+                args,
+            ))));
+        }
+        items.push(Ok(RawStreamingChoice::FinalResponse(MockResponse {
+            input_tokens: recorded.input_tokens,
+            output_tokens: recorded.output_tokens,
+        })));
+        Ok(StreamingCompletionResponse::stream(Box::pin(
+            futures::stream::iter(items),
+        )))
+    }
+}
+
+/// Recorded (tool name, result) pairs still to be returned.
+type ToolResults = Arc<Mutex<VecDeque<(String, String)>>>;
+
+/// Stands in for a real tool: same name and schema, but returns the next
+/// recorded result for that tool instead of running anything.
+struct ReplayTool {
+    name: String,
+    description: String,
+    schema: serde_json::Value,
+    results: ToolResults,
+}
+
+#[async_trait]
+impl Tool for ReplayTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.schema.clone()
+    }
+
+    async fn execute(&self, _params: serde_json::Value) -> Result<String> {
+        let mut results = lock(&self.results);
+        match results.iter().position(|(name, _)| *name == self.name) {
+            Some(i) => Ok(results.remove(i).map(|(_, r)| r).unwrap_or_default()),
+            None => Ok(format!(
+                "Error: replay has no recorded result for {}",
+                self.name
+            )),
+        }
+    }
+}
+
+/// Outcome of replaying one turn.
+#[derive(Debug, Default)]
+pub struct TurnReport {
+    pub user_message: String,
+    pub response: String,
+    pub response_matches: bool,
+    pub completions_recorded: usize,
+    pub completions_made: usize,
+    /// (recorded chars, replayed chars) when the system prompt changed.
+    pub system_prompt_changed: Option<(usize, usize)>,
+    pub tools_added: Vec<String>,
+    pub tools_removed: Vec<String>,
+    /// Recorded tool calls the current loop or schemas no longer accept.
+    pub issues: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl TurnReport {
+    /// Whether the loop behaved as recorded. Context and tool list changes
+    /// are reported but don't count as failures.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+            && self.response_matches
+            && self.completions_made == self.completions_recorded
+            && self.issues.is_empty()
+    }
+}
+
+/// Replay `recording` through `agent_loop`. The loop keeps its context
+/// builder and tool schemas but its models and tools are replaced with
+/// recorded ones, so this is only meant for a loop built for the replay.
+/// Turns run in order in one session, so history builds up as it did.
+pub async fn replay(
+    agent_loop: &mut AgentLoop,
+    recording: &Recording,
+    session_key: &str,
+) -> Vec<TurnReport> {
+    let model = MockCompletionModel::default();
+    agent_loop.models = model.clone().into_pool();
+
+    let results: ToolResults = Arc::default();
+    let real_tools = std::mem::take(&mut agent_loop.tools);
+    for tool in real_tools.list() {
+        agent_loop.tools.register(Box::new(ReplayTool {
+            name: tool.name().to_string(),
+            description: tool.description().to_string(),
+            schema: tool.parameters_schema(),
+            results: results.clone(),
+        }));
+    }
+
+    let mut reports = Vec::new();
+    for turn in &recording.turns {
+        model.push(turn.responses.iter().cloned());
+        *lock(&results) = turn.tool_results.iter().cloned().collect();
+
+        let outcome = agent_loop
+            .process_message_with_persona(
+                session_key,
+                &turn.user_message,
+                None,
+                None,
+                turn.tier.as_deref(),
+            )
+            .await;
+        let requests = model.take_requests();
+        model.clear();
+
+        let expected = turn.responses.last().map(|r| r.text.trim()).unwrap_or("");
+        let mut report = TurnReport {
+            user_message: turn.user_message.clone(),
+            completions_recorded: turn.responses.len(),
+            completions_made: requests.len(),
+            ..Default::default()
+        };
+        match outcome {
+            Ok((response, _)) => {
+                report.response_matches = response.trim() == expected;
+                report.response = response;
+            }
+            Err(e) => report.error = Some(e.to_string()),
+        }
+
+        if let Some(first) = requests.first() {
+            let preamble = first.preamble.as_deref().unwrap_or("");
+            if preamble != turn.system_prompt {
+                report.system_prompt_changed = Some((turn.system_prompt.len(), preamble.len()));
+            }
+            if !turn.tools.is_empty() {
+                let recorded: BTreeSet<&str> = turn.tools.iter().map(String::as_str).collect();
+                let current: BTreeSet<&str> = first.tools.iter().map(|t| t.name.as_str()).collect();
+                report.tools_added = current
+                    .difference(&recorded)
+                    .map(|s| s.to_string())
+                    .collect();
+                report.tools_removed = recorded
+                    .difference(&current)
+                    .map(|s| s.to_string())
+                    .collect();
+            }
+        }
+
+        for (name, args) in turn.responses.iter().flat_map(|r| &r.tool_calls) {
+            match agent_loop.tools.get(name) {
+                None => report.issues.push(format!("{name}: tool no longer exists")),
+                Some(tool) => {
+                    let errors = validate_params(args, &tool.parameters_schema());
+                    if !errors.is_empty() {
+                        report.issues.push(format!(
+                            "{name}: recorded arguments no longer valid: {}",
+                            errors.join("; ")
+                        ));
+                    }
+                }
+            }
+        }
+
+        reports.push(report);
+    }
+    reports
+}
+
+/// Human-readable summary of a replay.
+pub fn render_report(reports: &[TurnReport]) -> String {
+    let mut out = String::new();
+    for (i, r) in reports.iter().enumerate() {
+        let mut message: String = r.user_message.chars().take(60).collect();
+        if message.len() < r.user_message.len() {
+            message.push_str("...");
+        }
+        let status = if r.passed() { "ok" } else { "DIVERGED" };
+        out.push_str(&format!("Turn {}: {status} — {message}\n", i + 1));
+        if let Some(ref e) = r.error {
+            out.push_str(&format!("  error: {e}\n"));
+        }
+        if r.completions_made != r.completions_recorded {
+            out.push_str(&format!(
+                "  completions: {} made, {} recorded\n",
+                r.completions_made, r.completions_recorded
+            ));
+        }
+        if r.error.is_none() && !r.response_matches {
+            out.push_str("  final response differs from the recording\n");
+        }
+        if let Some((before, after)) = r.system_prompt_changed {
+            out.push_str(&format!(
+                "  system prompt changed: {before} → {after} chars\n"
+            ));
+        }
+        if !r.tools_added.is_empty() {
+            out.push_str(&format!("  tools added: {}\n", r.tools_added.join(", ")));
+        }
+        if !r.tools_removed.is_empty() {
+            out.push_str(&format!(
+                "  tools removed: {}\n",
+                r.tools_removed.join(", ")
+            ));
+        }
+        for issue in &r.issues {
+            out.push_str(&format!("  {issue}\n"));
+        }
+    }
+    let passed = reports.iter().filter(|r| r.passed()).count();
+    out.push_str(&format!(
+        "\n{passed}/{} turns replayed as recorded",
+        reports.len()
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"{"timestamp":"2026-03-01T10:00:00Z","event":"turn","session":"telegram:42","user_message":"what's 2+2?","tier":"default","agent":"default"}
+{"timestamp":"2026-03-01T10:00:00Z","event":"request","iteration":1,"model":"m","provider":"p","system_prompt":"You are patina.","messages":[],"tools":["calc","exec"]}
+{"timestamp":"2026-03-01T10:00:01Z","event":"response","iteration":1,"text":"","tool_calls":[{"name":"calc","arguments":{"expression":"2+2"}}],"input_tokens":120,"output_tokens":12}
+{"timestamp":"2026-03-01T10:00:01Z","event":"tool","name":"calc","arguments":{"expression":"2+2"},"result":"4"}
+{"timestamp":"2026-03-01T10:00:01Z","event":"request","iteration":2,"messages":[]}
+{"timestamp":"2026-03-01T10:00:02Z","event":"response","iteration":2,"text":"It's 4.","tool_calls":[],"input_tokens":140,"output_tokens":4}
+"#;
+
+    #[test]
+    fn test_parse_recording() {
+        let rec = Recording::parse(LOG).unwrap();
+        assert_eq!(rec.turns.len(), 1);
+        let turn = &rec.turns[0];
+        assert_eq!(turn.session_key, "telegram:42");
+        assert_eq!(turn.user_message, "what's 2+2?");
+        assert_eq!(turn.system_prompt, "You are patina.");
+        assert_eq!(turn.tools, vec!["calc", "exec"]);
+        assert_eq!(turn.responses.len(), 2);
+        assert_eq!(turn.responses[0].tool_calls[0].0, "calc");
+        assert_eq!(turn.responses[1].text, "It's 4.");
+        assert_eq!(turn.tool_results, vec![("calc".into(), "4".into())]);
+
+        assert!(Recording::parse("").is_err());
+        assert!(Recording::parse("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_replays_in_order() {
+        let rec = Recording::parse(LOG).unwrap();
+        let model = MockCompletionModel::new(rec.turns[0].responses.clone());
+        let request = || CompletionRequest {
+            preamble: Some("sys".into()),
+            chat_history: OneOrMany::one(rig::completion::Message::user("hi")),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: None,
+        };
+
+        let first = model.completion(request()).await.unwrap();
+        assert!(matches!(
+            first.choice.first(),
+            AssistantContent::ToolCall(_)
+        ));
+        assert_eq!(first.usage.input_tokens, 120);
+
+        let second = model.completion(request()).await.unwrap();
+        assert!(matches!(second.choice.first(), AssistantContent::Text(t) if t.text == "It's 4."));
+        assert_eq!(model.remaining(), 0);
+        assert!(model.completion(request()).await.is_err());
+
+        let seen = model.take_requests();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].preamble.as_deref(), Some("sys"));
+    }
+
+    #[test]
+    fn test_render_report() {
+        let reports = vec![
+            TurnReport {
+                user_message: "what's 2+2?".into(),
+                response: "It's 4.".into(),
+                response_matches: true,
+                completions_recorded: 2,
+                completions_made: 2,
+                system_prompt_changed: Some((15, 20)),
+                tools_added: vec!["web_search".into()],
+                ..Default::default()
+            },
+            TurnReport {
+                user_message: "and 3+3?".into(),
+                completions_recorded: 2,
+                completions_made: 1,
+                issues: vec!["calc: tool no longer exists".into()],
+                ..Default::default()
+            },
+        ];
+        let out = render_report(&reports);
+        assert!(out.contains("Turn 1: ok — what's 2+2?\n  system prompt changed: 15 → 20 chars\n  tools added: web_search\n"));
+        assert!(out.contains("Turn 2: DIVERGED — and 3+3?\n  completions: 1 made, 2 recorded\n"));
+        assert!(out.contains("  calc: tool no longer exists\n"));
+        assert!(out.ends_with("1/2 turns replayed as recorded"));
+    }
+}
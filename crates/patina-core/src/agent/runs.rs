@@ -0,0 +1,345 @@
+//! Keep a record of what each subagent run did.
+//!
+//! A [`RunRecorder`] is registered as middleware on the subagent's loop and
+//! collects every tool call with its arguments and result. When the run
+//! ends, [`write_run`] saves it under `workspace/runs/<id>/`:
+//!
+//! - `report.md`: the task, outcome, final reply, files, and tool calls
+//! - `transcript.jsonl`: one line per tool call, arguments and result in full
+//! - `files/`: copies of the files the run wrote or edited, as they were
+//!   when it finished
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::agent::middleware::{HookContext, Middleware, ToolDecision};
+
+/// Tools whose `path` argument is a file the run produced.
+const WRITE_TOOLS: &[&str] = &["write_file", "edit_file"];
+
+/// Produced files bigger than this are listed but not copied.
+const MAX_COPY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// One tool call made during a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallRecord {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    pub result: String,
+}
+
+/// Middleware that records a run's tool calls.
+#[derive(Default)]
+pub struct RunRecorder {
+    calls: Mutex<Vec<ToolCallRecord>>,
+    /// Arguments of the call in flight, set by `before_tool`.
+    pending: Mutex<Option<(String, serde_json::Value)>>,
+}
+
+impl RunRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The calls recorded so far.
+    pub fn calls(&self) -> Vec<ToolCallRecord> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl Middleware for RunRecorder {
+    async fn before_tool(
+        &self,
+        _ctx: &HookContext<'_>,
+        tool: &str,
+        args: &mut serde_json::Value,
+    ) -> Result<ToolDecision> {
+        *self.pending.lock().unwrap() = Some((tool.to_string(), args.clone()));
+        Ok(ToolDecision::Run)
+    }
+
+    async fn after_tool(
+        &self,
+        _ctx: &HookContext<'_>,
+        tool: &str,
+        result: &mut String,
+    ) -> Result<()> {
+        // Calls skipped by earlier middleware never reach `before_tool`
+        let arguments = match self.pending.lock().unwrap().take() {
+            Some((name, args)) if name == tool => args,
+            _ => serde_json::Value::Null,
+        };
+        self.calls.lock().unwrap().push(ToolCallRecord {
+            tool: tool.to_string(),
+            arguments,
+            result: result.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// A finished subagent run.
+pub struct RunReport<'a> {
+    pub id: &'a str,
+    pub label: &'a str,
+    pub task: &'a str,
+    /// The final reply, or the error that ended the run.
+    pub outcome: &'a Result<String>,
+    pub started: DateTime<Local>,
+    pub finished: DateTime<Local>,
+    pub calls: &'a [ToolCallRecord],
+}
+
+/// Where a run was saved.
+#[derive(Debug)]
+pub struct RunArtifacts {
+    pub dir: PathBuf,
+    pub tool_calls: usize,
+    /// Files the run wrote or edited, as the tools were given them.
+    pub files: Vec<String>,
+}
+
+impl RunArtifacts {
+    /// One line for the completion message.
+    pub fn summary(&self) -> String {
+        let mut line = format!(
+            "Run saved to {} ({} tool call{}",
+            self.dir.join("report.md").display(),
+            self.tool_calls,
+            if self.tool_calls == 1 { "" } else { "s" }
+        );
+        if !self.files.is_empty() {
+            line.push_str(&format!("; files: {}", self.files.join(", ")));
+        }
+        line.push(')');
+        line
+    }
+}
+
+/// Save `report` under `workspace/runs/<id>/`.
+pub fn write_run(workspace: &Path, report: &RunReport<'_>) -> Result<RunArtifacts> {
+    let dir = workspace.join("runs").join(report.id);
+    std::fs::create_dir_all(&dir)?;
+
+    let mut transcript = String::new();
+    for call in report.calls {
+        transcript.push_str(&serde_json::to_string(call)?);
+        transcript.push('\n');
+    }
+    std::fs::write(dir.join("transcript.jsonl"), transcript)?;
+
+    let files = produced_files(report.calls);
+    let mut file_lines = Vec::new();
+    for path in &files {
+        file_lines.push(copy_produced(workspace, &dir, path));
+    }
+
+    let (status, body) = match report.outcome {
+        Ok(reply) => ("completed", reply.clone()),
+        Err(e) => ("failed", format!("Error: {e}")),
+    };
+    let elapsed = (report.finished - report.started).num_seconds();
+    let mut md = format!(
+        "# {}\n\n\
+         - Run: {}\n\
+         - Status: {status}\n\
+         - Started: {}\n\
+         - Finished: {} ({elapsed}s)\n\
+         - Tool calls: {}\n\n\
+         ## Task\n\n{}\n\n\
+         ## Result\n\n{}\n",
+        report.label,
+        report.id,
+        report.started.format("%Y-%m-%d %H:%M:%S"),
+        report.finished.format("%Y-%m-%d %H:%M:%S"),
+        report.calls.len(),
+        report.task.trim(),
+        body.trim(),
+    );
+    if !file_lines.is_empty() {
+        md.push_str(&format!("\n## Files\n\n{}\n", file_lines.join("\n")));
+    }
+    if !report.calls.is_empty() {
+        md.push_str("\n## Tool calls\n\n");
+        for (i, call) in report.calls.iter().enumerate() {
+            md.push_str(&format!(
+                "{}. `{}` {}\n",
+                i + 1,
+                call.tool,
+                preview(&call.arguments.to_string(), 160)
+            ));
+        }
+        md.push_str("\nFull arguments and results are in transcript.jsonl.\n");
+    }
+    std::fs::write(dir.join("report.md"), md)?;
+
+    Ok(RunArtifacts {
+        dir,
+        tool_calls: report.calls.len(),
+        files,
+    })
+}
+
+/// Paths passed to the file-writing tools, first use first.
+fn produced_files(calls: &[ToolCallRecord]) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for call in calls
+        .iter()
+        .filter(|c| WRITE_TOOLS.contains(&c.tool.as_str()))
+    {
+        if call.result.starts_with("Error") {
+            continue;
+        }
+        if let Some(path) = call.arguments.get("path").and_then(|v| v.as_str()) {
+            if !files.iter().any(|f| f == path) {
+                files.push(path.to_string());
+            }
+        }
+    }
+    files
+}
+
+/// Copy a produced file into the run's `files/` directory, keeping its
+/// path relative to the workspace where it has one. Returns the report line.
+fn copy_produced(workspace: &Path, dir: &Path, path: &str) -> String {
+    let source = match crate::tools::filesystem::resolve_path(path, None) {
+        Ok(p) => p,
+        Err(e) => return format!("- {path} (not copied: {e})"),
+    };
+    let size = match std::fs::metadata(&source) {
+        Ok(m) if m.is_file() => m.len(),
+        _ => return format!("- {path} (no longer exists)"),
+    };
+    if size > MAX_COPY_BYTES {
+        return format!("- {path} (not copied: {size} bytes)");
+    }
+    let workspace = workspace
+        .canonicalize()
+        .unwrap_or_else(|_| workspace.to_path_buf());
+    let relative = match source.strip_prefix(&workspace) {
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => PathBuf::from(source.file_name().unwrap_or_default()),
+    };
+    let target = dir.join("files").join(&relative);
+    let copied = target
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::copy(&source, &target));
+    match copied {
+        Ok(_) => format!("- {path} → files/{}", relative.display()),
+        Err(e) => format!("- {path} (not copied: {e})"),
+    }
+}
+
+fn preview(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    format!("{cut}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(tool: &str, arguments: serde_json::Value, result: &str) -> ToolCallRecord {
+        ToolCallRecord {
+            tool: tool.into(),
+            arguments,
+            result: result.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn recorder_pairs_arguments_with_results() {
+        let recorder = RunRecorder::new();
+        let ctx = HookContext {
+            session_key: "subagent:1",
+            agent: "default",
+            model: "m",
+            provider: "p",
+            iteration: 1,
+        };
+        let mut args = serde_json::json!({"command": "ls"});
+        recorder.before_tool(&ctx, "exec", &mut args).await.unwrap();
+        recorder
+            .after_tool(&ctx, "exec", &mut "a.txt".to_string())
+            .await
+            .unwrap();
+        // Skipped before reaching the recorder
+        recorder
+            .after_tool(&ctx, "web_fetch", &mut "Error: denied".to_string())
+            .await
+            .unwrap();
+
+        let calls = recorder.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].arguments["command"], "ls");
+        assert_eq!(calls[0].result, "a.txt");
+        assert!(calls[1].arguments.is_null());
+    }
+
+    #[test]
+    fn writes_report_transcript_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path();
+        let notes = workspace.join("out/notes.md");
+        std::fs::create_dir_all(notes.parent().unwrap()).unwrap();
+        std::fs::write(&notes, "# Notes").unwrap();
+        let notes = notes.to_string_lossy().to_string();
+
+        let calls = vec![
+            call("exec", serde_json::json!({"command": "ls"}), "a.txt"),
+            call(
+                "write_file",
+                serde_json::json!({"path": notes}),
+                "Wrote 7 bytes",
+            ),
+            call("edit_file", serde_json::json!({"path": notes}), "Edited"),
+            call(
+                "write_file",
+                serde_json::json!({"path": "/nope"}),
+                "Error: denied",
+            ),
+        ];
+        let now = Local::now();
+        let outcome = Ok("Wrote the notes.".to_string());
+        let artifacts = write_run(
+            workspace,
+            &RunReport {
+                id: "ab12cd34",
+                label: "Take notes",
+                task: "Write notes to out/notes.md",
+                outcome: &outcome,
+                started: now,
+                finished: now,
+                calls: &calls,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(artifacts.dir, workspace.join("runs/ab12cd34"));
+        assert_eq!(artifacts.files, [notes]);
+        let copied = std::fs::read_to_string(artifacts.dir.join("files/out/notes.md")).unwrap();
+        assert_eq!(copied, "# Notes");
+
+        let transcript = std::fs::read_to_string(artifacts.dir.join("transcript.jsonl")).unwrap();
+        assert_eq!(transcript.lines().count(), 4);
+        let report = std::fs::read_to_string(artifacts.dir.join("report.md")).unwrap();
+        assert!(report.starts_with("# Take notes\n"));
+        assert!(report.contains("- Status: completed"));
+        assert!(report.contains("## Result\n\nWrote the notes."));
+        assert!(report.contains("→ files/out/notes.md"));
+        assert!(report.contains("1. `exec` {\"command\":\"ls\"}"));
+
+        let summary = artifacts.summary();
+        assert!(summary.contains("runs/ab12cd34/report.md (4 tool calls; files: "));
+    }
+}
@@ -0,0 +1,498 @@
+//! Per-session behavior overrides set from chat.
+//!
+//! `/set temperature 0.3`, `/set model fast`, `/set persona coder`,
+//! `/set language Spanish`, `/set timezone Europe/Berlin`, `/set locale de-DE`
+//! and `/set prompt ...` change how the agent answers in one session without
+//! touching config. Values live in session metadata, so they persist with the
+//! session file and survive restarts. `/show settings` lists them.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::locale::{normalize_locale, parse_timezone, UserLocale};
+use crate::persona::PersonaStore;
+
+/// Session metadata key for [`SessionSettings`].
+pub const SETTINGS_KEY: &str = "settings";
+/// Session metadata key for the active persona (shared with the web UI).
+pub const PERSONA_KEY: &str = "persona";
+/// Session metadata key for the language last detected in the user's messages.
+pub const DETECTED_LANGUAGE_KEY: &str = "detectedLanguage";
+/// Session metadata key for the workspace directories the agent last worked
+/// in, whose AGENTS.md files go into the prompt.
+pub const WORKING_DIRS_KEY: &str = "workingDirs";
+/// Session metadata key for the project the session is attached to, set
+/// with `/project`.
+pub const PROJECT_KEY: &str = "project";
+
+/// The project a session is attached to with `/project`, if any.
+pub fn session_project(metadata: &HashMap<String, Value>) -> Option<String> {
+    metadata
+        .get(PROJECT_KEY)
+        .and_then(|v| v.as_str())
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+}
+
+/// Overrides applied on top of the agent defaults and any persona.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Model tier name from `agents.models`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Replaces the system prompt (and a persona's preamble).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Language to reply in, overriding detection ("Spanish", "Japanese").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// IANA timezone ("Europe/Berlin"), overriding the user's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Locale ("de-DE"), overriding the user's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl SessionSettings {
+    pub fn from_metadata(metadata: &HashMap<String, Value>) -> Self {
+        metadata
+            .get(SETTINGS_KEY)
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// The language to reply in: the pinned one, else the one last detected
+    /// from the user's messages.
+    pub fn reply_language(&self, metadata: &HashMap<String, Value>) -> Option<String> {
+        self.language.clone().or_else(|| {
+            metadata
+                .get(DETECTED_LANGUAGE_KEY)
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        })
+    }
+
+    /// `user` with this session's timezone and locale overrides applied.
+    pub fn locale(&self, user: &UserLocale) -> UserLocale {
+        UserLocale::new(self.timezone.as_deref(), self.locale.as_deref()).or(user)
+    }
+
+    /// Write back to metadata, removing the key when nothing is overridden.
+    pub fn store(&self, metadata: &mut HashMap<String, Value>) {
+        if *self == Self::default() {
+            metadata.remove(SETTINGS_KEY);
+        } else if let Ok(value) = serde_json::to_value(self) {
+            metadata.insert(SETTINGS_KEY.to_string(), value);
+        }
+    }
+}
+
+/// A setting that can be changed with `/set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKey {
+    Temperature,
+    Model,
+    Persona,
+    Prompt,
+    Language,
+    Timezone,
+    Locale,
+}
+
+impl SettingKey {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "temperature" | "temp" => Some(Self::Temperature),
+            "model" | "tier" => Some(Self::Model),
+            "persona" => Some(Self::Persona),
+            "prompt" | "system" | "systemprompt" => Some(Self::Prompt),
+            "language" | "lang" => Some(Self::Language),
+            "timezone" | "tz" => Some(Self::Timezone),
+            "locale" => Some(Self::Locale),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed settings command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsCommand {
+    /// `/set <key> <value>`. "default", "none", or no value clears the override.
+    Set {
+        key: SettingKey,
+        value: Option<String>,
+    },
+    Show,
+    /// Malformed command, with a usage hint to send back.
+    Invalid(String),
+}
+
+const USAGE: &str =
+    "Usage: /set temperature|model|persona|prompt|language|timezone|locale <value>\n\
+                     Use \"default\" as the value to clear an override.";
+
+impl SettingsCommand {
+    /// Parse `/set ...` or `/show settings`. Returns `None` for anything else.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (command, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        Self::from_parts(command, rest)
+    }
+
+    /// Parse an already split command name (with its slash) and arguments.
+    pub fn from_parts(command: &str, args: &str) -> Option<Self> {
+        let rest = args.trim();
+        match command {
+            "/set" => {
+                let (name, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let Some(key) = SettingKey::parse(name) else {
+                    return Some(Self::Invalid(USAGE.to_string()));
+                };
+                let value = value.trim();
+                let clears = value.is_empty()
+                    || value.eq_ignore_ascii_case("default")
+                    || value.eq_ignore_ascii_case("none")
+                    || (key == SettingKey::Language && value.eq_ignore_ascii_case("auto"));
+                let value = (!clears).then(|| value.to_string());
+                Some(Self::Set { key, value })
+            }
+            "/show" if rest.is_empty() || rest == "settings" => Some(Self::Show),
+            "/settings" => Some(Self::Show),
+            _ => None,
+        }
+    }
+
+    /// Apply the command to a session's metadata and return the reply text.
+    ///
+    /// `tiers` are the configured model tiers; unknown tiers and personas are
+    /// rejected so a typo doesn't silently fall back to the default.
+    pub fn apply(
+        &self,
+        metadata: &mut HashMap<String, Value>,
+        tiers: &[&str],
+        personas: &PersonaStore,
+    ) -> String {
+        match self {
+            Self::Invalid(usage) => usage.clone(),
+            Self::Show => describe(metadata),
+            Self::Set { key, value } => {
+                let mut settings = SessionSettings::from_metadata(metadata);
+                let reply = match (key, value) {
+                    (SettingKey::Temperature, None) => {
+                        settings.temperature = None;
+                        "Temperature reset to default.".to_string()
+                    }
+                    (SettingKey::Temperature, Some(v)) => match v.parse::<f64>() {
+                        Ok(t) if (0.0..=2.0).contains(&t) => {
+                            settings.temperature = Some(t);
+                            format!("Temperature set to {t}.")
+                        }
+                        _ => return "Temperature must be a number from 0 to 2.".to_string(),
+                    },
+                    (SettingKey::Model, None) => {
+                        settings.model = None;
+                        "Model reset to default.".to_string()
+                    }
+                    (SettingKey::Model, Some(tier)) => {
+                        if !tiers.contains(&tier.as_str()) {
+                            let mut known = tiers.to_vec();
+                            known.sort_unstable();
+                            return format!(
+                                "Unknown model tier '{tier}'. Available: {}",
+                                known.join(", ")
+                            );
+                        }
+                        settings.model = Some(tier.clone());
+                        format!("Model set to {tier}.")
+                    }
+                    (SettingKey::Persona, None) => {
+                        metadata.remove(PERSONA_KEY);
+                        "Persona cleared.".to_string()
+                    }
+                    (SettingKey::Persona, Some(key)) => {
+                        let Some(persona) = personas.get(key) else {
+                            let mut known: Vec<&str> =
+                                personas.list().keys().map(String::as_str).collect();
+                            known.sort_unstable();
+                            return if known.is_empty() {
+                                format!("Unknown persona '{key}'. No personas are defined.")
+                            } else {
+                                format!("Unknown persona '{key}'. Available: {}", known.join(", "))
+                            };
+                        };
+                        metadata.insert(PERSONA_KEY.to_string(), Value::String(key.clone()));
+                        format!("Persona set to {}.", persona.name)
+                    }
+                    (SettingKey::Prompt, None) => {
+                        settings.system_prompt = None;
+                        "System prompt reset to default.".to_string()
+                    }
+                    (SettingKey::Prompt, Some(prompt)) => {
+                        settings.system_prompt = Some(prompt.clone());
+                        "System prompt set for this session.".to_string()
+                    }
+                    (SettingKey::Language, None) => {
+                        settings.language = None;
+                        "Language set to auto: replies follow the language you write in."
+                            .to_string()
+                    }
+                    (SettingKey::Language, Some(language)) => {
+                        settings.language = Some(language.clone());
+                        format!("Replies will be in {language}.")
+                    }
+                    (SettingKey::Timezone, None) => {
+                        settings.timezone = None;
+                        "Timezone reset to default.".to_string()
+                    }
+                    (SettingKey::Timezone, Some(name)) => {
+                        let Some(tz) = parse_timezone(name) else {
+                            return format!(
+                                "Unknown timezone '{name}'. Use an IANA name like Europe/Berlin or America/New_York."
+                            );
+                        };
+                        settings.timezone = Some(tz.name().to_string());
+                        format!("Timezone set to {}.", tz.name())
+                    }
+                    (SettingKey::Locale, None) => {
+                        settings.locale = None;
+                        "Locale reset to default.".to_string()
+                    }
+                    (SettingKey::Locale, Some(tag)) => {
+                        let Some(locale) = normalize_locale(tag) else {
+                            return format!(
+                                "Invalid locale '{tag}'. Use a tag like en-US, en-GB, or de-DE."
+                            );
+                        };
+                        settings.locale = Some(locale.clone());
+                        format!("Locale set to {locale}.")
+                    }
+                };
+                settings.store(metadata);
+                reply
+            }
+        }
+    }
+}
+
+/// `/show settings` output.
+fn describe(metadata: &HashMap<String, Value>) -> String {
+    let settings = SessionSettings::from_metadata(metadata);
+    let or_default = |v: Option<String>| v.unwrap_or_else(|| "default".to_string());
+    let language = match (&settings.language, settings.reply_language(metadata)) {
+        (Some(language), _) => language.clone(),
+        (None, Some(detected)) => format!("auto ({detected})"),
+        (None, None) => "auto".to_string(),
+    };
+    let persona = metadata
+        .get(PERSONA_KEY)
+        .and_then(|v| v.as_str())
+        .map(str::to_string);
+    let prompt = settings.system_prompt.map(|p| {
+        let preview: String = p.chars().take(80).collect();
+        if preview.len() < p.len() {
+            format!("\"{preview}...\"")
+        } else {
+            format!("\"{preview}\"")
+        }
+    });
+    format!(
+        "Session settings:\n- temperature: {}\n- model: {}\n- persona: {}\n- language: {}\n- timezone: {}\n- locale: {}\n- prompt: {}",
+        or_default(settings.temperature.map(|t| t.to_string())),
+        or_default(settings.model),
+        persona.unwrap_or_else(|| "none".to_string()),
+        language,
+        or_default(settings.timezone),
+        or_default(settings.locale),
+        or_default(prompt),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persona::Persona;
+
+    fn personas(dir: &std::path::Path) -> PersonaStore {
+        let mut store = PersonaStore::load(&dir.join("personas.json"));
+        store
+            .upsert(
+                "coder".into(),
+                Persona {
+                    name: "Coder".into(),
+                    description: String::new(),
+                    preamble: "You write code.".into(),
+                    model_tier: "coding".into(),
+                    color: String::new(),
+                },
+            )
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            SettingsCommand::parse("/set temperature 0.3"),
+            Some(SettingsCommand::Set {
+                key: SettingKey::Temperature,
+                value: Some("0.3".into())
+            })
+        );
+        assert_eq!(
+            SettingsCommand::parse("/set prompt  Be terse. Use bullet points."),
+            Some(SettingsCommand::Set {
+                key: SettingKey::Prompt,
+                value: Some("Be terse. Use bullet points.".into())
+            })
+        );
+        assert_eq!(
+            SettingsCommand::parse("/set model default"),
+            Some(SettingsCommand::Set {
+                key: SettingKey::Model,
+                value: None
+            })
+        );
+        assert_eq!(
+            SettingsCommand::parse("/show settings"),
+            Some(SettingsCommand::Show)
+        );
+        assert!(matches!(
+            SettingsCommand::parse("/set colour blue"),
+            Some(SettingsCommand::Invalid(_))
+        ));
+        assert_eq!(SettingsCommand::parse("/settle down"), None);
+        assert_eq!(SettingsCommand::parse("set temperature 1"), None);
+    }
+
+    #[test]
+    fn set_and_clear_round_trip_through_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = personas(dir.path());
+        let tiers = ["default", "fast"];
+        let mut metadata = HashMap::new();
+
+        let set = |input: &str, metadata: &mut HashMap<String, Value>| {
+            SettingsCommand::parse(input)
+                .unwrap()
+                .apply(metadata, &tiers, &store)
+        };
+
+        assert_eq!(
+            set("/set temperature 0.3", &mut metadata),
+            "Temperature set to 0.3."
+        );
+        assert_eq!(set("/set model fast", &mut metadata), "Model set to fast.");
+        assert_eq!(
+            set("/set persona coder", &mut metadata),
+            "Persona set to Coder."
+        );
+        let settings = SessionSettings::from_metadata(&metadata);
+        assert_eq!(settings.temperature, Some(0.3));
+        assert_eq!(settings.model.as_deref(), Some("fast"));
+        assert_eq!(metadata.get(PERSONA_KEY), Some(&Value::from("coder")));
+
+        let shown = set("/show settings", &mut metadata);
+        assert!(shown.contains("temperature: 0.3"));
+        assert!(shown.contains("persona: coder"));
+
+        set("/set temperature default", &mut metadata);
+        set("/set model default", &mut metadata);
+        set("/set persona none", &mut metadata);
+        assert!(!metadata.contains_key(SETTINGS_KEY));
+    }
+
+    #[test]
+    fn language_pins_over_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = personas(dir.path());
+        let mut metadata = HashMap::new();
+        let set = |input: &str, metadata: &mut HashMap<String, Value>| {
+            SettingsCommand::parse(input)
+                .unwrap()
+                .apply(metadata, &["default"], &store)
+        };
+
+        metadata.insert(DETECTED_LANGUAGE_KEY.into(), Value::from("French"));
+        let settings = SessionSettings::from_metadata(&metadata);
+        assert_eq!(
+            settings.reply_language(&metadata).as_deref(),
+            Some("French")
+        );
+        assert!(set("/show settings", &mut metadata).contains("language: auto (French)"));
+
+        assert_eq!(
+            set("/set lang Spanish", &mut metadata),
+            "Replies will be in Spanish."
+        );
+        let settings = SessionSettings::from_metadata(&metadata);
+        assert_eq!(
+            settings.reply_language(&metadata).as_deref(),
+            Some("Spanish")
+        );
+
+        set("/set language auto", &mut metadata);
+        assert!(!metadata.contains_key(SETTINGS_KEY));
+    }
+
+    #[test]
+    fn timezone_and_locale_override_the_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = personas(dir.path());
+        let mut metadata = HashMap::new();
+        let set = |input: &str, metadata: &mut HashMap<String, Value>| {
+            SettingsCommand::parse(input)
+                .unwrap()
+                .apply(metadata, &["default"], &store)
+        };
+        let user = UserLocale::new(Some("Europe/Lisbon"), Some("pt-PT"));
+
+        assert_eq!(
+            set("/set tz america/new_york", &mut metadata),
+            "Timezone set to America/New_York."
+        );
+        assert_eq!(
+            set("/set locale en_us", &mut metadata),
+            "Locale set to en-US."
+        );
+        let locale = SessionSettings::from_metadata(&metadata).locale(&user);
+        assert_eq!(locale.tz_name().as_deref(), Some("America/New_York"));
+        assert_eq!(locale.locale.as_deref(), Some("en-US"));
+        assert!(set("/show settings", &mut metadata).contains("timezone: America/New_York"));
+
+        assert!(set("/set timezone Atlantis", &mut metadata).starts_with("Unknown timezone"));
+        set("/set timezone default", &mut metadata);
+        set("/set locale default", &mut metadata);
+        assert_eq!(
+            SessionSettings::from_metadata(&metadata).locale(&user),
+            user
+        );
+        assert!(!metadata.contains_key(SETTINGS_KEY));
+    }
+
+    #[test]
+    fn rejects_invalid_values_without_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = personas(dir.path());
+        let mut metadata = HashMap::new();
+        let apply = |input: &str, metadata: &mut HashMap<String, Value>| {
+            SettingsCommand::parse(input)
+                .unwrap()
+                .apply(metadata, &["default"], &store)
+        };
+
+        assert!(apply("/set temperature hot", &mut metadata).contains("from 0 to 2"));
+        assert!(apply("/set temperature 3", &mut metadata).contains("from 0 to 2"));
+        assert_eq!(
+            apply("/set model turbo", &mut metadata),
+            "Unknown model tier 'turbo'. Available: default"
+        );
+        assert!(apply("/set persona pirate", &mut metadata).contains("Available: coder"));
+        assert!(metadata.is_empty());
+    }
+}
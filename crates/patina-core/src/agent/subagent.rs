@@ -8,10 +8,15 @@ use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
 use crate::agent::context::ContextBuilder;
+use crate::agent::middleware::Middleware;
 use crate::agent::model_pool::ModelPool;
 use crate::agent::r#loop::AgentLoop;
+use crate::agent::runs::{self, RunRecorder, RunReport};
 use crate::bus::InboundMessage;
+use crate::questions::Questions;
 use crate::session::SessionManager;
+use crate::session_log::SessionLogger;
+use crate::tools::ask_user::AskUserTool;
 use crate::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
 use crate::tools::shell::ExecTool;
 use crate::tools::web::{WebFetchTool, WebSearchTool};
@@ -32,6 +37,9 @@ pub struct SubagentManager {
     inbound_tx: mpsc::Sender<InboundMessage>,
     config: patina_config::Config,
     usage_tracker: Option<Arc<UsageTracker>>,
+    session_log: Option<Arc<SessionLogger>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    questions: Option<Arc<Questions>>,
 }
 
 impl SubagentManager {
@@ -48,6 +56,9 @@ impl SubagentManager {
             inbound_tx,
             config,
             usage_tracker: None,
+            session_log: None,
+            middleware: Vec::new(),
+            questions: None,
         }
     }
 
@@ -56,6 +67,22 @@ impl SubagentManager {
         self.usage_tracker = Some(tracker);
     }
 
+    /// Set the per-session debug logger for subagent runs.
+    pub fn set_session_log(&mut self, logger: Arc<SessionLogger>) {
+        self.session_log = Some(logger);
+    }
+
+    /// Let subagents ask the owner questions with `ask_user`.
+    pub fn set_questions(&mut self, questions: Arc<Questions>) {
+        self.questions = Some(questions);
+    }
+
+    /// Register middleware for subagent loops, so the same policy applies to
+    /// spawned work.
+    pub fn add_middleware(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middleware.push(middleware);
+    }
+
     /// Spawn a background agent task.
     pub async fn spawn(
         &self,
@@ -102,7 +129,9 @@ impl SubagentManager {
         info!("Spawning subagent {task_id} ({label_str}): {task}");
 
         // Build isolated agent loop with persona-aware prompt
-        let agent_loop = self.build_subagent_loop_with_persona(&task_id, preamble)?;
+        let recorder = Arc::new(RunRecorder::new());
+        let agent_loop =
+            self.build_subagent_loop_with_persona(&task_id, preamble, recorder.clone())?;
 
         let task_owned = task.to_string();
         let label_owned = label_str.clone();
@@ -113,6 +142,7 @@ impl SubagentManager {
         let running = self.running.clone();
         let preamble_owned = preamble.map(|s| s.to_string());
         let model_tier_owned = model_tier.map(|s| s.to_string());
+        let workspace = self.workspace.clone();
 
         let handle = tokio::spawn(async move {
             // Task-origin subagents write to the task session (unified timeline).
@@ -123,6 +153,7 @@ impl SubagentManager {
                 format!("subagent:{task_id_owned}")
             };
 
+            let started = chrono::Local::now();
             let result = Self::run_subagent_with_persona(
                 agent_loop,
                 &session_key,
@@ -132,8 +163,30 @@ impl SubagentManager {
             )
             .await;
 
-            // Format result announcement
-            let announcement = match &result {
+            // Keep the report, tool transcript, and produced files
+            let calls = recorder.calls();
+            let report = RunReport {
+                id: &task_id_owned,
+                label: &label_owned,
+                task: &task_owned,
+                outcome: &result,
+                started,
+                finished: chrono::Local::now(),
+                calls: &calls,
+            };
+            let artifacts = match runs::write_run(&workspace, &report) {
+                Ok(a) => Some(a),
+                Err(e) => {
+                    warn!("Failed to save subagent {task_id_owned} run: {e}");
+                    None
+                }
+            };
+
+            // Format result announcement. A webhook gets the bare result,
+            // passed on without an agent turn.
+            let mut announcement = match &result {
+                Ok(response) if origin_channel == "webhook" => response.clone(),
+                Err(e) if origin_channel == "webhook" => e.to_string(),
                 Ok(response) => {
                     format!(
                         "[Subagent '{label_owned}' completed]\n\
@@ -149,6 +202,10 @@ impl SubagentManager {
                     )
                 }
             };
+            if let Some(artifacts) = artifacts.as_ref().filter(|_| origin_channel != "webhook") {
+                announcement.push('\n');
+                announcement.push_str(&artifacts.summary());
+            }
 
             // Send result back through the message bus
             let msg = InboundMessage {
@@ -164,6 +221,12 @@ impl SubagentManager {
                         "subagent_id".to_string(),
                         serde_json::Value::String(task_id_owned.clone()),
                     );
+                    if let Some(artifacts) = &artifacts {
+                        m.insert(
+                            "run_dir".to_string(),
+                            serde_json::Value::String(artifacts.dir.display().to_string()),
+                        );
+                    }
                     m.insert(
                         "status".to_string(),
                         serde_json::Value::String(
@@ -217,14 +280,16 @@ impl SubagentManager {
 
     fn build_subagent_loop_with_persona(
         &self,
-        _task_id: &str,
+        task_id: &str,
         preamble: Option<&str>,
+        recorder: Arc<RunRecorder>,
     ) -> Result<AgentLoop> {
-        let sessions_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".patina")
-            .join("sessions");
-        let sessions = SessionManager::new(sessions_dir);
+        let questions_rule = if self.questions.is_some() {
+            "Do NOT start conversations. If you reach a decision you can't make \
+             yourself, ask with ask_user"
+        } else {
+            "Do NOT start conversations or ask questions"
+        };
 
         // If a persona preamble is provided, use it with task-focus rules appended.
         // Otherwise fall back to the generic worker prompt.
@@ -243,7 +308,7 @@ impl SubagentManager {
                  Your workspace is: {}\n\n\
                  IMPORTANT RULES:\n\
                  - Stay focused ONLY on your assigned task\n\
-                 - Do NOT start conversations or ask questions\n\
+                 - {questions_rule}\n\
                  - Do NOT work on anything besides your task\n\
                  - Be concise but thorough in your work\n\
                  - When done, provide a clear summary of what you accomplished",
@@ -253,7 +318,51 @@ impl SubagentManager {
 
         let context = ContextBuilder::with_preamble(&self.workspace, subagent_prompt);
 
-        // Restricted tool set — no message, spawn, or cron tools
+        let mut tools = self.worker_tools();
+        if let Some(questions) = &self.questions {
+            tools.register(Box::new(AskUserTool::new(
+                questions.clone(),
+                &format!("subagent {task_id}"),
+            )));
+        }
+
+        // The recorder goes last, so it sees arguments as the tools got them
+        let middleware = self
+            .middleware
+            .iter()
+            .cloned()
+            .chain([recorder as Arc<dyn Middleware>])
+            .collect();
+        let sessions = SessionManager::new(patina_config::data_dir().join("sessions"));
+        Ok(self.worker_loop(sessions, context, tools, middleware))
+    }
+
+    /// A loop for one `patina batch` worker, with sessions in `sessions_dir`
+    /// and the subagent tools, or none without `tools`. There's no
+    /// `ask_user`: nobody is waiting to answer.
+    pub fn batch_loop(&self, sessions_dir: PathBuf, tools: bool) -> AgentLoop {
+        let prompt = format!(
+            "You are answering one prompt of a batch job. Your workspace is: {}\n\n\
+             Reply with the answer only. It's saved as the result as-is, and nobody \
+             will see questions or offers of further help.",
+            self.workspace.display()
+        );
+        let tools = if tools {
+            self.worker_tools()
+        } else {
+            ToolRegistry::new()
+        };
+        self.worker_loop(
+            SessionManager::new(sessions_dir),
+            ContextBuilder::with_preamble(&self.workspace, prompt),
+            tools,
+            self.middleware.clone(),
+        )
+    }
+
+    /// The restricted tool set for background work: files, shell, and web.
+    /// No message, spawn, or cron tools.
+    fn worker_tools(&self) -> ToolRegistry {
         let mut tools = ToolRegistry::new();
         let allowed_dir: Option<PathBuf> = if self.config.tools.restrict_to_workspace {
             Some(self.workspace.clone())
@@ -264,11 +373,14 @@ impl SubagentManager {
         tools.register(Box::new(WriteFileTool::new(allowed_dir.clone())));
         tools.register(Box::new(EditFileTool::new(allowed_dir.clone())));
         tools.register(Box::new(ListDirTool::new(allowed_dir)));
-        tools.register(Box::new(ExecTool::new(
-            self.workspace.clone(),
-            self.config.tools.exec.timeout_secs,
-            self.config.tools.restrict_to_workspace,
-        )));
+        tools.register(Box::new(
+            ExecTool::new(
+                self.workspace.clone(),
+                self.config.tools.exec.timeout_secs,
+                self.config.tools.restrict_to_workspace,
+            )
+            .with_shell(self.config.tools.exec.shell.clone()),
+        ));
 
         let brave_api_key = if self.config.tools.web.search.api_key.is_empty() {
             std::env::var("BRAVE_API_KEY").unwrap_or_default()
@@ -280,13 +392,22 @@ impl SubagentManager {
             self.config.tools.web.search.max_results,
         )));
         tools.register(Box::new(WebFetchTool::new(50_000)));
+        tools
+    }
 
-        Ok(AgentLoop {
+    fn worker_loop(
+        &self,
+        sessions: SessionManager,
+        context: ContextBuilder,
+        tools: ToolRegistry,
+        middleware: Vec<Arc<dyn Middleware>>,
+    ) -> AgentLoop {
+        AgentLoop {
             models: self.models.clone(),
             sessions,
             context,
             tools,
-            max_iterations: 15, // Lower limit for subagents
+            max_iterations: 15, // Lower limit for background work
             temperature: self.config.agents.defaults.temperature as f64,
             max_tokens: self.config.agents.defaults.max_tokens as u64,
             memory_window: self.config.agents.defaults.memory_window,
@@ -294,8 +415,11 @@ impl SubagentManager {
             memory_index: None,
             channel_rules: std::collections::HashMap::new(),
             usage_tracker: self.usage_tracker.clone(),
+            session_log: self.session_log.clone(),
+            middleware,
             stream_tx: None,
-        })
+            memory_approvals: None,
+        }
     }
 
     async fn run_subagent_with_persona(
@@ -0,0 +1,242 @@
+//! Template expansion for workspace prompt files.
+//!
+//! AGENTS.md, SOUL.md, the other bootstrap files, and always-loaded skills
+//! may contain `{{...}}` placeholders, expanded each time the system prompt
+//! is built:
+//!
+//! - `{{include:notes/style.md}}`: another workspace file, itself expanded
+//! - `{{date}}`: today's date on the user's clock, `YYYY-MM-DD`
+//! - `{{channel}}`, `{{chat_id}}`, `{{persona}}`, `{{workspace}}`
+//! - `{{env:NAME}}`: an environment variable
+//! - `{{config:agents.defaults.model}}`: a config value, by its JSON path
+//!
+//! Anything else between braces is left as written, so code samples with
+//! `{{ }}` in them survive.
+
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+/// How deep `{{include:...}}` may nest.
+const MAX_INCLUDE_DEPTH: usize = 5;
+
+/// Values for the placeholders in one prompt.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateVars {
+    pub date: String,
+    pub channel: Option<String>,
+    pub chat_id: Option<String>,
+    pub persona: Option<String>,
+    pub workspace: PathBuf,
+    /// The config as JSON, with secrets already removed.
+    pub config: serde_json::Value,
+    /// Values never to put in a prompt, e.g. API keys read through `env:`.
+    pub secrets: Vec<String>,
+}
+
+/// Expand the placeholders in `text`.
+pub fn expand(text: &str, vars: &TemplateVars) -> String {
+    expand_in(text, vars, &mut Vec::new())
+}
+
+fn expand_in(text: &str, vars: &TemplateVars, including: &mut Vec<PathBuf>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let whole = &rest[start..start + 2 + end + 2];
+        match resolve(after[..end].trim(), vars, including) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(whole),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The value for one placeholder, or `None` to leave it as written.
+fn resolve(key: &str, vars: &TemplateVars, including: &mut Vec<PathBuf>) -> Option<String> {
+    if let Some(path) = key.strip_prefix("include:") {
+        return Some(include(path.trim(), vars, including));
+    }
+    if let Some(name) = key.strip_prefix("env:") {
+        let value = std::env::var(name.trim()).unwrap_or_default();
+        return Some(unless_secret(value, vars));
+    }
+    if let Some(path) = key.strip_prefix("config:") {
+        return Some(unless_secret(config_value(&vars.config, path.trim()), vars));
+    }
+    match key {
+        "date" => Some(vars.date.clone()),
+        "channel" => Some(vars.channel.clone().unwrap_or_default()),
+        "chat_id" => Some(vars.chat_id.clone().unwrap_or_default()),
+        "persona" => Some(vars.persona.clone().unwrap_or_default()),
+        "workspace" => Some(vars.workspace.display().to_string()),
+        _ => None,
+    }
+}
+
+fn unless_secret(value: String, vars: &TemplateVars) -> String {
+    if !value.is_empty() && vars.secrets.contains(&value) {
+        warn!("Left a secret out of the system prompt");
+        return String::new();
+    }
+    value
+}
+
+/// `agents.defaults.model` → the value at that path. Strings come out bare,
+/// anything else as JSON; a missing path is empty.
+fn config_value(config: &serde_json::Value, path: &str) -> String {
+    let mut value = config;
+    for key in path.split('.') {
+        value = match value {
+            serde_json::Value::Object(map) => match map.get(key) {
+                Some(v) => v,
+                None => return String::new(),
+            },
+            serde_json::Value::Array(items) => {
+                match key.parse::<usize>().ok().and_then(|i| items.get(i)) {
+                    Some(v) => v,
+                    None => return String::new(),
+                }
+            }
+            _ => return String::new(),
+        };
+    }
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Read and expand a workspace file. Problems show up in the prompt as a
+/// short note instead of failing the turn.
+fn include(path: &str, vars: &TemplateVars, including: &mut Vec<PathBuf>) -> String {
+    let resolved = match workspace_file(&vars.workspace, path) {
+        Ok(p) => p,
+        Err(reason) => {
+            warn!("Not including {path}: {reason}");
+            return format!("[{path} not included: {reason}]");
+        }
+    };
+    if including.contains(&resolved) {
+        warn!("Not including {path}: it includes itself");
+        return format!("[{path} not included: it includes itself]");
+    }
+    if including.len() >= MAX_INCLUDE_DEPTH {
+        warn!("Not including {path}: includes nest more than {MAX_INCLUDE_DEPTH} deep");
+        return format!("[{path} not included: includes nest too deep]");
+    }
+    let content = match std::fs::read_to_string(&resolved) {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("Not including {path}: {e}");
+            return format!("[{path} not included: {e}]");
+        }
+    };
+    including.push(resolved);
+    let expanded = expand_in(content.trim_end(), vars, including);
+    including.pop();
+    expanded
+}
+
+/// `path` under the workspace, refusing anything that resolves outside it.
+fn workspace_file(workspace: &Path, path: &str) -> Result<PathBuf, String> {
+    let root = workspace
+        .canonicalize()
+        .map_err(|e| format!("workspace unavailable: {e}"))?;
+    let file = root
+        .join(path)
+        .canonicalize()
+        .map_err(|_| "no such file".to_string())?;
+    if !file.starts_with(&root) {
+        return Err("outside the workspace".into());
+    }
+    Ok(file)
+}
+
+/// The config as JSON for `{{config:...}}`, with every secret string
+/// blanked out.
+pub fn config_json(config: &patina_config::Config) -> serde_json::Value {
+    let secrets = config.secrets();
+    let mut value = serde_json::to_value(config).unwrap_or_default();
+    redact(&mut value, &secrets);
+    value
+}
+
+fn redact(value: &mut serde_json::Value, secrets: &[String]) {
+    match value {
+        serde_json::Value::String(s) if secrets.contains(s) => *value = serde_json::Value::Null,
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|v| redact(v, secrets)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|v| redact(v, secrets)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expands_variables_and_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("notes")).unwrap();
+        std::fs::write(
+            dir.path().join("notes/style.md"),
+            "Style for {{channel}}.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("loop.md"), "{{include:loop.md}}").unwrap();
+        let vars = TemplateVars {
+            date: "2026-03-06".into(),
+            channel: Some("telegram".into()),
+            workspace: dir.path().to_path_buf(),
+            config: serde_json::json!({"agents": {"defaults": {"model": "gpt-4o", "key": "sk-1"}}}),
+            secrets: vec!["sk-1".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            expand(
+                "Today is {{date}} on {{ channel }}, chat {{chat_id}}.",
+                &vars
+            ),
+            "Today is 2026-03-06 on telegram, chat ."
+        );
+        assert_eq!(
+            expand(
+                "{{include:notes/style.md}} Model: {{config:agents.defaults.model}}",
+                &vars
+            ),
+            "Style for telegram. Model: gpt-4o"
+        );
+        assert_eq!(expand("{{config:agents.defaults.key}}", &vars), "");
+        assert_eq!(
+            expand("Keep {{ user.name }} and {{unclosed", &vars),
+            "Keep {{ user.name }} and {{unclosed"
+        );
+        assert_eq!(
+            expand("{{include:loop.md}}", &vars),
+            "[loop.md not included: it includes itself]"
+        );
+        assert_eq!(
+            expand("{{include:../outside.md}}", &vars),
+            "[../outside.md not included: no such file]"
+        );
+    }
+
+    #[test]
+    fn test_config_json_drops_secrets() {
+        let mut config = patina_config::Config::default();
+        config.channels.telegram.token = "123:abc".into();
+        let json = config_json(&config);
+        assert!(json["channels"]["telegram"]["token"].is_null());
+    }
+}
@@ -0,0 +1,1100 @@
+//! Assemble an agent from config, for the CLI or a program embedding patina.
+//!
+//! [`AgentBuilder`] does the wiring the `patina` binary does at startup:
+//! model tiers, sessions, the built-in tools, cron, tasks, and the memory
+//! index. Add your own [`Tool`]s and [`Middleware`] before building, then
+//! either drive [`AgentLoop`] directly or hand the [`Agent`] a
+//! [`MessageBus`] to serve:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), patina_core::PatinaError> {
+//! let config = patina_config::load_config(&patina_config::find_config_path())?;
+//! let mut agent = patina_core::AgentBuilder::new(config).build()?;
+//! let (reply, _) = agent
+//!     .agent_loop
+//!     .process_message("embed:demo", "What's on my task list?", None)
+//!     .await?;
+//! println!("{reply}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use patina_config::{data_dir, resolve_workspace};
+#[allow(deprecated)]
+use rig::client::completion::CompletionModelHandle;
+use rig::client::{CompletionClient, Nothing};
+use rig::providers::{anthropic, deepseek, gemini, groq, mistral, ollama, openai, openrouter};
+use tokio::sync::Mutex;
+
+use crate::agent::obsidian::Vault;
+use crate::agent::rate_limit::RateLimit;
+use crate::agent::subagent::SubagentManager;
+use crate::agent::{
+    AgentLoop, Citations, ContextBuilder, MemoryApprovals, MemoryIndex, Middleware, ModelOverrides,
+    ModelPool,
+};
+use crate::bus::{MessageBus, OutboundMessage};
+use crate::connectors;
+use crate::cron::{reply_metadata, CronService};
+use crate::drafts::DraftStore;
+use crate::dry_run::DryRun;
+use crate::error::PatinaError;
+use crate::knowledge::{self, HttpEmbedder, KnowledgeBase};
+use crate::locale::{Locales, UserLocale};
+use crate::location::LocationStore;
+use crate::lockdown::Lockdown;
+use crate::ollama::OllamaClient;
+use crate::persona::PersonaStore;
+use crate::questions::Questions;
+use crate::session::SessionManager;
+use crate::session_log::{Redactor, SessionLogger};
+use crate::standup::{self, STANDUP_CHANNEL};
+use crate::task::TaskManager;
+use crate::tools::ask_user::AskUserTool;
+use crate::tools::calc::CalcTool;
+use crate::tools::contacts::ContactsTool;
+use crate::tools::cron::CronTool;
+use crate::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
+use crate::tools::knowledge::KnowledgeSearchTool;
+use crate::tools::location::LocationTool;
+use crate::tools::memory_search::MemorySearchTool;
+use crate::tools::message::MessageTool;
+use crate::tools::models::ModelsTool;
+use crate::tools::shell::ExecTool;
+use crate::tools::spawn::SpawnTool;
+use crate::tools::task::TaskTool;
+use crate::tools::transcribe::TranscribeTool;
+use crate::tools::translate::TranslateTool;
+use crate::tools::usage::UsageReportTool;
+use crate::tools::web::{WebCrawlTool, WebFetchTool, WebSearchTool};
+use crate::tools::{ArcToolWrapper, Tool, ToolRegistry};
+
+/// Builds an [`Agent`] from a [`Config`](patina_config::Config).
+pub struct AgentBuilder {
+    config: patina_config::Config,
+    workspace: PathBuf,
+    data_dir: PathBuf,
+    models: Option<ModelPool>,
+    tools: Vec<Box<dyn Tool>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl AgentBuilder {
+    /// Start from `config`, using its workspace and patina's data directory
+    /// (`~/.patina`).
+    pub fn new(config: patina_config::Config) -> Self {
+        let workspace = resolve_workspace(&config.agents.defaults.workspace);
+        Self {
+            config,
+            workspace,
+            data_dir: data_dir(),
+            models: None,
+            tools: Vec::new(),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Use a different workspace (memory, skills, and the file tools' root).
+    pub fn with_workspace(mut self, workspace: impl Into<PathBuf>) -> Self {
+        self.workspace = workspace.into();
+        self
+    }
+
+    /// Keep sessions, usage, cron jobs, tasks, and the memory index here
+    /// instead of `~/.patina`.
+    pub fn with_data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = data_dir.into();
+        self
+    }
+
+    /// Use these models instead of building them from `agents.models`,
+    /// e.g. a [`MockCompletionModel`](crate::agent::MockCompletionModel) in tests.
+    pub fn with_models(mut self, models: ModelPool) -> Self {
+        self.models = Some(models);
+        self
+    }
+
+    /// Register a tool alongside the built-in ones. A tool with the same
+    /// name as a built-in replaces it.
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    /// Add middleware around every completion and tool call.
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Create the workspace and wire everything up. Must be called inside
+    /// a Tokio runtime: the memory index catches up in a background task.
+    #[allow(deprecated)]
+    pub fn build(self) -> Result<Agent, PatinaError> {
+        let Self {
+            config,
+            workspace,
+            data_dir,
+            models,
+            tools: extra_tools,
+            mut middleware,
+        } = self;
+        let defaults = &config.agents.defaults;
+        std::fs::create_dir_all(&workspace).map_err(anyhow::Error::from)?;
+        let model_pool = match models {
+            Some(models) => models,
+            None => create_model_pool(&config)?,
+        };
+
+        // Message bus
+        let bus = MessageBus::new(128);
+
+        // Sessions directory
+        let sessions_dir = data_dir.join("sessions");
+        let sessions_dir_for_tasks = sessions_dir.clone();
+        let sessions = SessionManager::new(sessions_dir).with_config(&config.sessions);
+
+        // Memory in an Obsidian vault instead of the workspace
+        let vault = config
+            .obsidian
+            .enabled
+            .then(|| match Vault::open(&config.obsidian) {
+                Ok(vault) => {
+                    tracing::info!("Memory kept in {}", vault.memory_dir().display());
+                    Some(Arc::new(vault))
+                }
+                Err(e) => {
+                    tracing::warn!("Obsidian vault disabled: {e}");
+                    None
+                }
+            })
+            .flatten();
+
+        // Context builder (workspace + embedded builtin skills)
+        let mut context = ContextBuilder::new(&workspace)
+            .with_language(config.language.clone())
+            .with_template_config(&config)
+            .with_vault(vault.clone());
+        let default_locale = Locales::new(&config.user, &config.users).for_user(None);
+        context.set_user_locale(default_locale.clone());
+
+        // Tool registry
+        let mut tools = ToolRegistry::new();
+        let allowed_dir: Option<PathBuf> = if config.tools.restrict_to_workspace {
+            Some(workspace.clone())
+        } else {
+            None
+        };
+        tools.register(Box::new(ReadFileTool::new(allowed_dir.clone())));
+        tools.register(Box::new(WriteFileTool::new(allowed_dir.clone())));
+        tools.register(Box::new(EditFileTool::new(allowed_dir.clone())));
+        tools.register(Box::new(ListDirTool::new(allowed_dir.clone())));
+        tools.register(Box::new(
+            ExecTool::new(
+                workspace.clone(),
+                config.tools.exec.timeout_secs,
+                config.tools.restrict_to_workspace,
+            )
+            .with_shell(config.tools.exec.shell.clone()),
+        ));
+
+        // Web tools
+        let brave_api_key = if config.tools.web.search.api_key.is_empty() {
+            std::env::var("BRAVE_API_KEY").unwrap_or_default()
+        } else {
+            config.tools.web.search.api_key.clone()
+        };
+        tools.register(Box::new(WebSearchTool::new(
+            brave_api_key,
+            config.tools.web.search.max_results,
+        )));
+        tools.register(Box::new(WebFetchTool::new(50_000)));
+        tools.register(Box::new(WebCrawlTool::new(50_000)));
+
+        tools.register(Box::new(CalcTool::new()));
+
+        // Contacts directory (workspace-managed contacts.toml)
+        tools.register(Box::new(ContactsTool::new(workspace.join("contacts.toml"))));
+
+        // Usage tracker
+        let usage_db_path = data_dir.join("usage.sqlite");
+        let usage_tracker = Arc::new(crate::usage::UsageTracker::new(&usage_db_path)?);
+        let usage_tool = Arc::new(UsageReportTool::new(
+            usage_tracker.clone(),
+            config.agents.pricing.clone(),
+        ));
+        tools.register(Box::new(ArcToolWrapper(usage_tool.clone())));
+
+        // Transcription tool (backend loads lazily on first call)
+        tools.register(Box::new(
+            TranscribeTool::new(
+                config.transcription.clone(),
+                transcription_keys(&config),
+                workspace.clone(),
+                allowed_dir.clone(),
+                &config.tools.transcribe,
+            )
+            .with_usage_tracker(usage_tracker.clone()),
+        ));
+
+        // Message tool
+        let message_tool = Arc::new(MessageTool::new(bus.outbound_tx.clone()));
+        message_tool.set_drafts(
+            DraftStore::load(&data_dir.join("drafts.json")),
+            &config.tools.message,
+        );
+        tools.register(Box::new(ArcToolWrapper(message_tool.clone())));
+
+        // Questions for the owner from background work
+        let questions =
+            Questions::new(&config.tools.ask_user, bus.outbound_tx.clone()).map(Arc::new);
+        let ask_user_tool = questions.clone().map(|q| Arc::new(AskUserTool::new(q, "")));
+        if let Some(ref tool) = ask_user_tool {
+            tools.register(Box::new(ArcToolWrapper(tool.clone())));
+        }
+
+        if config.language.translate_tool {
+            tools.register(Box::new(
+                TranslateTool::new(model_pool.clone(), &config.language.translation_tier)
+                    .with_usage_tracker(usage_tracker.clone()),
+            ));
+        }
+
+        // Subagent manager + spawn tool
+        let mut subagent_manager = SubagentManager::new(
+            model_pool.clone(),
+            workspace.clone(),
+            bus.inbound_tx.clone(),
+            config.clone(),
+        );
+        subagent_manager.set_usage_tracker(usage_tracker.clone());
+        let session_log = session_logger(&config, &data_dir);
+        if let Some(ref logger) = session_log {
+            subagent_manager.set_session_log(logger.clone());
+        }
+        if let Some(ref questions) = questions {
+            subagent_manager.set_questions(questions.clone());
+        }
+        // The panic switch covers subagents too
+        let lockdown = Arc::new(Lockdown::new());
+        subagent_manager.add_middleware(lockdown.clone());
+        let subagent_manager = Arc::new(subagent_manager);
+        let subagent_manager_for_tasks = subagent_manager.clone();
+        let subagents = subagent_manager.clone();
+        let spawn_tool = Arc::new(SpawnTool::new(subagent_manager));
+        tools.register(Box::new(ArcToolWrapper(spawn_tool.clone())));
+
+        // Cron service + cron tool
+        let cron_store_path = data_dir.join("cron").join("jobs.json");
+        let cron_service = Arc::new(Mutex::new(CronService::new(
+            cron_store_path,
+            bus.inbound_tx.clone(),
+        )));
+        let cron_tool = Arc::new(CronTool::new(cron_service.clone()));
+        message_tool.set_scheduler(cron_service.clone());
+        tools.register(Box::new(ArcToolWrapper(cron_tool.clone())));
+
+        // Task manager + task tool
+        let task_store_path = data_dir.join("tasks.json");
+        let task_manager = Arc::new(Mutex::new(TaskManager::load(&task_store_path)));
+        let task_tool = Arc::new(TaskTool::new(task_manager.clone()));
+        task_tool.set_subagent_manager(subagent_manager_for_tasks);
+        task_tool.set_sessions_dir(sessions_dir_for_tasks);
+        tools.register(Box::new(ArcToolWrapper(task_tool.clone())));
+
+        // Memory search index
+        let db_path = data_dir.join("memory.sqlite");
+        let memory_index =
+            Arc::new(MemoryIndex::new(&workspace, &db_path)?.with_vault(vault.clone()));
+        // Searches use the existing index until the reindex catches up
+        let index = memory_index.clone();
+        tokio::task::spawn_blocking(move || {
+            let started = std::time::Instant::now();
+            match index.reindex() {
+                Ok(()) => tracing::info!("Memory reindexed in {:.2?}", started.elapsed()),
+                Err(e) => tracing::warn!("Initial memory reindex failed: {e}"),
+            }
+        });
+        tools.register(Box::new(
+            MemorySearchTool::new(memory_index.clone())
+                .with_project(context.current_project())
+                .with_projects_dir(context.projects_dir()),
+        ));
+
+        // Knowledge base, indexed in the background and kept fresh
+        if config.knowledge.enabled {
+            match knowledge_base(&config, &workspace, &data_dir, Some(usage_tracker.clone())) {
+                Ok(kb) => {
+                    let interval = (config.knowledge.refresh_mins > 0).then(|| {
+                        std::time::Duration::from_secs(config.knowledge.refresh_mins * 60)
+                    });
+                    tokio::spawn(knowledge::keep_fresh(kb.clone(), interval));
+                    tools.register(Box::new(KnowledgeSearchTool::new(kb)));
+                }
+                Err(e) => tracing::warn!("Knowledge base disabled: {e}"),
+            }
+        }
+
+        // Location pings, for the location tool and geofences
+        let locations = if config.location.enabled {
+            match LocationStore::open(&data_dir.join("location.sqlite"), &config.location) {
+                Ok(store) => {
+                    let store = Arc::new(store);
+                    tools.register(Box::new(LocationTool::new(store.clone())));
+                    Some(store)
+                }
+                Err(e) => {
+                    tracing::warn!("Location disabled: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Ollama model management
+        let ollama_models = ollama_tiers(&config);
+        if !ollama_models.is_empty() || config.providers.ollama.is_some() {
+            tools.register(Box::new(ModelsTool::new(
+                ollama_client(&config),
+                ollama_models,
+            )));
+        }
+
+        // The embedder's tools, last so they win name clashes
+        for tool in extra_tools {
+            tools.register(tool);
+        }
+
+        let context_tools = ContextTools {
+            message_tool,
+            spawn_tool,
+            cron_tool,
+            task_tool,
+            usage_tool,
+            ask_user_tool,
+            default_locale,
+        };
+
+        // Plans go first, so a plan still lists what lockdown would refuse
+        let dry_run = Arc::new(DryRun::new());
+        middleware.insert(0, lockdown);
+        middleware.insert(0, dry_run.clone());
+        if config.citations.enabled {
+            middleware.push(Arc::new(Citations::new(&config.citations)));
+        }
+
+        let agent_loop = AgentLoop {
+            models: model_pool,
+            sessions,
+            context,
+            tools,
+            max_iterations: defaults.max_tool_iterations as usize,
+            temperature: defaults.temperature as f64,
+            max_tokens: defaults.max_tokens as u64,
+            memory_window: defaults.memory_window,
+            model_overrides: ModelOverrides::defaults(),
+            memory_index: Some(memory_index),
+            channel_rules: HashMap::new(),
+            usage_tracker: Some(usage_tracker),
+            session_log,
+            stream_tx: None,
+            middleware,
+            memory_approvals: (!defaults.memory_approval_channels.is_empty()).then(|| {
+                Arc::new(
+                    MemoryApprovals::new(
+                        &data_dir.join("memory_approvals.json"),
+                        defaults.memory_approval_channels.clone(),
+                    )
+                    .with_review(
+                        config.tools.ask_user.owner_chat.as_deref(),
+                        bus.outbound_tx.clone(),
+                    )
+                    .with_vault(vault.clone()),
+                )
+            }),
+        };
+
+        let personas = Arc::new(Mutex::new(PersonaStore::load(
+            &data_dir.join("personas.json"),
+        )));
+
+        Ok(Agent {
+            agent_loop,
+            context_tools,
+            bus,
+            cron: cron_service,
+            tasks: task_manager,
+            personas,
+            questions,
+            subagents,
+            locations,
+            dry_run,
+            workspace,
+            config,
+        })
+    }
+}
+
+/// A wired-up agent. The fields are public so a host can take it apart:
+/// the CLI drives `agent_loop` itself, while [`Agent::serve`] is a minimal
+/// gateway for programs that bring their own channels.
+pub struct Agent {
+    pub agent_loop: AgentLoop,
+    /// Call [`ContextTools::set_context`] before each message you process
+    /// through `agent_loop` directly.
+    pub context_tools: ContextTools,
+    /// Channels publish to `bus.inbound_tx` and subscribe to `bus.outbound_tx`.
+    pub bus: MessageBus,
+    pub cron: Arc<Mutex<CronService>>,
+    pub tasks: Arc<Mutex<TaskManager>>,
+    pub personas: Arc<Mutex<PersonaStore>>,
+    /// Questions from `ask_user` waiting for the owner, when
+    /// `tools.askUser.ownerChat` is set. Hosts answer them with `/answer`.
+    pub questions: Option<Arc<Questions>>,
+    /// Background agents started with the `spawn` tool.
+    pub subagents: Arc<SubagentManager>,
+    /// Location history, when `location.enabled`. Hosts record pings with
+    /// [`LocationStore::record`] and publish the
+    /// [`crate::location::geofence_messages`]
+    /// of the transitions it returns.
+    pub locations: Option<Arc<LocationStore>>,
+    /// Sessions whose turn is a plan. [`DryRun::begin`] before a turn for
+    /// `/plan` or `--dry-run`, and [`DryRun::end`] after it.
+    pub dry_run: Arc<DryRun>,
+    pub workspace: PathBuf,
+    pub config: patina_config::Config,
+}
+
+impl Agent {
+    /// Start cron (with the configured standups), then answer inbound
+    /// messages from the bus one at a time, publishing each reply to the chat
+    /// it came from. Subscribe channels to
+    /// `bus.outbound_tx` before calling this. Runs until the task is
+    /// cancelled.
+    pub async fn serve(mut self) {
+        {
+            let mut cron = self.cron.lock().await;
+            cron.set_exec_context(
+                self.bus.outbound_tx.clone(),
+                self.workspace.clone(),
+                self.config.tools.exec.clone(),
+            );
+            if let Err(e) = cron.start().await {
+                tracing::warn!("Failed to start cron service: {e}");
+            }
+            standup::sync_jobs(&mut cron, &self.config.standups);
+        }
+
+        while let Some(msg) = self.bus.inbound_rx.recv().await {
+            if msg.channel == STANDUP_CHANNEL {
+                if let Some(config) = self.config.standups.get(&msg.chat_id) {
+                    self.context_tools
+                        .set_context(&config.channel, &config.to)
+                        .await;
+                    let reply = standup::run(
+                        &mut self.agent_loop,
+                        &msg.chat_id,
+                        config,
+                        &self.personas,
+                        &self.tasks,
+                    )
+                    .await;
+                    let _ = self.bus.outbound_tx.send(reply);
+                }
+                continue;
+            }
+            self.context_tools
+                .set_context(&msg.channel, &msg.chat_id)
+                .await;
+            let session_key = msg.session_key();
+            let locale = self.agent_loop.locale_for(&session_key);
+            self.context_tools.set_locale(&locale).await;
+            let media = (!msg.media.is_empty()).then_some(msg.media.as_slice());
+            let (content, needs_consolidation, succeeded) = match self
+                .agent_loop
+                .process_message(&session_key, &msg.content, media)
+                .await
+            {
+                Ok((reply, needs_consolidation)) => (reply, needs_consolidation, true),
+                Err(e) => {
+                    tracing::error!("Error processing message for {session_key}: {e}");
+                    (format!("Sorry, I encountered an error: {e}"), false, false)
+                }
+            };
+            let _ = self.bus.outbound_tx.send(OutboundMessage {
+                channel: msg.channel,
+                chat_id: msg.chat_id,
+                content,
+                reply_to: None,
+                metadata: reply_metadata(&msg.metadata, succeeded),
+            });
+            if needs_consolidation {
+                self.agent_loop
+                    .consolidate_memory(&session_key, false)
+                    .await;
+            }
+        }
+    }
+}
+
+/// Holds context-aware tools that need set_context() called before each message.
+pub struct ContextTools {
+    pub message_tool: Arc<MessageTool>,
+    pub spawn_tool: Arc<SpawnTool>,
+    pub cron_tool: Arc<CronTool>,
+    pub task_tool: Arc<TaskTool>,
+    pub usage_tool: Arc<UsageReportTool>,
+    pub ask_user_tool: Option<Arc<AskUserTool>>,
+    /// Timezone and locale from the `user` config, which `set_context`
+    /// resets the tools to.
+    pub default_locale: UserLocale,
+}
+
+impl ContextTools {
+    /// Update all context-aware tools with the current channel/chat_id, and
+    /// go back to the default timezone and locale.
+    pub async fn set_context(&self, channel: &str, chat_id: &str) {
+        self.message_tool.set_context(channel, chat_id).await;
+        self.spawn_tool.set_context(channel, chat_id).await;
+        self.cron_tool.set_context(channel, chat_id).await;
+        self.task_tool.set_context(channel, chat_id).await;
+        self.usage_tool.set_context(channel, chat_id).await;
+        if let Some(ref tool) = self.ask_user_tool {
+            tool.set_context(channel, chat_id).await;
+        }
+        self.set_locale(&self.default_locale).await;
+    }
+
+    /// Set the timezone and locale the cron and message tools read and show
+    /// times in, usually [`AgentLoop::locale_for`] the session. Call it after
+    /// `set_context`.
+    pub async fn set_locale(&self, locale: &UserLocale) {
+        self.message_tool.set_locale(locale).await;
+        self.cron_tool.set_locale(locale).await;
+    }
+}
+
+/// Resolve an API key from config, falling back to an environment variable.
+pub fn resolve_api_key(
+    provider_cfg: &Option<patina_config::ProviderConfig>,
+    env_var: &str,
+) -> Option<String> {
+    provider_cfg
+        .as_ref()
+        .and_then(|c| c.api_key.clone())
+        .filter(|k| !k.is_empty())
+        .or_else(|| std::env::var(env_var).ok().filter(|k| !k.is_empty()))
+}
+
+/// HTTP client for an LLM provider, honouring its `connectTimeoutSecs`.
+///
+/// The overall request timeout is enforced per call by `AgentLoop` rather
+/// than here, since a reqwest timeout would also cut off long streams.
+fn provider_http_client(provider: &str, config: &patina_config::Config) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(secs) = config
+        .providers
+        .get(provider)
+        .and_then(|c| c.connect_timeout_secs)
+    {
+        builder = builder.connect_timeout(std::time::Duration::from_secs(secs));
+    }
+    builder
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to create HTTP client for {provider}: {e}"))
+}
+
+/// API keys for the cloud transcription backends.
+pub fn transcription_keys(config: &patina_config::Config) -> patina_transcribe::CloudKeys {
+    patina_transcribe::CloudKeys {
+        groq: resolve_api_key(&config.providers.groq, "GROQ_API_KEY"),
+        openai: resolve_api_key(&config.providers.openai, "OPENAI_API_KEY"),
+        deepgram: resolve_api_key(&config.providers.deepgram, "DEEPGRAM_API_KEY"),
+    }
+}
+
+/// Create a completion model for a specific provider + model combination.
+///
+/// Errors clearly if provider is unknown or has no API key.
+#[allow(deprecated)]
+pub fn create_model_for(
+    provider: &str,
+    model_name: &str,
+    config: &patina_config::Config,
+) -> Result<CompletionModelHandle<'static>> {
+    if provider.is_empty() {
+        anyhow::bail!(
+            "No provider configured.\n\
+             Valid providers: anthropic, openai, ollama, openrouter, deepseek, groq, gemini, mistral, llamacpp"
+        );
+    }
+
+    if model_name.is_empty() {
+        anyhow::bail!("No model configured for provider '{provider}'.");
+    }
+
+    let http = provider_http_client(provider, config)?;
+
+    match provider {
+        "anthropic" => {
+            let key = resolve_api_key(&config.providers.anthropic, "ANTHROPIC_API_KEY")
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Anthropic provider selected but no API key found. \
+                     Set providers.anthropic.apiKey in config.json or ANTHROPIC_API_KEY env var."
+                    )
+                })?;
+            let client: anthropic::Client = anthropic::Client::builder()
+                .api_key(&key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create Anthropic client: {e}"))?;
+            let model = client.completion_model(model_name).with_prompt_caching();
+            tracing::info!(
+                "Using Anthropic provider with model '{model_name}' (prompt caching enabled)"
+            );
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "openai" => {
+            let key =
+                resolve_api_key(&config.providers.openai, "OPENAI_API_KEY").ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "OpenAI provider selected but no API key found. \
+                     Set providers.openai.apiKey in config.json or OPENAI_API_KEY env var."
+                    )
+                })?;
+            let mut builder = openai::CompletionsClient::builder()
+                .api_key(&key)
+                .http_client(http);
+            if let Some(ref base) = config
+                .providers
+                .openai
+                .as_ref()
+                .and_then(|c| c.api_base.clone())
+                .filter(|b| !b.is_empty())
+            {
+                builder = builder.base_url(base);
+                tracing::info!("Using OpenAI provider with custom base: {base}");
+            }
+            let client: openai::CompletionsClient = builder
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create OpenAI client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using OpenAI provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "ollama" => {
+            let mut builder = ollama::Client::builder().api_key(Nothing).http_client(http);
+            if let Some(ref base) = config
+                .providers
+                .ollama
+                .as_ref()
+                .and_then(|c| c.api_base.clone())
+                .filter(|b| !b.is_empty())
+            {
+                builder = builder.base_url(base);
+            }
+            let client: ollama::Client = builder
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create Ollama client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using Ollama provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "openrouter" => {
+            let key = resolve_api_key(&config.providers.openrouter, "OPENROUTER_API_KEY")
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "OpenRouter provider selected but no API key found. \
+                     Set providers.openrouter.apiKey in config.json or OPENROUTER_API_KEY env var."
+                    )
+                })?;
+            let client: openrouter::Client = openrouter::Client::builder()
+                .api_key(&key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create OpenRouter client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using OpenRouter provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "deepseek" => {
+            let key = resolve_api_key(&config.providers.deepseek, "DEEPSEEK_API_KEY").ok_or_else(
+                || {
+                    anyhow::anyhow!(
+                        "DeepSeek provider selected but no API key found. \
+                     Set providers.deepseek.apiKey in config.json or DEEPSEEK_API_KEY env var."
+                    )
+                },
+            )?;
+            let client: deepseek::Client = deepseek::Client::builder()
+                .api_key(&key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create DeepSeek client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using DeepSeek provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "groq" => {
+            let key = resolve_api_key(&config.providers.groq, "GROQ_API_KEY").ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Groq provider selected but no API key found. \
+                     Set providers.groq.apiKey in config.json or GROQ_API_KEY env var."
+                )
+            })?;
+            let client: groq::Client = groq::Client::builder()
+                .api_key(&key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create Groq client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using Groq provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "gemini" => {
+            let key =
+                resolve_api_key(&config.providers.gemini, "GEMINI_API_KEY").ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Gemini provider selected but no API key found. \
+                     Set providers.gemini.apiKey in config.json or GEMINI_API_KEY env var."
+                    )
+                })?;
+            let client: gemini::Client = gemini::Client::builder()
+                .api_key(key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create Gemini client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using Gemini provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        "mistral" => {
+            let key =
+                resolve_api_key(&config.providers.mistral, "MISTRAL_API_KEY").ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Mistral provider selected but no API key found. \
+                     Set providers.mistral.apiKey in config.json or MISTRAL_API_KEY env var."
+                    )
+                })?;
+            let client: mistral::Client = mistral::Client::builder()
+                .api_key(&key)
+                .http_client(http)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create Mistral client: {e}"))?;
+            let model = client.completion_model(model_name);
+            tracing::info!("Using Mistral provider with model '{model_name}'");
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        #[cfg(feature = "llama-cpp")]
+        "llamacpp" => {
+            let settings = config.providers.llamacpp.clone().unwrap_or_default();
+            let path =
+                crate::llama_cpp::resolve_model_path(model_name, settings.models_dir.as_deref());
+            let model = crate::llama_cpp::LlamaCppModel::new(path.clone(), &settings)
+                .map_err(|e| anyhow::anyhow!("Failed to start llama.cpp model: {e}"))?;
+            tracing::info!(
+                "Using in-process llama.cpp with model {} (context {}, {} GPU layers)",
+                path.display(),
+                settings.context_size,
+                settings.gpu_layers
+            );
+            Ok(CompletionModelHandle::new(Arc::new(model)))
+        }
+
+        #[cfg(not(feature = "llama-cpp"))]
+        "llamacpp" => {
+            anyhow::bail!(
+                "Provider 'llamacpp' needs a build with the llama-cpp feature \
+                 (cargo build --release --features llama-cpp)."
+            );
+        }
+
+        other => {
+            anyhow::bail!(
+                "Unknown provider '{other}'. \
+                 Valid providers: anthropic, openai, ollama, openrouter, deepseek, groq, gemini, mistral, llamacpp"
+            );
+        }
+    }
+}
+
+/// Build a ModelPool from config.
+///
+/// Uses `agents.models` if present, otherwise falls back to the legacy
+/// `agents.defaults.provider` + `agents.defaults.model` fields.
+#[allow(deprecated)]
+pub fn create_model_pool(config: &patina_config::Config) -> Result<ModelPool> {
+    let mut models = std::collections::HashMap::new();
+
+    if config.agents.models.is_empty() {
+        // Backwards compatibility: migrate legacy provider/model to "default" tier
+        let provider = &config.agents.defaults.provider;
+        let model_name = &config.agents.defaults.model;
+
+        if provider.is_empty() || model_name.is_empty() {
+            anyhow::bail!(
+                "No models configured. Set agents.models in config.json with at least a \"default\" entry.\n\
+                 Example:\n  \
+                 \"models\": {{ \"default\": {{ \"provider\": \"ollama\", \"model\": \"llama3\" }} }}"
+            );
+        }
+
+        tracing::warn!(
+            "Using legacy agents.defaults.provider/model config. \
+             Migrate to agents.models for multi-tier support."
+        );
+
+        let handle = create_model_for(provider, model_name, config)?;
+        models.insert(
+            "default".to_string(),
+            (handle, model_name.clone(), provider.clone()),
+        );
+    } else {
+        // Validate "default" tier exists
+        if !config.agents.models.contains_key("default") {
+            anyhow::bail!("config.agents.models must contain at least a \"default\" entry.");
+        }
+
+        // Build the tiers in parallel: each builds its own HTTP client, which
+        // loads the system TLS roots
+        let started = std::time::Instant::now();
+        let built: Vec<_> = std::thread::scope(|s| {
+            let pending: Vec<_> = config
+                .agents
+                .models
+                .iter()
+                .map(|(tier, model_ref)| {
+                    let handle = s.spawn(move || {
+                        create_model_for(&model_ref.provider, &model_ref.model, config)
+                    });
+                    (tier, model_ref, handle)
+                })
+                .collect();
+            pending
+                .into_iter()
+                .map(|(tier, model_ref, handle)| {
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("model setup panicked")));
+                    (tier, model_ref, result)
+                })
+                .collect()
+        });
+        tracing::info!(
+            "Created {} model tier(s) in {:.2?}",
+            built.len(),
+            started.elapsed()
+        );
+
+        for (tier, model_ref, handle) in built {
+            let handle = handle
+                .map_err(|e| anyhow::anyhow!("Failed to create model for tier '{tier}': {e}"))?;
+            tracing::info!(
+                "Model tier '{tier}': {} / {}",
+                model_ref.provider,
+                model_ref.model
+            );
+            models.insert(
+                tier.clone(),
+                (handle, model_ref.model.clone(), model_ref.provider.clone()),
+            );
+        }
+    }
+
+    let timeouts = models
+        .values()
+        .filter_map(|(_, _, provider)| {
+            let secs = config.providers.get(provider)?.timeout_secs?;
+            Some((provider.clone(), std::time::Duration::from_secs(secs)))
+        })
+        .collect();
+
+    let rate_limits = models
+        .values()
+        .filter_map(|(_, _, provider)| {
+            let limit = RateLimit::from_config(config.providers.get(provider)?)?;
+            Some((provider.clone(), limit))
+        })
+        .collect();
+
+    let mut request_params = HashMap::new();
+    if let Some(keep_alive) = config
+        .providers
+        .ollama
+        .as_ref()
+        .and_then(|c| c.keep_alive.as_deref())
+    {
+        request_params.insert(
+            "ollama".to_string(),
+            serde_json::json!({ "keep_alive": crate::ollama::keep_alive_value(keep_alive) }),
+        );
+    }
+
+    Ok(ModelPool::new(models)
+        .with_timeouts(timeouts)
+        .with_rate_limits(rate_limits)
+        .with_request_params(request_params))
+}
+
+/// Ollama models used by the configured tiers, as (tier, model), sorted by tier.
+pub fn ollama_tiers(config: &patina_config::Config) -> Vec<(String, String)> {
+    if config.agents.models.is_empty() {
+        let defaults = &config.agents.defaults;
+        if defaults.provider == "ollama" && !defaults.model.is_empty() {
+            return vec![("default".to_string(), defaults.model.clone())];
+        }
+        return Vec::new();
+    }
+    let mut tiers: Vec<(String, String)> = config
+        .agents
+        .models
+        .iter()
+        .filter(|(_, m)| m.provider == "ollama")
+        .map(|(tier, m)| (tier.clone(), m.model.clone()))
+        .collect();
+    tiers.sort();
+    tiers
+}
+
+pub fn ollama_client(config: &patina_config::Config) -> OllamaClient {
+    OllamaClient::new(
+        config
+            .providers
+            .ollama
+            .as_ref()
+            .and_then(|c| c.api_base.as_deref()),
+    )
+}
+
+/// Open the knowledge index at `knowledge.dbPath` (default
+/// `knowledge.sqlite` under `data_dir`) with the configured embedder, which
+/// records its requests in `usage_tracker`, and connectors.
+pub fn knowledge_base(
+    config: &patina_config::Config,
+    workspace: &Path,
+    data_dir: &Path,
+    usage_tracker: Option<Arc<crate::usage::UsageTracker>>,
+) -> Result<Arc<KnowledgeBase>> {
+    let cfg = &config.knowledge;
+    // Ollama needs no key; anything else falls back to the OpenAI one
+    let api_key = cfg
+        .embedding
+        .api_key
+        .clone()
+        .filter(|k| !k.is_empty())
+        .or_else(|| {
+            (cfg.embedding.provider != "ollama")
+                .then(|| resolve_api_key(&config.providers.openai, "OPENAI_API_KEY"))
+                .flatten()
+        });
+    let mut embedder = HttpEmbedder::from_config(config, api_key)?;
+    if let Some(tracker) = usage_tracker {
+        embedder = embedder.with_usage_tracker(tracker);
+    }
+    let db_path = match cfg.db_path {
+        Some(ref p) => resolve_workspace(p),
+        None => data_dir.join("knowledge.sqlite"),
+    };
+    Ok(Arc::new(
+        KnowledgeBase::open(cfg, workspace, &db_path, Arc::new(embedder))?
+            .with_connectors(connectors::from_config(cfg, workspace)),
+    ))
+}
+
+/// Per-session debug logger, when `logging.sessionLogs` is enabled. Logs go
+/// to `logging.dir`, or `logs/` under `data_dir`.
+pub fn session_logger(
+    config: &patina_config::Config,
+    data_dir: &Path,
+) -> Option<Arc<SessionLogger>> {
+    let logging = &config.logging;
+    if !logging.session_logs {
+        return None;
+    }
+    let dir = match logging.dir {
+        Some(ref dir) => resolve_workspace(dir),
+        None => data_dir.join("logs"),
+    };
+    tracing::info!("Writing session debug logs to {}", dir.display());
+    Some(Arc::new(SessionLogger::new(
+        &dir,
+        logging.max_file_mb.max(1) * 1024 * 1024,
+        logging.max_files,
+        Redactor::new(config.secrets()),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::replay::RecordedResponse;
+    use crate::agent::MockCompletionModel;
+
+    struct Shout;
+
+    #[async_trait::async_trait]
+    impl Tool for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+        fn description(&self) -> &str {
+            "Upper-case some text"
+        }
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({"type": "object", "properties": {"text": {"type": "string"}}})
+        }
+        async fn execute(&self, params: serde_json::Value) -> Result<String> {
+            Ok(params["text"].as_str().unwrap_or("").to_uppercase())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_registers_custom_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let model = MockCompletionModel::new([
+            RecordedResponse {
+                tool_calls: vec![("shout".into(), serde_json::json!({"text": "hi"}))],
+                ..Default::default()
+            },
+            RecordedResponse {
+                text: "Done.".into(),
+                ..Default::default()
+            },
+        ]);
+        let mut agent = AgentBuilder::new(patina_config::Config::default())
+            .with_workspace(dir.path().join("workspace"))
+            .with_data_dir(dir.path().join("data"))
+            .with_models(model.into_pool())
+            .with_tool(Shout)
+            .build()
+            .unwrap();
+
+        assert!(dir.path().join("workspace").is_dir());
+        assert!(agent.agent_loop.tools.get("shout").is_some());
+        assert!(agent.agent_loop.tools.get("read_file").is_some());
+        assert!(agent.agent_loop.tools.get("translate").is_some());
+
+        let (reply, _) = agent
+            .agent_loop
+            .process_message("embed:test", "shout hi", None)
+            .await
+            .unwrap();
+        assert_eq!(reply, "Done.");
+        assert!(dir.path().join("data/sessions").is_dir());
+    }
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::sync::{broadcast, mpsc};
 
+use crate::bus_journal::{BusJournal, JOURNAL_ID};
+
 /// Message received from a chat channel.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InboundMessage {
@@ -23,6 +26,42 @@ impl InboundMessage {
     pub fn session_key(&self) -> String {
         format!("{}:{}", self.channel, self.chat_id)
     }
+
+    /// Which lane the gateway handles this message in.
+    pub fn priority(&self) -> Priority {
+        match (self.channel.as_str(), self.sender_id.as_str()) {
+            (_, "heartbeat") => Priority::Heartbeat,
+            (_, "cron" | "location") | ("task", _) => Priority::Cron,
+            (_, "subagent") | ("system", _) => Priority::System,
+            _ => Priority::Interactive,
+        }
+    }
+}
+
+/// Inbound message lanes, lowest first. A burst of background traffic
+/// can't hold up a person waiting for a reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Subagent results and other system messages.
+    System,
+    Heartbeat,
+    /// Cron jobs, task runs, and geofence prompts.
+    Cron,
+    /// Someone on a chat channel.
+    Interactive,
+}
+
+/// Remove the message to handle next from `queue`: the oldest one in the
+/// highest lane.
+pub fn take_next(queue: &mut Vec<InboundMessage>) -> Option<InboundMessage> {
+    let mut best: Option<(usize, Priority)> = None;
+    for (i, msg) in queue.iter().enumerate() {
+        let priority = msg.priority();
+        if best.is_none_or(|(_, p)| priority > p) {
+            best = Some((i, priority));
+        }
+    }
+    best.map(|(i, _)| queue.remove(i))
 }
 
 /// Message to send to a chat channel.
@@ -36,6 +75,41 @@ pub struct OutboundMessage {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+/// What a chat channel can display, so replies can be adapted to it.
+///
+/// The default describes a plain-text channel with no length limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelCapabilities {
+    /// Renders markdown (converting it to its own format if need be).
+    /// Replies to channels without it have their markdown stripped.
+    pub supports_markdown: bool,
+    /// Can update a message after sending it, e.g. to stream a reply.
+    pub supports_edit: bool,
+    /// Can deliver files along with text.
+    pub supports_attachments: bool,
+    /// Longest message the channel accepts, in bytes. Longer replies are
+    /// split into several messages.
+    pub max_message_len: Option<usize>,
+}
+
+impl ChannelCapabilities {
+    /// Guidance for the system prompt so the model writes for the channel.
+    /// Empty when nothing needs saying.
+    pub fn prompt_rules(&self) -> String {
+        let mut rules = Vec::new();
+        if !self.supports_markdown {
+            rules.push("This channel shows plain text: don't use markdown formatting.".to_string());
+        }
+        if let Some(max) = self.max_message_len {
+            rules.push(format!(
+                "Messages over {max} characters arrive in several parts, so keep replies shorter when you can."
+            ));
+        }
+        rules.join(" ")
+    }
+}
+
 /// Async message bus connecting channels to the agent.
 pub struct MessageBus {
     pub inbound_tx: mpsc::Sender<InboundMessage>,
@@ -53,6 +127,37 @@ impl MessageBus {
             outbound_tx,
         }
     }
+
+    /// Record inbound messages in `journal` before they reach `inbound_rx`,
+    /// tagging each with its entry id (see [`crate::bus_journal::journal_id`]),
+    /// and queue the messages left unfinished by the last run ahead of new
+    /// ones. Existing `inbound_tx` clones keep working. Returns the number
+    /// of messages replayed.
+    pub fn journal_inbound(&mut self, journal: Arc<BusJournal>) -> anyhow::Result<usize> {
+        let replay = journal.unfinished_inbound()?;
+        let replayed = replay.len();
+        let (tx, rx) = mpsc::channel(self.inbound_tx.max_capacity());
+        let mut raw_rx = std::mem::replace(&mut self.inbound_rx, rx);
+        tokio::spawn(async move {
+            for msg in replay {
+                if tx.send(msg).await.is_err() {
+                    return;
+                }
+            }
+            while let Some(mut msg) = raw_rx.recv().await {
+                match journal.record_inbound(&msg) {
+                    Ok(id) => {
+                        msg.metadata.insert(JOURNAL_ID.into(), id.into());
+                    }
+                    Err(e) => tracing::warn!("Failed to journal inbound message: {e}"),
+                }
+                if tx.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(replayed)
+    }
 }
 
 #[cfg(test)]
@@ -87,6 +192,52 @@ mod tests {
         assert_eq!(msg.session_key(), "cli:interactive");
     }
 
+    #[test]
+    fn test_take_next_by_priority() {
+        let msg = |channel: &str, sender_id: &str, content: &str| InboundMessage {
+            channel: channel.into(),
+            sender_id: sender_id.into(),
+            chat_id: "c".into(),
+            content: content.into(),
+            media: Vec::new(),
+            metadata: HashMap::new(),
+            timestamp: default_timestamp(),
+        };
+        let mut queue = vec![
+            msg("system", "subagent", "result"),
+            msg("system", "heartbeat", "beat"),
+            msg("telegram", "cron", "job"),
+            msg("telegram", "42", "first"),
+            msg("system", "heartbeat", "beat 2"),
+            msg("web", "web:ab", "second"),
+        ];
+        let order: Vec<String> = std::iter::from_fn(|| take_next(&mut queue))
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(
+            order,
+            ["first", "second", "job", "beat", "beat 2", "result"]
+        );
+    }
+
+    #[test]
+    fn test_capability_prompt_rules() {
+        let plain = ChannelCapabilities::default();
+        assert!(plain.prompt_rules().contains("plain text"));
+        let rich = ChannelCapabilities {
+            supports_markdown: true,
+            supports_edit: true,
+            supports_attachments: true,
+            max_message_len: None,
+        };
+        assert!(rich.prompt_rules().is_empty());
+        let limited = ChannelCapabilities {
+            max_message_len: Some(4096),
+            ..rich
+        };
+        assert!(limited.prompt_rules().contains("4096"));
+    }
+
     #[tokio::test]
     async fn test_inbound_send_receive() {
         let mut bus = MessageBus::new(16);
@@ -128,6 +279,37 @@ mod tests {
         assert_eq!(r2.content, "response");
     }
 
+    #[tokio::test]
+    async fn test_journaled_inbound() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Arc::new(BusJournal::open(&dir.path().join("bus.db")).unwrap());
+        let mut bus = MessageBus::new(16);
+        assert_eq!(bus.journal_inbound(journal.clone()).unwrap(), 0);
+
+        let tx = bus.inbound_tx.clone();
+        tx.send(InboundMessage {
+            channel: "test".into(),
+            sender_id: "u1".into(),
+            chat_id: "c1".into(),
+            content: "hello".into(),
+            media: Vec::new(),
+            metadata: HashMap::new(),
+            timestamp: default_timestamp(),
+        })
+        .await
+        .unwrap();
+        let received = bus.inbound_rx.recv().await.unwrap();
+        let id = crate::bus_journal::journal_id(&received.metadata).unwrap();
+
+        // Not completed yet, so a restart would replay it
+        let mut restarted = MessageBus::new(16);
+        assert_eq!(restarted.journal_inbound(journal.clone()).unwrap(), 1);
+        let replayed = restarted.inbound_rx.recv().await.unwrap();
+        assert_eq!(replayed.content, "hello");
+        journal.complete(id);
+        assert!(journal.unfinished_inbound().unwrap().is_empty());
+    }
+
     #[test]
     fn test_inbound_message_serialization() {
         let msg = InboundMessage {
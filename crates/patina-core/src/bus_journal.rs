@@ -0,0 +1,222 @@
+//! SQLite journal of bus traffic, so a crash doesn't lose queued messages.
+//!
+//! Inbound messages are recorded before the gateway sees them and completed
+//! once their turn has finished; outbound messages are recorded before
+//! dispatch and completed once the channel has taken them. On startup,
+//! anything left unfinished is replayed. Each entry is replayed at most
+//! [`MAX_REPLAYS`] times, so a message that crashes the gateway can't do it
+//! forever.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::params;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::bus::{InboundMessage, OutboundMessage};
+use crate::db::{Db, Migration, Step};
+
+/// Metadata key carrying a message's journal entry id.
+pub const JOURNAL_ID: &str = "journal_id";
+
+/// Replays allowed per entry before it is given up on.
+pub const MAX_REPLAYS: i64 = 2;
+
+/// The journal entry id attached to a message, if any.
+pub fn journal_id(metadata: &HashMap<String, serde_json::Value>) -> Option<i64> {
+    metadata.get(JOURNAL_ID).and_then(|v| v.as_i64())
+}
+
+/// The journal schema.
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "journal",
+    up: Step::Sql(
+        "CREATE TABLE IF NOT EXISTS journal (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            direction TEXT NOT NULL,
+            payload TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            replays INTEGER NOT NULL DEFAULT 0,
+            done_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_journal_pending ON journal(direction, done_at);",
+    ),
+}];
+
+pub struct BusJournal {
+    db: Db,
+}
+
+impl BusJournal {
+    /// Open or create the journal database.
+    pub fn open(db_path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: Db::open(db_path, MIGRATIONS)?,
+        })
+    }
+
+    pub fn record_inbound(&self, msg: &InboundMessage) -> Result<i64> {
+        self.record("inbound", msg)
+    }
+
+    pub fn record_outbound(&self, msg: &OutboundMessage) -> Result<i64> {
+        self.record("outbound", msg)
+    }
+
+    fn record<T: Serialize>(&self, direction: &str, msg: &T) -> Result<i64> {
+        let conn = self.db.conn()?;
+        conn.execute(
+            "INSERT INTO journal (direction, payload, created_at) VALUES (?1, ?2, ?3)",
+            params![
+                direction,
+                serde_json::to_string(msg)?,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Mark an entry as handled. Failures are logged, not returned: the
+    /// worst case is a duplicate after the next restart.
+    pub fn complete(&self, id: i64) {
+        let result = self.db.conn().and_then(|conn| {
+            Ok(conn.execute(
+                "UPDATE journal SET done_at = ?1 WHERE id = ?2 AND done_at IS NULL",
+                params![chrono::Utc::now().to_rfc3339(), id],
+            )?)
+        });
+        if let Err(e) = result {
+            tracing::warn!("Failed to complete journal entry {id}: {e}");
+        }
+    }
+
+    /// Unfinished inbound messages from earlier runs, oldest first, with
+    /// their entry id in metadata. Counts as a replay of each.
+    pub fn unfinished_inbound(&self) -> Result<Vec<InboundMessage>> {
+        self.unfinished("inbound", |msg: &mut InboundMessage, id| {
+            msg.metadata.insert(JOURNAL_ID.into(), id.into());
+        })
+    }
+
+    /// Unfinished outbound messages from earlier runs, like
+    /// [`unfinished_inbound`](Self::unfinished_inbound).
+    pub fn unfinished_outbound(&self) -> Result<Vec<OutboundMessage>> {
+        self.unfinished("outbound", |msg: &mut OutboundMessage, id| {
+            msg.metadata.insert(JOURNAL_ID.into(), id.into());
+        })
+    }
+
+    fn unfinished<T: DeserializeOwned>(
+        &self,
+        direction: &str,
+        tag: impl Fn(&mut T, i64),
+    ) -> Result<Vec<T>> {
+        let rows: Vec<(i64, String)> = self.db.write(|tx| {
+            let now = chrono::Utc::now().to_rfc3339();
+            let abandoned = tx.execute(
+                "UPDATE journal SET done_at = ?1
+             WHERE direction = ?2 AND done_at IS NULL AND replays >= ?3",
+                params![now, direction, MAX_REPLAYS],
+            )?;
+            if abandoned > 0 {
+                tracing::warn!(
+                    "Gave up on {abandoned} {direction} message(s) after {MAX_REPLAYS} replays"
+                );
+            }
+            let rows = {
+                let mut stmt = tx.prepare(
+                    "SELECT id, payload FROM journal
+                 WHERE direction = ?1 AND done_at IS NULL ORDER BY id",
+                )?;
+                let rows =
+                    stmt.query_map(params![direction], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<rusqlite::Result<_>>()?
+            };
+            tx.execute(
+                "UPDATE journal SET replays = replays + 1 WHERE direction = ?1 AND done_at IS NULL",
+                params![direction],
+            )?;
+            Ok(rows)
+        })?;
+
+        let mut messages = Vec::new();
+        for (id, payload) in rows {
+            match serde_json::from_str::<T>(&payload) {
+                Ok(mut msg) => {
+                    tag(&mut msg, id);
+                    messages.push(msg);
+                }
+                Err(e) => {
+                    tracing::warn!("Dropping unreadable journal entry {id}: {e}");
+                    self.complete(id);
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Delete finished entries older than `retain`.
+    pub fn prune(&self, retain: chrono::Duration) -> Result<usize> {
+        let cutoff = (chrono::Utc::now() - retain).to_rfc3339();
+        Ok(self.db.conn()?.execute(
+            "DELETE FROM journal WHERE done_at IS NOT NULL AND done_at < ?1",
+            params![cutoff],
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::default_timestamp;
+
+    fn inbound(content: &str) -> InboundMessage {
+        InboundMessage {
+            channel: "telegram".into(),
+            sender_id: "42".into(),
+            chat_id: "42".into(),
+            content: content.into(),
+            media: Vec::new(),
+            metadata: HashMap::new(),
+            timestamp: default_timestamp(),
+        }
+    }
+
+    #[test]
+    fn test_replay_unfinished() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bus.db");
+        {
+            let journal = BusJournal::open(&path).unwrap();
+            let done = journal.record_inbound(&inbound("handled")).unwrap();
+            journal.record_inbound(&inbound("queued")).unwrap();
+            journal.complete(done);
+            journal
+                .record_outbound(&OutboundMessage {
+                    channel: "telegram".into(),
+                    chat_id: "42".into(),
+                    content: "reply".into(),
+                    reply_to: None,
+                    metadata: HashMap::new(),
+                })
+                .unwrap();
+        }
+
+        // "Restart": only the unfinished entries come back, tagged with ids
+        let journal = BusJournal::open(&path).unwrap();
+        let replayed = journal.unfinished_inbound().unwrap();
+        assert_eq!(replayed.len(), 1);
+        assert_eq!(replayed[0].content, "queued");
+        assert!(journal_id(&replayed[0].metadata).is_some());
+        let outbound = journal.unfinished_outbound().unwrap();
+        assert_eq!(outbound[0].content, "reply");
+
+        // Still unfinished after MAX_REPLAYS replays: given up on
+        assert_eq!(journal.unfinished_inbound().unwrap().len(), 1);
+        assert!(journal.unfinished_inbound().unwrap().is_empty());
+
+        assert_eq!(journal.prune(chrono::Duration::zero()).unwrap(), 2);
+    }
+}
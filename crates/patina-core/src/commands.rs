@@ -0,0 +1,1377 @@
+//! Slash commands handled before a message reaches the agent.
+//!
+//! Each command declares its name, description, and the channels it's offered
+//! on. Frontends keep one [`CommandRegistry`], dispatch incoming `/...` text
+//! through it, and use it to render `/help` and Telegram's command menu, so a
+//! new command shows up everywhere once it's registered.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use patina_config::RolePolicy;
+use tokio::sync::Mutex;
+
+use crate::agent::memory::{project_name, MemoryFact};
+use crate::agent::profile;
+use crate::agent::settings::{
+    session_project, SessionSettings, SettingsCommand, PERSONA_KEY, PROJECT_KEY,
+};
+use crate::agent::{AgentLoop, ConsolidationResult};
+use crate::dry_run::{plan_prompt, report, DryRun};
+use crate::lockdown::Lockdown;
+use crate::persona::{Persona, PersonaStore};
+use crate::questions::Questions;
+use crate::task::{Capture, TaskManager};
+use crate::tools::message::MessageTool;
+
+/// What a command gets to work with.
+pub struct CommandContext<'a> {
+    pub agent_loop: &'a mut AgentLoop,
+    /// The registry the command was found in (for `/help`).
+    pub registry: &'a CommandRegistry,
+    pub channel: &'a str,
+    pub session_key: &'a str,
+    /// Text after the command name, trimmed.
+    pub args: &'a str,
+    /// What the sender's role allows, when roles are on. `None` allows
+    /// everything.
+    pub policy: Option<&'a RolePolicy>,
+}
+
+/// A slash command. Replies are sent back to the chat it came from.
+#[async_trait]
+pub trait Command: Send + Sync {
+    /// Name without the leading slash. Telegram only accepts `[a-z0-9_]`.
+    fn name(&self) -> &str;
+    fn description(&self) -> &str;
+    /// Argument hint shown in `/help`, e.g. `<key> <value>`.
+    fn usage(&self) -> &str {
+        ""
+    }
+    /// Other names that run this command. Not listed in help or menus.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+    /// Channels the command is offered on. `None` means all of them.
+    fn channels(&self) -> Option<&[&str]> {
+        None
+    }
+    /// Buttons to show under the reply on channels that have them, as
+    /// `(label, input)` pairs. Pressing one sends its input as a message.
+    async fn choices(&self, _args: &str) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String>;
+}
+
+/// Registered commands, in the order they're listed in `/help`.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a command, replacing any existing one with the same name.
+    pub fn register(&mut self, command: Box<dyn Command>) {
+        match self
+            .commands
+            .iter_mut()
+            .find(|c| c.name() == command.name())
+        {
+            Some(existing) => *existing = command,
+            None => self.commands.push(command),
+        }
+    }
+
+    /// Commands offered on `channel`.
+    pub fn available<'a>(&'a self, channel: &'a str) -> impl Iterator<Item = &'a dyn Command> {
+        self.commands
+            .iter()
+            .map(|c| c.as_ref())
+            .filter(move |c| offered_on(*c, channel))
+    }
+
+    /// Look up the command `input` invokes on `channel`, returning it with its
+    /// arguments. Telegram's `/cmd@botname` form is accepted.
+    pub fn find<'a>(&'a self, channel: &str, input: &'a str) -> Option<(&'a dyn Command, &'a str)> {
+        let input = input.trim().strip_prefix('/')?;
+        let (word, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        let name = word.split_once('@').map_or(word, |(name, _)| name);
+        let command = self.commands.iter().map(|c| c.as_ref()).find(|c| {
+            offered_on(*c, channel) && (c.name() == name || c.aliases().contains(&name))
+        })?;
+        Some((command, args.trim()))
+    }
+
+    /// Run `input` if it's a command on `channel`. `None` means it isn't one and
+    /// should go to the agent.
+    pub async fn dispatch(
+        &self,
+        input: &str,
+        agent_loop: &mut AgentLoop,
+        channel: &str,
+        session_key: &str,
+        policy: Option<&RolePolicy>,
+    ) -> Option<Result<String>> {
+        let (command, args) = self.find(channel, input)?;
+        let ctx = CommandContext {
+            agent_loop,
+            registry: self,
+            channel,
+            session_key,
+            args,
+            policy,
+        };
+        Some(command.execute(ctx).await)
+    }
+
+    /// The buttons for the reply to `input` on `channel`, if it's a command
+    /// that offers any (see [`Command::choices`]).
+    pub async fn choices(&self, channel: &str, input: &str) -> Vec<(String, String)> {
+        match self.find(channel, input) {
+            Some((command, args)) => command.choices(args).await,
+            None => Vec::new(),
+        }
+    }
+
+    /// The command list for `/help` on `channel`, leaving out commands
+    /// `policy` doesn't allow.
+    pub fn help(&self, channel: &str, policy: Option<&RolePolicy>) -> String {
+        let mut lines = vec!["Commands:".to_string()];
+        for command in self
+            .available(channel)
+            .filter(|c| policy.is_none_or(|p| p.allows_command(c.name())))
+        {
+            let usage = match command.usage() {
+                "" => String::new(),
+                usage => format!(" {usage}"),
+            };
+            lines.push(format!(
+                "/{}{usage} - {}",
+                command.name(),
+                command.description()
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// `(name, description)` pairs for a channel's command menu.
+    pub fn menu(&self, channel: &str) -> Vec<(String, String)> {
+        self.available(channel)
+            .map(|c| (c.name().to_string(), c.description().to_string()))
+            .collect()
+    }
+}
+
+fn offered_on(command: &dyn Command, channel: &str) -> bool {
+    command
+        .channels()
+        .is_none_or(|allowed| allowed.contains(&channel))
+}
+
+/// `/new`: save the conversation to memory and start over, then tell the
+/// user what was archived and what went into long-term memory.
+pub struct NewSessionCommand;
+
+#[async_trait]
+impl Command for NewSessionCommand {
+    fn name(&self) -> &str {
+        "new"
+    }
+
+    fn description(&self) -> &str {
+        "Start a new conversation"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let agent_loop = ctx.agent_loop;
+        let session_key = ctx.session_key;
+        let session = match agent_loop.sessions.get_or_create_checked(session_key) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Failed to load session '{session_key}': {e}");
+                return Ok(format!(
+                    "I couldn't load your session state: {e}. \
+                     Try checking session file permissions."
+                ));
+            }
+        };
+        let had_messages = !session.messages.is_empty();
+        let pending = session.messages.len() > session.last_consolidated;
+        let result = if pending {
+            agent_loop.consolidate_memory(session_key, true).await
+        } else {
+            None
+        };
+        let session = match agent_loop.sessions.get_or_create_checked(session_key) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Failed to reload session '{session_key}' before clear: {e}");
+                return Ok(format!("I couldn't reset your session: {e}"));
+            }
+        };
+        session.clear();
+        if let Err(e) = agent_loop.sessions.save(session_key) {
+            tracing::warn!("Failed to save cleared session '{session_key}': {e}");
+        }
+        agent_loop.sessions.invalidate(session_key);
+        Ok(match (result, pending) {
+            (Some(result), _) => archive_summary(&result),
+            (None, true) => "New session started, but saving the previous conversation to \
+                             memory failed, so it won't be remembered."
+                .to_string(),
+            (None, false) if had_messages => {
+                "New session started. The previous conversation was already saved to memory."
+                    .to_string()
+            }
+            (None, false) => "New session started.".to_string(),
+        })
+    }
+}
+
+/// Most new memory facts listed in the `/new` reply.
+const MAX_LISTED_FACTS: usize = 10;
+
+/// The `/new` reply after a successful consolidation.
+fn archive_summary(result: &ConsolidationResult) -> String {
+    let mut reply = format!(
+        "New session started. I archived {} from the previous conversation.",
+        plural(result.messages, "message")
+    );
+    if let Some(entry) = &result.history_entry {
+        // Drop the "[YYYY-MM-DD HH:MM]" prefix meant for grep
+        let summary = match entry
+            .trim()
+            .strip_prefix('[')
+            .and_then(|e| e.split_once(']'))
+        {
+            Some((_, rest)) => rest.trim(),
+            None => entry.trim(),
+        };
+        if !summary.is_empty() {
+            reply.push_str(&format!("\n\nSummary: {summary}"));
+        }
+    }
+    if let Some(held) = &result.held {
+        reply.push_str(&format!(
+            "\n\nThe memory update is waiting for the owner's approval ({}).",
+            held.id
+        ));
+    } else if result.new_facts.is_empty() {
+        reply.push_str("\n\nNothing new went into long-term memory.");
+    } else {
+        reply.push_str("\n\nAdded to long-term memory:");
+        for fact in result.new_facts.iter().take(MAX_LISTED_FACTS) {
+            reply.push_str(&format!("\n- {fact}"));
+        }
+        if result.new_facts.len() > MAX_LISTED_FACTS {
+            reply.push_str(&format!(
+                "\n- ...and {} more",
+                result.new_facts.len() - MAX_LISTED_FACTS
+            ));
+        }
+    }
+    reply
+}
+
+/// `/help`: what the agent can do in this chat, from the running state: the
+/// persona and model answering, the commands on this channel, and the tools
+/// the sender may use.
+pub struct HelpCommand {
+    /// Shown above the command list.
+    intro: String,
+    /// Shown below it, for anything the frontend handles itself.
+    footer: String,
+    /// Resolves the chat's persona key to its name and model tier.
+    personas: Option<Arc<Mutex<PersonaStore>>>,
+}
+
+impl HelpCommand {
+    pub fn new(intro: impl Into<String>) -> Self {
+        Self {
+            intro: intro.into(),
+            footer: String::new(),
+            personas: None,
+        }
+    }
+
+    pub fn with_footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = footer.into();
+        self
+    }
+
+    pub fn with_personas(mut self, personas: Arc<Mutex<PersonaStore>>) -> Self {
+        self.personas = Some(personas);
+        self
+    }
+
+    async fn render(&self, ctx: CommandContext<'_>) -> String {
+        let agent_loop = ctx.agent_loop;
+        let (persona_key, settings) =
+            match agent_loop.sessions.get_or_create_checked(ctx.session_key) {
+                Ok(session) => (
+                    session
+                        .metadata
+                        .get(PERSONA_KEY)
+                        .and_then(|v| v.as_str())
+                        .filter(|k| !k.is_empty())
+                        .map(str::to_string),
+                    SessionSettings::from_metadata(&session.metadata),
+                ),
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to load session '{}' for /help: {e}",
+                        ctx.session_key
+                    );
+                    (None, SessionSettings::default())
+                }
+            };
+        // Same precedence as the agent loop: /set model, then the persona's tier
+        let mut persona = persona_key.clone();
+        let mut persona_tier = None;
+        if let (Some(key), Some(store)) = (&persona_key, &self.personas) {
+            if let Some(p) = store.lock().await.get(key) {
+                persona = Some(p.name.clone());
+                persona_tier = Some(p.model_tier.clone()).filter(|t| !t.is_empty());
+            }
+        }
+        let tier = settings
+            .model
+            .or(persona_tier)
+            .unwrap_or_else(|| "default".to_string());
+        let (_, model, _) = agent_loop.models.get(&tier);
+        let mut tools: Vec<&str> = agent_loop.tools.list().iter().map(|t| t.name()).collect();
+        tools.sort_unstable();
+
+        [
+            self.intro.clone(),
+            help_status(persona.as_deref(), &tier, model),
+            ctx.registry.help(ctx.channel, ctx.policy),
+            help_tools(&tools, ctx.policy),
+            self.footer.clone(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+    }
+}
+
+/// Who's answering in `/help`.
+fn help_status(persona: Option<&str>, tier: &str, model: &str) -> String {
+    let model = format!("Model: {tier} ({model})");
+    match persona {
+        Some(persona) => format!("Persona: {persona}\n{model}"),
+        None => model,
+    }
+}
+
+/// The tools the sender may use in `/help`, and the ones their role can't.
+fn help_tools(tools: &[&str], policy: Option<&RolePolicy>) -> String {
+    if tools.is_empty() {
+        return String::new();
+    }
+    let (allowed, denied): (Vec<&str>, Vec<&str>) = tools
+        .iter()
+        .partition(|t| policy.is_none_or(|p| p.allows_tool(t)));
+    let mut out = match allowed.is_empty() {
+        true => "Tools: none".to_string(),
+        false => format!("Tools: {}", allowed.join(", ")),
+    };
+    if !denied.is_empty() {
+        out.push_str(&format!("\nNot available to you: {}", denied.join(", ")));
+    }
+    out
+}
+
+#[async_trait]
+impl Command for HelpCommand {
+    fn name(&self) -> &str {
+        "help"
+    }
+
+    fn description(&self) -> &str {
+        "Show what I can do here"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        Ok(self.render(ctx).await)
+    }
+}
+
+/// `/start`: Telegram's greeting when a chat is opened. Same reply as `/help`.
+pub struct StartCommand(pub HelpCommand);
+
+#[async_trait]
+impl Command for StartCommand {
+    fn name(&self) -> &str {
+        "start"
+    }
+
+    fn description(&self) -> &str {
+        "Start the bot"
+    }
+
+    fn channels(&self) -> Option<&[&str]> {
+        Some(&["telegram"])
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        Ok(self.0.render(ctx).await)
+    }
+}
+
+/// `/stop`: stop the reply being written in this chat. Hosts that run
+/// turns in the background intercept it while a turn is running (see
+/// [`AgentLoop::request_interrupt`]); it only reaches the registry when
+/// nothing is, so it clears any stale stop request.
+pub struct StopCommand;
+
+#[async_trait]
+impl Command for StopCommand {
+    fn name(&self) -> &str {
+        "stop"
+    }
+
+    fn description(&self) -> &str {
+        "Stop the current reply"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        AgentLoop::consume_interrupt(ctx.session_key);
+        Ok("Nothing to stop.".to_string())
+    }
+}
+
+/// `/set <key> <value>`: change a per-session setting.
+pub struct SetCommand {
+    personas: Arc<Mutex<PersonaStore>>,
+}
+
+impl SetCommand {
+    pub fn new(personas: Arc<Mutex<PersonaStore>>) -> Self {
+        Self { personas }
+    }
+}
+
+#[async_trait]
+impl Command for SetCommand {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn description(&self) -> &str {
+        "Change settings for this chat"
+    }
+
+    fn usage(&self) -> &str {
+        "temperature|model|persona|prompt|language|timezone|locale <value>"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let command = SettingsCommand::from_parts("/set", ctx.args);
+        apply_settings(command, ctx, &self.personas).await
+    }
+}
+
+/// `/show settings`: list this session's settings.
+pub struct ShowCommand {
+    personas: Arc<Mutex<PersonaStore>>,
+}
+
+impl ShowCommand {
+    pub fn new(personas: Arc<Mutex<PersonaStore>>) -> Self {
+        Self { personas }
+    }
+}
+
+#[async_trait]
+impl Command for ShowCommand {
+    fn name(&self) -> &str {
+        "show"
+    }
+
+    fn description(&self) -> &str {
+        "Show this chat's settings"
+    }
+
+    fn usage(&self) -> &str {
+        "settings"
+    }
+
+    fn aliases(&self) -> &[&str] {
+        &["settings"]
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let command = SettingsCommand::from_parts("/show", ctx.args);
+        apply_settings(command, ctx, &self.personas).await
+    }
+}
+
+/// `/persona [name]`: pick the persona answering in this chat. Without a
+/// name it lists the personas, offering them as buttons where it can.
+pub struct PersonaCommand {
+    personas: Arc<Mutex<PersonaStore>>,
+}
+
+impl PersonaCommand {
+    pub fn new(personas: Arc<Mutex<PersonaStore>>) -> Self {
+        Self { personas }
+    }
+}
+
+#[async_trait]
+impl Command for PersonaCommand {
+    fn name(&self) -> &str {
+        "persona"
+    }
+
+    fn description(&self) -> &str {
+        "Pick who answers in this chat"
+    }
+
+    fn usage(&self) -> &str {
+        "[name|none]"
+    }
+
+    async fn choices(&self, args: &str) -> Vec<(String, String)> {
+        if !args.is_empty() {
+            return Vec::new();
+        }
+        let store = self.personas.lock().await;
+        let mut choices: Vec<(String, String)> = sorted_personas(&store)
+            .into_iter()
+            .map(|(key, persona)| (persona.name.clone(), format!("/persona {key}")))
+            .collect();
+        if !choices.is_empty() {
+            choices.push(("No persona".to_string(), "/persona none".to_string()));
+        }
+        choices
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        if !ctx.args.is_empty() {
+            let command = SettingsCommand::from_parts("/set", &format!("persona {}", ctx.args));
+            return apply_settings(command, ctx, &self.personas).await;
+        }
+        let current = match ctx
+            .agent_loop
+            .sessions
+            .get_or_create_checked(ctx.session_key)
+        {
+            Ok(session) => session
+                .metadata
+                .get(PERSONA_KEY)
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            Err(e) => return Ok(format!("I couldn't load your session state: {e}")),
+        };
+        Ok(persona_list(
+            &*self.personas.lock().await,
+            current.as_deref(),
+        ))
+    }
+}
+
+fn sorted_personas(store: &PersonaStore) -> Vec<(&String, &Persona)> {
+    let mut personas: Vec<_> = store.list().iter().collect();
+    personas.sort_by(|a, b| a.0.cmp(b.0));
+    personas
+}
+
+/// The `/persona` reply: the personas to pick from, marking `current`.
+fn persona_list(store: &PersonaStore, current: Option<&str>) -> String {
+    let personas = sorted_personas(store);
+    if personas.is_empty() {
+        return "No personas are defined. Add them in the web UI.".to_string();
+    }
+    let mut reply = "Personas:".to_string();
+    for (key, persona) in personas {
+        reply.push_str(&format!("\n- {} ({key})", persona.name));
+        if !persona.description.is_empty() {
+            reply.push_str(&format!(": {}", persona.description));
+        }
+        if current == Some(key.as_str()) {
+            reply.push_str(" [current]");
+        }
+    }
+    if current.is_none() {
+        reply.push_str("\n\nNo persona is set.");
+    }
+    reply.push_str("\n\nSend /persona <name> to switch, or /persona none for no persona.");
+    reply
+}
+
+/// `/context`: what the next prompt for this chat is made of, with token
+/// estimates for each part.
+pub struct ContextCommand {
+    personas: Arc<Mutex<PersonaStore>>,
+}
+
+impl ContextCommand {
+    pub fn new(personas: Arc<Mutex<PersonaStore>>) -> Self {
+        Self { personas }
+    }
+}
+
+#[async_trait]
+impl Command for ContextCommand {
+    fn name(&self) -> &str {
+        "context"
+    }
+
+    fn description(&self) -> &str {
+        "Show what the prompt is made of, in tokens"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let personas = self.personas.lock().await;
+        Ok(
+            match profile::profile(ctx.agent_loop, ctx.session_key, &personas) {
+                Ok(report) => profile::render_report(&report),
+                Err(e) => format!("I couldn't load your session state: {e}"),
+            },
+        )
+    }
+}
+
+/// `/task <title>`: add a task to the board without asking the agent. Lines
+/// after the first become the description.
+pub struct TaskCommand {
+    tasks: Arc<Mutex<TaskManager>>,
+}
+
+impl TaskCommand {
+    pub fn new(tasks: Arc<Mutex<TaskManager>>) -> Self {
+        Self { tasks }
+    }
+}
+
+#[async_trait]
+impl Command for TaskCommand {
+    fn name(&self) -> &str {
+        "task"
+    }
+
+    fn description(&self) -> &str {
+        "Add a task to the board"
+    }
+
+    fn usage(&self) -> &str {
+        "<title>"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let Some(capture) = Capture::from_text(ctx.args) else {
+            return Ok(
+                "Usage: /task <title>\nLines after the first become the description.".into(),
+            );
+        };
+        let task = self.tasks.lock().await.capture(&capture, ctx.session_key)?;
+        Ok(format!("Added task '{}' (ID: {})", task.title, task.id))
+    }
+}
+
+/// `/draft`: review messages the agent wrote to other chats (see
+/// [`crate::drafts`]). Lists them, or sends, edits, or rejects one.
+pub struct DraftCommand {
+    messages: Arc<MessageTool>,
+}
+
+/// Longest draft preview in the `/draft` list.
+const DRAFT_PREVIEW_CHARS: usize = 80;
+
+impl DraftCommand {
+    pub fn new(messages: Arc<MessageTool>) -> Self {
+        Self { messages }
+    }
+
+    async fn list(&self) -> String {
+        let drafts = self.messages.drafts().await;
+        if drafts.is_empty() {
+            return "No drafts waiting.".to_string();
+        }
+        let mut out = format!("{} waiting:", plural(drafts.len(), "draft"));
+        for draft in &drafts {
+            let line = draft.content.lines().next().unwrap_or_default();
+            let mut preview: String = line.chars().take(DRAFT_PREVIEW_CHARS).collect();
+            if preview.len() < draft.content.len() {
+                preview.push_str("...");
+            }
+            out.push_str(&format!(
+                "\n[{}] to {}:{}: {preview}",
+                draft.id, draft.channel, draft.chat_id
+            ));
+        }
+        out.push_str("\n\nSend /draft send|edit|reject <id>.");
+        out
+    }
+}
+
+#[async_trait]
+impl Command for DraftCommand {
+    fn name(&self) -> &str {
+        "draft"
+    }
+
+    fn description(&self) -> &str {
+        "Review messages waiting to be sent"
+    }
+
+    fn usage(&self) -> &str {
+        "[send|edit|reject <id>]"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        const USAGE: &str = "Usage: /draft, /draft send <id>, /draft edit <id> <text>, \
+                             or /draft reject <id>";
+        let (action, rest) = ctx
+            .args
+            .split_once(char::is_whitespace)
+            .unwrap_or((ctx.args, ""));
+        let (id, text) = rest
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((rest.trim(), ""));
+        if !action.is_empty() && id.is_empty() {
+            return Ok(USAGE.to_string());
+        }
+        let not_found = || format!("No draft {id}.");
+        Ok(match action {
+            "" => self.list().await,
+            "send" => self
+                .messages
+                .approve_draft(id, None)
+                .await?
+                .unwrap_or_else(not_found),
+            "edit" if !text.trim().is_empty() => self
+                .messages
+                .approve_draft(id, Some(text.trim()))
+                .await?
+                .unwrap_or_else(not_found),
+            "reject" => match self.messages.reject_draft(id).await? {
+                Some(draft) => format!(
+                    "Rejected draft {id}. Nothing was sent to {}:{}.",
+                    draft.channel, draft.chat_id
+                ),
+                None => not_found(),
+            },
+            _ => USAGE.to_string(),
+        })
+    }
+}
+
+/// `/answer [id] <answer>`: answer a question from `ask_user`. The id can
+/// be left out when only one question is waiting.
+pub struct AnswerCommand {
+    questions: Arc<Questions>,
+}
+
+impl AnswerCommand {
+    pub fn new(questions: Arc<Questions>) -> Self {
+        Self { questions }
+    }
+}
+
+#[async_trait]
+impl Command for AnswerCommand {
+    fn name(&self) -> &str {
+        "answer"
+    }
+
+    fn description(&self) -> &str {
+        "Answer a question from background work"
+    }
+
+    fn usage(&self) -> &str {
+        "[id] <answer>"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        Ok(self.questions.answer(ctx.args))
+    }
+}
+
+/// `/lockdown [on|off|status]`: the panic switch. Turns off exec, file
+/// writes, messages, and spawning everywhere until `/lockdown off`.
+#[derive(Default)]
+pub struct LockdownCommand {
+    lockdown: Lockdown,
+}
+
+impl LockdownCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Command for LockdownCommand {
+    fn name(&self) -> &str {
+        "lockdown"
+    }
+
+    fn description(&self) -> &str {
+        "Turn off exec, writes, messages, and spawning everywhere"
+    }
+
+    fn usage(&self) -> &str {
+        "[on|off|status]"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        Ok(self.lockdown.command(ctx.args, ctx.session_key))
+    }
+}
+
+/// `/plan <request>`: have the agent work out what it would do without
+/// doing it (see [`crate::dry_run`]). The gateway runs plans as ordinary
+/// turns so personas and roles apply; this runs them elsewhere.
+pub struct PlanCommand {
+    dry_run: Arc<DryRun>,
+}
+
+impl PlanCommand {
+    pub fn new(dry_run: Arc<DryRun>) -> Self {
+        Self { dry_run }
+    }
+}
+
+#[async_trait]
+impl Command for PlanCommand {
+    fn name(&self) -> &str {
+        "plan"
+    }
+
+    fn description(&self) -> &str {
+        "Show what the agent would do, without doing it"
+    }
+
+    fn usage(&self) -> &str {
+        "<request>"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        if ctx.args.is_empty() {
+            return Ok(
+                "Usage: /plan <request>\nCommands, file changes, messages, and \
+                       spawning are described instead of run."
+                    .into(),
+            );
+        }
+        self.dry_run.begin(ctx.session_key);
+        let result = ctx
+            .agent_loop
+            .process_message(ctx.session_key, &plan_prompt(ctx.args), None)
+            .await;
+        let planned = self.dry_run.end(ctx.session_key);
+        let (reply, _) = result?;
+        Ok(report(&reply, &planned))
+    }
+}
+
+/// `/project [name|off]`: attach the session to a project, whose memory is
+/// kept under `memory/projects/<name>/` apart from the shared MEMORY.md.
+pub struct ProjectCommand;
+
+#[async_trait]
+impl Command for ProjectCommand {
+    fn name(&self) -> &str {
+        "project"
+    }
+
+    fn description(&self) -> &str {
+        "Keep this chat's memory in a project"
+    }
+
+    fn usage(&self) -> &str {
+        "[name|off]"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let agent_loop = ctx.agent_loop;
+        let session_key = ctx.session_key;
+        let (current, pending) = match agent_loop.sessions.get_or_create_checked(session_key) {
+            Ok(s) => (
+                session_project(&s.metadata),
+                s.messages.len() > s.last_consolidated,
+            ),
+            Err(e) => return Ok(format!("I couldn't load your session state: {e}")),
+        };
+        let next = match ctx.args {
+            "" => {
+                let mut reply = match &current {
+                    Some(p) => format!("This chat is attached to project {p}."),
+                    None => "This chat isn't attached to a project.".to_string(),
+                };
+                let projects = agent_loop.context.projects();
+                if !projects.is_empty() {
+                    reply.push_str(&format!("\nProjects: {}", projects.join(", ")));
+                }
+                reply.push_str("\n\nSend /project <name> to attach it, or /project off.");
+                return Ok(reply);
+            }
+            "off" | "none" => None,
+            name => match project_name(name) {
+                Some(name) => Some(name),
+                None => {
+                    return Ok("Project names are 1-40 letters, digits, - or _.".to_string());
+                }
+            },
+        };
+        if next == current {
+            return Ok(match next {
+                Some(p) => format!("This chat is already attached to project {p}."),
+                None => "This chat isn't attached to a project.".to_string(),
+            });
+        }
+
+        // What was said so far belongs in the memory it was said under
+        let saved = if pending {
+            agent_loop.consolidate_memory(session_key, true).await
+        } else {
+            None
+        };
+        let session = agent_loop.sessions.get_or_create_checked(session_key)?;
+        match &next {
+            Some(p) => session
+                .metadata
+                .insert(PROJECT_KEY.to_string(), serde_json::Value::from(p.as_str())),
+            None => session.metadata.remove(PROJECT_KEY),
+        };
+        agent_loop.sessions.save(session_key)?;
+
+        let mut reply = match &next {
+            Some(p) => format!(
+                "This chat is now attached to project {p}. What's remembered from here on goes \
+                 to {}/{p}/, and memory search looks there first.",
+                agent_loop.context.projects_dir()
+            ),
+            None => "This chat is no longer attached to a project. What's remembered from \
+                     here on goes to the shared memory."
+                .to_string(),
+        };
+        let before = current.map_or_else(|| "the shared".to_string(), |p| format!("{p}'s"));
+        if saved.is_some() {
+            reply.push_str(&format!(
+                " The conversation so far was saved to {before} memory first."
+            ));
+        } else if pending {
+            reply.push_str(&format!(
+                " Saving the conversation so far to {before} memory failed, so it will go \
+                 to the new one."
+            ));
+        }
+        Ok(reply)
+    }
+}
+
+/// `/memory [approve|reject <id>]`: review memory updates from
+/// `agents.defaults.memoryApprovalChannels` that wait for approval.
+pub struct MemoryCommand;
+
+#[async_trait]
+impl Command for MemoryCommand {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn description(&self) -> &str {
+        "Review memory updates waiting for approval"
+    }
+
+    fn usage(&self) -> &str {
+        "[approve|reject <id>]"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        const USAGE: &str = "Usage: /memory, /memory approve <id> [anyway], or /memory reject <id>";
+        let Some(approvals) = ctx.agent_loop.memory_approvals.clone() else {
+            return Ok("Memory updates don't need approval here. List channels in \
+                       agents.defaults.memoryApprovalChannels to review them first."
+                .to_string());
+        };
+        let mut args = ctx.args.split_whitespace();
+        let (action, id, extra) = (args.next(), args.next(), args.next());
+        let not_found = || format!("No memory update {}.", id.unwrap_or_default());
+        Ok(match (action, id, extra) {
+            (None, _, _) => {
+                let pending = approvals.list();
+                if pending.is_empty() {
+                    return Ok("No memory updates waiting.".to_string());
+                }
+                let mut out = format!("{} waiting:", plural(pending.len(), "memory update"));
+                for update in &pending {
+                    out.push_str(&format!(
+                        "\n\n[{}] from {} ({}):\n{}",
+                        update.id,
+                        update.session_key,
+                        plural(update.messages, "message"),
+                        update.preview(MEMORY_PREVIEW_LINES)
+                    ));
+                }
+                out.push_str("\n\nSend /memory approve|reject <id>.");
+                out
+            }
+            (Some("approve"), Some(id), None | Some("anyway")) => {
+                match approvals.approve(id, extra.is_some())? {
+                    Some(reply) => {
+                        if let Some(index) = &ctx.agent_loop.memory_index {
+                            if let Err(e) = index.reindex() {
+                                tracing::warn!("Memory reindex after /memory approve failed: {e}");
+                            }
+                        }
+                        reply
+                    }
+                    None => not_found(),
+                }
+            }
+            (Some("reject"), Some(id), None) => match approvals.reject(id)? {
+                Some(update) => format!(
+                    "Rejected memory update {id}. Nothing from those {} in {} goes into memory.",
+                    plural(update.messages, "message"),
+                    update.session_key
+                ),
+                None => not_found(),
+            },
+            _ => USAGE.to_string(),
+        })
+    }
+}
+
+/// Most MEMORY.md diff lines per update in the `/memory` list.
+const MEMORY_PREVIEW_LINES: usize = 10;
+
+/// `/forget <topic>`: remove matching facts from MEMORY.md and entries from
+/// HISTORY.md. Shows what would go first and waits for `/forget confirm`.
+#[derive(Default)]
+pub struct ForgetCommand {
+    /// Pending deletions by session key.
+    pending: Mutex<HashMap<String, PendingForget>>,
+}
+
+struct PendingForget {
+    topic: String,
+    facts: Vec<MemoryFact>,
+    history: Vec<String>,
+}
+
+/// Longest history entry preview in the confirmation prompt.
+const FORGET_PREVIEW_CHARS: usize = 100;
+
+impl ForgetCommand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn preview(pending: &PendingForget) -> String {
+        let mut out = format!(
+            "This will forget {} from memory and {} from history about '{}':",
+            plural(pending.facts.len(), "fact"),
+            plural(pending.history.len(), "entry"),
+            pending.topic
+        );
+        for fact in &pending.facts {
+            out.push_str(&format!("\n- {}", fact.text));
+        }
+        for entry in &pending.history {
+            let line = entry.lines().next().unwrap_or_default();
+            let mut preview: String = line.chars().take(FORGET_PREVIEW_CHARS).collect();
+            if preview.len() < entry.len() {
+                preview.push_str("...");
+            }
+            out.push_str(&format!("\n- {preview}"));
+        }
+        out.push_str("\n\nSend /forget confirm to delete, or /forget cancel to keep it.");
+        out
+    }
+}
+
+#[async_trait]
+impl Command for ForgetCommand {
+    fn name(&self) -> &str {
+        "forget"
+    }
+
+    fn description(&self) -> &str {
+        "Remove a topic from long-term memory"
+    }
+
+    fn usage(&self) -> &str {
+        "<topic>"
+    }
+
+    async fn execute(&self, ctx: CommandContext<'_>) -> Result<String> {
+        let mut pending = self.pending.lock().await;
+        match ctx.args.to_lowercase().as_str() {
+            "" => Ok(match pending.get(ctx.session_key) {
+                Some(p) => Self::preview(p),
+                None => "Usage: /forget <topic>".to_string(),
+            }),
+            "cancel" => Ok(match pending.remove(ctx.session_key) {
+                Some(p) => format!("Kept everything about '{}'.", p.topic),
+                None => "Nothing to cancel.".to_string(),
+            }),
+            "confirm" => {
+                let Some(p) = pending.remove(ctx.session_key) else {
+                    return Ok("Nothing to confirm. Start with /forget <topic>.".to_string());
+                };
+                let memory = ctx.agent_loop.context.memory();
+                // Bottom-up so earlier line numbers stay valid
+                let mut forgotten = 0;
+                for fact in p.facts.iter().rev() {
+                    if memory.delete_fact(fact.line, &fact.text)? {
+                        forgotten += 1;
+                    }
+                }
+                let removed = memory.remove_history(&p.history)?;
+                if let Some(index) = &ctx.agent_loop.memory_index {
+                    if let Err(e) = index.reindex() {
+                        tracing::warn!("Memory reindex after /forget failed: {e}");
+                    }
+                }
+                let mut reply = format!(
+                    "Forgot {} and {} about '{}'.",
+                    plural(forgotten, "fact"),
+                    plural(removed, "history entry"),
+                    p.topic
+                );
+                if forgotten < p.facts.len() {
+                    reply.push_str(
+                        " Memory changed in the meantime, so some facts were left; run /forget again to review them.",
+                    );
+                }
+                Ok(reply)
+            }
+            _ => {
+                let memory = ctx.agent_loop.context.memory();
+                let request = PendingForget {
+                    topic: ctx.args.to_string(),
+                    facts: memory.facts_matching(ctx.args)?,
+                    history: memory.history_matching(ctx.args)?,
+                };
+                if request.facts.is_empty() && request.history.is_empty() {
+                    pending.remove(ctx.session_key);
+                    return Ok(format!("Nothing in memory mentions '{}'.", ctx.args));
+                }
+                let reply = Self::preview(&request);
+                pending.insert(ctx.session_key.to_string(), request);
+                Ok(reply)
+            }
+        }
+    }
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    match (n, noun.strip_suffix('y')) {
+        (1, _) => format!("1 {noun}"),
+        (_, Some(stem)) => format!("{n} {stem}ies"),
+        _ => format!("{n} {noun}s"),
+    }
+}
+
+async fn apply_settings(
+    command: Option<SettingsCommand>,
+    ctx: CommandContext<'_>,
+    personas: &Mutex<PersonaStore>,
+) -> Result<String> {
+    let Some(command) = command else {
+        return Ok("Usage: /show settings".to_string());
+    };
+    let store = personas.lock().await;
+    let agent_loop = ctx.agent_loop;
+    let session_key = ctx.session_key;
+    let tiers = agent_loop.models.tiers();
+    let session = match agent_loop.sessions.get_or_create_checked(session_key) {
+        Ok(s) => s,
+        Err(e) => {
+            tracing::error!("Failed to load session '{session_key}': {e}");
+            return Ok(format!("I couldn't load your session state: {e}"));
+        }
+    };
+    let reply = command.apply(&mut session.metadata, &tiers, &store);
+    if let Err(e) = agent_loop.sessions.save(session_key) {
+        tracing::warn!("Failed to save settings for session '{session_key}': {e}");
+    }
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> CommandRegistry {
+        let dir = tempfile::tempdir().unwrap();
+        let personas = PersonaStore::load(&dir.path().join("personas.json"));
+        let personas = Arc::new(Mutex::new(personas));
+        let mut registry = CommandRegistry::new();
+        registry.register(Box::new(NewSessionCommand));
+        registry.register(Box::new(ShowCommand::new(personas)));
+        registry.register(Box::new(HelpCommand::new("Hi!")));
+        registry.register(Box::new(StartCommand(HelpCommand::new("Hi!"))));
+        registry
+    }
+
+    #[test]
+    fn finds_commands_with_arguments() {
+        let registry = registry();
+        let (command, args) = registry.find("slack", " /show  settings ").unwrap();
+        assert_eq!((command.name(), args), ("show", "settings"));
+        let (command, args) = registry.find("telegram", "/new@patina_bot").unwrap();
+        assert_eq!((command.name(), args), ("new", ""));
+        let (command, _) = registry.find("web", "/settings").unwrap();
+        assert_eq!(command.name(), "show");
+
+        assert!(registry.find("web", "/newer").is_none());
+        assert!(registry.find("web", "new").is_none());
+        assert!(registry.find("web", "/ new").is_none());
+    }
+
+    #[test]
+    fn respects_channel_availability() {
+        let registry = registry();
+        assert!(registry.find("telegram", "/start").is_some());
+        assert!(registry.find("slack", "/start").is_none());
+
+        let menu = registry.menu("telegram");
+        let names: Vec<&str> = menu.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["new", "show", "help", "start"]);
+        assert_eq!(registry.menu("cli").len(), 3);
+    }
+
+    #[test]
+    fn renders_help_in_registration_order() {
+        let mut registry = registry();
+        registry.register(Box::new(HelpCommand::new("Replaced")));
+        assert_eq!(
+            registry.help("web", None),
+            "Commands:\n\
+             /new - Start a new conversation\n\
+             /show settings - Show this chat's settings\n\
+             /help - Show what I can do here"
+        );
+        let guest = RolePolicy {
+            allow_commands: vec!["help".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            registry.help("web", Some(&guest)),
+            "Commands:\n/help - Show what I can do here"
+        );
+    }
+
+    #[test]
+    fn help_lists_model_and_tools() {
+        assert_eq!(
+            help_status(None, "default", "qwen3:8b"),
+            "Model: default (qwen3:8b)"
+        );
+        assert_eq!(
+            help_status(Some("Coder"), "fast", "gpt-4o-mini"),
+            "Persona: Coder\nModel: fast (gpt-4o-mini)"
+        );
+
+        let tools = ["calc", "exec", "web_search"];
+        assert_eq!(help_tools(&tools, None), "Tools: calc, exec, web_search");
+        let member = RolePolicy {
+            deny_tools: vec!["exec".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            help_tools(&tools, Some(&member)),
+            "Tools: calc, web_search\nNot available to you: exec"
+        );
+        assert_eq!(help_tools(&[], None), "");
+    }
+
+    #[tokio::test]
+    async fn persona_lists_personas_as_choices() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = PersonaStore::load(&dir.path().join("personas.json"));
+        assert_eq!(
+            persona_list(&store, None),
+            "No personas are defined. Add them in the web UI."
+        );
+        for (key, name, description) in
+            [("pirate", "Pirate", ""), ("coder", "Coder", "Writes code")]
+        {
+            let persona = Persona {
+                name: name.into(),
+                description: description.into(),
+                preamble: String::new(),
+                model_tier: String::new(),
+                color: String::new(),
+            };
+            store.upsert(key.into(), persona).unwrap();
+        }
+        assert_eq!(
+            persona_list(&store, Some("pirate")),
+            "Personas:\n- Coder (coder): Writes code\n- Pirate (pirate) [current]\n\n\
+             Send /persona <name> to switch, or /persona none for no persona."
+        );
+
+        let mut registry = registry();
+        registry.register(Box::new(PersonaCommand::new(Arc::new(Mutex::new(store)))));
+        let choices = registry.choices("telegram", "/persona").await;
+        let inputs: Vec<&str> = choices.iter().map(|(_, input)| input.as_str()).collect();
+        assert_eq!(
+            inputs,
+            ["/persona coder", "/persona pirate", "/persona none"]
+        );
+        assert_eq!(choices[0].0, "Coder");
+        assert!(registry
+            .choices("telegram", "/persona coder")
+            .await
+            .is_empty());
+        assert!(registry.choices("telegram", "/new").await.is_empty());
+    }
+
+    #[test]
+    fn forget_preview_lists_matches() {
+        let pending = PendingForget {
+            topic: "sam".into(),
+            facts: vec![MemoryFact {
+                line: 3,
+                text: "Partner is Sam".into(),
+                section: None,
+            }],
+            history: vec!["[2026-01-02] Dinner with Sam.\nBooked a table.".into()],
+        };
+        assert_eq!(
+            ForgetCommand::preview(&pending),
+            "This will forget 1 fact from memory and 1 entry from history about 'sam':\n\
+             - Partner is Sam\n\
+             - [2026-01-02] Dinner with Sam....\n\n\
+             Send /forget confirm to delete, or /forget cancel to keep it."
+        );
+        assert_eq!(plural(2, "history entry"), "2 history entries");
+    }
+
+    #[test]
+    fn new_session_summarizes_archive() {
+        let mut result = ConsolidationResult {
+            session_key: "web:1".into(),
+            end: 12,
+            messages: 12,
+            history_entry: Some("[2026-03-04 10:15] Planned the Lisbon trip.".into()),
+            new_facts: vec!["Flying to Lisbon on May 2".into()],
+            held: None,
+        };
+        assert_eq!(
+            archive_summary(&result),
+            "New session started. I archived 12 messages from the previous conversation.\n\n\
+             Summary: Planned the Lisbon trip.\n\n\
+             Added to long-term memory:\n\
+             - Flying to Lisbon on May 2"
+        );
+        result.new_facts.clear();
+        assert!(archive_summary(&result).ends_with("Nothing new went into long-term memory."));
+    }
+}
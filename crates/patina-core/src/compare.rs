@@ -0,0 +1,344 @@
+//! Side-by-side model comparisons ("A/B mode").
+//!
+//! The web UI sends one prompt to two model tiers at once, shows both
+//! replies, and asks which was better. Each comparison is kept in a SQLite
+//! store with both replies, their latency, tokens and cost, and the
+//! preference once it's given, so [`ComparisonStore::tier_stats`] can show
+//! how often each tier wins. Comparisons never touch the chat's session.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Db, Migration, Step};
+
+/// Which reply was better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preference {
+    A,
+    B,
+    Tie,
+    Neither,
+}
+
+impl Preference {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A => "a",
+            Self::B => "b",
+            Self::Tie => "tie",
+            Self::Neither => "neither",
+        }
+    }
+}
+
+impl FromStr for Preference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "a" => Ok(Self::A),
+            "b" => Ok(Self::B),
+            "tie" => Ok(Self::Tie),
+            "neither" => Ok(Self::Neither),
+            other => anyhow::bail!("unknown preference '{other}' (a, b, tie, neither)"),
+        }
+    }
+}
+
+/// One tier's reply in a comparison.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareSide {
+    pub tier: String,
+    pub model: String,
+    pub response: String,
+    pub latency_ms: u64,
+    pub tokens: u64,
+    /// Estimated from the pricing config; `None` if the model has no price.
+    pub cost: Option<f64>,
+    /// Set when the call failed; `response` holds whatever arrived first.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    pub id: String,
+    pub created_at: String,
+    /// The session whose history was used as context, if any.
+    pub session_key: Option<String>,
+    pub prompt: String,
+    pub a: CompareSide,
+    pub b: CompareSide,
+    pub preference: Option<Preference>,
+}
+
+/// How one tier has fared across comparisons.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TierStats {
+    pub tier: String,
+    pub comparisons: u64,
+    /// Comparisons with a preference recorded.
+    pub rated: u64,
+    pub preferred: u64,
+    pub ties: u64,
+    /// Rated comparisons where neither reply was good enough.
+    pub neither: u64,
+    pub avg_latency_ms: u64,
+    pub avg_tokens: u64,
+    pub total_cost: Option<f64>,
+}
+
+/// The comparison schema.
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "comparisons",
+    up: Step::Sql(
+        "CREATE TABLE IF NOT EXISTS comparisons (
+            id TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL,
+            session_key TEXT,
+            prompt TEXT NOT NULL,
+            side_a TEXT NOT NULL,
+            side_b TEXT NOT NULL,
+            preference TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_comparisons_created ON comparisons(created_at);",
+    ),
+}];
+
+pub struct ComparisonStore {
+    db: Db,
+}
+
+impl ComparisonStore {
+    /// Open or create the comparison database.
+    pub fn open(db_path: &Path) -> Result<Self> {
+        Ok(Self {
+            db: Db::open(db_path, MIGRATIONS)?,
+        })
+    }
+
+    pub fn record(&self, comparison: &Comparison) -> Result<()> {
+        self.db.conn()?.execute(
+            "INSERT INTO comparisons (id, created_at, session_key, prompt, side_a, side_b, preference)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                comparison.id,
+                comparison.created_at,
+                comparison.session_key,
+                comparison.prompt,
+                serde_json::to_string(&comparison.a)?,
+                serde_json::to_string(&comparison.b)?,
+                comparison.preference.map(|p| p.as_str()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record which reply was better. Returns false if there's no such
+    /// comparison. A later preference replaces an earlier one.
+    pub fn set_preference(&self, id: &str, preference: Preference) -> Result<bool> {
+        let changed = self.db.conn()?.execute(
+            "UPDATE comparisons SET preference = ?1 WHERE id = ?2",
+            params![preference.as_str(), id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<Comparison>> {
+        let conn = self.db.conn()?;
+        let row = conn
+            .query_row(
+                "SELECT id, created_at, session_key, prompt, side_a, side_b, preference
+                 FROM comparisons WHERE id = ?1",
+                params![id],
+                raw_row,
+            )
+            .optional()?;
+        row.map(parse_row).transpose()
+    }
+
+    /// The most recent comparisons, newest first.
+    pub fn recent(&self, limit: usize) -> Result<Vec<Comparison>> {
+        self.select(limit as i64)
+    }
+
+    /// Newest first; a negative limit means all of them.
+    fn select(&self, limit: i64) -> Result<Vec<Comparison>> {
+        let conn = self.db.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at, session_key, prompt, side_a, side_b, preference
+             FROM comparisons ORDER BY created_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![limit], raw_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter().map(parse_row).collect()
+    }
+
+    /// Per-tier win counts and averages over every comparison, ordered by
+    /// tier name.
+    pub fn tier_stats(&self) -> Result<Vec<TierStats>> {
+        let mut stats: Vec<TierStats> = Vec::new();
+        // The averages hold sums until the end
+        for c in self.select(-1)? {
+            for (side, won) in [(&c.a, Preference::A), (&c.b, Preference::B)] {
+                let i = match stats.iter().position(|s| s.tier == side.tier) {
+                    Some(i) => i,
+                    None => {
+                        stats.push(TierStats {
+                            tier: side.tier.clone(),
+                            ..Default::default()
+                        });
+                        stats.len() - 1
+                    }
+                };
+                let s = &mut stats[i];
+                s.comparisons += 1;
+                s.avg_latency_ms += side.latency_ms;
+                s.avg_tokens += side.tokens;
+                if let Some(cost) = side.cost {
+                    s.total_cost = Some(s.total_cost.unwrap_or(0.0) + cost);
+                }
+                if let Some(p) = c.preference {
+                    s.rated += 1;
+                    match p {
+                        p if p == won => s.preferred += 1,
+                        Preference::Tie => s.ties += 1,
+                        Preference::Neither => s.neither += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        for s in &mut stats {
+            s.avg_latency_ms /= s.comparisons;
+            s.avg_tokens /= s.comparisons;
+        }
+        stats.sort_by(|a, b| a.tier.cmp(&b.tier));
+        Ok(stats)
+    }
+}
+
+type RawRow = (
+    String,
+    String,
+    Option<String>,
+    String,
+    String,
+    String,
+    Option<String>,
+);
+
+fn raw_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<RawRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+    ))
+}
+
+fn parse_row(row: RawRow) -> Result<Comparison> {
+    let (id, created_at, session_key, prompt, side_a, side_b, preference) = row;
+    Ok(Comparison {
+        id,
+        created_at,
+        session_key,
+        prompt,
+        a: serde_json::from_str(&side_a)?,
+        b: serde_json::from_str(&side_b)?,
+        preference: preference.map(|p| p.parse()).transpose()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(tier: &str, latency_ms: u64, cost: Option<f64>) -> CompareSide {
+        CompareSide {
+            tier: tier.into(),
+            model: format!("{tier}-model"),
+            response: format!("{tier} says hi"),
+            latency_ms,
+            tokens: 100,
+            cost,
+            error: None,
+        }
+    }
+
+    fn comparison(id: &str, created_at: &str, a: CompareSide, b: CompareSide) -> Comparison {
+        Comparison {
+            id: id.into(),
+            created_at: created_at.into(),
+            session_key: Some("web:abc".into()),
+            prompt: "Say hi".into(),
+            a,
+            b,
+            preference: None,
+        }
+    }
+
+    #[test]
+    fn records_and_rates_comparisons() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ComparisonStore::open(&dir.path().join("compare.sqlite")).unwrap();
+        let first = comparison(
+            "c1",
+            "2026-03-01T10:00:00Z",
+            side("default", 2000, Some(0.01)),
+            side("fast", 500, Some(0.001)),
+        );
+        store.record(&first).unwrap();
+        store
+            .record(&comparison(
+                "c2",
+                "2026-03-02T10:00:00Z",
+                side("fast", 700, None),
+                side("default", 1000, Some(0.02)),
+            ))
+            .unwrap();
+
+        assert_eq!(store.get("c1").unwrap().unwrap(), first);
+        assert!(store.set_preference("c1", Preference::B).unwrap());
+        assert!(store.set_preference("c2", Preference::Tie).unwrap());
+        assert!(!store.set_preference("nope", Preference::A).unwrap());
+        assert_eq!(
+            store.get("c1").unwrap().unwrap().preference,
+            Some(Preference::B)
+        );
+
+        let recent = store.recent(10).unwrap();
+        assert_eq!(recent[0].id, "c2");
+        assert_eq!(recent.len(), 2);
+
+        let stats = store.tier_stats().unwrap();
+        assert_eq!(stats.len(), 2);
+        let (default, fast) = (&stats[0], &stats[1]);
+        assert_eq!(default.tier, "default");
+        assert_eq!((default.rated, default.preferred, default.ties), (2, 0, 1));
+        assert_eq!(default.avg_latency_ms, 1500);
+        assert!((default.total_cost.unwrap() - 0.03).abs() < 1e-9);
+        assert_eq!(fast.tier, "fast");
+        assert_eq!((fast.rated, fast.preferred, fast.ties), (2, 1, 1));
+        assert_eq!(fast.avg_latency_ms, 600);
+        assert!((fast.total_cost.unwrap() - 0.001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parses_preferences() {
+        assert_eq!("tie".parse::<Preference>().unwrap(), Preference::Tie);
+        assert_eq!(Preference::Neither.as_str(), "neither");
+        assert!("both".parse::<Preference>().is_err());
+    }
+}
@@ -0,0 +1,792 @@
+//! Read-only connectors that sync external documents into the knowledge base.
+//!
+//! A [`Connector`] lists the documents in its configured scope with a
+//! version (Notion's `last_edited_time`, Drive's `modifiedTime`), and
+//! [`KnowledgeBase::refresh`](crate::knowledge::KnowledgeBase::refresh)
+//! only fetches the ones whose version changed since the last sync. Nothing
+//! is ever written back.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use patina_config::{DriveSourceConfig, KnowledgeConfig, NotionSourceConfig};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+const NOTION_API: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+/// Nesting followed inside a Notion page (toggles, nested lists).
+const NOTION_MAX_DEPTH: usize = 6;
+
+const DRIVE_API: &str = "https://www.googleapis.com/drive/v3";
+const DRIVE_SCOPE: &str = "https://www.googleapis.com/auth/drive.readonly";
+const DRIVE_FOLDER: &str = "application/vnd.google-apps.folder";
+
+/// A document a connector can fetch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteDocument {
+    pub id: String,
+    /// Link to the document, used as its knowledge source for citing.
+    pub source: String,
+    pub title: String,
+    /// Changes whenever the document does.
+    pub version: String,
+    /// Connector-specific type, e.g. Drive's MIME type.
+    pub kind: String,
+}
+
+/// A read-only source of documents.
+#[async_trait]
+pub trait Connector: Send + Sync {
+    /// Recorded with each document, so a failed listing keeps this
+    /// connector's documents instead of dropping them.
+    fn name(&self) -> &str;
+
+    /// Every document in scope, without its text.
+    async fn list(&self) -> Result<Vec<RemoteDocument>>;
+
+    /// A listed document's text.
+    async fn fetch(&self, doc: &RemoteDocument) -> Result<String>;
+}
+
+/// The connectors configured in `knowledge`. Relative credential paths are
+/// resolved against `workspace`; a connector that can't be set up is
+/// logged and left out.
+pub fn from_config(config: &KnowledgeConfig, workspace: &Path) -> Vec<Arc<dyn Connector>> {
+    let mut connectors: Vec<Arc<dyn Connector>> = Vec::new();
+    if !config.notion.token.trim().is_empty() {
+        connectors.push(Arc::new(NotionConnector::new(&config.notion)));
+    }
+    if !config.drive.credentials_file.trim().is_empty() {
+        match DriveConnector::from_config(&config.drive, &config.extensions, workspace) {
+            Ok(drive) => connectors.push(Arc::new(drive)),
+            Err(e) => warn!("Knowledge: Google Drive disabled: {e}"),
+        }
+    }
+    connectors
+}
+
+/// Notion pages shared with an internal integration.
+pub struct NotionConnector {
+    http: reqwest::Client,
+    token: String,
+    /// Normalized IDs of the configured pages and databases.
+    roots: Vec<String>,
+}
+
+/// A page or database from Notion's search, for scoping.
+#[derive(Debug)]
+struct NotionObject {
+    id: String,
+    parent: Option<String>,
+    is_page: bool,
+    title: String,
+    url: String,
+    edited: String,
+}
+
+impl NotionConnector {
+    pub fn new(config: &NotionSourceConfig) -> Self {
+        let roots = config
+            .pages
+            .iter()
+            .filter_map(|p| {
+                let id = notion_id(p);
+                if id.is_none() {
+                    warn!("Knowledge: '{p}' isn't a Notion page ID or URL");
+                }
+                id
+            })
+            .collect();
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .build()
+                .unwrap_or_default(),
+            token: config.token.trim().to_string(),
+            roots,
+        }
+    }
+
+    /// Send a request built by `build`, waiting out rate limits.
+    async fn request(&self, build: impl Fn() -> reqwest::RequestBuilder) -> Result<Value> {
+        for _ in 0..4 {
+            let response = build()
+                .bearer_auth(&self.token)
+                .header("Notion-Version", NOTION_VERSION)
+                .send()
+                .await?;
+            let status = response.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let wait = response
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(1);
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                continue;
+            }
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                bail!("Notion request failed ({status}): {}", body.trim());
+            }
+            return Ok(response.json().await?);
+        }
+        bail!("Notion kept rate-limiting requests")
+    }
+
+    /// Blocks under `id` in order, with their nesting depth. Child pages
+    /// and databases are documents of their own and aren't followed.
+    fn blocks<'a>(
+        &'a self,
+        id: &'a str,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<Vec<(usize, Value)>>> {
+        async move {
+            let mut blocks = Vec::new();
+            let mut cursor: Option<String> = None;
+            loop {
+                let mut url = format!("{NOTION_API}/blocks/{id}/children?page_size=100");
+                if let Some(ref c) = cursor {
+                    url.push_str(&format!("&start_cursor={c}"));
+                }
+                let page = self.request(|| self.http.get(&url)).await?;
+                for block in page["results"].as_array().cloned().unwrap_or_default() {
+                    let nested = block["has_children"].as_bool().unwrap_or(false)
+                        && !matches!(
+                            block["type"].as_str(),
+                            Some("child_page" | "child_database")
+                        )
+                        && depth + 1 < NOTION_MAX_DEPTH;
+                    let child_id = block["id"].as_str().unwrap_or_default().to_string();
+                    blocks.push((depth, block));
+                    if nested {
+                        blocks.extend(self.blocks(&child_id, depth + 1).await?);
+                    }
+                }
+                cursor = next_cursor(&page);
+                if cursor.is_none() {
+                    return Ok(blocks);
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[async_trait]
+impl Connector for NotionConnector {
+    fn name(&self) -> &str {
+        "notion"
+    }
+
+    async fn list(&self) -> Result<Vec<RemoteDocument>> {
+        let mut objects = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut body = serde_json::json!({ "page_size": 100 });
+            if let Some(ref c) = cursor {
+                body["start_cursor"] = Value::String(c.clone());
+            }
+            let page = self
+                .request(|| self.http.post(format!("{NOTION_API}/search")).json(&body))
+                .await?;
+            objects.extend(
+                page["results"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(parse_notion_object),
+            );
+            cursor = next_cursor(&page);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(notion_scope(&objects, &self.roots)
+            .into_iter()
+            .map(|o| RemoteDocument {
+                id: o.id.clone(),
+                source: o.url.clone(),
+                title: o.title.clone(),
+                version: o.edited.clone(),
+                kind: "page".to_string(),
+            })
+            .collect())
+    }
+
+    async fn fetch(&self, doc: &RemoteDocument) -> Result<String> {
+        let mut text = format!("# {}\n\n", doc.title);
+        for (depth, block) in self.blocks(&doc.id, 0).await? {
+            if let Some(line) = notion_block_text(&block, depth) {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        Ok(text)
+    }
+}
+
+fn next_cursor(page: &Value) -> Option<String> {
+    page["has_more"]
+        .as_bool()
+        .unwrap_or(false)
+        .then(|| page["next_cursor"].as_str().map(String::from))
+        .flatten()
+}
+
+/// The 32-digit ID in a Notion ID or page URL, lowercase without dashes.
+fn notion_id(text: &str) -> Option<String> {
+    let path = text.trim().split(['?', '#']).next()?;
+    let last = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()?
+        .replace('-', "");
+    let id = last.get(last.len().checked_sub(32)?..)?;
+    id.chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| id.to_lowercase())
+}
+
+fn parse_notion_object(value: &Value) -> Option<NotionObject> {
+    if value["archived"].as_bool() == Some(true) || value["in_trash"].as_bool() == Some(true) {
+        return None;
+    }
+    let is_page = match value["object"].as_str()? {
+        "page" => true,
+        "database" => false,
+        _ => return None,
+    };
+    let parent = &value["parent"];
+    let parent = parent["type"]
+        .as_str()
+        .and_then(|t| parent[t].as_str())
+        .and_then(notion_id);
+    // A page's title is its title property; a database's is top-level
+    let title_text = if is_page {
+        value["properties"]
+            .as_object()
+            .and_then(|props| props.values().find(|p| p["type"] == "title"))
+            .map(|p| &p["title"])
+    } else {
+        Some(&value["title"])
+    };
+    let title = title_text.map(rich_text).unwrap_or_default();
+    Some(NotionObject {
+        id: notion_id(value["id"].as_str()?)?,
+        parent,
+        is_page,
+        title: if title.is_empty() {
+            "Untitled".to_string()
+        } else {
+            title
+        },
+        url: value["url"].as_str().unwrap_or_default().to_string(),
+        edited: value["last_edited_time"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// The pages among `objects` that are, or are under, one of `roots`. All
+/// pages when there are no roots.
+fn notion_scope<'a>(objects: &'a [NotionObject], roots: &[String]) -> Vec<&'a NotionObject> {
+    let parents: HashMap<&str, Option<&str>> = objects
+        .iter()
+        .map(|o| (o.id.as_str(), o.parent.as_deref()))
+        .collect();
+    let in_scope = |object: &NotionObject| {
+        let mut id = Some(object.id.as_str());
+        let mut visited = HashSet::new();
+        while let Some(current) = id {
+            if roots.iter().any(|r| r == current) {
+                return true;
+            }
+            if !visited.insert(current) {
+                return false;
+            }
+            id = parents.get(current).copied().flatten();
+        }
+        false
+    };
+    objects
+        .iter()
+        .filter(|o| o.is_page && (roots.is_empty() || in_scope(o)))
+        .collect()
+}
+
+fn rich_text(value: &Value) -> String {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t["plain_text"].as_str())
+        .collect()
+}
+
+/// A block as a line of markdown, indented by its depth. `None` for
+/// blocks without text.
+fn notion_block_text(block: &Value, depth: usize) -> Option<String> {
+    let kind = block["type"].as_str()?;
+    let data = &block[kind];
+    let text = rich_text(&data["rich_text"]);
+    let indent = "  ".repeat(depth);
+    let line = match kind {
+        "heading_1" => format!("# {text}"),
+        "heading_2" => format!("## {text}"),
+        "heading_3" => format!("### {text}"),
+        "bulleted_list_item" | "toggle" => format!("{indent}- {text}"),
+        "numbered_list_item" => format!("{indent}1. {text}"),
+        "to_do" => {
+            let mark = if data["checked"].as_bool() == Some(true) {
+                "x"
+            } else {
+                " "
+            };
+            format!("{indent}- [{mark}] {text}")
+        }
+        "quote" | "callout" => format!("> {text}"),
+        "code" => format!(
+            "```{}\n{text}\n```",
+            data["language"].as_str().unwrap_or_default()
+        ),
+        "table_row" => {
+            let cells: Vec<String> = data["cells"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(rich_text)
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        }
+        "equation" => data["expression"].as_str()?.to_string(),
+        "bookmark" | "embed" | "link_preview" => data["url"].as_str()?.to_string(),
+        "child_page" | "child_database" => format!("{indent}- {}", data["title"].as_str()?),
+        _ if !text.is_empty() => format!("{indent}{text}"),
+        _ => return None,
+    };
+    Some(line)
+}
+
+/// Google Drive folders shared with a service account.
+pub struct DriveConnector {
+    http: reqwest::Client,
+    key: ServiceAccountKey,
+    folders: Vec<String>,
+    /// Extensions of plain files (not Google Docs) worth downloading.
+    extensions: Vec<String>,
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+#[derive(Serialize)]
+struct TokenClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: u64,
+}
+
+/// How a Drive file's text is downloaded.
+#[derive(Debug, PartialEq)]
+enum Download {
+    /// A Google Docs file, exported as this MIME type.
+    Export(&'static str),
+    /// A plain file, downloaded as is.
+    Media,
+}
+
+impl DriveConnector {
+    pub fn from_config(
+        config: &DriveSourceConfig,
+        extensions: &[String],
+        workspace: &Path,
+    ) -> Result<Self> {
+        let mut path = patina_config::resolve_workspace(&config.credentials_file);
+        if path.is_relative() {
+            path = workspace.join(path);
+        }
+        let key = std::fs::read_to_string(&path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        let key: ServiceAccountKey = serde_json::from_str(&key)
+            .with_context(|| format!("{} isn't a service account key", path.display()))?;
+        let folders: Vec<String> = config.folders.iter().filter_map(|f| drive_id(f)).collect();
+        if folders.is_empty() {
+            bail!("knowledge.drive.folders is empty");
+        }
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(120))
+                .build()?,
+            key,
+            folders,
+            extensions: extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            token: Mutex::new(None),
+        })
+    }
+
+    /// An access token for the service account, reused until it's about
+    /// to expire.
+    async fn access_token(&self) -> Result<String> {
+        if let Some((ref token, expires)) = *self.token.lock().unwrap_or_else(|e| e.into_inner()) {
+            if Instant::now() < expires {
+                return Ok(token.clone());
+            }
+        }
+        let now = chrono::Utc::now().timestamp();
+        let claims = TokenClaims {
+            iss: &self.key.client_email,
+            scope: DRIVE_SCOPE,
+            aud: &self.key.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let assertion = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_rsa_pem(self.key.private_key.as_bytes())?,
+        )?;
+        let response = self
+            .http
+            .post(&self.key.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Google token request failed ({status}): {}", body.trim());
+        }
+        let token: TokenResponse = response.json().await?;
+        let lifetime = Duration::from_secs(token.expires_in.max(120) - 60);
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((token.access_token.clone(), Instant::now() + lifetime));
+        Ok(token.access_token)
+    }
+
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<reqwest::Response> {
+        let response = self
+            .http
+            .get(url)
+            .query(query)
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Drive request failed ({status}): {}", body.trim());
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Connector for DriveConnector {
+    fn name(&self) -> &str {
+        "drive"
+    }
+
+    async fn list(&self) -> Result<Vec<RemoteDocument>> {
+        let mut documents = Vec::new();
+        let mut folders = self.folders.clone();
+        let mut visited = HashSet::new();
+        while let Some(folder) = folders.pop() {
+            if !visited.insert(folder.clone()) {
+                continue;
+            }
+            let q = format!("'{folder}' in parents and trashed = false");
+            let mut page_token = String::new();
+            loop {
+                let mut query = vec![
+                    ("q", q.as_str()),
+                    (
+                        "fields",
+                        "nextPageToken,files(id,name,mimeType,modifiedTime,webViewLink)",
+                    ),
+                    ("pageSize", "1000"),
+                    ("supportsAllDrives", "true"),
+                    ("includeItemsFromAllDrives", "true"),
+                ];
+                if !page_token.is_empty() {
+                    query.push(("pageToken", page_token.as_str()));
+                }
+                let page: Value = self
+                    .get(&format!("{DRIVE_API}/files"), &query)
+                    .await?
+                    .json()
+                    .await?;
+                for file in page["files"].as_array().into_iter().flatten() {
+                    let (Some(id), Some(name), Some(mime)) = (
+                        file["id"].as_str(),
+                        file["name"].as_str(),
+                        file["mimeType"].as_str(),
+                    ) else {
+                        continue;
+                    };
+                    if mime == DRIVE_FOLDER {
+                        folders.push(id.to_string());
+                    } else if drive_download(mime, name, &self.extensions).is_some() {
+                        documents.push(RemoteDocument {
+                            id: id.to_string(),
+                            source: file["webViewLink"]
+                                .as_str()
+                                .map(String::from)
+                                .unwrap_or_else(|| {
+                                    format!("https://drive.google.com/file/d/{id}/view")
+                                }),
+                            title: name.to_string(),
+                            version: file["modifiedTime"]
+                                .as_str()
+                                .unwrap_or_default()
+                                .to_string(),
+                            kind: mime.to_string(),
+                        });
+                    }
+                }
+                match page["nextPageToken"].as_str() {
+                    Some(token) => page_token = token.to_string(),
+                    None => break,
+                }
+            }
+        }
+        Ok(documents)
+    }
+
+    async fn fetch(&self, doc: &RemoteDocument) -> Result<String> {
+        let response = match drive_download(&doc.kind, &doc.title, &self.extensions) {
+            Some(Download::Export(mime)) => {
+                self.get(
+                    &format!("{DRIVE_API}/files/{}/export", doc.id),
+                    &[("mimeType", mime)],
+                )
+                .await?
+            }
+            Some(Download::Media) => {
+                self.get(
+                    &format!("{DRIVE_API}/files/{}", doc.id),
+                    &[("alt", "media"), ("supportsAllDrives", "true")],
+                )
+                .await?
+            }
+            None => bail!("{} can't be read as text", doc.kind),
+        };
+        Ok(response.text().await?)
+    }
+}
+
+/// The folder or file ID in a Drive ID or URL.
+fn drive_id(text: &str) -> Option<String> {
+    let text = text.trim();
+    let id = match text
+        .split_once("/folders/")
+        .or_else(|| text.split_once("/d/"))
+    {
+        Some((_, rest)) => rest,
+        None => text.split_once("id=").map(|(_, rest)| rest).unwrap_or(text),
+    };
+    let id: String = id
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if id.is_empty() {
+        warn!("Knowledge: '{text}' isn't a Drive folder ID or URL");
+        return None;
+    }
+    Some(id)
+}
+
+/// How to get the text of a Drive file, or `None` if it has none worth
+/// indexing.
+fn drive_download(mime: &str, name: &str, extensions: &[String]) -> Option<Download> {
+    match mime {
+        "application/vnd.google-apps.document" | "application/vnd.google-apps.presentation" => {
+            Some(Download::Export("text/plain"))
+        }
+        "application/vnd.google-apps.spreadsheet" => Some(Download::Export("text/csv")),
+        _ if mime.starts_with("application/vnd.google-apps.") => None,
+        _ => {
+            let extension = name.rsplit_once('.')?.1.to_lowercase();
+            extensions.contains(&extension).then_some(Download::Media)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ids_come_from_urls() {
+        assert_eq!(
+            notion_id(
+                "https://www.notion.so/acme/Onboarding-0123456789abcdef0123456789ABCDEF?pvs=4"
+            )
+            .as_deref(),
+            Some("0123456789abcdef0123456789abcdef")
+        );
+        assert_eq!(
+            notion_id("01234567-89ab-cdef-0123-456789abcdef").as_deref(),
+            Some("0123456789abcdef0123456789abcdef")
+        );
+        assert_eq!(notion_id("Onboarding"), None);
+        assert_eq!(
+            drive_id("https://drive.google.com/drive/folders/1AbC-d_9?usp=sharing").as_deref(),
+            Some("1AbC-d_9")
+        );
+        assert_eq!(drive_id("1AbC-d_9").as_deref(), Some("1AbC-d_9"));
+    }
+
+    #[test]
+    fn notion_scope_follows_parents() {
+        let object = |id: &str, parent: Option<&str>, is_page: bool| NotionObject {
+            id: id.repeat(32),
+            parent: parent.map(|p| p.repeat(32)),
+            is_page,
+            title: id.to_string(),
+            url: String::new(),
+            edited: String::new(),
+        };
+        // a > b > (database c) > d; e stands alone
+        let objects = [
+            object("a", None, true),
+            object("b", Some("a"), true),
+            object("c", Some("b"), false),
+            object("d", Some("c"), true),
+            object("e", None, true),
+        ];
+        let titles = |roots: &[&str]| -> Vec<String> {
+            let roots: Vec<String> = roots.iter().map(|r| r.repeat(32)).collect();
+            notion_scope(&objects, &roots)
+                .iter()
+                .map(|o| o.title.clone())
+                .collect()
+        };
+        assert_eq!(titles(&["b"]), ["b", "d"]);
+        assert_eq!(titles(&["c"]), ["d"]);
+        assert_eq!(titles(&[]), ["a", "b", "d", "e"]);
+
+        let page = json!({
+            "object": "page",
+            "id": "01234567-89ab-cdef-0123-456789abcdef",
+            "parent": { "type": "database_id", "database_id": "c".repeat(32) },
+            "url": "https://www.notion.so/Leave-0123456789abcdef0123456789abcdef",
+            "last_edited_time": "2026-03-01T10:00:00.000Z",
+            "properties": {
+                "Tags": { "type": "multi_select", "multi_select": [] },
+                "Name": { "type": "title", "title": [{ "plain_text": "Leave " }, { "plain_text": "policy" }] }
+            }
+        });
+        let parsed = parse_notion_object(&page).unwrap();
+        assert_eq!(parsed.title, "Leave policy");
+        assert_eq!(parsed.parent, Some("c".repeat(32)));
+        assert!(
+            parse_notion_object(&json!({ "object": "page", "id": "x", "archived": true }))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn notion_blocks_become_markdown() {
+        let block = |kind: &str, data: Value| {
+            let mut block = json!({ "type": kind });
+            block[kind] = data;
+            block
+        };
+        let text = |s: &str| json!([{ "plain_text": s }]);
+        assert_eq!(
+            notion_block_text(
+                &block("heading_2", json!({ "rich_text": text("Leave") })),
+                0
+            ),
+            Some("## Leave".to_string())
+        );
+        assert_eq!(
+            notion_block_text(
+                &block(
+                    "to_do",
+                    json!({ "rich_text": text("Book flights"), "checked": true })
+                ),
+                1
+            ),
+            Some("  - [x] Book flights".to_string())
+        );
+        assert_eq!(
+            notion_block_text(
+                &block("table_row", json!({ "cells": [text("Days"), text("25")] })),
+                0
+            ),
+            Some("| Days | 25 |".to_string())
+        );
+        assert_eq!(notion_block_text(&block("divider", json!({})), 0), None);
+    }
+
+    #[test]
+    fn drive_files_are_exported_or_downloaded() {
+        let extensions = vec!["md".to_string(), "txt".to_string()];
+        assert_eq!(
+            drive_download(
+                "application/vnd.google-apps.document",
+                "Handbook",
+                &extensions
+            ),
+            Some(Download::Export("text/plain"))
+        );
+        assert_eq!(
+            drive_download(
+                "application/vnd.google-apps.spreadsheet",
+                "Budget",
+                &extensions
+            ),
+            Some(Download::Export("text/csv"))
+        );
+        assert_eq!(
+            drive_download("application/vnd.google-apps.form", "Survey", &extensions),
+            None
+        );
+        assert_eq!(
+            drive_download("text/markdown", "notes.MD", &extensions),
+            Some(Download::Media)
+        );
+        assert_eq!(
+            drive_download("application/pdf", "scan.pdf", &extensions),
+            None
+        );
+    }
+}
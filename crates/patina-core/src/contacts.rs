@@ -0,0 +1,331 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A person in the contacts directory.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    /// Given by the store; entries written by hand without one get one on
+    /// load.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Alternative names the user refers to this person by ("mom", "my landlord").
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub relationship: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phones: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub address: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    #[serde(default)]
+    pub updated_at_ms: i64,
+}
+
+impl Contact {
+    /// Score how well this contact matches a lowercase query (0 = no match).
+    fn match_score(&self, query: &str) -> u32 {
+        let name = self.name.to_lowercase();
+        if name == query || self.aliases.iter().any(|a| a.to_lowercase() == query) {
+            return 3;
+        }
+        if name.contains(query)
+            || self
+                .aliases
+                .iter()
+                .any(|a| a.to_lowercase().contains(query))
+            || self.relationship.to_lowercase().contains(query)
+        {
+            return 2;
+        }
+        if self.emails.iter().any(|e| e.to_lowercase().contains(query))
+            || self.phones.iter().any(|p| p.contains(query))
+            || self.notes.to_lowercase().contains(query)
+        {
+            return 1;
+        }
+        0
+    }
+}
+
+/// Top-level persistence structure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactBook {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+impl Default for ContactBook {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            contacts: Vec::new(),
+        }
+    }
+}
+
+/// Manages the workspace contacts file (`contacts.toml`).
+///
+/// The file is re-read before every operation so hand edits are picked up.
+/// A file that doesn't parse reads as empty, and changes are refused until
+/// it's fixed, so a typo never gets the user's contacts overwritten.
+pub struct ContactStore {
+    path: PathBuf,
+    book: ContactBook,
+    /// Why the file couldn't be read, if it couldn't.
+    unreadable: Option<String>,
+}
+
+impl ContactStore {
+    pub fn load(path: &Path) -> Self {
+        let (mut book, unreadable) = match std::fs::read_to_string(path) {
+            Ok(content) if !content.trim().is_empty() => {
+                match toml::from_str::<ContactBook>(&content) {
+                    Ok(book) => (book, None),
+                    Err(e) => {
+                        warn!("Failed to parse contacts file {}: {e}", path.display());
+                        (ContactBook::default(), Some(e.to_string()))
+                    }
+                }
+            }
+            _ => (ContactBook::default(), None),
+        };
+        let mut missing_ids = false;
+        for contact in book.contacts.iter_mut().filter(|c| c.id.is_empty()) {
+            contact.id = new_id();
+            missing_ids = true;
+        }
+        let store = Self {
+            path: path.to_path_buf(),
+            book,
+            unreadable,
+        };
+        // Keep the new IDs, so they stay the same on the next read
+        if missing_ids {
+            if let Err(e) = store.save() {
+                warn!("Failed to save contact IDs to {}: {e}", path.display());
+            }
+        }
+        store
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(e) = &self.unreadable {
+            anyhow::bail!(
+                "{} couldn't be read, so it wasn't changed. Fix it first: {e}",
+                self.path.display()
+            );
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = toml::to_string_pretty(&self.book)?;
+        std::fs::write(&self.path, text)?;
+        Ok(())
+    }
+
+    /// Reload from disk (picks up manual edits to the workspace file).
+    pub fn refresh_from_disk(&mut self) {
+        *self = Self::load(&self.path);
+    }
+
+    pub fn list(&self) -> &[Contact] {
+        &self.book.contacts
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Contact> {
+        self.book.contacts.iter().find(|c| c.id == id)
+    }
+
+    /// Search by name, alias, relationship, email, phone, or notes.
+    /// Results are ordered best match first. A leading "my " is ignored so
+    /// "my landlord" matches the relationship "landlord".
+    pub fn search(&self, query: &str) -> Vec<&Contact> {
+        let query = query.trim().to_lowercase();
+        let query = query.strip_prefix("my ").unwrap_or(&query).trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut scored: Vec<(u32, &Contact)> = self
+            .book
+            .contacts
+            .iter()
+            .map(|c| (c.match_score(query), c))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+        scored.into_iter().map(|(_, c)| c).collect()
+    }
+
+    /// Add a new contact. The id is assigned by the store.
+    pub fn add(&mut self, mut contact: Contact) -> Result<Contact> {
+        self.refresh_from_disk();
+        contact.id = new_id();
+        contact.updated_at_ms = chrono::Utc::now().timestamp_millis();
+        self.book.contacts.push(contact.clone());
+        self.save()?;
+        Ok(contact)
+    }
+
+    /// Apply an update closure to a contact. Returns the updated contact, if found.
+    pub fn update(
+        &mut self,
+        id: &str,
+        apply: impl FnOnce(&mut Contact),
+    ) -> Result<Option<Contact>> {
+        self.refresh_from_disk();
+        let Some(contact) = self.book.contacts.iter_mut().find(|c| c.id == id) else {
+            return Ok(None);
+        };
+        apply(contact);
+        contact.updated_at_ms = chrono::Utc::now().timestamp_millis();
+        let updated = contact.clone();
+        self.save()?;
+        Ok(Some(updated))
+    }
+
+    /// Remove a contact. Returns true if it existed.
+    pub fn remove(&mut self, id: &str) -> Result<bool> {
+        self.refresh_from_disk();
+        let before = self.book.contacts.len();
+        self.book.contacts.retain(|c| c.id != id);
+        let existed = self.book.contacts.len() != before;
+        if existed {
+            self.save()?;
+        }
+        Ok(existed)
+    }
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()[..8].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn contact(name: &str, aliases: &[&str], relationship: &str) -> Contact {
+        Contact {
+            name: name.into(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            relationship: relationship.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let dir = TempDir::new().unwrap();
+        let store = ContactStore::load(&dir.path().join("contacts.toml"));
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn test_add_and_reload() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("contacts.toml");
+        let mut store = ContactStore::load(&path);
+        let added = store
+            .add(Contact {
+                emails: vec!["jane@example.com".into()],
+                ..contact("Jane Doe", &["mom"], "mother")
+            })
+            .unwrap();
+        assert_eq!(added.id.len(), 8);
+
+        let store2 = ContactStore::load(&path);
+        let c = store2.get(&added.id).unwrap();
+        assert_eq!(c.name, "Jane Doe");
+        assert_eq!(c.emails, vec!["jane@example.com"]);
+    }
+
+    #[test]
+    fn test_search_ranks_exact_alias_first() {
+        let dir = TempDir::new().unwrap();
+        let mut store = ContactStore::load(&dir.path().join("contacts.toml"));
+        store.add(contact("Momo Tanaka", &[], "coworker")).unwrap();
+        store.add(contact("Jane Doe", &["Mom"], "mother")).unwrap();
+        store.add(contact("Bob", &[], "landlord")).unwrap();
+
+        let results = store.search("mom");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "Jane Doe");
+        assert_eq!(results[1].name, "Momo Tanaka");
+
+        let results = store.search("my landlord");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Bob");
+    }
+
+    #[test]
+    fn test_update_and_remove() {
+        let dir = TempDir::new().unwrap();
+        let mut store = ContactStore::load(&dir.path().join("contacts.toml"));
+        let added = store.add(contact("Bob", &[], "landlord")).unwrap();
+
+        let updated = store
+            .update(&added.id, |c| c.phones.push("+1 555 0100".into()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.phones, vec!["+1 555 0100"]);
+        assert!(store.update("missing", |_| {}).unwrap().is_none());
+
+        assert!(store.remove(&added.id).unwrap());
+        assert!(!store.remove(&added.id).unwrap());
+        assert!(store.list().is_empty());
+    }
+
+    #[test]
+    fn test_picks_up_manual_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("contacts.toml");
+        let mut store = ContactStore::load(&path);
+        std::fs::write(
+            &path,
+            "[[contacts]]\nid = \"abc\"\nname = \"Alice\"\n\n\
+             [[contacts]]\nname = \"Bob\"\nrelationship = \"landlord\"\n",
+        )
+        .unwrap();
+        store.refresh_from_disk();
+        assert_eq!(store.get("abc").unwrap().name, "Alice");
+        // An entry written without an ID gets one
+        let bob = store.search("landlord")[0].id.clone();
+        assert_eq!(bob.len(), 8);
+        store
+            .update(&bob, |c| c.notes = "Rent".into())
+            .unwrap()
+            .unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("Rent"));
+    }
+
+    #[test]
+    fn test_unreadable_file_is_left_alone() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("contacts.toml");
+        let broken = "[[contacts]]\nname = \"Alice\n";
+        std::fs::write(&path, broken).unwrap();
+
+        let mut store = ContactStore::load(&path);
+        assert!(store.list().is_empty());
+        let err = store.add(contact("Bob", &[], "")).unwrap_err();
+        assert!(err.to_string().contains("couldn't be read"), "{err}");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), broken);
+    }
+}
@@ -0,0 +1,113 @@
+//! Cached results of agent-turn cron jobs.
+//!
+//! A job with `cacheTtlSecs` set keeps its reply in `cache.json` next to the
+//! cron store, keyed on the prompt and the day on the job's clock. When the
+//! job runs again within the TTL on the same day, the cached reply is sent
+//! instead of asking the agent, so re-running a morning briefing doesn't
+//! spend tokens twice.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::memory_index::hex_sha256;
+
+/// How long entries are kept at most, whatever their job's TTL.
+const MAX_AGE_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedReply {
+    job_id: String,
+    response: String,
+    stored_at_ms: i64,
+}
+
+/// The reply cache file.
+pub struct ResponseCache {
+    path: PathBuf,
+}
+
+impl ResponseCache {
+    /// The cache kept next to the cron store at `store_path`.
+    pub fn beside(store_path: &Path) -> Self {
+        Self {
+            path: store_path.with_file_name("cache.json"),
+        }
+    }
+
+    /// The cache key for `prompt` run on `day`.
+    pub fn key(prompt: &str, day: NaiveDate) -> String {
+        hex_sha256(&format!("{day}\n{prompt}"))
+    }
+
+    /// The reply stored under `key`, if it's younger than `ttl_secs`.
+    pub fn get(&self, key: &str, ttl_secs: u64, now_ms: i64) -> Option<String> {
+        let entry = self.load().remove(key)?;
+        let age_ms = now_ms - entry.stored_at_ms;
+        (age_ms >= 0 && age_ms < ttl_secs as i64 * 1000).then_some(entry.response)
+    }
+
+    /// Store `response` under `key`, dropping entries older than a day.
+    pub fn put(&self, key: &str, job_id: &str, response: &str, now_ms: i64) -> Result<()> {
+        let mut entries = self.load();
+        entries.retain(|_, e| now_ms - e.stored_at_ms < MAX_AGE_MS);
+        entries.insert(
+            key.to_string(),
+            CachedReply {
+                job_id: job_id.to_string(),
+                response: response.to_string(),
+                stored_at_ms: now_ms,
+            },
+        );
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&entries)?)?;
+        Ok(())
+    }
+
+    fn load(&self) -> HashMap<String, CachedReply> {
+        std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replies_expire_and_change_daily() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::beside(&dir.path().join("cron/jobs.json"));
+        let day = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let key = ResponseCache::key("Morning briefing", day);
+        assert_ne!(
+            key,
+            ResponseCache::key("Morning briefing", day.succ_opt().unwrap())
+        );
+        assert_ne!(key, ResponseCache::key("Evening briefing", day));
+
+        assert_eq!(cache.get(&key, 3600, 1_000), None);
+        cache.put(&key, "j1", "Sunny, two meetings", 1_000).unwrap();
+        assert!(dir.path().join("cron/cache.json").exists());
+        assert_eq!(
+            cache.get(&key, 3600, 1_000 + 3_599_000).as_deref(),
+            Some("Sunny, two meetings")
+        );
+        assert_eq!(cache.get(&key, 3600, 1_000 + 3_600_000), None);
+
+        // Old entries are dropped on the next write
+        let other = ResponseCache::key("Evening briefing", day);
+        cache
+            .put(&other, "j2", "Quiet", 1_000 + MAX_AGE_MS)
+            .unwrap();
+        assert_eq!(cache.load().len(), 1);
+    }
+}
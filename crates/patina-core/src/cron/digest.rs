@@ -0,0 +1,377 @@
+//! Built-in "digest" cron payload.
+//!
+//! A digest job summarizes what happened since its previous run (or since it
+//! was created): sessions with new messages, tasks created or moved, other
+//! cron jobs that ran, and LLM usage with estimated cost. The summary is
+//! delivered straight to the job's channel without going through the agent.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, Utc};
+use patina_config::schema::ModelPricing;
+
+use crate::cron::types::CronJob;
+use crate::locale::UserLocale;
+use crate::session::SessionManager;
+use crate::task::{TaskManager, TaskStatus};
+use crate::usage::{UsageFilter, UsageTracker};
+
+/// Where a digest reads activity from.
+#[derive(Clone)]
+pub struct DigestSources {
+    pub sessions_dir: PathBuf,
+    pub task_store: PathBuf,
+    pub usage: Option<Arc<UsageTracker>>,
+    pub pricing: HashMap<String, ModelPricing>,
+}
+
+/// A session with messages in the digest window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionActivity {
+    pub key: String,
+    pub messages: usize,
+}
+
+/// A task created or updated in the digest window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskActivity {
+    pub title: String,
+    pub status: TaskStatus,
+    pub created: bool,
+}
+
+/// A cron job that ran in the digest window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronRun {
+    pub name: String,
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// LLM usage in the digest window. `cost` is `None` without pricing config.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub total_tokens: u64,
+    pub cost: Option<f64>,
+}
+
+/// Activity between `since_ms` and `until_ms`.
+#[derive(Debug, Clone, Default)]
+pub struct Digest {
+    pub since_ms: i64,
+    pub until_ms: i64,
+    pub sessions: Vec<SessionActivity>,
+    pub tasks: Vec<TaskActivity>,
+    pub cron_runs: Vec<CronRun>,
+    pub usage: Option<UsageTotals>,
+}
+
+impl Digest {
+    /// Collect activity in the window. `jobs` are the cron jobs to report on;
+    /// `exclude_job` (the digest itself) is skipped.
+    pub fn gather(
+        sources: &DigestSources,
+        jobs: &[CronJob],
+        exclude_job: &str,
+        since_ms: i64,
+        until_ms: i64,
+    ) -> Self {
+        let in_window = |ms: i64| ms >= since_ms && ms < until_ms;
+
+        let cron_runs = jobs
+            .iter()
+            .filter(|j| j.id != exclude_job)
+            .filter(|j| j.state.last_run_at_ms.is_some_and(in_window))
+            .map(|j| CronRun {
+                name: j.name.clone(),
+                status: j.state.last_status.clone().unwrap_or_else(|| "ok".into()),
+                error: j.state.last_error.clone(),
+            })
+            .collect();
+
+        let mut tasks: Vec<TaskActivity> = TaskManager::load(&sources.task_store)
+            .all_tasks()
+            .iter()
+            .filter(|t| in_window(t.updated_at_ms))
+            .map(|t| TaskActivity {
+                title: t.title.clone(),
+                status: t.status.clone(),
+                created: in_window(t.created_at_ms),
+            })
+            .collect();
+        tasks.sort_by_key(|t| status_order(&t.status));
+
+        Self {
+            since_ms,
+            until_ms,
+            sessions: session_activity(sources, since_ms, until_ms),
+            tasks,
+            cron_runs,
+            usage: sources
+                .usage
+                .as_ref()
+                .and_then(|u| usage_totals(u, &sources.pricing, since_ms, until_ms)),
+        }
+    }
+
+    /// Format the digest as a chat message, with times on `locale`'s clock.
+    pub fn render(&self, title: &str, locale: &UserLocale) -> String {
+        let mut out = format!(
+            "{title}\n{} to {}\n",
+            locale.format_ms(self.since_ms),
+            locale.format_ms(self.until_ms)
+        );
+
+        out.push_str(&format!("\nSessions ({}):\n", self.sessions.len()));
+        if self.sessions.is_empty() {
+            out.push_str("- none\n");
+        }
+        for s in &self.sessions {
+            let plural = if s.messages == 1 { "" } else { "s" };
+            out.push_str(&format!("- {}: {} message{plural}\n", s.key, s.messages));
+        }
+
+        out.push_str(&format!("\nTasks ({}):\n", self.tasks.len()));
+        if self.tasks.is_empty() {
+            out.push_str("- none\n");
+        }
+        for t in &self.tasks {
+            let new = if t.created { " (new)" } else { "" };
+            out.push_str(&format!(
+                "- [{}] {}{new}\n",
+                status_label(&t.status),
+                t.title
+            ));
+        }
+
+        out.push_str(&format!("\nCron runs ({}):\n", self.cron_runs.len()));
+        if self.cron_runs.is_empty() {
+            out.push_str("- none\n");
+        }
+        for r in &self.cron_runs {
+            match &r.error {
+                Some(e) => out.push_str(&format!("- {}: {} ({e})\n", r.name, r.status)),
+                None => out.push_str(&format!("- {}: {}\n", r.name, r.status)),
+            }
+        }
+
+        if let Some(usage) = &self.usage {
+            out.push_str(&format!(
+                "\nUsage: {} calls, {} tokens",
+                usage.calls, usage.total_tokens
+            ));
+            if let Some(cost) = usage.cost {
+                out.push_str(&format!(", ${cost:.2}"));
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string()
+    }
+}
+
+/// Sessions whose files changed in the window, with the number of messages
+/// added in it.
+fn session_activity(sources: &DigestSources, since_ms: i64, until_ms: i64) -> Vec<SessionActivity> {
+    let sessions = SessionManager::new(sources.sessions_dir.clone());
+    // Message timestamps are local time without an offset
+    let local = |ms: i64| {
+        DateTime::<Utc>::from_timestamp_millis(ms)
+            .map(|dt| {
+                dt.with_timezone(&Local)
+                    .format("%Y-%m-%dT%H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    let (since, until) = (local(since_ms), local(until_ms));
+    let since_utc = DateTime::<Utc>::from_timestamp_millis(since_ms).unwrap_or_default();
+
+    let mut activity: Vec<SessionActivity> = sessions
+        .list_sessions()
+        .into_iter()
+        .filter(|info| DateTime::parse_from_rfc3339(&info.updated_at).is_ok_and(|t| t >= since_utc))
+        .filter_map(|info| {
+            let session = sessions.load(&info.key).ok()??;
+            let messages = session
+                .messages
+                .iter()
+                .filter_map(|m| m.timestamp.as_deref())
+                .filter(|ts| *ts >= since.as_str() && *ts < until.as_str())
+                .count();
+            (messages > 0).then_some(SessionActivity {
+                key: info.key,
+                messages,
+            })
+        })
+        .collect();
+    activity.sort_by(|a, b| b.messages.cmp(&a.messages).then(a.key.cmp(&b.key)));
+    activity
+}
+
+fn usage_totals(
+    tracker: &UsageTracker,
+    pricing: &HashMap<String, ModelPricing>,
+    since_ms: i64,
+    until_ms: i64,
+) -> Option<UsageTotals> {
+    let rfc3339 = |ms: i64| DateTime::<Utc>::from_timestamp_millis(ms).map(|t| t.to_rfc3339());
+    let filter = UsageFilter {
+        from: rfc3339(since_ms),
+        to: rfc3339(until_ms),
+        group_by: Some("model".into()),
+        ..Default::default()
+    };
+    let rows = match tracker.query_summary_with_cost(&filter, pricing) {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Digest usage query failed: {e}");
+            return None;
+        }
+    };
+    Some(rows.iter().fold(UsageTotals::default(), |mut acc, row| {
+        acc.calls += row.calls;
+        acc.total_tokens += row.total_tokens;
+        if let Some(cost) = row.estimated_cost {
+            acc.cost = Some(acc.cost.unwrap_or(0.0) + cost);
+        }
+        acc
+    }))
+}
+
+fn status_order(status: &TaskStatus) -> u8 {
+    match status {
+        TaskStatus::Done => 0,
+        TaskStatus::InProgress => 1,
+        TaskStatus::Todo => 2,
+        TaskStatus::Backlog => 3,
+    }
+}
+
+fn status_label(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Done => "done",
+        TaskStatus::InProgress => "in progress",
+        TaskStatus::Todo => "todo",
+        TaskStatus::Backlog => "backlog",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::types::{CronJobState, CronPayload, CronSchedule, DeliverOn, ScheduleKind};
+    use crate::task::TaskPriority;
+
+    fn job(id: &str, last_run_at_ms: Option<i64>, status: &str) -> CronJob {
+        CronJob {
+            id: id.into(),
+            name: format!("job-{id}"),
+            enabled: true,
+            schedule: CronSchedule {
+                kind: ScheduleKind::Every,
+                at_ms: None,
+                every_ms: Some(60_000),
+                expr: None,
+                tz: None,
+            },
+            payload: CronPayload {
+                kind: "exec".into(),
+                message: "true".into(),
+                deliver: false,
+                channel: None,
+                to: None,
+                cache_ttl_secs: None,
+                model_tier: None,
+                deliver_on: DeliverOn::Always,
+            },
+            state: CronJobState {
+                next_run_at_ms: None,
+                last_run_at_ms,
+                last_status: Some(status.into()),
+                last_error: (status == "error").then(|| "boom".into()),
+            },
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: false,
+            on_success: None,
+            on_failure: None,
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn gathers_activity_in_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = DigestSources {
+            sessions_dir: dir.path().join("sessions"),
+            task_store: dir.path().join("tasks.json"),
+            usage: None,
+            pricing: HashMap::new(),
+        };
+        let since = Utc::now().timestamp_millis() - 60_000;
+
+        let mut sessions = SessionManager::new(sources.sessions_dir.clone());
+        let session = sessions.get_or_create("telegram:1");
+        session.add_message("user", "hi");
+        session.add_message("assistant", "hello");
+        sessions.save("telegram:1").unwrap();
+        let old = sessions.get_or_create("web:old");
+        old.add_message("user", "ancient");
+        old.messages[0].timestamp = Some("2020-01-01T00:00:00".into());
+        sessions.save("web:old").unwrap();
+
+        let mut tasks = TaskManager::load(&sources.task_store);
+        let task = tasks
+            .add("Ship it", "", TaskPriority::Medium, None, vec![], "user")
+            .unwrap();
+        tasks.move_task(&task.id, TaskStatus::Done).unwrap();
+
+        let jobs = [
+            job("a", Some(since + 1000), "ok"),
+            job("b", Some(since - 1000), "ok"),
+            job("c", Some(since + 2000), "error"),
+            job("digest", Some(since + 3000), "ok"),
+        ];
+        let until = Utc::now().timestamp_millis() + 1000;
+        let digest = Digest::gather(&sources, &jobs, "digest", since, until);
+
+        assert_eq!(
+            digest.sessions,
+            vec![SessionActivity {
+                key: "telegram:1".into(),
+                messages: 2
+            }]
+        );
+        assert_eq!(digest.tasks.len(), 1);
+        assert!(digest.tasks[0].created);
+        let runs: Vec<&str> = digest.cron_runs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(runs, ["job-a", "job-c"]);
+        assert!(digest.usage.is_none());
+
+        let text = digest.render("Nightly digest", &UserLocale::default());
+        assert!(text.starts_with("Nightly digest\n"));
+        assert!(text.contains("- telegram:1: 2 messages"));
+        assert!(text.contains("- [done] Ship it (new)"));
+        assert!(text.contains("- job-c: error (boom)"));
+    }
+
+    #[test]
+    fn renders_empty_digest_and_usage() {
+        let digest = Digest {
+            usage: Some(UsageTotals {
+                calls: 3,
+                total_tokens: 4200,
+                cost: Some(0.126),
+            }),
+            ..Default::default()
+        };
+        let text = digest.render("Weekly digest", &UserLocale::new(Some("Asia/Tokyo"), None));
+        assert!(text.starts_with("Weekly digest\n1970-01-01 09:00 (UTC+09:00) to"));
+        assert!(text.contains("Sessions (0):\n- none"));
+        assert!(text.contains("Cron runs (0):\n- none"));
+        assert!(text.ends_with("Usage: 3 calls, 4200 tokens, $0.13"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Run history of exec cron jobs.
+//!
+//! Each run of an `exec` job appends a [`RunRecord`] to `history.jsonl` next
+//! to the cron store: when it ran, how long it took, how it went, and its
+//! stdout and stderr, each cut to [`MAX_STREAM_CHARS`] keeping the start and
+//! the end, where errors usually are. The file keeps the last
+//! [`MAX_RECORDS`] runs across all jobs.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Most characters of stdout or stderr kept per run.
+pub const MAX_STREAM_CHARS: usize = 8_000;
+
+/// Most runs kept in the file.
+pub const MAX_RECORDS: usize = 500;
+
+/// One run of an exec job.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub job_id: String,
+    pub job_name: String,
+    pub started_at_ms: i64,
+    pub duration_ms: u64,
+    /// "ok" or "error", as in the job's `lastStatus`.
+    pub status: String,
+    /// `None` when the command didn't finish, or was killed by a signal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    /// Why it failed, as in the job's `lastError`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The run history file.
+pub struct RunHistory {
+    path: PathBuf,
+}
+
+impl RunHistory {
+    /// The history kept next to the cron store at `store_path`.
+    pub fn beside(store_path: &Path) -> Self {
+        Self {
+            path: store_path.with_file_name("history.jsonl"),
+        }
+    }
+
+    /// Add `record`, cutting its streams and dropping the oldest runs past
+    /// [`MAX_RECORDS`].
+    pub fn append(&self, mut record: RunRecord) -> Result<()> {
+        record.stdout = truncate_middle(&record.stdout, MAX_STREAM_CHARS);
+        record.stderr = truncate_middle(&record.stderr, MAX_STREAM_CHARS);
+        let mut records = self.load();
+        records.push(record);
+        let excess = records.len().saturating_sub(MAX_RECORDS);
+        records.drain(..excess);
+
+        let mut out = String::new();
+        for r in &records {
+            out.push_str(&serde_json::to_string(r)?);
+            out.push('\n');
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, out)?;
+        Ok(())
+    }
+
+    /// The last `limit` runs, of `job_id` or of every job, newest first.
+    pub fn recent(&self, job_id: Option<&str>, limit: usize) -> Vec<RunRecord> {
+        self.load()
+            .into_iter()
+            .rev()
+            .filter(|r| job_id.is_none_or(|id| r.job_id == id))
+            .take(limit)
+            .collect()
+    }
+
+    fn load(&self) -> Vec<RunRecord> {
+        let Ok(text) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping unreadable cron history line: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// `text` cut to about `max_chars`, keeping its start and end around a note
+/// of how much was left out.
+pub fn truncate_middle(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    let head: String = text.chars().take(max_chars / 2).collect();
+    let tail: String = text.chars().skip(total - max_chars / 2).collect();
+    format!(
+        "{head}\n... ({} chars cut) ...\n{tail}",
+        total - 2 * (max_chars / 2)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_runs_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = RunHistory::beside(&dir.path().join("cron/jobs.json"));
+        assert!(history.recent(None, 10).is_empty());
+
+        for i in 0..MAX_RECORDS + 2 {
+            let job_id = if i % 2 == 0 { "even" } else { "odd" };
+            history
+                .append(RunRecord {
+                    job_id: job_id.to_string(),
+                    started_at_ms: i as i64,
+                    status: "ok".to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        assert!(dir.path().join("cron/history.jsonl").exists());
+        assert_eq!(history.load().len(), MAX_RECORDS);
+        assert_eq!(history.load()[0].started_at_ms, 2);
+
+        let odd = history.recent(Some("odd"), 2);
+        let starts: Vec<i64> = odd.iter().map(|r| r.started_at_ms).collect();
+        assert_eq!(starts, [MAX_RECORDS as i64 + 1, MAX_RECORDS as i64 - 1]);
+    }
+
+    #[test]
+    fn cuts_the_middle_of_long_output() {
+        assert_eq!(truncate_middle("short", 10), "short");
+        let text = format!("start{}end", "é".repeat(100));
+        let cut = truncate_middle(&text, 10);
+        assert_eq!(cut, "start\n... (98 chars cut) ...\nééend");
+    }
+}
@@ -1,5 +1,14 @@
+pub mod cache;
+pub mod digest;
+pub mod history;
 pub mod service;
 pub mod types;
 
-pub use service::CronService;
-pub use types::{CronJob, CronJobState, CronPayload, CronSchedule, ScheduleKind};
+pub use cache::ResponseCache;
+pub use digest::DigestSources;
+pub use history::{RunHistory, RunRecord};
+pub use service::{reply_metadata, CronService};
+pub use types::{
+    CronJob, CronJobState, CronPayload, CronSchedule, DeliverOn, JobDefinition, JobFile,
+    OutputCondition, ScheduleKind,
+};
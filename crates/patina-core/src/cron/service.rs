@@ -1,25 +1,42 @@
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Local, Utc};
 use croner::Cron;
-use tokio::sync::{broadcast, mpsc};
+use patina_config::ExecToolConfig;
+use regex::Regex;
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
 use crate::bus::{InboundMessage, OutboundMessage};
+use crate::cron::cache::ResponseCache;
+use crate::cron::digest::{Digest, DigestSources};
+use crate::cron::history::{RunHistory, RunRecord};
 use crate::cron::types::*;
+use crate::locale::{parse_timezone, UserLocale};
+use crate::lockdown::Lockdown;
+use crate::portable::{check_unique, merge, Imported};
+use crate::tools::shell::shell_command;
+
+/// Payload kinds an import may create.
+const IMPORTABLE_KINDS: &[&str] = &["agent_turn", "exec", "digest", "message"];
 
 /// Service that manages scheduled cron jobs.
 pub struct CronService {
     store_path: PathBuf,
     jobs: Vec<CronJob>,
     timer_handle: Option<JoinHandle<()>>,
+    reply_watcher: Option<JoinHandle<()>>,
+    chain_tx: mpsc::UnboundedSender<ChainedReply>,
+    chain_rx: Arc<Mutex<mpsc::UnboundedReceiver<ChainedReply>>>,
     inbound_tx: mpsc::Sender<InboundMessage>,
     outbound_tx: Option<broadcast::Sender<OutboundMessage>>,
     workspace: Option<PathBuf>,
-    exec_timeout_secs: u64,
+    exec: ExecToolConfig,
+    digest_sources: Option<DigestSources>,
 }
 
 impl CronService {
@@ -34,27 +51,47 @@ impl CronService {
     }
 
     pub fn new(store_path: PathBuf, inbound_tx: mpsc::Sender<InboundMessage>) -> Self {
+        let (chain_tx, chain_rx) = mpsc::unbounded_channel();
         Self {
             store_path,
             jobs: Vec::new(),
             timer_handle: None,
+            reply_watcher: None,
+            chain_tx,
+            chain_rx: Arc::new(Mutex::new(chain_rx)),
             inbound_tx,
             outbound_tx: None,
             workspace: None,
-            exec_timeout_secs: 60,
+            exec: ExecToolConfig::default(),
+            digest_sources: None,
         }
     }
 
-    /// Set the outbound sender and workspace for direct exec jobs.
+    /// Set the outbound sender, workspace and exec settings (timeout, shell)
+    /// for direct exec jobs. Agent replies to jobs that cache them or have a
+    /// chain are read back from the outbound sender.
     pub fn set_exec_context(
         &mut self,
         outbound_tx: broadcast::Sender<OutboundMessage>,
         workspace: PathBuf,
-        exec_timeout_secs: u64,
+        exec: ExecToolConfig,
     ) {
+        if let Some(handle) = self.reply_watcher.take() {
+            handle.abort();
+        }
+        self.reply_watcher = Some(spawn_reply_watcher(
+            ResponseCache::beside(&self.store_path),
+            outbound_tx.subscribe(),
+            self.chain_tx.clone(),
+        ));
         self.outbound_tx = Some(outbound_tx);
         self.workspace = Some(workspace);
-        self.exec_timeout_secs = exec_timeout_secs;
+        self.exec = exec;
+    }
+
+    /// Set where "digest" jobs read activity from. Without this they fail.
+    pub fn set_digest_sources(&mut self, sources: DigestSources) {
+        self.digest_sources = Some(sources);
     }
 
     /// Load jobs from disk and start the timer.
@@ -71,6 +108,9 @@ impl CronService {
             handle.abort();
             info!("Cron service stopped");
         }
+        if let Some(handle) = self.reply_watcher.take() {
+            handle.abort();
+        }
     }
 
     /// List all jobs (optionally including disabled).
@@ -112,6 +152,9 @@ impl CronService {
                 deliver,
                 channel,
                 to,
+                cache_ttl_secs: None,
+                model_tier: None,
+                deliver_on: DeliverOn::Always,
             },
             state: CronJobState {
                 next_run_at_ms: next_run,
@@ -122,6 +165,9 @@ impl CronService {
             created_at_ms: now_ms,
             updated_at_ms: now_ms,
             delete_after_run,
+            on_success: None,
+            on_failure: None,
+            condition: None,
         };
 
         self.jobs.push(job.clone());
@@ -132,6 +178,46 @@ impl CronService {
         Ok(job)
     }
 
+    /// Schedule `content` to be sent to `channel`/`to` at `at_ms`, as a
+    /// one-time "message" job that deletes itself once sent.
+    pub fn schedule_message(
+        &mut self,
+        at_ms: i64,
+        content: &str,
+        channel: &str,
+        to: &str,
+    ) -> Result<CronJob> {
+        let name: String = content.chars().take(30).collect();
+        self.add_job(
+            &name,
+            CronSchedule {
+                kind: ScheduleKind::At,
+                at_ms: Some(at_ms),
+                every_ms: None,
+                expr: None,
+                tz: None,
+            },
+            content,
+            "message",
+            true,
+            Some(channel.to_string()),
+            Some(to.to_string()),
+            true,
+        )
+    }
+
+    /// Messages scheduled with [`schedule_message`](Self::schedule_message)
+    /// that haven't been sent yet, soonest first.
+    pub fn scheduled_messages(&mut self) -> Vec<&CronJob> {
+        let mut jobs: Vec<&CronJob> = self
+            .list_jobs(false)
+            .into_iter()
+            .filter(|j| j.payload.kind == "message" && j.state.next_run_at_ms.is_some())
+            .collect();
+        jobs.sort_by_key(|j| j.state.next_run_at_ms);
+        jobs
+    }
+
     /// Remove a job by ID.
     pub fn remove_job(&mut self, job_id: &str) -> bool {
         self.refresh_from_disk();
@@ -174,201 +260,204 @@ impl CronService {
         }
     }
 
-    /// Execute due jobs (called by the timer).
-    async fn execute_due_jobs(
-        jobs: &mut Vec<CronJob>,
-        store_path: &Path,
-        inbound_tx: &mpsc::Sender<InboundMessage>,
-        outbound_tx: Option<&broadcast::Sender<OutboundMessage>>,
-        workspace: Option<&Path>,
-        exec_timeout_secs: u64,
-    ) {
-        let now_ms = Utc::now().timestamp_millis();
-        let mut jobs_to_delete = Vec::new();
-
-        for job in jobs.iter_mut() {
-            if !job.enabled {
-                continue;
-            }
-            let next = match job.state.next_run_at_ms {
-                Some(t) => t,
-                None => continue,
-            };
-            if now_ms < next {
-                continue;
-            }
+    /// Set how long an agent-turn job reuses the day's reply, or stop it
+    /// caching with `None`.
+    pub fn set_cache_ttl(&mut self, job_id: &str, ttl_secs: Option<u64>) -> Option<&CronJob> {
+        self.refresh_from_disk();
+        let job = self.jobs.iter_mut().find(|j| j.id == job_id)?;
+        job.payload.cache_ttl_secs = ttl_secs.filter(|&ttl| ttl > 0);
+        job.updated_at_ms = Utc::now().timestamp_millis();
+        if let Err(e) = self.save() {
+            warn!("Failed to persist cron store after updating job {job_id}: {e}");
+        }
+        self.arm_timer();
+        self.jobs.iter().find(|j| j.id == job_id)
+    }
 
-            info!("Executing cron job '{}' (id: {})", job.name, job.id);
+    /// Set the model tier an agent-turn job is answered with.
+    pub fn set_model_tier(&mut self, job_id: &str, tier: Option<String>) -> Option<&CronJob> {
+        self.refresh_from_disk();
+        let job = self.jobs.iter_mut().find(|j| j.id == job_id)?;
+        job.payload.model_tier = tier.filter(|t| !t.is_empty());
+        job.updated_at_ms = Utc::now().timestamp_millis();
+        if let Err(e) = self.save() {
+            warn!("Failed to persist cron store after updating job {job_id}: {e}");
+        }
+        self.arm_timer();
+        self.jobs.iter().find(|j| j.id == job_id)
+    }
 
-            // Direct exec: run command and send output to channel, no LLM
-            if job.payload.kind == "exec" {
-                let cwd = workspace
-                    .map(|p| p.to_path_buf())
-                    .unwrap_or_else(|| PathBuf::from("."));
-
-                let result = tokio::time::timeout(
-                    std::time::Duration::from_secs(exec_timeout_secs),
-                    tokio::process::Command::new("sh")
-                        .arg("-c")
-                        .arg(&job.payload.message)
-                        .current_dir(&cwd)
-                        .output(),
-                )
-                .await;
-
-                let output = match result {
-                    Ok(Ok(out)) => {
-                        let stdout = String::from_utf8_lossy(&out.stdout);
-                        let stderr = String::from_utf8_lossy(&out.stderr);
-                        let mut text = stdout.to_string();
-                        if !stderr.trim().is_empty() {
-                            if !text.is_empty() {
-                                text.push('\n');
-                            }
-                            text.push_str(&format!("STDERR: {stderr}"));
-                        }
-                        if !out.status.success() {
-                            text.push_str(&format!(
-                                "\nExit code: {}",
-                                out.status.code().unwrap_or(-1)
-                            ));
-                        }
-                        if text.is_empty() {
-                            text = "(no output)".to_string();
-                        }
-                        job.state.last_status = Some("ok".to_string());
-                        job.state.last_error = None;
-                        text
-                    }
-                    Ok(Err(e)) => {
-                        let msg = format!("Exec error: {e}");
-                        job.state.last_status = Some("error".to_string());
-                        job.state.last_error = Some(msg.clone());
-                        msg
-                    }
-                    Err(_) => {
-                        let msg = format!("Exec timed out after {exec_timeout_secs}s");
-                        job.state.last_status = Some("error".to_string());
-                        job.state.last_error = Some(msg.clone());
-                        msg
-                    }
-                };
+    /// Set which runs of an exec job are delivered.
+    pub fn set_deliver_on(&mut self, job_id: &str, deliver_on: DeliverOn) -> Option<&CronJob> {
+        self.refresh_from_disk();
+        let job = self.jobs.iter_mut().find(|j| j.id == job_id)?;
+        job.payload.deliver_on = deliver_on;
+        job.updated_at_ms = Utc::now().timestamp_millis();
+        if let Err(e) = self.save() {
+            warn!("Failed to persist cron store after updating job {job_id}: {e}");
+        }
+        self.arm_timer();
+        self.jobs.iter().find(|j| j.id == job_id)
+    }
 
-                // Deliver output to channel if configured
-                if job.payload.deliver {
-                    if let (Some(tx), Some(channel), Some(to)) =
-                        (outbound_tx, &job.payload.channel, &job.payload.to)
-                    {
-                        if let Err(e) = tx.send(OutboundMessage {
-                            channel: channel.clone(),
-                            chat_id: to.clone(),
-                            content: output,
-                            reply_to: None,
-                            metadata: HashMap::new(),
-                        }) {
-                            warn!("Failed to deliver cron exec output: {e}");
-                        }
-                    } else {
-                        warn!(
-                            "Cron job '{}' has deliver=true but missing channel/to or outbound_tx",
-                            job.name
-                        );
-                    }
-                }
+    /// The last `limit` recorded exec runs, of `job_id` or of every job,
+    /// newest first.
+    pub fn runs(&self, job_id: Option<&str>, limit: usize) -> Vec<RunRecord> {
+        RunHistory::beside(&self.store_path).recent(job_id, limit)
+    }
 
-                job.state.last_run_at_ms = Some(now_ms);
-                job.updated_at_ms = now_ms;
-            } else {
-                // agent_turn: send through inbound bus for LLM processing
-                let channel = job
-                    .payload
-                    .channel
-                    .clone()
-                    .unwrap_or_else(|| "system".to_string());
-                let chat_id = job.payload.to.clone().unwrap_or_else(|| "cron".to_string());
-
-                let msg = InboundMessage {
-                    channel: channel.clone(),
-                    sender_id: "cron".to_string(),
-                    chat_id,
-                    content: job.payload.message.clone(),
-                    media: Vec::new(),
-                    timestamp: crate::bus::default_timestamp(),
-                    metadata: {
-                        let mut m = HashMap::new();
-                        m.insert(
-                            "cron_job_id".to_string(),
-                            serde_json::Value::String(job.id.clone()),
-                        );
-                        m.insert(
-                            "cron_job_name".to_string(),
-                            serde_json::Value::String(job.name.clone()),
-                        );
-                        m
-                    },
-                };
+    /// Set the jobs that run after `job_id` and the condition its output has
+    /// to meet for `on_success`, replacing any it had. Fails if a job
+    /// doesn't exist or the regex doesn't compile.
+    pub fn set_chain(
+        &mut self,
+        job_id: &str,
+        on_success: Option<String>,
+        on_failure: Option<String>,
+        condition: Option<OutputCondition>,
+    ) -> Result<CronJob> {
+        self.refresh_from_disk();
+        for next in on_success.iter().chain(&on_failure) {
+            if !self.jobs.iter().any(|j| &j.id == next) {
+                anyhow::bail!("No cron job {next} to chain to");
+            }
+        }
+        if let Some(pattern) = condition.as_ref().and_then(|c| c.regex.as_deref()) {
+            Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid regex '{pattern}': {e}"))?;
+        }
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|j| j.id == job_id)
+            .ok_or_else(|| anyhow::anyhow!("No cron job {job_id}"))?;
+        job.on_success = on_success;
+        job.on_failure = on_failure;
+        job.condition = condition.filter(|c| *c != OutputCondition::default());
+        job.updated_at_ms = Utc::now().timestamp_millis();
+        let job = job.clone();
+        self.save()?;
+        self.arm_timer();
+        Ok(job)
+    }
 
-                if let Err(e) = inbound_tx.send(msg).await {
-                    warn!("Failed to send cron job message: {e}");
-                    job.state.last_status = Some("error".to_string());
-                    job.state.last_error = Some(format!("Failed to send: {e}"));
-                } else {
-                    job.state.last_status = Some("ok".to_string());
-                    job.state.last_error = None;
-                }
+    /// Every job's definition, for `patina cron export`. Standup jobs are
+    /// left out, since they're made from the `standups` config.
+    pub fn export_jobs(&mut self) -> JobFile {
+        JobFile {
+            version: 1,
+            jobs: self
+                .list_jobs(true)
+                .into_iter()
+                .filter(|j| j.payload.kind != "standup")
+                .map(Into::into)
+                .collect(),
+        }
+    }
 
-                job.state.last_run_at_ms = Some(now_ms);
-                job.updated_at_ms = now_ms;
+    /// Add and update jobs from `file`, and with `replace` remove the ones
+    /// it doesn't list, other than standup jobs. Updated jobs keep their run
+    /// state. Nothing changes
+    /// unless every definition is valid: a known payload kind, a schedule
+    /// that parses, chains to jobs that will exist, and regexes that compile.
+    pub fn import_jobs(&mut self, file: JobFile, replace: bool) -> Result<Imported> {
+        self.refresh_from_disk();
+        let now_ms = Utc::now().timestamp_millis();
+        let mut definitions = file.jobs;
+        for def in &mut definitions {
+            if def.id.is_empty() {
+                def.id = uuid::Uuid::new_v4().to_string()[..8].to_string();
             }
+        }
+        check_unique(definitions.iter().map(|d| d.id.as_str()))?;
 
-            // Handle one-time jobs
-            if job.schedule.kind == ScheduleKind::At {
-                if job.delete_after_run {
-                    jobs_to_delete.push(job.id.clone());
+        let (mut jobs, standups): (Vec<CronJob>, Vec<CronJob>) = self
+            .jobs
+            .iter()
+            .cloned()
+            .partition(|j| j.payload.kind != "standup");
+        let incoming = definitions
+            .into_iter()
+            .map(|def| {
+                let name = if def.name.trim().is_empty() {
+                    &def.id
                 } else {
-                    job.enabled = false;
-                    job.state.next_run_at_ms = None;
+                    &def.name
+                };
+                let context = format!("Cron job '{name}'");
+                if !IMPORTABLE_KINDS.contains(&def.payload.kind.as_str()) {
+                    anyhow::bail!(
+                        "{context}: unknown payload kind '{}' (expected {})",
+                        def.payload.kind,
+                        IMPORTABLE_KINDS.join(", ")
+                    );
                 }
-            } else {
-                // Recompute next run for recurring jobs
-                job.state.next_run_at_ms = match compute_next_run(&job.schedule, now_ms) {
-                    Ok(next) => next,
-                    Err(e) => {
-                        warn!("Failed to compute next run for cron job {}: {e}", job.id);
-                        None
-                    }
+                if let Some(pattern) = def.condition.as_ref().and_then(|c| c.regex.as_deref()) {
+                    Regex::new(pattern).map_err(|e| {
+                        anyhow::anyhow!("{context}: invalid regex '{pattern}': {e}")
+                    })?;
+                }
+                let next_run = compute_next_run(&def.schedule, now_ms)
+                    .map_err(|e| anyhow::anyhow!("{context}: {e}"))?;
+                Ok(CronJob {
+                    id: def.id,
+                    name: def.name.chars().take(30).collect(),
+                    enabled: def.enabled,
+                    schedule: def.schedule,
+                    payload: def.payload,
+                    state: CronJobState {
+                        next_run_at_ms: next_run.filter(|_| def.enabled),
+                        ..Default::default()
+                    },
+                    created_at_ms: now_ms,
+                    updated_at_ms: now_ms,
+                    delete_after_run: def.delete_after_run,
+                    on_success: def.on_success,
+                    on_failure: def.on_failure,
+                    condition: def.condition,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let imported = merge(
+            &mut jobs,
+            incoming,
+            replace,
+            |j| &j.id,
+            |job, new| {
+                *job = CronJob {
+                    state: CronJobState {
+                        next_run_at_ms: new.state.next_run_at_ms,
+                        ..job.state.clone()
+                    },
+                    created_at_ms: job.created_at_ms,
+                    ..new
                 };
-            }
-        }
-
-        // Delete one-time jobs that requested it
-        jobs.retain(|j| !jobs_to_delete.contains(&j.id));
-
-        // Save updated state
-        let store = CronStore {
-            version: 1,
-            jobs: jobs.clone(),
-        };
-        match serde_json::to_string_pretty(&store) {
-            Ok(json) => {
-                if let Err(e) = std::fs::write(store_path, json) {
-                    warn!(
-                        "Failed to persist cron store '{}': {e}",
-                        store_path.display()
+            },
+        );
+        jobs.extend(standups);
+        for job in &jobs {
+            for next in job.on_success.iter().chain(&job.on_failure) {
+                if !jobs.iter().any(|j| &j.id == next) {
+                    anyhow::bail!(
+                        "Cron job '{}' chains to {next}, which doesn't exist",
+                        job.name
                     );
                 }
             }
-            Err(e) => {
-                warn!("Failed to serialize cron store: {e}");
-            }
         }
+
+        self.jobs = jobs;
+        self.save()?;
+        self.arm_timer();
+        info!("Imported cron jobs: {imported}");
+        Ok(imported)
     }
 
     /// Arm the timer to wake at the next due job.
     ///
     /// Spawns a background loop that sleeps until the next job is due,
-    /// executes it, and re-arms for the next one. The loop continues
-    /// until there are no more enabled jobs with a next_run_at_ms.
+    /// executes it, and re-arms for the next one. Agent replies to chained
+    /// jobs wake it too, to run the next step, so it keeps waiting for them
+    /// when nothing is scheduled.
     fn arm_timer(&mut self) {
         // Cancel existing timer
         if let Some(handle) = self.timer_handle.take() {
@@ -376,11 +465,17 @@ impl CronService {
         }
 
         let mut jobs = self.jobs.clone();
-        let store_path = self.store_path.clone();
-        let inbound_tx = self.inbound_tx.clone();
-        let outbound_tx = self.outbound_tx.clone();
-        let workspace = self.workspace.clone();
-        let exec_timeout_secs = self.exec_timeout_secs;
+        let runner = JobRunner {
+            store_path: self.store_path.clone(),
+            inbound_tx: self.inbound_tx.clone(),
+            outbound_tx: self.outbound_tx.clone(),
+            workspace: self.workspace.clone(),
+            exec: self.exec.clone(),
+            digest_sources: self.digest_sources.clone(),
+            cache: ResponseCache::beside(&self.store_path),
+            history: RunHistory::beside(&self.store_path),
+        };
+        let replies = self.chain_rx.clone();
 
         self.timer_handle = Some(tokio::spawn(async move {
             loop {
@@ -391,33 +486,29 @@ impl CronService {
                     .filter(|j| j.enabled)
                     .filter_map(|j| j.state.next_run_at_ms)
                     .min();
-
-                let sleep_ms = match earliest {
-                    Some(t) if t > now_ms => (t - now_ms) as u64,
-                    Some(_) => 0,  // Already due
-                    None => break, // No jobs to schedule — exit loop
+                let due = async {
+                    match earliest {
+                        Some(t) if t > now_ms => {
+                            tokio::time::sleep(tokio::time::Duration::from_millis(
+                                (t - now_ms) as u64,
+                            ))
+                            .await
+                        }
+                        Some(_) => {}                         // Already due
+                        None => std::future::pending().await, // Only a chain can wake it
+                    }
                 };
 
-                if sleep_ms > 0 {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(sleep_ms)).await;
-                }
-
-                Self::execute_due_jobs(
-                    &mut jobs,
-                    &store_path,
-                    &inbound_tx,
-                    outbound_tx.as_ref(),
-                    workspace.as_deref(),
-                    exec_timeout_secs,
-                )
-                .await;
-
-                // If no enabled jobs remain, exit the loop
-                let has_scheduled = jobs
-                    .iter()
-                    .any(|j| j.enabled && j.state.next_run_at_ms.is_some());
-                if !has_scheduled {
-                    break;
+                let reply = {
+                    let mut replies = replies.lock().await;
+                    tokio::select! {
+                        _ = due => None,
+                        Some(reply) = replies.recv() => Some(reply),
+                    }
+                };
+                match reply {
+                    None => runner.execute_due_jobs(&mut jobs).await,
+                    Some(reply) => runner.continue_chain(&mut jobs, reply).await,
                 }
             }
         }));
@@ -458,6 +549,509 @@ impl CronService {
     }
 }
 
+/// Most steps a chain runs, so jobs chaining to each other in a loop stop.
+const MAX_CHAIN_DEPTH: usize = 10;
+
+/// Replaced by the previous step's output in a chained job's message.
+pub const OUTPUT_PLACEHOLDER: &str = "{{output}}";
+
+/// A chained job waiting to run, with the output of the step before it.
+struct ChainStep {
+    job_id: String,
+    input: String,
+    depth: usize,
+}
+
+/// The agent's reply to a job with a chain, read back by the reply watcher.
+struct ChainedReply {
+    job_id: String,
+    output: String,
+    depth: usize,
+    /// Whether the turn succeeded, from the reply's `cron_ok`.
+    succeeded: bool,
+}
+
+/// The metadata for the reply to an inbound message carrying `metadata`:
+/// the same, plus `cron_ok` saying whether the turn succeeded when it was a
+/// cron job's, which the reply watcher needs to cache and chain it.
+pub fn reply_metadata(
+    metadata: &HashMap<String, serde_json::Value>,
+    succeeded: bool,
+) -> HashMap<String, serde_json::Value> {
+    let mut metadata = metadata.clone();
+    if metadata.contains_key("cron_job_id") {
+        metadata.insert("cron_ok".to_string(), serde_json::Value::Bool(succeeded));
+    }
+    metadata
+}
+
+/// The step after `job` finished with `output`, if its chain has one.
+fn next_step(job: &CronJob, succeeded: bool, output: String, depth: usize) -> Option<ChainStep> {
+    job.next_job(succeeded, &output).map(|next| ChainStep {
+        job_id: next.to_string(),
+        input: output,
+        depth,
+    })
+}
+
+/// Digests report on the other jobs, which are borrowed mutably while
+/// running, so they get a copy.
+fn digest_snapshot(jobs: &[CronJob]) -> Vec<CronJob> {
+    if jobs.iter().any(|j| j.payload.kind == "digest") {
+        jobs.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+/// What the timer runs jobs with.
+struct JobRunner {
+    store_path: PathBuf,
+    inbound_tx: mpsc::Sender<InboundMessage>,
+    outbound_tx: Option<broadcast::Sender<OutboundMessage>>,
+    workspace: Option<PathBuf>,
+    exec: ExecToolConfig,
+    digest_sources: Option<DigestSources>,
+    cache: ResponseCache,
+    history: RunHistory,
+}
+
+impl JobRunner {
+    /// Execute due jobs and the jobs they chain to.
+    async fn execute_due_jobs(&self, jobs: &mut Vec<CronJob>) {
+        let now_ms = Utc::now().timestamp_millis();
+        let mut jobs_to_delete = Vec::new();
+        let mut steps = VecDeque::new();
+        let snapshot = digest_snapshot(jobs);
+
+        for job in jobs.iter_mut() {
+            if !job.enabled {
+                continue;
+            }
+            let next = match job.state.next_run_at_ms {
+                Some(t) => t,
+                None => continue,
+            };
+            if now_ms < next {
+                continue;
+            }
+
+            info!("Executing cron job '{}' (id: {})", job.name, job.id);
+            let message = job.payload.message.clone();
+            if let Some(output) = self.run_job(job, &message, &snapshot, now_ms, 0).await {
+                let succeeded = job.state.last_status.as_deref() == Some("ok");
+                steps.extend(next_step(job, succeeded, output, 1));
+            }
+
+            // Handle one-time jobs
+            if job.schedule.kind == ScheduleKind::At {
+                if job.delete_after_run {
+                    jobs_to_delete.push(job.id.clone());
+                } else {
+                    job.enabled = false;
+                    job.state.next_run_at_ms = None;
+                }
+            } else {
+                // Recompute next run for recurring jobs
+                job.state.next_run_at_ms = match compute_next_run(&job.schedule, now_ms) {
+                    Ok(next) => next,
+                    Err(e) => {
+                        warn!("Failed to compute next run for cron job {}: {e}", job.id);
+                        None
+                    }
+                };
+            }
+        }
+
+        // Delete one-time jobs that requested it
+        jobs.retain(|j| !jobs_to_delete.contains(&j.id));
+
+        self.run_chain(jobs, steps, &snapshot, now_ms).await;
+        self.persist(jobs);
+    }
+
+    /// Carry on a chain from the agent's reply to one of its jobs.
+    async fn continue_chain(&self, jobs: &mut [CronJob], reply: ChainedReply) {
+        let Some(job) = jobs.iter_mut().find(|j| j.id == reply.job_id) else {
+            warn!("Cron job {} replied to is gone", reply.job_id);
+            return;
+        };
+        // Queuing the turn counted as "ok"; the reply says how it went
+        if !reply.succeeded {
+            job.state.last_status = Some("error".to_string());
+            job.state.last_error = Some(reply.output.clone());
+        }
+        let steps = next_step(job, reply.succeeded, reply.output, reply.depth + 1)
+            .into_iter()
+            .collect();
+        let snapshot = digest_snapshot(jobs);
+        self.run_chain(jobs, steps, &snapshot, Utc::now().timestamp_millis())
+            .await;
+        self.persist(jobs);
+    }
+
+    /// Run chained jobs, whatever their schedule, and the jobs they chain
+    /// to in turn. Disabled jobs break the chain.
+    async fn run_chain(
+        &self,
+        jobs: &mut [CronJob],
+        mut steps: VecDeque<ChainStep>,
+        snapshot: &[CronJob],
+        now_ms: i64,
+    ) {
+        while let Some(step) = steps.pop_front() {
+            if step.depth > MAX_CHAIN_DEPTH {
+                warn!(
+                    "Stopped a cron chain before job {}: more than {MAX_CHAIN_DEPTH} steps",
+                    step.job_id
+                );
+                continue;
+            }
+            let Some(job) = jobs.iter_mut().find(|j| j.id == step.job_id) else {
+                warn!("Cron chain step {} doesn't exist", step.job_id);
+                continue;
+            };
+            if !job.enabled {
+                info!("Skipped disabled cron job '{}' in a chain", job.name);
+                continue;
+            }
+            info!(
+                "Executing cron job '{}' (id: {}) in a chain",
+                job.name, job.id
+            );
+            let message = job.payload.message.replace(OUTPUT_PLACEHOLDER, &step.input);
+            if let Some(output) = self
+                .run_job(job, &message, snapshot, now_ms, step.depth)
+                .await
+            {
+                let succeeded = job.state.last_status.as_deref() == Some("ok");
+                steps.extend(next_step(job, succeeded, output, step.depth + 1));
+            }
+        }
+    }
+
+    /// Run one job with `message` and record how it went. Returns its
+    /// output, or `None` for an agent turn, whose reply comes later.
+    async fn run_job(
+        &self,
+        job: &mut CronJob,
+        message: &str,
+        snapshot: &[CronJob],
+        now_ms: i64,
+        depth: usize,
+    ) -> Option<String> {
+        let outbound_tx = self.outbound_tx.as_ref();
+        let previous_run_ms = job.state.last_run_at_ms;
+        job.state.last_run_at_ms = Some(now_ms);
+        job.updated_at_ms = now_ms;
+
+        // Lockdown stops commands and outgoing messages. Agent turns
+        // still run, with the locked tools refused by the middleware.
+        if matches!(job.payload.kind.as_str(), "exec" | "message") && Lockdown::new().is_on() {
+            warn!("Skipped cron job '{}': lockdown is on", job.name);
+            let error = "Skipped: lockdown is on".to_string();
+            job.state.last_status = Some("error".to_string());
+            job.state.last_error = Some(error.clone());
+            Some(error)
+        } else if job.payload.kind == "exec" {
+            // Direct exec: run command and send output to channel, no LLM
+            let cwd = self.workspace.clone().unwrap_or_else(|| PathBuf::from("."));
+            let started = std::time::Instant::now();
+
+            let result = tokio::time::timeout(
+                std::time::Duration::from_secs(self.exec.timeout_secs),
+                shell_command(self.exec.shell.as_deref(), message)
+                    .current_dir(&cwd)
+                    .output(),
+            )
+            .await;
+
+            let mut record = RunRecord {
+                job_id: job.id.clone(),
+                job_name: job.name.clone(),
+                started_at_ms: now_ms,
+                ..Default::default()
+            };
+            let output = match result {
+                Ok(Ok(out)) => {
+                    record.stdout = String::from_utf8_lossy(&out.stdout).into_owned();
+                    record.stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+                    record.exit_code = out.status.code();
+                    let mut text = record.stdout.clone();
+                    if !record.stderr.trim().is_empty() {
+                        if !text.is_empty() {
+                            text.push('\n');
+                        }
+                        text.push_str(&format!("STDERR: {}", record.stderr));
+                    }
+                    if !out.status.success() {
+                        let code = format!("Exit code: {}", out.status.code().unwrap_or(-1));
+                        text.push_str(&format!("\n{code}"));
+                        record.error = Some(code);
+                    }
+                    if text.is_empty() {
+                        text = "(no output)".to_string();
+                    }
+                    text
+                }
+                Ok(Err(e)) => {
+                    let msg = format!("Exec error: {e}");
+                    record.error = Some(msg.clone());
+                    msg
+                }
+                Err(_) => {
+                    let msg = format!("Exec timed out after {}s", self.exec.timeout_secs);
+                    record.error = Some(msg.clone());
+                    msg
+                }
+            };
+            let failed = record.error.is_some();
+            record.status = if failed { "error" } else { "ok" }.to_string();
+            record.duration_ms = started.elapsed().as_millis() as u64;
+            job.state.last_status = Some(record.status.clone());
+            job.state.last_error = record.error.clone();
+            if let Err(e) = self.history.append(record) {
+                warn!("Failed to record cron job '{}' run: {e}", job.name);
+            }
+
+            // Deliver output to channel if configured
+            if job.payload.deliver && job.payload.deliver_on.allows(failed) {
+                let content = match job.payload.deliver_on {
+                    DeliverOn::Failure => format!("Cron job '{}' failed:\n{output}", job.name),
+                    DeliverOn::Always => output.clone(),
+                };
+                deliver(job, outbound_tx, content);
+            }
+            Some(output)
+        } else if job.payload.kind == "message" {
+            // Scheduled message: send the text as-is, no LLM
+            if deliver(job, outbound_tx, message.to_string()) {
+                job.state.last_status = Some("ok".to_string());
+                job.state.last_error = None;
+            } else {
+                job.state.last_status = Some("error".to_string());
+                job.state.last_error = Some("Message could not be delivered".to_string());
+            }
+            Some(message.to_string())
+        } else if job.payload.kind == "digest" {
+            // Digest: summarize activity since the last run, no LLM
+            let Some(sources) = self.digest_sources.as_ref() else {
+                warn!(
+                    "Cron job '{}' is a digest but no digest sources are set",
+                    job.name
+                );
+                let error = "Digests are not available".to_string();
+                job.state.last_status = Some("error".to_string());
+                job.state.last_error = Some(error.clone());
+                return Some(error);
+            };
+            let since_ms = previous_run_ms.unwrap_or(job.created_at_ms);
+            let digest = Digest::gather(sources, snapshot, &job.id, since_ms, now_ms);
+            let title = match message.trim() {
+                "" => job.name.clone(),
+                title => title.to_string(),
+            };
+            // The zone it was scheduled in, which is its chat's
+            let locale = UserLocale::new(job.schedule.tz.as_deref(), None);
+            let rendered = digest.render(&title, &locale);
+            if deliver(job, outbound_tx, rendered.clone()) {
+                job.state.last_status = Some("ok".to_string());
+                job.state.last_error = None;
+            } else {
+                job.state.last_status = Some("error".to_string());
+                job.state.last_error = Some("Digest could not be delivered".to_string());
+            }
+            Some(rendered)
+        } else if let Some(reply) = cached_reply(job, message, &self.cache, now_ms) {
+            // The same prompt was answered recently today. It goes where
+            // the agent's reply would have, if that's a chat.
+            info!("Cron job '{}' reused its cached reply", job.name);
+            if job.payload.channel.is_none() || deliver(job, outbound_tx, reply.clone()) {
+                job.state.last_status = Some("ok".to_string());
+                job.state.last_error = None;
+            } else {
+                job.state.last_status = Some("error".to_string());
+                job.state.last_error = Some("Cached reply could not be delivered".to_string());
+            }
+            Some(reply)
+        } else {
+            // agent_turn (and standup, on its own channel): send through
+            // inbound bus for LLM processing
+            let channel = job
+                .payload
+                .channel
+                .clone()
+                .unwrap_or_else(|| "system".to_string());
+            let chat_id = job.payload.to.clone().unwrap_or_else(|| "cron".to_string());
+
+            let msg = InboundMessage {
+                channel: channel.clone(),
+                sender_id: "cron".to_string(),
+                chat_id,
+                content: message.to_string(),
+                media: Vec::new(),
+                timestamp: crate::bus::default_timestamp(),
+                metadata: {
+                    let mut m = HashMap::new();
+                    m.insert(
+                        "cron_job_id".to_string(),
+                        serde_json::Value::String(job.id.clone()),
+                    );
+                    m.insert(
+                        "cron_job_name".to_string(),
+                        serde_json::Value::String(job.name.clone()),
+                    );
+                    // Read back from the reply by the reply watcher
+                    if let Some(key) = cache_key(job, message, now_ms) {
+                        m.insert("cron_cache_key".to_string(), serde_json::Value::String(key));
+                    }
+                    if job.has_chain() {
+                        m.insert("cron_chain_depth".to_string(), serde_json::json!(depth));
+                    }
+                    if let Some(tier) = &job.payload.model_tier {
+                        m.insert("model_tier".to_string(), serde_json::json!(tier));
+                    }
+                    m
+                },
+            };
+
+            if let Err(e) = self.inbound_tx.send(msg).await {
+                warn!("Failed to send cron job message: {e}");
+                let error = format!("Failed to send: {e}");
+                job.state.last_status = Some("error".to_string());
+                job.state.last_error = Some(error.clone());
+                return Some(error);
+            }
+            job.state.last_status = Some("ok".to_string());
+            job.state.last_error = None;
+            None
+        }
+    }
+
+    /// Save the timer's copy of the jobs.
+    fn persist(&self, jobs: &[CronJob]) {
+        let store = CronStore {
+            version: 1,
+            jobs: jobs.to_vec(),
+        };
+        match serde_json::to_string_pretty(&store) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&self.store_path, json) {
+                    warn!(
+                        "Failed to persist cron store '{}': {e}",
+                        self.store_path.display()
+                    );
+                }
+            }
+            Err(e) => {
+                warn!("Failed to serialize cron store: {e}");
+            }
+        }
+    }
+}
+
+/// The cache key for an agent-turn job run at `now_ms` with `message`: the
+/// message and the day on its clock. `None` if the job doesn't cache.
+fn cache_key(job: &CronJob, message: &str, now_ms: i64) -> Option<String> {
+    if job.payload.kind != "agent_turn" || job.payload.cache_ttl_secs.is_none() {
+        return None;
+    }
+    let now = DateTime::from_timestamp_millis(now_ms)?;
+    let day = UserLocale::new(job.schedule.tz.as_deref(), None)
+        .localize(now)
+        .date_naive();
+    Some(ResponseCache::key(message, day))
+}
+
+/// The job's cached reply to `message`, if it caches and has a fresh one.
+fn cached_reply(
+    job: &CronJob,
+    message: &str,
+    cache: &ResponseCache,
+    now_ms: i64,
+) -> Option<String> {
+    let key = cache_key(job, message, now_ms)?;
+    cache.get(&key, job.payload.cache_ttl_secs?, now_ms)
+}
+
+/// Watch for the agent's replies to cron jobs: store them for jobs that
+/// cache them, recognized by the `cron_cache_key` the job's message
+/// carried, and hand them to the timer for jobs with a chain, recognized by
+/// `cron_chain_depth`. Replies whose `cron_ok` isn't `true` are failed
+/// turns: they aren't cached and take the chain's failure path.
+fn spawn_reply_watcher(
+    cache: ResponseCache,
+    mut outbound_rx: broadcast::Receiver<OutboundMessage>,
+    chain_tx: mpsc::UnboundedSender<ChainedReply>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let msg = match outbound_rx.recv().await {
+                Ok(msg) => msg,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Cron reply watcher missed {n} outbound messages");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            let text = |key: &str| msg.metadata.get(key).and_then(|v| v.as_str());
+            let Some(job_id) = text("cron_job_id") else {
+                continue;
+            };
+            let succeeded = msg.metadata.get("cron_ok").and_then(|v| v.as_bool()) == Some(true);
+            if let (Some(key), true) = (text("cron_cache_key"), succeeded) {
+                let now_ms = Utc::now().timestamp_millis();
+                if let Err(e) = cache.put(key, job_id, &msg.content, now_ms) {
+                    warn!("Failed to cache the reply to cron job {job_id}: {e}");
+                }
+            }
+            if let Some(depth) = msg
+                .metadata
+                .get("cron_chain_depth")
+                .and_then(|v| v.as_u64())
+            {
+                let _ = chain_tx.send(ChainedReply {
+                    job_id: job_id.to_string(),
+                    output: msg.content.clone(),
+                    depth: depth as usize,
+                    succeeded,
+                });
+            }
+        }
+    })
+}
+
+/// Send a job's output to its channel, naming the job in the metadata like
+/// agent replies to it. Returns `false` if it had nowhere to go.
+fn deliver(
+    job: &CronJob,
+    outbound_tx: Option<&broadcast::Sender<OutboundMessage>>,
+    content: String,
+) -> bool {
+    let (Some(tx), Some(channel), Some(to)) = (outbound_tx, &job.payload.channel, &job.payload.to)
+    else {
+        warn!(
+            "Cron job '{}' has nowhere to deliver (missing channel/to or outbound_tx)",
+            job.name
+        );
+        return false;
+    };
+    if let Err(e) = tx.send(OutboundMessage {
+        channel: channel.clone(),
+        chat_id: to.clone(),
+        content,
+        reply_to: None,
+        metadata: HashMap::from([
+            ("cron_job_id".to_string(), serde_json::json!(job.id)),
+            ("cron_job_name".to_string(), serde_json::json!(job.name)),
+        ]),
+    }) {
+        warn!("Failed to deliver cron job '{}' output: {e}", job.name);
+        return false;
+    }
+    true
+}
+
 /// Compute the next run time for a schedule (public for testing).
 pub(crate) fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Result<Option<i64>> {
     match schedule.kind {
@@ -488,10 +1082,19 @@ pub(crate) fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Result<O
 
             let now = chrono::DateTime::from_timestamp_millis(now_ms).unwrap_or_else(Utc::now);
 
-            match cron.find_next_occurrence(&now, false) {
-                Ok(next) => Ok(Some(next.timestamp_millis())),
-                Err(_) => Ok(None),
-            }
+            // On the schedule's clock, so "0 9 * * *" is 9:00 where the user is
+            let next = match schedule.tz.as_deref() {
+                Some(name) => {
+                    let tz = parse_timezone(name)
+                        .ok_or_else(|| anyhow::anyhow!("unknown timezone '{name}'"))?;
+                    cron.find_next_occurrence(&now.with_timezone(&tz), false)
+                        .map(|next| next.timestamp_millis())
+                }
+                None => cron
+                    .find_next_occurrence(&now.with_timezone(&Local), false)
+                    .map(|next| next.timestamp_millis()),
+            };
+            Ok(next.ok())
         }
     }
 }
@@ -575,6 +1178,28 @@ mod tests {
         assert!(result.unwrap() > now_ms());
     }
 
+    #[test]
+    fn test_cron_schedule_uses_timezone() {
+        let mut schedule = CronSchedule {
+            kind: ScheduleKind::Cron,
+            at_ms: None,
+            every_ms: None,
+            expr: Some("0 9 * * *".into()),
+            tz: Some("Asia/Tokyo".into()),
+        };
+        // 21:00 in Tokyo, so the next 9:00 there is midnight UTC
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-05T12:00:00Z")
+            .unwrap()
+            .timestamp_millis();
+        let next = chrono::DateTime::parse_from_rfc3339("2026-03-06T00:00:00Z")
+            .unwrap()
+            .timestamp_millis();
+        assert_eq!(compute_next_run(&schedule, now).unwrap(), Some(next));
+
+        schedule.tz = Some("Nowhere/Special".into());
+        assert!(compute_next_run(&schedule, now).is_err());
+    }
+
     #[test]
     fn test_cron_schedule_invalid_expr() {
         let schedule = CronSchedule {
@@ -703,6 +1328,371 @@ mod tests {
         assert!(!svc.remove_job("nonexistent"));
     }
 
+    #[tokio::test]
+    async fn test_scheduled_message_is_sent_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(out_tx, dir.path().to_path_buf(), ExecToolConfig::default());
+
+        let at_ms = now_ms() + 100;
+        let job = svc
+            .schedule_message(at_ms, "Time to stretch", "telegram", "42")
+            .unwrap();
+        assert_eq!(svc.scheduled_messages().len(), 1);
+
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (sent.channel.as_str(), sent.chat_id.as_str()),
+            ("telegram", "42")
+        );
+        assert_eq!(sent.content, "Time to stretch");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(svc.scheduled_messages().is_empty());
+        assert!(!svc.remove_job(&job.id));
+    }
+
+    #[tokio::test]
+    async fn test_exec_runs_are_recorded_and_failures_delivered() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(out_tx, dir.path().to_path_buf(), ExecToolConfig::default());
+
+        for (name, command, ms) in [
+            ("healthy", "echo fine", 100),
+            ("broken", "echo bad >&2; exit 3", 300),
+        ] {
+            let schedule = CronSchedule {
+                kind: ScheduleKind::At,
+                at_ms: Some(now_ms() + ms),
+                every_ms: None,
+                expr: None,
+                tz: None,
+            };
+            let job = svc
+                .add_job(
+                    name,
+                    schedule,
+                    command,
+                    "exec",
+                    true,
+                    Some("telegram".into()),
+                    Some("42".into()),
+                    false,
+                )
+                .unwrap();
+            svc.set_deliver_on(&job.id, DeliverOn::Failure).unwrap();
+        }
+
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(sent
+            .content
+            .starts_with("Cron job 'broken' failed:\nSTDERR: bad"));
+        assert!(sent.content.ends_with("Exit code: 3"));
+
+        let runs = svc.runs(None, 10);
+        let names: Vec<&str> = runs.iter().map(|r| r.job_name.as_str()).collect();
+        assert_eq!(names, ["broken", "healthy"]);
+        assert_eq!(runs[0].exit_code, Some(3));
+        assert_eq!(runs[0].stderr, "bad\n");
+        assert_eq!(runs[0].error.as_deref(), Some("Exit code: 3"));
+        assert_eq!(
+            (runs[1].status.as_str(), runs[1].stdout.as_str()),
+            ("ok", "fine\n")
+        );
+        assert_eq!(svc.runs(Some(&runs[1].job_id), 10).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cached_reply_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(
+            out_tx.clone(),
+            dir.path().to_path_buf(),
+            ExecToolConfig::default(),
+        );
+
+        let at = |ms| CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: Some(now_ms() + ms),
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        for (name, ms) in [("first", 100), ("again", 600)] {
+            let job = svc
+                .add_job(
+                    name,
+                    at(ms),
+                    "Morning briefing",
+                    "agent_turn",
+                    false,
+                    Some("telegram".into()),
+                    Some("42".into()),
+                    false,
+                )
+                .unwrap();
+            let job = svc.set_cache_ttl(&job.id, Some(3600)).unwrap();
+            assert_eq!(job.payload.cache_ttl_secs, Some(3600));
+        }
+
+        // The first run asks the agent, whose reply is cached
+        let asked = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(asked.metadata.contains_key("cron_cache_key"));
+        out_tx
+            .send(OutboundMessage {
+                channel: "telegram".into(),
+                chat_id: "42".into(),
+                content: "Sunny, two meetings".into(),
+                reply_to: None,
+                metadata: reply_metadata(&asked.metadata, true),
+            })
+            .unwrap();
+        out_rx.recv().await.unwrap();
+
+        // The second is answered from the cache
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.content, "Sunny, two meetings");
+        assert_eq!(sent.chat_id, "42");
+        assert!(rx.try_recv().is_err());
+    }
+
+    /// A job that only runs when another chains to it.
+    fn chained_only() -> CronSchedule {
+        CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: None,
+            every_ms: None,
+            expr: None,
+            tz: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_follows_the_condition() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(4);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(out_tx, dir.path().to_path_buf(), ExecToolConfig::default());
+
+        let mut step = |name: &str, message: &str| {
+            svc.add_job(
+                name,
+                chained_only(),
+                message,
+                "message",
+                true,
+                Some("telegram".into()),
+                Some("42".into()),
+                false,
+            )
+            .unwrap()
+            .id
+        };
+        let alert = step("alert", "Disk almost full: {{output}}");
+        let fine = step("fine", "Disks are fine");
+        assert!(svc.scheduled_messages().is_empty());
+
+        let schedule = CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: Some(now_ms() + 100),
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        let check = svc
+            .add_job(
+                "check", schedule, "echo 93%", "exec", false, None, None, false,
+            )
+            .unwrap();
+        let condition = OutputCondition {
+            regex: Some(r"9\d%".into()),
+            ..Default::default()
+        };
+        assert!(svc
+            .set_chain(&check.id, Some("nope".into()), None, None)
+            .is_err());
+        let check = svc
+            .set_chain(&check.id, Some(alert), Some(fine), Some(condition))
+            .unwrap();
+        assert_eq!(check.next_job(true, "71%"), check.on_failure.as_deref());
+        assert_eq!(check.next_job(false, "93%"), check.on_failure.as_deref());
+
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.content.trim(), "Disk almost full: 93%");
+        assert_eq!(sent.metadata["cron_job_name"], "alert");
+    }
+
+    #[tokio::test]
+    async fn test_agent_reply_continues_the_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(
+            out_tx.clone(),
+            dir.path().to_path_buf(),
+            ExecToolConfig::default(),
+        );
+
+        let deliver = svc
+            .add_job(
+                "deliver",
+                chained_only(),
+                "Summary: {{output}}",
+                "message",
+                true,
+                Some("telegram".into()),
+                Some("42".into()),
+                false,
+            )
+            .unwrap();
+        let schedule = CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: Some(now_ms() + 100),
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        let summarize = svc
+            .add_job(
+                "summarize",
+                schedule,
+                "Summarize the news",
+                "agent_turn",
+                false,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        svc.set_chain(&summarize.id, Some(deliver.id), None, None)
+            .unwrap();
+        svc.set_model_tier(&summarize.id, Some("fast".into()));
+
+        let asked = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(asked.metadata["cron_chain_depth"], 0);
+        assert_eq!(asked.metadata["model_tier"], "fast");
+        out_tx
+            .send(OutboundMessage {
+                channel: "cli".into(),
+                chat_id: "cron".into(),
+                content: "Quiet day".into(),
+                reply_to: None,
+                metadata: reply_metadata(&asked.metadata, true),
+            })
+            .unwrap();
+        out_rx.recv().await.unwrap();
+
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.content, "Summary: Quiet day");
+    }
+
+    #[tokio::test]
+    async fn test_failed_agent_turn_takes_the_failure_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let (out_tx, mut out_rx) = broadcast::channel(4);
+        let mut svc = CronService::new(dir.path().join("cron/jobs.json"), tx);
+        svc.set_exec_context(
+            out_tx.clone(),
+            dir.path().to_path_buf(),
+            ExecToolConfig::default(),
+        );
+
+        let step = |svc: &mut CronService, name: &str, message: &str| {
+            svc.add_job(
+                name,
+                chained_only(),
+                message,
+                "message",
+                true,
+                Some("telegram".into()),
+                Some("42".into()),
+                false,
+            )
+            .unwrap()
+            .id
+        };
+        let fine = step(&mut svc, "fine", "Summary: {{output}}");
+        let alert = step(&mut svc, "alert", "Summarizing failed: {{output}}");
+        let schedule = CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: Some(now_ms() + 100),
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        let summarize = svc
+            .add_job(
+                "summarize",
+                schedule,
+                "Summarize the news",
+                "agent_turn",
+                false,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        svc.set_chain(&summarize.id, Some(fine), Some(alert), None)
+            .unwrap();
+
+        let asked = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        // The gateway's error reply keeps the job's metadata
+        let metadata = reply_metadata(&asked.metadata, false);
+        assert_eq!(metadata["cron_ok"], false);
+        assert_eq!(metadata["cron_chain_depth"], 0);
+        out_tx
+            .send(OutboundMessage {
+                channel: "cli".into(),
+                chat_id: "cron".into(),
+                content: "model unavailable".into(),
+                reply_to: None,
+                metadata,
+            })
+            .unwrap();
+        out_rx.recv().await.unwrap();
+
+        let sent = tokio::time::timeout(std::time::Duration::from_secs(5), out_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent.content, "Summarizing failed: model unavailable");
+        assert!(reply_metadata(&HashMap::new(), true).is_empty());
+    }
+
     #[tokio::test]
     async fn test_enable_disable_job() {
         let dir = tempfile::tempdir().unwrap();
@@ -814,6 +1804,117 @@ mod tests {
         assert_eq!(jobs[0].payload.to, Some("123".into()));
     }
 
+    #[tokio::test]
+    async fn test_jobs_round_trip_through_yaml() {
+        use crate::portable::{from_yaml, to_yaml};
+
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let mut svc = CronService::new(dir.path().join("a/jobs.json"), tx.clone());
+        let every = CronSchedule {
+            kind: ScheduleKind::Every,
+            at_ms: None,
+            every_ms: Some(3_600_000),
+            expr: None,
+            tz: None,
+        };
+        let check = svc
+            .add_job(
+                "disk check",
+                every,
+                "df -h /",
+                "exec",
+                false,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        let chained = CronSchedule {
+            kind: ScheduleKind::At,
+            at_ms: None,
+            every_ms: None,
+            expr: None,
+            tz: None,
+        };
+        let report = svc
+            .add_job(
+                "report",
+                chained,
+                "Summarise: {{output}}",
+                "agent_turn",
+                false,
+                None,
+                None,
+                false,
+            )
+            .unwrap();
+        svc.set_chain(&check.id, Some(report.id.clone()), None, None)
+            .unwrap();
+
+        let yaml = to_yaml("cron jobs", &svc.export_jobs()).unwrap();
+        assert!(yaml.starts_with("# cron jobs\nversion: 1\njobs:\n- id: "));
+        assert!(yaml.contains("everyMs: 3600000"));
+        assert!(!yaml.contains("null") && !yaml.contains("nextRunAtMs"));
+
+        // Into an empty store, keeping IDs so the chain holds
+        let mut other = CronService::new(dir.path().join("b/jobs.json"), tx);
+        let imported = other.import_jobs(from_yaml(&yaml).unwrap(), false).unwrap();
+        assert_eq!((imported.added, imported.updated), (2, 0));
+        let jobs = other.list_jobs(true);
+        let copy = jobs.iter().find(|j| j.id == check.id).unwrap();
+        assert_eq!(copy.on_success.as_deref(), Some(report.id.as_str()));
+        assert!(copy.state.next_run_at_ms.is_some());
+        let created_at_ms = copy.created_at_ms;
+
+        // Edited by hand: one changed, one new without an ID, one dropped
+        let edited = format!(
+            "jobs:
+- id: {}
+  name: disk check
+  schedule: {{ kind: cron, expr: '0 7 * * *' }}
+  payload: {{ kind: exec, message: df -h / /home }}
+- name: backup
+  enabled: false
+  schedule: {{ kind: every, everyMs: 86400000 }}
+  payload: {{ kind: exec, message: restic backup }}
+",
+            check.id
+        );
+        let imported = other
+            .import_jobs(from_yaml(&edited).unwrap(), true)
+            .unwrap();
+        assert_eq!(imported.to_string(), "1 added, 1 updated, 1 removed");
+        let jobs = other.list_jobs(true);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].payload.message, "df -h / /home");
+        assert_eq!(jobs[0].created_at_ms, created_at_ms);
+        assert_eq!(jobs[1].id.len(), 8);
+        assert!(!jobs[1].enabled && jobs[1].state.next_run_at_ms.is_none());
+
+        // Invalid files change nothing
+        let bad = "jobs:
+- name: broken
+  schedule: { kind: cron, expr: 'not a cron' }
+  payload: { kind: exec, message: x }
+";
+        let err = other
+            .import_jobs(from_yaml(bad).unwrap(), true)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Cron job 'broken'"), "{err}");
+        let dangling = "jobs:
+- name: a
+  schedule: { kind: every, everyMs: 60000 }
+  payload: { kind: exec, message: x }
+  onSuccess: nope
+";
+        assert!(other
+            .import_jobs(from_yaml(dangling).unwrap(), false)
+            .is_err());
+        assert_eq!(other.list_jobs(true).len(), 2);
+    }
+
     // --- CronJob/CronStore serialization tests ---
 
     #[test]
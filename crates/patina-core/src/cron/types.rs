@@ -1,4 +1,6 @@
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 /// Schedule type for a cron job.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -28,7 +30,9 @@ pub struct CronSchedule {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CronPayload {
-    /// "agent_turn" (LLM processing) or "exec" (direct shell command)
+    /// "agent_turn" (LLM processing), "exec" (direct shell command),
+    /// "digest" (activity summary), "message" (send the text as-is), or
+    /// "standup" (a persona run from the `standups` config)
     #[serde(default = "default_payload_kind")]
     pub kind: String,
     /// The message/task to execute.
@@ -40,6 +44,41 @@ pub struct CronPayload {
     pub channel: Option<String>,
     /// Target chat_id for delivery.
     pub to: Option<String>,
+    /// For agent turns: send the reply to the same prompt from earlier the
+    /// same day, if it's younger than this, instead of asking the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_secs: Option<u64>,
+    /// For agent turns: the model tier to answer with, e.g. a cheap "fast"
+    /// tier for routine checks. Unset uses the chat's usual tier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_tier: Option<String>,
+    /// For exec jobs: which runs are delivered.
+    #[serde(default, skip_serializing_if = "DeliverOn::is_always")]
+    pub deliver_on: DeliverOn,
+}
+
+/// Which runs of an exec job are delivered to its channel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliverOn {
+    #[default]
+    Always,
+    /// Only runs that failed, so a healthy check stays quiet.
+    Failure,
+}
+
+impl DeliverOn {
+    pub fn is_always(&self) -> bool {
+        *self == DeliverOn::Always
+    }
+
+    /// Whether a run that `failed` or not is delivered.
+    pub fn allows(self, failed: bool) -> bool {
+        match self {
+            DeliverOn::Always => true,
+            DeliverOn::Failure => failed,
+        }
+    }
 }
 
 fn default_payload_kind() -> String {
@@ -71,12 +110,122 @@ pub struct CronJob {
     pub updated_at_ms: i64,
     #[serde(default)]
     pub delete_after_run: bool,
+    /// Job to run next when this one succeeds and its output meets
+    /// `condition`. The output replaces `{{output}}` in its message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_success: Option<String>,
+    /// Job to run next when this one fails or its output doesn't meet
+    /// `condition`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<OutputCondition>,
+}
+
+impl CronJob {
+    /// Whether another job runs after this one.
+    pub fn has_chain(&self) -> bool {
+        self.on_success.is_some() || self.on_failure.is_some()
+    }
+
+    /// The job to run after this one finished with `output`: `on_success`
+    /// if it succeeded and the output meets the condition, `on_failure`
+    /// otherwise.
+    pub fn next_job(&self, succeeded: bool, output: &str) -> Option<&str> {
+        let met = self.condition.as_ref().is_none_or(|c| c.matches(output));
+        if succeeded && met {
+            self.on_success.as_deref()
+        } else {
+            self.on_failure.as_deref()
+        }
+    }
+}
+
+/// What a job's output has to contain for its chain to take `on_success`.
+/// With both set, both must match.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputCondition {
+    /// Text the output contains, compared exactly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    /// Regex the output matches somewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+}
+
+impl OutputCondition {
+    pub fn matches(&self, output: &str) -> bool {
+        let contains = self.contains.as_deref().is_none_or(|c| output.contains(c));
+        let regex = self
+            .regex
+            .as_deref()
+            .is_none_or(|pattern| match Regex::new(pattern) {
+                Ok(re) => re.is_match(output),
+                Err(e) => {
+                    warn!("Cron condition regex '{pattern}' doesn't compile: {e}");
+                    false
+                }
+            });
+        contains && regex
+    }
 }
 
 fn default_true() -> bool {
     true
 }
 
+/// A job as exported to YAML: what it does and when, without its run state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobDefinition {
+    /// Empty for a new job, which gets an ID on import.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub schedule: CronSchedule,
+    pub payload: CronPayload,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delete_after_run: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_success: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<OutputCondition>,
+}
+
+impl From<&CronJob> for JobDefinition {
+    fn from(job: &CronJob) -> Self {
+        Self {
+            id: job.id.clone(),
+            name: job.name.clone(),
+            enabled: job.enabled,
+            schedule: job.schedule.clone(),
+            payload: job.payload.clone(),
+            delete_after_run: job.delete_after_run,
+            on_success: job.on_success.clone(),
+            on_failure: job.on_failure.clone(),
+            condition: job.condition.clone(),
+        }
+    }
+}
+
+/// The YAML file of `patina cron export` and `import`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobFile {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    pub jobs: Vec<JobDefinition>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
 /// Persistence format for cron jobs.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
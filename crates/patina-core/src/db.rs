@@ -0,0 +1,308 @@
+//! Shared SQLite access.
+//!
+//! Every database patina keeps (usage, the memory index, the knowledge base,
+//! locations, comparisons, the bus journal) opens through [`Db`], which sets
+//! the same pragmas on each connection: WAL, so readers don't block the
+//! writer; a busy timeout, so a second writer waits instead of failing with
+//! `database is locked`; and `synchronous=NORMAL`, which is safe under WAL.
+//!
+//! Connections come from a small pool and go back to it when dropped, so
+//! the web dashboard reading usage doesn't wait behind a reindex. Queries
+//! that read more than once and need the answers to agree go through
+//! [`Db::read`], which holds one snapshot for all of them; writes that must
+//! land together go through [`Db::write`].
+//!
+//! Schemas are a list of [`Migration`]s applied in order on open. The number
+//! applied is kept in the file's `user_version`, each one runs in its own
+//! transaction, and a file from a newer patina is refused rather than
+//! written to. Migrations must never be edited or reordered once released;
+//! schema changes are new migrations at the end of the list. Files created
+//! before migrations existed have version 0, so the first migration of each
+//! store uses `IF NOT EXISTS` and [`add_column`] to adopt them as they are.
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use tracing::info;
+
+/// How long a connection waits for another writer before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Most idle connections kept per database.
+const MAX_IDLE: usize = 4;
+
+/// One step of a schema.
+pub struct Migration {
+    /// What it does, for the log.
+    pub name: &'static str,
+    pub up: Step,
+}
+
+/// What a migration runs.
+pub enum Step {
+    /// Statements run as a batch.
+    Sql(&'static str),
+    /// Code, for changes SQL can't make conditionally, such as adding a
+    /// column only where it's missing.
+    Code(fn(&Transaction<'_>) -> rusqlite::Result<()>),
+}
+
+/// A SQLite database with its schema up to date.
+pub struct Db {
+    path: PathBuf,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl Db {
+    /// Open or create the database at `path` and apply the `migrations` it
+    /// hasn't had yet.
+    pub fn open(path: &Path, migrations: &[Migration]) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut conn = connect(path)?;
+        migrate(&mut conn, path, migrations)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            idle: Mutex::new(vec![conn]),
+        })
+    }
+
+    /// A connection from the pool, or a new one if all are in use.
+    pub fn conn(&self) -> Result<Pooled<'_>> {
+        let idle = self
+            .idle
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Lock poisoned: {e}"))?
+            .pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => connect(&self.path)?,
+        };
+        Ok(Pooled {
+            db: self,
+            conn: Some(conn),
+        })
+    }
+
+    /// Run `f` against one snapshot of the database, so every query in it
+    /// sees the same data even while others write.
+    pub fn read<T>(&self, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let out = f(&tx)?;
+        tx.commit()?;
+        Ok(out)
+    }
+
+    /// Run `f` in a write transaction, committed if it succeeds. The write
+    /// lock is taken up front, so two writers wait their turn instead of one
+    /// failing halfway.
+    pub fn write<T>(&self, f: impl FnOnce(&Transaction<'_>) -> Result<T>) -> Result<T> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let out = f(&tx)?;
+        tx.commit()?;
+        Ok(out)
+    }
+}
+
+/// A connection borrowed from a [`Db`], returned to it when dropped.
+pub struct Pooled<'a> {
+    db: &'a Db,
+    conn: Option<Connection>,
+}
+
+impl Deref for Pooled<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+            .as_ref()
+            .expect("connection is taken only on drop")
+    }
+}
+
+impl DerefMut for Pooled<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn
+            .as_mut()
+            .expect("connection is taken only on drop")
+    }
+}
+
+impl Drop for Pooled<'_> {
+    fn drop(&mut self) {
+        let (Some(conn), Ok(mut idle)) = (self.conn.take(), self.db.idle.lock()) else {
+            return;
+        };
+        if idle.len() < MAX_IDLE {
+            idle.push(conn);
+        }
+    }
+}
+
+/// Add `column` to `table` unless it's already there.
+pub fn add_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
+
+fn connect(path: &Path) -> Result<Connection> {
+    let conn =
+        Connection::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
+    Ok(conn)
+}
+
+/// Apply the migrations past the file's `user_version`, one transaction
+/// each. The version is re-read under the write lock, so two processes
+/// opening the same file don't both run a migration.
+fn migrate(conn: &mut Connection, path: &Path, migrations: &[Migration]) -> Result<()> {
+    loop {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: usize = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > migrations.len() {
+            anyhow::bail!(
+                "{} is at schema version {version}, but this version of patina only knows {}; \
+                 upgrade patina to use it",
+                path.display(),
+                migrations.len()
+            );
+        }
+        let Some(migration) = migrations.get(version) else {
+            return Ok(());
+        };
+        match migration.up {
+            Step::Sql(sql) => tx.execute_batch(sql),
+            Step::Code(f) => f(&tx),
+        }
+        .with_context(|| {
+            format!(
+                "migration {} ({}) of {} failed",
+                version + 1,
+                migration.name,
+                path.display()
+            )
+        })?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+        tx.commit()?;
+        info!(
+            "Migrated {} to version {}: {}",
+            path.display(),
+            version + 1,
+            migration.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            name: "notes",
+            up: Step::Sql("CREATE TABLE IF NOT EXISTS notes (id INTEGER PRIMARY KEY, text TEXT)"),
+        },
+        Migration {
+            name: "note tags",
+            up: Step::Code(|tx| add_column(tx, "notes", "tag", "TEXT NOT NULL DEFAULT ''")),
+        },
+    ];
+
+    fn version(db: &Db) -> usize {
+        db.conn()
+            .unwrap()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn migrations_run_once_and_adopt_old_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/notes.sqlite");
+
+        let db = Db::open(&path, &MIGRATIONS[..1]).unwrap();
+        assert_eq!(version(&db), 1);
+        db.write(|tx| {
+            tx.execute("INSERT INTO notes (text) VALUES ('first')", [])?;
+            Ok(())
+        })
+        .unwrap();
+        drop(db);
+
+        // A later build adds the column and keeps the data
+        let db = Db::open(&path, MIGRATIONS).unwrap();
+        assert_eq!(version(&db), 2);
+        let row: (String, String) = db
+            .conn()
+            .unwrap()
+            .query_row("SELECT text, tag FROM notes", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(row, ("first".to_string(), String::new()));
+        drop(db);
+        assert_eq!(version(&Db::open(&path, MIGRATIONS).unwrap()), 2);
+
+        // A file made before migrations, already with the column, is adopted
+        let old = dir.path().join("old.sqlite");
+        Connection::open(&old)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, text TEXT, tag TEXT)")
+            .unwrap();
+        assert_eq!(version(&Db::open(&old, MIGRATIONS).unwrap()), 2);
+
+        // An older build refuses a newer file
+        let err = Db::open(&path, &MIGRATIONS[..1]).err().unwrap();
+        assert!(err.to_string().contains("schema version 2"), "{err}");
+    }
+
+    #[test]
+    fn failed_writes_roll_back_and_connections_are_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open(&dir.path().join("notes.sqlite"), MIGRATIONS).unwrap();
+
+        let result: Result<()> = db.write(|tx| {
+            tx.execute("INSERT INTO notes (text) VALUES ('half')", [])?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(result.is_err());
+        let count = |db: &Db| -> i64 {
+            db.read(|conn| Ok(conn.query_row("SELECT count(*) FROM notes", [], |row| row.get(0))?))
+                .unwrap()
+        };
+        assert_eq!(count(&db), 0);
+
+        // Two at once, then both back in the pool
+        let (a, b) = (db.conn().unwrap(), db.conn().unwrap());
+        a.execute("INSERT INTO notes (text) VALUES ('a')", [])
+            .unwrap();
+        assert_eq!(
+            b.query_row("SELECT count(*) FROM notes", [], |row| row.get::<_, i64>(0))
+                .unwrap(),
+            1
+        );
+        drop((a, b));
+        assert_eq!(db.idle.lock().unwrap().len(), 2);
+        assert_eq!(count(&db), 1);
+    }
+}
@@ -0,0 +1,120 @@
+//! Messages the agent wrote to other chats, held until the owner reviews
+//! them.
+//!
+//! With `tools.message.draftChannels` set, the message tool doesn't send to
+//! a chat other than the one it's talking in on those channels. It saves a
+//! [`Draft`] here and shows it to the owner, who sends, edits, or rejects it
+//! with `/draft`. Drafts are kept in `~/.patina/drafts.json`, so they survive
+//! a restart.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// A message waiting for approval.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Draft {
+    pub id: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub content: String,
+    /// When the agent asked for it to be sent, if not right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_at_ms: Option<i64>,
+    /// Session key of the conversation that wrote it.
+    pub origin: String,
+    pub created_at_ms: i64,
+}
+
+/// Manages the drafts file. Like [`ContactStore`](crate::contacts::ContactStore),
+/// it's re-read before every change.
+pub struct DraftStore {
+    path: PathBuf,
+    drafts: Vec<Draft>,
+}
+
+impl DraftStore {
+    pub fn load(path: &Path) -> Self {
+        let drafts = match std::fs::read_to_string(path) {
+            Ok(content) if !content.trim().is_empty() => serde_json::from_str(&content)
+                .unwrap_or_else(|e| {
+                    warn!("Failed to parse drafts file {}: {e}", path.display());
+                    Vec::new()
+                }),
+            _ => Vec::new(),
+        };
+        Self {
+            path: path.to_path_buf(),
+            drafts,
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.drafts)?)?;
+        Ok(())
+    }
+
+    fn refresh_from_disk(&mut self) {
+        *self = Self::load(&self.path);
+    }
+
+    /// Drafts, oldest first.
+    pub fn list(&self) -> &[Draft] {
+        &self.drafts
+    }
+
+    /// Save a new draft. The id and creation time are assigned by the store.
+    pub fn add(&mut self, mut draft: Draft) -> Result<Draft> {
+        self.refresh_from_disk();
+        draft.id = uuid::Uuid::new_v4().to_string()[..8].to_string();
+        draft.created_at_ms = chrono::Utc::now().timestamp_millis();
+        self.drafts.push(draft.clone());
+        self.save()?;
+        Ok(draft)
+    }
+
+    /// Remove a draft and return it, e.g. to send it.
+    pub fn take(&mut self, id: &str) -> Result<Option<Draft>> {
+        self.refresh_from_disk();
+        let Some(pos) = self.drafts.iter().position(|d| d.id == id) else {
+            return Ok(None);
+        };
+        let draft = self.drafts.remove(pos);
+        self.save()?;
+        Ok(Some(draft))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drafts_persist_until_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("drafts.json");
+        let mut store = DraftStore::load(&path);
+        let draft = store
+            .add(Draft {
+                channel: "slack".into(),
+                chat_id: "C42".into(),
+                content: "Running late".into(),
+                origin: "telegram:1".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(draft.id.len(), 8);
+
+        let mut reloaded = DraftStore::load(&path);
+        assert_eq!(reloaded.list(), std::slice::from_ref(&draft));
+        assert_eq!(reloaded.take("nope").unwrap(), None);
+        assert_eq!(reloaded.take(&draft.id).unwrap(), Some(draft));
+        assert!(DraftStore::load(&path).list().is_empty());
+    }
+}
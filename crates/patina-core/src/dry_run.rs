@@ -0,0 +1,260 @@
+//! Plans instead of actions.
+//!
+//! `/plan <request>` and `patina agent -m ... --dry-run` run a normal turn
+//! in which only tools that just look things up ([`READ_ONLY_TOOLS`], and
+//! the read actions in [`READ_ONLY_ACTIONS`]) run. Every other call, exec,
+//! file writes, messages, and spawning included, is stubbed: the model is
+//! told what would have happened and carries on planning, and the call is
+//! listed under the reply. Tools not known to be read-only, such as those
+//! from connectors, count as acting.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use tracing::info;
+
+use crate::agent::middleware::{HookContext, Middleware, ToolDecision};
+
+/// Tools that run during a plan, whatever their arguments.
+pub const READ_ONLY_TOOLS: &[&str] = &[
+    "calc",
+    "knowledge_search",
+    "list_dir",
+    "location",
+    "memory_search",
+    "read_file",
+    "transcribe",
+    "translate",
+    "usage_report",
+    "web_crawl",
+    "web_fetch",
+    "web_search",
+];
+
+/// Tools whose `action` decides whether they change anything, with the
+/// actions that run during a plan.
+pub const READ_ONLY_ACTIONS: &[(&str, &[&str])] = &[
+    ("clipboard", &["read"]),
+    ("contacts", &["search", "list"]),
+    ("cron", &["list", "history"]),
+    ("message", &["list"]),
+    ("models", &["list"]),
+    ("task", &["list", "get"]),
+];
+
+/// Longest text quoted from a call's arguments.
+const PREVIEW_CHARS: usize = 80;
+
+/// A call that was stubbed during a plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedCall {
+    pub tool: String,
+    /// What it would have done, e.g. "run `cargo test`".
+    pub summary: String,
+}
+
+/// Sessions whose current turn is a plan, with the calls stubbed so far.
+/// Sessions not planning are left alone.
+#[derive(Default)]
+pub struct DryRun {
+    plans: Mutex<HashMap<String, Vec<PlannedCall>>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the next turn in `session_key` a plan.
+    pub fn begin(&self, session_key: &str) {
+        self.lock().insert(session_key.to_string(), Vec::new());
+    }
+
+    /// Stop planning in `session_key`, returning the calls that were stubbed.
+    pub fn end(&self, session_key: &str) -> Vec<PlannedCall> {
+        self.lock().remove(session_key).unwrap_or_default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<PlannedCall>>> {
+        match self.plans.lock() {
+            Ok(g) => g,
+            Err(e) => e.into_inner(),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for DryRun {
+    async fn before_tool(
+        &self,
+        ctx: &HookContext<'_>,
+        tool: &str,
+        args: &mut serde_json::Value,
+    ) -> Result<ToolDecision> {
+        let mut plans = self.lock();
+        let Some(planned) = plans.get_mut(ctx.session_key) else {
+            return Ok(ToolDecision::Run);
+        };
+        if is_read_only(tool, args) {
+            return Ok(ToolDecision::Run);
+        }
+        let summary = describe(tool, args);
+        info!("Dry run in '{}': would {summary}", ctx.session_key);
+        let reply = format!(
+            "[dry run] Not run: this would {summary}. Nothing was changed. Carry on \
+             as if it had worked, and include this step in your plan."
+        );
+        planned.push(PlannedCall {
+            tool: tool.to_string(),
+            summary,
+        });
+        Ok(ToolDecision::Skip(reply))
+    }
+}
+
+/// The message the agent gets for `/plan <request>`.
+pub fn plan_prompt(request: &str) -> String {
+    format!(
+        "[Plan only] Work out how you would handle the request below, without \
+         doing it. Tools that look things up work as usual; tools that change \
+         anything (running commands, writing files, sending messages, starting \
+         agents, scheduling) are simulated and won't run. Call them as you \
+         would for real, then reply with the steps you'd take and what each \
+         would change.\n\n{}",
+        request.trim()
+    )
+}
+
+/// `reply` followed by the calls that were stubbed while planning it.
+pub fn report(reply: &str, planned: &[PlannedCall]) -> String {
+    if planned.is_empty() {
+        return format!("{reply}\n\nDry run: nothing needed to change.");
+    }
+    let mut out = format!("{reply}\n\nDry run, nothing was done. Would have:");
+    for (i, call) in planned.iter().enumerate() {
+        out.push_str(&format!("\n{}. {}: {}", i + 1, call.tool, call.summary));
+    }
+    out
+}
+
+fn is_read_only(tool: &str, args: &Value) -> bool {
+    if READ_ONLY_TOOLS.contains(&tool) {
+        return true;
+    }
+    let action = args.get("action").and_then(Value::as_str);
+    READ_ONLY_ACTIONS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .is_some_and(|(_, actions)| action.is_some_and(|a| actions.contains(&a)))
+}
+
+/// What a call to `tool` with `args` would do, as a phrase after "would".
+fn describe(tool: &str, args: &Value) -> String {
+    let arg = |key: &str| args.get(key).and_then(Value::as_str).unwrap_or("?");
+    let action = args.get("action").and_then(Value::as_str);
+    match tool {
+        "exec" => match args.get("working_dir").and_then(Value::as_str) {
+            Some(dir) => format!("run `{}` in {dir}", arg("command")),
+            None => format!("run `{}`", arg("command")),
+        },
+        "write_file" => format!(
+            "write {} characters to {}",
+            arg("content").chars().count(),
+            arg("path")
+        ),
+        "edit_file" => format!("edit {}", arg("path")),
+        "message" if matches!(action, None | Some("send")) => {
+            let to = match (args.get("channel"), args.get("chat_id")) {
+                (Some(_), Some(_)) => format!("{}:{}", arg("channel"), arg("chat_id")),
+                _ => "this chat".to_string(),
+            };
+            format!("send to {to}: \"{}\"", preview(arg("content")))
+        }
+        "spawn" => format!("start a background agent to {}", preview(arg("task"))),
+        _ => format!("call {tool} with {}", preview(&args.to_string())),
+    }
+}
+
+fn preview(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    let mut preview: String = line.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < text.len() {
+        preview.push_str("...");
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(session_key: &str) -> HookContext<'_> {
+        HookContext {
+            session_key,
+            agent: "default",
+            model: "m",
+            provider: "p",
+            iteration: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_stubs_calls_that_change_things() {
+        let dry_run = DryRun::new();
+        let mut exec = serde_json::json!({ "command": "rm -rf build" });
+        let mut read = serde_json::json!({ "path": "notes.md" });
+        let mut list = serde_json::json!({ "action": "list" });
+        let mut add = serde_json::json!({ "action": "add", "title": "Ship it" });
+
+        // Not planning: everything runs
+        let decision = dry_run
+            .before_tool(&ctx("cli:1"), "exec", &mut exec)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+
+        dry_run.begin("cli:1");
+        let decision = dry_run
+            .before_tool(&ctx("cli:1"), "exec", &mut exec)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, ToolDecision::Skip(ref r) if r.contains("would run `rm -rf build`"))
+        );
+        for (tool, args) in [("read_file", &mut read), ("task", &mut list)] {
+            let decision = dry_run
+                .before_tool(&ctx("cli:1"), tool, args)
+                .await
+                .unwrap();
+            assert_eq!(decision, ToolDecision::Run, "{tool}");
+        }
+        let decision = dry_run
+            .before_tool(&ctx("cli:1"), "task", &mut add)
+            .await
+            .unwrap();
+        assert!(matches!(decision, ToolDecision::Skip(_)));
+        // Other sessions are unaffected
+        let decision = dry_run
+            .before_tool(&ctx("telegram:2"), "exec", &mut exec)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+
+        let planned = dry_run.end("cli:1");
+        assert_eq!(planned.len(), 2);
+        assert_eq!(
+            report("I'd clean the build.", &planned[..1]),
+            "I'd clean the build.\n\nDry run, nothing was done. Would have:\n\
+             1. exec: run `rm -rf build`"
+        );
+        assert!(dry_run.end("cli:1").is_empty());
+        let decision = dry_run
+            .before_tool(&ctx("cli:1"), "exec", &mut exec)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+    }
+}
@@ -0,0 +1,222 @@
+//! Typed errors for the public API.
+//!
+//! Internally patina uses `anyhow`. These are what crosses the boundary (an
+//! agent turn, a tool call, channel setup, loading config) so embedders can
+//! tell a rate limit from a bad API key from a timeout without matching on
+//! message text. Code inside raises the specific error (a [`ProviderError`],
+//! say) and lets `?` carry it through anyhow; converting to [`PatinaError`]
+//! at the boundary finds it again.
+
+use std::fmt;
+
+pub use patina_config::ConfigError;
+
+/// Any failure from patina's public API, by kind.
+#[derive(Debug, thiserror::Error)]
+pub enum PatinaError {
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(transparent)]
+    Tool(#[from] ToolError),
+    #[error(transparent)]
+    Channel(#[from] ChannelError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// Anything else: session storage, I/O, bugs.
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for PatinaError {
+    /// Recover the typed error an `anyhow::Error` was made from, if any.
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<PatinaError>() {
+            Ok(e) => return e,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ProviderError>() {
+            Ok(e) => return e.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ToolError>() {
+            Ok(e) => return e.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ChannelError>() {
+            Ok(e) => return e.into(),
+            Err(err) => err,
+        };
+        match err.downcast::<ConfigError>() {
+            Ok(e) => e.into(),
+            Err(err) => Self::Other(err),
+        }
+    }
+}
+
+/// An LLM provider call failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    /// No response within the configured request timeout.
+    #[error("LLM provider '{provider}' did not respond within {secs}s")]
+    Timeout { provider: String, secs: u64 },
+    /// The provider is throttling requests (HTTP 429, quota exhausted).
+    #[error("LLM provider '{provider}' is rate limiting requests: {message}")]
+    RateLimited { provider: String, message: String },
+    /// Missing, invalid, or unauthorized API key (HTTP 401/403).
+    #[error("LLM provider '{provider}' rejected its credentials: {message}")]
+    Auth { provider: String, message: String },
+    /// Provider down or unreachable (connection failures, HTTP 5xx).
+    #[error("LLM provider '{provider}' is unavailable: {message}")]
+    Unavailable { provider: String, message: String },
+    /// Any other failed request, e.g. one the provider rejected as invalid.
+    #[error("LLM provider '{provider}' request failed: {message}")]
+    Request { provider: String, message: String },
+}
+
+impl ProviderError {
+    /// Classify a failure reported by a provider client. rig flattens HTTP
+    /// errors into text, so the status code and a few common phrases are
+    /// all there is to go on.
+    pub fn classify(provider: &str, err: impl fmt::Display) -> Self {
+        let provider = provider.to_string();
+        let message = err.to_string();
+        let lower = message.to_lowercase();
+        let mentions = |phrases: &[&str]| phrases.iter().any(|p| lower.contains(p));
+
+        let status = http_status(&lower);
+        if status == Some(429) || mentions(&["rate limit", "rate_limit", "too many requests"]) {
+            Self::RateLimited { provider, message }
+        } else if matches!(status, Some(401 | 403))
+            || mentions(&["unauthorized", "invalid api key", "invalid_api_key"])
+        {
+            Self::Auth { provider, message }
+        } else if status.is_some_and(|s| s >= 500)
+            || mentions(&["connection refused", "error sending request", "overloaded"])
+        {
+            Self::Unavailable { provider, message }
+        } else {
+            Self::Request { provider, message }
+        }
+    }
+
+    pub fn provider(&self) -> &str {
+        match self {
+            Self::Timeout { provider, .. }
+            | Self::RateLimited { provider, .. }
+            | Self::Auth { provider, .. }
+            | Self::Unavailable { provider, .. }
+            | Self::Request { provider, .. } => provider,
+        }
+    }
+
+    /// Whether the same request could succeed if retried later.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Timeout { .. } | Self::RateLimited { .. } | Self::Unavailable { .. }
+        )
+    }
+}
+
+/// The first standalone three-digit number in 400..=599 in an error message.
+fn http_status(message: &str) -> Option<u16> {
+    message
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|word| word.len() == 3)
+        .filter_map(|word| word.parse().ok())
+        .find(|code| (400..=599).contains(code))
+}
+
+/// A [`ToolRegistry`](crate::tools::ToolRegistry) call failed.
+///
+/// Failures the model should see and recover from (bad arguments, a denied
+/// path) are still returned as `Ok("Error: ...")` text, not as this.
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    #[error("unknown tool: {0}")]
+    NotFound(String),
+    #[error("{source}")]
+    Failed {
+        tool: String,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Setting up or starting a channel failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ChannelError {
+    /// A credential or setting the channel needs is missing.
+    #[error("{channel} {what} not configured")]
+    NotConfigured { channel: String, what: String },
+    /// The channel couldn't be built from its config (bad proxy URL, TLS).
+    #[error("failed to set up {channel}: {source}")]
+    Setup {
+        channel: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// The channel's listener failed or panicked right after starting.
+    #[error("channel '{channel}' failed to start: {message}")]
+    Start { channel: String, message: String },
+    /// No channel by that name is registered with the gateway.
+    #[error("no channel '{channel}' is running in this gateway")]
+    NotRegistered { channel: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_provider_failures() {
+        let kind = |message: &str| ProviderError::classify("openai", message);
+        assert!(matches!(
+            kind("HTTP 429 Too Many Requests"),
+            ProviderError::RateLimited { .. }
+        ));
+        assert!(matches!(
+            kind("ProviderError: Incorrect API key provided (status 401)"),
+            ProviderError::Auth { .. }
+        ));
+        assert!(matches!(
+            kind("HttpError: error sending request for url"),
+            ProviderError::Unavailable { .. }
+        ));
+        assert!(matches!(
+            kind("503 Service Unavailable"),
+            ProviderError::Unavailable { .. }
+        ));
+        // Token counts aren't status codes
+        assert!(matches!(
+            kind("context length 4096 exceeded by 1500 tokens"),
+            ProviderError::Request { .. }
+        ));
+        assert!(kind("429").is_transient());
+        assert!(!kind("401").is_transient());
+        assert_eq!(kind("boom").provider(), "openai");
+    }
+
+    #[test]
+    fn recovers_typed_errors_from_anyhow() {
+        let err = anyhow::Error::from(ProviderError::Timeout {
+            provider: "ollama".into(),
+            secs: 30,
+        })
+        .context("while answering");
+        assert!(matches!(
+            PatinaError::from(err),
+            PatinaError::Provider(ProviderError::Timeout { secs: 30, .. })
+        ));
+
+        let err = anyhow::Error::from(PatinaError::from(ToolError::NotFound("nope".into())));
+        assert!(matches!(
+            PatinaError::from(err),
+            PatinaError::Tool(ToolError::NotFound(_))
+        ));
+
+        let err = PatinaError::from(anyhow::anyhow!("disk full"));
+        assert!(matches!(err, PatinaError::Other(_)));
+        assert_eq!(err.to_string(), "disk full");
+    }
+}
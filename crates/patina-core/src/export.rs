@@ -0,0 +1,246 @@
+//! Render a session as a standalone Markdown, HTML, or JSON document.
+//!
+//! Exports include every message in the session file (not just the memory
+//! window), with timestamps, the tools each reply used, and any reasoning from
+//! thinking models.
+
+use std::str::FromStr;
+
+use crate::session::{Message, Session};
+
+/// Output format for [`export_session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "html" | "htm" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            other => anyhow::bail!("Unknown export format '{other}' (expected md, html, or json)"),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown; charset=utf-8",
+            Self::Html => "text/html; charset=utf-8",
+            Self::Json => "application/json",
+        }
+    }
+}
+
+/// Render the whole conversation in `format`.
+pub fn export_session(session: &Session, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(session),
+        ExportFormat::Html => to_html(session),
+        ExportFormat::Json => to_json(session),
+    }
+}
+
+fn to_markdown(session: &Session) -> String {
+    let mut out = format!(
+        "# Conversation: {}\n\n- Created: {}\n- Updated: {}\n- Messages: {}\n",
+        session.key,
+        session.created_at.format("%Y-%m-%d %H:%M UTC"),
+        session.updated_at.format("%Y-%m-%d %H:%M UTC"),
+        session.messages.len()
+    );
+    for msg in &session.messages {
+        out.push_str("\n---\n\n");
+        out.push_str(&format!("### {}", role_label(&msg.role)));
+        if let Some(ts) = timestamp(msg) {
+            out.push_str(&format!(" · {ts}"));
+        }
+        out.push_str("\n\n");
+        out.push_str(msg.content.trim_end());
+        out.push('\n');
+        if let Some(tools) = msg.tools_used.as_deref().filter(|t| !t.is_empty()) {
+            let tools: Vec<String> = tools.iter().map(|t| format!("`{t}`")).collect();
+            out.push_str(&format!("\n*Tools used: {}*\n", tools.join(", ")));
+        }
+        if let Some(reasoning) = reasoning(msg) {
+            out.push_str("\n> **Reasoning**\n>\n");
+            for line in reasoning.lines() {
+                if line.is_empty() {
+                    out.push_str(">\n");
+                } else {
+                    out.push_str(&format!("> {line}\n"));
+                }
+            }
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:820px;margin:2rem auto;\
+padding:0 1rem;color:#222;line-height:1.5}header.meta{color:#666;margin-bottom:2rem}\
+section{border-radius:8px;padding:.75rem 1rem;margin:1rem 0}\
+section.user{background:#eef4ff}section.assistant{background:#f5f5f5}\
+section header{font-size:.85rem;color:#555;margin-bottom:.5rem}\
+.content{white-space:pre-wrap;word-wrap:break-word}\
+.tools{font-size:.85rem;color:#555;margin:.5rem 0 0}\
+code{background:#e8e8e8;border-radius:3px;padding:0 .25rem}\
+details{margin-top:.5rem;font-size:.9rem;color:#444}";
+
+fn to_html(session: &Session) -> String {
+    let key = html_escape::encode_text(&session.key);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Conversation: {key}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>Conversation: {key}</h1>\n\
+         <header class=\"meta\">Created {} &middot; Updated {} &middot; {} messages</header>\n",
+        session.created_at.format("%Y-%m-%d %H:%M UTC"),
+        session.updated_at.format("%Y-%m-%d %H:%M UTC"),
+        session.messages.len()
+    );
+    for msg in &session.messages {
+        let role = html_escape::encode_double_quoted_attribute(&msg.role);
+        out.push_str(&format!(
+            "<section class=\"{role}\">\n<header><strong>{}</strong>",
+            role_label(&msg.role)
+        ));
+        if let Some(ts) = timestamp(msg) {
+            out.push_str(&format!(
+                " &middot; <time>{}</time>",
+                html_escape::encode_text(&ts)
+            ));
+        }
+        out.push_str("</header>\n");
+        out.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            html_escape::encode_text(msg.content.trim_end())
+        ));
+        if let Some(tools) = msg.tools_used.as_deref().filter(|t| !t.is_empty()) {
+            let tools: Vec<String> = tools
+                .iter()
+                .map(|t| format!("<code>{}</code>", html_escape::encode_text(t)))
+                .collect();
+            out.push_str(&format!(
+                "<p class=\"tools\">Tools used: {}</p>\n",
+                tools.join(", ")
+            ));
+        }
+        if let Some(reasoning) = reasoning(msg) {
+            out.push_str(&format!(
+                "<details><summary>Reasoning</summary><div class=\"content\">{}</div></details>\n",
+                html_escape::encode_text(reasoning)
+            ));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn to_json(session: &Session) -> String {
+    let doc = serde_json::json!({
+        "key": session.key,
+        "created_at": session.created_at.to_rfc3339(),
+        "updated_at": session.updated_at.to_rfc3339(),
+        "metadata": session.metadata,
+        "messages": session.messages,
+    });
+    serde_json::to_string_pretty(&doc).unwrap_or_default()
+}
+
+/// "user" -> "User", "assistant" -> "Assistant".
+fn role_label(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => "Unknown".to_string(),
+    }
+}
+
+/// "2026-03-01T14:03:22" -> "2026-03-01 14:03:22".
+fn timestamp(msg: &Message) -> Option<String> {
+    msg.timestamp
+        .as_deref()
+        .filter(|ts| !ts.is_empty())
+        .map(|ts| ts.replacen('T', " ", 1))
+}
+
+fn reasoning(msg: &Message) -> Option<&str> {
+    msg.reasoning_content
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut s = Session::new("telegram:42".into());
+        s.add_message("user", "Plan the <launch> & budget");
+        s.add_message_full(
+            "assistant",
+            "Here's the plan.",
+            vec!["read_file".into(), "web_search".into()],
+            Some("Check the budget first.\n\nThen dates.".into()),
+        );
+        s.messages[0].timestamp = Some("2026-03-01T14:03:22".into());
+        s
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!(
+            "md".parse::<ExportFormat>().unwrap(),
+            ExportFormat::Markdown
+        );
+        assert_eq!("HTML".parse::<ExportFormat>().unwrap(), ExportFormat::Html);
+        assert_eq!("json".parse::<ExportFormat>().unwrap(), ExportFormat::Json);
+        assert!("pdf".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn markdown_includes_timestamps_tools_and_reasoning() {
+        let md = export_session(&session(), ExportFormat::Markdown);
+        assert!(md.starts_with("# Conversation: telegram:42\n"));
+        assert!(md.contains("- Messages: 2\n"));
+        assert!(md.contains("### User · 2026-03-01 14:03:22\n\nPlan the <launch> & budget\n"));
+        assert!(md.contains("*Tools used: `read_file`, `web_search`*"));
+        assert!(md.contains("> Check the budget first.\n>\n> Then dates.\n"));
+    }
+
+    #[test]
+    fn html_escapes_content() {
+        let html = export_session(&session(), ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Plan the &lt;launch&gt; &amp; budget"));
+        assert!(html.contains("<time>2026-03-01 14:03:22</time>"));
+        assert!(html.contains("<code>web_search</code>"));
+        assert!(html.contains("<details><summary>Reasoning</summary>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn json_round_trips_messages() {
+        let json = export_session(&session(), ExportFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["key"], "telegram:42");
+        assert_eq!(value["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(value["messages"][1]["tools_used"][1], "web_search");
+    }
+}
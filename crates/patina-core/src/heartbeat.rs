@@ -1,12 +1,18 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
-use tokio::sync::mpsc;
+use chrono::Utc;
+use patina_config::TaskPickupConfig;
+use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinHandle;
 use tracing::{info, warn};
 
 use crate::bus::InboundMessage;
+use crate::task::{Task, TaskManager, TaskPriority, TaskStatus};
+use crate::tools::task::TaskTool;
+use crate::watchdog::{Watchdog, HEARTBEAT};
 
 const DEFAULT_INTERVAL_SECS: u64 = 30 * 60; // 30 minutes
 
@@ -20,6 +26,9 @@ pub struct HeartbeatService {
     workspace: PathBuf,
     interval: Duration,
     inbound_tx: mpsc::Sender<InboundMessage>,
+    model_tier: Option<String>,
+    pickup: Option<Arc<TaskPickup>>,
+    watchdog: Option<Arc<Watchdog>>,
     handle: Option<JoinHandle<()>>,
 }
 
@@ -33,28 +42,67 @@ impl HeartbeatService {
             workspace,
             interval: Duration::from_secs(interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS)),
             inbound_tx,
+            model_tier: None,
+            pickup: None,
+            watchdog: None,
             handle: None,
         }
     }
 
+    /// Answer heartbeats with this model tier instead of the default one.
+    pub fn with_model_tier(mut self, tier: &str) -> Self {
+        self.model_tier = Some(tier.to_string()).filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Also pick up waiting tasks for autonomous personas on every beat.
+    pub fn with_task_pickup(mut self, pickup: TaskPickup) -> Self {
+        self.pickup = Some(Arc::new(pickup));
+        self
+    }
+
+    /// Beat for the watchdog on every tick, so it notices the loop stopping.
+    pub fn with_watchdog(mut self, watchdog: Arc<Watchdog>) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
     /// Start the heartbeat background loop.
     pub fn start(&mut self) {
         let workspace = self.workspace.clone();
         let interval = self.interval;
         let inbound_tx = self.inbound_tx.clone();
+        let model_tier = self.model_tier.clone();
+        let pickup = self.pickup.clone();
+        let watchdog = self.watchdog.clone();
 
         self.handle = Some(tokio::spawn(async move {
             info!(
                 "Heartbeat service started (interval: {}s)",
                 interval.as_secs()
             );
+            // The first tick is a whole interval away
+            if let Some(watchdog) = &watchdog {
+                watchdog.beat(HEARTBEAT, Utc::now().timestamp_millis());
+            }
 
             loop {
                 tokio::time::sleep(interval).await;
 
-                if let Err(e) = tick(&workspace, &inbound_tx).await {
+                let result = tick(&workspace, &inbound_tx, model_tier.as_deref()).await;
+                if let Some(watchdog) = &watchdog {
+                    let now_ms = Utc::now().timestamp_millis();
+                    watchdog.beat(HEARTBEAT, now_ms);
+                    if let Err(e) = &result {
+                        watchdog.record(HEARTBEAT, Err(e.to_string()), now_ms);
+                    }
+                }
+                if let Err(e) = result {
                     warn!("Heartbeat tick error: {e}");
                 }
+                if let Some(pickup) = &pickup {
+                    pickup.run().await;
+                }
             }
         }));
     }
@@ -69,7 +117,16 @@ impl HeartbeatService {
 
     /// Trigger a heartbeat check immediately (for testing).
     pub async fn trigger_now(&self) -> anyhow::Result<()> {
-        tick(&self.workspace, &self.inbound_tx).await
+        tick(
+            &self.workspace,
+            &self.inbound_tx,
+            self.model_tier.as_deref(),
+        )
+        .await?;
+        if let Some(pickup) = &self.pickup {
+            pickup.run().await;
+        }
+        Ok(())
     }
 
     /// Path to the heartbeat file.
@@ -78,8 +135,118 @@ impl HeartbeatService {
     }
 }
 
+/// Channels that can't take a message nobody asked for.
+const INTERNAL_CHANNELS: &[&str] = &["cli", "system", "task", "cron", "heartbeat", "subagent"];
+
+/// Starts subagents for todo tasks assigned to autonomous personas.
+///
+/// Each picked-up task runs as its assignee, the same way as `assign` with
+/// `auto_execute`. The completion message carries `pickup` and the chat to
+/// notify, so the gateway can post the result there; a failed run moves the
+/// task to backlog rather than back to todo, so it isn't retried every beat.
+pub struct TaskPickup {
+    config: TaskPickupConfig,
+    tasks: Arc<Mutex<TaskManager>>,
+    task_tool: Arc<TaskTool>,
+    /// Tasks started here, dropped once they leave in_progress.
+    started: Mutex<Vec<String>>,
+}
+
+impl TaskPickup {
+    pub fn new(
+        config: TaskPickupConfig,
+        tasks: Arc<Mutex<TaskManager>>,
+        task_tool: Arc<TaskTool>,
+    ) -> Self {
+        Self {
+            config,
+            tasks,
+            task_tool,
+            started: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Start as many waiting tasks as the concurrency budget allows.
+    /// Returns the IDs of the tasks started.
+    pub async fn run(&self) -> Vec<String> {
+        let picked: Vec<(String, Option<(String, String)>)> = {
+            let mut tm = self.tasks.lock().await;
+            let tasks = tm.all_tasks();
+            let mut started = self.started.lock().await;
+            started.retain(|id| {
+                tasks
+                    .iter()
+                    .any(|t| t.id == *id && t.status == TaskStatus::InProgress)
+            });
+            let budget = self.config.max_concurrent.saturating_sub(started.len());
+            pickable(tasks, &self.config.personas, budget)
+                .into_iter()
+                .map(|t| (t.id.clone(), self.notify_target(t)))
+                .collect()
+        };
+
+        let mut ids = Vec::new();
+        for (id, target) in picked {
+            let mut metadata = HashMap::new();
+            metadata.insert("pickup".to_string(), serde_json::json!(true));
+            if let Some((channel, to)) = target {
+                metadata.insert("notify_channel".to_string(), serde_json::json!(channel));
+                metadata.insert("notify_to".to_string(), serde_json::json!(to));
+            }
+            match self.task_tool.execute_task(&id, metadata).await {
+                Ok(subagent_id) => {
+                    info!("Heartbeat: picked up task {id} (subagent {subagent_id})");
+                    self.started.lock().await.push(id.clone());
+                    ids.push(id);
+                }
+                Err(e) => {
+                    warn!("Heartbeat: failed to pick up task {id}: {e}");
+                    let mut tm = self.tasks.lock().await;
+                    let _ = tm.move_task(&id, TaskStatus::Backlog);
+                    let _ = tm.add_comment(&id, "heartbeat", &format!("Pickup failed: {e}"));
+                }
+            }
+        }
+        ids
+    }
+
+    /// Where to post the result: the configured chat, else the chat the
+    /// task was created from.
+    fn notify_target(&self, task: &Task) -> Option<(String, String)> {
+        if !self.config.channel.is_empty() && !self.config.to.is_empty() {
+            return Some((self.config.channel.clone(), self.config.to.clone()));
+        }
+        let (channel, chat_id) = task.created_by.split_once(':')?;
+        (!INTERNAL_CHANNELS.contains(&channel) && !chat_id.is_empty())
+            .then(|| (channel.to_string(), chat_id.to_string()))
+    }
+}
+
+/// Up to `limit` todo tasks assigned to one of `personas`, most urgent
+/// first, then oldest first.
+fn pickable<'a>(tasks: &'a [Task], personas: &[String], limit: usize) -> Vec<&'a Task> {
+    let rank = |p: &TaskPriority| match p {
+        TaskPriority::Urgent => 0,
+        TaskPriority::High => 1,
+        TaskPriority::Medium => 2,
+        TaskPriority::Low => 3,
+    };
+    let mut waiting: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Todo)
+        .filter(|t| t.assignee.as_ref().is_some_and(|a| personas.contains(a)))
+        .collect();
+    waiting.sort_by_key(|t| (rank(&t.priority), t.created_at_ms));
+    waiting.truncate(limit);
+    waiting
+}
+
 /// Run a single heartbeat tick.
-async fn tick(workspace: &Path, inbound_tx: &mpsc::Sender<InboundMessage>) -> anyhow::Result<()> {
+async fn tick(
+    workspace: &Path,
+    inbound_tx: &mpsc::Sender<InboundMessage>,
+    model_tier: Option<&str>,
+) -> anyhow::Result<()> {
     let heartbeat_path = workspace.join("HEARTBEAT.md");
 
     if !heartbeat_path.exists() {
@@ -102,7 +269,9 @@ async fn tick(workspace: &Path, inbound_tx: &mpsc::Sender<InboundMessage>) -> an
         chat_id: "system:heartbeat".to_string(),
         content: HEARTBEAT_PROMPT.to_string(),
         media: Vec::new(),
-        metadata: HashMap::new(),
+        metadata: model_tier
+            .map(|tier| HashMap::from([("model_tier".to_string(), serde_json::json!(tier))]))
+            .unwrap_or_default(),
         timestamp: crate::bus::default_timestamp(),
     };
 
@@ -114,6 +283,15 @@ async fn tick(workspace: &Path, inbound_tx: &mpsc::Sender<InboundMessage>) -> an
     Ok(())
 }
 
+/// Whether a heartbeat reply says nothing needed attention, allowing for
+/// the punctuation or formatting a model wraps around `HEARTBEAT_OK`.
+pub fn is_heartbeat_ok(reply: &str) -> bool {
+    reply
+        .trim()
+        .trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+        == "HEARTBEAT_OK"
+}
+
 /// Check if HEARTBEAT.md contains only structural content (no actionable tasks).
 ///
 /// Skips: empty lines, lines starting with #, lines starting with <!--,
@@ -190,6 +368,80 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn heartbeat_ok_replies_are_recognized() {
+        assert!(is_heartbeat_ok("HEARTBEAT_OK"));
+        assert!(is_heartbeat_ok("  **HEARTBEAT_OK**.\n"));
+        assert!(!is_heartbeat_ok("Renewed the certificate. HEARTBEAT_OK"));
+        assert!(!is_heartbeat_ok("The backup disk is 95% full."));
+    }
+
+    #[test]
+    fn pickable_orders_by_priority_then_age() {
+        let task = |id: &str, assignee: Option<&str>, priority, status, created_at_ms| Task {
+            id: id.into(),
+            title: id.into(),
+            description: String::new(),
+            status,
+            priority,
+            assignee: assignee.map(Into::into),
+            tags: Vec::new(),
+            created_by: "telegram:42".into(),
+            created_at_ms,
+            updated_at_ms: created_at_ms,
+            completed_at_ms: None,
+            comments: Vec::new(),
+        };
+        let tasks = [
+            task(
+                "old",
+                Some("coder"),
+                TaskPriority::Medium,
+                TaskStatus::Todo,
+                1,
+            ),
+            task(
+                "new",
+                Some("coder"),
+                TaskPriority::Medium,
+                TaskStatus::Todo,
+                2,
+            ),
+            task(
+                "urgent",
+                Some("coder"),
+                TaskPriority::Urgent,
+                TaskStatus::Todo,
+                3,
+            ),
+            task(
+                "human",
+                Some("alice"),
+                TaskPriority::Urgent,
+                TaskStatus::Todo,
+                0,
+            ),
+            task("nobody", None, TaskPriority::Urgent, TaskStatus::Todo, 0),
+            task(
+                "running",
+                Some("coder"),
+                TaskPriority::Urgent,
+                TaskStatus::InProgress,
+                0,
+            ),
+        ];
+        let personas = vec!["coder".to_string()];
+        let ids = |limit| -> Vec<&str> {
+            pickable(&tasks, &personas, limit)
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect()
+        };
+        assert_eq!(ids(5), ["urgent", "old", "new"]);
+        assert_eq!(ids(2), ["urgent", "old"]);
+        assert!(ids(0).is_empty());
+    }
+
     #[tokio::test]
     async fn trigger_now_emits_inbound_message_when_actionable() {
         let dir = tempdir().unwrap();
@@ -207,6 +459,14 @@ mod tests {
         assert_eq!(msg.channel, "system");
         assert_eq!(msg.sender_id, "heartbeat");
         assert_eq!(msg.chat_id, "system:heartbeat");
+        assert!(!msg.metadata.contains_key("model_tier"));
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let svc =
+            HeartbeatService::new(dir.path().to_path_buf(), tx, Some(1)).with_model_tier("fast");
+        svc.trigger_now().await.unwrap();
+        let msg = rx.recv().await.unwrap();
+        assert_eq!(msg.metadata["model_tier"], "fast");
     }
 
     #[tokio::test]
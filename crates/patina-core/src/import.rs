@@ -0,0 +1,476 @@
+//! Import conversations exported from ChatGPT and Claude as sessions.
+//!
+//! Both services export a `conversations.json` inside a zip. ChatGPT stores
+//! each conversation as a tree of message nodes (edits and regenerations are
+//! branches), so only the branch ending at `current_node`, the one the user
+//! last saw, is imported. Claude stores a flat `chat_messages` list. Tool
+//! calls, hidden system messages, and other non-text parts are left out.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::session::{Message, Session};
+
+/// Where an export came from. Also the channel part of the imported
+/// sessions' keys (`chatgpt:<id>`, `claude:<id>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSource {
+    ChatGpt,
+    Claude,
+}
+
+impl ExportSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ChatGpt => "chatgpt",
+            Self::Claude => "claude",
+        }
+    }
+}
+
+/// One conversation read from an export.
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub source: ExportSource,
+    pub id: String,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<Message>,
+}
+
+impl ImportedConversation {
+    pub fn session_key(&self) -> String {
+        format!("{}:{}", self.source.name(), self.id)
+    }
+
+    /// The conversation as a session, with its title and source in the
+    /// metadata and nothing consolidated yet.
+    pub fn into_session(self) -> Session {
+        let mut session = Session::new(self.session_key());
+        session.created_at = self.created_at;
+        session.updated_at = self.updated_at;
+        session.metadata.insert("title".into(), self.title.into());
+        session
+            .metadata
+            .insert("imported_from".into(), self.source.name().into());
+        session.messages = self.messages;
+        session
+    }
+}
+
+/// Read the conversations in an export: the zip as downloaded, or the
+/// `conversations.json` taken out of it. Sorted oldest first; conversations
+/// without any text are dropped.
+pub fn read_export(path: &Path) -> Result<Vec<ImportedConversation>> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let json = if bytes.starts_with(b"PK\x03\x04") {
+        conversations_from_zip(&bytes)
+            .with_context(|| format!("failed to read conversations from {}", path.display()))?
+    } else {
+        String::from_utf8(bytes).context("export is neither a zip nor a JSON file")?
+    };
+    parse_conversations(&json)
+}
+
+fn conversations_from_zip(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let name = archive
+        .file_names()
+        .filter(|n| n.rsplit('/').next() == Some("conversations.json"))
+        .min_by_key(|n| n.len())
+        .map(str::to_string)
+        .context("no conversations.json in the zip")?;
+    let mut json = String::new();
+    archive.by_name(&name)?.read_to_string(&mut json)?;
+    Ok(json)
+}
+
+/// Parse a `conversations.json` from either service, telling them apart by
+/// their fields.
+pub fn parse_conversations(json: &str) -> Result<Vec<ImportedConversation>> {
+    let items: Vec<Value> =
+        serde_json::from_str(json).context("conversations.json isn't a JSON list")?;
+    let mut conversations = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        let conversation = if item.get("mapping").is_some() {
+            serde_json::from_value(item).map(chatgpt_conversation)
+        } else if item.get("chat_messages").is_some() {
+            serde_json::from_value(item).map(claude_conversation)
+        } else {
+            anyhow::bail!("conversation {i} is neither a ChatGPT nor a Claude conversation");
+        };
+        let conversation = conversation.with_context(|| format!("malformed conversation {i}"))?;
+        if !conversation.messages.is_empty() {
+            conversations.push(conversation);
+        }
+    }
+    conversations.sort_by_key(|c| c.created_at);
+    Ok(conversations)
+}
+
+#[derive(Deserialize)]
+struct GptConversation {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    update_time: Option<f64>,
+    mapping: HashMap<String, GptNode>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GptNode {
+    #[serde(default)]
+    message: Option<GptMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GptMessage {
+    author: GptAuthor,
+    #[serde(default)]
+    create_time: Option<f64>,
+    content: GptContent,
+    #[serde(default)]
+    recipient: Option<String>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+struct GptAuthor {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct GptContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<Value>,
+}
+
+fn chatgpt_conversation(conv: GptConversation) -> ImportedConversation {
+    let created = conv
+        .create_time
+        .and_then(from_epoch)
+        .unwrap_or_else(Utc::now);
+    let updated = conv.update_time.and_then(from_epoch).unwrap_or(created);
+
+    // Walk up from the last node shown, or else the newest leaf
+    let leaf = conv
+        .current_node
+        .filter(|id| conv.mapping.contains_key(id))
+        .or_else(|| {
+            conv.mapping
+                .iter()
+                .filter(|(_, node)| node.children.is_empty())
+                .max_by(|a, b| node_time(a.1).total_cmp(&node_time(b.1)))
+                .map(|(id, _)| id.clone())
+        });
+    let mut branch = Vec::new();
+    let mut next = leaf;
+    while let Some(node) = next.and_then(|id| conv.mapping.get(&id)) {
+        // A malformed tree could loop
+        if branch.len() > conv.mapping.len() {
+            break;
+        }
+        branch.push(node);
+        next = node.parent.clone();
+    }
+    branch.reverse();
+
+    let messages = branch
+        .into_iter()
+        .filter_map(|node| node.message.as_ref())
+        .filter_map(|msg| {
+            let role = match msg.author.role.as_str() {
+                "user" => "user",
+                "assistant" => "assistant",
+                _ => return None,
+            };
+            let hidden = msg.metadata["is_visually_hidden_from_conversation"] == true;
+            let to_tool = msg.recipient.as_deref().is_some_and(|r| r != "all");
+            if hidden || to_tool {
+                return None;
+            }
+            let text = match msg.content.content_type.as_str() {
+                "text" | "multimodal_text" => gpt_parts_text(&msg.content.parts),
+                _ => return None,
+            };
+            let time = msg.create_time.and_then(from_epoch).unwrap_or(created);
+            message(role, &text, time)
+        })
+        .collect();
+
+    ImportedConversation {
+        source: ExportSource::ChatGpt,
+        id: conv
+            .id
+            .or(conv.conversation_id)
+            .unwrap_or_else(|| created.timestamp().to_string()),
+        title: conv.title.unwrap_or_default(),
+        created_at: created,
+        updated_at: updated,
+        messages,
+    }
+}
+
+fn node_time(node: &GptNode) -> f64 {
+    node.message
+        .as_ref()
+        .and_then(|m| m.create_time)
+        .unwrap_or_default()
+}
+
+/// The text of a ChatGPT message, with uploaded images marked.
+fn gpt_parts_text(parts: &[Value]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(obj)
+                if obj.get("content_type").and_then(Value::as_str)
+                    == Some("image_asset_pointer") =>
+            {
+                Some("[image]".into())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Deserialize)]
+struct ClaudeConversation {
+    uuid: String,
+    #[serde(default)]
+    name: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    chat_messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeMessage {
+    sender: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    content: Vec<Value>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+}
+
+fn claude_conversation(conv: ClaudeConversation) -> ImportedConversation {
+    let messages = conv
+        .chat_messages
+        .iter()
+        .filter_map(|msg| {
+            let role = match msg.sender.as_str() {
+                "human" => "user",
+                "assistant" => "assistant",
+                _ => return None,
+            };
+            // Newer exports split the text into content blocks, next to
+            // tool use, and may leave `text` empty
+            let blocks: Vec<&str> = msg
+                .content
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect();
+            let text = if blocks.is_empty() {
+                msg.text.clone()
+            } else {
+                blocks.join("\n")
+            };
+            message(role, &text, msg.created_at.unwrap_or(conv.created_at))
+        })
+        .collect();
+
+    ImportedConversation {
+        source: ExportSource::Claude,
+        id: conv.uuid,
+        title: conv.name.unwrap_or_default(),
+        created_at: conv.created_at,
+        updated_at: conv.updated_at.unwrap_or(conv.created_at),
+        messages,
+    }
+}
+
+/// A session message, timestamped in local time like live ones. `None` for
+/// empty text.
+fn message(role: &str, text: &str, time: DateTime<Utc>) -> Option<Message> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(Message {
+        role: role.into(),
+        content: text.into(),
+        timestamp: Some(
+            time.with_timezone(&Local)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string(),
+        ),
+        tools_used: None,
+        reasoning_content: None,
+    })
+}
+
+fn from_epoch(secs: f64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(secs.trunc() as i64, 0).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn chatgpt_export() -> Value {
+        // root -> system -> question -> (first answer | regenerated answer)
+        json!([{
+            "id": "c1",
+            "title": "Sourdough",
+            "create_time": 1_700_000_000.5,
+            "update_time": 1_700_000_600.0,
+            "current_node": "a2",
+            "mapping": {
+                "root": { "message": null, "parent": null, "children": ["sys"] },
+                "sys": {
+                    "message": {
+                        "author": { "role": "system" },
+                        "content": { "content_type": "text", "parts": [""] },
+                        "metadata": { "is_visually_hidden_from_conversation": true }
+                    },
+                    "parent": "root", "children": ["q"]
+                },
+                "q": {
+                    "message": {
+                        "author": { "role": "user" },
+                        "create_time": 1_700_000_100.0,
+                        "content": {
+                            "content_type": "multimodal_text",
+                            "parts": [{ "content_type": "image_asset_pointer" }, "Why is my starter flat?"]
+                        }
+                    },
+                    "parent": "sys", "children": ["a1", "a2"]
+                },
+                "a1": {
+                    "message": {
+                        "author": { "role": "assistant" },
+                        "content": { "content_type": "text", "parts": ["First try"] }
+                    },
+                    "parent": "q", "children": []
+                },
+                "a2": {
+                    "message": {
+                        "author": { "role": "assistant" },
+                        "create_time": 1_700_000_200.0,
+                        "content": { "content_type": "text", "parts": ["Feed it more often."] }
+                    },
+                    "parent": "q", "children": []
+                }
+            }
+        }])
+    }
+
+    #[test]
+    fn chatgpt_follows_the_current_branch() {
+        let conversations = parse_conversations(&chatgpt_export().to_string()).unwrap();
+        assert_eq!(conversations.len(), 1);
+        let conv = &conversations[0];
+        assert_eq!(conv.session_key(), "chatgpt:c1");
+        assert_eq!(conv.title, "Sourdough");
+        let texts: Vec<(&str, &str)> = conv
+            .messages
+            .iter()
+            .map(|m| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                ("user", "[image]\nWhy is my starter flat?"),
+                ("assistant", "Feed it more often.")
+            ]
+        );
+
+        let session = conversations[0].clone().into_session();
+        assert_eq!(session.created_at.timestamp(), 1_700_000_000);
+        assert_eq!(session.metadata["imported_from"], "chatgpt");
+        assert_eq!(session.last_consolidated, 0);
+    }
+
+    #[test]
+    fn claude_prefers_text_blocks_and_sorts_oldest_first() {
+        let export = json!([
+            {
+                "uuid": "later",
+                "name": "Trip",
+                "created_at": "2024-06-02T10:00:00Z",
+                "chat_messages": [
+                    { "sender": "human", "text": "Plan Lisbon", "content": [] },
+                    {
+                        "sender": "assistant",
+                        "text": "",
+                        "created_at": "2024-06-02T10:00:05Z",
+                        "content": [
+                            { "type": "tool_use", "name": "web_search" },
+                            { "type": "text", "text": "Day one: Alfama." }
+                        ]
+                    }
+                ]
+            },
+            {
+                "uuid": "earlier",
+                "created_at": "2024-01-01T00:00:00Z",
+                "chat_messages": [{ "sender": "human", "text": "Hi" }]
+            },
+            { "uuid": "empty", "created_at": "2023-01-01T00:00:00Z", "chat_messages": [] }
+        ]);
+        let conversations = parse_conversations(&export.to_string()).unwrap();
+        let keys: Vec<String> = conversations.iter().map(|c| c.session_key()).collect();
+        assert_eq!(keys, ["claude:earlier", "claude:later"]);
+        assert_eq!(conversations[1].messages[0].role, "user");
+        assert_eq!(conversations[1].messages[1].content, "Day one: Alfama.");
+
+        assert!(parse_conversations(r#"[{"foo": 1}]"#).is_err());
+    }
+
+    #[test]
+    fn reads_conversations_from_a_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("chat.html", options).unwrap();
+        zip.write_all(b"<html></html>").unwrap();
+        zip.start_file("conversations.json", options).unwrap();
+        zip.write_all(chatgpt_export().to_string().as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let conversations = read_export(&path).unwrap();
+        assert_eq!(conversations[0].session_key(), "chatgpt:c1");
+    }
+}
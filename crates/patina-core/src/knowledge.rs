@@ -0,0 +1,880 @@
+//! Knowledge base: documents the agent can answer from.
+//!
+//! The `knowledge` config points at folders, files, web pages, and
+//! [connectors](crate::connectors) for Notion and Google Drive. Each
+//! [`KnowledgeBase::refresh`] reads them, splits changed documents into
+//! chunks (the same ~1600-character line chunks as the memory index),
+//! embeds the chunks through an OpenAI-compatible `/embeddings` endpoint,
+//! and stores text and vectors in SQLite. [`KnowledgeBase::search`] embeds
+//! the query and returns the closest passages with their source and lines,
+//! which the `knowledge_search` tool hands to the model to cite.
+//!
+//! Like the memory index, the database is a cache (`knowledge.sqlite`, safe
+//! to delete); the documents remain the source of truth.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use patina_config::{Config, KnowledgeConfig};
+use rusqlite::params;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::agent::memory_index::{chunk_text, hex_sha256};
+use crate::connectors::Connector;
+use crate::db::{add_column, Db, Migration, Step};
+use crate::tools::web::WebFetchTool;
+use crate::tools::Tool;
+use crate::usage::{estimate_tokens, UsageRecord, UsageTracker};
+
+/// Characters kept from a fetched web page.
+const MAX_URL_CHARS: usize = 200_000;
+
+/// The index schema. The connector columns of `documents` are added only
+/// where missing, since files from before migrations may have them already.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "documents and chunks",
+        up: Step::Sql(
+            "CREATE TABLE IF NOT EXISTS documents (
+                source TEXT PRIMARY KEY,
+                hash TEXT NOT NULL,
+                indexed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                end_line INTEGER NOT NULL,
+                hash TEXT NOT NULL,
+                text TEXT NOT NULL,
+                embedding BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_knowledge_source ON chunks(source);
+            CREATE INDEX IF NOT EXISTS idx_knowledge_hash ON chunks(hash);",
+        ),
+    },
+    Migration {
+        name: "document origin and version",
+        up: Step::Code(|tx| {
+            add_column(tx, "documents", "origin", "TEXT")?;
+            add_column(tx, "documents", "version", "TEXT")
+        }),
+    },
+];
+
+/// Turns text into vectors.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// One vector per text, in order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// An OpenAI-compatible `/embeddings` endpoint (OpenAI, Ollama's `/v1`,
+/// and most hosted providers).
+pub struct HttpEmbedder {
+    http: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    provider: String,
+    model: String,
+    batch_size: usize,
+    usage_tracker: Option<Arc<UsageTracker>>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+    #[serde(default)]
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl HttpEmbedder {
+    /// Build the embedder for `knowledge.embedding`. `api_key` is the
+    /// resolved key, if the endpoint needs one.
+    pub fn from_config(config: &Config, api_key: Option<String>) -> Result<Self> {
+        let embedding = &config.knowledge.embedding;
+        let provider_base = |p: &Option<patina_config::ProviderConfig>| {
+            p.as_ref()
+                .and_then(|p| p.api_base.clone())
+                .filter(|b| !b.is_empty())
+        };
+        let base = match embedding.api_base.clone().filter(|b| !b.is_empty()) {
+            Some(base) => base,
+            None => match embedding.provider.as_str() {
+                "ollama" => {
+                    let base = provider_base(&config.providers.ollama)
+                        .unwrap_or_else(|| crate::ollama::DEFAULT_BASE_URL.to_string());
+                    format!("{}/v1", base.trim_end_matches('/').trim_end_matches("/v1"))
+                }
+                "openai" => provider_base(&config.providers.openai)
+                    .unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
+                other => bail!(
+                    "knowledge.embedding.provider '{other}' needs an apiBase (or use ollama or openai)"
+                ),
+            },
+        };
+        Ok(Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(120))
+                .build()?,
+            url: format!("{}/embeddings", base.trim_end_matches('/')),
+            api_key,
+            provider: embedding.provider.clone(),
+            model: embedding.model.clone(),
+            batch_size: embedding.batch_size.max(1),
+            usage_tracker: None,
+        })
+    }
+
+    /// Record the tokens of each request as `embedding` usage.
+    pub fn with_usage_tracker(mut self, tracker: Arc<UsageTracker>) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+
+    fn record_usage(&self, batch: &[String], reported: Option<EmbeddingUsage>) {
+        let Some(ref tracker) = self.usage_tracker else {
+            return;
+        };
+        // Not every endpoint reports usage; estimate from the text instead
+        let tokens = reported
+            .map(|u| u.prompt_tokens)
+            .filter(|&t| t > 0)
+            .unwrap_or_else(|| batch.iter().map(|t| estimate_tokens(t)).sum());
+        tracker.record(&UsageRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            model: self.model.clone(),
+            provider: self.provider.clone(),
+            agent: "knowledge".to_string(),
+            input_tokens: tokens,
+            total_tokens: tokens,
+            call_type: "embedding".to_string(),
+            ..Default::default()
+        });
+    }
+}
+
+#[async_trait]
+impl Embedder for HttpEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            let mut request = self.http.post(&self.url).json(&serde_json::json!({
+                "model": self.model,
+                "input": batch,
+            }));
+            if let Some(ref key) = self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                bail!("embedding request failed ({status}): {}", body.trim());
+            }
+            let EmbeddingResponse { mut data, usage } = response.json().await?;
+            if data.len() != batch.len() {
+                bail!(
+                    "embedding endpoint returned {} vectors for {} texts",
+                    data.len(),
+                    batch.len()
+                );
+            }
+            self.record_usage(batch, usage);
+            data.sort_by_key(|d| d.index);
+            vectors.extend(data.into_iter().map(|d| d.embedding));
+        }
+        Ok(vectors)
+    }
+}
+
+/// A retrieved chunk of a document.
+#[derive(Debug, Clone)]
+pub struct Passage {
+    /// File path (under its configured folder) or URL, for citing.
+    pub source: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    /// Cosine similarity to the query.
+    pub score: f32,
+}
+
+/// What a refresh did.
+#[derive(Debug, Default, PartialEq)]
+pub struct RefreshReport {
+    pub documents: usize,
+    pub updated: usize,
+    pub removed: usize,
+    /// Chunks sent to the embedder; unchanged chunks reuse their vectors.
+    pub embedded: usize,
+    pub failed: usize,
+}
+
+/// A document read for indexing.
+struct Document {
+    source: String,
+    text: String,
+    /// The connector it came from, if any.
+    origin: Option<String>,
+    /// The connector's version of it, to skip fetching it unchanged.
+    version: Option<String>,
+}
+
+/// What the index knows about a document.
+struct Indexed {
+    hash: String,
+    origin: Option<String>,
+    version: Option<String>,
+}
+
+/// Embedded document chunks in SQLite.
+pub struct KnowledgeBase {
+    db: Db,
+    embedder: Arc<dyn Embedder>,
+    /// Configured folders and files, resolved.
+    roots: Vec<PathBuf>,
+    urls: Vec<String>,
+    extensions: Vec<String>,
+    connectors: Vec<Arc<dyn Connector>>,
+    top_k: usize,
+}
+
+impl KnowledgeBase {
+    /// Open (or create) the index at `db_path`. Relative paths in `config`
+    /// are resolved against `workspace`.
+    pub fn open(
+        config: &KnowledgeConfig,
+        workspace: &Path,
+        db_path: &Path,
+        embedder: Arc<dyn Embedder>,
+    ) -> Result<Self> {
+        let roots = config
+            .paths
+            .iter()
+            .map(|p| {
+                let path = patina_config::resolve_workspace(p);
+                if path.is_relative() {
+                    workspace.join(path)
+                } else {
+                    path
+                }
+            })
+            .collect();
+        Ok(Self {
+            db: Db::open(db_path, MIGRATIONS)?,
+            embedder,
+            roots,
+            urls: config.urls.clone(),
+            extensions: config
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .collect(),
+            connectors: Vec::new(),
+            top_k: config.top_k.max(1),
+        })
+    }
+
+    /// Also sync the documents of `connectors` on each refresh.
+    pub fn with_connectors(mut self, connectors: Vec<Arc<dyn Connector>>) -> Self {
+        self.connectors = connectors;
+        self
+    }
+
+    /// Passages returned when the caller doesn't ask for a number.
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+
+    /// Re-read every source and re-embed the documents that changed.
+    /// Documents that no longer exist are dropped; a URL or connector
+    /// document that can't be fetched keeps its last indexed copy, as do
+    /// all of a connector's documents when it can't list them.
+    pub async fn refresh(&self) -> Result<RefreshReport> {
+        let mut report = RefreshReport::default();
+        let roots = self.roots.clone();
+        let extensions = self.extensions.clone();
+        let (mut documents, unreadable) =
+            tokio::task::spawn_blocking(move || read_files(&roots, &extensions)).await?;
+        report.failed += unreadable;
+
+        let mut seen: HashSet<String> = documents.iter().map(|d| d.source.clone()).collect();
+        let fetcher = WebFetchTool::new(MAX_URL_CHARS);
+        for url in &self.urls {
+            seen.insert(url.clone());
+            match fetch_url(&fetcher, url).await {
+                Ok(text) => documents.push(Document {
+                    source: url.clone(),
+                    text,
+                    origin: None,
+                    version: None,
+                }),
+                Err(e) => {
+                    warn!("Knowledge: couldn't fetch {url}: {e}");
+                    report.failed += 1;
+                }
+            }
+        }
+
+        let indexed: HashMap<String, Indexed> = {
+            let conn = self.db.conn()?;
+            let mut stmt = conn.prepare("SELECT source, hash, origin, version FROM documents")?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get(0)?,
+                        Indexed {
+                            hash: row.get(1)?,
+                            origin: row.get(2)?,
+                            version: row.get(3)?,
+                        },
+                    ))
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            rows
+        };
+
+        for connector in &self.connectors {
+            let name = connector.name();
+            let listed = match connector.list().await {
+                Ok(listed) => listed,
+                Err(e) => {
+                    warn!("Knowledge: couldn't list {name} documents: {e}");
+                    report.failed += 1;
+                    seen.extend(
+                        indexed
+                            .iter()
+                            .filter(|(_, i)| i.origin.as_deref() == Some(name))
+                            .map(|(source, _)| source.clone()),
+                    );
+                    continue;
+                }
+            };
+            for remote in listed {
+                seen.insert(remote.source.clone());
+                let unchanged = indexed.get(&remote.source).is_some_and(|i| {
+                    i.origin.as_deref() == Some(name)
+                        && i.version.as_deref() == Some(remote.version.as_str())
+                });
+                if unchanged {
+                    continue;
+                }
+                match connector.fetch(&remote).await {
+                    Ok(text) => documents.push(Document {
+                        source: remote.source,
+                        text,
+                        origin: Some(name.to_string()),
+                        version: Some(remote.version),
+                    }),
+                    Err(e) => {
+                        warn!(
+                            "Knowledge: couldn't fetch {name} document {}: {e}",
+                            remote.source
+                        );
+                        report.failed += 1;
+                    }
+                }
+            }
+        }
+        report.documents = seen.len();
+
+        for doc in documents {
+            let hash = hex_sha256(&doc.text);
+            if let Some(i) = indexed.get(&doc.source).filter(|i| i.hash == hash) {
+                if doc.version.is_some() && (i.version != doc.version || i.origin != doc.origin) {
+                    self.db.conn()?.execute(
+                        "UPDATE documents SET origin = ?2, version = ?3 WHERE source = ?1",
+                        params![doc.source, doc.origin, doc.version],
+                    )?;
+                }
+                continue;
+            }
+            match self.index_document(&doc, &hash).await {
+                Ok(embedded) => {
+                    report.updated += 1;
+                    report.embedded += embedded;
+                }
+                Err(e) => {
+                    warn!("Knowledge: couldn't index {}: {e}", doc.source);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        report.removed = self.db.write(|tx| {
+            let mut removed = 0;
+            for source in indexed.keys().filter(|s| !seen.contains(*s)) {
+                tx.execute("DELETE FROM chunks WHERE source = ?1", params![source])?;
+                tx.execute("DELETE FROM documents WHERE source = ?1", params![source])?;
+                removed += 1;
+            }
+            Ok(removed)
+        })?;
+        Ok(report)
+    }
+
+    /// Replace a document's chunks. Returns how many chunks were embedded.
+    async fn index_document(&self, doc: &Document, hash: &str) -> Result<usize> {
+        let chunks: Vec<_> = chunk_text(&doc.text)
+            .into_iter()
+            .filter(|c| !c.text.trim().is_empty())
+            .map(|c| {
+                let hash = hex_sha256(&c.text);
+                (c, hash)
+            })
+            .collect();
+
+        // Chunks whose text is already indexed anywhere keep their vector
+        let mut vectors: HashMap<String, Vec<u8>> = HashMap::new();
+        {
+            let conn = self.db.conn()?;
+            let mut stmt = conn.prepare("SELECT embedding FROM chunks WHERE hash = ?1 LIMIT 1")?;
+            for (_, hash) in &chunks {
+                let existing: Option<Vec<u8>> =
+                    stmt.query_row(params![hash], |row| row.get(0)).ok();
+                if let Some(blob) = existing {
+                    vectors.insert(hash.clone(), blob);
+                }
+            }
+        }
+        let mut missing: Vec<(String, String)> = Vec::new();
+        for (chunk, hash) in &chunks {
+            if !vectors.contains_key(hash) && !missing.iter().any(|(h, _)| h == hash) {
+                missing.push((hash.clone(), chunk.text.clone()));
+            }
+        }
+        let texts: Vec<String> = missing.iter().map(|(_, t)| t.clone()).collect();
+        let embedded = if texts.is_empty() {
+            Vec::new()
+        } else {
+            self.embedder.embed(&texts).await?
+        };
+        if embedded.len() != texts.len() {
+            bail!(
+                "got {} embeddings for {} chunks",
+                embedded.len(),
+                texts.len()
+            );
+        }
+        for ((hash, _), vector) in missing.iter().zip(embedded) {
+            vectors.insert(hash.clone(), to_blob(&vector));
+        }
+        debug!(
+            "Knowledge: {} has {} chunks, {} embedded",
+            doc.source,
+            chunks.len(),
+            missing.len()
+        );
+
+        self.db.write(|tx| {
+            tx.execute("DELETE FROM chunks WHERE source = ?1", params![doc.source])?;
+            for (chunk, hash) in &chunks {
+                tx.execute(
+                    "INSERT INTO chunks (source, start_line, end_line, hash, text, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        doc.source,
+                        chunk.start_line as i64,
+                        chunk.end_line as i64,
+                        hash,
+                        chunk.text,
+                        vectors[hash],
+                    ],
+                )?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO documents (source, hash, indexed_at, origin, version)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    doc.source,
+                    hash,
+                    chrono::Utc::now().to_rfc3339(),
+                    doc.origin,
+                    doc.version
+                ],
+            )?;
+            Ok(())
+        })?;
+        Ok(missing.len())
+    }
+
+    /// The `limit` passages most similar to `query`, best first.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<Passage>> {
+        let query = self.embedder.embed(&[query.to_string()]).await?;
+        let Some(query) = query.into_iter().next() else {
+            return Ok(Vec::new());
+        };
+        let conn = self.db.conn()?;
+        let mut stmt =
+            conn.prepare("SELECT source, start_line, end_line, text, embedding FROM chunks")?;
+        let mut passages: Vec<Passage> = stmt
+            .query_map([], |row| {
+                let blob: Vec<u8> = row.get(4)?;
+                Ok(Passage {
+                    source: row.get(0)?,
+                    start_line: row.get::<_, i64>(1)? as usize,
+                    end_line: row.get::<_, i64>(2)? as usize,
+                    text: row.get(3)?,
+                    score: cosine(&query, &from_blob(&blob)),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+        passages.truncate(limit);
+        Ok(passages)
+    }
+
+    /// Number of indexed documents and chunks.
+    pub fn counts(&self) -> Result<(usize, usize)> {
+        self.db.read(|conn| {
+            let documents: i64 =
+                conn.query_row("SELECT COUNT(*) FROM documents", [], |r| r.get(0))?;
+            let chunks: i64 = conn.query_row("SELECT COUNT(*) FROM chunks", [], |r| r.get(0))?;
+            Ok((documents as usize, chunks as usize))
+        })
+    }
+}
+
+/// Refresh `kb` now and then every `interval` (if any), logging the result.
+pub async fn keep_fresh(kb: Arc<KnowledgeBase>, interval: Option<Duration>) {
+    loop {
+        let started = std::time::Instant::now();
+        match kb.refresh().await {
+            Ok(r) if r.updated > 0 || r.removed > 0 || r.failed > 0 => info!(
+                "Knowledge refreshed in {:.2?}: {} documents, {} updated, {} removed, {} failed",
+                started.elapsed(),
+                r.documents,
+                r.updated,
+                r.removed,
+                r.failed
+            ),
+            Ok(_) => debug!("Knowledge unchanged"),
+            Err(e) => warn!("Knowledge refresh failed: {e}"),
+        }
+        match interval {
+            Some(interval) => tokio::time::sleep(interval).await,
+            None => return,
+        }
+    }
+}
+
+/// Read every matching file under `roots`. Sources are the path from the
+/// root's parent, e.g. `handbook/onboarding.md`. Returns the documents and
+/// the number of files that couldn't be read.
+fn read_files(roots: &[PathBuf], extensions: &[String]) -> (Vec<Document>, usize) {
+    let mut documents = Vec::new();
+    let mut failed = 0;
+    for root in roots {
+        let base = root.parent().unwrap_or(root);
+        let files = if root.is_file() {
+            vec![root.clone()]
+        } else {
+            let mut files = Vec::new();
+            walk(root, extensions, &mut files);
+            files.sort();
+            files
+        };
+        if files.is_empty() && !root.exists() {
+            warn!("Knowledge path {} doesn't exist", root.display());
+        }
+        for file in files {
+            match std::fs::read_to_string(&file) {
+                Ok(text) => documents.push(Document {
+                    source: file
+                        .strip_prefix(base)
+                        .unwrap_or(&file)
+                        .to_string_lossy()
+                        .to_string(),
+                    text,
+                    origin: None,
+                    version: None,
+                }),
+                Err(e) => {
+                    warn!("Knowledge: couldn't read {}: {e}", file.display());
+                    failed += 1;
+                }
+            }
+        }
+    }
+    (documents, failed)
+}
+
+/// Collect files under `dir` with one of `extensions`, skipping hidden
+/// entries.
+fn walk(dir: &Path, extensions: &[String], out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, extensions, out);
+        } else if path
+            .extension()
+            .is_some_and(|e| extensions.contains(&e.to_string_lossy().to_lowercase()))
+        {
+            out.push(path);
+        }
+    }
+}
+
+/// A web page's readable text, via `web_fetch`.
+async fn fetch_url(fetcher: &WebFetchTool, url: &str) -> Result<String> {
+    let output = fetcher.execute(serde_json::json!({ "url": url })).await?;
+    let value: serde_json::Value = serde_json::from_str(&output)?;
+    if let Some(error) = value.get("error").and_then(|e| e.as_str()) {
+        bail!("{error}");
+    }
+    let status = value.get("status").and_then(|s| s.as_u64()).unwrap_or(0);
+    if !(200..300).contains(&status) {
+        bail!("HTTP {status}");
+    }
+    Ok(value
+        .get("text")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::RemoteDocument;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Bag-of-words vectors over a tiny vocabulary, counting calls.
+    #[derive(Default)]
+    struct WordEmbedder {
+        embedded: AtomicUsize,
+    }
+
+    const VOCAB: &[&str] = &["vacation", "days", "expense", "laptop", "deploy", "friday"];
+
+    #[async_trait]
+    impl Embedder for WordEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            self.embedded.fetch_add(texts.len(), Ordering::SeqCst);
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    let t = t.to_lowercase();
+                    VOCAB.iter().map(|w| t.matches(w).count() as f32).collect()
+                })
+                .collect())
+        }
+    }
+
+    fn open(dir: &Path, embedder: Arc<WordEmbedder>) -> KnowledgeBase {
+        let config = KnowledgeConfig {
+            enabled: true,
+            paths: vec!["handbook".into()],
+            ..Default::default()
+        };
+        KnowledgeBase::open(&config, dir, &dir.join("knowledge.sqlite"), embedder).unwrap()
+    }
+
+    #[tokio::test]
+    async fn indexes_and_searches_a_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let handbook = dir.path().join("handbook");
+        std::fs::create_dir_all(handbook.join("it")).unwrap();
+        std::fs::write(
+            handbook.join("leave.md"),
+            "# Leave\n\nEveryone gets 25 vacation days a year.",
+        )
+        .unwrap();
+        std::fs::write(
+            handbook.join("it/laptop.md"),
+            "Request a new laptop through the expense form.",
+        )
+        .unwrap();
+        std::fs::write(handbook.join("notes.pdf"), "deploy deploy").unwrap();
+        std::fs::write(handbook.join(".draft.md"), "deploy on friday").unwrap();
+
+        let embedder = Arc::new(WordEmbedder::default());
+        let kb = open(dir.path(), embedder.clone());
+        let report = kb.refresh().await.unwrap();
+        assert_eq!(
+            (report.documents, report.updated, report.embedded),
+            (2, 2, 2)
+        );
+
+        let hits = kb
+            .search("How many vacation days do I get?", 5)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].source, "handbook/leave.md");
+        assert_eq!((hits[0].start_line, hits[0].end_line), (1, 3));
+        assert!(hits[0].score > hits[1].score);
+        let hits = kb.search("new laptop", 1).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].source, "handbook/it/laptop.md");
+
+        // Unchanged files aren't re-embedded; removed ones are dropped
+        let before = embedder.embedded.load(Ordering::SeqCst);
+        std::fs::remove_file(handbook.join("it/laptop.md")).unwrap();
+        let report = kb.refresh().await.unwrap();
+        assert_eq!((report.updated, report.removed), (0, 1));
+        assert_eq!(embedder.embedded.load(Ordering::SeqCst), before);
+        assert_eq!(kb.counts().unwrap(), (1, 1));
+    }
+
+    /// Documents in memory, counting fetches; `list` fails when `down`.
+    #[derive(Default)]
+    struct FakeConnector {
+        docs: Mutex<Vec<(RemoteDocument, String)>>,
+        fetched: AtomicUsize,
+        down: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Connector for FakeConnector {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        async fn list(&self) -> Result<Vec<RemoteDocument>> {
+            if self.down.load(Ordering::SeqCst) {
+                bail!("unreachable");
+            }
+            Ok(self
+                .docs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(d, _)| d.clone())
+                .collect())
+        }
+
+        async fn fetch(&self, doc: &RemoteDocument) -> Result<String> {
+            self.fetched.fetch_add(1, Ordering::SeqCst);
+            let docs = self.docs.lock().unwrap();
+            Ok(docs.iter().find(|(d, _)| d.id == doc.id).unwrap().1.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn connectors_only_fetch_changed_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = |id: &str, version: &str, text: &str| {
+            (
+                RemoteDocument {
+                    id: id.to_string(),
+                    source: format!("https://docs.example/{id}"),
+                    title: id.to_string(),
+                    version: version.to_string(),
+                    kind: String::new(),
+                },
+                text.to_string(),
+            )
+        };
+        let connector = Arc::new(FakeConnector::default());
+        *connector.docs.lock().unwrap() = vec![
+            remote("leave", "1", "25 vacation days"),
+            remote("deploys", "1", "No deploy on friday"),
+        ];
+        let kb = open(dir.path(), Arc::new(WordEmbedder::default()))
+            .with_connectors(vec![connector.clone()]);
+        let report = kb.refresh().await.unwrap();
+        assert_eq!((report.documents, report.updated), (2, 2));
+        assert_eq!(connector.fetched.load(Ordering::SeqCst), 2);
+
+        // Only the edited document is fetched again
+        connector.docs.lock().unwrap()[0] = remote("leave", "2", "30 vacation days");
+        let report = kb.refresh().await.unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(connector.fetched.load(Ordering::SeqCst), 3);
+        let hits = kb.search("vacation", 1).await.unwrap();
+        assert_eq!(hits[0].source, "https://docs.example/leave");
+        assert_eq!(hits[0].text, "30 vacation days");
+
+        // An unreachable source keeps its documents; a removed one is dropped
+        connector.down.store(true, Ordering::SeqCst);
+        let report = kb.refresh().await.unwrap();
+        assert_eq!((report.failed, report.removed), (1, 0));
+        connector.down.store(false, Ordering::SeqCst);
+        connector.docs.lock().unwrap().remove(1);
+        let report = kb.refresh().await.unwrap();
+        assert_eq!((report.removed, report.updated), (1, 0));
+        assert_eq!(kb.counts().unwrap(), (1, 1));
+    }
+
+    #[test]
+    fn embeddings_round_trip_and_compare() {
+        let v = vec![0.5, -1.25, 3.0];
+        assert_eq!(from_blob(&to_blob(&v)), v);
+        assert!((cosine(&v, &v) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn records_embedding_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = Arc::new(UsageTracker::new(&dir.path().join("usage.sqlite")).unwrap());
+        let embedder = HttpEmbedder::from_config(&Config::default(), None)
+            .unwrap()
+            .with_usage_tracker(tracker.clone());
+        let batch = vec!["vacation days".to_string()];
+        embedder.record_usage(&batch, Some(EmbeddingUsage { prompt_tokens: 7 }));
+        // Estimated when the endpoint doesn't say
+        embedder.record_usage(&batch, None);
+
+        let rows = tracker
+            .query_summary(&crate::usage::UsageFilter {
+                group_by: Some("call_type".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(rows[0].group_key, "embedding");
+        assert_eq!(rows[0].calls, 2);
+        assert_eq!(rows[0].input_tokens, 7 + 3);
+    }
+}
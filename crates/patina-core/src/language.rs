@@ -0,0 +1,190 @@
+//! Guess which language a user is writing in.
+//!
+//! Good enough to keep replies in the user's language, not a general
+//! classifier: non-Latin scripts are recognised by their letters, and a
+//! handful of Latin-script languages by common function words. Short or
+//! ambiguous messages give `None`, so the session keeps the language it had.
+
+/// Function words that are frequent in one language and rare in the others.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "English",
+        &[
+            "the", "and", "is", "are", "you", "to", "of", "what", "how", "can", "this", "that",
+            "with", "for", "it", "my", "do", "have", "i", "be", "not", "please", "was", "me",
+        ],
+    ),
+    (
+        "Spanish",
+        &[
+            "el", "los", "las", "que", "es", "y", "en", "una", "por", "para", "con", "qué", "cómo",
+            "está", "mi", "del", "al", "pero", "muy", "también", "puedes", "hola", "gracias", "yo",
+            "tengo",
+        ],
+    ),
+    (
+        "French",
+        &[
+            "le", "les", "est", "et", "une", "des", "du", "je", "vous", "tu", "pas", "qui", "pour",
+            "dans", "ce", "avec", "sur", "mais", "bonjour", "merci", "très", "il", "nous", "peux",
+            "suis",
+        ],
+    ),
+    (
+        "German",
+        &[
+            "der", "die", "das", "und", "ist", "ich", "nicht", "ein", "eine", "zu", "mit", "sie",
+            "du", "auf", "für", "wie", "was", "den", "dem", "auch", "bitte", "danke", "kannst",
+            "hallo", "mir",
+        ],
+    ),
+    (
+        "Italian",
+        &[
+            "il", "gli", "è", "di", "che", "non", "per", "sono", "ti", "come", "cosa", "questo",
+            "anche", "ciao", "grazie", "puoi", "della", "perché", "mi", "ho",
+        ],
+    ),
+    (
+        "Portuguese",
+        &[
+            "o", "os", "é", "de", "do", "da", "em", "um", "não", "você", "eu", "meu", "obrigado",
+            "olá", "isso", "também", "com", "uma", "para", "tenho",
+        ],
+    ),
+    (
+        "Dutch",
+        &[
+            "de", "het", "een", "en", "ik", "je", "niet", "van", "dat", "op", "te", "met", "voor",
+            "zijn", "wat", "hoe", "kun", "kunt", "bedankt", "ook", "maar", "dit",
+        ],
+    ),
+];
+
+/// The language `text` is most likely written in, by English name
+/// ("Spanish", "Japanese"), or `None` if it's too short to tell.
+pub fn detect(text: &str) -> Option<&'static str> {
+    if let Some(lang) = detect_script(text) {
+        return Some(lang);
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(lang, stopwords)| {
+            let hits = words
+                .iter()
+                .filter(|w| stopwords.contains(&w.as_str()))
+                .count();
+            (*lang, hits)
+        })
+        .collect();
+    // Letters only one of these languages uses
+    for (lang, letters) in [("Spanish", "ñ¿¡"), ("German", "ß"), ("Portuguese", "ãõ")] {
+        if text.chars().any(|c| letters.contains(c)) {
+            if let Some(score) = scores.iter_mut().find(|(l, _)| *l == lang) {
+                score.1 += 2;
+            }
+        }
+    }
+
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    let (best, hits) = scores[0];
+    (hits >= 2 && hits > scores[1].1).then_some(best)
+}
+
+/// A language identified by its script, when most letters are in one.
+fn detect_script(text: &str) -> Option<&'static str> {
+    let mut letters = 0;
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    let mut kana = false;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        let script = match c as u32 {
+            0x3040..=0x30FF => {
+                kana = true;
+                "Japanese"
+            }
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => "Chinese",
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => "Korean",
+            0x0400..=0x04FF => "Russian",
+            0x0600..=0x06FF => "Arabic",
+            0x0590..=0x05FF => "Hebrew",
+            0x0370..=0x03FF => "Greek",
+            0x0900..=0x097F => "Hindi",
+            0x0E00..=0x0E7F => "Thai",
+            _ => continue,
+        };
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some(count) => count.1 += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    // Japanese mixes kanji with kana
+    if kana {
+        let kanji = counts
+            .iter()
+            .find(|(s, _)| *s == "Chinese")
+            .map_or(0, |(_, n)| *n);
+        counts.retain(|(s, _)| *s != "Chinese");
+        if let Some(japanese) = counts.iter_mut().find(|(s, _)| *s == "Japanese") {
+            japanese.1 += kanji;
+        }
+    }
+    let (script, count) = counts.into_iter().max_by_key(|(_, n)| *n)?;
+    if count * 2 < letters {
+        return None;
+    }
+    // Ukrainian uses a few letters Russian doesn't
+    if script == "Russian" && text.chars().any(|c| "іїєґІЇЄҐ".contains(c)) {
+        return Some("Ukrainian");
+    }
+    Some(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_latin_languages() {
+        assert_eq!(detect("What's the weather like today?"), Some("English"));
+        assert_eq!(
+            detect("¿Puedes ayudarme con mi tarea de matemáticas?"),
+            Some("Spanish")
+        );
+        assert_eq!(
+            detect("Bonjour, est-ce que tu peux m'aider ?"),
+            Some("French")
+        );
+        assert_eq!(detect("Kannst du mir bitte helfen?"), Some("German"));
+        assert_eq!(detect("Ciao, come stai? Cosa fai oggi?"), Some("Italian"));
+        assert_eq!(
+            detect("Olá, você pode me ajudar com isso?"),
+            Some("Portuguese")
+        );
+        assert_eq!(detect("Hoe gaat het met je vandaag?"), Some("Dutch"));
+    }
+
+    #[test]
+    fn detects_scripts() {
+        assert_eq!(detect("今日はいい天気ですね"), Some("Japanese"));
+        assert_eq!(detect("今天天气很好"), Some("Chinese"));
+        assert_eq!(detect("안녕하세요, 도와주세요"), Some("Korean"));
+        assert_eq!(detect("Привет, как дела?"), Some("Russian"));
+        assert_eq!(detect("Привіт, як справи? Їжак"), Some("Ukrainian"));
+        assert_eq!(detect("مرحبا كيف حالك"), Some("Arabic"));
+    }
+
+    #[test]
+    fn short_or_mixed_text_is_unknown() {
+        assert_eq!(detect("ok"), None);
+        assert_eq!(detect("thanks!"), None);
+        assert_eq!(detect("https://example.com 42"), None);
+        assert_eq!(detect(""), None);
+    }
+}
@@ -1,14 +1,44 @@
 pub mod agent;
+pub mod builder;
 pub mod bus;
+pub mod bus_journal;
+pub mod commands;
+pub mod compare;
+pub mod connectors;
+pub mod contacts;
 pub mod cron;
+pub mod db;
+pub mod drafts;
+pub mod dry_run;
+pub mod error;
+pub mod export;
 pub mod heartbeat;
+pub mod import;
+pub mod knowledge;
+pub mod language;
+pub mod llama_cpp;
+pub mod locale;
+pub mod location;
+pub mod lockdown;
+pub mod log_stream;
+pub mod moderation;
+pub mod ollama;
 pub mod persona;
+pub mod portable;
+pub mod questions;
 pub mod session;
+pub mod session_log;
+pub mod standup;
 pub mod task;
 pub mod tools;
 pub mod usage;
+pub mod users;
+pub mod watchdog;
 
 // Re-export key types
+pub use builder::{Agent, AgentBuilder};
+pub use commands::CommandRegistry;
+pub use error::{ChannelError, ConfigError, PatinaError, ProviderError, ToolError};
 pub use persona::{Persona, PersonaStore};
 pub use session::{Message, Session, SessionManager};
 pub use task::TaskManager;
@@ -0,0 +1,688 @@
+//! In-process LLM inference for GGUF models via llama.cpp.
+//!
+//! The model is loaded once on a dedicated OS thread, like the Parakeet
+//! transcription workers; async callers send it generation jobs over a
+//! channel and receive text pieces back. Requests are rendered with the
+//! model's own chat template. Tools use the Hermes-style `<tool_call>`
+//! convention (as used by Qwen 2.5 and Hermes models): tool definitions go in
+//! the system prompt and calls are parsed out of the generated text.
+//!
+//! The prompt helpers here are always compiled; the model itself needs the
+//! `llama-cpp` feature.
+
+use std::path::PathBuf;
+
+use rig::completion::{Message, ToolDefinition};
+use rig::message::{AssistantContent, ToolResultContent, UserContent};
+
+#[cfg(feature = "llama-cpp")]
+pub use inner::{LlamaCppClient, LlamaCppModel, LlamaCppResponse};
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+/// Resolve a GGUF model reference: `~` is expanded and relative paths are
+/// taken from `models_dir` (default `~/.patina/models`).
+pub fn resolve_model_path(model: &str, models_dir: Option<&str>) -> PathBuf {
+    let path = patina_config::resolve_workspace(model);
+    if path.is_absolute() {
+        return path;
+    }
+    let dir = models_dir
+        .filter(|d| !d.is_empty())
+        .map(patina_config::resolve_workspace)
+        .unwrap_or_else(|| patina_config::data_dir().join("models"));
+    dir.join(path)
+}
+
+/// System prompt section describing the available tools.
+pub fn tools_prompt(tools: &[ToolDefinition]) -> String {
+    let mut out = String::from(
+        "# Tools\n\nYou may call one or more functions to assist with the user query.\n\n\
+         You are provided with function signatures within <tools></tools> XML tags:\n<tools>",
+    );
+    for tool in tools {
+        let def = serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }
+        });
+        out.push('\n');
+        out.push_str(&def.to_string());
+    }
+    out.push_str(
+        "\n</tools>\n\nFor each function call, return a json object with function name and \
+         arguments within <tool_call></tool_call> XML tags:\n<tool_call>\n\
+         {\"name\": <function-name>, \"arguments\": <args-json-object>}\n</tool_call>",
+    );
+    out
+}
+
+/// Flatten a rig request into (role, content) pairs for the chat template.
+///
+/// Tool calls and results are written in the same `<tool_call>` /
+/// `<tool_response>` form the model is asked to produce, so they survive
+/// templates that only understand plain text messages.
+pub fn chat_messages(
+    preamble: Option<&str>,
+    tools: &[ToolDefinition],
+    history: &[Message],
+) -> Vec<(String, String)> {
+    let mut system = preamble.unwrap_or_default().to_string();
+    if !tools.is_empty() {
+        if !system.is_empty() {
+            system.push_str("\n\n");
+        }
+        system.push_str(&tools_prompt(tools));
+    }
+
+    let mut messages = Vec::new();
+    if !system.is_empty() {
+        messages.push(("system".to_string(), system));
+    }
+    for message in history {
+        let (role, parts): (&str, Vec<String>) = if let Message::User { content } = message {
+            let parts = content
+                .iter()
+                .filter_map(|c| match c {
+                    UserContent::Text(t) => Some(t.text.clone()),
+                    UserContent::ToolResult(r) => {
+                        let text: Vec<&str> = r
+                            .content
+                            .iter()
+                            .filter_map(|c| match c {
+                                ToolResultContent::Text(t) => Some(t.text.as_str()),
+                                _ => None,
+                            })
+                            .collect();
+                        Some(format!(
+                            "<tool_response>\n{}\n</tool_response>",
+                            text.join("\n")
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect();
+            ("user", parts)
+        } else if let Message::Assistant { content, .. } = message {
+            let parts = content
+                .iter()
+                .filter_map(|c| match c {
+                    AssistantContent::Text(t) => Some(t.text.clone()),
+                    AssistantContent::ToolCall(tc) => {
+                        let call = serde_json::json!({
+                            "name": tc.function.name,
+                            "arguments": tc.function.arguments,
+                        });
+                        Some(format!("{TOOL_CALL_OPEN}\n{call}\n{TOOL_CALL_CLOSE}"))
+                    }
+                    _ => None,
+                })
+                .collect();
+            ("assistant", parts)
+        } else {
+            continue;
+        };
+        if !parts.is_empty() {
+            messages.push((role.to_string(), parts.join("\n")));
+        }
+    }
+    messages
+}
+
+/// Split generated text into the plain reply and any `<tool_call>` blocks,
+/// as (text, [(name, arguments)]). Blocks that aren't valid JSON are left in
+/// the text so the failure is visible rather than silently dropped.
+pub fn parse_tool_calls(text: &str) -> (String, Vec<(String, serde_json::Value)>) {
+    let mut plain = String::new();
+    let mut calls = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(TOOL_CALL_OPEN) {
+        plain.push_str(&rest[..start]);
+        let after = &rest[start + TOOL_CALL_OPEN.len()..];
+        let (body, next) = match after.find(TOOL_CALL_CLOSE) {
+            Some(end) => (&after[..end], &after[end + TOOL_CALL_CLOSE.len()..]),
+            // Generation stopped before the closing tag
+            None => (after, ""),
+        };
+        let parsed = serde_json::from_str::<serde_json::Value>(body.trim())
+            .ok()
+            .and_then(|v| {
+                let name = v.get("name")?.as_str()?.to_string();
+                let args = v
+                    .get("arguments")
+                    .cloned()
+                    .unwrap_or_else(|| serde_json::json!({}));
+                Some((name, args))
+            });
+        match parsed {
+            Some(call) => calls.push(call),
+            None => plain.push_str(&rest[start..rest.len() - next.len()]),
+        }
+        rest = next;
+    }
+    plain.push_str(rest);
+    (plain.trim().to_string(), calls)
+}
+
+/// Withholds streamed text from the first `<tool_call>` onwards, so the
+/// raw call JSON isn't shown to the user while it's being generated.
+#[derive(Default)]
+pub struct ToolCallFilter {
+    pending: String,
+    in_call: bool,
+}
+
+impl ToolCallFilter {
+    /// Add a generated piece; returns the text that is safe to show.
+    pub fn push(&mut self, piece: &str) -> String {
+        if self.in_call {
+            return String::new();
+        }
+        self.pending.push_str(piece);
+        if let Some(start) = self.pending.find(TOOL_CALL_OPEN) {
+            self.in_call = true;
+            let visible = self.pending[..start].to_string();
+            self.pending.clear();
+            return visible;
+        }
+        // Hold back a trailing partial "<tool_call" until we know what it is
+        let keep = (1..TOOL_CALL_OPEN.len())
+            .rev()
+            .find(|n| self.pending.ends_with(&TOOL_CALL_OPEN[..*n]))
+            .unwrap_or(0);
+        let split = self.pending.len() - keep;
+        let visible = self.pending[..split].to_string();
+        self.pending.drain(..split);
+        visible
+    }
+
+    /// Text still held back once generation has finished.
+    pub fn finish(self) -> String {
+        if self.in_call {
+            String::new()
+        } else {
+            self.pending
+        }
+    }
+}
+
+/// Take the longest valid UTF-8 prefix out of `bytes`, leaving an incomplete
+/// trailing character for the next token to finish.
+#[cfg(any(feature = "llama-cpp", test))]
+fn take_utf8(bytes: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(s) => s.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Invalid sequence, not just incomplete: don't stall on it
+        Err(_) => bytes.len(),
+    };
+    let text = String::from_utf8_lossy(&bytes[..valid]).into_owned();
+    bytes.drain(..valid);
+    text
+}
+
+#[cfg(feature = "llama-cpp")]
+mod inner {
+    use std::collections::VecDeque;
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+
+    use anyhow::{bail, Result};
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+    use llama_cpp_2::sampling::LlamaSampler;
+    use patina_config::LlamaCppConfig;
+    use rig::completion::{
+        CompletionError, CompletionModel, CompletionRequest, CompletionResponse, GetTokenUsage,
+        Usage,
+    };
+    use rig::message::{AssistantContent, Text};
+    use rig::streaming::{RawStreamingChoice, RawStreamingToolCall, StreamingCompletionResponse};
+    use rig::OneOrMany;
+    use serde::{Deserialize, Serialize};
+    use tokio::sync::mpsc;
+    use tracing::{info, warn};
+
+    use super::{chat_messages, parse_tool_calls, take_utf8, ToolCallFilter};
+
+    /// Prompt tokens are decoded in batches of this size.
+    const PROMPT_BATCH: usize = 512;
+
+    /// llama.cpp's backend may only be initialised once per process.
+    static BACKEND: OnceLock<std::result::Result<LlamaBackend, String>> = OnceLock::new();
+
+    fn backend() -> Result<&'static LlamaBackend> {
+        match BACKEND.get_or_init(|| LlamaBackend::init().map_err(|e| e.to_string())) {
+            Ok(b) => Ok(b),
+            Err(e) => bail!("llama.cpp backend failed to initialise: {e}"),
+        }
+    }
+
+    /// Token counts for a finished generation.
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct LlamaCppResponse {
+        pub input_tokens: u64,
+        pub output_tokens: u64,
+    }
+
+    impl GetTokenUsage for LlamaCppResponse {
+        fn token_usage(&self) -> Option<Usage> {
+            let mut usage = Usage::new();
+            usage.input_tokens = self.input_tokens;
+            usage.output_tokens = self.output_tokens;
+            usage.total_tokens = self.input_tokens + self.output_tokens;
+            Some(usage)
+        }
+    }
+
+    /// Output from the worker thread for one job.
+    enum Piece {
+        Text(String),
+        Done(LlamaCppResponse),
+        Error(String),
+    }
+
+    struct Job {
+        messages: Vec<(String, String)>,
+        temperature: f64,
+        max_tokens: u64,
+        reply_tx: mpsc::UnboundedSender<Piece>,
+    }
+
+    /// Shared settings for models created with [`CompletionModel::make`].
+    #[derive(Clone, Default)]
+    pub struct LlamaCppClient {
+        pub config: LlamaCppConfig,
+    }
+
+    /// A GGUF model running in-process.
+    ///
+    /// Cloning is cheap: clones share the same worker thread and loaded model.
+    /// Jobs run one at a time in arrival order.
+    #[derive(Clone)]
+    pub struct LlamaCppModel {
+        name: String,
+        job_tx: std::sync::mpsc::Sender<Job>,
+    }
+
+    impl LlamaCppModel {
+        /// Start a worker thread that loads the GGUF at `path`.
+        ///
+        /// Loading happens in the background; if it fails, every request
+        /// returns the load error.
+        pub fn new(path: PathBuf, config: &LlamaCppConfig) -> Result<Self> {
+            if !path.is_file() {
+                bail!("GGUF model not found at {}", path.display());
+            }
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (job_tx, job_rx) = std::sync::mpsc::channel::<Job>();
+            let config = config.clone();
+            std::thread::Builder::new()
+                .name(format!("llama-cpp-{name}"))
+                .spawn(move || worker_thread(path, config, job_rx))?;
+            Ok(Self { name, job_tx })
+        }
+
+        /// Queue a generation and return the receiver for its output.
+        fn submit(
+            &self,
+            request: &CompletionRequest,
+        ) -> std::result::Result<mpsc::UnboundedReceiver<Piece>, CompletionError> {
+            let history: Vec<_> = request.chat_history.iter().cloned().collect();
+            let (reply_tx, reply_rx) = mpsc::unbounded_channel();
+            let job = Job {
+                messages: chat_messages(request.preamble.as_deref(), &request.tools, &history),
+                temperature: request.temperature.unwrap_or(0.7),
+                max_tokens: request.max_tokens.unwrap_or(2048),
+                reply_tx,
+            };
+            self.job_tx.send(job).map_err(|_| {
+                CompletionError::ProviderError(format!(
+                    "llama.cpp worker for {} stopped",
+                    self.name
+                ))
+            })?;
+            Ok(reply_rx)
+        }
+    }
+
+    fn worker_thread(
+        path: PathBuf,
+        config: LlamaCppConfig,
+        job_rx: std::sync::mpsc::Receiver<Job>,
+    ) {
+        let loaded = load_model(&path, &config);
+        match &loaded {
+            Ok(_) => info!("llama.cpp model loaded: {}", path.display()),
+            Err(e) => warn!("llama.cpp failed to load {}: {e}", path.display()),
+        }
+        for job in job_rx {
+            let reply_tx = job.reply_tx.clone();
+            let result = match &loaded {
+                Ok(model) => generate(model, &config, job),
+                Err(e) => Err(anyhow::anyhow!("Failed to load {}: {e}", path.display())),
+            };
+            if let Err(e) = result {
+                let _ = reply_tx.send(Piece::Error(e.to_string()));
+            }
+        }
+    }
+
+    fn load_model(path: &Path, config: &LlamaCppConfig) -> Result<LlamaModel> {
+        let params = LlamaModelParams::default().with_n_gpu_layers(config.gpu_layers);
+        Ok(LlamaModel::load_from_file(backend()?, path, &params)?)
+    }
+
+    /// Run one job to completion, streaming pieces to `job.reply_tx`. Stops
+    /// early if the receiver is dropped (timeout or interrupt).
+    fn generate(model: &LlamaModel, config: &LlamaCppConfig, job: Job) -> Result<()> {
+        let template = model.chat_template(None)?;
+        let chat = job
+            .messages
+            .into_iter()
+            .map(|(role, content)| LlamaChatMessage::new(role, content))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let prompt = model.apply_chat_template(&template, &chat, true)?;
+        // Chat templates include the BOS token themselves where needed
+        let tokens = model.str_to_token(&prompt, AddBos::Never)?;
+
+        let n_ctx = config.context_size as usize;
+        let max_tokens = job.max_tokens as usize;
+        if tokens.len() + max_tokens > n_ctx {
+            bail!(
+                "Prompt is {} tokens, too long for a {n_ctx}-token context with {max_tokens} \
+                 reserved for the reply (raise providers.llamacpp.contextSize)",
+                tokens.len()
+            );
+        }
+
+        let mut ctx_params =
+            LlamaContextParams::default().with_n_ctx(NonZeroU32::new(config.context_size));
+        if let Some(threads) = config.threads {
+            ctx_params = ctx_params
+                .with_n_threads(threads)
+                .with_n_threads_batch(threads);
+        }
+        let mut ctx = model.new_context(backend()?, ctx_params)?;
+
+        // Feed the prompt in batches, requesting logits for the last token only
+        let mut batch = LlamaBatch::new(PROMPT_BATCH, 1);
+        let mut pos: i32 = 0;
+        for chunk in tokens.chunks(PROMPT_BATCH) {
+            batch.clear();
+            for token in chunk {
+                let last = pos as usize == tokens.len() - 1;
+                batch.add(*token, pos, &[0], last)?;
+                pos += 1;
+            }
+            ctx.decode(&mut batch)?;
+        }
+
+        let mut sampler = if job.temperature <= 0.0 {
+            LlamaSampler::greedy()
+        } else {
+            LlamaSampler::chain_simple([
+                LlamaSampler::temp(job.temperature as f32),
+                LlamaSampler::dist(rand_seed()),
+            ])
+        };
+
+        let mut bytes = Vec::new();
+        let mut output_tokens = 0u64;
+        while output_tokens < job.max_tokens {
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if model.is_eog_token(token) {
+                break;
+            }
+            output_tokens += 1;
+            bytes.extend(model.token_to_bytes(token, Special::Tokenize)?);
+            let text = take_utf8(&mut bytes);
+            if !text.is_empty() && job.reply_tx.send(Piece::Text(text)).is_err() {
+                return Ok(());
+            }
+
+            batch.clear();
+            batch.add(token, pos, &[0], true)?;
+            pos += 1;
+            ctx.decode(&mut batch)?;
+        }
+        if !bytes.is_empty() {
+            let _ = job
+                .reply_tx
+                .send(Piece::Text(String::from_utf8_lossy(&bytes).into_owned()));
+        }
+        let _ = job.reply_tx.send(Piece::Done(LlamaCppResponse {
+            input_tokens: tokens.len() as u64,
+            output_tokens,
+        }));
+        Ok(())
+    }
+
+    fn rand_seed() -> u32 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0)
+    }
+
+    fn tool_call_id(i: usize) -> String {
+        format!("call_{}_{i}", uuid::Uuid::new_v4().simple())
+    }
+
+    /// State for turning worker pieces into a rig stream.
+    struct StreamState {
+        reply_rx: mpsc::UnboundedReceiver<Piece>,
+        filter: ToolCallFilter,
+        text: String,
+        queue: VecDeque<std::result::Result<RawStreamingChoice<LlamaCppResponse>, CompletionError>>,
+        finished: bool,
+    }
+
+    impl CompletionModel for LlamaCppModel {
+        type Response = LlamaCppResponse;
+        type StreamingResponse = LlamaCppResponse;
+        type Client = LlamaCppClient;
+
+        fn make(client: &Self::Client, model: impl Into<String>) -> Self {
+            let model = model.into();
+            let path = super::resolve_model_path(&model, client.config.models_dir.as_deref());
+            Self::new(path, &client.config).unwrap_or_else(|e| {
+                // Keep the error for the first request rather than panicking here
+                let (job_tx, job_rx) = std::sync::mpsc::channel::<Job>();
+                let message = e.to_string();
+                std::thread::spawn(move || {
+                    for job in job_rx {
+                        let _ = job.reply_tx.send(Piece::Error(message.clone()));
+                    }
+                });
+                Self {
+                    name: model,
+                    job_tx,
+                }
+            })
+        }
+
+        async fn completion(
+            &self,
+            request: CompletionRequest,
+        ) -> std::result::Result<CompletionResponse<LlamaCppResponse>, CompletionError> {
+            let mut reply_rx = self.submit(&request)?;
+            let mut text = String::new();
+            let usage = loop {
+                match reply_rx.recv().await {
+                    Some(Piece::Text(t)) => text.push_str(&t),
+                    Some(Piece::Done(usage)) => break usage,
+                    Some(Piece::Error(e)) => return Err(CompletionError::ProviderError(e)),
+                    None => {
+                        return Err(CompletionError::ProviderError(
+                            "llama.cpp worker stopped".into(),
+                        ))
+                    }
+                }
+            };
+
+            let (plain, calls) = parse_tool_calls(&text);
+            let mut choice = Vec::new();
+            if !plain.is_empty() {
+                choice.push(AssistantContent::Text(Text { text: plain }));
+            }
+            for (i, (name, args)) in calls.into_iter().enumerate() {
+                choice.push(AssistantContent::tool_call(tool_call_id(i), name, args));
+            }
+            let choice = OneOrMany::many(choice).unwrap_or_else(|_| {
+                OneOrMany::one(AssistantContent::Text(Text {
+                    text: String::new(),
+                }))
+            });
+            Ok(CompletionResponse {
+                choice,
+                usage: usage.token_usage().unwrap_or_default(),
+                raw_response: usage,
+            })
+        }
+
+        async fn stream(
+            &self,
+            request: CompletionRequest,
+        ) -> std::result::Result<StreamingCompletionResponse<LlamaCppResponse>, CompletionError>
+        {
+            let state = StreamState {
+                reply_rx: self.submit(&request)?,
+                filter: ToolCallFilter::default(),
+                text: String::new(),
+                queue: VecDeque::new(),
+                finished: false,
+            };
+            let stream = futures::stream::unfold(state, |mut st| async move {
+                loop {
+                    if let Some(item) = st.queue.pop_front() {
+                        return Some((item, st));
+                    }
+                    if st.finished {
+                        return None;
+                    }
+                    match st.reply_rx.recv().await {
+                        Some(Piece::Text(t)) => {
+                            st.text.push_str(&t);
+                            let visible = st.filter.push(&t);
+                            if !visible.is_empty() {
+                                st.queue.push_back(Ok(RawStreamingChoice::Message(visible)));
+                            }
+                        }
+                        Some(Piece::Done(usage)) => {
+                            st.finished = true;
+                            let rest = std::mem::take(&mut st.filter).finish();
+                            if !rest.is_empty() {
+                                st.queue.push_back(Ok(RawStreamingChoice::Message(rest)));
+                            }
+                            let (_, calls) = parse_tool_calls(&st.text);
+                            for (i, (name, args)) in calls.into_iter().enumerate() {
+                                st.queue.push_back(Ok(RawStreamingChoice::ToolCall(
+                                    RawStreamingToolCall::new(tool_call_id(i), name, args),
+                                )));
+                            }
+                            st.queue
+                                .push_back(Ok(RawStreamingChoice::FinalResponse(usage)));
+                        }
+                        Some(Piece::Error(e)) => {
+                            st.finished = true;
+                            st.queue.push_back(Err(CompletionError::ProviderError(e)));
+                        }
+                        None => {
+                            st.finished = true;
+                            st.queue.push_back(Err(CompletionError::ProviderError(
+                                "llama.cpp worker stopped".into(),
+                            )));
+                        }
+                    }
+                }
+            });
+            Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let (text, calls) = parse_tool_calls(
+            "Let me check.\n<tool_call>\n{\"name\": \"calc\", \"arguments\": {\"expr\": \"2+2\"}}\n</tool_call>",
+        );
+        assert_eq!(text, "Let me check.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, "calc");
+        assert_eq!(calls[0].1, serde_json::json!({"expr": "2+2"}));
+
+        // Unterminated call at the end of generation still counts
+        let (text, calls) = parse_tool_calls("<tool_call>{\"name\": \"list_dir\"}");
+        assert_eq!(text, "");
+        assert_eq!(calls[0].1, serde_json::json!({}));
+
+        // Malformed JSON stays in the reply
+        let (text, calls) = parse_tool_calls("<tool_call>not json</tool_call> done");
+        assert!(calls.is_empty());
+        assert_eq!(text, "<tool_call>not json</tool_call> done");
+    }
+
+    #[test]
+    fn test_tool_call_filter_withholds_calls() {
+        let mut filter = ToolCallFilter::default();
+        let mut shown = String::new();
+        for piece in [
+            "Sure",
+            ", one moment.",
+            "\n<tool",
+            "_call>\n{\"name\"",
+            "}</tool_call>",
+        ] {
+            shown.push_str(&filter.push(piece));
+        }
+        shown.push_str(&filter.finish());
+        assert_eq!(shown, "Sure, one moment.\n");
+
+        let mut filter = ToolCallFilter::default();
+        assert_eq!(filter.push("a <to"), "a ");
+        assert_eq!(filter.push("ken>"), "<token>");
+        assert_eq!(filter.push(" <"), " ");
+        assert_eq!(filter.finish(), "<");
+    }
+
+    #[test]
+    fn test_take_utf8_keeps_partial_chars() {
+        let mut bytes = "hé".as_bytes().to_vec();
+        bytes.push(0xE2); // first byte of a 3-byte character
+        assert_eq!(take_utf8(&mut bytes), "hé");
+        assert_eq!(bytes, vec![0xE2]);
+        bytes.extend([0x82, 0xAC]);
+        assert_eq!(take_utf8(&mut bytes), "€");
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_model_path() {
+        assert_eq!(
+            resolve_model_path("/opt/models/qwen.gguf", Some("/srv")),
+            PathBuf::from("/opt/models/qwen.gguf")
+        );
+        assert_eq!(
+            resolve_model_path("qwen.gguf", Some("/srv/models")),
+            PathBuf::from("/srv/models/qwen.gguf")
+        );
+        assert!(resolve_model_path("qwen.gguf", None).ends_with(".patina/models/qwen.gguf"));
+    }
+}
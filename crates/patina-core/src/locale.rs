@@ -0,0 +1,307 @@
+//! Timezone and locale of the person the agent is talking to.
+//!
+//! `user.timezone` and `user.locale` in the config are the defaults, a
+//! `users.<name>` entry can override them for one person, and `/set
+//! timezone` / `/set locale` for one session. The resulting [`UserLocale`]
+//! decides what "now" is in the runtime facts, how the times given to the
+//! cron and message tools are read, how scheduled times are shown, and which
+//! zone new cron expressions run in. Without a timezone, everything uses the
+//! server's local time.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use patina_config::{UserConfig, UserPrefsConfig};
+use tracing::warn;
+
+/// Regions that write the month before the day, with a 12-hour clock.
+const MONTH_FIRST: &[&str] = &["US", "PH"];
+/// Regions that write the year first.
+const YEAR_FIRST: &[&str] = &["CN", "JP", "KR", "TW", "HU", "LT", "SE", "CA"];
+/// Day-first regions that use a 12-hour clock.
+const TWELVE_HOUR: &[&str] = &["AU", "NZ", "IN", "PK", "EG"];
+
+/// Where a user is and how they write dates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserLocale {
+    /// `None` is the server's local time.
+    pub timezone: Option<Tz>,
+    /// Normalized BCP 47 tag ("en-GB"). `None` writes dates as ISO 8601.
+    pub locale: Option<String>,
+}
+
+impl UserLocale {
+    /// From config values. Unknown names are logged and ignored.
+    pub fn new(timezone: Option<&str>, locale: Option<&str>) -> Self {
+        let timezone = timezone.filter(|tz| !tz.trim().is_empty()).and_then(|tz| {
+            let parsed = parse_timezone(tz);
+            if parsed.is_none() {
+                warn!("Unknown timezone '{tz}', using the server's local time");
+            }
+            parsed
+        });
+        let locale = locale.filter(|l| !l.trim().is_empty()).and_then(|l| {
+            let parsed = normalize_locale(l);
+            if parsed.is_none() {
+                warn!("Invalid locale '{l}', ignoring it");
+            }
+            parsed
+        });
+        Self { timezone, locale }
+    }
+
+    /// `self`, with anything unset taken from `fallback`.
+    pub fn or(self, fallback: &UserLocale) -> Self {
+        Self {
+            timezone: self.timezone.or(fallback.timezone),
+            locale: self.locale.or_else(|| fallback.locale.clone()),
+        }
+    }
+
+    /// The zone's IANA name, for [`CronSchedule::tz`](crate::cron::CronSchedule).
+    pub fn tz_name(&self) -> Option<String> {
+        self.timezone.map(|tz| tz.name().to_string())
+    }
+
+    /// The zone's name for people: "Europe/Berlin" or "server local time".
+    pub fn timezone_label(&self) -> String {
+        self.tz_name()
+            .unwrap_or_else(|| "server local time".to_string())
+    }
+
+    /// `dt` on this user's clock.
+    pub fn localize(&self, dt: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.timezone {
+            Some(tz) => dt.with_timezone(&tz).fixed_offset(),
+            None => dt.with_timezone(&Local).fixed_offset(),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.localize(Utc::now())
+    }
+
+    /// `dt` on this user's clock, written the way their locale writes
+    /// dates, followed by the UTC offset: "6 Mar 2026 14:05 (UTC+01:00)".
+    /// Month names are always English.
+    pub fn format(&self, dt: DateTime<Utc>) -> String {
+        let local = self.localize(dt);
+        format!(
+            "{} (UTC{})",
+            local.format(date_time_pattern(self.locale.as_deref())),
+            local.format("%:z")
+        )
+    }
+
+    /// [`format`](Self::format) for a millisecond timestamp.
+    pub fn format_ms(&self, ms: i64) -> String {
+        DateTime::from_timestamp_millis(ms)
+            .map(|dt| self.format(dt))
+            .unwrap_or_else(|| "?".to_string())
+    }
+
+    /// Parse a time given by the user or the model: RFC 3339 with an
+    /// offset, or a date and time without one ("2026-03-06 14:00",
+    /// "2026-03-06T14:00:00", "2026-03-06"), read on this user's clock.
+    pub fn parse(&self, input: &str) -> Result<DateTime<FixedOffset>, String> {
+        let input = input.trim();
+        if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+            return Ok(dt);
+        }
+        const FORMATS: &[&str] = &[
+            "%Y-%m-%d %H:%M:%S",
+            "%Y-%m-%dT%H:%M:%S",
+            "%Y-%m-%d %H:%M",
+            "%Y-%m-%dT%H:%M",
+        ];
+        let naive = FORMATS
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(input, f).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+            })
+            .ok_or_else(|| {
+                format!("could not parse '{input}' (use 'YYYY-MM-DD HH:MM' or RFC 3339)")
+            })?;
+        let resolved = match self.timezone {
+            Some(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|dt| dt.fixed_offset()),
+            None => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|dt| dt.fixed_offset()),
+        };
+        resolved.ok_or_else(|| {
+            format!(
+                "'{input}' doesn't exist in {} (the clocks skip it)",
+                self.timezone_label()
+            )
+        })
+    }
+}
+
+/// An IANA timezone name, matched case-insensitively ("europe/berlin").
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    let name = name.trim();
+    name.parse::<Tz>().ok().or_else(|| {
+        chrono_tz::TZ_VARIANTS
+            .iter()
+            .find(|tz| tz.name().eq_ignore_ascii_case(name))
+            .copied()
+    })
+}
+
+/// A locale tag in its usual form ("en_gb" → "en-GB"), or `None` if it
+/// isn't shaped like one.
+pub fn normalize_locale(tag: &str) -> Option<String> {
+    let mut parts = tag.trim().split(['-', '_']);
+    let language = parts.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut out = language.to_ascii_lowercase();
+    for part in parts {
+        if part.is_empty() || part.len() > 8 || !part.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        out.push('-');
+        if part.len() == 2 {
+            out.push_str(&part.to_ascii_uppercase());
+        } else {
+            out.push_str(part);
+        }
+    }
+    Some(out)
+}
+
+/// The strftime pattern for a date and time in `locale`.
+fn date_time_pattern(locale: Option<&str>) -> &'static str {
+    let Some(locale) = locale else {
+        return "%Y-%m-%d %H:%M";
+    };
+    let region = locale
+        .split('-')
+        .skip(1)
+        .find(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_uppercase()))
+        .unwrap_or_else(|| if locale == "en" { "US" } else { "" });
+    if MONTH_FIRST.contains(&region) {
+        "%b %-d, %Y %-I:%M %p"
+    } else if YEAR_FIRST.contains(&region) {
+        "%Y-%m-%d %H:%M"
+    } else if TWELVE_HOUR.contains(&region) {
+        "%-d %b %Y %-I:%M %p"
+    } else {
+        "%-d %b %Y %H:%M"
+    }
+}
+
+/// Per-user timezones and locales from the config.
+#[derive(Debug, Clone, Default)]
+pub struct Locales {
+    default: UserLocale,
+    users: HashMap<String, UserLocale>,
+}
+
+impl Locales {
+    pub fn new(user: &UserPrefsConfig, users: &HashMap<String, UserConfig>) -> Self {
+        let default = UserLocale::new(user.timezone.as_deref(), user.locale.as_deref());
+        let users = users
+            .iter()
+            .map(|(name, u)| {
+                let locale = UserLocale::new(u.timezone.as_deref(), u.locale.as_deref());
+                (name.clone(), locale.or(&default))
+            })
+            .collect();
+        Self { default, users }
+    }
+
+    /// The locale for configured user `user`, or the default for anyone
+    /// else.
+    pub fn for_user(&self, user: Option<&str>) -> UserLocale {
+        user.and_then(|name| self.users.get(name))
+            .unwrap_or(&self.default)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse_in_user_zone() {
+        let berlin = UserLocale::new(Some("europe/berlin"), Some("de_de"));
+        assert_eq!(berlin.tz_name().as_deref(), Some("Europe/Berlin"));
+        assert_eq!(berlin.locale.as_deref(), Some("de-DE"));
+
+        let at = berlin.parse("2026-03-06 14:05").unwrap();
+        assert_eq!(at.to_rfc3339(), "2026-03-06T14:05:00+01:00");
+        assert_eq!(
+            berlin.format(at.with_timezone(&Utc)),
+            "6 Mar 2026 14:05 (UTC+01:00)"
+        );
+        // An explicit offset wins over the zone
+        let utc = berlin.parse("2026-07-01T09:00:00Z").unwrap();
+        assert_eq!(
+            berlin.format(utc.with_timezone(&Utc)),
+            "1 Jul 2026 11:00 (UTC+02:00)"
+        );
+        assert!(berlin.parse("2026-03-29 02:30").is_err());
+        assert!(berlin.parse("tomorrow").is_err());
+
+        let us = UserLocale::new(Some("America/New_York"), Some("en-US"));
+        assert_eq!(
+            us.format(utc.with_timezone(&Utc)),
+            "Jul 1, 2026 5:00 AM (UTC-04:00)"
+        );
+        let iso = UserLocale::new(Some("Asia/Tokyo"), None);
+        assert_eq!(
+            iso.format(utc.with_timezone(&Utc)),
+            "2026-07-01 18:00 (UTC+09:00)"
+        );
+    }
+
+    #[test]
+    fn test_per_user_falls_back_to_default() {
+        let users = HashMap::from([
+            (
+                "sam".to_string(),
+                UserConfig {
+                    timezone: Some("America/Chicago".into()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "kim".to_string(),
+                UserConfig {
+                    timezone: Some("Mars/Olympus".into()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let locales = Locales::new(
+            &UserPrefsConfig {
+                timezone: Some("Europe/Lisbon".into()),
+                locale: Some("pt-PT".into()),
+            },
+            &users,
+        );
+        let sam = locales.for_user(Some("sam"));
+        assert_eq!(sam.tz_name().as_deref(), Some("America/Chicago"));
+        assert_eq!(sam.locale.as_deref(), Some("pt-PT"));
+        assert_eq!(
+            locales.for_user(Some("kim")).tz_name().as_deref(),
+            Some("Europe/Lisbon")
+        );
+        assert_eq!(
+            locales.for_user(None).tz_name().as_deref(),
+            Some("Europe/Lisbon")
+        );
+        assert_eq!(normalize_locale("english"), None);
+    }
+}
@@ -0,0 +1,468 @@
+//! Where people are: location pings and geofences.
+//!
+//! OwnTracks (in HTTP mode) posts its `location` JSON to the web channel's
+//! `POST /api/location`, which hands each ping to [`LocationStore::record`].
+//! Pings are kept per user for `location.historyDays`, and each one is
+//! checked against `location.places`: crossing a place's edge is a
+//! [`Transition`], and [`geofence_messages`] turns it into an inbound
+//! message for every matching `location.geofences` entry, so "left work"
+//! runs its prompt like a cron job would.
+//!
+//! The database (`location.sqlite` in the data directory) is the only copy
+//! of the history.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone, Utc};
+use patina_config::{GeofenceConfig, GeofenceTransition, LocationConfig, PlaceConfig};
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+
+use crate::bus::InboundMessage;
+use crate::db::{Db, Migration, Step};
+
+/// Sender of geofence messages.
+pub const LOCATION_SENDER: &str = "location";
+
+/// How far past a place's edge (or the ping's accuracy, if worse) someone
+/// has to be to have left it, so GPS jitter at the edge doesn't flap.
+const LEAVE_MARGIN_M: f64 = 50.0;
+
+/// One location ping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub user: String,
+    pub lat: f64,
+    pub lon: f64,
+    /// Radius the position is accurate to, in meters.
+    pub accuracy_m: Option<f64>,
+    /// Battery percentage of the phone.
+    pub battery: Option<u8>,
+    pub at: DateTime<Utc>,
+}
+
+/// Someone arriving at or leaving a place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub user: String,
+    pub place: String,
+    pub kind: GeofenceTransition,
+    pub at: DateTime<Utc>,
+}
+
+impl Transition {
+    /// E.g. "ada left work".
+    pub fn describe(&self) -> String {
+        match self.kind {
+            GeofenceTransition::Enter => format!("{} arrived at {}", self.user, self.place),
+            GeofenceTransition::Leave => format!("{} left {}", self.user, self.place),
+        }
+    }
+}
+
+/// The fix in an OwnTracks message for `user`. Other OwnTracks messages
+/// (waypoints, cards, status) have none, and `_type` can be left out by
+/// anything else posting `{"lat": .., "lon": ..}`.
+pub fn parse_owntracks(body: &Value, user: &str) -> Option<Fix> {
+    if !matches!(
+        body["_type"].as_str(),
+        None | Some("location" | "transition")
+    ) {
+        return None;
+    }
+    let lat = body["lat"]
+        .as_f64()
+        .filter(|l| (-90.0..=90.0).contains(l))?;
+    let lon = body["lon"]
+        .as_f64()
+        .filter(|l| (-180.0..=180.0).contains(l))?;
+    let at = body["tst"]
+        .as_i64()
+        .and_then(|t| Utc.timestamp_opt(t, 0).single())
+        .unwrap_or_else(Utc::now);
+    Some(Fix {
+        user: user.to_string(),
+        lat,
+        lon,
+        accuracy_m: body["acc"].as_f64(),
+        battery: body["batt"].as_u64().map(|b| b.min(100) as u8),
+        at,
+    })
+}
+
+/// Great-circle distance in meters.
+pub fn distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    const EARTH_RADIUS_M: f64 = 6_371_000.0;
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+/// The location schema.
+const MIGRATIONS: &[Migration] = &[Migration {
+    name: "fixes, presence, and transitions",
+    up: Step::Sql(
+        "CREATE TABLE IF NOT EXISTS fixes (
+            user TEXT NOT NULL,
+            at INTEGER NOT NULL,
+            lat REAL NOT NULL,
+            lon REAL NOT NULL,
+            accuracy REAL,
+            battery INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_fixes_user_at ON fixes(user, at);
+        CREATE TABLE IF NOT EXISTS presence (
+            user TEXT NOT NULL,
+            place TEXT NOT NULL,
+            inside INTEGER NOT NULL,
+            PRIMARY KEY (user, place)
+        );
+        CREATE TABLE IF NOT EXISTS transitions (
+            user TEXT NOT NULL,
+            place TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_transitions_user_at ON transitions(user, at);",
+    ),
+}];
+
+/// Location history and who is in which place, in SQLite.
+pub struct LocationStore {
+    db: Db,
+    places: HashMap<String, PlaceConfig>,
+    history_days: u32,
+    max_accuracy_m: f64,
+}
+
+impl LocationStore {
+    pub fn open(db_path: &Path, config: &LocationConfig) -> Result<Self> {
+        Ok(Self {
+            db: Db::open(db_path, MIGRATIONS)?,
+            places: config.places.clone(),
+            history_days: config.history_days,
+            max_accuracy_m: config.max_accuracy_m,
+        })
+    }
+
+    /// Store a ping and return the places it moved its user in or out of.
+    /// A user's first ping only notes where they are, and pings older than
+    /// the latest one (a phone catching up) or too inaccurate move no one.
+    pub fn record(&self, fix: &Fix) -> Result<Vec<Transition>> {
+        self.db.write(|tx| {
+            let latest: Option<i64> = tx.query_row(
+                "SELECT MAX(at) FROM fixes WHERE user = ?1",
+                params![fix.user],
+                |row| row.get(0),
+            )?;
+            tx.execute(
+                "INSERT INTO fixes (user, at, lat, lon, accuracy, battery)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    fix.user,
+                    fix.at.timestamp(),
+                    fix.lat,
+                    fix.lon,
+                    fix.accuracy_m,
+                    fix.battery
+                ],
+            )?;
+            let cutoff =
+                (Utc::now() - chrono::Duration::days(self.history_days.into())).timestamp();
+            tx.execute("DELETE FROM fixes WHERE at < ?1", params![cutoff])?;
+            tx.execute("DELETE FROM transitions WHERE at < ?1", params![cutoff])?;
+
+            let accuracy = fix.accuracy_m.unwrap_or(0.0);
+            let mut transitions = Vec::new();
+            if latest.is_none_or(|latest| fix.at.timestamp() >= latest)
+                && accuracy <= self.max_accuracy_m
+            {
+                let mut names: Vec<&String> = self.places.keys().collect();
+                names.sort();
+                for name in names {
+                    let place = &self.places[name];
+                    let distance = distance_m(fix.lat, fix.lon, place.lat, place.lon);
+                    let inside = if distance <= place.radius_m {
+                        true
+                    } else if distance > place.radius_m + accuracy.max(LEAVE_MARGIN_M) {
+                        false
+                    } else {
+                        continue;
+                    };
+                    let was_inside: Option<bool> = tx
+                        .query_row(
+                            "SELECT inside FROM presence WHERE user = ?1 AND place = ?2",
+                            params![fix.user, name],
+                            |row| row.get(0),
+                        )
+                        .optional()?;
+                    if was_inside == Some(inside) {
+                        continue;
+                    }
+                    tx.execute(
+                        "INSERT OR REPLACE INTO presence (user, place, inside) VALUES (?1, ?2, ?3)",
+                        params![fix.user, name, inside],
+                    )?;
+                    if was_inside.is_some() {
+                        let kind = if inside {
+                            GeofenceTransition::Enter
+                        } else {
+                            GeofenceTransition::Leave
+                        };
+                        tx.execute(
+                        "INSERT INTO transitions (user, place, kind, at) VALUES (?1, ?2, ?3, ?4)",
+                        params![fix.user, name, kind_name(kind), fix.at.timestamp()],
+                    )?;
+                        transitions.push(Transition {
+                            user: fix.user.clone(),
+                            place: name.clone(),
+                            kind,
+                            at: fix.at,
+                        });
+                    }
+                }
+            }
+            Ok(transitions)
+        })
+    }
+
+    /// Users with any stored pings, sorted.
+    pub fn users(&self) -> Result<Vec<String>> {
+        let conn = self.db.conn()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT user FROM fixes ORDER BY user")?;
+        let users = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(users)
+    }
+
+    /// A user's most recent ping.
+    pub fn latest(&self, user: &str) -> Result<Option<Fix>> {
+        let conn = self.db.conn()?;
+        let fix = conn
+            .query_row(
+                "SELECT at, lat, lon, accuracy, battery FROM fixes
+                 WHERE user = ?1 ORDER BY at DESC LIMIT 1",
+                params![user],
+                |row| {
+                    Ok(Fix {
+                        user: user.to_string(),
+                        at: Utc
+                            .timestamp_opt(row.get(0)?, 0)
+                            .single()
+                            .unwrap_or_default(),
+                        lat: row.get(1)?,
+                        lon: row.get(2)?,
+                        accuracy_m: row.get(3)?,
+                        battery: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(fix)
+    }
+
+    /// A user's arrivals and departures since `since`, oldest first.
+    pub fn transitions(&self, user: &str, since: DateTime<Utc>) -> Result<Vec<Transition>> {
+        let conn = self.db.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT place, kind, at FROM transitions
+             WHERE user = ?1 AND at >= ?2 ORDER BY at, rowid",
+        )?;
+        let transitions = stmt
+            .query_map(params![user, since.timestamp()], |row| {
+                let kind: String = row.get(1)?;
+                Ok(Transition {
+                    user: user.to_string(),
+                    place: row.get(0)?,
+                    kind: if kind == "leave" {
+                        GeofenceTransition::Leave
+                    } else {
+                        GeofenceTransition::Enter
+                    },
+                    at: Utc
+                        .timestamp_opt(row.get(2)?, 0)
+                        .single()
+                        .unwrap_or_default(),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(transitions)
+    }
+
+    /// The closest place `fix` is in, with its distance from the center.
+    pub fn place_of(&self, fix: &Fix) -> Option<(&str, f64)> {
+        self.places
+            .iter()
+            .map(|(name, place)| {
+                let distance = distance_m(fix.lat, fix.lon, place.lat, place.lon);
+                (name.as_str(), place, distance)
+            })
+            .filter(|(_, place, distance)| *distance <= place.radius_m)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(name, _, distance)| (name, distance))
+    }
+}
+
+fn kind_name(kind: GeofenceTransition) -> &'static str {
+    match kind {
+        GeofenceTransition::Enter => "enter",
+        GeofenceTransition::Leave => "leave",
+    }
+}
+
+/// The inbound messages `transition` triggers: one per geofence for its
+/// place and kind (and user, if the geofence names one), carrying the
+/// geofence's prompt to its chat.
+pub fn geofence_messages(
+    geofences: &[GeofenceConfig],
+    transition: &Transition,
+) -> Vec<InboundMessage> {
+    geofences
+        .iter()
+        .filter(|g| {
+            g.place == transition.place
+                && g.on == transition.kind
+                && g.user.as_ref().is_none_or(|u| *u == transition.user)
+                && !g.prompt.trim().is_empty()
+        })
+        .map(|g| {
+            let time = transition.at.with_timezone(&Local).format("%H:%M");
+            InboundMessage {
+                channel: g.channel.clone().unwrap_or_else(|| "system".to_string()),
+                sender_id: LOCATION_SENDER.to_string(),
+                chat_id: g.to.clone().unwrap_or_else(|| LOCATION_SENDER.to_string()),
+                content: format!(
+                    "[Location: {} at {time}]\n\n{}",
+                    transition.describe(),
+                    g.prompt.trim()
+                ),
+                media: Vec::new(),
+                metadata: HashMap::from([(
+                    "location_event".to_string(),
+                    serde_json::json!({
+                        "user": transition.user,
+                        "place": transition.place,
+                        "event": kind_name(transition.kind),
+                    }),
+                )]),
+                timestamp: crate::bus::default_timestamp(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &Path) -> LocationStore {
+        let config = LocationConfig {
+            enabled: true,
+            places: HashMap::from([(
+                "work".to_string(),
+                PlaceConfig {
+                    lat: 51.5000,
+                    lon: -0.1200,
+                    radius_m: 100.0,
+                },
+            )]),
+            ..Default::default()
+        };
+        LocationStore::open(&dir.join("location.sqlite"), &config).unwrap()
+    }
+
+    fn fix(lat: f64, minute: i64) -> Fix {
+        Fix {
+            user: "ada".to_string(),
+            lat,
+            lon: -0.1200,
+            accuracy_m: Some(10.0),
+            battery: None,
+            at: Utc::now() - chrono::Duration::hours(1) + chrono::Duration::minutes(minute),
+        }
+    }
+
+    #[test]
+    fn parses_owntracks_locations() {
+        let body = serde_json::json!({
+            "_type": "location", "lat": 51.5, "lon": -0.12, "acc": 12,
+            "batt": 80, "tst": 1_767_225_600, "tid": "ad"
+        });
+        let fix = parse_owntracks(&body, "ada").unwrap();
+        assert_eq!(
+            (fix.lat, fix.accuracy_m, fix.battery),
+            (51.5, Some(12.0), Some(80))
+        );
+        assert_eq!(fix.at.timestamp(), 1_767_225_600);
+        assert!(parse_owntracks(
+            &serde_json::json!({"_type": "waypoint", "lat": 1.0, "lon": 1.0}),
+            "ada"
+        )
+        .is_none());
+        assert!(parse_owntracks(&serde_json::json!({"lat": 91.0, "lon": 1.0}), "ada").is_none());
+        // Roughly 111 m per thousandth of a degree of latitude
+        assert!((distance_m(51.5, -0.12, 51.501, -0.12) - 111.2).abs() < 0.5);
+    }
+
+    #[test]
+    fn crossing_a_place_edge_is_a_transition() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(dir.path());
+        // First ping only notes where ada is
+        assert!(store.record(&fix(51.5001, 0)).unwrap().is_empty());
+        // Just past the edge isn't far enough to have left
+        assert!(store.record(&fix(51.5011, 1)).unwrap().is_empty());
+        let left = store.record(&fix(51.5100, 2)).unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].describe(), "ada left work");
+        // A late ping from inside doesn't bring ada back
+        assert!(store.record(&fix(51.5000, 1)).unwrap().is_empty());
+        let back = store.record(&fix(51.5002, 3)).unwrap();
+        assert_eq!(back[0].kind, GeofenceTransition::Enter);
+
+        let latest = store.latest("ada").unwrap().unwrap();
+        assert_eq!(latest.lat, 51.5002);
+        assert_eq!(store.place_of(&latest).unwrap().0, "work");
+        let since = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(store.transitions("ada", since).unwrap().len(), 2);
+        assert_eq!(store.users().unwrap(), ["ada"]);
+    }
+
+    #[test]
+    fn geofences_match_place_kind_and_user() {
+        let transition = Transition {
+            user: "ada".to_string(),
+            place: "work".to_string(),
+            kind: GeofenceTransition::Leave,
+            at: Utc::now(),
+        };
+        let geofence = |on, user: Option<&str>| GeofenceConfig {
+            place: "work".to_string(),
+            on,
+            user: user.map(String::from),
+            prompt: "Remind me to buy milk".to_string(),
+            channel: Some("telegram".to_string()),
+            to: Some("123".to_string()),
+        };
+        let messages = geofence_messages(
+            &[
+                geofence(GeofenceTransition::Leave, None),
+                geofence(GeofenceTransition::Enter, None),
+                geofence(GeofenceTransition::Leave, Some("bob")),
+            ],
+            &transition,
+        );
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].session_key(), "telegram:123");
+        assert_eq!(messages[0].sender_id, LOCATION_SENDER);
+        assert!(messages[0]
+            .content
+            .starts_with("[Location: ada left work at "));
+        assert!(messages[0].content.ends_with("\n\nRemind me to buy milk"));
+    }
+}
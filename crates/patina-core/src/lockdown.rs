@@ -0,0 +1,203 @@
+//! The panic switch.
+//!
+//! `patina lockdown` or `/lockdown` turns off the tools that act on the
+//! world ([`LOCKED_TOOLS`]) everywhere at once: the gateway, subagents, and
+//! cron's `exec` and `message` jobs, until it's lifted. The state is a flag
+//! file in the data directory, so the CLI can flip it for a running gateway
+//! and it survives restarts.
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rig::completion::CompletionRequest;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::agent::middleware::{HookContext, Middleware, ToolDecision};
+
+/// Tools that are off during a lockdown.
+pub const LOCKED_TOOLS: &[&str] = &["exec", "write_file", "edit_file", "message", "spawn"];
+
+/// Who turned lockdown on, and when.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockdownState {
+    pub since: chrono::DateTime<chrono::Utc>,
+    pub by: String,
+}
+
+/// The lockdown flag. Checked on every call to a locked tool, so a change
+/// applies from the next call, even mid-turn.
+pub struct Lockdown {
+    path: PathBuf,
+}
+
+impl Default for Lockdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lockdown {
+    /// The flag in `~/.patina/lockdown`.
+    pub fn new() -> Self {
+        Self::at(patina_config::data_dir().join("lockdown"))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// `None` when lockdown is off.
+    pub fn state(&self) -> Option<LockdownState> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        Some(serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Unreadable lockdown flag {}: {e}", self.path.display());
+            LockdownState {
+                since: chrono::Utc::now(),
+                by: "unknown".into(),
+            }
+        }))
+    }
+
+    /// Turn lockdown on. Leaves an existing lockdown's details alone.
+    pub fn engage(&self, by: &str) -> std::io::Result<()> {
+        if self.is_on() {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let state = LockdownState {
+            since: chrono::Utc::now(),
+            by: by.to_string(),
+        };
+        std::fs::write(&self.path, serde_json::to_string(&state)?)
+    }
+
+    /// Turn lockdown off, returning whether it was on.
+    pub fn lift(&self) -> std::io::Result<bool> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Handle `on`, `off`, or `status` (empty means `on`) from `by`, for
+    /// `/lockdown` and `patina lockdown`. Returns the reply.
+    pub fn command(&self, args: &str, by: &str) -> String {
+        match args.trim() {
+            "" | "on" => match self.engage(by) {
+                Ok(()) => {
+                    warn!("Lockdown engaged by {by}");
+                    format!(
+                        "Lockdown on. {} are off everywhere, cron exec and message jobs \
+                         included, until /lockdown off or `patina lockdown off`.",
+                        LOCKED_TOOLS.join(", ")
+                    )
+                }
+                Err(e) => format!("Couldn't turn lockdown on: {e}"),
+            },
+            "off" => match self.lift() {
+                Ok(true) => {
+                    warn!("Lockdown lifted by {by}");
+                    "Lockdown lifted. All tools are back.".to_string()
+                }
+                Ok(false) => "Lockdown wasn't on.".to_string(),
+                Err(e) => format!("Couldn't lift lockdown: {e}"),
+            },
+            "status" => match self.state() {
+                Some(state) => format!(
+                    "Lockdown on since {} (by {}).",
+                    state.since.format("%Y-%m-%d %H:%M UTC"),
+                    state.by
+                ),
+                None => "Lockdown is off.".to_string(),
+            },
+            other => format!("Unknown option '{other}'. Use on, off, or status."),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for Lockdown {
+    async fn before_completion(
+        &self,
+        _ctx: &HookContext<'_>,
+        request: &mut CompletionRequest,
+    ) -> Result<()> {
+        if self.is_on() {
+            request
+                .tools
+                .retain(|t| !LOCKED_TOOLS.contains(&t.name.as_str()));
+        }
+        Ok(())
+    }
+
+    async fn before_tool(
+        &self,
+        ctx: &HookContext<'_>,
+        tool: &str,
+        _args: &mut serde_json::Value,
+    ) -> Result<ToolDecision> {
+        if !LOCKED_TOOLS.contains(&tool) || !self.is_on() {
+            return Ok(ToolDecision::Run);
+        }
+        warn!("Refused {tool} in '{}': lockdown is on", ctx.session_key);
+        Ok(ToolDecision::Skip(format!(
+            "Error: {tool} is off because the owner put patina in lockdown. Don't try \
+             another way around it; tell the user what you were going to do."
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lockdown_blocks_locked_tools_until_lifted() {
+        let dir = tempfile::tempdir().unwrap();
+        let lockdown = Lockdown::at(dir.path().join("lockdown"));
+        let ctx = HookContext {
+            session_key: "telegram:1",
+            agent: "default",
+            model: "m",
+            provider: "p",
+            iteration: 1,
+        };
+        let mut args = serde_json::json!({});
+
+        assert_eq!(lockdown.command("status", "cli"), "Lockdown is off.");
+        assert!(lockdown
+            .command("", "telegram:1")
+            .starts_with("Lockdown on."));
+        assert_eq!(lockdown.state().unwrap().by, "telegram:1");
+        let decision = lockdown.before_tool(&ctx, "exec", &mut args).await.unwrap();
+        assert!(
+            matches!(decision, ToolDecision::Skip(ref r) if r.starts_with("Error: exec is off"))
+        );
+        let decision = lockdown
+            .before_tool(&ctx, "read_file", &mut args)
+            .await
+            .unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+
+        // A second engage keeps who started it
+        lockdown.engage("cli").unwrap();
+        assert_eq!(lockdown.state().unwrap().by, "telegram:1");
+
+        assert_eq!(
+            lockdown.command("off", "cli"),
+            "Lockdown lifted. All tools are back."
+        );
+        assert_eq!(lockdown.command("off", "cli"), "Lockdown wasn't on.");
+        let decision = lockdown.before_tool(&ctx, "exec", &mut args).await.unwrap();
+        assert_eq!(decision, ToolDecision::Run);
+    }
+}
@@ -0,0 +1,166 @@
+//! A live copy of the process's log output, for watching a headless
+//! deployment from the web UI (`/api/logs/stream`).
+//!
+//! [`LogStreamLayer`] sits beside the terminal formatter in the tracing
+//! subscriber, so it sees the same events the `RUST_LOG` filter lets
+//! through, and publishes each one to [`stream()`]. Subscribers get the
+//! last [`BACKLOG_LINES`] lines first, then new ones as they're logged.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// Lines kept for subscribers that connect later.
+pub const BACKLOG_LINES: usize = 500;
+
+/// Lines a slow subscriber can fall behind before it skips ahead.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// One log event.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    pub timestamp: String,
+    /// "ERROR", "WARN", "INFO", "DEBUG", or "TRACE".
+    pub level: String,
+    pub target: String,
+    /// The message followed by the event's other fields as `key=value`.
+    pub message: String,
+}
+
+impl LogLine {
+    /// Whether the line is at `max` or more severe (`max` INFO keeps ERROR,
+    /// WARN, and INFO).
+    pub fn within(&self, max: Level) -> bool {
+        self.level.parse::<Level>().is_ok_and(|level| level <= max)
+    }
+}
+
+/// The recent lines and the channel new ones are sent on.
+pub struct LogStream {
+    tx: broadcast::Sender<LogLine>,
+    recent: Mutex<VecDeque<LogLine>>,
+}
+
+impl LogStream {
+    fn new() -> Self {
+        Self {
+            tx: broadcast::channel(CHANNEL_CAPACITY).0,
+            recent: Mutex::new(VecDeque::with_capacity(BACKLOG_LINES)),
+        }
+    }
+
+    pub fn publish(&self, line: LogLine) {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        if recent.len() == BACKLOG_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line.clone());
+        // Sent under the lock so a new subscriber gets each line exactly once
+        let _ = self.tx.send(line);
+    }
+
+    /// The recent lines, oldest first, and a receiver for the ones after.
+    pub fn subscribe(&self) -> (Vec<LogLine>, broadcast::Receiver<LogLine>) {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        (recent.iter().cloned().collect(), self.tx.subscribe())
+    }
+}
+
+/// The process-wide stream [`LogStreamLayer`] publishes to.
+pub fn stream() -> &'static LogStream {
+    static STREAM: OnceLock<LogStream> = OnceLock::new();
+    STREAM.get_or_init(LogStream::new)
+}
+
+/// Tracing layer that publishes every event it sees to [`stream()`].
+pub struct LogStreamLayer;
+
+impl<S: Subscriber> Layer<S> for LogStreamLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        stream().publish(LogLine {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        });
+    }
+}
+
+/// Collects the `message` field and the rest as `key=value`.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(mut self) -> String {
+        if !self.fields.is_empty() {
+            if !self.message.is_empty() {
+                self.message.push(' ');
+            }
+            self.message.push_str(&self.fields);
+        }
+        self.message
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={value:?}", field.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn publishes_events_with_their_fields() {
+        let subscriber = tracing_subscriber::registry().with(LogStreamLayer);
+        let (_, mut rx) = stream().subscribe();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(chat = "web:1", attempt = 2, "Provider slow");
+            tracing::debug!("details");
+        });
+
+        let line = rx.try_recv().unwrap();
+        assert_eq!(line.level, "WARN");
+        assert_eq!(line.message, r#"Provider slow chat="web:1" attempt=2"#);
+        assert!(line.within(Level::INFO));
+
+        let line = rx.try_recv().unwrap();
+        assert_eq!(line.message, "details");
+        assert!(!line.within(Level::INFO) && line.within(Level::DEBUG));
+
+        let (recent, _) = stream().subscribe();
+        assert!(recent.ends_with(&[line]));
+    }
+}
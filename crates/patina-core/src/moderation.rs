@@ -0,0 +1,380 @@
+//! Content moderation for chat channels: local keyword/regex rules plus an
+//! optional hosted classifier, applied to user messages before they reach
+//! the agent and to replies before they are sent.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use patina_config::{ModerationAction, ModerationConfig, ModerationDirection};
+use regex::Regex;
+use serde::Deserialize;
+use tracing::warn;
+
+const DEFAULT_API_BASE: &str = "https://api.openai.com/v1";
+
+/// Which way a message is travelling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From a user to the agent.
+    Inbound,
+    /// From the agent to a user.
+    Outbound,
+}
+
+impl Direction {
+    fn matches(self, rule: ModerationDirection) -> bool {
+        matches!(
+            (self, rule),
+            (_, ModerationDirection::Both)
+                | (Direction::Inbound, ModerationDirection::Inbound)
+                | (Direction::Outbound, ModerationDirection::Outbound)
+        )
+    }
+}
+
+/// Result of moderating one message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    /// Strictest action that applied, or `None` if nothing matched.
+    pub action: Option<ModerationAction>,
+    /// Text to use from here on: rewritten if a rewrite rule matched, the
+    /// block message if blocked, otherwise the original.
+    pub text: String,
+    /// Names of the rules or classifier categories that matched.
+    pub reasons: Vec<String>,
+}
+
+impl Verdict {
+    fn allow(text: &str) -> Self {
+        Self {
+            action: None,
+            text: text.to_string(),
+            reasons: Vec::new(),
+        }
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.action == Some(ModerationAction::Block)
+    }
+
+    /// Whether `text` differs from the original message.
+    pub fn changed(&self) -> bool {
+        matches!(
+            self.action,
+            Some(ModerationAction::Rewrite | ModerationAction::Block)
+        )
+    }
+
+    fn escalate(&mut self, action: ModerationAction, reason: String) {
+        self.action = Some(self.action.map_or(action, |a| a.max(action)));
+        self.reasons.push(reason);
+    }
+}
+
+struct Rule {
+    name: String,
+    regex: Regex,
+    action: ModerationAction,
+    replacement: String,
+    direction: ModerationDirection,
+    channels: Vec<String>,
+}
+
+struct Classifier {
+    http: reqwest::Client,
+    url: String,
+    api_key: String,
+    model: String,
+    action: ModerationAction,
+    fail_closed: bool,
+}
+
+#[derive(Deserialize)]
+struct ModerationResponse {
+    results: Vec<ModerationResult>,
+}
+
+#[derive(Deserialize)]
+struct ModerationResult {
+    flagged: bool,
+    #[serde(default)]
+    categories: std::collections::HashMap<String, bool>,
+}
+
+/// Applies the `moderation` config to messages on moderated channels.
+pub struct Moderator {
+    channels: Vec<String>,
+    inbound: bool,
+    outbound: bool,
+    rules: Vec<Rule>,
+    classifier: Option<Classifier>,
+    block_message: String,
+}
+
+impl Moderator {
+    /// Compile the configured rules. `api_key` is the resolved key for the
+    /// classifier; without one the classifier is skipped with a warning.
+    pub fn new(config: &ModerationConfig, api_key: Option<String>) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (i, rule) in config.rules.iter().enumerate() {
+            let name = if rule.name.is_empty() {
+                format!("rule {}", i + 1)
+            } else {
+                rule.name.clone()
+            };
+            let mut alternatives: Vec<String> = rule.pattern.iter().cloned().collect();
+            let keywords: Vec<String> = rule
+                .keywords
+                .iter()
+                .map(|k| k.trim())
+                .filter(|k| !k.is_empty())
+                .map(regex::escape)
+                .collect();
+            if !keywords.is_empty() {
+                alternatives.push(format!(r"(?i)\b(?:{})\b", keywords.join("|")));
+            }
+            if alternatives.is_empty() {
+                warn!("Moderation {name} has no pattern or keywords, ignoring");
+                continue;
+            }
+            let regex = Regex::new(&alternatives.join("|"))
+                .map_err(|e| anyhow!("Invalid moderation pattern in {name}: {e}"))?;
+            rules.push(Rule {
+                name,
+                regex,
+                action: rule.action,
+                replacement: rule.replacement.clone(),
+                direction: rule.direction,
+                channels: rule.channels.clone(),
+            });
+        }
+
+        let classifier = match (&config.provider, api_key) {
+            (Some(p), Some(api_key)) => Some(Classifier {
+                http: reqwest::Client::builder()
+                    .timeout(Duration::from_secs(15))
+                    .build()
+                    .unwrap_or_default(),
+                url: format!(
+                    "{}/moderations",
+                    p.api_base
+                        .as_deref()
+                        .unwrap_or(DEFAULT_API_BASE)
+                        .trim_end_matches('/')
+                ),
+                api_key,
+                model: p.model.clone(),
+                action: p.action,
+                fail_closed: p.fail_closed,
+            }),
+            (Some(_), None) => {
+                warn!("moderation.provider is set but no API key was found, skipping it");
+                None
+            }
+            (None, _) => None,
+        };
+
+        Ok(Self {
+            channels: config.channels.clone(),
+            inbound: config.inbound,
+            outbound: config.outbound,
+            rules,
+            classifier,
+            block_message: config.block_message.clone(),
+        })
+    }
+
+    /// Whether messages on `channel` going `direction` are checked.
+    pub fn covers(&self, channel: &str, direction: Direction) -> bool {
+        let enabled = match direction {
+            Direction::Inbound => self.inbound,
+            Direction::Outbound => self.outbound,
+        };
+        enabled && (self.channels.is_empty() || self.channels.iter().any(|c| c == channel))
+    }
+
+    /// Run the rules, then the classifier unless a rule already blocked.
+    pub async fn check(&self, channel: &str, direction: Direction, text: &str) -> Verdict {
+        if !self.covers(channel, direction) {
+            return Verdict::allow(text);
+        }
+        let mut verdict = self.apply_rules(channel, direction, text);
+        if verdict.is_blocked() {
+            return verdict;
+        }
+        if let Some(ref classifier) = self.classifier {
+            match classifier.classify(&verdict.text).await {
+                Ok(categories) if !categories.is_empty() => {
+                    let action = match classifier.action {
+                        ModerationAction::Flag => ModerationAction::Flag,
+                        ModerationAction::Rewrite | ModerationAction::Block => {
+                            ModerationAction::Block
+                        }
+                    };
+                    for category in categories {
+                        verdict.escalate(action, category);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if classifier.fail_closed => {
+                    warn!("Moderation classifier failed, blocking: {e}");
+                    verdict.escalate(ModerationAction::Block, "classifier unavailable".into());
+                }
+                Err(e) => warn!("Moderation classifier failed, allowing: {e}"),
+            }
+        }
+        if verdict.is_blocked() {
+            verdict.text.clone_from(&self.block_message);
+        }
+        verdict
+    }
+
+    fn apply_rules(&self, channel: &str, direction: Direction, text: &str) -> Verdict {
+        let mut verdict = Verdict::allow(text);
+        for rule in &self.rules {
+            if !direction.matches(rule.direction)
+                || !(rule.channels.is_empty() || rule.channels.iter().any(|c| c == channel))
+                || !rule.regex.is_match(&verdict.text)
+            {
+                continue;
+            }
+            verdict.escalate(rule.action, rule.name.clone());
+            match rule.action {
+                ModerationAction::Block => {
+                    verdict.text.clone_from(&self.block_message);
+                    break;
+                }
+                ModerationAction::Rewrite => {
+                    verdict.text = rule
+                        .regex
+                        .replace_all(&verdict.text, rule.replacement.as_str())
+                        .into_owned();
+                }
+                ModerationAction::Flag => {}
+            }
+        }
+        verdict
+    }
+}
+
+impl Classifier {
+    /// Names of the categories the classifier flagged (or "flagged" if it
+    /// gave none).
+    async fn classify(&self, text: &str) -> Result<Vec<String>> {
+        let resp: ModerationResponse = self
+            .http
+            .post(&self.url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "model": self.model, "input": text }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut flagged = Vec::new();
+        for result in resp.results.into_iter().filter(|r| r.flagged) {
+            let mut categories: Vec<String> = result
+                .categories
+                .into_iter()
+                .filter(|(_, hit)| *hit)
+                .map(|(name, _)| name)
+                .collect();
+            categories.sort();
+            if categories.is_empty() {
+                categories.push("flagged".to_string());
+            }
+            flagged.extend(categories);
+        }
+        Ok(flagged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use patina_config::ModerationRule;
+
+    fn moderator() -> Moderator {
+        let config = ModerationConfig {
+            enabled: true,
+            channels: vec!["web".into()],
+            rules: vec![
+                ModerationRule {
+                    name: "card numbers".into(),
+                    pattern: Some(r"\b\d{4}(?: ?\d{4}){3}\b".into()),
+                    action: ModerationAction::Rewrite,
+                    direction: ModerationDirection::Outbound,
+                    ..Default::default()
+                },
+                ModerationRule {
+                    name: "banned".into(),
+                    keywords: vec!["Forbidden Topic".into()],
+                    action: ModerationAction::Block,
+                    ..Default::default()
+                },
+                ModerationRule {
+                    keywords: vec!["crypto".into()],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        Moderator::new(&config, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rules() {
+        let m = moderator();
+
+        let v = m
+            .check("web", Direction::Outbound, "Card: 4111 1111 1111 1111, ok?")
+            .await;
+        assert_eq!(v.action, Some(ModerationAction::Rewrite));
+        assert_eq!(v.text, "Card: [removed], ok?");
+        assert!(v.changed());
+
+        // Outbound-only rule doesn't apply inbound
+        let v = m
+            .check("web", Direction::Inbound, "4111 1111 1111 1111")
+            .await;
+        assert_eq!(v, Verdict::allow("4111 1111 1111 1111"));
+
+        let v = m
+            .check(
+                "web",
+                Direction::Inbound,
+                "tell me about the forbidden topic and crypto",
+            )
+            .await;
+        assert!(v.is_blocked());
+        assert_eq!(v.text, "Sorry, I can't help with that.");
+        assert_eq!(v.reasons, vec!["banned"]);
+
+        let v = m.check("web", Direction::Inbound, "Crypto prices?").await;
+        assert_eq!(v.action, Some(ModerationAction::Flag));
+        assert_eq!(v.text, "Crypto prices?");
+        assert_eq!(v.reasons, vec!["rule 3"]);
+        assert!(!v.changed());
+
+        // Unmoderated channel
+        assert!(!m.covers("telegram", Direction::Inbound));
+        let v = m
+            .check("telegram", Direction::Inbound, "the forbidden topic")
+            .await;
+        assert_eq!(v.action, None);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let config = ModerationConfig {
+            rules: vec![ModerationRule {
+                name: "broken".into(),
+                pattern: Some("(".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let err = Moderator::new(&config, None).err().unwrap();
+        assert!(err.to_string().contains("broken"), "{err}");
+    }
+}
@@ -0,0 +1,312 @@
+//! Ollama model management: list installed models, pull missing ones, and
+//! control how long models stay loaded (`keep_alive`).
+//!
+//! Completions go through rig's Ollama provider; this module only talks to
+//! Ollama's native endpoints (`/api/tags`, `/api/ps`, `/api/pull`, and
+//! `/api/generate` to apply `keep_alive`).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tracing::info;
+
+/// Base URL used when `providers.ollama.apiBase` is not set.
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Give up on a pull if Ollama sends nothing for this long.
+const PULL_READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A model installed on the Ollama host.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelDetails {
+    #[serde(default)]
+    pub parameter_size: String,
+    #[serde(default)]
+    pub quantization_level: String,
+}
+
+/// A model currently loaded into memory.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoadedModel {
+    pub name: String,
+    #[serde(default)]
+    pub size_vram: u64,
+    /// RFC 3339 time at which Ollama will unload the model.
+    #[serde(default)]
+    pub expires_at: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList<T> {
+    #[serde(default = "Vec::new")]
+    models: Vec<T>,
+}
+
+/// One line of the `/api/pull` progress stream.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PullProgress {
+    #[serde(default)]
+    pub status: String,
+    pub total: Option<u64>,
+    pub completed: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl PullProgress {
+    pub fn percent(&self) -> Option<u8> {
+        let total = self.total.filter(|t| *t > 0)?;
+        Some(((self.completed.unwrap_or(0).min(total) * 100) / total) as u8)
+    }
+}
+
+/// Callback for pull progress events.
+pub type PullProgressFn = Arc<dyn Fn(&PullProgress) + Send + Sync>;
+
+/// Whether two Ollama model names refer to the same model. A name without a
+/// tag means `:latest`.
+pub fn same_model(a: &str, b: &str) -> bool {
+    fn with_tag(name: &str) -> String {
+        let last = name.rsplit('/').next().unwrap_or(name);
+        if last.contains(':') {
+            name.to_string()
+        } else {
+            format!("{name}:latest")
+        }
+    }
+    with_tag(a) == with_tag(b)
+}
+
+/// Human-readable size, e.g. "4.7 GB".
+pub fn format_size(bytes: u64) -> String {
+    const GB: f64 = 1_000_000_000.0;
+    const MB: f64 = 1_000_000.0;
+    let b = bytes as f64;
+    if b >= GB {
+        format!("{:.1} GB", b / GB)
+    } else {
+        format!("{:.0} MB", b / MB)
+    }
+}
+
+/// `keep_alive` as Ollama expects it: bare numbers are seconds and must be
+/// sent as numbers, anything else (e.g. "30m") as a duration string.
+pub fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    let keep_alive = keep_alive.trim();
+    match keep_alive.parse::<i64>() {
+        Ok(secs) => serde_json::json!(secs),
+        Err(_) => serde_json::json!(keep_alive),
+    }
+}
+
+/// Parse one NDJSON line of pull progress. Blank lines yield `None`.
+fn parse_progress_line(line: &str) -> Result<Option<PullProgress>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let progress: PullProgress = serde_json::from_str(line)?;
+    if let Some(ref e) = progress.error {
+        bail!("{e}");
+    }
+    Ok(Some(progress))
+}
+
+/// Client for Ollama's model management API.
+#[derive(Clone)]
+pub struct OllamaClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl OllamaClient {
+    /// Create a client for `api_base`, falling back to [`DEFAULT_BASE_URL`].
+    pub fn new(api_base: Option<&str>) -> Self {
+        let base = api_base
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .unwrap_or(DEFAULT_BASE_URL);
+        Self {
+            base_url: base.trim_end_matches('/').to_string(),
+            http: reqwest::Client::builder()
+                .connect_timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Models installed on the host.
+    pub async fn list_models(&self) -> Result<Vec<LocalModel>> {
+        let list: ModelList<LocalModel> = self
+            .http
+            .get(format!("{}/api/tags", self.base_url))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(list.models)
+    }
+
+    /// Models currently loaded into memory.
+    pub async fn loaded_models(&self) -> Result<Vec<LoadedModel>> {
+        let list: ModelList<LoadedModel> = self
+            .http
+            .get(format!("{}/api/ps", self.base_url))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(list.models)
+    }
+
+    /// Whether `model` is installed.
+    pub async fn has_model(&self, model: &str) -> Result<bool> {
+        Ok(self
+            .list_models()
+            .await?
+            .iter()
+            .any(|m| same_model(&m.name, model)))
+    }
+
+    /// Download `model`, reporting progress roughly once per percent.
+    pub async fn pull(&self, model: &str, progress: Option<PullProgressFn>) -> Result<()> {
+        info!("Pulling Ollama model '{model}'");
+        let mut resp = self
+            .http
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut buf = String::new();
+        let mut last: Option<(String, Option<u8>)> = None;
+        let mut succeeded = false;
+        loop {
+            let chunk = match tokio::time::timeout(PULL_READ_TIMEOUT, resp.chunk()).await {
+                Ok(chunk) => chunk?,
+                Err(_) => bail!("Pull of '{model}' stalled"),
+            };
+            let Some(chunk) = chunk else { break };
+            buf.push_str(&String::from_utf8_lossy(&chunk));
+
+            while let Some(pos) = buf.find('\n') {
+                let line: String = buf.drain(..=pos).collect();
+                let Some(event) = parse_progress_line(&line)
+                    .map_err(|e| anyhow::anyhow!("Pull of '{model}' failed: {e}"))?
+                else {
+                    continue;
+                };
+                succeeded |= event.status == "success";
+
+                let key = (event.status.clone(), event.percent());
+                if last.as_ref() == Some(&key) {
+                    continue;
+                }
+                if last.as_ref().map(|(s, _)| s) != Some(&event.status)
+                    || key.1.is_some_and(|p| p % 10 == 0)
+                {
+                    match key.1 {
+                        Some(p) => info!("Pulling {model}: {} {p}%", event.status),
+                        None => info!("Pulling {model}: {}", event.status),
+                    }
+                }
+                last = Some(key);
+                if let Some(ref cb) = progress {
+                    cb(&event);
+                }
+            }
+        }
+
+        if !succeeded {
+            bail!("Pull of '{model}' ended before completing");
+        }
+        Ok(())
+    }
+
+    /// Load `model` and keep it in memory for `keep_alive` (e.g. "30m",
+    /// "-1" for forever, "0" to unload now).
+    pub async fn set_keep_alive(&self, model: &str, keep_alive: &str) -> Result<()> {
+        self.http
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&serde_json::json!({
+                "model": model,
+                "keep_alive": keep_alive_value(keep_alive),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_model() {
+        assert!(same_model("llama3.1", "llama3.1:latest"));
+        assert!(same_model("library/qwen2.5:7b", "library/qwen2.5:7b"));
+        assert!(same_model(
+            "host:5000/team/model",
+            "host:5000/team/model:latest"
+        ));
+        assert!(!same_model("llama3.1:8b", "llama3.1:70b"));
+        assert!(!same_model("llama3.1:8b", "llama3.1"));
+    }
+
+    #[test]
+    fn test_parse_progress_lines() {
+        let p = parse_progress_line(
+            r#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a07","total":4000,"completed":1000}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(p.status, "pulling 6a0746a1ec1a");
+        assert_eq!(p.percent(), Some(25));
+
+        let p = parse_progress_line(r#"{"status":"verifying sha256 digest"}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(p.percent(), None);
+
+        assert!(parse_progress_line("  \n").unwrap().is_none());
+
+        let err = parse_progress_line(r#"{"error":"pull model manifest: file does not exist"}"#)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "pull model manifest: file does not exist");
+    }
+
+    #[test]
+    fn test_keep_alive_value() {
+        assert_eq!(keep_alive_value("-1"), serde_json::json!(-1));
+        assert_eq!(keep_alive_value(" 600 "), serde_json::json!(600));
+        assert_eq!(keep_alive_value("30m"), serde_json::json!("30m"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(4_661_224_676), "4.7 GB");
+        assert_eq!(format_size(274_302_450), "274 MB");
+    }
+}
@@ -0,0 +1,131 @@
+//! YAML import and export of cron jobs and the task board.
+//!
+//! `patina cron export` and `patina tasks export` write YAML meant to be
+//! edited by hand and kept in version control, and `import` loads it back,
+//! here or on a second machine. Entries are matched on `id`: one that exists
+//! is updated, a new one is added under its ID (so chains between jobs stay
+//! intact), and one without an ID gets a new one. With `--replace`, entries
+//! missing from the file are removed, making the file the whole list.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// What an import changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Imported {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for Imported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} updated, {} removed",
+            self.added, self.updated, self.removed
+        )
+    }
+}
+
+/// `value` as YAML under a `# header` comment, leaving out empty fields.
+pub fn to_yaml<T: Serialize>(header: &str, value: &T) -> Result<String> {
+    let mut value = serde_yaml::to_value(value)?;
+    drop_nulls(&mut value);
+    Ok(format!("# {header}\n{}", serde_yaml::to_string(&value)?))
+}
+
+/// Parse YAML written by [`to_yaml`] or by hand.
+pub fn from_yaml<T: DeserializeOwned>(text: &str) -> Result<T> {
+    serde_yaml::from_str(text).context("Invalid YAML")
+}
+
+fn drop_nulls(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(drop_nulls);
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(drop_nulls),
+        _ => {}
+    }
+}
+
+/// Fail if two of `ids` are the same.
+pub(crate) fn check_unique<'a>(ids: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            anyhow::bail!("ID {id} appears more than once");
+        }
+    }
+    Ok(())
+}
+
+/// Merge `incoming` into `existing` by ID: `update` the entries that match,
+/// add the rest, and with `replace` remove those not in `incoming`.
+pub(crate) fn merge<T>(
+    existing: &mut Vec<T>,
+    incoming: Vec<T>,
+    replace: bool,
+    id: impl Fn(&T) -> &str,
+    update: impl Fn(&mut T, T),
+) -> Imported {
+    let mut imported = Imported::default();
+    if replace {
+        let keep: HashSet<&str> = incoming.iter().map(&id).collect();
+        let before = existing.len();
+        existing.retain(|e| keep.contains(id(e)));
+        imported.removed = before - existing.len();
+    }
+    for item in incoming {
+        match existing.iter_mut().find(|e| id(e) == id(&item)) {
+            Some(e) => {
+                update(e, item);
+                imported.updated += 1;
+            }
+            None => {
+                existing.push(item);
+                imported.added += 1;
+            }
+        }
+    }
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_by_id() {
+        let mut existing = vec![("a", 1), ("b", 2), ("c", 3)];
+        let imported = merge(
+            &mut existing,
+            vec![("b", 20), ("d", 4)],
+            false,
+            |e| e.0,
+            |e, new| e.1 = new.1,
+        );
+        assert_eq!(existing, [("a", 1), ("b", 20), ("c", 3), ("d", 4)]);
+        assert_eq!(imported.to_string(), "1 added, 1 updated, 0 removed");
+
+        let imported = merge(
+            &mut existing,
+            vec![("d", 40)],
+            true,
+            |e| e.0,
+            |e, new| *e = new,
+        );
+        assert_eq!(existing, [("d", 40)]);
+        assert_eq!(imported.removed, 3);
+
+        assert!(check_unique(["a", "b"]).is_ok());
+        assert!(check_unique(["a", "b", "a"]).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Questions background work puts to the owner.
+//!
+//! The `ask_user` tool sends a question to `tools.askUser.ownerChat` and
+//! waits for the answer, which the owner gives with `/answer`. Subagents and
+//! cron turns use it at decision points instead of guessing. After the
+//! timeout, the run goes on with the default it gave, or without an answer.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use patina_config::AskUserConfig;
+use tokio::sync::{broadcast, oneshot};
+use tracing::{info, warn};
+
+use crate::bus::OutboundMessage;
+
+struct Pending {
+    id: String,
+    from: String,
+    question: String,
+    reply: oneshot::Sender<String>,
+}
+
+/// Questions waiting for the owner, and the chat they're asked in.
+pub struct Questions {
+    outbound_tx: broadcast::Sender<OutboundMessage>,
+    channel: String,
+    chat_id: String,
+    timeout: Duration,
+    pending: Mutex<Vec<Pending>>,
+}
+
+impl Questions {
+    /// `None` unless `config.owner_chat` names a chat.
+    pub fn new(
+        config: &AskUserConfig,
+        outbound_tx: broadcast::Sender<OutboundMessage>,
+    ) -> Option<Self> {
+        let owner = config.owner_chat.as_deref()?;
+        let Some((channel, chat_id)) = owner
+            .split_once(':')
+            .filter(|(c, id)| !c.is_empty() && !id.is_empty())
+        else {
+            warn!("tools.askUser.ownerChat '{owner}' isn't \"channel:chat_id\"; ask_user is off");
+            return None;
+        };
+        Some(Self {
+            outbound_tx,
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            timeout: Duration::from_secs(config.timeout_mins.max(1) * 60),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The owner chat's session key.
+    pub fn owner_session(&self) -> String {
+        format!("{}:{}", self.channel, self.chat_id)
+    }
+
+    /// The configured wait for an answer.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Pending>> {
+        match self.pending.lock() {
+            Ok(g) => g,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    /// Send `question` from `from` to the owner and wait up to `timeout` for
+    /// the answer. `None` means nobody answered in time, or no channel is
+    /// running to ask on.
+    pub async fn ask(
+        &self,
+        from: &str,
+        question: &str,
+        default: Option<&str>,
+        timeout: Duration,
+    ) -> Option<String> {
+        let id = uuid::Uuid::new_v4().to_string()[..6].to_string();
+        let (reply, answer) = oneshot::channel();
+        self.lock().push(Pending {
+            id: id.clone(),
+            from: from.to_string(),
+            question: question.to_string(),
+            reply,
+        });
+
+        let fallback = match default {
+            Some(d) => format!("it goes on with: {d}"),
+            None => "it goes on without one".to_string(),
+        };
+        let content = format!(
+            "Question from {from} (ID {id}):\n{question}\n\nReply with /answer {id} <answer>. \
+             With no answer in {}, {fallback}.",
+            minutes(timeout)
+        );
+        if self
+            .outbound_tx
+            .send(OutboundMessage {
+                channel: self.channel.clone(),
+                chat_id: self.chat_id.clone(),
+                content,
+                reply_to: None,
+                metadata: Default::default(),
+            })
+            .is_err()
+        {
+            // Nobody to ask (e.g. the CLI runs no channels)
+            warn!("No channel receivers for question {id} from {from}");
+            self.lock().retain(|p| p.id != id);
+            return None;
+        }
+        info!("Waiting for an answer to question {id} from {from}");
+
+        let answered = tokio::time::timeout(timeout, answer).await;
+        self.lock().retain(|p| p.id != id);
+        answered.ok().and_then(Result::ok)
+    }
+
+    /// Handle `/answer <args>`: "<id> <answer>", or just the answer when
+    /// one question is waiting. Returns the reply for the owner.
+    pub fn answer(&self, args: &str) -> String {
+        let args = args.trim();
+        let mut pending = self.lock();
+        if pending.is_empty() {
+            return "No questions are waiting for an answer.".to_string();
+        }
+        let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let (index, text) = match pending.iter().position(|p| p.id == first) {
+            Some(i) => (i, rest.trim()),
+            None if pending.len() == 1 => (0, args),
+            None => {
+                let mut out = "Which question? Reply with /answer <id> <answer>:".to_string();
+                for p in pending.iter() {
+                    out.push_str(&format!("\n[{}] {}: {}", p.id, p.from, p.question));
+                }
+                return out;
+            }
+        };
+        if text.is_empty() {
+            let p = &pending[index];
+            return format!(
+                "{} asked: {}\nReply with /answer {} <answer>.",
+                p.from, p.question, p.id
+            );
+        }
+        let p = pending.remove(index);
+        match p.reply.send(text.to_string()) {
+            Ok(()) => format!("Passed your answer on to {}.", p.from),
+            Err(_) => format!("{} stopped waiting for that answer.", p.from),
+        }
+    }
+}
+
+/// "15 minutes", "1 minute".
+fn minutes(d: Duration) -> String {
+    match d.as_secs().div_ceil(60).max(1) {
+        1 => "1 minute".to_string(),
+        n => format!("{n} minutes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn questions() -> (Questions, broadcast::Receiver<OutboundMessage>) {
+        let (tx, rx) = broadcast::channel(8);
+        let config = AskUserConfig {
+            owner_chat: Some("telegram:42".into()),
+            ..Default::default()
+        };
+        (Questions::new(&config, tx).unwrap(), rx)
+    }
+
+    #[tokio::test]
+    async fn test_answer_resumes_the_asker() {
+        let (questions, mut rx) = questions();
+        assert_eq!(questions.owner_session(), "telegram:42");
+        assert_eq!(
+            questions.answer("yes"),
+            "No questions are waiting for an answer."
+        );
+
+        let asking = questions.ask(
+            "subagent ab12",
+            "Overwrite report.md?",
+            Some("no"),
+            Duration::from_secs(5),
+        );
+        let answering = async {
+            let notice = rx.recv().await.unwrap();
+            assert_eq!(notice.chat_id, "42");
+            assert!(notice.content.contains("it goes on with: no"));
+            let id = notice.content.split("(ID ").nth(1).unwrap()[..6].to_string();
+            assert!(questions.answer(&id).starts_with("subagent ab12 asked"));
+            questions.answer(&format!("{id} yes, overwrite it"))
+        };
+        let (answer, reply) = tokio::join!(asking, answering);
+        assert_eq!(answer.as_deref(), Some("yes, overwrite it"));
+        assert_eq!(reply, "Passed your answer on to subagent ab12.");
+
+        let unanswered = questions
+            .ask("cron:daily", "Which repo?", None, Duration::from_millis(10))
+            .await;
+        assert_eq!(unanswered, None);
+        assert!(questions.lock().is_empty());
+    }
+}
@@ -1,11 +1,43 @@
 use std::collections::HashMap;
 use std::io::{BufRead, Write};
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, Utc};
+use patina_config::{FsyncPolicy, SessionsConfig};
 use serde::{Deserialize, Serialize};
 
+/// Device names Windows reserves in every directory.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn a session key into a file name that is valid on every platform:
+/// `telegram:12345` becomes `telegram_12345`. Characters Windows rejects,
+/// whitespace and control characters become `_`, as does a trailing dot,
+/// and reserved device names like `NUL` get a `_` prefix.
+pub fn safe_file_name(key: &str) -> String {
+    let mut safe: String = key
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if safe.ends_with('.') {
+        safe.pop();
+        safe.push('_');
+    }
+    let stem = safe.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        safe.insert(0, '_');
+    }
+    safe
+}
+
 /// A single message in a session.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -108,11 +140,145 @@ impl Session {
     }
 }
 
+fn metadata_line(session: &Session) -> Result<String> {
+    let meta = SessionMetadata {
+        type_field: "metadata".into(),
+        created_at: session.created_at.to_rfc3339(),
+        updated_at: session.updated_at.to_rfc3339(),
+        metadata: session.metadata.clone(),
+        last_consolidated: session.last_consolidated,
+    };
+    Ok(serde_json::to_string(&meta)?)
+}
+
+/// A session's compacted JSONL file contents: the metadata line, then one
+/// line per message.
+fn render(session: &Session) -> Result<String> {
+    let mut out = metadata_line(session)?;
+    out.push('\n');
+    for msg in &session.messages {
+        out.push_str(&serde_json::to_string(msg)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn hash(value: &impl Serialize) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    // Through a Value so map keys come out sorted, whatever order the
+    // HashMaps iterate in
+    serde_json::to_value(value)
+        .map(|v| v.to_string())
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// What a session's file holds, as far as this manager knows.
+#[derive(Clone, Copy, PartialEq)]
+struct Snapshot {
+    messages: usize,
+    /// Hash of the last message, to catch one rewritten in place.
+    last_message: u64,
+    /// Hash of the metadata, apart from `updated_at`.
+    header: u64,
+}
+
+impl Snapshot {
+    fn of(session: &Session) -> Self {
+        Self {
+            messages: session.messages.len(),
+            last_message: session.messages.last().map(hash).unwrap_or_default(),
+            header: hash(&(
+                session.created_at,
+                &session.metadata,
+                session.last_consolidated,
+            )),
+        }
+    }
+
+    /// Whether `session` only adds messages to this snapshot, so the file
+    /// can be brought up to date by appending.
+    fn extended_by(&self, session: &Session) -> bool {
+        session.messages.len() >= self.messages
+            && (self.messages == 0
+                || hash(&session.messages[self.messages - 1]) == self.last_message)
+    }
+}
+
+struct Persisted {
+    snapshot: Snapshot,
+    /// Appends since the file was last rewritten.
+    appends: usize,
+    /// The file ends with a complete line, so appending is safe. False after
+    /// loading a file whose last write was cut off.
+    clean_tail: bool,
+}
+
+/// Whether the file at `path` is empty or ends with a newline.
+fn ends_with_newline(path: &std::path::Path) -> bool {
+    use std::io::{Read, Seek, SeekFrom};
+    let Ok(mut file) = std::fs::File::open(path) else {
+        return true;
+    };
+    if file.seek(SeekFrom::End(-1)).is_err() {
+        return true;
+    }
+    let mut last = [0u8; 1];
+    file.read_exact(&mut last).is_ok() && last[0] == b'\n'
+}
+
+/// The current metadata line of a session file. Appended saves end with a
+/// metadata line; compacted files start with one.
+pub fn read_metadata(path: &std::path::Path) -> Option<serde_json::Value> {
+    use std::io::{Read, Seek, SeekFrom};
+    let is_metadata =
+        |v: &serde_json::Value| v.get("_type").and_then(|t| t.as_str()) == Some("metadata");
+    let mut file = std::fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(64 * 1024)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    let last = String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .and_then(|l| serde_json::from_str::<serde_json::Value>(l.trim()).ok())
+        .filter(is_metadata);
+    if last.is_some() {
+        return last;
+    }
+    file.seek(SeekFrom::Start(0)).ok()?;
+    let mut first = String::new();
+    std::io::BufReader::new(file).read_line(&mut first).ok()?;
+    serde_json::from_str(first.trim()).ok().filter(is_metadata)
+}
+
 /// Manages multiple sessions with JSONL persistence.
 /// Compatible with Python nanobot's session format (backward compat).
+///
+/// Loaded sessions are cached. With a cache limit
+/// ([`with_cache_max`](Self::with_cache_max)), the least recently used
+/// session is evicted once the limit is passed, after being saved if it has
+/// changes that weren't.
+///
+/// Saves append the new messages and a fresh metadata line instead of
+/// rewriting the file; the loader takes the last metadata line. Every
+/// `compact_every` appends, or when earlier messages changed, the file is
+/// rewritten compactly through a temporary file and a rename.
 pub struct SessionManager {
     sessions_dir: PathBuf,
     pub sessions: HashMap<String, Session>,
+    cache_max: usize,
+    compact_every: usize,
+    fsync: FsyncPolicy,
+    /// Access tick per cached key, for picking the least recently used.
+    last_used: HashMap<String, u64>,
+    tick: u64,
+    /// File state of each cached session as last loaded or saved.
+    persisted: Mutex<HashMap<String, Persisted>>,
 }
 
 impl SessionManager {
@@ -123,17 +289,108 @@ impl SessionManager {
                 sessions_dir.display()
             );
         }
+        let defaults = SessionsConfig::default();
         Self {
             sessions_dir,
             sessions: HashMap::new(),
+            cache_max: 0,
+            compact_every: defaults.compact_every,
+            fsync: defaults.fsync,
+            last_used: HashMap::new(),
+            tick: 0,
+            persisted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keep at most `cache_max` sessions in memory (0 for no limit).
+    pub fn with_cache_max(mut self, cache_max: usize) -> Self {
+        self.cache_max = cache_max;
+        self
+    }
+
+    /// Apply `config.sessions`: cache size, compaction, and fsync policy.
+    pub fn with_config(self, config: &SessionsConfig) -> Self {
+        Self {
+            compact_every: config.compact_every,
+            fsync: config.fsync,
+            ..self.with_cache_max(config.cache_max)
+        }
+    }
+
+    fn lock_persisted(&self) -> std::sync::MutexGuard<'_, HashMap<String, Persisted>> {
+        match self.persisted.lock() {
+            Ok(p) => p,
+            Err(e) => e.into_inner(),
+        }
+    }
+
+    /// Add a freshly loaded or created session to the cache, making room
+    /// for it first.
+    fn cache(&mut self, key: &str, session: Session) {
+        let clean_tail = ends_with_newline(&self.session_path(key));
+        self.lock_persisted().insert(
+            key.into(),
+            Persisted {
+                snapshot: Snapshot::of(&session),
+                appends: 0,
+                clean_tail,
+            },
+        );
+        self.sessions.insert(key.into(), session);
+        self.evict_over_limit(key);
+    }
+
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        self.last_used.insert(key.into(), self.tick);
+    }
+
+    /// Evict least recently used sessions, other than `keep`, until the
+    /// cache is within its limit.
+    fn evict_over_limit(&mut self, keep: &str) {
+        if self.cache_max == 0 {
+            return;
+        }
+        while self.sessions.len() > self.cache_max {
+            let oldest = self
+                .last_used
+                .iter()
+                .filter(|(k, _)| k.as_str() != keep)
+                .min_by_key(|(_, tick)| **tick)
+                .map(|(k, _)| k.clone());
+            let Some(oldest) = oldest else { break };
+            if self.is_dirty(&oldest) {
+                if let Err(e) = self.save(&oldest) {
+                    // Keep it rather than lose the changes; try again next time
+                    tracing::warn!("Failed to save session '{oldest}' before eviction: {e}");
+                    break;
+                }
+            }
+            self.sessions.remove(&oldest);
+            self.forget(&oldest);
+            tracing::debug!("Evicted session '{oldest}' from cache");
         }
     }
 
+    /// Whether a cached session differs from what was last loaded or saved.
+    fn is_dirty(&self, key: &str) -> bool {
+        let Some(session) = self.sessions.get(key) else {
+            return false;
+        };
+        self.lock_persisted()
+            .get(key)
+            .is_none_or(|p| p.snapshot != Snapshot::of(session))
+    }
+
+    fn forget(&mut self, key: &str) {
+        self.last_used.remove(key);
+        self.lock_persisted().remove(key);
+    }
+
     /// Get the file path for a session key.
     fn session_path(&self, key: &str) -> PathBuf {
-        // Replace : with _ for filesystem safety (matches Python's safe_filename)
-        let safe_key = key.replace(':', "_");
-        self.sessions_dir.join(format!("{safe_key}.jsonl"))
+        self.sessions_dir
+            .join(format!("{}.jsonl", safe_file_name(key)))
     }
 
     /// Get or create a session, loading from disk if it exists.
@@ -147,8 +404,9 @@ impl SessionManager {
                     Session::new(key.into())
                 }
             };
-            self.sessions.insert(key.into(), session);
+            self.cache(key, session);
         }
+        self.touch(key);
         // Safety: we just inserted the key above if it was missing
         self.sessions
             .get_mut(key)
@@ -159,15 +417,30 @@ impl SessionManager {
     pub fn get_or_create_checked(&mut self, key: &str) -> Result<&mut Session> {
         if !self.sessions.contains_key(key) {
             let session = self.load(key)?.unwrap_or_else(|| Session::new(key.into()));
-            self.sessions.insert(key.into(), session);
+            self.cache(key, session);
         }
+        self.touch(key);
         self.sessions
             .get_mut(key)
             .context("session inserted but missing from cache")
     }
 
-    /// Load a session from its JSONL file.
-    fn load(&self, key: &str) -> Result<Option<Session>> {
+    /// Get a session from the cache or disk, without creating it. `None` if
+    /// it doesn't exist.
+    pub fn get_existing(&mut self, key: &str) -> Result<Option<&mut Session>> {
+        if !self.sessions.contains_key(key) {
+            let Some(session) = self.load(key)? else {
+                return Ok(None);
+            };
+            self.cache(key, session);
+        }
+        self.touch(key);
+        Ok(self.sessions.get_mut(key))
+    }
+
+    /// Load a session from its JSONL file, bypassing the cache. `None` if it
+    /// doesn't exist.
+    pub fn load(&self, key: &str) -> Result<Option<Session>> {
         let path = self.session_path(key);
         if !path.exists() {
             return Ok(None);
@@ -236,7 +509,8 @@ impl SessionManager {
         }))
     }
 
-    /// Save a session to its JSONL file.
+    /// Save a session to its JSONL file, by appending when only new
+    /// messages were added since the last save.
     pub fn save(&self, key: &str) -> Result<()> {
         let session = self
             .sessions
@@ -248,34 +522,68 @@ impl SessionManager {
             std::fs::create_dir_all(parent)?;
         }
 
-        let mut file = std::fs::File::create(&path)?;
-
-        // Write metadata line first
-        let meta = SessionMetadata {
-            type_field: "metadata".into(),
-            created_at: session.created_at.to_rfc3339(),
-            updated_at: session.updated_at.to_rfc3339(),
-            metadata: session.metadata.clone(),
-            last_consolidated: session.last_consolidated,
+        let mut persisted = self.lock_persisted();
+        let append_from = persisted
+            .get(key)
+            .filter(|p| {
+                p.clean_tail
+                    && p.appends < self.compact_every
+                    && p.snapshot.extended_by(session)
+                    && path.exists()
+            })
+            .map(|p| (p.snapshot.messages, p.appends));
+
+        let appends = match append_from {
+            Some((from, appends)) => {
+                let mut out = String::new();
+                for msg in &session.messages[from..] {
+                    out.push_str(&serde_json::to_string(msg)?);
+                    out.push('\n');
+                }
+                out.push_str(&metadata_line(session)?);
+                out.push('\n');
+                let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+                file.write_all(out.as_bytes())?;
+                if self.fsync == FsyncPolicy::Always {
+                    file.sync_data()?;
+                }
+                appends + 1
+            }
+            None => {
+                // Write the whole file aside and swap it in, so a crash
+                // leaves either the old file or the new one
+                let tmp = path.with_extension("jsonl.tmp");
+                let mut file = std::fs::File::create(&tmp)?;
+                file.write_all(render(session)?.as_bytes())?;
+                if self.fsync != FsyncPolicy::Never {
+                    file.sync_all()?;
+                }
+                drop(file);
+                std::fs::rename(&tmp, &path)?;
+                0
+            }
         };
-        writeln!(file, "{}", serde_json::to_string(&meta)?)?;
-
-        // Write each message
-        for msg in &session.messages {
-            writeln!(file, "{}", serde_json::to_string(msg)?)?;
-        }
-
+        persisted.insert(
+            key.into(),
+            Persisted {
+                snapshot: Snapshot::of(session),
+                appends,
+                clean_tail: true,
+            },
+        );
         Ok(())
     }
 
     /// Remove a session from the in-memory cache.
     pub fn invalidate(&mut self, key: &str) {
         self.sessions.remove(key);
+        self.forget(key);
     }
 
     /// Delete a session from cache and disk. Returns true if the file existed.
     pub fn delete(&mut self, key: &str) -> Result<bool> {
         self.sessions.remove(key);
+        self.forget(key);
         let path = self.session_path(key);
         if path.exists() {
             std::fs::remove_file(&path)
@@ -301,22 +609,7 @@ impl SessionManager {
                 continue;
             }
 
-            let file = match std::fs::File::open(&path) {
-                Ok(f) => f,
-                Err(_) => continue,
-            };
-            let mut reader = std::io::BufReader::new(file);
-            let mut first_line = String::new();
-            if reader.read_line(&mut first_line).is_err() || first_line.is_empty() {
-                continue;
-            }
-
-            let data: serde_json::Value = match serde_json::from_str(first_line.trim()) {
-                Ok(d) => d,
-                Err(_) => continue,
-            };
-
-            if data.get("_type").and_then(|v| v.as_str()) == Some("metadata") {
+            if let Some(data) = read_metadata(&path) {
                 let key = path
                     .file_stem()
                     .and_then(|s| s.to_str())
@@ -515,6 +808,86 @@ mod tests {
 
         let path = mgr.session_path("telegram:12345");
         assert!(path.to_string_lossy().contains("telegram_12345.jsonl"));
+
+        assert_eq!(safe_file_name("slack:C1/thread 2"), "slack_C1_thread_2");
+        assert_eq!(safe_file_name(r#"web:a<b>"c"|d?*\e"#), "web_a_b__c__d___e");
+        assert_eq!(safe_file_name("cli."), "cli_");
+        assert_eq!(safe_file_name("nul"), "_nul");
+        assert_eq!(safe_file_name("com1.tar"), "_com1.tar");
+        assert_eq!(safe_file_name("console"), "console");
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mgr = SessionManager::new(dir.path().to_path_buf()).with_cache_max(2);
+
+        mgr.get_or_create("web:a").add_message("user", "unsaved");
+        mgr.get_or_create("web:b");
+        mgr.get_or_create("web:a");
+        // b is now the least recently used, and has nothing to write back
+        mgr.get_or_create("web:c");
+        assert_eq!(mgr.sessions.len(), 2);
+        assert!(!mgr.sessions.contains_key("web:b"));
+        assert!(!mgr.session_path("web:b").exists());
+
+        // a's unsaved message is written back when it goes
+        mgr.get_or_create("web:d");
+        assert!(!mgr.sessions.contains_key("web:a"));
+        let a = mgr.get_or_create("web:a");
+        assert_eq!(a.messages.len(), 1);
+        assert_eq!(a.messages[0].content, "unsaved");
+        assert_eq!(mgr.sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_save_appends_and_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SessionsConfig {
+            compact_every: 3,
+            ..Default::default()
+        };
+        let mut mgr = SessionManager::new(dir.path().to_path_buf()).with_config(&config);
+        let path = mgr.session_path("k");
+        let line_count = || std::fs::read_to_string(&path).unwrap().lines().count();
+
+        mgr.get_or_create("k").add_message("user", "one");
+        mgr.save("k").unwrap();
+        assert_eq!(line_count(), 2);
+
+        // Each save appends its new messages and a metadata line
+        let session = mgr.get_or_create("k");
+        session.add_message("assistant", "two");
+        session.add_message("user", "three");
+        session.metadata.insert("title".into(), "Counting".into());
+        mgr.save("k").unwrap();
+        assert_eq!(line_count(), 5);
+        assert_eq!(
+            read_metadata(&path).unwrap()["metadata"]["title"],
+            "Counting"
+        );
+
+        let loaded = mgr.load("k").unwrap().unwrap();
+        let contents: Vec<_> = loaded.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["one", "two", "three"]);
+        assert_eq!(loaded.metadata["title"], "Counting");
+
+        // Changing an already written message forces a rewrite
+        mgr.get_or_create("k").messages[2].content = "3".into();
+        mgr.save("k").unwrap();
+        assert_eq!(line_count(), 4);
+        assert_eq!(mgr.load("k").unwrap().unwrap().messages[2].content, "3");
+
+        // As does reaching compact_every appends
+        for i in 0..3 {
+            mgr.get_or_create("k").add_message("user", &i.to_string());
+            mgr.save("k").unwrap();
+        }
+        assert_eq!(line_count(), 4 + 3 * 2);
+        mgr.get_or_create("k").add_message("user", "last");
+        mgr.save("k").unwrap();
+        assert_eq!(line_count(), 8);
+        assert!(!path.with_extension("jsonl.tmp").exists());
     }
 
     #[test]
@@ -0,0 +1,310 @@
+//! Scheduled persona runs ("standups").
+//!
+//! Each entry in the `standups` config becomes a cron job of kind
+//! `standup`. When it fires, cron sends an inbound message on the
+//! [`STANDUP_CHANNEL`] whose chat ID is the standup's name; the gateway hands
+//! it to [`run`], which fills the prompt template with the task board and
+//! recent HISTORY.md entries, answers it as the configured persona in its own
+//! `standup:<name>` session, and addresses the reply to the configured chat.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Local, TimeZone};
+use patina_config::StandupConfig;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::agent::AgentLoop;
+use crate::bus::OutboundMessage;
+use crate::cron::{CronSchedule, CronService, ScheduleKind};
+use crate::persona::PersonaStore;
+use crate::task::{Task, TaskManager, TaskPriority, TaskStatus};
+
+/// Inbound channel (and cron payload kind) for standup runs.
+pub const STANDUP_CHANNEL: &str = "standup";
+
+/// Make the cron jobs match the `standups` config: add missing ones, replace
+/// those whose schedule changed, and remove those no longer configured.
+pub fn sync_jobs(cron: &mut CronService, standups: &HashMap<String, StandupConfig>) {
+    let wanted = |name: &str| standups.get(name).filter(|s| s.enabled);
+    let stale: Vec<String> = cron
+        .list_jobs(true)
+        .into_iter()
+        .filter(|j| j.payload.kind == STANDUP_CHANNEL)
+        .filter(|j| {
+            let name = j.payload.to.as_deref().unwrap_or_default();
+            wanted(name).is_none_or(|s| {
+                j.schedule.expr.as_deref() != Some(s.schedule.as_str()) || j.schedule.tz != s.tz
+            })
+        })
+        .map(|j| j.id.clone())
+        .collect();
+    for id in stale {
+        cron.remove_job(&id);
+    }
+
+    let existing: Vec<String> = cron
+        .list_jobs(true)
+        .into_iter()
+        .filter(|j| j.payload.kind == STANDUP_CHANNEL)
+        .filter_map(|j| j.payload.to.clone())
+        .collect();
+    for (name, standup) in standups {
+        if !standup.enabled || existing.contains(name) {
+            continue;
+        }
+        if standup.schedule.is_empty() || standup.channel.is_empty() || standup.to.is_empty() {
+            warn!("Standup '{name}' needs a schedule, channel, and to; skipping");
+            continue;
+        }
+        let schedule = CronSchedule {
+            kind: ScheduleKind::Cron,
+            at_ms: None,
+            every_ms: None,
+            expr: Some(standup.schedule.clone()),
+            tz: standup.tz.clone(),
+        };
+        match cron.add_job(
+            &format!("standup: {name}"),
+            schedule,
+            name,
+            STANDUP_CHANNEL,
+            true,
+            Some(STANDUP_CHANNEL.to_string()),
+            Some(name.clone()),
+            false,
+        ) {
+            Ok(_) => info!("Scheduled standup '{name}' ({})", standup.schedule),
+            Err(e) => warn!("Failed to schedule standup '{name}': {e}"),
+        }
+    }
+}
+
+/// Run standup `name` and return the reply, addressed to its chat.
+///
+/// A missing persona or a failed turn is reported in the reply, so the
+/// chat sees that the standup didn't happen rather than nothing at all.
+pub async fn run(
+    agent_loop: &mut AgentLoop,
+    name: &str,
+    standup: &StandupConfig,
+    personas: &Mutex<PersonaStore>,
+    tasks: &Mutex<TaskManager>,
+) -> OutboundMessage {
+    let reply = |content: String| OutboundMessage {
+        channel: standup.channel.clone(),
+        chat_id: standup.to.clone(),
+        content,
+        reply_to: None,
+        metadata: HashMap::new(),
+    };
+
+    let (persona_name, preamble, tier) = if standup.persona.is_empty() {
+        (String::new(), None, None)
+    } else {
+        match personas.lock().await.get(&standup.persona) {
+            Some(p) => (
+                p.name.clone(),
+                Some(p.preamble.clone()).filter(|s| !s.is_empty()),
+                Some(p.model_tier.clone()).filter(|s| !s.is_empty()),
+            ),
+            None => {
+                return reply(format!(
+                    "Standup '{name}' skipped: persona '{}' doesn't exist.",
+                    standup.persona
+                ))
+            }
+        }
+    };
+
+    let now = Local::now();
+    let since = now - Duration::days(standup.history_days.into());
+    let board = task_board(tasks.lock().await.all_tasks(), since.timestamp_millis());
+    let history = match agent_loop
+        .context
+        .memory()
+        .history_since(since.date_naive())
+    {
+        Ok(entries) if !entries.is_empty() => entries.join("\n\n"),
+        Ok(_) => "(nothing recorded)".to_string(),
+        Err(e) => {
+            warn!("Standup '{name}' couldn't read history: {e}");
+            "(history unavailable)".to_string()
+        }
+    };
+    let prompt = render_prompt(&standup.prompt, &persona_name, &board, &history, now, since);
+
+    let session_key = format!("{STANDUP_CHANNEL}:{name}");
+    match agent_loop
+        .process_message_with_persona(
+            &session_key,
+            &prompt,
+            None,
+            preamble.as_deref(),
+            tier.as_deref(),
+        )
+        .await
+    {
+        Ok((content, _)) => reply(content),
+        Err(e) => {
+            warn!("Standup '{name}' failed: {e}");
+            reply(format!("Standup '{name}' failed: {e}"))
+        }
+    }
+}
+
+/// Fill in the prompt template's placeholders.
+fn render_prompt<Tz: TimeZone>(
+    template: &str,
+    persona: &str,
+    tasks: &str,
+    history: &str,
+    now: DateTime<Tz>,
+    since: DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    template
+        .replace("{{date}}", &now.format("%A, %B %-d, %Y").to_string())
+        .replace("{{since}}", &since.format("%Y-%m-%d").to_string())
+        .replace("{{persona}}", persona)
+        .replace("{{tasks}}", tasks)
+        .replace("{{history}}", history)
+}
+
+/// The task board as markdown lists: open tasks by column, then tasks
+/// finished since `since_ms`.
+fn task_board(tasks: &[Task], since_ms: i64) -> String {
+    let columns = [
+        (TaskStatus::InProgress, "In progress"),
+        (TaskStatus::Todo, "To do"),
+        (TaskStatus::Backlog, "Backlog"),
+        (TaskStatus::Done, "Done"),
+    ];
+    let mut out = Vec::new();
+    for (status, title) in columns {
+        let lines: Vec<String> = tasks
+            .iter()
+            .filter(|t| t.status == status)
+            .filter(|t| {
+                status != TaskStatus::Done
+                    || t.completed_at_ms.unwrap_or(t.updated_at_ms) >= since_ms
+            })
+            .map(|t| {
+                let mut line = format!("- {}", t.title);
+                if let Some(assignee) = &t.assignee {
+                    line.push_str(&format!(" (@{assignee})"));
+                }
+                if matches!(t.priority, TaskPriority::High | TaskPriority::Urgent) {
+                    line.push_str(&format!(" [{:?}]", t.priority).to_lowercase());
+                }
+                line
+            })
+            .collect();
+        if !lines.is_empty() {
+            out.push(format!("{title}:\n{}", lines.join("\n")));
+        }
+    }
+    if out.is_empty() {
+        "(no tasks)".to_string()
+    } else {
+        out.join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn task(title: &str, status: TaskStatus, updated_at_ms: i64) -> Task {
+        Task {
+            id: title.into(),
+            title: title.into(),
+            description: String::new(),
+            status,
+            priority: TaskPriority::Medium,
+            assignee: None,
+            tags: Vec::new(),
+            created_by: "user".into(),
+            created_at_ms: 0,
+            updated_at_ms,
+            completed_at_ms: None,
+            comments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn renders_board_and_prompt() {
+        let mut urgent = task("Fix login", TaskStatus::InProgress, 50);
+        urgent.priority = TaskPriority::Urgent;
+        urgent.assignee = Some("coder".into());
+        let tasks = [
+            urgent,
+            task("Write docs", TaskStatus::Todo, 50),
+            task("Old release", TaskStatus::Done, 10),
+            task("New release", TaskStatus::Done, 200),
+        ];
+        let board = task_board(&tasks, 100);
+        assert_eq!(
+            board,
+            "In progress:\n- Fix login (@coder) [urgent]\n\n\
+             To do:\n- Write docs\n\n\
+             Done:\n- New release"
+        );
+        assert_eq!(task_board(&[], 0), "(no tasks)");
+
+        let now = Utc.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap();
+        let prompt = render_prompt(
+            "{{persona}} on {{date}} since {{since}}:\n{{tasks}}\n{{history}}",
+            "Analyst",
+            "(no tasks)",
+            "[2026-03-05] Shipped.",
+            now,
+            now - Duration::days(7),
+        );
+        assert_eq!(
+            prompt,
+            "Analyst on Monday, March 9, 2026 since 2026-03-02:\n(no tasks)\n[2026-03-05] Shipped."
+        );
+    }
+
+    #[tokio::test]
+    async fn sync_jobs_follows_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let mut cron = CronService::new(dir.path().join("jobs.json"), tx);
+        let mut standups = HashMap::new();
+        standups.insert(
+            "monday".to_string(),
+            StandupConfig {
+                persona: "analyst".into(),
+                schedule: "0 9 * * 1".into(),
+                channel: "telegram".into(),
+                to: "42".into(),
+                ..Default::default()
+            },
+        );
+        standups.insert("incomplete".to_string(), StandupConfig::default());
+
+        sync_jobs(&mut cron, &standups);
+        sync_jobs(&mut cron, &standups);
+        let jobs = cron.list_jobs(true);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].payload.kind, STANDUP_CHANNEL);
+        assert_eq!(jobs[0].payload.to.as_deref(), Some("monday"));
+        let id = jobs[0].id.clone();
+
+        standups.get_mut("monday").unwrap().schedule = "0 10 * * 1".into();
+        sync_jobs(&mut cron, &standups);
+        let jobs = cron.list_jobs(true);
+        assert_eq!(jobs.len(), 1);
+        assert_ne!(jobs[0].id, id);
+        assert_eq!(jobs[0].schedule.expr.as_deref(), Some("0 10 * * 1"));
+
+        standups.get_mut("monday").unwrap().enabled = false;
+        sync_jobs(&mut cron, &standups);
+        assert!(cron.list_jobs(true).is_empty());
+    }
+}
@@ -5,6 +5,8 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
+use crate::portable::{check_unique, merge, Imported};
+
 /// Kanban column status.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -62,6 +64,14 @@ fn default_priority() -> TaskPriority {
     TaskPriority::Medium
 }
 
+fn default_status() -> TaskStatus {
+    TaskStatus::Todo
+}
+
+fn default_version() -> u32 {
+    1
+}
+
 /// A comment or activity entry on a task.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -75,10 +85,13 @@ pub struct TaskComment {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Task {
+    /// Empty only in a hand-written import, which gives it an ID.
+    #[serde(default)]
     pub id: String,
     pub title: String,
     #[serde(default)]
     pub description: String,
+    #[serde(default = "default_status")]
     pub status: TaskStatus,
     #[serde(default = "default_priority")]
     pub priority: TaskPriority,
@@ -86,8 +99,11 @@ pub struct Task {
     pub assignee: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    #[serde(default)]
     pub created_by: String,
+    #[serde(default)]
     pub created_at_ms: i64,
+    #[serde(default)]
     pub updated_at_ms: i64,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub completed_at_ms: Option<i64>,
@@ -99,6 +115,7 @@ pub struct Task {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskStore {
+    #[serde(default = "default_version")]
     pub version: u32,
     pub tasks: Vec<Task>,
 }
@@ -112,6 +129,54 @@ impl Default for TaskStore {
     }
 }
 
+/// A task taken straight from chat text: `/task ...` or a message starting
+/// with the capture prefix (`todo: ...`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub title: String,
+    pub details: String,
+}
+
+/// Longest title taken from the body of a forwarded message.
+const CAPTURE_TITLE_CHARS: usize = 80;
+
+impl Capture {
+    /// The first line is the title and the rest the details. With an empty
+    /// first line, the title comes from the start of the details instead.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+        let (first, rest) = (first.trim(), rest.trim());
+        let title = if first.is_empty() {
+            let line = rest.lines().find(|l| !l.trim().is_empty())?.trim();
+            let mut title: String = line.chars().take(CAPTURE_TITLE_CHARS).collect();
+            if title.len() < line.len() {
+                title.push_str("...");
+            }
+            title
+        } else {
+            first.to_string()
+        };
+        Some(Self {
+            title,
+            details: rest.to_string(),
+        })
+    }
+
+    /// Parse a message that starts with `prefix` (case-insensitive).
+    pub fn from_message(content: &str, prefix: &str) -> Option<Self> {
+        if prefix.is_empty() {
+            return None;
+        }
+        let content = content.trim_start();
+        let head = content.get(..prefix.len())?;
+        if !head.eq_ignore_ascii_case(prefix) {
+            return None;
+        }
+        Self::from_text(&content[prefix.len()..])
+    }
+}
+
 /// Manages task persistence and CRUD operations.
 pub struct TaskManager {
     path: PathBuf,
@@ -191,6 +256,25 @@ impl TaskManager {
         Ok(task)
     }
 
+    /// Add a captured task, tagged "captured" and linking back to the session
+    /// it came from.
+    pub fn capture(&mut self, capture: &Capture, session_key: &str) -> Result<Task> {
+        let source = format!("Captured from `{session_key}`");
+        let description = if capture.details.is_empty() {
+            source
+        } else {
+            format!("{}\n\n{source}", capture.details)
+        };
+        self.add(
+            &capture.title,
+            &description,
+            TaskPriority::Medium,
+            None,
+            vec!["captured".to_string()],
+            session_key,
+        )
+    }
+
     pub fn get(&self, id: &str) -> Option<&Task> {
         self.store.tasks.iter().find(|t| t.id == id)
     }
@@ -310,6 +394,60 @@ impl TaskManager {
         }
     }
 
+    /// The whole board, for `patina tasks export`.
+    pub fn export(&mut self) -> TaskStore {
+        self.refresh_from_disk();
+        self.store.clone()
+    }
+
+    /// Add and update tasks from `store`, and with `replace` remove the ones
+    /// it doesn't list. Tasks written by hand get an ID, `createdBy`, and
+    /// timestamps if they lack them; an update without them keeps the
+    /// existing task's.
+    pub fn import(&mut self, store: TaskStore, replace: bool) -> Result<Imported> {
+        self.refresh_from_disk();
+        let now = Utc::now().timestamp_millis();
+        let mut tasks = store.tasks;
+        for task in &mut tasks {
+            if task.title.trim().is_empty() {
+                anyhow::bail!("Every task needs a title");
+            }
+            if task.id.is_empty() {
+                task.id = uuid::Uuid::new_v4().to_string()[..8].to_string();
+            }
+        }
+        check_unique(tasks.iter().map(|t| t.id.as_str()))?;
+
+        let imported = merge(
+            &mut self.store.tasks,
+            tasks,
+            replace,
+            |t| &t.id,
+            |task, mut new| {
+                if new.created_by.is_empty() {
+                    new.created_by = std::mem::take(&mut task.created_by);
+                }
+                if new.created_at_ms == 0 {
+                    new.created_at_ms = task.created_at_ms;
+                }
+                *task = new;
+            },
+        );
+        for task in &mut self.store.tasks {
+            if task.created_by.is_empty() {
+                task.created_by = "import".to_string();
+            }
+            if task.created_at_ms == 0 {
+                task.created_at_ms = now;
+            }
+            if task.updated_at_ms == 0 {
+                task.updated_at_ms = now;
+            }
+        }
+        self.save()?;
+        Ok(imported)
+    }
+
     /// Get all tasks (for API serialization).
     pub fn all_tasks(&mut self) -> &[Task] {
         self.refresh_from_disk();
@@ -407,6 +545,71 @@ mod tests {
         assert_eq!(t.priority, TaskPriority::Urgent);
     }
 
+    #[test]
+    fn test_import_merges_by_id() {
+        let (mut mgr, _f) = test_manager();
+        let kept = mgr
+            .add(
+                "Renew passport",
+                "",
+                TaskPriority::High,
+                None,
+                vec![],
+                "test:1",
+            )
+            .unwrap();
+        let dropped = mgr
+            .add("Old idea", "", TaskPriority::Low, None, vec![], "test:1")
+            .unwrap();
+        mgr.add_comment(&kept.id, "me", "Photos done").unwrap();
+
+        let yaml = crate::portable::to_yaml("tasks", &mgr.export()).unwrap();
+        assert!(yaml.contains("title: Renew passport"));
+        let (mut copy, _g) = test_manager();
+        let store: TaskStore = crate::portable::from_yaml(&yaml).unwrap();
+        assert_eq!(copy.import(store, false).unwrap().added, 2);
+        assert_eq!(copy.get(&kept.id).unwrap().comments.len(), 1);
+
+        let edited = format!(
+            "tasks:
+- id: {}
+  title: Renew passport
+  status: in_progress
+  priority: high
+- title: Book dentist
+",
+            kept.id
+        );
+        let imported = mgr
+            .import(crate::portable::from_yaml(&edited).unwrap(), true)
+            .unwrap();
+        assert_eq!(imported.to_string(), "1 added, 1 updated, 1 removed");
+        assert!(mgr.get(&dropped.id).is_none());
+        let task = mgr.get(&kept.id).unwrap();
+        assert_eq!(task.status, TaskStatus::InProgress);
+        assert_eq!(
+            (task.created_by.as_str(), task.created_at_ms),
+            ("test:1", kept.created_at_ms)
+        );
+        let new = mgr
+            .list(None, None)
+            .into_iter()
+            .find(|t| t.title == "Book dentist");
+        let new = new.unwrap();
+        assert_eq!(
+            (new.status.clone(), new.created_by.as_str()),
+            (TaskStatus::Todo, "import")
+        );
+
+        let duplicate = format!(
+            "tasks:\n- id: {0}\n  title: a\n- id: {0}\n  title: b\n",
+            kept.id
+        );
+        assert!(mgr
+            .import(crate::portable::from_yaml(&duplicate).unwrap(), false)
+            .is_err());
+    }
+
     #[test]
     fn test_move_task() {
         let (mut mgr, _f) = test_manager();
@@ -513,4 +716,37 @@ mod tests {
         assert_eq!(TaskStatus::from_str("todo"), Some(TaskStatus::Todo));
         assert_eq!(TaskStatus::from_str("invalid"), None);
     }
+
+    #[test]
+    fn test_capture_from_message() {
+        let capture = Capture::from_message("TODO: renew passport\nExpires in May", "todo:");
+        assert_eq!(
+            capture,
+            Some(Capture {
+                title: "renew passport".into(),
+                details: "Expires in May".into(),
+            })
+        );
+        // A forwarded message with the prefix on its own line
+        let capture = Capture::from_message("todo:\n\nCan you send the Q3 numbers?", "todo:");
+        assert_eq!(capture.unwrap().title, "Can you send the Q3 numbers?");
+
+        assert!(Capture::from_message("what's on my todo: list?", "todo:").is_none());
+        assert!(Capture::from_message("todo:   ", "todo:").is_none());
+        assert!(Capture::from_message("todo: x", "").is_none());
+    }
+
+    #[test]
+    fn test_capture_links_session() {
+        let (mut mgr, _f) = test_manager();
+        let capture = Capture::from_text("Call the bank\nAbout the fee").unwrap();
+        let task = mgr.capture(&capture, "telegram:42").unwrap();
+        assert_eq!(task.title, "Call the bank");
+        assert_eq!(task.created_by, "telegram:42");
+        assert_eq!(task.tags, vec!["captured"]);
+        assert_eq!(
+            task.description,
+            "About the fee\n\nCaptured from `telegram:42`"
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::RwLock;
+
+use super::Tool;
+use crate::questions::Questions;
+
+/// Longest wait a run may ask for, in minutes.
+const MAX_TIMEOUT_MINS: u64 = 24 * 60;
+
+/// Tool that asks the owner a question and waits for the answer.
+pub struct AskUserTool {
+    questions: Arc<Questions>,
+    /// Who is asking, shown with the question: a subagent, or the session
+    /// the main agent is working in.
+    from: RwLock<String>,
+}
+
+impl AskUserTool {
+    pub fn new(questions: Arc<Questions>, from: &str) -> Self {
+        Self {
+            questions,
+            from: RwLock::new(from.to_string()),
+        }
+    }
+
+    /// Update who is asking to the current channel/chat_id.
+    pub async fn set_context(&self, channel: &str, chat_id: &str) {
+        *self.from.write().await = format!("{channel}:{chat_id}");
+    }
+}
+
+#[async_trait]
+impl Tool for AskUserTool {
+    fn name(&self) -> &str {
+        "ask_user"
+    }
+
+    fn description(&self) -> &str {
+        "Ask the user a question and wait for their answer. Only for background work (tasks, \
+         scheduled jobs) that reaches a decision it shouldn't make alone; in a conversation, \
+         ask in your reply instead. Give a default to fall back on if they don't answer in time."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "question": {
+                    "type": "string",
+                    "description": "The question, with the context needed to answer it"
+                },
+                "default": {
+                    "type": "string",
+                    "description": "What you'll do if there's no answer in time"
+                },
+                "timeout_mins": {
+                    "type": "integer",
+                    "description": "Minutes to wait. Defaults to the configured wait.",
+                    "minimum": 1,
+                    "maximum": MAX_TIMEOUT_MINS
+                }
+            },
+            "required": ["question"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let question = params
+            .get("question")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+        if question.is_empty() {
+            return Ok("Error: question is required".into());
+        }
+        let default = params
+            .get("default")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|d| !d.is_empty());
+        let timeout = params
+            .get("timeout_mins")
+            .and_then(|v| v.as_u64())
+            .map(|m| Duration::from_secs(m.clamp(1, MAX_TIMEOUT_MINS) * 60))
+            .unwrap_or(self.questions.timeout());
+
+        let from = self.from.read().await.clone();
+        if from == self.questions.owner_session() {
+            return Ok("You're talking with the user right now. Ask them in your reply.".into());
+        }
+
+        Ok(
+            match self.questions.ask(&from, question, default, timeout).await {
+                Some(answer) => format!("The user answered: {answer}"),
+                None => match default {
+                    Some(default) => {
+                        format!("No answer in time. Go ahead with your default: {default}")
+                    }
+                    None => "No answer in time. Make the safest choice, or stop and report \
+                             what needs deciding."
+                        .to_string(),
+                },
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use patina_config::AskUserConfig;
+
+    #[tokio::test]
+    async fn test_checks_question_and_owner_chat() {
+        let (tx, _rx) = tokio::sync::broadcast::channel(4);
+        let config = AskUserConfig {
+            owner_chat: Some("web:owner".into()),
+            ..Default::default()
+        };
+        let questions = Arc::new(Questions::new(&config, tx).unwrap());
+        let tool = AskUserTool::new(questions, "subagent ab12");
+
+        let result = tool.execute(serde_json::json!({})).await.unwrap();
+        assert_eq!(result, "Error: question is required");
+
+        tool.set_context("web", "owner").await;
+        let result = tool
+            .execute(serde_json::json!({"question": "Ship it?"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("You're talking with the user"));
+    }
+}
@@ -0,0 +1,830 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use super::Tool;
+
+/// Deterministic calculator: arithmetic, unit conversion, and timezone-aware date math.
+///
+/// The model is bad at mental arithmetic and timezone offsets; this tool gives it
+/// exact answers for reminders and summaries.
+pub struct CalcTool;
+
+impl CalcTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for CalcTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for CalcTool {
+    fn name(&self) -> &str {
+        "calc"
+    }
+
+    fn description(&self) -> &str {
+        "Exact calculations. Never do arithmetic, unit conversion, or timezone math in your head.\n\
+         Actions:\n\
+         - eval: evaluate an expression (+ - * / % ^, parentheses, sqrt, abs, round, floor, ceil, ln, log10, sin, cos, tan, min, max, pi, e)\n\
+         - convert: convert a value between units (length, mass, volume, time, speed, data, temperature)\n\
+         - now: current date/time in a timezone\n\
+         - date_add: add a duration like '2d 3h', '-90m', '1mo', '1y 2w' to a datetime\n\
+         - date_diff: time between two datetimes\n\
+         - tz_convert: convert a datetime from one timezone to another\n\
+         Datetimes are RFC3339 or 'YYYY-MM-DD[ HH:MM[:SS]]' in 'timezone' (IANA name, default UTC)."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["eval", "convert", "now", "date_add", "date_diff", "tz_convert"],
+                    "description": "The calculation to perform"
+                },
+                "expression": {
+                    "type": "string",
+                    "description": "Math expression (for 'eval')"
+                },
+                "value": {
+                    "type": "number",
+                    "description": "Value to convert (for 'convert')"
+                },
+                "from": {
+                    "type": "string",
+                    "description": "Source unit (for 'convert'), e.g. 'mi', 'lb', 'F'"
+                },
+                "to": {
+                    "type": "string",
+                    "description": "Target unit (for 'convert'), e.g. 'km', 'kg', 'C'"
+                },
+                "datetime": {
+                    "type": "string",
+                    "description": "Start datetime (for date_add, date_diff, tz_convert). Defaults to now."
+                },
+                "end": {
+                    "type": "string",
+                    "description": "End datetime (for 'date_diff')"
+                },
+                "duration": {
+                    "type": "string",
+                    "description": "Duration to add (for 'date_add'), e.g. '1w 2d 3h 30m', '-45m', '1mo'"
+                },
+                "timezone": {
+                    "type": "string",
+                    "description": "IANA timezone for input/output, e.g. 'America/New_York' (default: UTC)"
+                },
+                "toTimezone": {
+                    "type": "string",
+                    "description": "Target IANA timezone (for 'tz_convert')"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: action"))?;
+
+        let result = match action {
+            "eval" => handle_eval(&params),
+            "convert" => handle_convert(&params),
+            "now" => handle_now(&params),
+            "date_add" => handle_date_add(&params),
+            "date_diff" => handle_date_diff(&params),
+            "tz_convert" => handle_tz_convert(&params),
+            _ => Err(format!(
+                "Unknown action: {action}. Use 'eval', 'convert', 'now', 'date_add', 'date_diff', or 'tz_convert'."
+            )),
+        };
+        Ok(result.unwrap_or_else(|e| format!("Error: {e}")))
+    }
+}
+
+type CalcResult = std::result::Result<String, String>;
+
+fn str_param<'a>(params: &'a serde_json::Value, key: &str) -> Option<&'a str> {
+    params
+        .get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+}
+
+fn handle_eval(params: &serde_json::Value) -> CalcResult {
+    let expr = str_param(params, "expression").ok_or("expression is required")?;
+    let value = eval_expression(expr)?;
+    Ok(format!("{expr} = {}", format_number(value)))
+}
+
+fn handle_convert(params: &serde_json::Value) -> CalcResult {
+    let value = params
+        .get("value")
+        .and_then(|v| v.as_f64())
+        .ok_or("value is required")?;
+    let from = str_param(params, "from").ok_or("from is required")?;
+    let to = str_param(params, "to").ok_or("to is required")?;
+    let converted = convert_units(value, from, to)?;
+    Ok(format!(
+        "{} {from} = {} {to}",
+        format_number(value),
+        format_number(converted)
+    ))
+}
+
+fn handle_now(params: &serde_json::Value) -> CalcResult {
+    let tz = parse_tz(str_param(params, "timezone"))?;
+    let now = Utc::now().with_timezone(&tz);
+    Ok(format_datetime(&now))
+}
+
+fn handle_date_add(params: &serde_json::Value) -> CalcResult {
+    let tz = parse_tz(str_param(params, "timezone"))?;
+    let start = parse_datetime_or_now(str_param(params, "datetime"), &tz)?;
+    let duration = str_param(params, "duration").ok_or("duration is required")?;
+    let result = add_duration(start, duration)?;
+    Ok(format!(
+        "{} + {duration} = {}",
+        format_datetime(&start),
+        format_datetime(&result)
+    ))
+}
+
+fn handle_date_diff(params: &serde_json::Value) -> CalcResult {
+    let tz = parse_tz(str_param(params, "timezone"))?;
+    let start = parse_datetime_or_now(str_param(params, "datetime"), &tz)?;
+    let end_str = str_param(params, "end").ok_or("end is required")?;
+    let end = parse_datetime(end_str, &tz)?;
+    let diff = end.signed_duration_since(start);
+    Ok(format!(
+        "From {} to {}: {} ({} total minutes, {:.2} days)",
+        format_datetime(&start),
+        format_datetime(&end),
+        format_duration(diff),
+        diff.num_minutes(),
+        diff.num_seconds() as f64 / 86_400.0
+    ))
+}
+
+fn handle_tz_convert(params: &serde_json::Value) -> CalcResult {
+    let tz = parse_tz(str_param(params, "timezone"))?;
+    let to_tz = parse_tz(Some(
+        str_param(params, "toTimezone").ok_or("toTimezone is required")?,
+    ))?;
+    let start = parse_datetime_or_now(str_param(params, "datetime"), &tz)?;
+    let converted = start.with_timezone(&to_tz);
+    Ok(format!(
+        "{} = {}",
+        format_datetime(&start),
+        format_datetime(&converted)
+    ))
+}
+
+// ---------------------------------------------------------------------------
+// Expression evaluation
+// ---------------------------------------------------------------------------
+
+/// Evaluate an arithmetic expression with a small recursive-descent parser.
+fn eval_expression(expr: &str) -> std::result::Result<f64, String> {
+    let mut parser = ExprParser {
+        chars: expr.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+    };
+    let value = parser.parse_sum()?;
+    if parser.pos < parser.chars.len() {
+        return Err(format!(
+            "unexpected '{}' at position {}",
+            parser.chars[parser.pos],
+            parser.pos + 1
+        ));
+    }
+    if !value.is_finite() {
+        return Err("result is not a finite number".into());
+    }
+    Ok(value)
+}
+
+struct ExprParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> std::result::Result<f64, String> {
+        let mut value = self.parse_product()?;
+        loop {
+            if self.eat('+') {
+                value += self.parse_product()?;
+            } else if self.eat('-') {
+                value -= self.parse_product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // product := unary (('*' | '/' | '%') unary)*
+    fn parse_product(&mut self) -> std::result::Result<f64, String> {
+        let mut value = self.parse_unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.parse_unary()?;
+            } else if self.eat('/') {
+                let divisor = self.parse_unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".into());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.parse_unary()?;
+                if divisor == 0.0 {
+                    return Err("modulo by zero".into());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn parse_unary(&mut self) -> std::result::Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.parse_unary()?);
+        }
+        if self.eat('+') {
+            return self.parse_unary();
+        }
+        self.parse_power()
+    }
+
+    // power := atom ('^' unary)?   (right-associative)
+    fn parse_power(&mut self) -> std::result::Result<f64, String> {
+        let base = self.parse_atom()?;
+        if self.eat('^') {
+            let exp = self.parse_unary()?;
+            return Ok(base.powf(exp));
+        }
+        Ok(base)
+    }
+
+    fn parse_atom(&mut self) -> std::result::Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.parse_sum()?;
+                if !self.eat(')') {
+                    return Err("missing closing parenthesis".into());
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => self.parse_identifier(),
+            Some(c) => Err(format!("unexpected '{c}' at position {}", self.pos + 1)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    fn parse_number(&mut self) -> std::result::Result<f64, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '.' || c == '_') {
+            self.pos += 1;
+        }
+        // Scientific notation: 1.5e3, 2E-4
+        if matches!(self.peek(), Some('e' | 'E'))
+            && matches!(self.chars.get(self.pos + 1), Some(c) if c.is_ascii_digit() || *c == '-' || *c == '+')
+        {
+            self.pos += 2;
+            while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+        }
+        let text: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        text.parse::<f64>()
+            .map_err(|_| format!("invalid number '{text}'"))
+    }
+
+    fn parse_identifier(&mut self) -> std::result::Result<f64, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+        match name.as_str() {
+            "pi" => return Ok(std::f64::consts::PI),
+            "e" => return Ok(std::f64::consts::E),
+            _ => {}
+        }
+
+        if !self.eat('(') {
+            return Err(format!("unknown identifier '{name}'"));
+        }
+        let mut args = vec![self.parse_sum()?];
+        while self.eat(',') {
+            args.push(self.parse_sum()?);
+        }
+        if !self.eat(')') {
+            return Err(format!("missing closing parenthesis for {name}()"));
+        }
+
+        let one = |f: fn(f64) -> f64| -> std::result::Result<f64, String> {
+            match args.as_slice() {
+                [x] => Ok(f(*x)),
+                _ => Err(format!("{name}() takes exactly one argument")),
+            }
+        };
+        match name.as_str() {
+            "sqrt" => one(f64::sqrt),
+            "abs" => one(f64::abs),
+            "floor" => one(f64::floor),
+            "ceil" => one(f64::ceil),
+            "ln" => one(f64::ln),
+            "log10" | "log" => one(f64::log10),
+            "sin" => one(f64::sin),
+            "cos" => one(f64::cos),
+            "tan" => one(f64::tan),
+            "round" => match args.as_slice() {
+                [x] => Ok(x.round()),
+                [x, digits] => {
+                    let factor = 10f64.powi(*digits as i32);
+                    Ok((x * factor).round() / factor)
+                }
+                _ => Err("round() takes one or two arguments".into()),
+            },
+            "min" => Ok(args.iter().copied().fold(f64::INFINITY, f64::min)),
+            "max" => Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+            _ => Err(format!("unknown function '{name}'")),
+        }
+    }
+}
+
+/// Format a number without float noise (e.g. 0.30000000000000004 → 0.3).
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let rounded = format!("{value:.10}");
+    rounded
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Unit conversion
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Mass,
+    Volume,
+    Time,
+    Speed,
+    Data,
+    Area,
+}
+
+/// Look up a linear unit: (dimension, factor to the SI/base unit).
+fn linear_unit(unit: &str) -> Option<(Dimension, f64)> {
+    use Dimension::*;
+    let u = unit.trim();
+    // Case-sensitive data units first (MB vs Mb would be ambiguous otherwise)
+    let data = match u {
+        "B" | "byte" | "bytes" => Some(1.0),
+        "KB" | "kB" => Some(1e3),
+        "MB" => Some(1e6),
+        "GB" => Some(1e9),
+        "TB" => Some(1e12),
+        "KiB" => Some(1024.0),
+        "MiB" => Some(1024.0 * 1024.0),
+        "GiB" => Some(1024.0 * 1024.0 * 1024.0),
+        "TiB" => Some(1024.0 * 1024.0 * 1024.0 * 1024.0),
+        "bit" | "bits" => Some(0.125),
+        _ => None,
+    };
+    if let Some(f) = data {
+        return Some((Data, f));
+    }
+
+    let entry = match u.to_lowercase().as_str() {
+        // Length (meters)
+        "m" | "meter" | "meters" | "metre" | "metres" => (Length, 1.0),
+        "km" | "kilometer" | "kilometers" => (Length, 1000.0),
+        "cm" | "centimeter" | "centimeters" => (Length, 0.01),
+        "mm" | "millimeter" | "millimeters" => (Length, 0.001),
+        "mi" | "mile" | "miles" => (Length, 1609.344),
+        "yd" | "yard" | "yards" => (Length, 0.9144),
+        "ft" | "foot" | "feet" => (Length, 0.3048),
+        "in" | "inch" | "inches" => (Length, 0.0254),
+        "nmi" | "nautical mile" | "nautical miles" => (Length, 1852.0),
+        // Mass (kilograms)
+        "kg" | "kilogram" | "kilograms" => (Mass, 1.0),
+        "g" | "gram" | "grams" => (Mass, 0.001),
+        "mg" | "milligram" | "milligrams" => (Mass, 1e-6),
+        "t" | "tonne" | "tonnes" => (Mass, 1000.0),
+        "lb" | "lbs" | "pound" | "pounds" => (Mass, 0.453_592_37),
+        "oz" | "ounce" | "ounces" => (Mass, 0.028_349_523_125),
+        "st" | "stone" | "stones" => (Mass, 6.350_293_18),
+        // Volume (liters)
+        "l" | "liter" | "liters" | "litre" | "litres" => (Volume, 1.0),
+        "ml" | "milliliter" | "milliliters" => (Volume, 0.001),
+        "gal" | "gallon" | "gallons" => (Volume, 3.785_411_784),
+        "qt" | "quart" | "quarts" => (Volume, 0.946_352_946),
+        "pt" | "pint" | "pints" => (Volume, 0.473_176_473),
+        "cup" | "cups" => (Volume, 0.236_588_236_5),
+        "floz" | "fl oz" => (Volume, 0.029_573_529_562_5),
+        "tbsp" => (Volume, 0.014_786_764_781_25),
+        "tsp" => (Volume, 0.004_928_921_593_75),
+        // Time (seconds)
+        "s" | "sec" | "second" | "seconds" => (Time, 1.0),
+        "min" | "minute" | "minutes" => (Time, 60.0),
+        "h" | "hr" | "hour" | "hours" => (Time, 3600.0),
+        "d" | "day" | "days" => (Time, 86_400.0),
+        "wk" | "week" | "weeks" => (Time, 604_800.0),
+        // Speed (meters/second)
+        "m/s" | "mps" => (Speed, 1.0),
+        "km/h" | "kph" | "kmh" => (Speed, 1000.0 / 3600.0),
+        "mph" => (Speed, 1609.344 / 3600.0),
+        "kn" | "knot" | "knots" => (Speed, 1852.0 / 3600.0),
+        // Area (square meters)
+        "m2" | "sqm" => (Area, 1.0),
+        "km2" => (Area, 1e6),
+        "ft2" | "sqft" => (Area, 0.092_903_04),
+        "acre" | "acres" => (Area, 4_046.856_422_4),
+        "ha" | "hectare" | "hectares" => (Area, 10_000.0),
+        _ => return None,
+    };
+    Some(entry)
+}
+
+/// Normalize a temperature unit name to 'c', 'f', or 'k'.
+fn temperature_unit(unit: &str) -> Option<char> {
+    match unit.trim().to_lowercase().trim_start_matches('°') {
+        "c" | "celsius" => Some('c'),
+        "f" | "fahrenheit" => Some('f'),
+        "k" | "kelvin" => Some('k'),
+        _ => None,
+    }
+}
+
+fn convert_units(value: f64, from: &str, to: &str) -> std::result::Result<f64, String> {
+    if let (Some(f), Some(t)) = (temperature_unit(from), temperature_unit(to)) {
+        let celsius = match f {
+            'f' => (value - 32.0) * 5.0 / 9.0,
+            'k' => value - 273.15,
+            _ => value,
+        };
+        return Ok(match t {
+            'f' => celsius * 9.0 / 5.0 + 32.0,
+            'k' => celsius + 273.15,
+            _ => celsius,
+        });
+    }
+
+    let (from_dim, from_factor) =
+        linear_unit(from).ok_or_else(|| format!("unknown unit '{from}'"))?;
+    let (to_dim, to_factor) = linear_unit(to).ok_or_else(|| format!("unknown unit '{to}'"))?;
+    if from_dim != to_dim {
+        return Err(format!(
+            "cannot convert {from} ({from_dim:?}) to {to} ({to_dim:?})"
+        ));
+    }
+    Ok(value * from_factor / to_factor)
+}
+
+// ---------------------------------------------------------------------------
+// Date math
+// ---------------------------------------------------------------------------
+
+fn parse_tz(name: Option<&str>) -> std::result::Result<Tz, String> {
+    match name {
+        None => Ok(Tz::UTC),
+        Some(n) => n
+            .trim()
+            .parse::<Tz>()
+            .map_err(|_| format!("unknown timezone '{n}' (use an IANA name like 'Europe/Berlin')")),
+    }
+}
+
+/// Parse RFC3339, or a naive datetime/date interpreted in `tz`.
+fn parse_datetime(input: &str, tz: &Tz) -> std::result::Result<DateTime<Tz>, String> {
+    let input = input.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(input) {
+        return Ok(dt.with_timezone(tz));
+    }
+    const FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ];
+    let naive = FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(input, f).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            format!("could not parse datetime '{input}' (use RFC3339 or 'YYYY-MM-DD HH:MM')")
+        })?;
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("'{input}' does not exist in {tz} (DST gap)"))
+}
+
+fn parse_datetime_or_now(
+    input: Option<&str>,
+    tz: &Tz,
+) -> std::result::Result<DateTime<Tz>, String> {
+    match input {
+        Some(s) => parse_datetime(s, tz),
+        None => Ok(Utc::now().with_timezone(tz)),
+    }
+}
+
+/// Add a duration like "1y 2mo 1w 2d 3h 30m 10s" (or with a leading '-').
+///
+/// Calendar units (years, months, days, weeks) are applied to the local wall-clock
+/// time so "1d" across a DST change keeps the same hour.
+fn add_duration(start: DateTime<Tz>, duration: &str) -> std::result::Result<DateTime<Tz>, String> {
+    let trimmed = duration.trim();
+    let (negative, body) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    let out_of_range = || format!("duration '{duration}' is out of range");
+    let mut months: i64 = 0;
+    let mut days: i64 = 0;
+    let mut seconds: i64 = 0;
+    let mut matched_any = false;
+
+    let chars: Vec<char> = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let num_start = i;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+        if num_start == i {
+            return Err(format!("invalid duration '{duration}'"));
+        }
+        let n: i64 = chars[num_start..i]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| format!("invalid duration '{duration}'"))?;
+        let unit_start = i;
+        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+            i += 1;
+        }
+        let unit: String = chars[unit_start..i]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+        let (total, per) = match unit.as_str() {
+            "y" | "yr" | "yrs" | "year" | "years" => (&mut months, 12),
+            "mo" | "mon" | "month" | "months" => (&mut months, 1),
+            "w" | "wk" | "week" | "weeks" => (&mut days, 7),
+            "d" | "day" | "days" => (&mut days, 1),
+            "h" | "hr" | "hrs" | "hour" | "hours" => (&mut seconds, 3600),
+            "m" | "min" | "mins" | "minute" | "minutes" => (&mut seconds, 60),
+            "s" | "sec" | "secs" | "second" | "seconds" => (&mut seconds, 1),
+            "" => return Err(format!("missing unit in duration '{duration}'")),
+            other => return Err(format!("unknown duration unit '{other}'")),
+        };
+        *total = n
+            .checked_mul(per)
+            .and_then(|n| total.checked_add(n))
+            .ok_or_else(out_of_range)?;
+        matched_any = true;
+    }
+    if !matched_any {
+        return Err(format!("invalid duration '{duration}'"));
+    }
+
+    let sign = if negative { -1 } else { 1 };
+    let tz = start.timezone();
+    let mut local = start.naive_local();
+    if months != 0 {
+        let m = Months::new(u32::try_from(months).map_err(|_| out_of_range())?);
+        local = if negative {
+            local.checked_sub_months(m)
+        } else {
+            local.checked_add_months(m)
+        }
+        .ok_or("date out of range")?;
+    }
+    local = Duration::try_days(sign * days)
+        .and_then(|d| local.checked_add_signed(d))
+        .ok_or("date out of range")?;
+    let calendar = tz
+        .from_local_datetime(&local)
+        .earliest()
+        .ok_or("resulting local time falls in a DST gap")?;
+    Duration::try_seconds(sign * seconds)
+        .and_then(|d| calendar.checked_add_signed(d))
+        .ok_or_else(|| "date out of range".to_string())
+}
+
+fn format_datetime(dt: &DateTime<Tz>) -> String {
+    format!(
+        "{} ({}, {})",
+        dt.format("%Y-%m-%d %H:%M:%S %Z"),
+        dt.weekday(),
+        dt.to_rfc3339()
+    )
+}
+
+fn format_duration(d: Duration) -> String {
+    let negative = d.num_seconds() < 0;
+    let total = d.num_seconds().unsigned_abs();
+    let days = total / 86_400;
+    let hours = (total % 86_400) / 3600;
+    let minutes = (total % 3600) / 60;
+    let seconds = total % 60;
+    let mut parts = Vec::new();
+    if days > 0 {
+        parts.push(format!("{days}d"));
+    }
+    if hours > 0 {
+        parts.push(format!("{hours}h"));
+    }
+    if minutes > 0 {
+        parts.push(format!("{minutes}m"));
+    }
+    if seconds > 0 || parts.is_empty() {
+        parts.push(format!("{seconds}s"));
+    }
+    let joined = parts.join(" ");
+    if negative {
+        format!("-{joined}")
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_precedence() {
+        assert_eq!(eval_expression("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(eval_expression("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(eval_expression("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(eval_expression("-2 ^ 2").unwrap(), -4.0);
+        assert_eq!(eval_expression("10 % 3").unwrap(), 1.0);
+        assert_eq!(eval_expression("1_000 * 1.5e2").unwrap(), 150_000.0);
+    }
+
+    #[test]
+    fn test_eval_functions() {
+        assert_eq!(eval_expression("sqrt(16) + abs(-2)").unwrap(), 6.0);
+        assert_eq!(eval_expression("round(2.71828, 2)").unwrap(), 2.72);
+        assert_eq!(eval_expression("max(1, 7, 3)").unwrap(), 7.0);
+        assert!((eval_expression("pi").unwrap() - std::f64::consts::PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        assert!(eval_expression("1 / 0")
+            .unwrap_err()
+            .contains("division by zero"));
+        assert!(eval_expression("(1 + 2")
+            .unwrap_err()
+            .contains("parenthesis"));
+        assert!(eval_expression("foo(1)")
+            .unwrap_err()
+            .contains("unknown function"));
+        assert!(eval_expression("2 +").is_err());
+    }
+
+    #[test]
+    fn test_format_number_hides_float_noise() {
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(42.0), "42");
+        assert_eq!(format_number(-1.5), "-1.5");
+    }
+
+    #[test]
+    fn test_convert_units() {
+        assert!((convert_units(1.0, "mi", "km").unwrap() - 1.609344).abs() < 1e-9);
+        assert!((convert_units(10.0, "lb", "kg").unwrap() - 4.5359237).abs() < 1e-9);
+        assert_eq!(convert_units(1.0, "GiB", "MiB").unwrap(), 1024.0);
+        assert_eq!(convert_units(212.0, "F", "C").unwrap(), 100.0);
+        assert!((convert_units(0.0, "°C", "K").unwrap() - 273.15).abs() < 1e-9);
+        assert!(convert_units(1.0, "kg", "km")
+            .unwrap_err()
+            .contains("cannot convert"));
+        assert!(convert_units(1.0, "parsec", "km")
+            .unwrap_err()
+            .contains("unknown unit"));
+    }
+
+    #[test]
+    fn test_add_duration_across_dst_keeps_wall_clock() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        // DST starts 2025-03-09 in the US
+        let start = parse_datetime("2025-03-08 09:00", &tz).unwrap();
+        let next = add_duration(start, "1d").unwrap();
+        assert_eq!(
+            next.format("%Y-%m-%d %H:%M").to_string(),
+            "2025-03-09 09:00"
+        );
+        // Exact hours do not follow the wall clock
+        let next = add_duration(start, "24h").unwrap();
+        assert_eq!(
+            next.format("%Y-%m-%d %H:%M").to_string(),
+            "2025-03-09 10:00"
+        );
+    }
+
+    #[test]
+    fn test_add_duration_months_and_negative() {
+        let start = parse_datetime("2025-01-31T12:00:00Z", &Tz::UTC).unwrap();
+        let next = add_duration(start, "1mo").unwrap();
+        assert_eq!(next.format("%Y-%m-%d").to_string(), "2025-02-28");
+        let prev = add_duration(start, "-1w 2h").unwrap();
+        assert_eq!(
+            prev.format("%Y-%m-%d %H:%M").to_string(),
+            "2025-01-24 10:00"
+        );
+        assert!(add_duration(start, "3 fortnights").is_err());
+        assert!(add_duration(start, "").is_err());
+        // Too large for the calendar or for the arithmetic: errors, not panics
+        for huge in [
+            "100000000d",
+            "9223372036854775807y",
+            "5000000000mo",
+            "1d 9223372036854775807s",
+        ] {
+            assert!(add_duration(start, huge).is_err(), "{huge}");
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::seconds(90_061)), "1d 1h 1m 1s");
+        assert_eq!(format_duration(Duration::minutes(-30)), "-30m");
+        assert_eq!(format_duration(Duration::zero()), "0s");
+    }
+
+    #[tokio::test]
+    async fn test_tz_convert_action() {
+        let tool = CalcTool::new();
+        let result = tool
+            .execute(serde_json::json!({
+                "action": "tz_convert",
+                "datetime": "2025-06-01 09:00",
+                "timezone": "Europe/London",
+                "toTimezone": "Asia/Tokyo"
+            }))
+            .await
+            .unwrap();
+        assert!(result.contains("2025-06-01 17:00:00 JST"), "{result}");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_timezone() {
+        let tool = CalcTool::new();
+        let result = tool
+            .execute(serde_json::json!({"action": "now", "timezone": "Mars/Olympus"}))
+            .await
+            .unwrap();
+        assert!(result.contains("unknown timezone"));
+    }
+}
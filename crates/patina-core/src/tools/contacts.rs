@@ -0,0 +1,339 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use crate::contacts::{Contact, ContactStore};
+use crate::tools::Tool;
+
+/// Tool for looking up and maintaining the workspace contacts directory.
+pub struct ContactsTool {
+    store: Mutex<ContactStore>,
+}
+
+impl ContactsTool {
+    /// Create a tool backed by `<workspace>/contacts.toml`.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: Mutex::new(ContactStore::load(&path)),
+        }
+    }
+}
+
+/// Read an optional string-array parameter.
+fn string_list(params: &serde_json::Value, key: &str) -> Option<Vec<String>> {
+    params.get(key).and_then(|v| v.as_array()).map(|arr| {
+        arr.iter()
+            .filter_map(|v| v.as_str().map(|s| s.to_string()))
+            .collect()
+    })
+}
+
+/// Read an optional string parameter.
+fn string_param(params: &serde_json::Value, key: &str) -> Option<String> {
+    params
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn format_contact(c: &Contact) -> String {
+    let mut out = format!("- {} (ID: {})", c.name, c.id);
+    if !c.relationship.is_empty() {
+        out.push_str(&format!("\n  Relationship: {}", c.relationship));
+    }
+    if !c.aliases.is_empty() {
+        out.push_str(&format!("\n  Aliases: {}", c.aliases.join(", ")));
+    }
+    if !c.emails.is_empty() {
+        out.push_str(&format!("\n  Email: {}", c.emails.join(", ")));
+    }
+    if !c.phones.is_empty() {
+        out.push_str(&format!("\n  Phone: {}", c.phones.join(", ")));
+    }
+    if !c.address.is_empty() {
+        out.push_str(&format!("\n  Address: {}", c.address));
+    }
+    if !c.notes.is_empty() {
+        out.push_str(&format!("\n  Notes: {}", c.notes));
+    }
+    out
+}
+
+#[async_trait]
+impl Tool for ContactsTool {
+    fn name(&self) -> &str {
+        "contacts"
+    }
+
+    fn description(&self) -> &str {
+        "Look up and maintain the user's contacts directory. Use 'search' to resolve names, \
+         aliases, or relationships (e.g. 'mom', 'landlord') to real email addresses and phone \
+         numbers before drafting messages or reminders. Use 'add'/'update' when the user shares \
+         contact details."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["search", "list", "add", "update", "remove"],
+                    "description": "The action to perform"
+                },
+                "query": {
+                    "type": "string",
+                    "description": "Name, alias, relationship, email, or phone to search for (required for 'search')"
+                },
+                "contact_id": {
+                    "type": "string",
+                    "description": "Contact ID (required for 'update' and 'remove')"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "Full name (required for 'add')"
+                },
+                "aliases": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Alternative names, e.g. ['mom']"
+                },
+                "relationship": {
+                    "type": "string",
+                    "description": "Relationship to the user, e.g. 'mother', 'landlord'"
+                },
+                "emails": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Email addresses"
+                },
+                "phones": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Phone numbers"
+                },
+                "address": {
+                    "type": "string",
+                    "description": "Postal address"
+                },
+                "notes": {
+                    "type": "string",
+                    "description": "Free-form notes"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: action"))?;
+
+        match action {
+            "search" => self.handle_search(&params).await,
+            "list" => self.handle_list().await,
+            "add" => self.handle_add(&params).await,
+            "update" => self.handle_update(&params).await,
+            "remove" => self.handle_remove(&params).await,
+            _ => Ok(format!(
+                "Unknown action: {action}. Use 'search', 'list', 'add', 'update', or 'remove'."
+            )),
+        }
+    }
+}
+
+impl ContactsTool {
+    async fn handle_search(&self, params: &serde_json::Value) -> Result<String> {
+        let query = params
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: query"))?;
+
+        let mut store = self.store.lock().await;
+        store.refresh_from_disk();
+        let results = store.search(query);
+        if results.is_empty() {
+            return Ok(format!("No contacts match '{query}'."));
+        }
+        let lines: Vec<String> = results.iter().map(|c| format_contact(c)).collect();
+        Ok(format!(
+            "{} contact(s) matching '{query}':\n{}",
+            results.len(),
+            lines.join("\n")
+        ))
+    }
+
+    async fn handle_list(&self) -> Result<String> {
+        let mut store = self.store.lock().await;
+        store.refresh_from_disk();
+        if store.list().is_empty() {
+            return Ok("No contacts.".into());
+        }
+        let mut contacts: Vec<&Contact> = store.list().iter().collect();
+        contacts.sort_by(|a, b| a.name.cmp(&b.name));
+        let lines: Vec<String> = contacts.iter().map(|c| format_contact(c)).collect();
+        Ok(format!("Contacts ({}):\n{}", lines.len(), lines.join("\n")))
+    }
+
+    async fn handle_add(&self, params: &serde_json::Value) -> Result<String> {
+        let name = params
+            .get("name")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: name"))?;
+
+        let contact = Contact {
+            name: name.trim().to_string(),
+            aliases: string_list(params, "aliases").unwrap_or_default(),
+            relationship: string_param(params, "relationship").unwrap_or_default(),
+            emails: string_list(params, "emails").unwrap_or_default(),
+            phones: string_list(params, "phones").unwrap_or_default(),
+            address: string_param(params, "address").unwrap_or_default(),
+            notes: string_param(params, "notes").unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let mut store = self.store.lock().await;
+        let added = store.add(contact)?;
+        Ok(format!("Contact '{}' added (ID: {})", added.name, added.id))
+    }
+
+    async fn handle_update(&self, params: &serde_json::Value) -> Result<String> {
+        let id = params
+            .get("contact_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: contact_id"))?;
+
+        let mut store = self.store.lock().await;
+        let updated = store.update(id, |c| {
+            if let Some(name) = string_param(params, "name").filter(|n| !n.trim().is_empty()) {
+                c.name = name;
+            }
+            if let Some(aliases) = string_list(params, "aliases") {
+                c.aliases = aliases;
+            }
+            if let Some(relationship) = string_param(params, "relationship") {
+                c.relationship = relationship;
+            }
+            if let Some(emails) = string_list(params, "emails") {
+                c.emails = emails;
+            }
+            if let Some(phones) = string_list(params, "phones") {
+                c.phones = phones;
+            }
+            if let Some(address) = string_param(params, "address") {
+                c.address = address;
+            }
+            if let Some(notes) = string_param(params, "notes") {
+                c.notes = notes;
+            }
+        })?;
+
+        match updated {
+            Some(c) => Ok(format!("Contact updated:\n{}", format_contact(&c))),
+            None => Ok(format!("Contact {id} not found.")),
+        }
+    }
+
+    async fn handle_remove(&self, params: &serde_json::Value) -> Result<String> {
+        let id = params
+            .get("contact_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: contact_id"))?;
+
+        let mut store = self.store.lock().await;
+        if store.remove(id)? {
+            Ok(format!("Contact {id} removed."))
+        } else {
+            Ok(format!("Contact {id} not found."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn extract_id(result: &str) -> String {
+        let start = result.find("ID: ").unwrap() + 4;
+        result[start..start + 8].to_string()
+    }
+
+    #[tokio::test]
+    async fn test_add_then_search_by_alias() {
+        let dir = TempDir::new().unwrap();
+        let tool = ContactsTool::new(dir.path().join("contacts.toml"));
+        let result = tool
+            .execute(serde_json::json!({
+                "action": "add",
+                "name": "Jane Doe",
+                "aliases": ["mom"],
+                "emails": ["jane@example.com"]
+            }))
+            .await
+            .unwrap();
+        assert!(result.contains("added"));
+
+        let result = tool
+            .execute(serde_json::json!({"action": "search", "query": "Mom"}))
+            .await
+            .unwrap();
+        assert!(result.contains("Jane Doe"));
+        assert!(result.contains("jane@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_unspecified_fields() {
+        let dir = TempDir::new().unwrap();
+        let tool = ContactsTool::new(dir.path().join("contacts.toml"));
+        let result = tool
+            .execute(serde_json::json!({
+                "action": "add",
+                "name": "Bob",
+                "relationship": "landlord",
+                "phones": ["555-0100"]
+            }))
+            .await
+            .unwrap();
+        let id = extract_id(&result);
+
+        let result = tool
+            .execute(serde_json::json!({
+                "action": "update",
+                "contact_id": id,
+                "emails": ["bob@example.com"]
+            }))
+            .await
+            .unwrap();
+        assert!(result.contains("bob@example.com"));
+        assert!(result.contains("555-0100"));
+        assert!(result.contains("landlord"));
+    }
+
+    #[tokio::test]
+    async fn test_search_no_match() {
+        let dir = TempDir::new().unwrap();
+        let tool = ContactsTool::new(dir.path().join("contacts.toml"));
+        let result = tool
+            .execute(serde_json::json!({"action": "search", "query": "nobody"}))
+            .await
+            .unwrap();
+        assert!(result.contains("No contacts match"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_action() {
+        let dir = TempDir::new().unwrap();
+        let tool = ContactsTool::new(dir.path().join("contacts.toml"));
+        let result = tool
+            .execute(serde_json::json!({"action": "merge"}))
+            .await
+            .unwrap();
+        assert!(result.contains("Unknown action"));
+    }
+}
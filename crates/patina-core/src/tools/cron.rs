@@ -4,15 +4,22 @@ use anyhow::Result;
 use async_trait::async_trait;
 use tokio::sync::{Mutex, RwLock};
 
+use crate::cron::history::truncate_middle;
 use crate::cron::service::CronService;
-use crate::cron::types::{CronSchedule, ScheduleKind};
+use crate::cron::types::{CronSchedule, DeliverOn, OutputCondition, ScheduleKind};
+use crate::locale::UserLocale;
 use crate::tools::Tool;
 
+/// Most characters of a run's stdout or stderr shown by `history`.
+const SHOWN_STREAM_CHARS: usize = 1_500;
+
 /// Tool for scheduling cron jobs.
 pub struct CronTool {
     service: Arc<Mutex<CronService>>,
     default_channel: Arc<RwLock<String>>,
     default_chat_id: Arc<RwLock<String>>,
+    /// Clock that `at` times and new cron expressions are read on.
+    locale: RwLock<UserLocale>,
 }
 
 impl CronTool {
@@ -21,6 +28,7 @@ impl CronTool {
             service,
             default_channel: Arc::new(RwLock::new(String::new())),
             default_chat_id: Arc::new(RwLock::new(String::new())),
+            locale: RwLock::new(UserLocale::default()),
         }
     }
 
@@ -29,6 +37,11 @@ impl CronTool {
         *self.default_channel.write().await = channel.to_string();
         *self.default_chat_id.write().await = chat_id.to_string();
     }
+
+    /// Set the timezone and locale of the user being talked to.
+    pub async fn set_locale(&self, locale: &UserLocale) {
+        *self.locale.write().await = locale.clone();
+    }
 }
 
 #[async_trait]
@@ -40,14 +53,23 @@ impl Tool for CronTool {
     fn description(&self) -> &str {
         "Schedule, list, or remove recurring tasks. Supports three schedule types:\n\
          - 'every_seconds': Run every N seconds (e.g. every 3600 = every hour)\n\
-         - 'cron_expr': Standard cron expression (e.g. '0 9 * * *' = daily at 9am)\n\
-         - 'at': One-time execution at an ISO datetime (e.g. '2025-01-15T14:00:00Z')\n\
-         Use action 'add' to create, 'list' to view, 'remove' to delete.\n\
+         - 'cron_expr': Standard cron expression (e.g. '0 9 * * *' = daily at 9am on the user's clock)\n\
+         - 'at': One-time execution at a date and time on the user's clock (e.g. '2025-01-15 14:00'), \
+         or RFC 3339 with an offset\n\
+         Use action 'add' to create, 'list' to view, 'remove' to delete, and 'history' to see \
+         recent exec runs with their output and exit codes.\n\
          \n\
-         Jobs support two payload kinds:\n\
+         Jobs support three payload kinds:\n\
          - 'agent_turn' (default): sends the message through the AI agent for reasoning and tool use.\n\
          - 'exec': runs the message as a shell command directly — much faster, no LLM needed. \
-         Use 'exec' for simple scheduled commands like API calls, scripts, or data fetches."
+         Use 'exec' for simple scheduled commands like API calls, scripts, or data fetches. \
+         With deliver_on 'failure', only failed runs are sent.\n\
+         - 'digest': sends a summary of activity since the last run (sessions, tasks, cron runs, \
+         usage cost) to this chat. The message is used as the digest's title.\n\
+         \n\
+         Small pipelines chain jobs: 'on_success' and 'on_failure' name the job to run next, \
+         with 'if_contains'/'if_match' deciding what counts as success. Create the later steps \
+         first with 'chained': true; their message can use {{output}} for the previous output."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -56,7 +78,7 @@ impl Tool for CronTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["add", "list", "remove"],
+                    "enum": ["add", "list", "remove", "history"],
                     "description": "The action to perform"
                 },
                 "message": {
@@ -73,20 +95,68 @@ impl Tool for CronTool {
                 },
                 "cron_expr": {
                     "type": "string",
-                    "description": "Cron expression (e.g. '0 9 * * *' for daily at 9am)"
+                    "description": "Cron expression (e.g. '0 9 * * *' for daily at 9am), run on the user's clock"
                 },
                 "at": {
                     "type": "string",
-                    "description": "ISO datetime for one-time execution (e.g. '2025-01-15T14:00:00Z')"
+                    "description": "Date and time for one-time execution, on the user's clock (e.g. '2025-01-15 14:00'), or RFC 3339 with an offset"
                 },
                 "job_id": {
                     "type": "string",
-                    "description": "Job ID (required for 'remove')"
+                    "description": "Job ID (required for 'remove'; for 'history', only this job's runs)"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "For 'history': how many recent runs to show (default 5)",
+                    "minimum": 1,
+                    "maximum": 50
+                },
+                "model_tier": {
+                    "type": "string",
+                    "description": "For 'agent_turn': model tier to run the job on, e.g. a cheap 'fast' tier for routine checks. Defaults to the chat's usual tier"
+                },
+                "deliver_on": {
+                    "type": "string",
+                    "enum": ["always", "failure"],
+                    "description": "For 'exec': deliver every run's output (default), or only runs that fail (non-zero exit, error, or timeout), for checks that should stay quiet while healthy"
+                },
+                "notify": {
+                    "type": "string",
+                    "description": "Deliver results to this notification target (e.g. a phone via ntfy or Pushover) instead of this chat. Only targets the user has configured exist."
+                },
+                "webhook": {
+                    "type": "string",
+                    "description": "POST results as JSON to this webhook target (e.g. an n8n workflow or Home Assistant automation) instead of this chat. Only targets the user has configured exist."
+                },
+                "cache_ttl_seconds": {
+                    "type": "integer",
+                    "description": "For 'agent_turn': when the job runs again the same day within this many seconds, resend the earlier reply instead of asking the agent (e.g. 3600 for a briefing)",
+                    "minimum": 1
+                },
+                "on_success": {
+                    "type": "string",
+                    "description": "ID of a job to run next when this one succeeds (and its output meets if_contains/if_match). Its message can use {{output}} for this job's output"
+                },
+                "on_failure": {
+                    "type": "string",
+                    "description": "ID of a job to run next when this one fails, or its output doesn't meet the condition"
+                },
+                "if_contains": {
+                    "type": "string",
+                    "description": "Only count the run as a success when the output contains this text"
+                },
+                "if_match": {
+                    "type": "string",
+                    "description": "Only count the run as a success when the output matches this regex"
+                },
+                "chained": {
+                    "type": "boolean",
+                    "description": "Create a job with no schedule that only runs when another job's on_success/on_failure points to it. Create it first, then the job that chains to it"
                 },
                 "kind": {
                     "type": "string",
-                    "enum": ["agent_turn", "exec"],
-                    "description": "Payload kind: 'agent_turn' (default) sends message through the LLM agent loop. 'exec' runs the message as a shell command directly (no LLM), much faster for simple commands like API calls or scripts."
+                    "enum": ["agent_turn", "exec", "digest"],
+                    "description": "Payload kind: 'agent_turn' (default) sends message through the LLM agent loop. 'exec' runs the message as a shell command directly (no LLM), much faster for simple commands like API calls or scripts. 'digest' sends an activity summary (sessions, tasks, cron runs, usage cost) since the previous run, titled with the message."
                 }
             },
             "required": ["action"]
@@ -103,8 +173,9 @@ impl Tool for CronTool {
             "add" => self.handle_add(&params).await,
             "list" => self.handle_list().await,
             "remove" => self.handle_remove(&params).await,
+            "history" => self.handle_history(&params).await,
             _ => Ok(format!(
-                "Unknown action: {action}. Use 'add', 'list', or 'remove'."
+                "Unknown action: {action}. Use 'add', 'list', 'remove', or 'history'."
             )),
         }
     }
@@ -122,6 +193,8 @@ impl CronTool {
             .and_then(|v| v.as_str())
             .unwrap_or_else(|| &message[..message.len().min(30)]);
 
+        let locale = self.locale.read().await.clone();
+
         // Determine schedule type
         let schedule = if let Some(secs) = params.get("every_seconds").and_then(|v| v.as_i64()) {
             CronSchedule {
@@ -137,11 +210,13 @@ impl CronTool {
                 at_ms: None,
                 every_ms: None,
                 expr: Some(expr.to_string()),
-                tz: None,
+                tz: locale.tz_name(),
             }
         } else if let Some(at_str) = params.get("at").and_then(|v| v.as_str()) {
-            let dt = chrono::DateTime::parse_from_rfc3339(at_str)
-                .map_err(|e| anyhow::anyhow!("invalid datetime '{at_str}': {e}"))?;
+            let dt = match locale.parse(at_str) {
+                Ok(dt) => dt,
+                Err(e) => return Ok(format!("Error: invalid 'at': {e}")),
+            };
             CronSchedule {
                 kind: ScheduleKind::At,
                 at_ms: Some(dt.timestamp_millis()),
@@ -149,8 +224,18 @@ impl CronTool {
                 expr: None,
                 tz: None,
             }
+        } else if params.get("chained").and_then(|v| v.as_bool()) == Some(true) {
+            CronSchedule {
+                kind: ScheduleKind::At,
+                at_ms: None,
+                every_ms: None,
+                expr: None,
+                tz: None,
+            }
         } else {
-            return Ok("Error: Must specify one of: every_seconds, cron_expr, or at".to_string());
+            return Ok(
+                "Error: Must specify one of: every_seconds, cron_expr, at, or chained".to_string(),
+            );
         };
 
         let channel = {
@@ -169,6 +254,12 @@ impl CronTool {
                 Some(ci.clone())
             }
         };
+        let param = |key: &str| params.get(key).and_then(|v| v.as_str());
+        let (channel, chat_id) = match (param("notify"), param("webhook")) {
+            (Some(target), _) => (Some("notify".to_string()), Some(target.to_string())),
+            (None, Some(target)) => (Some("webhook".to_string()), Some(target.to_string())),
+            (None, None) => (channel, chat_id),
+        };
 
         let kind = params
             .get("kind")
@@ -176,16 +267,76 @@ impl CronTool {
             .unwrap_or("agent_turn")
             .to_string();
 
+        let deliver_on = match param("deliver_on") {
+            None | Some("always") => DeliverOn::Always,
+            Some("failure") if kind == "exec" => DeliverOn::Failure,
+            Some("failure") => return Ok("Error: deliver_on only applies to 'exec' jobs".into()),
+            Some(other) => {
+                return Ok(format!(
+                    "Error: unknown deliver_on '{other}' (use 'always' or 'failure')"
+                ))
+            }
+        };
+
+        let model_tier = param("model_tier")
+            .filter(|t| !t.is_empty() && kind == "agent_turn")
+            .map(String::from);
+
+        let cache_ttl = params
+            .get("cache_ttl_seconds")
+            .and_then(|v| v.as_u64())
+            .filter(|_| kind == "agent_turn");
+
+        let condition = OutputCondition {
+            contains: param("if_contains").map(String::from),
+            regex: param("if_match").map(String::from),
+        };
+        let on_success = param("on_success").map(String::from);
+        let on_failure = param("on_failure").map(String::from);
+        let chains = on_success.is_some() || on_failure.is_some();
+
         let mut service = self.service.lock().await;
         match service.add_job(
             name, schedule, message, &kind, true, channel, chat_id, false,
         ) {
             Ok(job) => {
+                let job = match cache_ttl {
+                    Some(ttl) => service
+                        .set_cache_ttl(&job.id, Some(ttl))
+                        .cloned()
+                        .unwrap_or(job),
+                    None => job,
+                };
+                let job = match model_tier {
+                    Some(tier) => service
+                        .set_model_tier(&job.id, Some(tier))
+                        .cloned()
+                        .unwrap_or(job),
+                    None => job,
+                };
+                let job = if deliver_on.is_always() {
+                    job
+                } else {
+                    service
+                        .set_deliver_on(&job.id, deliver_on)
+                        .cloned()
+                        .unwrap_or(job)
+                };
+                let job = if chains {
+                    match service.set_chain(&job.id, on_success, on_failure, Some(condition)) {
+                        Ok(job) => job,
+                        Err(e) => {
+                            service.remove_job(&job.id);
+                            return Ok(format!("Failed to create job: {e}"));
+                        }
+                    }
+                } else {
+                    job
+                };
                 let next = job
                     .state
                     .next_run_at_ms
-                    .and_then(chrono::DateTime::from_timestamp_millis)
-                    .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .map(|ms| locale.format_ms(ms))
                     .unwrap_or_else(|| "N/A".to_string());
 
                 Ok(format!(
@@ -198,8 +349,14 @@ impl CronTool {
     }
 
     async fn handle_list(&self) -> Result<String> {
+        let locale = self.locale.read().await.clone();
         let mut service = self.service.lock().await;
-        let jobs = service.list_jobs(false);
+        // Scheduled messages are listed by the message tool
+        let jobs: Vec<_> = service
+            .list_jobs(false)
+            .into_iter()
+            .filter(|j| j.payload.kind != "message")
+            .collect();
 
         if jobs.is_empty() {
             return Ok("No active cron jobs.".to_string());
@@ -212,33 +369,79 @@ impl CronTool {
                     let secs = job.schedule.every_ms.unwrap_or(0) / 1000;
                     format!("every {secs}s")
                 }
-                ScheduleKind::Cron => {
-                    format!("cron: {}", job.schedule.expr.as_deref().unwrap_or("?"))
-                }
-                ScheduleKind::At => {
-                    let ts = job.schedule.at_ms.unwrap_or(0);
-                    chrono::DateTime::from_timestamp_millis(ts)
-                        .map(|dt| format!("at {}", dt.format("%Y-%m-%d %H:%M:%S UTC")))
-                        .unwrap_or_else(|| "at ?".to_string())
-                }
+                ScheduleKind::Cron => format!(
+                    "cron: {} ({})",
+                    job.schedule.expr.as_deref().unwrap_or("?"),
+                    job.schedule.tz.as_deref().unwrap_or("server local time")
+                ),
+                ScheduleKind::At => match job.schedule.at_ms {
+                    Some(ms) => format!("at {}", locale.format_ms(ms)),
+                    None => "when chained".to_string(),
+                },
             };
 
             let next = job
                 .state
                 .next_run_at_ms
-                .and_then(chrono::DateTime::from_timestamp_millis)
-                .map(|dt| dt.format("%H:%M:%S UTC").to_string())
+                .map(|ms| locale.format_ms(ms))
                 .unwrap_or_else(|| "N/A".to_string());
 
             output.push_str(&format!(
-                "  [{}] '{}' — {} (next: {})\n",
+                "  [{}] '{}' — {} (next: {})",
                 job.id, job.name, schedule_desc, next
             ));
+            if let Some(id) = &job.on_success {
+                output.push_str(&format!(", then {id}"));
+            }
+            if let Some(id) = &job.on_failure {
+                output.push_str(&format!(", on failure {id}"));
+            }
+            output.push('\n');
         }
 
         Ok(output)
     }
 
+    async fn handle_history(&self, params: &serde_json::Value) -> Result<String> {
+        let job_id = params.get("job_id").and_then(|v| v.as_str());
+        let limit = params
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(5)
+            .clamp(1, 50) as usize;
+        let locale = self.locale.read().await.clone();
+        let runs = self.service.lock().await.runs(job_id, limit);
+        if runs.is_empty() {
+            return Ok("No recorded exec job runs.".to_string());
+        }
+
+        let mut output = String::from("Recent exec job runs, newest first:\n");
+        for run in runs {
+            let exit = run
+                .exit_code
+                .map(|c| format!("exit {c}"))
+                .unwrap_or_else(|| "no exit code".to_string());
+            output.push_str(&format!(
+                "\n[{}] '{}' at {}: {} ({exit}, {} ms)\n",
+                run.job_id,
+                run.job_name,
+                locale.format_ms(run.started_at_ms),
+                run.status,
+                run.duration_ms
+            ));
+            if let Some(error) = &run.error {
+                output.push_str(&format!("Error: {error}\n"));
+            }
+            for (label, text) in [("stdout", &run.stdout), ("stderr", &run.stderr)] {
+                if !text.trim().is_empty() {
+                    let text = truncate_middle(text.trim_end(), SHOWN_STREAM_CHARS);
+                    output.push_str(&format!("{label}:\n{text}\n"));
+                }
+            }
+        }
+        Ok(output)
+    }
+
     async fn handle_remove(&self, params: &serde_json::Value) -> Result<String> {
         let job_id = params
             .get("job_id")
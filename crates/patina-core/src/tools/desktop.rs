@@ -0,0 +1,361 @@
+//! Desktop integration tools (clipboard + notifications) for interactive CLI use,
+//! and screen capture for `patina ask --screenshot`.
+//!
+//! These are only registered for `patina agent` — a gateway running on a server
+//! has no clipboard or notification daemon to talk to.
+
+use std::path::Path;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+
+use super::Tool;
+
+/// Maximum clipboard content returned to the LLM.
+const MAX_CLIPBOARD_CHARS: usize = 20_000;
+
+/// External commands used to read and write the system clipboard.
+#[derive(Debug, Clone, PartialEq)]
+struct ClipboardBackend {
+    read: Vec<&'static str>,
+    write: Vec<&'static str>,
+}
+
+/// Pick a clipboard backend for the current platform from the tools on PATH.
+fn detect_clipboard_backend() -> Option<ClipboardBackend> {
+    let candidates: Vec<ClipboardBackend> = if cfg!(target_os = "macos") {
+        vec![ClipboardBackend {
+            read: vec!["pbpaste"],
+            write: vec!["pbcopy"],
+        }]
+    } else if cfg!(windows) {
+        vec![ClipboardBackend {
+            read: vec!["powershell", "-NoProfile", "-Command", "Get-Clipboard"],
+            write: vec!["clip"],
+        }]
+    } else {
+        let mut list = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            list.push(ClipboardBackend {
+                read: vec!["wl-paste", "--no-newline"],
+                write: vec!["wl-copy"],
+            });
+        }
+        list.push(ClipboardBackend {
+            read: vec!["xclip", "-selection", "clipboard", "-o"],
+            write: vec!["xclip", "-selection", "clipboard"],
+        });
+        list.push(ClipboardBackend {
+            read: vec!["xsel", "--clipboard", "--output"],
+            write: vec!["xsel", "--clipboard", "--input"],
+        });
+        list
+    };
+
+    candidates
+        .into_iter()
+        .find(|b| which::which(b.read[0]).is_ok() && which::which(b.write[0]).is_ok())
+}
+
+/// Read and write the system clipboard.
+pub struct ClipboardTool {
+    backend: Option<ClipboardBackend>,
+}
+
+impl ClipboardTool {
+    pub fn new() -> Self {
+        let backend = detect_clipboard_backend();
+        match &backend {
+            Some(b) => tracing::debug!("Clipboard backend: {}", b.read[0]),
+            None => tracing::debug!("No clipboard backend found on PATH"),
+        }
+        Self { backend }
+    }
+}
+
+impl Default for ClipboardTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for ClipboardTool {
+    fn name(&self) -> &str {
+        "clipboard"
+    }
+
+    fn description(&self) -> &str {
+        "Read from or write to the user's system clipboard. Use 'read' when the user refers to \
+         something they copied; use 'write' to hand back text they can paste."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["read", "write"],
+                    "description": "Read the clipboard or replace its contents"
+                },
+                "content": {
+                    "type": "string",
+                    "description": "Text to copy (required for 'write')"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: action"))?;
+
+        let Some(backend) = &self.backend else {
+            return Ok(
+                "Error: No clipboard utility found. Install pbcopy/pbpaste (macOS), \
+                       wl-clipboard (Wayland), or xclip/xsel (X11)."
+                    .into(),
+            );
+        };
+
+        match action {
+            "read" => {
+                let output = tokio::process::Command::new(backend.read[0])
+                    .args(&backend.read[1..])
+                    .output()
+                    .await?;
+                if !output.status.success() {
+                    return Ok(format!(
+                        "Error reading clipboard: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                let text = String::from_utf8_lossy(&output.stdout).to_string();
+                if text.is_empty() {
+                    return Ok("Clipboard is empty.".into());
+                }
+                if text.len() > MAX_CLIPBOARD_CHARS {
+                    let mut end = MAX_CLIPBOARD_CHARS;
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    return Ok(format!(
+                        "{}\n... (truncated, {} more chars)",
+                        &text[..end],
+                        text.len() - end
+                    ));
+                }
+                Ok(text)
+            }
+            "write" => {
+                let content = params
+                    .get("content")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("missing required parameter: content"))?;
+                let mut child = tokio::process::Command::new(backend.write[0])
+                    .args(&backend.write[1..])
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::piped())
+                    .spawn()?;
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(content.as_bytes()).await?;
+                }
+                let output = child.wait_with_output().await?;
+                if !output.status.success() {
+                    return Ok(format!(
+                        "Error writing clipboard: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    ));
+                }
+                Ok(format!(
+                    "Copied {} chars to clipboard.",
+                    content.chars().count()
+                ))
+            }
+            _ => Ok(format!("Unknown action: {action}. Use 'read' or 'write'.")),
+        }
+    }
+}
+
+/// Screenshot commands for the current platform, most preferred first. The
+/// output path is appended to each.
+fn screenshot_commands() -> Vec<Vec<&'static str>> {
+    if cfg!(target_os = "macos") {
+        vec![vec!["screencapture", "-x"]]
+    } else if cfg!(windows) {
+        Vec::new()
+    } else {
+        let mut list = Vec::new();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            list.push(vec!["grim"]);
+        }
+        list.push(vec!["scrot", "--overwrite"]);
+        list.push(vec!["gnome-screenshot", "-f"]);
+        list.push(vec!["import", "-window", "root"]);
+        list
+    }
+}
+
+/// Capture the whole screen to `path` as a PNG.
+pub async fn capture_screen(path: &Path) -> Result<()> {
+    let Some(command) = screenshot_commands()
+        .into_iter()
+        .find(|c| which::which(c[0]).is_ok())
+    else {
+        anyhow::bail!(
+            "No screenshot utility found. Install grim (Wayland) or scrot, gnome-screenshot, \
+             or ImageMagick (X11); macOS has screencapture built in."
+        );
+    };
+    run_capture(&command, path).await
+}
+
+async fn run_capture(command: &[&str], path: &Path) -> Result<()> {
+    let output = tokio::process::Command::new(command[0])
+        .args(&command[1..])
+        .arg(path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} failed: {}",
+            command[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    // screencapture exits successfully when screen recording isn't allowed
+    if !path.is_file() {
+        anyhow::bail!(
+            "{} didn't save a screenshot (does the terminal have permission to record the screen?)",
+            command[0]
+        );
+    }
+    Ok(())
+}
+
+/// Show a desktop notification.
+pub struct NotifyTool;
+
+impl NotifyTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for NotifyTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for NotifyTool {
+    fn name(&self) -> &str {
+        "notify"
+    }
+
+    fn description(&self) -> &str {
+        "Show a desktop notification to the user (e.g. when a long-running task finishes)."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "title": {
+                    "type": "string",
+                    "description": "Notification title"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Notification body text"
+                }
+            },
+            "required": ["title"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let title = params
+            .get("title")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: title"))?
+            .to_string();
+        let body = params
+            .get("body")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        // notify-rust talks to D-Bus / platform APIs synchronously
+        let shown = tokio::task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("patina")
+                .summary(&title)
+                .body(&body)
+                .show()
+                .map(|_| title)
+        })
+        .await?;
+
+        match shown {
+            Ok(title) => Ok(format!("Notification shown: {title}")),
+            Err(e) => Ok(format!("Error showing notification: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clipboard_without_backend() {
+        let tool = ClipboardTool { backend: None };
+        let result = tool
+            .execute(serde_json::json!({"action": "read"}))
+            .await
+            .unwrap();
+        assert!(result.contains("No clipboard utility found"));
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_roundtrip_with_fake_backend() {
+        // `cat` stands in for a clipboard writer and `echo` for a reader
+        let tool = ClipboardTool {
+            backend: Some(ClipboardBackend {
+                read: vec!["echo", "copied text"],
+                write: vec!["cat"],
+            }),
+        };
+        let result = tool
+            .execute(serde_json::json!({"action": "write", "content": "héllo"}))
+            .await
+            .unwrap();
+        assert_eq!(result, "Copied 5 chars to clipboard.");
+
+        let result = tool
+            .execute(serde_json::json!({"action": "read"}))
+            .await
+            .unwrap();
+        assert_eq!(result.trim(), "copied text");
+    }
+
+    #[tokio::test]
+    async fn test_capture_needs_a_saved_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("screen.png");
+        let err = run_capture(&["true"], &path).await.unwrap_err();
+        assert!(err.to_string().contains("didn't save a screenshot"));
+
+        // `touch` stands in for a screenshot tool
+        run_capture(&["touch"], &path).await.unwrap();
+        assert!(path.is_file());
+    }
+}
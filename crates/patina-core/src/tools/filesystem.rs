@@ -6,7 +6,10 @@ use async_trait::async_trait;
 use super::Tool;
 
 /// Resolve a path, expanding ~ and enforcing optional directory restriction.
-fn resolve_path(path: &str, allowed_dir: Option<&Path>) -> std::result::Result<PathBuf, String> {
+pub(crate) fn resolve_path(
+    path: &str,
+    allowed_dir: Option<&Path>,
+) -> std::result::Result<PathBuf, String> {
     let expanded = if path.starts_with("~/") || path == "~" {
         dirs::home_dir()
             .map(|h| h.join(path.strip_prefix("~/").unwrap_or("")))
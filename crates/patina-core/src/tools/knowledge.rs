@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::Tool;
+use crate::knowledge::KnowledgeBase;
+
+/// Tool that searches the configured knowledge documents by meaning.
+pub struct KnowledgeSearchTool {
+    kb: Arc<KnowledgeBase>,
+}
+
+impl KnowledgeSearchTool {
+    pub fn new(kb: Arc<KnowledgeBase>) -> Self {
+        Self { kb }
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeSearchTool {
+    fn name(&self) -> &str {
+        "knowledge_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search the user's knowledge base (handbooks, docs, notes folders, and pages they configured) by meaning. Use it for questions those documents could answer, and cite the sources you use."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "What to look for, as a question or description"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "Maximum number of passages to return",
+                    "minimum": 1,
+                    "maximum": 20
+                }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let query = params
+            .get("query")
+            .and_then(|q| q.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        if query.trim().is_empty() {
+            return Ok("Error: query is required".into());
+        }
+
+        let limit = params
+            .get("limit")
+            .and_then(|l| l.as_u64())
+            .map(|l| l.clamp(1, 20) as usize)
+            .unwrap_or(self.kb.top_k());
+
+        let passages = match self.kb.search(&query, limit).await {
+            Ok(p) => p,
+            Err(e) => return Ok(format!("Error: knowledge search failed: {e}")),
+        };
+
+        if passages.is_empty() {
+            return Ok("No results found. The knowledge base may still be indexing.".into());
+        }
+
+        let mut output = String::new();
+        for (i, passage) in passages.iter().enumerate() {
+            if i > 0 {
+                output.push_str("\n---\n");
+            }
+            output.push_str(&format!(
+                "[{}] {} (lines {}-{}, score: {:.2})\n\n{}\n",
+                i + 1,
+                passage.source,
+                passage.start_line,
+                passage.end_line,
+                passage.score,
+                passage.text,
+            ));
+        }
+        output.push_str(
+            "\nCite the passages you rely on by source, e.g. \"(handbook/leave.md)\" or the URL.",
+        );
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{Local, Utc};
+
+use super::Tool;
+use crate::location::LocationStore;
+
+/// Tool that says where someone is, from their location pings.
+pub struct LocationTool {
+    store: Arc<LocationStore>,
+}
+
+impl LocationTool {
+    pub fn new(store: Arc<LocationStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for LocationTool {
+    fn name(&self) -> &str {
+        "location"
+    }
+
+    fn description(&self) -> &str {
+        "Where a user is, from their phone's location pings: the named place they're at (or coordinates), how recent and accurate the ping is, and their arrivals and departures over the last hours. Use it for questions like \"am I still at the office?\" or to tailor reminders to where someone is."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "user": {
+                    "type": "string",
+                    "description": "Whose location. Optional when only one user shares theirs"
+                },
+                "hours": {
+                    "type": "integer",
+                    "description": "Also list arrivals and departures over this many hours",
+                    "minimum": 1,
+                    "maximum": 720
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let users = match self.store.users() {
+            Ok(users) => users,
+            Err(e) => return Ok(format!("Error: couldn't read locations: {e}")),
+        };
+        let user = match params.get("user").and_then(|u| u.as_str()) {
+            Some(user) if !user.trim().is_empty() => user.trim().to_string(),
+            _ if users.len() == 1 => users[0].clone(),
+            _ if users.is_empty() => return Ok("No one has shared a location yet.".into()),
+            _ => {
+                return Ok(format!(
+                    "Error: user is required (sharing locations: {})",
+                    users.join(", ")
+                ))
+            }
+        };
+
+        let fix = match self.store.latest(&user) {
+            Ok(Some(fix)) => fix,
+            Ok(None) => {
+                return Ok(format!(
+                    "No location for '{user}' (sharing locations: {})",
+                    users.join(", ")
+                ))
+            }
+            Err(e) => return Ok(format!("Error: couldn't read locations: {e}")),
+        };
+
+        let minutes = (Utc::now() - fix.at).num_minutes().max(0);
+        let age = match minutes {
+            0 => "just now".to_string(),
+            m if m < 120 => format!("{m} min ago"),
+            m => format!("{} h ago", m / 60),
+        };
+        let mut output = match self.store.place_of(&fix) {
+            Some((place, distance)) => {
+                format!("{user} is at {place} ({distance:.0} m from its center)")
+            }
+            None => format!("{user} isn't at any named place"),
+        };
+        output.push_str(&format!(
+            "\nLast ping: {} ({age}) at {:.5}, {:.5}",
+            fix.at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            fix.lat,
+            fix.lon
+        ));
+        if let Some(accuracy) = fix.accuracy_m {
+            output.push_str(&format!(", accurate to {accuracy:.0} m"));
+        }
+        if let Some(battery) = fix.battery {
+            output.push_str(&format!(", battery {battery}%"));
+        }
+
+        if let Some(hours) = params.get("hours").and_then(|h| h.as_i64()) {
+            let since = Utc::now() - chrono::Duration::hours(hours.clamp(1, 720));
+            match self.store.transitions(&user, since) {
+                Ok(transitions) if transitions.is_empty() => output.push_str(&format!(
+                    "\nNo arrivals or departures in the last {hours} h"
+                )),
+                Ok(transitions) => {
+                    output.push_str(&format!("\nIn the last {hours} h:"));
+                    for t in transitions {
+                        output.push_str(&format!(
+                            "\n- {} {}",
+                            t.at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                            t.describe()
+                        ));
+                    }
+                }
+                Err(e) => output.push_str(&format!("\nError: couldn't read the history: {e}")),
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Fix;
+    use patina_config::{LocationConfig, PlaceConfig};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn says_where_the_only_user_is() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LocationConfig {
+            places: HashMap::from([(
+                "home".to_string(),
+                PlaceConfig {
+                    lat: 48.85,
+                    lon: 2.35,
+                    radius_m: 100.0,
+                },
+            )]),
+            ..Default::default()
+        };
+        let store = Arc::new(LocationStore::open(&dir.path().join("l.sqlite"), &config).unwrap());
+        let tool = LocationTool::new(store.clone());
+        assert_eq!(
+            tool.execute(serde_json::json!({})).await.unwrap(),
+            "No one has shared a location yet."
+        );
+
+        store
+            .record(&Fix {
+                user: "ada".to_string(),
+                lat: 48.8501,
+                lon: 2.35,
+                accuracy_m: Some(8.0),
+                battery: Some(64),
+                at: Utc::now(),
+            })
+            .unwrap();
+        let output = tool
+            .execute(serde_json::json!({ "hours": 24 }))
+            .await
+            .unwrap();
+        assert!(output.starts_with("ada is at home (11 m from its center)"));
+        assert!(output.contains("(just now)"));
+        assert!(output.contains("accurate to 8 m, battery 64%"));
+        assert!(output.ends_with("No arrivals or departures in the last 24 h"));
+    }
+}
@@ -4,16 +4,37 @@ use anyhow::Result;
 use async_trait::async_trait;
 
 use super::Tool;
+use crate::agent::memory::{CurrentProject, PROJECTS_DIR};
 use crate::agent::memory_index::MemoryIndex;
 
 /// Tool that searches memory and history files using FTS5 full-text search.
+/// In a session attached to a project, that project's memory ranks first.
 pub struct MemorySearchTool {
     index: Arc<MemoryIndex>,
+    project: CurrentProject,
+    /// Where project memory is, as the index shows paths.
+    projects_dir: String,
 }
 
 impl MemorySearchTool {
     pub fn new(index: Arc<MemoryIndex>) -> Self {
-        Self { index }
+        Self {
+            index,
+            project: CurrentProject::default(),
+            projects_dir: format!("memory/{PROJECTS_DIR}"),
+        }
+    }
+
+    /// Rank matches in the memory of the turn's project first.
+    pub fn with_project(mut self, project: CurrentProject) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Look for project memory in `dir` instead of `memory/projects`.
+    pub fn with_projects_dir(mut self, dir: String) -> Self {
+        self.projects_dir = dir;
+        self
     }
 }
 
@@ -63,7 +84,12 @@ impl Tool for MemorySearchTool {
             .map(|l| l.clamp(1, 20) as usize)
             .unwrap_or(5);
 
-        let results = self.index.search(&query, limit)?;
+        let prefix = self
+            .project
+            .get()
+            .map(|p| format!("{}/{p}/", self.projects_dir))
+            .unwrap_or_default();
+        let results = self.index.search_preferring(&query, limit, &prefix)?;
 
         if results.is_empty() {
             return Ok("No results found.".into());
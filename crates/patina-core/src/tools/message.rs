@@ -1,12 +1,17 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 use anyhow::Result;
 use async_trait::async_trait;
-use tokio::sync::{broadcast, RwLock};
+use chrono::{DateTime, FixedOffset};
+use patina_config::MessageToolConfig;
+use tokio::sync::{broadcast, Mutex, RwLock};
 use tracing::info;
 
-use crate::bus::OutboundMessage;
+use crate::bus::{ChannelCapabilities, OutboundMessage};
+use crate::cron::service::CronService;
+use crate::drafts::{Draft, DraftStore};
+use crate::locale::UserLocale;
 use crate::tools::Tool;
 
 /// Tool for sending messages to chat channels.
@@ -14,6 +19,17 @@ pub struct MessageTool {
     outbound_tx: broadcast::Sender<OutboundMessage>,
     default_channel: Arc<RwLock<String>>,
     default_chat_id: Arc<RwLock<String>>,
+    /// Reads `send_at` times and shows scheduled ones on the user's clock.
+    locale: RwLock<UserLocale>,
+    scheduler: OnceLock<Arc<Mutex<CronService>>>,
+    channels: OnceLock<HashMap<String, ChannelCapabilities>>,
+    drafts: OnceLock<Drafts>,
+}
+
+/// Which sends are held for review, and where they wait.
+struct Drafts {
+    store: Mutex<DraftStore>,
+    config: MessageToolConfig,
 }
 
 impl MessageTool {
@@ -22,14 +38,45 @@ impl MessageTool {
             outbound_tx,
             default_channel: Arc::new(RwLock::new(String::new())),
             default_chat_id: Arc::new(RwLock::new(String::new())),
+            locale: RwLock::new(UserLocale::default()),
+            scheduler: OnceLock::new(),
+            channels: OnceLock::new(),
+            drafts: OnceLock::new(),
         }
     }
 
+    /// Hold messages to other chats on `config.draft_channels` in `store`
+    /// until the owner approves them. Can only be called once.
+    pub fn set_drafts(&self, store: DraftStore, config: &MessageToolConfig) {
+        let _ = self.drafts.set(Drafts {
+            store: Mutex::new(store),
+            config: config.clone(),
+        });
+    }
+
+    /// Set the channels messages can go to, with what each can display.
+    /// Once set, sends to any other channel are refused. Can only be
+    /// called once.
+    pub fn set_channels(&self, channels: HashMap<String, ChannelCapabilities>) {
+        let _ = self.channels.set(channels);
+    }
+
+    /// Set the cron service that holds messages sent with `send_at`. Can
+    /// only be called once.
+    pub fn set_scheduler(&self, service: Arc<Mutex<CronService>>) {
+        let _ = self.scheduler.set(service);
+    }
+
     /// Update the default routing context for this tool.
     pub async fn set_context(&self, channel: &str, chat_id: &str) {
         *self.default_channel.write().await = channel.to_string();
         *self.default_chat_id.write().await = chat_id.to_string();
     }
+
+    /// Set the timezone and locale of the user being talked to.
+    pub async fn set_locale(&self, locale: &UserLocale) {
+        *self.locale.write().await = locale.clone();
+    }
 }
 
 #[async_trait]
@@ -41,17 +88,34 @@ impl Tool for MessageTool {
     fn description(&self) -> &str {
         "Send a message to the user via a chat channel. Use this to proactively send messages \
          or notifications. The message will be delivered to the current channel/chat unless \
-         overridden with explicit channel and chat_id parameters."
+         overridden with explicit channel and chat_id parameters.\n\
+         To send it later (e.g. a reminder), set send_at; the text is delivered as-is at that \
+         time. Use action 'list' to see messages waiting to be sent and 'cancel' with their ID \
+         to withdraw one. Messages to other chats may be held for the user's approval; \
+         the result says when."
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
         serde_json::json!({
             "type": "object",
             "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["send", "list", "cancel"],
+                    "description": "'send' (default), 'list' scheduled messages, or 'cancel' one"
+                },
                 "content": {
                     "type": "string",
                     "description": "The message content to send"
                 },
+                "send_at": {
+                    "type": "string",
+                    "description": "When to send it instead of now, on the user's clock (e.g. '2025-01-15 18:00'), or RFC 3339 with an offset"
+                },
+                "id": {
+                    "type": "string",
+                    "description": "Scheduled message ID (required for 'cancel')"
+                },
                 "channel": {
                     "type": "string",
                     "description": "Target channel (e.g. 'telegram', 'cli'). Defaults to current channel."
@@ -61,11 +125,24 @@ impl Tool for MessageTool {
                     "description": "Target chat ID. Defaults to current chat."
                 }
             },
-            "required": ["content"]
+            "required": []
         })
     }
 
     async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        match params.get("action").and_then(|v| v.as_str()) {
+            None | Some("send") => self.handle_send(&params).await,
+            Some("list") => self.handle_list().await,
+            Some("cancel") => self.handle_cancel(&params).await,
+            Some(action) => Ok(format!(
+                "Unknown action: {action}. Use 'send', 'list', or 'cancel'."
+            )),
+        }
+    }
+}
+
+impl MessageTool {
+    async fn handle_send(&self, params: &serde_json::Value) -> Result<String> {
         let content = params
             .get("content")
             .and_then(|v| v.as_str())
@@ -87,9 +164,41 @@ impl Tool for MessageTool {
             );
         }
 
+        if let Some(channels) = self.channels.get() {
+            if !channels.contains_key(&channel) {
+                let mut names: Vec<&str> = channels.keys().map(String::as_str).collect();
+                names.sort();
+                return Ok(format!(
+                    "Error: Unknown channel '{channel}'. Available: {}",
+                    names.join(", ")
+                ));
+            }
+        }
+
+        let send_at = match params.get("send_at").and_then(|v| v.as_str()) {
+            Some(send_at) => match self.send_time(send_at).await {
+                Ok(at) => Some(at),
+                Err(e) => return Ok(e),
+            },
+            None => None,
+        };
+
+        if let Some(drafts) = self.drafts_for(&channel, &chat_id).await {
+            return self
+                .hold(drafts, content, &channel, &chat_id, send_at)
+                .await;
+        }
+        match send_at {
+            Some(at) => self.schedule(at, content, &channel, &chat_id).await,
+            None => Ok(self.send_now(content, &channel, &chat_id)),
+        }
+    }
+
+    fn send_now(&self, content: &str, channel: &str, chat_id: &str) -> String {
+        let caps = self.channels.get().and_then(|c| c.get(channel));
         let msg = OutboundMessage {
-            channel: channel.clone(),
-            chat_id: chat_id.clone(),
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
             content: content.to_string(),
             reply_to: None,
             metadata: HashMap::new(),
@@ -98,15 +207,340 @@ impl Tool for MessageTool {
         match self.outbound_tx.send(msg) {
             Ok(_) => {
                 info!("Message sent to {channel}:{chat_id}");
-                Ok(format!("Message sent to {channel}:{chat_id}"))
+                let note = match caps.and_then(|c| c.max_message_len) {
+                    Some(max) if content.len() > max => format!(
+                        " (over {channel}'s {max}-character limit, so it arrives in {} parts)",
+                        content.len().div_ceil(max)
+                    ),
+                    _ => String::new(),
+                };
+                format!("Message sent to {channel}:{chat_id}{note}")
             }
             Err(_) => {
                 // No receivers (e.g. CLI mode) — message is logged but not delivered
                 info!("Message logged (no active channel receivers): {channel}:{chat_id}");
+                format!("Message logged to {channel}:{chat_id} (no active channel receivers)")
+            }
+        }
+    }
+
+    /// Read a `send_at` on the user's clock. Errors are the tool's reply.
+    async fn send_time(&self, send_at: &str) -> Result<DateTime<FixedOffset>, String> {
+        let at = self
+            .locale
+            .read()
+            .await
+            .parse(send_at)
+            .map_err(|e| format!("Error: invalid send_at: {e}"))?;
+        if at <= chrono::Utc::now() {
+            return Err(format!("Error: send_at '{send_at}' is in the past."));
+        }
+        Ok(at)
+    }
+
+    async fn schedule(
+        &self,
+        at: DateTime<FixedOffset>,
+        content: &str,
+        channel: &str,
+        chat_id: &str,
+    ) -> Result<String> {
+        let Some(scheduler) = self.scheduler.get() else {
+            return Ok("Error: Scheduled messages are not available here.".into());
+        };
+        let locale = self.locale.read().await.clone();
+        let mut service = scheduler.lock().await;
+        match service.schedule_message(at.timestamp_millis(), content, channel, chat_id) {
+            Ok(job) => {
+                info!("Message scheduled for {channel}:{chat_id} at {at}");
                 Ok(format!(
-                    "Message logged to {channel}:{chat_id} (no active channel receivers)"
+                    "Message scheduled for {channel}:{chat_id} at {} (ID: {}).",
+                    locale.format(at.to_utc()),
+                    job.id
                 ))
             }
+            Err(e) => Ok(format!("Error: failed to schedule message: {e}")),
         }
     }
+
+    /// The draft policy, if a send to `channel:chat_id` has to wait for
+    /// approval. Replies in the current chat never do.
+    async fn drafts_for(&self, channel: &str, chat_id: &str) -> Option<&Drafts> {
+        let drafts = self.drafts.get()?;
+        if !drafts.config.drafts(channel) {
+            return None;
+        }
+        let current = *self.default_channel.read().await == channel
+            && *self.default_chat_id.read().await == chat_id;
+        (!current).then_some(drafts)
+    }
+
+    /// Save a message as a draft and show it to the owner.
+    async fn hold(
+        &self,
+        drafts: &Drafts,
+        content: &str,
+        channel: &str,
+        chat_id: &str,
+        send_at: Option<DateTime<FixedOffset>>,
+    ) -> Result<String> {
+        let origin_channel = self.default_channel.read().await.clone();
+        let origin_chat = self.default_chat_id.read().await.clone();
+        let draft = Draft {
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            content: content.to_string(),
+            send_at_ms: send_at.map(|at| at.timestamp_millis()),
+            origin: format!("{origin_channel}:{origin_chat}"),
+            ..Default::default()
+        };
+        let draft = match drafts.store.lock().await.add(draft) {
+            Ok(draft) => draft,
+            Err(e) => return Ok(format!("Error: failed to save draft: {e}")),
+        };
+        info!("Held message to {channel}:{chat_id} as draft {}", draft.id);
+
+        let (review_channel, review_chat) = drafts
+            .config
+            .review_chat
+            .as_deref()
+            .and_then(|r| r.split_once(':'))
+            .map(|(c, id)| (c.to_string(), id.to_string()))
+            .unwrap_or((origin_channel, origin_chat));
+        let shown = if review_channel.is_empty() || review_chat.is_empty() {
+            "they can see it with /draft".to_string()
+        } else {
+            let notice = review_notice(&draft, &*self.locale.read().await);
+            let _ = self.outbound_tx.send(OutboundMessage {
+                channel: review_channel.clone(),
+                chat_id: review_chat.clone(),
+                content: notice,
+                reply_to: None,
+                metadata: HashMap::new(),
+            });
+            format!("it was shown to them in {review_channel}:{review_chat}")
+        };
+        Ok(format!(
+            "Not sent yet: the message to {channel}:{chat_id} is held as draft {} until the \
+             owner approves it ({shown}). Tell the user it's waiting for their review.",
+            draft.id
+        ))
+    }
+
+    /// Messages held for review, oldest first.
+    pub async fn drafts(&self) -> Vec<Draft> {
+        match self.drafts.get() {
+            Some(drafts) => drafts.store.lock().await.list().to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Send a held draft, with `content` in place of the agent's text if
+    /// given. Returns what happened, or `None` if there's no such draft.
+    pub async fn approve_draft(&self, id: &str, content: Option<&str>) -> Result<Option<String>> {
+        let Some(drafts) = self.drafts.get() else {
+            return Ok(None);
+        };
+        let Some(mut draft) = drafts.store.lock().await.take(id)? else {
+            return Ok(None);
+        };
+        if let Some(content) = content {
+            draft.content = content.to_string();
+        }
+        let later = draft
+            .send_at_ms
+            .filter(|ms| *ms > chrono::Utc::now().timestamp_millis())
+            .and_then(DateTime::from_timestamp_millis);
+        let result = match later {
+            Some(at) => {
+                let at = self.locale.read().await.localize(at);
+                self.schedule(at, &draft.content, &draft.channel, &draft.chat_id)
+                    .await?
+            }
+            None => self.send_now(&draft.content, &draft.channel, &draft.chat_id),
+        };
+        Ok(Some(result))
+    }
+
+    /// Drop a held draft without sending it.
+    pub async fn reject_draft(&self, id: &str) -> Result<Option<Draft>> {
+        match self.drafts.get() {
+            Some(drafts) => drafts.store.lock().await.take(id),
+            None => Ok(None),
+        }
+    }
+
+    async fn handle_list(&self) -> Result<String> {
+        let Some(scheduler) = self.scheduler.get() else {
+            return Ok("No scheduled messages.".into());
+        };
+        let locale = self.locale.read().await.clone();
+        let mut service = scheduler.lock().await;
+        let jobs = service.scheduled_messages();
+        if jobs.is_empty() {
+            return Ok("No scheduled messages.".into());
+        }
+        let mut output = String::from("Scheduled messages:\n");
+        for job in jobs {
+            let when = job
+                .state
+                .next_run_at_ms
+                .map(|ms| locale.format_ms(ms))
+                .unwrap_or_else(|| "N/A".to_string());
+            output.push_str(&format!(
+                "  [{}] {} to {}:{}: {}\n",
+                job.id,
+                when,
+                job.payload.channel.as_deref().unwrap_or("?"),
+                job.payload.to.as_deref().unwrap_or("?"),
+                job.payload.message
+            ));
+        }
+        Ok(output)
+    }
+
+    async fn handle_cancel(&self, params: &serde_json::Value) -> Result<String> {
+        let id = params
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: id"))?;
+        let Some(scheduler) = self.scheduler.get() else {
+            return Ok(format!("Scheduled message {id} not found."));
+        };
+        let mut service = scheduler.lock().await;
+        // Only messages: cron jobs are removed with the cron tool
+        if !service.scheduled_messages().iter().any(|j| j.id == id) {
+            return Ok(format!("Scheduled message {id} not found."));
+        }
+        service.remove_job(id);
+        Ok(format!("Scheduled message {id} cancelled."))
+    }
+}
+
+/// The message that asks the owner to review `draft`.
+fn review_notice(draft: &Draft, locale: &UserLocale) -> String {
+    let when = draft
+        .send_at_ms
+        .map(|ms| format!(", to go out {}", locale.format_ms(ms)))
+        .unwrap_or_default();
+    format!(
+        "Draft {id} for {}:{}{when}:\n\n{}\n\n/draft send {id} sends it, /draft edit {id} <text> \
+         sends your version instead, and /draft reject {id} drops it.",
+        draft.channel,
+        draft.chat_id,
+        draft.content,
+        id = draft.id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_checks_channel_capabilities() {
+        let (tx, _rx) = broadcast::channel(4);
+        let tool = MessageTool::new(tx);
+        let caps = ChannelCapabilities {
+            max_message_len: Some(10),
+            ..Default::default()
+        };
+        tool.set_channels(HashMap::from([("telegram".to_string(), caps)]));
+
+        let sent = |channel: &str, content: &str| serde_json::json!({"channel": channel, "chat_id": "1", "content": content});
+        let result = tool.execute(sent("discord", "hi")).await.unwrap();
+        assert_eq!(
+            result,
+            "Error: Unknown channel 'discord'. Available: telegram"
+        );
+        let result = tool.execute(sent("telegram", "hi")).await.unwrap();
+        assert_eq!(result, "Message sent to telegram:1");
+        let result = tool
+            .execute(sent("telegram", "twenty-five characters..."))
+            .await
+            .unwrap();
+        assert!(result.ends_with("arrives in 3 parts)"), "{result}");
+    }
+
+    #[tokio::test]
+    async fn test_messages_to_other_chats_wait_for_approval() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, mut rx) = broadcast::channel(8);
+        let tool = MessageTool::new(tx);
+        tool.set_drafts(
+            DraftStore::load(&dir.path().join("drafts.json")),
+            &MessageToolConfig {
+                draft_channels: vec!["slack".into()],
+                review_chat: None,
+            },
+        );
+        tool.set_context("slack", "owner").await;
+
+        // The current chat and channels without a draft policy go straight out
+        let result = tool
+            .execute(serde_json::json!({"content": "hi"}))
+            .await
+            .unwrap();
+        assert_eq!(result, "Message sent to slack:owner");
+        rx.recv().await.unwrap();
+
+        let result = tool
+            .execute(serde_json::json!({"chat_id": "C42", "content": "Running late"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("Not sent yet"), "{result}");
+        let notice = rx.recv().await.unwrap();
+        assert_eq!(notice.chat_id, "owner");
+        let draft = tool.drafts().await.remove(0);
+        assert!(notice
+            .content
+            .contains(&format!("/draft send {}", draft.id)));
+        assert_eq!(draft.origin, "slack:owner");
+
+        let result = tool
+            .approve_draft(&draft.id, Some("Running 10 minutes late"))
+            .await
+            .unwrap();
+        assert_eq!(result.as_deref(), Some("Message sent to slack:C42"));
+        let sent = rx.recv().await.unwrap();
+        assert_eq!(
+            (sent.chat_id.as_str(), sent.content.as_str()),
+            ("C42", "Running 10 minutes late")
+        );
+        assert!(tool.drafts().await.is_empty());
+        assert_eq!(tool.approve_draft(&draft.id, None).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_send_at_uses_user_clock() {
+        let dir = tempfile::tempdir().unwrap();
+        let (inbound_tx, _inbound_rx) = tokio::sync::mpsc::channel(1);
+        let cron = CronService::new(dir.path().join("jobs.json"), inbound_tx);
+        let (tx, _rx) = broadcast::channel(4);
+        let tool = MessageTool::new(tx);
+        tool.set_scheduler(Arc::new(Mutex::new(cron)));
+        tool.set_context("telegram", "1").await;
+        tool.set_locale(&UserLocale::new(Some("Asia/Tokyo"), Some("en-US")))
+            .await;
+
+        let result = tool
+            .execute(serde_json::json!({"content": "stretch", "send_at": "2099-01-01 09:00"}))
+            .await
+            .unwrap();
+        assert!(
+            result
+                .starts_with("Message scheduled for telegram:1 at Jan 1, 2099 9:00 AM (UTC+09:00)"),
+            "{result}"
+        );
+        let list = tool
+            .execute(serde_json::json!({"action": "list"}))
+            .await
+            .unwrap();
+        assert!(list.contains("Jan 1, 2099 9:00 AM (UTC+09:00) to telegram:1: stretch"));
+        let result = tool
+            .execute(serde_json::json!({"content": "x", "send_at": "soon"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("Error: invalid send_at: could not parse 'soon'"));
+    }
 }
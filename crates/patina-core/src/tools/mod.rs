@@ -1,17 +1,31 @@
+pub mod ask_user;
+pub mod calc;
+pub mod contacts;
 pub mod cron;
+#[cfg(feature = "desktop")]
+pub mod desktop;
 pub mod filesystem;
+pub mod knowledge;
+pub mod location;
 pub mod memory_search;
 pub mod message;
+pub mod models;
 pub mod shell;
 pub mod spawn;
 pub mod task;
+pub mod transcribe;
+pub mod translate;
+pub mod usage;
 pub mod web;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
 
+use crate::error::ToolError;
+
 /// Trait for tools callable by the LLM agent.
 #[async_trait]
 pub trait Tool: Send + Sync {
@@ -21,6 +35,26 @@ pub trait Tool: Send + Sync {
     async fn execute(&self, params: serde_json::Value) -> Result<String>;
 }
 
+/// Wrapper to register an `Arc<T: Tool>` in the ToolRegistry (which expects
+/// `Box<dyn Tool>`), for tools the caller also keeps a handle to.
+pub struct ArcToolWrapper<T: Tool>(pub Arc<T>);
+
+#[async_trait]
+impl<T: Tool + 'static> Tool for ArcToolWrapper<T> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+    fn description(&self) -> &str {
+        self.0.description()
+    }
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.0.parameters_schema()
+    }
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        self.0.execute(params).await
+    }
+}
+
 /// Registry of available tools.
 pub struct ToolRegistry {
     tools: HashMap<String, Box<dyn Tool>>,
@@ -62,7 +96,14 @@ impl ToolRegistry {
             .collect()
     }
 
-    pub async fn execute(&self, name: &str, params: serde_json::Value) -> Result<String> {
+    /// Run a tool. Invalid parameters come back as `Ok("Error: ...")` for
+    /// the model to correct; an unknown tool or a failure the tool raised
+    /// is a [`ToolError`].
+    pub async fn execute(
+        &self,
+        name: &str,
+        params: serde_json::Value,
+    ) -> Result<String, ToolError> {
         match self.tools.get(name) {
             Some(tool) => {
                 let errors = validate_params(&params, &tool.parameters_schema());
@@ -73,9 +114,14 @@ impl ToolRegistry {
                         errors.join("; ")
                     ));
                 }
-                tool.execute(params).await
+                tool.execute(params)
+                    .await
+                    .map_err(|source| ToolError::Failed {
+                        tool: name.to_string(),
+                        source,
+                    })
             }
-            None => anyhow::bail!("unknown tool: {name}"),
+            None => Err(ToolError::NotFound(name.to_string())),
         }
     }
 }
@@ -88,7 +134,10 @@ impl Default for ToolRegistry {
 
 /// Validate tool parameters against a JSON schema.
 /// Returns a list of validation error strings (empty if valid).
-fn validate_params(params: &serde_json::Value, schema: &serde_json::Value) -> Vec<String> {
+pub(crate) fn validate_params(
+    params: &serde_json::Value,
+    schema: &serde_json::Value,
+) -> Vec<String> {
     let mut errors = Vec::new();
     validate_value(params, schema, "", &mut errors);
     errors
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::ollama::{format_size, same_model, LoadedModel, LocalModel, OllamaClient};
+use crate::tools::Tool;
+
+/// Tool for managing models on the configured Ollama host.
+pub struct ModelsTool {
+    client: OllamaClient,
+    /// Ollama models referenced by `agents.models`, as (tier, model).
+    configured: Vec<(String, String)>,
+}
+
+impl ModelsTool {
+    pub fn new(client: OllamaClient, configured: Vec<(String, String)>) -> Self {
+        Self { client, configured }
+    }
+}
+
+/// Render installed models, marking loaded ones and configured models that
+/// are missing.
+fn render_models(
+    installed: &[LocalModel],
+    loaded: &[LoadedModel],
+    configured: &[(String, String)],
+) -> String {
+    let mut out = if installed.is_empty() {
+        "No models installed.".to_string()
+    } else {
+        format!("Installed models ({}):", installed.len())
+    };
+    for m in installed {
+        out.push_str(&format!("\n- {} ({}", m.name, format_size(m.size)));
+        for detail in [&m.details.parameter_size, &m.details.quantization_level] {
+            if !detail.is_empty() {
+                out.push_str(&format!(", {detail}"));
+            }
+        }
+        out.push(')');
+        if let Some(l) = loaded.iter().find(|l| same_model(&l.name, &m.name)) {
+            out.push_str(" [loaded");
+            if !l.expires_at.is_empty() {
+                out.push_str(&format!(" until {}", l.expires_at));
+            }
+            out.push(']');
+        }
+        let tiers: Vec<&str> = configured
+            .iter()
+            .filter(|(_, model)| same_model(model, &m.name))
+            .map(|(tier, _)| tier.as_str())
+            .collect();
+        if !tiers.is_empty() {
+            out.push_str(&format!(" used by: {}", tiers.join(", ")));
+        }
+    }
+
+    let missing: Vec<String> = configured
+        .iter()
+        .filter(|(_, model)| !installed.iter().any(|m| same_model(&m.name, model)))
+        .map(|(tier, model)| format!("{model} (tier '{tier}')"))
+        .collect();
+    if !missing.is_empty() {
+        out.push_str(&format!(
+            "\n\nConfigured but not installed: {}",
+            missing.join(", ")
+        ));
+    }
+    out
+}
+
+#[async_trait]
+impl Tool for ModelsTool {
+    fn name(&self) -> &str {
+        "models"
+    }
+
+    fn description(&self) -> &str {
+        "Manage local models on the Ollama host. Use action 'list' to see installed and loaded \
+         models, 'pull' to download a model (can take several minutes for large models), or \
+         'keep_alive' to load a model and keep it in memory for a duration ('30m', '2h', '-1' \
+         for forever, '0' to unload now)."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "action": {
+                    "type": "string",
+                    "enum": ["list", "pull", "keep_alive"],
+                    "description": "The action to perform"
+                },
+                "model": {
+                    "type": "string",
+                    "description": "Model name, e.g. 'llama3.1:8b' (required for 'pull' and 'keep_alive')"
+                },
+                "duration": {
+                    "type": "string",
+                    "description": "How long to keep the model loaded (for 'keep_alive')"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let action = params.get("action").and_then(|v| v.as_str()).unwrap_or("");
+        let model = params
+            .get("model")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .unwrap_or("");
+        let base = self.client.base_url();
+
+        match action {
+            "list" => {
+                let installed = match self.client.list_models().await {
+                    Ok(m) => m,
+                    Err(e) => return Ok(format!("Error: Ollama at {base} is unreachable: {e}")),
+                };
+                let loaded = self.client.loaded_models().await.unwrap_or_default();
+                Ok(render_models(&installed, &loaded, &self.configured))
+            }
+            "pull" => {
+                if model.is_empty() {
+                    return Ok("Error: 'model' is required for pull".to_string());
+                }
+                match self.client.pull(model, None).await {
+                    Ok(()) => Ok(format!("Pulled {model}.")),
+                    Err(e) => Ok(format!("Error: {e}")),
+                }
+            }
+            "keep_alive" => {
+                let Some(duration) = params.get("duration").and_then(|v| v.as_str()) else {
+                    return Ok("Error: 'duration' is required for keep_alive".to_string());
+                };
+                if model.is_empty() {
+                    return Ok("Error: 'model' is required for keep_alive".to_string());
+                }
+                match self.client.set_keep_alive(model, duration).await {
+                    Ok(()) if duration.trim() == "0" => Ok(format!("Unloaded {model}.")),
+                    Ok(()) => Ok(format!("Loaded {model} with keep_alive {duration}.")),
+                    Err(e) => Ok(format!("Error: keep_alive for {model} failed: {e}")),
+                }
+            }
+            other => Ok(format!("Error: Unknown action '{other}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(name: &str, size: u64) -> LocalModel {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "size": size,
+            "details": {"parameter_size": "8.0B", "quantization_level": "Q4_K_M"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_models() {
+        let installed = vec![
+            local("llama3.1:8b", 4_920_753_328),
+            local("nomic-embed-text:latest", 274_302_450),
+        ];
+        let loaded = vec![LoadedModel {
+            name: "llama3.1:8b".into(),
+            size_vram: 0,
+            expires_at: "2026-03-18T15:30:00Z".into(),
+        }];
+        let configured = vec![
+            ("default".to_string(), "llama3.1:8b".to_string()),
+            ("coding".to_string(), "qwen2.5-coder".to_string()),
+        ];
+
+        let out = render_models(&installed, &loaded, &configured);
+        assert!(out.starts_with("Installed models (2):"));
+        assert!(out.contains(
+            "- llama3.1:8b (4.9 GB, 8.0B, Q4_K_M) [loaded until 2026-03-18T15:30:00Z] used by: default"
+        ));
+        assert!(out.contains("- nomic-embed-text:latest (274 MB, 8.0B, Q4_K_M)\n"));
+        assert!(out.ends_with("Configured but not installed: qwen2.5-coder (tier 'coding')"));
+    }
+}
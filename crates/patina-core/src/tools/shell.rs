@@ -8,13 +8,37 @@ use tokio::process::Command;
 
 use super::Tool;
 
+/// Build a command that runs `command` through `shell`, or the platform
+/// default (`sh` on Unix, `cmd` on Windows). `bash`, `zsh`, `cmd`,
+/// `powershell` and `pwsh` get their own flags; anything else is treated
+/// like `sh`.
+pub fn shell_command(shell: Option<&str>, command: &str) -> Command {
+    let shell = shell
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(if cfg!(windows) { "cmd" } else { "sh" });
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(shell)
+        .to_lowercase();
+    let mut cmd = Command::new(shell);
+    match name.as_str() {
+        "cmd" => cmd.arg("/C"),
+        "powershell" | "pwsh" => cmd.args(["-NoProfile", "-NonInteractive", "-Command"]),
+        _ => cmd.arg("-c"),
+    };
+    cmd.arg(command);
+    cmd
+}
+
 /// Shell command execution tool with safety guards.
 pub struct ExecTool {
     timeout: Duration,
     working_dir: PathBuf,
     deny_patterns: Vec<Regex>,
     restrict_to_workspace: bool,
-    posix_path_re: Regex,
+    abs_path_re: Regex,
+    shell: Option<String>,
 }
 
 impl ExecTool {
@@ -33,17 +57,27 @@ impl ExecTool {
         .filter_map(|p| Regex::new(p).ok())
         .collect();
 
-        let posix_path_re = Regex::new(r#"(?:^|[\s|>])(/[^\s"'>]+)"#).unwrap();
+        // `/etc/passwd`, and on Windows `C:\Users` or `D:/data`
+        let abs_path_re =
+            Regex::new(r#"(?:^|[\s|>])(/[^\s"'>]+|[A-Za-z]:[\\/][^\s"'>]*)"#).unwrap();
 
         Self {
             timeout: Duration::from_secs(timeout_secs),
             working_dir,
             deny_patterns,
             restrict_to_workspace,
-            posix_path_re,
+            abs_path_re,
+            shell: None,
         }
     }
 
+    /// Run commands through `shell` instead of the platform default. See
+    /// [`shell_command`].
+    pub fn with_shell(mut self, shell: Option<String>) -> Self {
+        self.shell = shell;
+        self
+    }
+
     fn guard_command(&self, command: &str, cwd: &Path) -> Option<String> {
         let lower = command.to_lowercase();
 
@@ -67,7 +101,7 @@ impl ExecTool {
             let cwd_resolved = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
 
             // Check for absolute paths outside workspace
-            for cap in self.posix_path_re.captures_iter(command) {
+            for cap in self.abs_path_re.captures_iter(command) {
                 if let Some(m) = cap.get(1) {
                     let p = Path::new(m.as_str());
                     if p.is_absolute() {
@@ -133,9 +167,7 @@ impl Tool for ExecTool {
 
         let result = tokio::time::timeout(
             self.timeout,
-            Command::new("sh")
-                .arg("-c")
-                .arg(command)
+            shell_command(self.shell.as_deref(), command)
                 .current_dir(&cwd)
                 .output(),
         )
@@ -306,8 +338,9 @@ mod tests {
     #[tokio::test]
     async fn test_exec_no_output() {
         let tool = make_tool(false);
+        let command = if cfg!(windows) { "rem" } else { "true" };
         let result = tool
-            .execute(serde_json::json!({"command": "true"}))
+            .execute(serde_json::json!({ "command": command }))
             .await
             .unwrap();
         assert_eq!(result, "(no output)");
@@ -319,13 +352,68 @@ mod tests {
         std::fs::create_dir_all(&dir).ok();
         let tool = ExecTool::new(dir, 1, false); // 1 second timeout
 
+        let command = if cfg!(windows) {
+            "ping -n 11 127.0.0.1 >nul"
+        } else {
+            "sleep 10"
+        };
         let result = tool
-            .execute(serde_json::json!({"command": "sleep 10"}))
+            .execute(serde_json::json!({ "command": command }))
             .await
             .unwrap();
         assert!(result.contains("timed out"));
     }
 
+    #[test]
+    fn test_shell_command_flags() {
+        let args = |shell: Option<&str>| {
+            let cmd = shell_command(shell, "echo hi");
+            let cmd = cmd.as_std();
+            let mut parts = vec![cmd.get_program().to_string_lossy().to_string()];
+            parts.extend(cmd.get_args().map(|a| a.to_string_lossy().to_string()));
+            parts
+        };
+        let default = if cfg!(windows) {
+            ["cmd", "/C", "echo hi"]
+        } else {
+            ["sh", "-c", "echo hi"]
+        };
+        assert_eq!(args(None), default);
+        assert_eq!(args(Some("")), default);
+        assert_eq!(args(Some("/bin/bash")), ["/bin/bash", "-c", "echo hi"]);
+        assert_eq!(args(Some("CMD.EXE")), ["CMD.EXE", "/C", "echo hi"]);
+        assert_eq!(
+            args(Some("pwsh")),
+            [
+                "pwsh",
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                "echo hi"
+            ]
+        );
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_exec_powershell() {
+        let tool = make_tool(false).with_shell(Some("powershell".into()));
+        let result = tool
+            .execute(serde_json::json!({"command": "Write-Output hello; exit 3"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("hello"), "{result}");
+        assert!(result.contains("Exit code: 3"), "{result}");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_guard_blocks_drive_paths_when_restricted() {
+        let tool = make_tool(true);
+        let result = tool.guard_command(r"type C:\Windows\win.ini", &tool.working_dir);
+        assert!(result.unwrap().contains("outside working dir"));
+    }
+
     #[tokio::test]
     async fn test_exec_blocked_command() {
         let tool = make_tool(false);
@@ -54,6 +54,10 @@ impl Tool for SpawnTool {
                 "label": {
                     "type": "string",
                     "description": "Short label for identifying this subagent (e.g. 'research-api', 'fix-tests')"
+                },
+                "webhook": {
+                    "type": "string",
+                    "description": "POST the result as JSON to this webhook target (e.g. an n8n workflow) instead of reporting back here. Only targets the user has configured exist."
                 }
             },
             "required": ["task"]
@@ -68,8 +72,16 @@ impl Tool for SpawnTool {
 
         let label = params.get("label").and_then(|v| v.as_str()).unwrap_or("");
 
-        let channel = self.default_channel.read().await.clone();
-        let chat_id = self.default_chat_id.read().await.clone();
+        // A webhook target takes the result instead of this chat
+        let (channel, chat_id) = match params.get("webhook").and_then(|v| v.as_str()) {
+            Some(target) if !target.trim().is_empty() => {
+                ("webhook".to_string(), target.trim().to_string())
+            }
+            _ => (
+                self.default_channel.read().await.clone(),
+                self.default_chat_id.read().await.clone(),
+            ),
+        };
 
         if channel.is_empty() || chat_id.is_empty() {
             return Ok("Error: No context set for subagent result delivery. Cannot spawn.".into());
@@ -82,9 +94,14 @@ impl Tool for SpawnTool {
                 } else {
                     label.to_string()
                 };
+                let delivery = if channel == "webhook" {
+                    format!("post its result to webhook '{chat_id}'")
+                } else {
+                    "report back".to_string()
+                };
                 Ok(format!(
                     "Subagent '{label_display}' spawned (ID: {task_id}). \
-                     It will work on the task in the background and report back when done."
+                     It will work on the task in the background and {delivery} when done."
                 ))
             }
             Err(e) => Ok(format!("Failed to spawn subagent: {e}")),
@@ -49,6 +49,62 @@ impl TaskTool {
         let _ = self.sessions_dir.set(path);
     }
 
+    /// Move an assigned task to in_progress and spawn a subagent to work on
+    /// it as the assignee's persona. `metadata` is passed through to the
+    /// completion message alongside the task ID. Returns the subagent ID.
+    pub async fn execute_task(
+        &self,
+        task_id: &str,
+        mut metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<String> {
+        let sam = self
+            .subagent_manager
+            .get()
+            .ok_or_else(|| anyhow::anyhow!("no subagent manager configured"))?;
+        let (prompt, title, assignee) = {
+            let mut mgr = self.manager.lock().await;
+            let task = mgr
+                .get(task_id)
+                .ok_or_else(|| anyhow::anyhow!("task {task_id} not found"))?;
+            let assignee = task
+                .assignee
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("task {task_id} has no assignee"))?;
+            let prompt = if task.description.is_empty() {
+                task.title.clone()
+            } else {
+                format!("{}\n\n{}", task.title, task.description)
+            };
+            let title = task.title.clone();
+            mgr.move_task(task_id, TaskStatus::InProgress)?;
+            (prompt, title, assignee)
+        };
+
+        // Resolve persona preamble + model tier
+        let (preamble, model_tier) = match self.persona_store.get() {
+            Some(store) => match store.lock().await.get(&assignee) {
+                Some(persona) => (
+                    Some(persona.preamble.clone()).filter(|s| !s.is_empty()),
+                    Some(persona.model_tier.clone()).filter(|s| !s.is_empty()),
+                ),
+                None => (None, None),
+            },
+            None => (None, None),
+        };
+
+        metadata.insert("task_id".to_string(), serde_json::json!(task_id));
+        sam.spawn_with_persona(
+            &prompt,
+            &title,
+            "task",
+            task_id,
+            preamble.as_deref(),
+            model_tier.as_deref(),
+            metadata,
+        )
+        .await
+    }
+
     /// Update the context for task creation attribution.
     pub async fn set_context(&self, channel: &str, chat_id: &str) {
         *self.default_channel.write().await = channel.to_string();
@@ -414,74 +470,15 @@ impl TaskTool {
         let assignee_display = assignee.unwrap_or("nobody");
 
         if auto_execute && assignee.is_some() {
-            let assignee_key = assignee.unwrap().to_string();
-
-            // Resolve persona preamble + model tier
-            let (preamble, model_tier) = if let Some(store) = self.persona_store.get() {
-                let ps = store.lock().await;
-                match ps.get(&assignee_key) {
-                    Some(persona) => (
-                        if persona.preamble.is_empty() {
-                            None
-                        } else {
-                            Some(persona.preamble.clone())
-                        },
-                        if persona.model_tier.is_empty() {
-                            None
-                        } else {
-                            Some(persona.model_tier.clone())
-                        },
-                    ),
-                    None => (None, None),
-                }
-            } else {
-                (None, None)
-            };
-
-            // Build task prompt from title + description
-            let task_prompt = {
-                let task = mgr.get(task_id).expect("task just assigned");
-                if task.description.is_empty() {
-                    task.title.clone()
-                } else {
-                    format!("{}\n\n{}", task.title, task.description)
-                }
-            };
-            let task_title = mgr.get(task_id).unwrap().title.clone();
-
-            // Move to in_progress
-            let _ = mgr.move_task(task_id, TaskStatus::InProgress);
-
-            // Drop the TaskManager lock before spawning
             drop(mgr);
-
-            // Spawn subagent
-            if let Some(sam) = self.subagent_manager.get() {
-                let mut extra = HashMap::new();
-                extra.insert("task_id".to_string(), serde_json::json!(task_id));
-
-                match sam.spawn_with_persona(
-                    &task_prompt,
-                    &task_title,
-                    "task",
-                    task_id,
-                    preamble.as_deref(),
-                    model_tier.as_deref(),
-                    extra,
-                ).await {
-                    Ok(subagent_id) => Ok(format!(
-                        "Task {task_id} assigned to {assignee_display} and executing (subagent {subagent_id})."
-                    )),
-                    Err(e) => Ok(format!(
-                        "Task {task_id} assigned to {assignee_display} and moved to in_progress, \
-                         but failed to spawn subagent: {e}"
-                    )),
-                }
-            } else {
-                Ok(format!(
-                    "Task {task_id} assigned to {assignee_display} and moved to in_progress. \
-                     (auto_execute unavailable — no subagent manager configured)"
-                ))
+            match self.execute_task(task_id, HashMap::new()).await {
+                Ok(subagent_id) => Ok(format!(
+                    "Task {task_id} assigned to {assignee_display} and executing (subagent {subagent_id})."
+                )),
+                Err(e) => Ok(format!(
+                    "Task {task_id} assigned to {assignee_display} and moved to in_progress, \
+                     but failed to spawn subagent: {e}"
+                )),
             }
         } else {
             drop(mgr);
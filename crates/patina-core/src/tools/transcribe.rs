@@ -0,0 +1,410 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use patina_config::{TranscribeToolConfig, TranscriptionConfig};
+use patina_transcribe::{audio, CloudKeys, TranscribeOptions, Transcriber};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use super::filesystem::resolve_path;
+use super::Tool;
+use crate::usage::{TrackedTranscriber, UsageTracker};
+
+const DEFAULT_MAX_CHARS: usize = 100_000;
+
+/// Tool for transcribing workspace audio/video files or remote media URLs.
+///
+/// The transcription backend is created on first use so `patina agent` doesn't
+/// pay for model loading (or downloading) unless the tool is actually called.
+pub struct TranscribeTool {
+    transcriber: OnceCell<Arc<dyn Transcriber>>,
+    transcription: TranscriptionConfig,
+    keys: CloudKeys,
+    workspace: PathBuf,
+    allowed_dir: Option<PathBuf>,
+    allow_urls: bool,
+    max_download_bytes: u64,
+    usage_tracker: Option<Arc<UsageTracker>>,
+}
+
+impl TranscribeTool {
+    pub fn new(
+        transcription: TranscriptionConfig,
+        keys: CloudKeys,
+        workspace: PathBuf,
+        allowed_dir: Option<PathBuf>,
+        config: &TranscribeToolConfig,
+    ) -> Self {
+        Self {
+            transcriber: OnceCell::new(),
+            transcription,
+            keys,
+            workspace,
+            allowed_dir,
+            allow_urls: config.allow_urls,
+            max_download_bytes: config.max_download_mb.saturating_mul(1024 * 1024),
+            usage_tracker: None,
+        }
+    }
+
+    /// Record the audio transcribed by the backend this tool creates.
+    pub fn with_usage_tracker(mut self, tracker: Arc<UsageTracker>) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+
+    /// Reuse an already-initialized transcriber (e.g. the gateway's voice-note one).
+    pub fn with_transcriber(self, transcriber: Arc<dyn Transcriber>) -> Self {
+        let _ = self.transcriber.set(transcriber);
+        self
+    }
+
+    async fn transcriber(&self) -> Result<Arc<dyn Transcriber>> {
+        self.transcriber
+            .get_or_try_init(|| async {
+                let t =
+                    patina_transcribe::create_transcriber(&self.transcription, self.keys.clone())
+                        .await?;
+                let t: Arc<dyn Transcriber> = Arc::from(t);
+                Ok::<_, anyhow::Error>(match self.usage_tracker {
+                    Some(ref tracker) => Arc::new(TrackedTranscriber::new(t, tracker.clone())),
+                    None => t,
+                })
+            })
+            .await
+            .cloned()
+    }
+
+    /// Resolve a local source, treating relative paths as workspace-relative.
+    fn resolve_local(&self, source: &str) -> std::result::Result<PathBuf, String> {
+        let candidate = if Path::new(source).is_relative() && !source.starts_with('~') {
+            self.workspace.join(source).to_string_lossy().to_string()
+        } else {
+            source.to_string()
+        };
+        resolve_path(&candidate, self.allowed_dir.as_deref())
+    }
+
+    /// Download remote media into `dir`, preferring yt-dlp (handles podcast and
+    /// video pages) and falling back to a direct, size-capped HTTP fetch.
+    async fn download(&self, url: &str, dir: &Path) -> Result<PathBuf> {
+        tokio::fs::create_dir_all(dir).await?;
+        let id = &uuid::Uuid::new_v4().to_string()[..8];
+
+        if audio::yt_dlp_available() {
+            let template = dir.join(format!("{id}.%(ext)s"));
+            let max_mb = (self.max_download_bytes / (1024 * 1024)).max(1);
+            let output = Command::new("yt-dlp")
+                .args([
+                    "--no-playlist",
+                    "--quiet",
+                    "-f",
+                    "bestaudio/best",
+                    "--max-filesize",
+                    &format!("{max_mb}M"),
+                    "-o",
+                    &template.to_string_lossy(),
+                    "--print",
+                    "after_move:filepath",
+                    url,
+                ])
+                .output()
+                .await?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                bail!("yt-dlp failed: {}", stderr.trim());
+            }
+            let path = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .last()
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            if path.is_empty() || !Path::new(&path).exists() {
+                bail!("yt-dlp did not produce a file (it may exceed the size limit)");
+            }
+            return Ok(PathBuf::from(path));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(600))
+            .build()?;
+        let mut resp = client.get(url).send().await?.error_for_status()?;
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+        if !content_type.starts_with("audio/")
+            && !content_type.starts_with("video/")
+            && content_type != "application/octet-stream"
+        {
+            bail!(
+                "URL is not an audio/video file ({content_type}); install yt-dlp to transcribe media pages"
+            );
+        }
+        if resp
+            .content_length()
+            .is_some_and(|len| len > self.max_download_bytes)
+        {
+            bail!(
+                "File exceeds the {} MB download limit",
+                self.max_download_bytes / (1024 * 1024)
+            );
+        }
+
+        let ext = url::Url::parse(url)
+            .ok()
+            .and_then(|u| {
+                Path::new(u.path())
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase())
+            })
+            .or_else(|| {
+                mime_guess::get_mime_extensions_str(&content_type)?
+                    .first()
+                    .map(|e| e.to_string())
+            })
+            .unwrap_or_else(|| "bin".to_string());
+        let path = dir.join(format!("{id}.{ext}"));
+
+        let mut file = tokio::fs::File::create(&path).await?;
+        let mut written: u64 = 0;
+        while let Some(chunk) = resp.chunk().await? {
+            written += chunk.len() as u64;
+            if written > self.max_download_bytes {
+                drop(file);
+                let _ = tokio::fs::remove_file(&path).await;
+                bail!(
+                    "File exceeds the {} MB download limit",
+                    self.max_download_bytes / (1024 * 1024)
+                );
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        Ok(path)
+    }
+}
+
+/// Truncate at a char boundary, noting how much was cut.
+fn truncate_transcript(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    format!(
+        "{cut}\n\n[truncated: showing {max_chars} of {} characters]",
+        text.chars().count()
+    )
+}
+
+#[async_trait]
+impl Tool for TranscribeTool {
+    fn name(&self) -> &str {
+        "transcribe"
+    }
+
+    fn description(&self) -> &str {
+        "Transcribe speech from an audio or video file in the workspace, or from a media URL \
+         (podcast episode, video page) when URL transcription is enabled. Returns the transcript \
+         text for summarizing, quoting, or note-taking."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "source": {
+                    "type": "string",
+                    "description": "Workspace-relative or absolute file path, or an http(s) URL"
+                },
+                "language": {
+                    "type": "string",
+                    "description": "Optional spoken-language hint (ISO 639-1, e.g. 'en'). Omit to auto-detect."
+                },
+                "maxChars": {
+                    "type": "integer",
+                    "description": "Maximum transcript characters to return (default 100000)"
+                }
+            },
+            "required": ["source"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let source = params
+            .get("source")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+        if source.is_empty() {
+            return Ok("Error: 'source' is required".to_string());
+        }
+        let language = params
+            .get("language")
+            .and_then(|v| v.as_str())
+            .and_then(patina_transcribe::language::normalize_language);
+        let max_chars = params
+            .get("maxChars")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_MAX_CHARS)
+            .max(100);
+
+        // Files created here (downloads, extracted audio) are removed afterwards.
+        let mut temp_files: Vec<PathBuf> = Vec::new();
+        let is_url = source.starts_with("http://") || source.starts_with("https://");
+
+        let input = if is_url {
+            if !self.allow_urls {
+                return Ok(
+                    "Error: URL transcription is disabled. Set tools.transcribe.allowUrls to true \
+                     in config, or download the file into the workspace first."
+                        .to_string(),
+                );
+            }
+            let dir = std::env::temp_dir().join("patina-transcribe");
+            info!("Downloading media for transcription: {source}");
+            match self.download(source, &dir).await {
+                Ok(path) => {
+                    temp_files.push(path.clone());
+                    path
+                }
+                Err(e) => return Ok(format!("Error downloading {source}: {e}")),
+            }
+        } else {
+            match self.resolve_local(source) {
+                Ok(path) if path.is_file() => path,
+                Ok(path) => return Ok(format!("Error: file not found: {}", path.display())),
+                Err(e) => return Ok(format!("Error: {e}")),
+            }
+        };
+
+        // Strip video and re-encode downloads so long media fits cloud upload limits.
+        let mut audio_path = input.to_string_lossy().to_string();
+        if (is_url || audio::is_video_file(&audio_path)) && audio::ffmpeg_available() {
+            match audio::extract_audio(&audio_path).await {
+                Ok(extracted) => {
+                    temp_files.push(PathBuf::from(&extracted));
+                    audio_path = extracted;
+                }
+                Err(e) => warn!("Audio extraction failed, using original file: {e}"),
+            }
+        }
+
+        let result = match self.transcriber().await {
+            Ok(t) => {
+                let options = TranscribeOptions {
+                    language,
+                    chat_key: None,
+                };
+                t.transcribe(&audio_path, &options).await
+            }
+            Err(e) => Err(e.context("Transcription unavailable")),
+        };
+
+        for path in &temp_files {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+
+        match result {
+            Ok(transcript) if transcript.text.trim().is_empty() => {
+                Ok(format!("No speech detected in {source}"))
+            }
+            Ok(transcript) => {
+                let lang = transcript.language.as_deref().unwrap_or("unknown");
+                Ok(format!(
+                    "Transcript of {source} (language: {lang}, {} chars):\n\n{}",
+                    transcript.text.chars().count(),
+                    truncate_transcript(&transcript.text, max_chars)
+                ))
+            }
+            Err(e) => Ok(format!("Error transcribing {source}: {e:#}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeTranscriber;
+
+    #[async_trait]
+    impl Transcriber for FakeTranscriber {
+        async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+            Ok(format!("heard {}", Path::new(file_path).display()))
+        }
+    }
+
+    fn tool(workspace: &Path, allow_urls: bool) -> TranscribeTool {
+        let config = TranscribeToolConfig {
+            allow_urls,
+            ..Default::default()
+        };
+        TranscribeTool::new(
+            TranscriptionConfig::default(),
+            CloudKeys::default(),
+            workspace.to_path_buf(),
+            Some(workspace.to_path_buf()),
+            &config,
+        )
+        .with_transcriber(Arc::new(FakeTranscriber))
+    }
+
+    #[tokio::test]
+    async fn transcribes_workspace_relative_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("note.ogg"), b"fake").unwrap();
+        let out = tool(dir.path(), false)
+            .execute(serde_json::json!({"source": "note.ogg", "language": "Spanish"}))
+            .await
+            .unwrap();
+        assert!(
+            out.starts_with("Transcript of note.ogg (language: es"),
+            "{out}"
+        );
+        assert!(out.contains("heard"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_and_outside_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let t = tool(dir.path(), false);
+        let out = t
+            .execute(serde_json::json!({"source": "missing.mp3"}))
+            .await
+            .unwrap();
+        assert!(out.starts_with("Error: file not found"), "{out}");
+        let out = t
+            .execute(serde_json::json!({"source": "/etc/hostname"}))
+            .await
+            .unwrap();
+        assert!(out.contains("outside allowed directory"), "{out}");
+    }
+
+    #[tokio::test]
+    async fn urls_require_opt_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tool(dir.path(), false)
+            .execute(serde_json::json!({"source": "https://example.com/episode.mp3"}))
+            .await
+            .unwrap();
+        assert!(out.contains("allowUrls"), "{out}");
+    }
+
+    #[test]
+    fn truncates_long_transcripts() {
+        assert_eq!(truncate_transcript("short", 100), "short");
+        let out = truncate_transcript(&"é".repeat(150), 100);
+        assert!(out.starts_with(&"é".repeat(100)));
+        assert!(out.ends_with("[truncated: showing 100 of 150 characters]"));
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rig::completion::{CompletionModel, CompletionRequest};
+use rig::message::{AssistantContent, Message, Text, UserContent};
+use rig::OneOrMany;
+
+use crate::agent::model_pool::ModelPool;
+use crate::tools::Tool;
+use crate::usage::{UsageRecord, UsageTracker};
+
+/// Tool that translates text with a dedicated model call, so quoted
+/// documents or messages for someone else can be translated faithfully
+/// without the agent paraphrasing them in its own reply.
+pub struct TranslateTool {
+    models: ModelPool,
+    tier: String,
+    usage_tracker: Option<Arc<UsageTracker>>,
+}
+
+impl TranslateTool {
+    pub fn new(models: ModelPool, tier: impl Into<String>) -> Self {
+        Self {
+            models,
+            tier: tier.into(),
+            usage_tracker: None,
+        }
+    }
+
+    /// Record translation calls in the usage database.
+    pub fn with_usage_tracker(mut self, tracker: Arc<UsageTracker>) -> Self {
+        self.usage_tracker = Some(tracker);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for TranslateTool {
+    fn name(&self) -> &str {
+        "translate"
+    }
+
+    fn description(&self) -> &str {
+        "Translate text into another language. Use it for text the user wants translated \
+         (a document, a message to forward) rather than for your own replies, which should \
+         already be in the user's language."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "description": "Text to translate"
+                },
+                "to": {
+                    "type": "string",
+                    "description": "Target language, e.g. 'Spanish' or 'ja'"
+                },
+                "from": {
+                    "type": "string",
+                    "description": "Source language, if known (detected otherwise)"
+                }
+            },
+            "required": ["text", "to"]
+        })
+    }
+
+    #[allow(deprecated)]
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let text = params
+            .get("text")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: text"))?;
+        let to = params
+            .get("to")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("missing required parameter: to"))?;
+        let from = params
+            .get("from")
+            .and_then(|v| v.as_str())
+            .map(|f| format!(" from {f}"))
+            .unwrap_or_default();
+
+        let preamble = format!(
+            "Translate the user's message{from} into {to}. Keep the meaning, tone, and \
+             formatting (markdown, line breaks, code, names, URLs). Reply with the translation \
+             only, no notes or quotes."
+        );
+        let (model, model_name, provider) = self.models.get(&self.tier);
+        let request = CompletionRequest {
+            preamble: Some(preamble.clone()),
+            chat_history: OneOrMany::one(Message::User {
+                content: OneOrMany::one(UserContent::Text(Text {
+                    text: text.to_string(),
+                })),
+            }),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: Some(0.2),
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: self.models.request_params(provider),
+        };
+
+        self.models.throttle(provider, &request).await;
+        let limit = self.models.timeout(provider);
+        let response = match tokio::time::timeout(limit, model.completion(request)).await {
+            Ok(Ok(r)) => r,
+            Ok(Err(e)) => return Ok(format!("Error: Translation failed: {e}")),
+            Err(_) => {
+                return Ok(format!(
+                    "Error: Translation timed out after {}s",
+                    limit.as_secs()
+                ))
+            }
+        };
+        let translation: String = response
+            .choice
+            .iter()
+            .filter_map(|c| match c {
+                AssistantContent::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        if let Some(tracker) = &self.usage_tracker {
+            let mut record = UsageRecord {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                session_key: String::new(),
+                model: model_name.to_string(),
+                provider: provider.to_string(),
+                agent: "translate".to_string(),
+                input_tokens: response.usage.input_tokens,
+                output_tokens: response.usage.output_tokens,
+                total_tokens: response.usage.total_tokens,
+                cached_input_tokens: response.usage.cached_input_tokens,
+                call_type: "translation".to_string(),
+                ..Default::default()
+            };
+            record.fill_estimates(&format!("{preamble}\n{text}"), &translation);
+            tracker.record(&record);
+        }
+
+        let translation = translation.trim();
+        if translation.is_empty() {
+            return Ok("Error: The model returned an empty translation".into());
+        }
+        Ok(translation.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::replay::{MockCompletionModel, RecordedResponse};
+
+    #[tokio::test]
+    async fn translates_with_a_single_call() {
+        let model = MockCompletionModel::new([RecordedResponse {
+            text: "Hola, mundo.\n".into(),
+            ..Default::default()
+        }]);
+        let tool = TranslateTool::new(model.clone().into_pool(), "translation");
+
+        let result = tool
+            .execute(serde_json::json!({"text": "Hello, world.", "to": "Spanish"}))
+            .await
+            .unwrap();
+        assert_eq!(result, "Hola, mundo.");
+
+        let requests = model.take_requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .preamble
+            .as_deref()
+            .unwrap()
+            .contains("into Spanish"));
+        assert!(requests[0].tools.is_empty());
+
+        // The queue is empty now, so the provider error comes back as text
+        let result = tool
+            .execute(serde_json::json!({"text": "Bye", "to": "French"}))
+            .await
+            .unwrap();
+        assert!(result.starts_with("Error: Translation failed"));
+    }
+}
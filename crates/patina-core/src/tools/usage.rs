@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Utc};
+use patina_config::schema::ModelPricing;
+use tokio::sync::RwLock;
+
+use crate::tools::Tool;
+use crate::usage::{UsageFilter, UsageSummary, UsageTracker};
+
+/// Tool for reporting recorded LLM usage and estimated cost.
+pub struct UsageReportTool {
+    tracker: Arc<UsageTracker>,
+    pricing: HashMap<String, ModelPricing>,
+    default_channel: Arc<RwLock<String>>,
+    default_chat_id: Arc<RwLock<String>>,
+}
+
+impl UsageReportTool {
+    pub fn new(tracker: Arc<UsageTracker>, pricing: HashMap<String, ModelPricing>) -> Self {
+        Self {
+            tracker,
+            pricing,
+            default_channel: Arc::new(RwLock::new(String::new())),
+            default_chat_id: Arc::new(RwLock::new(String::new())),
+        }
+    }
+
+    /// Update the session that `scope: "session"` reports on.
+    pub async fn set_context(&self, channel: &str, chat_id: &str) {
+        *self.default_channel.write().await = channel.to_string();
+        *self.default_chat_id.write().await = chat_id.to_string();
+    }
+
+    async fn session_key(&self) -> String {
+        let ch = self.default_channel.read().await;
+        let ci = self.default_chat_id.read().await;
+        format!("{ch}:{ci}")
+    }
+}
+
+/// Start and end of a period in local time. `None` means unbounded.
+type Bounds = (Option<DateTime<Local>>, Option<DateTime<Local>>);
+
+/// Bounds of a named period ("today", "week", ...) containing `now`.
+fn period_bounds(period: &str, now: DateTime<Local>) -> Option<Bounds> {
+    let midnight = |dt: DateTime<Local>| {
+        Local
+            .from_local_datetime(&dt.date_naive().and_time(NaiveTime::MIN))
+            .earliest()
+            .unwrap_or(dt)
+    };
+    let today = midnight(now);
+    match period {
+        "today" => Some((Some(today), None)),
+        "yesterday" => Some((Some(midnight(today - Duration::hours(12))), Some(today))),
+        "week" => {
+            let days = now.weekday().num_days_from_monday() as i64;
+            Some((Some(midnight(now - Duration::days(days))), None))
+        }
+        "month" => {
+            let days = now.day0() as i64;
+            Some((Some(midnight(now - Duration::days(days))), None))
+        }
+        "all" => Some((None, None)),
+        _ => None,
+    }
+}
+
+fn describe_period(period: &str) -> &str {
+    match period {
+        "week" => "this week",
+        "month" => "this month",
+        "all" => "all time",
+        other => other,
+    }
+}
+
+fn audio_minutes(seconds: f64) -> String {
+    format!("{:.1} min", seconds / 60.0)
+}
+
+/// Render per-model rows as a report, with a total line first.
+fn render_report(heading: &str, rows: &[UsageSummary], priced: bool) -> String {
+    if rows.is_empty() {
+        return format!("{heading}: no LLM calls recorded.");
+    }
+    let calls: u64 = rows.iter().map(|r| r.calls).sum();
+    let input: u64 = rows.iter().map(|r| r.input_tokens).sum();
+    let output: u64 = rows.iter().map(|r| r.output_tokens).sum();
+    let total: u64 = rows.iter().map(|r| r.total_tokens).sum();
+    let audio: f64 = rows.iter().map(|r| r.audio_seconds).sum();
+    let cost: f64 = rows.iter().filter_map(|r| r.estimated_cost).sum();
+    let unpriced: Vec<&str> = rows
+        .iter()
+        .filter(|r| r.estimated_cost.is_none())
+        .map(|r| r.group_key.as_str())
+        .collect();
+
+    let mut out =
+        format!("{heading}: {calls} calls, {total} tokens ({input} input, {output} output)");
+    if audio > 0.0 {
+        out.push_str(&format!(", {} of audio", audio_minutes(audio)));
+    }
+    if priced {
+        out.push_str(&format!(", estimated cost ${cost:.4}"));
+    }
+    out.push_str("\n\nBy model:");
+    for r in rows {
+        out.push_str(&format!(
+            "\n- {}: {} calls, {} tokens",
+            r.group_key, r.calls, r.total_tokens
+        ));
+        if r.audio_seconds > 0.0 {
+            out.push_str(&format!(", {} of audio", audio_minutes(r.audio_seconds)));
+        }
+        if let Some(c) = r.estimated_cost {
+            out.push_str(&format!(", ${c:.4}"));
+        }
+    }
+    if !priced {
+        out.push_str("\n\nNo pricing is configured (agents.pricing), so cost can't be estimated.");
+    } else if !unpriced.is_empty() {
+        out.push_str(&format!(
+            "\n\nNo pricing configured for: {}. Their cost is not included.",
+            unpriced.join(", ")
+        ));
+    }
+    out
+}
+
+#[async_trait]
+impl Tool for UsageReportTool {
+    fn name(&self) -> &str {
+        "usage_report"
+    }
+
+    fn description(&self) -> &str {
+        "Report recorded usage (LLM calls, tokens, transcribed audio) and estimated cost from \
+         the usage database, for this session or everything. Use this whenever the user asks how \
+         much they've used or spent; never estimate these numbers yourself."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "period": {
+                    "type": "string",
+                    "enum": ["today", "yesterday", "week", "month", "all"],
+                    "description": "Time period in local time (default 'today'). 'week' starts Monday, 'month' on the 1st."
+                },
+                "scope": {
+                    "type": "string",
+                    "enum": ["session", "all"],
+                    "description": "'session' for this conversation only, 'all' for every session and agent (default 'all')"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let period = params
+            .get("period")
+            .and_then(|v| v.as_str())
+            .unwrap_or("today");
+        let scope = params
+            .get("scope")
+            .and_then(|v| v.as_str())
+            .unwrap_or("all");
+
+        let Some((from, to)) = period_bounds(period, Local::now()) else {
+            return Ok(format!("Error: Unknown period '{period}'"));
+        };
+        let rfc3339 = |dt: DateTime<Local>| dt.with_timezone(&Utc).to_rfc3339();
+        let session = (scope == "session").then_some(self.session_key().await);
+        let filter = UsageFilter {
+            from: from.map(rfc3339),
+            to: to.map(rfc3339),
+            session: session.clone(),
+            group_by: Some("model".into()),
+            ..Default::default()
+        };
+
+        let rows = match self.tracker.query_summary_with_cost(&filter, &self.pricing) {
+            Ok(rows) => rows,
+            Err(e) => return Ok(format!("Error: Usage query failed: {e}")),
+        };
+        let heading = match session {
+            Some(key) => format!("Usage {} in session {key}", describe_period(period)),
+            None => format!("Usage {}", describe_period(period)),
+        };
+        Ok(render_report(&heading, &rows, !self.pricing.is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::UsageRecord;
+
+    fn record(session_key: &str, model: &str, tokens: u64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            session_key: session_key.into(),
+            model: model.into(),
+            provider: "test".into(),
+            agent: "default".into(),
+            input_tokens: tokens,
+            output_tokens: tokens,
+            total_tokens: tokens * 2,
+            cached_input_tokens: 0,
+            call_type: "chat".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_period_bounds() {
+        // Wednesday afternoon
+        let now = Local.with_ymd_and_hms(2026, 3, 18, 15, 30, 0).unwrap();
+        let day = |d: u32| Local.with_ymd_and_hms(2026, 3, d, 0, 0, 0).unwrap();
+
+        assert_eq!(period_bounds("today", now), Some((Some(day(18)), None)));
+        assert_eq!(
+            period_bounds("yesterday", now),
+            Some((Some(day(17)), Some(day(18))))
+        );
+        assert_eq!(period_bounds("week", now), Some((Some(day(16)), None)));
+        assert_eq!(period_bounds("month", now), Some((Some(day(1)), None)));
+        assert_eq!(period_bounds("all", now), Some((None, None)));
+        assert_eq!(period_bounds("fortnight", now), None);
+    }
+
+    #[tokio::test]
+    async fn test_reports_session_and_total_cost() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracker = Arc::new(UsageTracker::new(&dir.path().join("usage.sqlite")).unwrap());
+        tracker.record(&record("telegram:1", "big", 1_000_000));
+        tracker.record(&record("telegram:1", "local", 500));
+        tracker.record(&record("web:2", "big", 1_000_000));
+
+        let mut pricing = HashMap::new();
+        pricing.insert(
+            "big".to_string(),
+            ModelPricing {
+                input: 3.0,
+                output: 15.0,
+                cached_input: 0.0,
+                ..Default::default()
+            },
+        );
+        let tool = UsageReportTool::new(tracker, pricing);
+        tool.set_context("telegram", "1").await;
+
+        let report = tool
+            .execute(serde_json::json!({"scope": "session"}))
+            .await
+            .unwrap();
+        assert!(report.starts_with(
+            "Usage today in session telegram:1: 2 calls, 2001000 tokens \
+             (1000500 input, 1000500 output), estimated cost $18.0000"
+        ));
+        assert!(report.contains("No pricing configured for: local."));
+
+        let report = tool.execute(serde_json::json!({})).await.unwrap();
+        assert!(report.contains("3 calls"));
+        assert!(report.contains("- big: 2 calls, 4000000 tokens, $36.0000"));
+
+        let report = tool
+            .execute(serde_json::json!({"period": "yesterday"}))
+            .await
+            .unwrap();
+        assert_eq!(report, "Usage yesterday: no LLM calls recorded.");
+    }
+}
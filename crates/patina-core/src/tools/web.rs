@@ -1,3 +1,5 @@
+use std::collections::{HashSet, VecDeque};
+
 use anyhow::Result;
 use async_trait::async_trait;
 use regex::Regex;
@@ -177,6 +179,57 @@ impl WebFetchTool {
             .trim()
             .to_string()
     }
+
+    /// Extract readable text from a fetched response body.
+    ///
+    /// Returns the extracted text and the name of the extractor that produced it.
+    fn extract(
+        &self,
+        body: String,
+        content_type: &str,
+        final_url: &str,
+        extract_mode: &str,
+    ) -> Result<(String, &'static str)> {
+        let extracted = if content_type.contains("application/json") {
+            // JSON content — pretty-print
+            let formatted = match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(v) => serde_json::to_string_pretty(&v).unwrap_or(body),
+                Err(_) => body,
+            };
+            (formatted, "json")
+        } else if content_type.contains("text/html") || looks_like_html(&body) {
+            // HTML content — extract with readability, fall back to regex
+            let parsed_url = url::Url::parse(final_url)
+                .or_else(|_| url::Url::parse("http://localhost"))
+                .map_err(|e| anyhow::anyhow!("failed to parse URL for extraction: {e}"))?;
+            let readability_result =
+                readability::extractor::extract(&mut body.as_bytes(), &parsed_url);
+            match readability_result {
+                Ok(product) if !product.text.trim().is_empty() => {
+                    let text = if extract_mode == "text" {
+                        product.text
+                    } else {
+                        // Use extracted HTML content and convert to markdown
+                        self.to_markdown(&product.content)
+                    };
+                    (text, "readability")
+                }
+                _ => {
+                    // Fallback to regex-based extraction
+                    let text = if extract_mode == "text" {
+                        self.strip_tags(&body)
+                    } else {
+                        self.to_markdown(&body)
+                    };
+                    (text, "regex")
+                }
+            }
+        } else {
+            // Raw text
+            (body, "raw")
+        };
+        Ok(extracted)
+    }
 }
 
 #[async_trait]
@@ -266,51 +319,7 @@ impl Tool for WebFetchTool {
             }
         };
 
-        let (text, extractor) = if content_type.contains("application/json") {
-            // JSON content — pretty-print
-            let formatted = match serde_json::from_str::<serde_json::Value>(&body) {
-                Ok(v) => serde_json::to_string_pretty(&v).unwrap_or(body),
-                Err(_) => body,
-            };
-            (formatted, "json")
-        } else if content_type.contains("text/html")
-            || body.trim_start()[..body.len().min(256)]
-                .to_lowercase()
-                .starts_with("<!doctype")
-            || body.trim_start()[..body.len().min(256)]
-                .to_lowercase()
-                .starts_with("<html")
-        {
-            // HTML content — extract with readability, fall back to regex
-            let parsed_url = url::Url::parse(&final_url)
-                .or_else(|_| url::Url::parse("http://localhost"))
-                .map_err(|e| anyhow::anyhow!("failed to parse URL for extraction: {e}"))?;
-            let readability_result =
-                readability::extractor::extract(&mut body.as_bytes(), &parsed_url);
-            match readability_result {
-                Ok(product) if !product.text.trim().is_empty() => {
-                    let text = if extract_mode == "text" {
-                        product.text
-                    } else {
-                        // Use extracted HTML content and convert to markdown
-                        self.to_markdown(&product.content)
-                    };
-                    (text, "readability")
-                }
-                _ => {
-                    // Fallback to regex-based extraction
-                    let text = if extract_mode == "text" {
-                        self.strip_tags(&body)
-                    } else {
-                        self.to_markdown(&body)
-                    };
-                    (text, "regex")
-                }
-            }
-        } else {
-            // Raw text
-            (body, "raw")
-        };
+        let (text, extractor) = self.extract(body, &content_type, &final_url, extract_mode)?;
 
         let truncated = text.len() > max_chars;
         let text = if truncated {
@@ -330,3 +339,380 @@ impl Tool for WebFetchTool {
         }))?)
     }
 }
+
+/// Requests a crawl may make per page it is allowed to return, so failed,
+/// empty, and duplicate pages can't keep it fetching.
+const FETCHES_PER_PAGE: usize = 2;
+
+/// File extensions that never contain crawlable HTML.
+const SKIP_EXTENSIONS: &[&str] = &[
+    ".pdf", ".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp", ".ico", ".zip", ".gz", ".tar",
+    ".mp3", ".mp4", ".mov", ".css", ".js", ".xml", ".rss",
+];
+
+/// Truncate a string to at most `max` bytes on a char boundary.
+fn truncate_at_boundary(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while end > 0 && !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Normalize a URL for deduplication: drop the fragment and a trailing slash.
+fn normalize_url(url: &url::Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let s = url.to_string();
+    match s.strip_suffix('/') {
+        Some(stripped) if url.path() != "/" => stripped.to_string(),
+        _ => s,
+    }
+}
+
+/// Crawl a site breadth-first from a start URL, following same-domain links.
+pub struct WebCrawlTool {
+    fetcher: WebFetchTool,
+    href_re: Regex,
+}
+
+impl WebCrawlTool {
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            fetcher: WebFetchTool::new(max_chars),
+            href_re: Regex::new(r#"(?i)<a\s+[^>]*href=["']([^"']+)["']"#).unwrap(),
+        }
+    }
+
+    /// Extract followable same-host links from an HTML page.
+    fn extract_links(&self, base: &url::Url, html: &str) -> Vec<url::Url> {
+        let mut links = Vec::new();
+        for caps in self.href_re.captures_iter(html) {
+            let href = html_escape::decode_html_entities(&caps[1]).to_string();
+            let Ok(mut link) = base.join(href.trim()) else {
+                continue;
+            };
+            if !matches!(link.scheme(), "http" | "https") || link.host_str() != base.host_str() {
+                continue;
+            }
+            let path = link.path().to_lowercase();
+            if SKIP_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+                continue;
+            }
+            link.set_fragment(None);
+            links.push(link);
+        }
+        links
+    }
+}
+
+#[async_trait]
+impl Tool for WebCrawlTool {
+    fn name(&self) -> &str {
+        "web_crawl"
+    }
+
+    fn description(&self) -> &str {
+        "Crawl a website starting at a URL, following same-domain links breadth-first. \
+         Returns the readable content of each visited page (deduplicated) as one merged corpus. \
+         Use for multi-page research instead of repeated web_fetch calls."
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {"type": "string", "description": "Start URL"},
+                "maxPages": {"type": "integer", "description": "Maximum pages to fetch (1-20, default 5)", "minimum": 1, "maximum": 20},
+                "maxDepth": {"type": "integer", "description": "Maximum link depth from the start URL (0-3, default 1)", "minimum": 0, "maximum": 3},
+                "pathPrefix": {"type": "string", "description": "Only follow links whose path starts with this prefix (e.g. '/docs')"},
+                "extractMode": {"type": "string", "enum": ["markdown", "text"], "default": "markdown"},
+                "maxChars": {"type": "integer", "description": "Total character budget shared across all pages", "minimum": 100}
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, params: serde_json::Value) -> Result<String> {
+        let url = params
+            .get("url")
+            .and_then(|u| u.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        if url.is_empty() {
+            return Ok("Error: url is required".into());
+        }
+
+        let start = match url::Url::parse(&url) {
+            Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+            Ok(u) => {
+                return Ok(serde_json::to_string_pretty(&serde_json::json!({
+                    "error": format!("Only http/https allowed, got '{}'", u.scheme()),
+                    "url": url
+                }))?);
+            }
+            Err(e) => {
+                return Ok(serde_json::to_string_pretty(&serde_json::json!({
+                    "error": format!("Invalid URL: {e}"),
+                    "url": url
+                }))?);
+            }
+        };
+
+        let max_pages = params
+            .get("maxPages")
+            .and_then(|m| m.as_u64())
+            .map(|m| m.clamp(1, 20) as usize)
+            .unwrap_or(5);
+        let max_depth = params
+            .get("maxDepth")
+            .and_then(|m| m.as_u64())
+            .map(|m| m.min(3) as usize)
+            .unwrap_or(1);
+        let path_prefix = params
+            .get("pathPrefix")
+            .and_then(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_string());
+        let extract_mode = params
+            .get("extractMode")
+            .and_then(|m| m.as_str())
+            .unwrap_or("markdown");
+        let max_chars = params
+            .get("maxChars")
+            .and_then(|m| m.as_u64())
+            .map(|m| m as usize)
+            .unwrap_or(self.fetcher.max_chars);
+        let per_page_chars = (max_chars / max_pages).max(100);
+        let max_fetches = max_pages * FETCHES_PER_PAGE;
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::limited(5))
+            .timeout(std::time::Duration::from_secs(30))
+            .user_agent(USER_AGENT)
+            .build()?;
+
+        let mut queue: VecDeque<(url::Url, usize)> = VecDeque::new();
+        let mut seen_urls: HashSet<String> = HashSet::new();
+        let mut seen_content: HashSet<u64> = HashSet::new();
+        let mut pages = Vec::new();
+        let mut errors = Vec::new();
+        let mut total_chars = 0usize;
+        let mut fetches = 0usize;
+        let mut truncated = false;
+
+        seen_urls.insert(normalize_url(&start));
+        queue.push_back((start, 0));
+
+        while let Some((page_url, depth)) = queue.pop_front() {
+            if pages.len() >= max_pages || total_chars >= max_chars || fetches >= max_fetches {
+                truncated = truncated || !queue.is_empty();
+                break;
+            }
+            fetches += 1;
+
+            let resp = match client.get(page_url.as_str()).send().await {
+                Ok(r) => r,
+                Err(e) => {
+                    errors.push(
+                        serde_json::json!({"url": page_url.as_str(), "error": e.to_string()}),
+                    );
+                    continue;
+                }
+            };
+            let status = resp.status().as_u16();
+            let final_url = resp.url().clone();
+            let content_type = resp
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            if !resp.status().is_success() {
+                errors.push(serde_json::json!({"url": page_url.as_str(), "status": status}));
+                continue;
+            }
+            let body = match resp.text().await {
+                Ok(t) => t,
+                Err(e) => {
+                    errors.push(
+                        serde_json::json!({"url": page_url.as_str(), "error": e.to_string()}),
+                    );
+                    continue;
+                }
+            };
+
+            // Only follow links from HTML pages, and never leave the start host
+            // (a redirect may have moved us elsewhere).
+            if depth < max_depth
+                && content_type.contains("text/html")
+                && final_url.host_str() == page_url.host_str()
+            {
+                for link in self.extract_links(&final_url, &body) {
+                    if let Some(ref prefix) = path_prefix {
+                        if !link.path().starts_with(prefix.as_str()) {
+                            continue;
+                        }
+                    }
+                    if seen_urls.insert(normalize_url(&link)) {
+                        queue.push_back((link, depth + 1));
+                    }
+                }
+            }
+
+            let (text, _) =
+                self.fetcher
+                    .extract(body, &content_type, final_url.as_str(), extract_mode)?;
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+
+            // Skip pages whose content duplicates an earlier page (mirrors, ?ref= variants)
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(text, &mut hasher);
+            if !seen_content.insert(std::hash::Hasher::finish(&hasher)) {
+                continue;
+            }
+
+            let budget = per_page_chars.min(max_chars - total_chars);
+            let page_text = truncate_at_boundary(text, budget);
+            if page_text.len() < text.len() {
+                truncated = true;
+            }
+            total_chars += page_text.len();
+            pages.push(serde_json::json!({
+                "url": final_url.as_str(),
+                "depth": depth,
+                "length": page_text.len(),
+                "text": page_text,
+            }));
+        }
+
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "url": url,
+            "pagesFetched": pages.len(),
+            "truncated": truncated,
+            "length": total_chars,
+            "pages": pages,
+            "errors": errors,
+        }))?)
+    }
+}
+
+/// Whether `body` starts like an HTML document, whatever its content type.
+fn looks_like_html(body: &str) -> bool {
+    let start: String = body.trim_start().chars().take(9).collect();
+    let start = start.to_lowercase();
+    start.starts_with("<!doctype") || start.starts_with("<html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_html() {
+        assert!(looks_like_html("\n  <!DOCTYPE html><html></html>"));
+        assert!(looks_like_html("<html lang=\"en\">"));
+        assert!(!looks_like_html("  plain text"));
+        // Multi-byte text around where the old 256-byte cut fell
+        assert!(!looks_like_html(&format!("\n{}", "é".repeat(200))));
+    }
+
+    #[test]
+    fn test_extract_links_same_host_only() {
+        let tool = WebCrawlTool::new(10_000);
+        let base = url::Url::parse("https://example.com/docs/").unwrap();
+        let html = r#"
+            <a href="/docs/intro">Intro</a>
+            <a class="x" href="guide.html#setup">Guide</a>
+            <a href="https://other.com/page">Other</a>
+            <a href="mailto:me@example.com">Mail</a>
+            <a href="/files/manual.pdf">PDF</a>
+        "#;
+        let links: Vec<String> = tool
+            .extract_links(&base, html)
+            .iter()
+            .map(|u| u.to_string())
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/docs/intro",
+                "https://example.com/docs/guide.html",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_url_dedup() {
+        let a = url::Url::parse("https://example.com/docs/#top").unwrap();
+        let b = url::Url::parse("https://example.com/docs").unwrap();
+        assert_eq!(normalize_url(&a), normalize_url(&b));
+
+        let root = url::Url::parse("https://example.com/").unwrap();
+        assert_eq!(normalize_url(&root), "https://example.com/");
+    }
+
+    #[test]
+    fn test_truncate_at_boundary() {
+        assert_eq!(truncate_at_boundary("hello", 10), "hello");
+        assert_eq!(truncate_at_boundary("hello", 3), "hel");
+        // "é" is two bytes; never split it
+        assert_eq!(truncate_at_boundary("aé", 2), "a");
+    }
+
+    #[tokio::test]
+    async fn test_crawl_rejects_non_http() {
+        let tool = WebCrawlTool::new(10_000);
+        let result = tool
+            .execute(serde_json::json!({"url": "file:///etc/passwd"}))
+            .await
+            .unwrap();
+        assert!(result.contains("Only http/https allowed"));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_caps_fetches_when_pages_are_not_kept() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Every path serves the same page, so all but the first are duplicates
+        let links: String = (0..40)
+            .map(|i| format!("<a href=\"/p{i}\">p{i}</a>"))
+            .collect();
+        let page = format!("<html><body><p>Same page.</p>{links}</body></html>");
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut sock, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 2048];
+                let _ = sock.read(&mut buf).await;
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{page}",
+                    page.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        let tool = WebCrawlTool::new(10_000);
+        let result = tool
+            .execute(serde_json::json!({"url": format!("http://{addr}/"), "maxPages": 3}))
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["pagesFetched"], 1);
+        assert_eq!(result["truncated"], true);
+        assert_eq!(requests.load(Ordering::SeqCst), 3 * FETCHES_PER_PAGE);
+    }
+}
@@ -1,13 +1,18 @@
 use std::collections::HashMap;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::Arc;
 
 use anyhow::Result;
+use async_trait::async_trait;
 use patina_config::schema::ModelPricing;
-use rusqlite::Connection;
+use patina_transcribe::{audio, TranscribeOptions, Transcriber, Transcript};
 use serde::Serialize;
 
-/// A single LLM API call record.
+use crate::db::{add_column, Db, Migration, Step};
+
+/// A single billable API call: an LLM completion, or a transcription,
+/// embedding, or image generation request.
+#[derive(Debug, Clone, Default)]
 pub struct UsageRecord {
     pub timestamp: String,
     pub session_key: String,
@@ -19,6 +24,52 @@ pub struct UsageRecord {
     pub total_tokens: u64,
     pub cached_input_tokens: u64,
     pub call_type: String,
+    /// Seconds of audio transcribed.
+    pub audio_seconds: f64,
+    /// Images generated.
+    pub images: u64,
+}
+
+impl UsageRecord {
+    /// Replace zero token counts with estimates from the prompt and response
+    /// text. Some providers (and streams that end without a usage chunk)
+    /// report nothing, which would otherwise record the call as free.
+    /// Returns true if any count was estimated.
+    pub fn fill_estimates(&mut self, prompt: &str, output: &str) -> bool {
+        let mut estimated = false;
+        if self.input_tokens == 0 && !prompt.is_empty() {
+            self.input_tokens = estimate_tokens(prompt);
+            estimated = true;
+        }
+        if self.output_tokens == 0 && !output.is_empty() {
+            self.output_tokens = estimate_tokens(output);
+            estimated = true;
+        }
+        if estimated || self.total_tokens == 0 {
+            self.total_tokens = self.input_tokens + self.output_tokens;
+        }
+        estimated
+    }
+}
+
+/// Approximate the token count of `text` the way BPE tokenizers split it:
+/// each run of letters/digits costs one token per ~4 characters, and every
+/// other non-whitespace character is its own token.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut tokens = 0u64;
+    let mut word_len = 0u64;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word_len.div_ceil(4)
 }
 
 /// Filter parameters for usage queries.
@@ -42,6 +93,8 @@ pub struct UsageSummary {
     pub output_tokens: u64,
     pub total_tokens: u64,
     pub cached_input_tokens: u64,
+    pub audio_seconds: f64,
+    pub images: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub estimated_cost: Option<f64>,
 }
@@ -55,26 +108,100 @@ pub struct DailyUsage {
     pub output_tokens: u64,
     pub total_tokens: u64,
     pub cached_input_tokens: u64,
+    pub audio_seconds: f64,
+    pub images: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub estimated_cost: Option<f64>,
 }
 
-/// Tracks LLM API usage in a SQLite database.
-pub struct UsageTracker {
-    conn: Mutex<Connection>,
+/// Sums over a group of usage rows, read from [`Totals::COLUMNS`].
+#[derive(Debug, Default)]
+struct Totals {
+    calls: u64,
+    input_tokens: u64,
+    output_tokens: u64,
+    total_tokens: u64,
+    cached_input_tokens: u64,
+    audio_seconds: f64,
+    images: u64,
 }
 
-impl UsageTracker {
-    /// Open or create the usage database.
-    pub fn new(db_path: &Path) -> Result<Self> {
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent)?;
+impl Totals {
+    const COLUMNS: &'static str = "COUNT(*) AS calls,
+                    SUM(input_tokens) AS input_tokens,
+                    SUM(output_tokens) AS output_tokens,
+                    SUM(total_tokens) AS total_tokens,
+                    SUM(cached_input_tokens) AS cached_input_tokens,
+                    SUM(audio_seconds) AS audio_seconds,
+                    SUM(images) AS images";
+
+    /// Read [`Self::COLUMNS`] starting at column `first`.
+    fn read(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Self> {
+        Ok(Self {
+            calls: row.get::<_, i64>(first)? as u64,
+            input_tokens: row.get::<_, i64>(first + 1)? as u64,
+            output_tokens: row.get::<_, i64>(first + 2)? as u64,
+            total_tokens: row.get::<_, i64>(first + 3)? as u64,
+            cached_input_tokens: row.get::<_, i64>(first + 4)? as u64,
+            audio_seconds: row.get(first + 5)?,
+            images: row.get::<_, i64>(first + 6)? as u64,
+        })
+    }
+
+    fn add(&mut self, other: &Totals) {
+        self.calls += other.calls;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cached_input_tokens += other.cached_input_tokens;
+        self.audio_seconds += other.audio_seconds;
+        self.images += other.images;
+    }
+
+    fn cost(&self, pricing: &ModelPricing) -> f64 {
+        calculate_cost(
+            self.input_tokens,
+            self.output_tokens,
+            self.cached_input_tokens,
+            pricing,
+        ) + media_cost(self.audio_seconds, self.images, pricing)
+    }
+
+    fn into_summary(self, group_key: String, estimated_cost: Option<f64>) -> UsageSummary {
+        UsageSummary {
+            group_key,
+            calls: self.calls,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            total_tokens: self.total_tokens,
+            cached_input_tokens: self.cached_input_tokens,
+            audio_seconds: self.audio_seconds,
+            images: self.images,
+            estimated_cost,
         }
+    }
 
-        let conn = Connection::open(db_path)?;
-        conn.execute_batch("PRAGMA journal_mode=WAL;")?;
+    fn into_daily(self, date: String, estimated_cost: Option<f64>) -> DailyUsage {
+        DailyUsage {
+            date,
+            calls: self.calls,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+            total_tokens: self.total_tokens,
+            cached_input_tokens: self.cached_input_tokens,
+            audio_seconds: self.audio_seconds,
+            images: self.images,
+            estimated_cost,
+        }
+    }
+}
 
-        conn.execute_batch(
+/// The usage schema. Columns added after the first release are added only
+/// where missing, since files from before migrations may have them already.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "usage table",
+        up: Step::Sql(
             "CREATE TABLE IF NOT EXISTS usage (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 timestamp TEXT NOT NULL,
@@ -92,31 +219,43 @@ impl UsageTracker {
             CREATE INDEX IF NOT EXISTS idx_usage_session ON usage(session_key);
             CREATE INDEX IF NOT EXISTS idx_usage_model ON usage(model);
             CREATE INDEX IF NOT EXISTS idx_usage_agent ON usage(agent);",
-        )?;
+        ),
+    },
+    Migration {
+        name: "audio seconds and images",
+        up: Step::Code(|tx| {
+            add_column(tx, "usage", "audio_seconds", "REAL NOT NULL DEFAULT 0")?;
+            add_column(tx, "usage", "images", "INTEGER NOT NULL DEFAULT 0")
+        }),
+    },
+];
+
+/// Tracks billable API usage (completions, transcriptions, embeddings and
+/// images) in a SQLite database.
+pub struct UsageTracker {
+    db: Db,
+}
 
+impl UsageTracker {
+    /// Open or create the usage database.
+    pub fn new(db_path: &Path) -> Result<Self> {
         Ok(Self {
-            conn: Mutex::new(conn),
+            db: Db::open(db_path, MIGRATIONS)?,
         })
     }
 
-    fn lock_conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
-        self.conn
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Lock poisoned: {e}"))
-    }
-
-    /// Record a single LLM API call.
+    /// Record a single billable API call.
     pub fn record(&self, rec: &UsageRecord) {
-        let conn = match self.lock_conn() {
+        let conn = match self.db.conn() {
             Ok(c) => c,
             Err(e) => {
-                tracing::warn!("Usage tracking failed (lock): {e}");
+                tracing::warn!("Usage tracking failed (connection): {e}");
                 return;
             }
         };
         if let Err(e) = conn.execute(
-            "INSERT INTO usage (timestamp, session_key, model, provider, agent, input_tokens, output_tokens, total_tokens, cached_input_tokens, call_type)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO usage (timestamp, session_key, model, provider, agent, input_tokens, output_tokens, total_tokens, cached_input_tokens, call_type, audio_seconds, images)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             rusqlite::params![
                 rec.timestamp,
                 rec.session_key,
@@ -128,6 +267,8 @@ impl UsageTracker {
                 rec.total_tokens as i64,
                 rec.cached_input_tokens as i64,
                 rec.call_type,
+                rec.audio_seconds,
+                rec.images as i64,
             ],
         ) {
             tracing::warn!("Usage tracking failed (insert): {e}");
@@ -139,45 +280,25 @@ impl UsageTracker {
     /// Valid `group_by` values: "model", "provider", "agent", "session", "day", "call_type".
     /// Falls back to "model" if invalid.
     pub fn query_summary(&self, filter: &UsageFilter) -> Result<Vec<UsageSummary>> {
-        let conn = self.lock_conn()?;
-
-        let group_col = match filter.group_by.as_deref() {
-            Some("model") => "model",
-            Some("provider") => "provider",
-            Some("agent") => "agent",
-            Some("session") => "session_key",
-            Some("call_type") => "call_type",
-            Some("day") => "date(timestamp)",
-            _ => "model",
-        };
+        let conn = self.db.conn()?;
 
+        let group_col = group_column(filter.group_by.as_deref());
         let (where_clause, params) = build_where_clause(filter);
 
         let sql = format!(
             "SELECT {group_col} AS group_key,
-                    COUNT(*) AS calls,
-                    SUM(input_tokens) AS input_tokens,
-                    SUM(output_tokens) AS output_tokens,
-                    SUM(total_tokens) AS total_tokens,
-                    SUM(cached_input_tokens) AS cached_input_tokens
+                    {}
              FROM usage
              {where_clause}
              GROUP BY group_key
-             ORDER BY total_tokens DESC"
+             ORDER BY total_tokens DESC",
+            Totals::COLUMNS
         );
 
         let mut stmt = conn.prepare(&sql)?;
         let rows = stmt
             .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-                Ok(UsageSummary {
-                    group_key: row.get(0)?,
-                    calls: row.get::<_, i64>(1)? as u64,
-                    input_tokens: row.get::<_, i64>(2)? as u64,
-                    output_tokens: row.get::<_, i64>(3)? as u64,
-                    total_tokens: row.get::<_, i64>(4)? as u64,
-                    cached_input_tokens: row.get::<_, i64>(5)? as u64,
-                    estimated_cost: None,
-                })
+                Ok(Totals::read(row, 1)?.into_summary(row.get(0)?, None))
             })?
             .filter_map(|r| r.ok())
             .collect();
@@ -187,35 +308,24 @@ impl UsageTracker {
 
     /// Query per-day usage totals.
     pub fn query_daily(&self, filter: &UsageFilter) -> Result<Vec<DailyUsage>> {
-        let conn = self.lock_conn()?;
+        let conn = self.db.conn()?;
 
         let (where_clause, params) = build_where_clause(filter);
 
         let sql = format!(
             "SELECT date(timestamp) AS day,
-                    COUNT(*) AS calls,
-                    SUM(input_tokens) AS input_tokens,
-                    SUM(output_tokens) AS output_tokens,
-                    SUM(total_tokens) AS total_tokens,
-                    SUM(cached_input_tokens) AS cached_input_tokens
+                    {}
              FROM usage
              {where_clause}
              GROUP BY day
-             ORDER BY day DESC"
+             ORDER BY day DESC",
+            Totals::COLUMNS
         );
 
         let mut stmt = conn.prepare(&sql)?;
         let rows = stmt
             .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-                Ok(DailyUsage {
-                    date: row.get(0)?,
-                    calls: row.get::<_, i64>(1)? as u64,
-                    input_tokens: row.get::<_, i64>(2)? as u64,
-                    output_tokens: row.get::<_, i64>(3)? as u64,
-                    total_tokens: row.get::<_, i64>(4)? as u64,
-                    cached_input_tokens: row.get::<_, i64>(5)? as u64,
-                    estimated_cost: None,
-                })
+                Ok(Totals::read(row, 1)?.into_daily(row.get(0)?, None))
             })?
             .filter_map(|r| r.ok())
             .collect();
@@ -223,107 +333,75 @@ impl UsageTracker {
         Ok(rows)
     }
 
-    /// Query aggregated usage with estimated costs applied from pricing config.
-    ///
-    /// When `group_by` is "model", cost is calculated directly per row.
-    /// For other groupings, a secondary per-model query is run to calculate
-    /// accurate costs, then results are re-aggregated.
-    pub fn query_summary_with_cost(
+    /// Totals per (`group_col`, model), so per-model pricing can be applied
+    /// to any grouping.
+    fn query_by_model(
         &self,
+        group_col: &str,
         filter: &UsageFilter,
-        pricing: &HashMap<String, ModelPricing>,
-    ) -> Result<Vec<UsageSummary>> {
-        if pricing.is_empty() {
-            // No pricing configured — return plain results with None costs
-            return self.query_summary(filter);
-        }
-
-        let group_by = filter.group_by.as_deref().unwrap_or("model");
-
-        if group_by == "model" {
-            // Direct: each row is already per-model, apply pricing inline
-            let mut rows = self.query_summary(filter)?;
-            for row in &mut rows {
-                row.estimated_cost = pricing.get(&row.group_key).map(|p| {
-                    calculate_cost(
-                        row.input_tokens,
-                        row.output_tokens,
-                        row.cached_input_tokens,
-                        p,
-                    )
-                });
-            }
-            return Ok(rows);
-        }
-
-        // For non-model groupings, we need per-model token counts to apply
-        // pricing accurately. Query per-(group_key, model) from SQL, apply
-        // pricing in Rust, then re-aggregate by group_key.
-        let conn = self.lock_conn()?;
-
-        let group_col = match group_by {
-            "provider" => "provider",
-            "agent" => "agent",
-            "session" => "session_key",
-            "call_type" => "call_type",
-            "day" => "date(timestamp)",
-            _ => "model",
-        };
-
+    ) -> Result<Vec<(String, String, Totals)>> {
+        let conn = self.db.conn()?;
         let (where_clause, params) = build_where_clause(filter);
 
         let sql = format!(
             "SELECT {group_col} AS group_key, model,
-                    COUNT(*) AS calls,
-                    SUM(input_tokens) AS input_tokens,
-                    SUM(output_tokens) AS output_tokens,
-                    SUM(total_tokens) AS total_tokens,
-                    SUM(cached_input_tokens) AS cached_input_tokens
+                    {}
              FROM usage
              {where_clause}
-             GROUP BY group_key, model
-             ORDER BY total_tokens DESC"
+             GROUP BY group_key, model",
+            Totals::COLUMNS
         );
 
         let mut stmt = conn.prepare(&sql)?;
-        let detail_rows: Vec<(String, String, u64, u64, u64, u64, u64)> = stmt
+        let rows = stmt
             .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, i64>(2)? as u64,
-                    row.get::<_, i64>(3)? as u64,
-                    row.get::<_, i64>(4)? as u64,
-                    row.get::<_, i64>(5)? as u64,
-                    row.get::<_, i64>(6)? as u64,
-                ))
+                Ok((row.get(0)?, row.get(1)?, Totals::read(row, 2)?))
             })?
             .filter_map(|r| r.ok())
             .collect();
+        Ok(rows)
+    }
 
-        // Re-aggregate by group_key, summing costs across models
-        let mut agg: HashMap<String, UsageSummary> = HashMap::new();
-        for (gk, model, calls, inp, out, total, cached) in detail_rows {
-            let entry = agg.entry(gk.clone()).or_insert_with(|| UsageSummary {
-                group_key: gk,
-                calls: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                total_tokens: 0,
-                cached_input_tokens: 0,
-                estimated_cost: Some(0.0),
-            });
-            entry.calls += calls;
-            entry.input_tokens += inp;
-            entry.output_tokens += out;
-            entry.total_tokens += total;
-            entry.cached_input_tokens += cached;
-            if let Some(p) = pricing.get(&model) {
-                *entry.estimated_cost.as_mut().unwrap() += calculate_cost(inp, out, cached, p);
-            }
+    /// Query aggregated usage with estimated costs applied from pricing config.
+    ///
+    /// Usage is totalled per (group, model) so each model's pricing applies,
+    /// then re-aggregated by group. Token, audio and image costs are summed.
+    pub fn query_summary_with_cost(
+        &self,
+        filter: &UsageFilter,
+        pricing: &HashMap<String, ModelPricing>,
+    ) -> Result<Vec<UsageSummary>> {
+        if pricing.is_empty() {
+            // No pricing configured — return plain results with None costs
+            return self.query_summary(filter);
         }
 
-        let mut results: Vec<UsageSummary> = agg.into_values().collect();
+        let group_col = group_column(filter.group_by.as_deref());
+        let detail_rows = self.query_by_model(group_col, filter)?;
+
+        let mut results: Vec<UsageSummary> = if group_col == "model" {
+            // Direct: each row is already per-model, apply pricing inline
+            detail_rows
+                .into_iter()
+                .map(|(_, model, totals)| {
+                    let cost = pricing.get(&model).map(|p| totals.cost(p));
+                    totals.into_summary(model, cost)
+                })
+                .collect()
+        } else {
+            // Re-aggregate by group_key, summing costs across models
+            let mut agg: HashMap<String, (Totals, f64)> = HashMap::new();
+            for (gk, model, totals) in detail_rows {
+                let (sum, cost) = agg.entry(gk).or_default();
+                sum.add(&totals);
+                if let Some(p) = pricing.get(&model) {
+                    *cost += totals.cost(p);
+                }
+            }
+            agg.into_iter()
+                .map(|(gk, (totals, cost))| totals.into_summary(gk, Some(cost)))
+                .collect()
+        };
         results.sort_by(|a, b| b.total_tokens.cmp(&a.total_tokens));
         Ok(results)
     }
@@ -338,61 +416,20 @@ impl UsageTracker {
             return self.query_daily(filter);
         }
 
-        let conn = self.lock_conn()?;
-        let (where_clause, params) = build_where_clause(filter);
-
-        // Query per-(day, model) so we can apply per-model pricing
-        let sql = format!(
-            "SELECT date(timestamp) AS day, model,
-                    COUNT(*) AS calls,
-                    SUM(input_tokens) AS input_tokens,
-                    SUM(output_tokens) AS output_tokens,
-                    SUM(total_tokens) AS total_tokens,
-                    SUM(cached_input_tokens) AS cached_input_tokens
-             FROM usage
-             {where_clause}
-             GROUP BY day, model
-             ORDER BY day DESC"
-        );
-
-        let mut stmt = conn.prepare(&sql)?;
-        let detail_rows: Vec<(String, String, u64, u64, u64, u64, u64)> = stmt
-            .query_map(rusqlite::params_from_iter(params.iter()), |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, i64>(2)? as u64,
-                    row.get::<_, i64>(3)? as u64,
-                    row.get::<_, i64>(4)? as u64,
-                    row.get::<_, i64>(5)? as u64,
-                    row.get::<_, i64>(6)? as u64,
-                ))
-            })?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        let mut agg: HashMap<String, DailyUsage> = HashMap::new();
-        for (day, model, calls, inp, out, total, cached) in detail_rows {
-            let entry = agg.entry(day.clone()).or_insert_with(|| DailyUsage {
-                date: day,
-                calls: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                total_tokens: 0,
-                cached_input_tokens: 0,
-                estimated_cost: Some(0.0),
-            });
-            entry.calls += calls;
-            entry.input_tokens += inp;
-            entry.output_tokens += out;
-            entry.total_tokens += total;
-            entry.cached_input_tokens += cached;
+        // Per-(day, model) so we can apply per-model pricing
+        let mut agg: HashMap<String, (Totals, f64)> = HashMap::new();
+        for (day, model, totals) in self.query_by_model("date(timestamp)", filter)? {
+            let (sum, cost) = agg.entry(day).or_default();
+            sum.add(&totals);
             if let Some(p) = pricing.get(&model) {
-                *entry.estimated_cost.as_mut().unwrap() += calculate_cost(inp, out, cached, p);
+                *cost += totals.cost(p);
             }
         }
 
-        let mut results: Vec<DailyUsage> = agg.into_values().collect();
+        let mut results: Vec<DailyUsage> = agg
+            .into_iter()
+            .map(|(day, (totals, cost))| totals.into_daily(day, Some(cost)))
+            .collect();
         results.sort_by(|a, b| b.date.cmp(&a.date));
         Ok(results)
     }
@@ -405,7 +442,7 @@ impl UsageTracker {
             _ => return Ok(Vec::new()),
         };
 
-        let conn = self.lock_conn()?;
+        let conn = self.db.conn()?;
         let sql = format!("SELECT DISTINCT {col} FROM usage ORDER BY {col}");
         let mut stmt = conn.prepare(&sql)?;
         let values = stmt
@@ -416,6 +453,18 @@ impl UsageTracker {
     }
 }
 
+/// The SQL expression for a `group_by` value, defaulting to the model.
+fn group_column(group_by: Option<&str>) -> &'static str {
+    match group_by {
+        Some("provider") => "provider",
+        Some("agent") => "agent",
+        Some("session") => "session_key",
+        Some("call_type") => "call_type",
+        Some("day") => "date(timestamp)",
+        _ => "model",
+    }
+}
+
 /// Build a WHERE clause from filter parameters.
 /// Returns (clause_string, param_values).
 fn build_where_clause(filter: &UsageFilter) -> (String, Vec<String>) {
@@ -477,6 +526,55 @@ pub fn calculate_cost(
     input_cost + output_cost + cached_cost
 }
 
+/// Calculate the estimated cost of transcribed audio and generated images.
+pub fn media_cost(audio_seconds: f64, images: u64, pricing: &ModelPricing) -> f64 {
+    audio_seconds / 3600.0 * pricing.audio_per_hour + images as f64 * pricing.per_image
+}
+
+/// Records each transcription it passes through: the audio's length and the
+/// backend and model that handled it. Failed attempts aren't recorded.
+pub struct TrackedTranscriber {
+    inner: Arc<dyn Transcriber>,
+    tracker: Arc<UsageTracker>,
+}
+
+impl TrackedTranscriber {
+    pub fn new(inner: Arc<dyn Transcriber>, tracker: Arc<UsageTracker>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+#[async_trait]
+impl Transcriber for TrackedTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        Ok(self
+            .transcribe(file_path, &TranscribeOptions::default())
+            .await?
+            .text)
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        // Callers delete the file afterwards, so measure it alongside
+        let (transcript, seconds) = tokio::join!(
+            self.inner.transcribe(file_path, options),
+            audio::probe_duration_secs(file_path)
+        );
+        let transcript = transcript?;
+        let backend = transcript.backend.as_deref().unwrap_or("unknown");
+        self.tracker.record(&UsageRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_key: options.chat_key.clone().unwrap_or_default(),
+            model: transcript.model.as_deref().unwrap_or(backend).to_string(),
+            provider: backend.to_string(),
+            agent: "transcribe".to_string(),
+            call_type: "transcription".to_string(),
+            audio_seconds: seconds.unwrap_or(0) as f64,
+            ..Default::default()
+        });
+        Ok(transcript)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -498,9 +596,33 @@ mod tests {
             total_tokens: tokens + tokens / 2,
             cached_input_tokens: 0,
             call_type: "chat".to_string(),
+            ..Default::default()
         }
     }
 
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hello world"), 4);
+        assert_eq!(estimate_tokens("Hi, you!"), 4);
+        assert_eq!(estimate_tokens("  \n\t "), 0);
+    }
+
+    #[test]
+    fn test_fill_estimates() {
+        let mut rec = sample_record("m", "ollama", "default", 0);
+        assert!(rec.fill_estimates("what is the time", "noon."));
+        assert_eq!(rec.input_tokens, 4);
+        assert_eq!(rec.output_tokens, 2);
+        assert_eq!(rec.total_tokens, 6);
+
+        // Reported counts are kept as-is
+        let mut rec = sample_record("m", "openai", "default", 100);
+        assert!(!rec.fill_estimates("what is the time", "noon."));
+        assert_eq!(rec.input_tokens, 100);
+        assert_eq!(rec.total_tokens, 150);
+    }
+
     #[test]
     fn test_record_and_query_summary() {
         let tracker = tracker_in_memory();
@@ -682,6 +804,7 @@ mod tests {
                 input: 30.0,  // $30/1M input
                 output: 60.0, // $60/1M output
                 cached_input: 15.0,
+                ..Default::default()
             },
         );
         pricing.insert(
@@ -690,6 +813,7 @@ mod tests {
                 input: 3.0,
                 output: 15.0,
                 cached_input: 0.0, // falls back to input rate
+                ..Default::default()
             },
         );
         pricing
@@ -701,6 +825,7 @@ mod tests {
             input: 3.0,
             output: 15.0,
             cached_input: 0.30,
+            ..Default::default()
         };
         // input_tokens includes cached, so: 800K uncached × $3 = $2.40,
         // 500K output × $15 = $7.50, 200K cached × $0.30 = $0.06
@@ -714,6 +839,7 @@ mod tests {
             input: 3.0,
             output: 15.0,
             cached_input: 0.0, // should use input rate
+            ..Default::default()
         };
         // input_tokens includes cached, so: 0 uncached × $3 = $0,
         // 0 output, 1M cached at input rate = $3
@@ -835,4 +961,105 @@ mod tests {
         assert_eq!(results[0].date, "2026-02-20");
         assert_eq!(results[1].date, "2026-02-19");
     }
+
+    #[test]
+    fn test_cost_includes_transcription_and_embeddings() {
+        let tracker = tracker_in_memory();
+        tracker.record(&sample_record("gpt-4", "openai", "default", 1000));
+        tracker.record(&UsageRecord {
+            timestamp: "2026-02-20T12:05:00Z".to_string(),
+            model: "whisper-large-v3".to_string(),
+            provider: "groq".to_string(),
+            agent: "transcribe".to_string(),
+            call_type: "transcription".to_string(),
+            audio_seconds: 1800.0,
+            ..Default::default()
+        });
+        tracker.record(&UsageRecord {
+            timestamp: "2026-02-20T12:10:00Z".to_string(),
+            model: "text-embedding-3-small".to_string(),
+            provider: "openai".to_string(),
+            agent: "knowledge".to_string(),
+            input_tokens: 500_000,
+            total_tokens: 500_000,
+            call_type: "embedding".to_string(),
+            ..Default::default()
+        });
+
+        let mut pricing = test_pricing();
+        pricing.insert(
+            "whisper-large-v3".to_string(),
+            ModelPricing {
+                audio_per_hour: 0.111,
+                ..Default::default()
+            },
+        );
+        pricing.insert(
+            "text-embedding-3-small".to_string(),
+            ModelPricing {
+                input: 0.02,
+                ..Default::default()
+            },
+        );
+        let results = tracker
+            .query_summary_with_cost(
+                &UsageFilter {
+                    group_by: Some("call_type".to_string()),
+                    ..Default::default()
+                },
+                &pricing,
+            )
+            .unwrap();
+        let row = |key: &str| results.iter().find(|r| r.group_key == key).unwrap();
+        assert_eq!(row("transcription").audio_seconds, 1800.0);
+        assert!((row("transcription").estimated_cost.unwrap() - 0.0555).abs() < 1e-9);
+        assert!((row("embedding").estimated_cost.unwrap() - 0.01).abs() < 1e-9);
+
+        let daily = tracker
+            .query_daily_with_cost(&UsageFilter::default(), &pricing)
+            .unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].calls, 3);
+        let chat = calculate_cost(1000, 500, 0, &pricing["gpt-4"]);
+        assert!((daily[0].estimated_cost.unwrap() - (chat + 0.0655)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adds_columns_to_old_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage.sqlite");
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE usage (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    timestamp TEXT NOT NULL,
+                    session_key TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    provider TEXT NOT NULL,
+                    agent TEXT NOT NULL DEFAULT 'default',
+                    input_tokens INTEGER NOT NULL,
+                    output_tokens INTEGER NOT NULL,
+                    total_tokens INTEGER NOT NULL,
+                    cached_input_tokens INTEGER NOT NULL DEFAULT 0,
+                    call_type TEXT NOT NULL DEFAULT 'chat'
+                );
+                INSERT INTO usage (timestamp, session_key, model, provider, input_tokens, output_tokens, total_tokens)
+                VALUES ('2026-02-19T10:00:00Z', 'cli:1', 'gpt-4', 'openai', 10, 5, 15);",
+            )
+            .unwrap();
+
+        let tracker = UsageTracker::new(&path).unwrap();
+        let mut rec = sample_record("gpt-4", "openai", "default", 0);
+        rec.audio_seconds = 42.0;
+        tracker.record(&rec);
+        // Opening again finds the columns already there
+        drop(tracker);
+        let tracker = UsageTracker::new(&path).unwrap();
+
+        let results = tracker.query_summary(&UsageFilter::default()).unwrap();
+        assert_eq!(results[0].calls, 2);
+        assert_eq!(results[0].total_tokens, 15);
+        assert_eq!(results[0].audio_seconds, 42.0);
+    }
 }
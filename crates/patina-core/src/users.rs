@@ -0,0 +1,282 @@
+//! Who is talking: maps channel identities (Telegram ids, Slack member ids,
+//! web tokens) to the users in `config.users` and their roles.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::NaiveDate;
+use patina_config::{Role, RolePolicy, RolesConfig, UserConfig};
+
+use crate::bus::InboundMessage;
+
+/// Senders that are patina itself rather than a person.
+const INTERNAL_SENDERS: &[&str] = &["cron", "heartbeat", "subagent", "location"];
+
+/// The resolved sender of a message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    /// Configured user name, if the sender matched one.
+    pub user: Option<String>,
+    pub role: Role,
+    /// Key for per-user limits: the user name, or "channel:sender" for
+    /// unknown senders.
+    pub key: String,
+}
+
+impl Identity {
+    fn owner(key: &str) -> Self {
+        Self {
+            user: None,
+            role: Role::Owner,
+            key: key.to_string(),
+        }
+    }
+
+    /// The less trusted of two identities, for turns that combine messages
+    /// from several senders.
+    pub fn least_trusted(self, other: Identity) -> Identity {
+        if other.role < self.role {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Name for logs and replies.
+    pub fn display_name(&self) -> &str {
+        self.user.as_deref().unwrap_or(&self.key)
+    }
+}
+
+/// Lookup table built from `config.users` and `config.roles`.
+pub struct UserDirectory {
+    users: HashMap<String, UserConfig>,
+    /// "channel:id" (or a bare channel name) → user name.
+    by_identity: HashMap<String, String>,
+    /// Web token → user name.
+    web_tokens: HashMap<String, String>,
+    roles: RolesConfig,
+    daily_counts: Mutex<HashMap<String, (NaiveDate, u32)>>,
+}
+
+impl UserDirectory {
+    pub fn new(users: &HashMap<String, UserConfig>, roles: &RolesConfig) -> Self {
+        let mut by_identity = HashMap::new();
+        let mut web_tokens = HashMap::new();
+        for (name, user) in users {
+            for identity in &user.identities {
+                let identity = identity.trim();
+                match identity.strip_prefix("web:") {
+                    Some(token) if !token.is_empty() => {
+                        web_tokens.insert(token.to_string(), name.clone());
+                    }
+                    _ if !identity.is_empty() => {
+                        by_identity.insert(identity.to_string(), name.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Self {
+            users: users.clone(),
+            by_identity,
+            web_tokens,
+            roles: roles.clone(),
+            daily_counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether any users are configured. Without them everyone is the owner.
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    /// Web tokens (token → user name) for `WebChannel::set_user_tokens`.
+    pub fn web_tokens(&self) -> HashMap<String, String> {
+        self.web_tokens.clone()
+    }
+
+    pub fn policy(&self, role: Role) -> &RolePolicy {
+        self.roles.policy(role)
+    }
+
+    /// Resolve the sender of `msg`. Internal messages (cron, heartbeat,
+    /// subagent results, tasks, the CLI) are always the owner, and web guest
+    /// links are always guests, even when no users are configured.
+    pub fn resolve(&self, msg: &InboundMessage) -> Identity {
+        if msg.channel == "web" {
+            if let Some(id) = msg.metadata.get("guest").and_then(|v| v.as_str()) {
+                return Identity {
+                    user: None,
+                    role: Role::Guest,
+                    key: format!("guest:{id}"),
+                };
+            }
+        }
+        let sender_key = format!("{}:{}", msg.channel, msg.sender_id);
+        if !self.is_enabled()
+            || matches!(msg.channel.as_str(), "system" | "task" | "cli")
+            || INTERNAL_SENDERS.contains(&msg.sender_id.as_str())
+        {
+            return Identity::owner(&sender_key);
+        }
+
+        // The web channel sets `user` for connections opened with a token
+        let from_token = (msg.channel == "web")
+            .then(|| msg.metadata.get("user").and_then(|v| v.as_str()))
+            .flatten();
+        // Senders look like "id" or "id|username"; either part can match
+        let from_sender = || {
+            msg.sender_id
+                .split('|')
+                .filter(|part| !part.is_empty())
+                .find_map(|part| self.by_identity.get(&format!("{}:{part}", msg.channel)))
+                .or_else(|| self.by_identity.get(&msg.channel))
+                .map(String::as_str)
+        };
+
+        match from_token
+            .or_else(from_sender)
+            .and_then(|name| self.users.get_key_value(name))
+        {
+            Some((name, user)) => Identity {
+                user: Some(name.clone()),
+                role: user.role,
+                key: name.clone(),
+            },
+            None => Identity {
+                user: None,
+                role: self.roles.unknown_senders,
+                key: sender_key,
+            },
+        }
+    }
+
+    /// Count a message against the identity's daily limit. Returns false if
+    /// the limit was already reached today. Counts are kept in memory and
+    /// start over when the gateway restarts.
+    pub fn take_message(&self, identity: &Identity) -> bool {
+        let limit = self.policy(identity.role).daily_messages;
+        if limit == 0 {
+            return true;
+        }
+        let today = chrono::Local::now().date_naive();
+        let mut counts = match self.daily_counts.lock() {
+            Ok(c) => c,
+            Err(e) => e.into_inner(),
+        };
+        let entry = counts.entry(identity.key.clone()).or_insert((today, 0));
+        if entry.0 != today {
+            *entry = (today, 0);
+        }
+        if entry.1 >= limit {
+            return false;
+        }
+        entry.1 += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::default_timestamp;
+
+    fn msg(channel: &str, sender_id: &str) -> InboundMessage {
+        InboundMessage {
+            channel: channel.into(),
+            sender_id: sender_id.into(),
+            chat_id: "1".into(),
+            content: "hi".into(),
+            media: Vec::new(),
+            metadata: HashMap::new(),
+            timestamp: default_timestamp(),
+        }
+    }
+
+    fn directory() -> UserDirectory {
+        let users = HashMap::from([
+            (
+                "me".to_string(),
+                UserConfig {
+                    role: Role::Owner,
+                    identities: vec!["telegram:111".into(), "web:tok-me".into()],
+                    ..Default::default()
+                },
+            ),
+            (
+                "pal".to_string(),
+                UserConfig {
+                    role: Role::Member,
+                    identities: vec!["telegram:palname".into(), "slack".into()],
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let mut roles = RolesConfig::default();
+        roles.guest.daily_messages = 2;
+        UserDirectory::new(&users, &roles)
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = directory();
+        assert_eq!(
+            dir.web_tokens().get("tok-me").map(String::as_str),
+            Some("me")
+        );
+
+        let me = dir.resolve(&msg("telegram", "111|me_handle"));
+        assert_eq!((me.user.as_deref(), me.role), (Some("me"), Role::Owner));
+
+        let pal = dir.resolve(&msg("telegram", "222|palname"));
+        assert_eq!((pal.user.as_deref(), pal.role), (Some("pal"), Role::Member));
+
+        // Bare channel identity matches anyone on that channel
+        let anyone = dir.resolve(&msg("slack", "U999"));
+        assert_eq!(anyone.user.as_deref(), Some("pal"));
+
+        let stranger = dir.resolve(&msg("telegram", "333"));
+        assert_eq!(stranger.role, Role::Guest);
+        assert_eq!(stranger.key, "telegram:333");
+        assert_eq!(stranger.clone().least_trusted(me.clone()), stranger);
+
+        let mut web = msg("web", "web:abcd1234");
+        assert_eq!(dir.resolve(&web).role, Role::Guest);
+        web.metadata.insert("user".into(), serde_json::json!("me"));
+        assert_eq!(dir.resolve(&web).role, Role::Owner);
+
+        // Internal senders are trusted; `user` metadata only counts on web
+        assert_eq!(dir.resolve(&msg("telegram", "cron")).role, Role::Owner);
+        assert_eq!(dir.resolve(&msg("system", "x")).role, Role::Owner);
+        let mut spoofed = msg("telegram", "444");
+        spoofed
+            .metadata
+            .insert("user".into(), serde_json::json!("me"));
+        assert_eq!(dir.resolve(&spoofed).role, Role::Guest);
+
+        // No users configured: everyone is the owner, except guest links
+        let open = UserDirectory::new(&HashMap::new(), &RolesConfig::default());
+        assert_eq!(open.resolve(&msg("telegram", "333")).role, Role::Owner);
+        let mut guest = msg("web", "guest:ab12");
+        guest
+            .metadata
+            .insert("guest".into(), serde_json::json!("ab12"));
+        let guest = open.resolve(&guest);
+        assert_eq!(
+            (guest.role, guest.key.as_str()),
+            (Role::Guest, "guest:ab12")
+        );
+    }
+
+    #[test]
+    fn test_daily_limit() {
+        let dir = directory();
+        let stranger = dir.resolve(&msg("telegram", "333"));
+        assert!(dir.take_message(&stranger));
+        assert!(dir.take_message(&stranger));
+        assert!(!dir.take_message(&stranger));
+        let me = dir.resolve(&msg("telegram", "111"));
+        assert!((0..10).all(|_| dir.take_message(&me)));
+    }
+}
@@ -0,0 +1,451 @@
+//! The dead-man's switch.
+//!
+//! Recurring cron jobs and the heartbeat fail quietly: when the gateway
+//! crashes, a provider key expires, or the timer wedges, nothing runs and
+//! nothing says so. The watchdog notices. [`Watchdog::problems`] lists
+//! watched jobs more than `toleranceMins` past due or whose last run failed,
+//! a heartbeat that stopped beating or whose last turn failed, and a gateway
+//! that stopped checking in. [`Watchdog::check`] turns new problems into
+//! alerts and cleared ones into all-clears, once each.
+//!
+//! The gateway runs the checks every `intervalSecs`. It can't report its own
+//! death, so `patina watchdog --alert`, run from the system's cron, checks
+//! that the gateway is still beating ([`Watchdog::check_gateway`]).
+//!
+//! What the checks go on is kept in `watchdog.json` in the data directory:
+//! when the gateway and heartbeat last beat, the failures the gateway saw in
+//! heartbeat and cron turns, and what's been alerted.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use patina_config::{HeartbeatConfig, WatchdogConfig};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::bus::OutboundMessage;
+use crate::cron::types::{CronJob, CronStore, ScheduleKind};
+use crate::locale::UserLocale;
+
+/// Beat of the gateway's own check loop.
+pub const GATEWAY: &str = "gateway";
+/// Beat of the heartbeat loop, and failures of heartbeat turns.
+pub const HEARTBEAT: &str = "heartbeat";
+
+/// Key for a cron job's failures and problems.
+pub fn job_key(job_id: &str) -> String {
+    format!("job:{job_id}")
+}
+
+/// A turn that failed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Failure {
+    pub error: String,
+    pub at_ms: i64,
+}
+
+/// Everything in `watchdog.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WatchdogState {
+    /// When each loop last beat, by name.
+    pub beats: BTreeMap<String, i64>,
+    /// Last failure of the heartbeat or a job, cleared by its next success.
+    pub failures: BTreeMap<String, Failure>,
+    /// Problems already alerted, with their alert, by key.
+    pub alerting: BTreeMap<String, String>,
+}
+
+/// Something the watchdog reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub key: String,
+    pub message: String,
+}
+
+/// The watchdog's checks and state.
+pub struct Watchdog {
+    config: WatchdogConfig,
+    state_path: PathBuf,
+    cron_store: PathBuf,
+    /// The heartbeat interval, when heartbeats are on.
+    heartbeat_secs: Option<u64>,
+    locale: UserLocale,
+}
+
+impl Watchdog {
+    /// A watchdog keeping its state in `data_dir`, reading the cron store
+    /// from there too.
+    pub fn new(
+        config: WatchdogConfig,
+        heartbeat: &HeartbeatConfig,
+        data_dir: &Path,
+        locale: UserLocale,
+    ) -> Self {
+        Self {
+            config,
+            state_path: data_dir.join("watchdog.json"),
+            cron_store: data_dir.join("cron").join("jobs.json"),
+            heartbeat_secs: heartbeat.enabled.then_some(heartbeat.interval_secs),
+            locale,
+        }
+    }
+
+    /// Where alerts go, if anywhere.
+    pub fn target(&self) -> Option<(&str, &str)> {
+        let (channel, to) = (self.config.channel.as_str(), self.config.to.as_str());
+        (!channel.is_empty() && !to.is_empty()).then_some((channel, to))
+    }
+
+    /// Note that the `what` loop is alive.
+    pub fn beat(&self, what: &str, now_ms: i64) {
+        self.update(|state| {
+            state.beats.insert(what.to_string(), now_ms);
+        });
+    }
+
+    /// Note how a turn for `key` ([`HEARTBEAT`] or a [`job_key`]) went.
+    pub fn record(&self, key: &str, outcome: Result<(), String>, now_ms: i64) {
+        self.update(|state| match outcome {
+            Ok(()) => {
+                state.failures.remove(key);
+            }
+            Err(error) => {
+                state.failures.insert(
+                    key.to_string(),
+                    Failure {
+                        error,
+                        at_ms: now_ms,
+                    },
+                );
+            }
+        });
+    }
+
+    /// Everything wrong right now.
+    pub fn problems(&self, now_ms: i64) -> Vec<Problem> {
+        let state = self.load();
+        let tolerance_ms = self.config.tolerance_mins as i64 * 60_000;
+        let mut problems = Vec::new();
+
+        // Twice the check interval, so one slow check isn't a dead gateway
+        let gateway_ms = 2 * self.config.interval_secs as i64 * 1000 + tolerance_ms;
+        if let Some(&beat) = state.beats.get(GATEWAY) {
+            if now_ms - beat > gateway_ms {
+                problems.push(Problem {
+                    key: GATEWAY.to_string(),
+                    message: format!(
+                        "The gateway hasn't checked in since {}. Is it running?",
+                        self.locale.format_ms(beat)
+                    ),
+                });
+            }
+        }
+
+        if let Some(secs) = self.heartbeat_secs {
+            let late = state
+                .beats
+                .get(HEARTBEAT)
+                .filter(|&&beat| now_ms - beat > secs as i64 * 1000 + tolerance_ms);
+            if let Some(&beat) = late {
+                problems.push(Problem {
+                    key: HEARTBEAT.to_string(),
+                    message: format!("No heartbeat since {}.", self.locale.format_ms(beat)),
+                });
+            } else if let Some(failure) = state.failures.get(HEARTBEAT) {
+                problems.push(Problem {
+                    key: HEARTBEAT.to_string(),
+                    message: format!(
+                        "The heartbeat failed at {}: {}",
+                        self.locale.format_ms(failure.at_ms),
+                        failure.error
+                    ),
+                });
+            }
+        }
+
+        for job in self.watched_jobs() {
+            let key = job_key(&job.id);
+            let overdue = job
+                .state
+                .next_run_at_ms
+                .filter(|&due| now_ms - due > tolerance_ms);
+            let message = if let Some(due) = overdue {
+                format!(
+                    "Cron job '{}' was due at {} and hasn't run.",
+                    job.name,
+                    self.locale.format_ms(due)
+                )
+            } else if let Some(failure) = state.failures.get(&key) {
+                format!("Cron job '{}' failed: {}", job.name, failure.error)
+            } else if job.state.last_status.as_deref() == Some("error") {
+                format!(
+                    "Cron job '{}' failed: {}",
+                    job.name,
+                    job.state.last_error.as_deref().unwrap_or("unknown error")
+                )
+            } else {
+                continue;
+            };
+            problems.push(Problem { key, message });
+        }
+        problems
+    }
+
+    /// Alerts for new problems and all-clears for fixed ones, each sent
+    /// once. Used by the gateway, so a dead gateway is left to
+    /// [`Self::check_gateway`].
+    pub fn check(&self, now_ms: i64) -> Vec<String> {
+        let problems = self
+            .problems(now_ms)
+            .into_iter()
+            .filter(|p| p.key != GATEWAY)
+            .collect();
+        self.reconcile(problems, |_| true)
+    }
+
+    /// The alert or all-clear for the gateway only, for `patina watchdog`
+    /// while the gateway might be down.
+    pub fn check_gateway(&self, now_ms: i64) -> Vec<String> {
+        let problems = self
+            .problems(now_ms)
+            .into_iter()
+            .filter(|p| p.key == GATEWAY)
+            .collect();
+        self.reconcile(problems, |key| key == GATEWAY)
+    }
+
+    /// Run the checks every `intervalSecs`, beating for the gateway and
+    /// sending what they find to the alert target.
+    pub fn spawn(
+        self: Arc<Self>,
+        outbound_tx: broadcast::Sender<OutboundMessage>,
+    ) -> JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.interval_secs.max(30));
+        tokio::spawn(async move {
+            info!("Watchdog started (every {}s)", interval.as_secs());
+            loop {
+                let now_ms = Utc::now().timestamp_millis();
+                self.beat(GATEWAY, now_ms);
+                for message in self.check(now_ms) {
+                    let Some((channel, to)) = self.target() else {
+                        warn!("{message}");
+                        continue;
+                    };
+                    let msg = OutboundMessage {
+                        channel: channel.to_string(),
+                        chat_id: to.to_string(),
+                        content: message,
+                        reply_to: None,
+                        metadata: Default::default(),
+                    };
+                    if let Err(e) = outbound_tx.send(msg) {
+                        warn!("Failed to send watchdog alert: {e}");
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Alert `problems` not yet alerted and clear alerted ones in scope that
+    /// are gone.
+    fn reconcile(&self, problems: Vec<Problem>, in_scope: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut messages = Vec::new();
+        self.update(|state| {
+            let fixed: Vec<String> = state
+                .alerting
+                .keys()
+                .filter(|key| in_scope(key) && !problems.iter().any(|p| &p.key == *key))
+                .cloned()
+                .collect();
+            for key in fixed {
+                if let Some(alert) = state.alerting.remove(&key) {
+                    messages.push(format!("Watchdog: resolved: {alert}"));
+                }
+            }
+            for problem in &problems {
+                if !state.alerting.contains_key(&problem.key) {
+                    messages.push(format!("Watchdog: {}", problem.message));
+                    state
+                        .alerting
+                        .insert(problem.key.clone(), problem.message.clone());
+                }
+            }
+        });
+        messages
+    }
+
+    /// Enabled recurring jobs, or the ones named in `jobs`.
+    fn watched_jobs(&self) -> Vec<CronJob> {
+        let store: CronStore = std::fs::read_to_string(&self.cron_store)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        store
+            .jobs
+            .into_iter()
+            .filter(|j| j.enabled)
+            .filter(|j| matches!(j.schedule.kind, ScheduleKind::Every | ScheduleKind::Cron))
+            .filter(|j| {
+                self.config.jobs.is_empty()
+                    || self.config.jobs.iter().any(|w| *w == j.id || *w == j.name)
+            })
+            .collect()
+    }
+
+    fn load(&self) -> WatchdogState {
+        std::fs::read_to_string(&self.state_path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn update(&self, change: impl FnOnce(&mut WatchdogState)) {
+        let mut state = self.load();
+        change(&mut state);
+        if let Err(e) = self.save(&state) {
+            warn!("Failed to save watchdog state: {e}");
+        }
+    }
+
+    fn save(&self, state: &WatchdogState) -> Result<()> {
+        if let Some(parent) = self.state_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.state_path, serde_json::to_string_pretty(state)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::types::{CronJobState, CronPayload, CronSchedule};
+
+    const MIN: i64 = 60_000;
+
+    fn job(id: &str, kind: ScheduleKind, next_run_at_ms: Option<i64>) -> CronJob {
+        CronJob {
+            id: id.to_string(),
+            name: format!("{id}-job"),
+            enabled: true,
+            schedule: CronSchedule {
+                kind,
+                at_ms: None,
+                every_ms: Some(3_600_000),
+                expr: None,
+                tz: None,
+            },
+            payload: CronPayload {
+                kind: "exec".to_string(),
+                message: "true".to_string(),
+                deliver: false,
+                channel: None,
+                to: None,
+                cache_ttl_secs: None,
+                model_tier: None,
+                deliver_on: Default::default(),
+            },
+            state: CronJobState {
+                next_run_at_ms,
+                ..Default::default()
+            },
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: false,
+            on_success: None,
+            on_failure: None,
+            condition: None,
+        }
+    }
+
+    fn watchdog(dir: &Path, jobs: Vec<CronJob>) -> Watchdog {
+        let store = CronStore { version: 1, jobs };
+        std::fs::create_dir_all(dir.join("cron")).unwrap();
+        std::fs::write(
+            dir.join("cron/jobs.json"),
+            serde_json::to_string(&store).unwrap(),
+        )
+        .unwrap();
+        let heartbeat = HeartbeatConfig {
+            enabled: true,
+            interval_secs: 1800,
+            ..Default::default()
+        };
+        let config = WatchdogConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        Watchdog::new(config, &heartbeat, dir, UserLocale::new(Some("UTC"), None))
+    }
+
+    #[test]
+    fn finds_late_and_failed_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = 1_000 * MIN;
+        let dog = watchdog(
+            dir.path(),
+            vec![
+                job("late", ScheduleKind::Every, Some(now - 20 * MIN)),
+                job("fine", ScheduleKind::Cron, Some(now - 5 * MIN)),
+                job("once", ScheduleKind::At, Some(now - 60 * MIN)),
+            ],
+        );
+        dog.beat(GATEWAY, now - 5 * MIN);
+        dog.beat(HEARTBEAT, now - 40 * MIN);
+        dog.record(&job_key("fine"), Err("provider: 401".into()), now - MIN);
+        let keys: Vec<String> = dog.problems(now).into_iter().map(|p| p.key).collect();
+        assert_eq!(keys, ["job:late", "job:fine"]);
+
+        dog.beat(HEARTBEAT, now - 50 * MIN);
+        dog.record(HEARTBEAT, Err("provider: 401".into()), now);
+        dog.record(&job_key("fine"), Ok(()), now);
+        let problems = dog.problems(now + 30 * MIN);
+        assert_eq!(problems[0].key, GATEWAY);
+        assert_eq!(
+            problems[1].message,
+            "No heartbeat since 1970-01-01 15:50 (UTC+00:00)."
+        );
+        assert_eq!(problems.len(), 4);
+    }
+
+    #[test]
+    fn alerts_once_and_clears() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = 1_000 * MIN;
+        let dog = watchdog(dir.path(), Vec::new());
+        dog.beat(GATEWAY, now);
+        dog.beat(HEARTBEAT, now);
+        dog.record(HEARTBEAT, Err("provider: 401".into()), now);
+
+        let alerts = dog.check(now);
+        assert_eq!(
+            alerts,
+            ["Watchdog: The heartbeat failed at 1970-01-01 16:40 (UTC+00:00): provider: 401"]
+        );
+        assert!(dog.check(now + MIN).is_empty());
+
+        // A dead gateway is only reported from outside, then cleared by the
+        // gateway's next check
+        let later = now + 60 * MIN;
+        assert!(dog.check(later).iter().all(|a| !a.contains("gateway")));
+        assert_eq!(dog.check_gateway(later).len(), 1);
+        assert!(dog.check_gateway(later).is_empty());
+        dog.beat(GATEWAY, later);
+        dog.beat(HEARTBEAT, later);
+        dog.record(HEARTBEAT, Ok(()), later);
+        let cleared = dog.check(later);
+        assert_eq!(cleared.len(), 2);
+        assert!(cleared
+            .iter()
+            .all(|m| m.starts_with("Watchdog: resolved: ")));
+    }
+}
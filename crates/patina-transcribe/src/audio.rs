@@ -1,18 +1,154 @@
-//! Audio format conversion via ffmpeg.
+//! Audio format conversion.
 //!
 //! Converts any audio file (OGG Opus, MP3, M4A, etc.) to 16kHz mono WAV
-//! suitable for Parakeet inference.
+//! suitable for Parakeet inference. Uses the pure-Rust decoder when built with
+//! `native-decode`, falling back to ffmpeg for formats it can't handle.
 
 use std::path::Path;
 
 use anyhow::{bail, Result};
 use tokio::process::Command;
+#[cfg(feature = "native-decode")]
+use tracing::debug;
 
 /// Check if ffmpeg is available on the system.
 pub fn ffmpeg_available() -> bool {
     which::which("ffmpeg").is_ok()
 }
 
+/// Whether the pure-Rust decoder is compiled in.
+pub fn native_decode_available() -> bool {
+    cfg!(feature = "native-decode")
+}
+
+/// Whether audio can be converted for local inference at all.
+pub fn conversion_available() -> bool {
+    native_decode_available() || ffmpeg_available()
+}
+
+/// Human-readable description of the available conversion path.
+pub fn conversion_backend() -> &'static str {
+    match (native_decode_available(), ffmpeg_available()) {
+        (true, true) => "native (ffmpeg fallback)",
+        (true, false) => "native",
+        (false, true) => "ffmpeg",
+        (false, false) => "unavailable",
+    }
+}
+
+/// Duration of an audio or video file in whole seconds (rounded up), read from
+/// headers natively or via ffprobe. `None` when neither can tell.
+pub async fn probe_duration_secs(path: &str) -> Option<u64> {
+    #[cfg(feature = "native-decode")]
+    {
+        let input = Path::new(path).to_path_buf();
+        let native = tokio::task::spawn_blocking(move || crate::decode::probe_duration(&input))
+            .await
+            .ok()
+            .and_then(|r| r.ok())
+            .flatten();
+        if let Some(secs) = native {
+            return Some(secs.ceil() as u64);
+        }
+    }
+
+    if which::which("ffprobe").is_err() {
+        return None;
+    }
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()
+        .await
+        .ok()?;
+    let secs: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(secs.ceil() as u64)
+}
+
+/// Check if yt-dlp is available on the system.
+pub fn yt_dlp_available() -> bool {
+    which::which("yt-dlp").is_ok()
+}
+
+/// Whether a path looks like a video container rather than plain audio.
+pub fn is_video_file(path: &str) -> bool {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    matches!(ext.as_str(), "mp4" | "mkv" | "mov" | "avi" | "webm" | "m4v")
+}
+
+/// MIME type for uploading an audio file to a cloud backend, by extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("ogg") => "audio/ogg",
+        Some("mp3") => "audio/mpeg",
+        Some("m4a") => "audio/mp4",
+        Some("wav") => "audio/wav",
+        Some("flac") => "audio/flac",
+        Some("webm") => "audio/webm",
+        Some("mp4") => "audio/mp4",
+        _ => "audio/ogg",
+    }
+}
+
+/// Extract the audio track of any media file into a compact mono Opus file.
+///
+/// Keeps long recordings (podcasts, videos) small enough for cloud upload
+/// limits: roughly 10 MB per hour of speech. Returns the path to the new
+/// `.ogg` file in the system temp directory; the caller cleans it up.
+pub async fn extract_audio(input_path: &str) -> Result<String> {
+    let output = std::env::temp_dir().join(format!("patina-audio-{}.ogg", uuid::Uuid::new_v4()));
+    let output_str = output.to_string_lossy().to_string();
+
+    let result = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-i",
+            input_path,
+            "-vn",
+            "-ac",
+            "1",
+            "-ar",
+            "16000",
+            "-c:a",
+            "libopus",
+            "-b:a",
+            "24k",
+            "-loglevel",
+            "error",
+            &output_str,
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .output()
+        .await?;
+
+    if !result.status.success() {
+        let _ = tokio::fs::remove_file(&output).await;
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        bail!(
+            "ffmpeg audio extraction failed with status {}: {}",
+            result.status,
+            stderr.trim()
+        );
+    }
+
+    Ok(output_str)
+}
+
 /// Convert any audio file to 16kHz mono WAV suitable for Parakeet.
 ///
 /// Returns the path to the converted WAV file (placed alongside the input).
@@ -23,6 +159,21 @@ pub async fn convert_to_wav_16k(input_path: &str) -> Result<String> {
     let output = input.with_file_name(format!("{stem}_16k.wav"));
     let output_str = output.to_string_lossy().to_string();
 
+    #[cfg(feature = "native-decode")]
+    {
+        let (src, dst) = (input.to_path_buf(), output.clone());
+        let native =
+            tokio::task::spawn_blocking(move || crate::decode::decode_to_wav_16k(&src, &dst))
+                .await?;
+        match native {
+            Ok(()) => return Ok(output_str),
+            Err(e) if ffmpeg_available() => {
+                debug!("Native decode failed for {input_path}, using ffmpeg: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
     let status = Command::new("ffmpeg")
         .args([
             "-y",
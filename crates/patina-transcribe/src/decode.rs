@@ -0,0 +1,338 @@
+//! Pure-Rust audio decoding and resampling.
+//!
+//! Decodes OGG (Vorbis, and Opus with the `opus` feature), MP3, M4A/AAC, FLAC,
+//! and WAV via symphonia, downmixes to mono, resamples to 16kHz with rubato,
+//! and writes a 16-bit WAV for Parakeet. Anything symphonia can't handle is
+//! left to the ffmpeg fallback in `audio`.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use rubato::{FftFixedIn, Resampler};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Target sample rate for Parakeet.
+pub const TARGET_RATE: u32 = 16_000;
+
+/// Decode `input` and write 16kHz mono 16-bit WAV to `output`.
+///
+/// Blocking; call from `spawn_blocking`.
+pub fn decode_to_wav_16k(input: &Path, output: &Path) -> Result<()> {
+    let (samples, rate) = decode_mono(input)?;
+    if samples.is_empty() {
+        bail!("No audio samples decoded from {}", input.display());
+    }
+    let resampled = resample(&samples, rate, TARGET_RATE)?;
+    write_wav(output, &resampled)
+}
+
+/// Decode the first audio track of a file into mono f32 samples.
+/// Returns the samples and their sample rate.
+pub fn decode_mono(input: &Path) -> Result<(Vec<f32>, u32)> {
+    let file = std::fs::File::open(input)
+        .with_context(|| format!("Failed to open {}", input.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = input.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| anyhow!("Unsupported container: {e}"))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow!("No audio track found"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    if params.codec == CODEC_TYPE_OPUS {
+        return decode_opus(format.as_mut(), track_id, &params);
+    }
+
+    let rate = params
+        .sample_rate
+        .ok_or_else(|| anyhow!("Unknown sample rate"))?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&params, &DecoderOptions::default())
+        .map_err(|e| anyhow!("Unsupported codec: {e}"))?;
+
+    let mut samples = Vec::new();
+    let mut buf: Option<SampleBuffer<f32>> = None;
+    while let Some(packet) = next_packet(format.as_mut())? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(d) => d,
+            // Corrupt frames are skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(anyhow!("Decode failed: {e}")),
+        };
+        let spec = *decoded.spec();
+        let needed = decoded.capacity() as u64;
+        let sb = match buf {
+            Some(ref mut b) if b.capacity() as u64 >= needed => b,
+            _ => buf.insert(SampleBuffer::new(needed, spec)),
+        };
+        sb.copy_interleaved_ref(decoded);
+        push_mono(&mut samples, sb.samples(), spec.channels.count());
+    }
+
+    Ok((samples, rate))
+}
+
+/// Duration of the first audio track in seconds, from container headers only.
+/// `None` when the container doesn't record a frame count.
+pub fn probe_duration(input: &Path) -> Result<Option<f64>> {
+    let file = std::fs::File::open(input)
+        .with_context(|| format!("Failed to open {}", input.display()))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = input.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| anyhow!("Unsupported container: {e}"))?;
+    let duration = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .and_then(|t| {
+            let frames = t.codec_params.n_frames?;
+            let rate = t.codec_params.sample_rate?;
+            Some(frames as f64 / rate as f64)
+        });
+    Ok(duration)
+}
+
+/// Read the next packet, treating end-of-stream as `None`.
+fn next_packet(format: &mut dyn FormatReader) -> Result<Option<symphonia::core::formats::Packet>> {
+    match format.next_packet() {
+        Ok(p) => Ok(Some(p)),
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Ok(None)
+        }
+        Err(SymphoniaError::ResetRequired) => Ok(None),
+        Err(e) => Err(anyhow!("Failed to read packet: {e}")),
+    }
+}
+
+/// Average interleaved frames down to a single channel.
+pub(crate) fn push_mono(out: &mut Vec<f32>, interleaved: &[f32], channels: usize) {
+    if channels <= 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
+
+/// Symphonia demuxes Opus but has no decoder for it; use libopus when enabled.
+#[cfg(feature = "opus")]
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &symphonia::core::codecs::CodecParameters,
+) -> Result<(Vec<f32>, u32)> {
+    use audiopus::coder::Decoder;
+    use audiopus::{Channels, SampleRate};
+
+    const OPUS_RATE: u32 = 48_000;
+    // Largest Opus frame is 120ms
+    const MAX_FRAME: usize = 5_760;
+
+    let channels = match params.channels.map(|c| c.count()).unwrap_or(1) {
+        1 => Channels::Mono,
+        _ => Channels::Stereo,
+    };
+    let n_channels = channels as usize;
+    let mut decoder = Decoder::new(SampleRate::Hz48000, channels)
+        .map_err(|e| anyhow!("Failed to create Opus decoder: {e}"))?;
+
+    let mut samples = Vec::new();
+    let mut frame = vec![0f32; MAX_FRAME * n_channels];
+    while let Some(packet) = next_packet(format)? {
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let input = match audiopus::packet::Packet::try_from(packet.buf()) {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+        let output = audiopus::MutSignals::try_from(&mut frame[..])
+            .map_err(|e| anyhow!("Opus buffer error: {e}"))?;
+        match decoder.decode_float(Some(input), output, false) {
+            Ok(n) => push_mono(&mut samples, &frame[..n * n_channels], n_channels),
+            Err(_) => continue,
+        }
+    }
+
+    // Drop the encoder pre-skip so the transcript doesn't start mid-click
+    let skip = (params.delay.unwrap_or(0) as usize).min(samples.len());
+    samples.drain(..skip);
+    Ok((samples, OPUS_RATE))
+}
+
+#[cfg(not(feature = "opus"))]
+fn decode_opus(
+    _format: &mut dyn FormatReader,
+    _track_id: u32,
+    _params: &symphonia::core::codecs::CodecParameters,
+) -> Result<(Vec<f32>, u32)> {
+    bail!("Opus decoding not available: built without 'opus' feature")
+}
+
+/// Resample mono audio from `from` Hz to `to` Hz.
+pub fn resample(samples: &[f32], from: u32, to: u32) -> Result<Vec<f32>> {
+    if from == to {
+        return Ok(samples.to_vec());
+    }
+
+    let mut resampler = FftFixedIn::<f32>::new(from as usize, to as usize, 1024, 2, 1)
+        .map_err(|e| anyhow!("Failed to create resampler: {e}"))?;
+    let expected = (samples.len() as u64 * to as u64).div_ceil(from as u64) as usize;
+    let delay = resampler.output_delay();
+    let mut out: Vec<f32> = Vec::with_capacity(expected + delay);
+
+    let mut pos = 0;
+    while samples.len() - pos >= resampler.input_frames_next() {
+        let end = pos + resampler.input_frames_next();
+        let chunk = resampler
+            .process(&[&samples[pos..end]], None)
+            .map_err(|e| anyhow!("Resampling failed: {e}"))?;
+        out.extend_from_slice(&chunk[0]);
+        pos = end;
+    }
+    if pos < samples.len() {
+        let chunk = resampler
+            .process_partial(Some(&[&samples[pos..]]), None)
+            .map_err(|e| anyhow!("Resampling failed: {e}"))?;
+        out.extend_from_slice(&chunk[0]);
+    }
+    // Flush the resampler's internal delay line
+    while out.len() < expected + delay {
+        let chunk = resampler
+            .process_partial::<&[f32]>(None, None)
+            .map_err(|e| anyhow!("Resampling failed: {e}"))?;
+        if chunk[0].is_empty() {
+            break;
+        }
+        out.extend_from_slice(&chunk[0]);
+    }
+
+    let start = delay.min(out.len());
+    let end = (start + expected).min(out.len());
+    Ok(out[start..end].to_vec())
+}
+
+/// Write mono f32 samples as 16kHz 16-bit PCM WAV.
+pub(crate) fn write_wav(output: &Path, samples: &[f32]) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: TARGET_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(output, spec)?;
+    for s in samples {
+        writer.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_sine(path: &Path, rate: u32, channels: u16, secs: f32) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate: rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut w = hound::WavWriter::create(path, spec).unwrap();
+        let frames = (rate as f32 * secs) as usize;
+        for i in 0..frames {
+            let v = (i as f32 * 440.0 * 2.0 * std::f32::consts::PI / rate as f32).sin();
+            for _ in 0..channels {
+                w.write_sample((v * 0.5 * i16::MAX as f32) as i16).unwrap();
+            }
+        }
+        w.finalize().unwrap();
+    }
+
+    #[test]
+    fn converts_stereo_44k_to_mono_16k() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        let output = dir.path().join("out.wav");
+        write_sine(&input, 44_100, 2, 1.0);
+
+        decode_to_wav_16k(&input, &output).unwrap();
+
+        let reader = hound::WavReader::open(&output).unwrap();
+        let spec = reader.spec();
+        assert_eq!(spec.channels, 1);
+        assert_eq!(spec.sample_rate, TARGET_RATE);
+        assert_eq!(reader.duration(), TARGET_RATE);
+    }
+
+    #[test]
+    fn probes_duration_from_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        write_sine(&input, 8_000, 1, 2.5);
+        let secs = probe_duration(&input).unwrap().unwrap();
+        assert!((secs - 2.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn resample_is_identity_at_same_rate() {
+        let samples = vec![0.1, -0.2, 0.3];
+        assert_eq!(resample(&samples, 16_000, 16_000).unwrap(), samples);
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        let mut out = Vec::new();
+        push_mono(&mut out, &[1.0, 0.0, 0.5, 0.5], 2);
+        assert_eq!(out, vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn rejects_non_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("notes.txt");
+        std::fs::write(&input, "not audio").unwrap();
+        assert!(decode_mono(&input).is_err());
+    }
+}
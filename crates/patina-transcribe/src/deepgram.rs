@@ -0,0 +1,164 @@
+//! Deepgram speech-to-text backend.
+//!
+//! Uploads the raw audio file to the pre-recorded `/v1/listen` endpoint.
+
+use anyhow::{bail, Result};
+use tracing::error;
+
+use crate::audio;
+use crate::error::BackendError;
+use crate::language::normalize_language;
+use crate::{TranscribeOptions, Transcriber, Transcript};
+
+pub const DEFAULT_MODEL: &str = "nova-3";
+
+/// Cloud transcription backend via Deepgram.
+pub struct DeepgramTranscriber {
+    api_key: String,
+    model: String,
+    keyterms: Vec<String>,
+}
+
+impl DeepgramTranscriber {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            model: DEFAULT_MODEL.to_string(),
+            keyterms: Vec::new(),
+        }
+    }
+
+    /// Override the model (e.g. "nova-2").
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        if let Some(model) = model.filter(|m| !m.is_empty()) {
+            self.model = model;
+        }
+        self
+    }
+
+    /// Names and jargon to boost. Only Nova-3 models support key terms.
+    pub fn with_keyterms(mut self, keyterms: Vec<String>) -> Self {
+        self.keyterms = keyterms;
+        self
+    }
+
+    fn query(&self, options: &TranscribeOptions) -> Vec<(&'static str, String)> {
+        let mut query = vec![
+            ("model", self.model.clone()),
+            ("smart_format", "true".to_string()),
+        ];
+        match options.language {
+            Some(ref language) => query.push(("language", language.clone())),
+            None => query.push(("detect_language", "true".to_string())),
+        }
+        if self.model.starts_with("nova-3") {
+            query.extend(self.keyterms.iter().map(|k| ("keyterm", k.clone())));
+        }
+        query
+    }
+}
+
+#[async_trait::async_trait]
+impl Transcriber for DeepgramTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        Ok(self
+            .transcribe(file_path, &TranscribeOptions::default())
+            .await?
+            .text)
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let path = std::path::Path::new(file_path);
+        if !path.exists() {
+            bail!("Audio file not found: {file_path}");
+        }
+
+        let file_bytes = tokio::fs::read(path).await?;
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post("https://api.deepgram.com/v1/listen")
+            .query(&self.query(options))
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header("Content-Type", audio::mime_type(path))
+            .body(file_bytes)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            error!("Deepgram transcription failed ({status}): {body}");
+            return Err(BackendError::Http {
+                backend: "Deepgram",
+                status,
+            }
+            .into());
+        }
+
+        let data: serde_json::Value = resp.json().await?;
+        let mut transcript = parse_response(&data, options)?;
+        transcript.model = Some(self.model.clone());
+        Ok(transcript)
+    }
+}
+
+/// Extract the first channel's best transcript and detected language.
+fn parse_response(data: &serde_json::Value, options: &TranscribeOptions) -> Result<Transcript> {
+    let channel = data
+        .pointer("/results/channels/0")
+        .ok_or_else(|| anyhow::anyhow!("No channels in Deepgram response"))?;
+    let text = channel
+        .pointer("/alternatives/0/transcript")
+        .and_then(|t| t.as_str())
+        .map(|t| t.trim().to_string())
+        .ok_or_else(|| anyhow::anyhow!("No transcript in Deepgram response"))?;
+    let language = channel
+        .get("detected_language")
+        .and_then(|l| l.as_str())
+        .and_then(normalize_language)
+        .or_else(|| options.language.clone());
+    Ok(Transcript {
+        text,
+        language,
+        backend: None,
+        model: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_transcript_and_detected_language() {
+        let data = serde_json::json!({
+            "results": {"channels": [{
+                "detected_language": "de",
+                "alternatives": [{"transcript": " Guten Morgen. ", "confidence": 0.98}]
+            }]}
+        });
+        let t = parse_response(&data, &TranscribeOptions::default()).unwrap();
+        assert_eq!(t.text, "Guten Morgen.");
+        assert_eq!(t.language.as_deref(), Some("de"));
+        assert!(parse_response(&serde_json::json!({"results": {}}), &Default::default()).is_err());
+    }
+
+    #[test]
+    fn query_uses_hint_or_detection() {
+        let t = DeepgramTranscriber::new("k".into()).with_keyterms(vec!["Patina".into()]);
+        let q = t.query(&TranscribeOptions::default());
+        assert!(q.contains(&("detect_language", "true".to_string())));
+        assert!(q.contains(&("keyterm", "Patina".to_string())));
+
+        let t = t.with_model(Some("nova-2".into()));
+        let q = t.query(&TranscribeOptions {
+            language: Some("fr".into()),
+            chat_key: None,
+        });
+        assert!(q.contains(&("language", "fr".to_string())));
+        assert!(!q
+            .iter()
+            .any(|(k, _)| *k == "keyterm" || *k == "detect_language"));
+    }
+}
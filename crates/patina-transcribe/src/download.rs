@@ -0,0 +1,346 @@
+//! Local model download.
+//!
+//! Files are streamed to `<name>.part` and renamed into place once complete, so
+//! an interrupted download never leaves a truncated model behind. A lock file
+//! in the model directory serializes concurrent downloads (e.g. two gateways
+//! starting at once); the loser waits and then reuses the winner's files.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// Files that make up the Parakeet TDT ONNX model.
+pub const MODEL_FILES: [&str; 4] = [
+    "encoder-model.onnx",
+    "encoder-model.onnx.data",
+    "decoder_joint-model.onnx",
+    "vocab.txt",
+];
+
+const LOCK_FILE: &str = ".download.lock";
+/// A lock not touched for this long belongs to a dead process.
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(120);
+/// How often the holder refreshes its lock, whether or not bytes are arriving.
+const LOCK_REFRESH: Duration = Duration::from_secs(30);
+/// Give up waiting on another process after this long.
+const LOCK_MAX_WAIT: Duration = Duration::from_secs(60 * 60);
+/// Abort if the server sends nothing for this long.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Progress of a model download, reported roughly once per percent.
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    pub file: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    /// Set on the final event, once every file is in place.
+    pub done: bool,
+}
+
+impl DownloadProgress {
+    pub fn percent(&self) -> Option<u8> {
+        self.total
+            .filter(|t| *t > 0)
+            .map(|t| ((self.downloaded.min(t) * 100) / t) as u8)
+    }
+}
+
+/// Callback for download progress events.
+pub type ProgressFn = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/// Names of `files` not yet present in `dir`.
+fn missing_files<'a>(dir: &Path, files: &[&'a str]) -> Vec<&'a str> {
+    files
+        .iter()
+        .copied()
+        .filter(|f| !dir.join(f).exists())
+        .collect()
+}
+
+/// Exclusive download lock, kept fresh in the background and removed on drop.
+struct DownloadLock {
+    path: PathBuf,
+    heartbeat: tokio::task::JoinHandle<()>,
+}
+
+impl DownloadLock {
+    /// Acquire the lock, waiting for (or breaking a stale lock from) another process.
+    async fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        let started = std::time::Instant::now();
+        let mut announced = false;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => {
+                    let heartbeat = tokio::spawn(keep_fresh(path.clone(), LOCK_REFRESH));
+                    let lock = Self { path, heartbeat };
+                    lock.touch(&format!("pid {}", std::process::id()));
+                    return Ok(lock);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        warn!("Removing stale model download lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_MAX_WAIT {
+                        bail!(
+                            "Timed out waiting for another process to finish downloading the model ({})",
+                            path.display()
+                        );
+                    }
+                    if !announced {
+                        info!("Another process is downloading the model, waiting...");
+                        announced = true;
+                    }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        }
+    }
+
+    /// Rewrite the lock contents, refreshing its mtime so waiters know we're alive.
+    fn touch(&self, status: &str) {
+        let _ = std::fs::write(&self.path, status);
+    }
+}
+
+impl Drop for DownloadLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Bump the lock's mtime every `every`, so a slow download (or one without a
+/// Content-Length) never looks stale to a waiting process. Never recreates a
+/// removed lock.
+async fn keep_fresh(path: PathBuf, every: Duration) {
+    let mut tick = tokio::time::interval(every);
+    tick.tick().await;
+    loop {
+        tick.tick().await;
+        let bumped = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()));
+        if bumped.is_err() {
+            return;
+        }
+    }
+}
+
+fn is_stale(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_some_and(|age| age > LOCK_STALE_AFTER)
+}
+
+/// Download any missing Parakeet model files from `base_url` into `model_path`.
+pub async fn download_missing_model_files(
+    model_path: &str,
+    base_url: &str,
+    progress: Option<ProgressFn>,
+) -> Result<()> {
+    download_missing_files(Path::new(model_path), base_url, &MODEL_FILES, progress).await
+}
+
+/// Download whichever of `files` are missing from `dir`, fetching each from
+/// `<base_url>/<file>`.
+pub async fn download_missing_files(
+    dir: &Path,
+    base_url: &str,
+    files: &[&str],
+    progress: Option<ProgressFn>,
+) -> Result<()> {
+    if missing_files(dir, files).is_empty() {
+        return Ok(());
+    }
+    tokio::fs::create_dir_all(dir).await?;
+
+    let lock = DownloadLock::acquire(dir).await?;
+    // Another process may have finished while we waited for the lock
+    let missing = missing_files(dir, files);
+
+    let base = base_url.trim_end_matches('/');
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(30))
+        .build()?;
+
+    for file in &missing {
+        let url = format!("{base}/{file}");
+        info!("Downloading model file: {url}");
+        download_file(&client, &url, dir, file, &lock, progress.as_ref()).await?;
+    }
+
+    if let Some(ref cb) = progress {
+        cb(&DownloadProgress {
+            file: String::new(),
+            downloaded: 0,
+            total: None,
+            done: true,
+        });
+    }
+    Ok(())
+}
+
+async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    dir: &Path,
+    file: &str,
+    lock: &DownloadLock,
+    progress: Option<&ProgressFn>,
+) -> Result<()> {
+    let target = dir.join(file);
+    let partial = dir.join(format!("{file}.part"));
+
+    let mut resp = client.get(url).send().await?.error_for_status()?;
+    let total = resp.content_length();
+    let mut out = tokio::fs::File::create(&partial).await?;
+    let mut downloaded: u64 = 0;
+    let mut last_percent: Option<u8> = None;
+
+    loop {
+        let chunk = match tokio::time::timeout(READ_TIMEOUT, resp.chunk()).await {
+            Ok(chunk) => chunk?,
+            Err(_) => bail!("Download of {file} stalled"),
+        };
+        let Some(chunk) = chunk else { break };
+        out.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        let event = DownloadProgress {
+            file: file.to_string(),
+            downloaded,
+            total,
+            done: false,
+        };
+        let percent = event.percent();
+        if percent != last_percent {
+            last_percent = percent;
+            lock.touch(&format!("pid {} {file} {downloaded}", std::process::id()));
+            if let Some(p) = percent.filter(|p| p % 10 == 0) {
+                info!("Downloading {file}: {p}%");
+            }
+            if let Some(cb) = progress {
+                cb(&event);
+            }
+        }
+    }
+
+    out.flush().await?;
+    drop(out);
+    if let Some(expected) = total {
+        if downloaded != expected {
+            let _ = tokio::fs::remove_file(&partial).await;
+            bail!("Download of {file} incomplete ({downloaded} of {expected} bytes)");
+        }
+    }
+    tokio::fs::rename(&partial, &target).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_is_clamped_and_optional() {
+        let mut p = DownloadProgress {
+            file: "vocab.txt".into(),
+            downloaded: 50,
+            total: Some(200),
+            done: false,
+        };
+        assert_eq!(p.percent(), Some(25));
+        p.downloaded = 400;
+        assert_eq!(p.percent(), Some(100));
+        p.total = None;
+        assert_eq!(p.percent(), None);
+    }
+
+    #[test]
+    fn lists_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            missing_files(dir.path(), &MODEL_FILES).len(),
+            MODEL_FILES.len()
+        );
+        std::fs::write(dir.path().join("vocab.txt"), "a").unwrap();
+        assert!(!missing_files(dir.path(), &MODEL_FILES).contains(&"vocab.txt"));
+    }
+
+    #[tokio::test]
+    async fn lock_is_exclusive_and_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = DownloadLock::acquire(dir.path()).await.unwrap();
+        assert!(dir.path().join(LOCK_FILE).exists());
+
+        let dir_path = dir.path().to_path_buf();
+        let waiter = tokio::spawn(async move { DownloadLock::acquire(&dir_path).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiter.is_finished());
+
+        drop(lock);
+        let second = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        drop(second);
+        assert!(!dir.path().join(LOCK_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn stale_lock_is_broken() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        let file = std::fs::File::create(&path).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(600);
+        file.set_modified(old).unwrap();
+        drop(file);
+
+        let lock = tokio::time::timeout(Duration::from_secs(5), DownloadLock::acquire(dir.path()))
+            .await
+            .unwrap()
+            .unwrap();
+        drop(lock);
+    }
+
+    #[tokio::test]
+    async fn heartbeat_keeps_lock_fresh_without_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(600))
+            .unwrap();
+        drop(file);
+        assert!(is_stale(&path));
+
+        let heartbeat = tokio::spawn(keep_fresh(path.clone(), Duration::from_millis(50)));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!is_stale(&path));
+
+        // A removed lock stays removed
+        std::fs::remove_file(&path).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), heartbeat)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!path.exists());
+    }
+}
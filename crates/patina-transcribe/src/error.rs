@@ -0,0 +1,149 @@
+//! Backend failures and how the fallback chain treats them.
+//!
+//! Most failures (timeouts, rate limits, outages, audio a backend can't decode)
+//! move on to the next backend in the chain. Auth failures don't: a wrong API
+//! key is a config problem, and silently sending the audio elsewhere would hide it.
+
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+/// Typed failures raised by backends so the chain can classify them.
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    #[error("{backend} transcription failed ({status})")]
+    Http {
+        backend: &'static str,
+        status: StatusCode,
+    },
+    #[error("{backend} transcription timed out after {}s", .after.as_secs())]
+    Timeout {
+        backend: &'static str,
+        after: Duration,
+    },
+    #[error("Could not decode audio: {0}")]
+    Decode(String),
+}
+
+/// Coarse failure category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Missing, invalid, or unauthorized API key.
+    Auth,
+    RateLimited,
+    /// Backend down or unreachable.
+    Unavailable,
+    Timeout,
+    /// The audio couldn't be decoded or the backend rejected it.
+    Decode,
+    Other,
+}
+
+impl FailureKind {
+    /// Whether the chain should try the next backend after this failure.
+    pub fn falls_back(self) -> bool {
+        self != Self::Auth
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auth => "auth",
+            Self::RateLimited => "rate limited",
+            Self::Unavailable => "unavailable",
+            Self::Timeout => "timeout",
+            Self::Decode => "decode",
+            Self::Other => "error",
+        }
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        match status.as_u16() {
+            401..=403 => Self::Auth,
+            429 => Self::RateLimited,
+            400 | 413 | 415 | 422 => Self::Decode,
+            s if s >= 500 => Self::Unavailable,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Classify an error from any backend by looking through its cause chain.
+pub fn classify(err: &anyhow::Error) -> FailureKind {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<BackendError>() {
+            return match e {
+                BackendError::Http { status, .. } => FailureKind::from_status(*status),
+                BackendError::Timeout { .. } => FailureKind::Timeout,
+                BackendError::Decode(_) => FailureKind::Decode,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                return FailureKind::Timeout;
+            }
+            if e.is_connect() {
+                return FailureKind::Unavailable;
+            }
+            if let Some(status) = e.status() {
+                return FailureKind::from_status(status);
+            }
+        }
+    }
+    FailureKind::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn http(status: StatusCode) -> anyhow::Error {
+        BackendError::Http {
+            backend: "Groq",
+            status,
+        }
+        .into()
+    }
+
+    #[test]
+    fn classifies_http_statuses() {
+        assert_eq!(classify(&http(StatusCode::UNAUTHORIZED)), FailureKind::Auth);
+        assert_eq!(classify(&http(StatusCode::FORBIDDEN)), FailureKind::Auth);
+        assert_eq!(
+            classify(&http(StatusCode::TOO_MANY_REQUESTS)),
+            FailureKind::RateLimited
+        );
+        assert_eq!(
+            classify(&http(StatusCode::UNSUPPORTED_MEDIA_TYPE)),
+            FailureKind::Decode
+        );
+        assert_eq!(
+            classify(&http(StatusCode::BAD_GATEWAY)),
+            FailureKind::Unavailable
+        );
+        assert!(!FailureKind::Auth.falls_back());
+        assert!(FailureKind::Decode.falls_back());
+    }
+
+    #[test]
+    fn finds_typed_errors_behind_context() {
+        let err = anyhow::Error::from(BackendError::Decode("bad header".into()))
+            .context("while transcribing voice.ogg");
+        assert_eq!(classify(&err), FailureKind::Decode);
+
+        let err = anyhow::Error::from(BackendError::Timeout {
+            backend: "local",
+            after: Duration::from_secs(5),
+        });
+        assert_eq!(classify(&err), FailureKind::Timeout);
+        assert_eq!(err.to_string(), "local transcription timed out after 5s");
+
+        assert_eq!(classify(&anyhow::anyhow!("boom")), FailureKind::Other);
+    }
+}
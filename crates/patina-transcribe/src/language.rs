@@ -0,0 +1,174 @@
+//! Spoken-language hints and normalization.
+//!
+//! Backends report languages in different shapes (Whisper's verbose JSON uses
+//! English names like "spanish", config uses ISO 639-1 codes, Telegram uses
+//! BCP 47 tags like "pt-BR"). Everything is normalized to lowercase ISO 639-1.
+
+use std::collections::HashMap;
+
+/// Languages supported by Parakeet TDT 0.6B v3, the default local model.
+pub const PARAKEET_V3_LANGUAGES: &[&str] = &[
+    "bg", "hr", "cs", "da", "nl", "en", "et", "fi", "fr", "de", "el", "hu", "it", "lv", "lt", "mt",
+    "pl", "pt", "ro", "sk", "sl", "es", "sv", "ru", "uk",
+];
+
+/// English language names (as returned by Whisper) mapped to ISO 639-1 codes.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("arabic", "ar"),
+    ("bulgarian", "bg"),
+    ("catalan", "ca"),
+    ("chinese", "zh"),
+    ("croatian", "hr"),
+    ("czech", "cs"),
+    ("danish", "da"),
+    ("dutch", "nl"),
+    ("english", "en"),
+    ("estonian", "et"),
+    ("finnish", "fi"),
+    ("french", "fr"),
+    ("german", "de"),
+    ("greek", "el"),
+    ("hebrew", "he"),
+    ("hindi", "hi"),
+    ("hungarian", "hu"),
+    ("indonesian", "id"),
+    ("italian", "it"),
+    ("japanese", "ja"),
+    ("korean", "ko"),
+    ("latvian", "lv"),
+    ("lithuanian", "lt"),
+    ("malay", "ms"),
+    ("maltese", "mt"),
+    ("norwegian", "no"),
+    ("persian", "fa"),
+    ("polish", "pl"),
+    ("portuguese", "pt"),
+    ("romanian", "ro"),
+    ("russian", "ru"),
+    ("slovak", "sk"),
+    ("slovenian", "sl"),
+    ("spanish", "es"),
+    ("swedish", "sv"),
+    ("tagalog", "tl"),
+    ("thai", "th"),
+    ("turkish", "tr"),
+    ("ukrainian", "uk"),
+    ("urdu", "ur"),
+    ("vietnamese", "vi"),
+];
+
+/// Normalize a language code or name to lowercase ISO 639-1.
+///
+/// Accepts "en", "EN", "pt-BR", "pt_BR", and "english". Returns `None` for
+/// empty input or names that aren't recognized.
+pub fn normalize_language(input: &str) -> Option<String> {
+    let lower = input.trim().to_lowercase();
+    if lower.is_empty() {
+        return None;
+    }
+    let primary = lower.split(['-', '_']).next().unwrap_or(&lower);
+    if primary.len() == 2 && primary.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Some(primary.to_string());
+    }
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, code)| code.to_string())
+}
+
+/// Resolves the language hint for a chat from per-chat and default config.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageHints {
+    default: Option<String>,
+    chats: HashMap<String, String>,
+}
+
+impl LanguageHints {
+    pub fn new(default: Option<&str>, chats: &HashMap<String, String>) -> Self {
+        Self {
+            default: default.and_then(normalize_language),
+            chats: chats
+                .iter()
+                .filter_map(|(k, v)| normalize_language(v).map(|code| (k.clone(), code)))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.chats.is_empty()
+    }
+
+    /// Look up the hint for `chat_key` ("channel:chat_id"). A thread-scoped key
+    /// like "telegram:123:45" falls back to "telegram:123", then to the default.
+    pub fn resolve(&self, chat_key: Option<&str>) -> Option<String> {
+        if let Some(mut key) = chat_key {
+            loop {
+                if let Some(code) = self.chats.get(key) {
+                    return Some(code.clone());
+                }
+                match key.rfind(':') {
+                    Some(idx) if key[..idx].contains(':') => key = &key[..idx],
+                    _ => break,
+                }
+            }
+        }
+        self.default.clone()
+    }
+}
+
+/// Whether a local model restricted to `supported` can handle `language`.
+/// An empty list means the built-in Parakeet v3 list.
+pub fn local_supports(supported: &[String], language: &str) -> bool {
+    if supported.is_empty() {
+        PARAKEET_V3_LANGUAGES.contains(&language)
+    } else {
+        supported
+            .iter()
+            .filter_map(|l| normalize_language(l))
+            .any(|l| l == language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_codes_tags_and_names() {
+        assert_eq!(normalize_language("EN").as_deref(), Some("en"));
+        assert_eq!(normalize_language("pt-BR").as_deref(), Some("pt"));
+        assert_eq!(normalize_language("zh_Hans").as_deref(), Some("zh"));
+        assert_eq!(normalize_language("Spanish").as_deref(), Some("es"));
+        assert_eq!(normalize_language(""), None);
+        assert_eq!(normalize_language("klingon"), None);
+    }
+
+    #[test]
+    fn resolves_chat_then_thread_parent_then_default() {
+        let mut chats = HashMap::new();
+        chats.insert("telegram:100".to_string(), "es".to_string());
+        let hints = LanguageHints::new(Some("en"), &chats);
+
+        assert_eq!(hints.resolve(Some("telegram:100")).as_deref(), Some("es"));
+        assert_eq!(hints.resolve(Some("telegram:100:7")).as_deref(), Some("es"));
+        assert_eq!(hints.resolve(Some("telegram:200")).as_deref(), Some("en"));
+        assert_eq!(hints.resolve(None).as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn no_hints_means_auto_detect() {
+        let hints = LanguageHints::new(None, &HashMap::new());
+        assert!(hints.is_empty());
+        assert_eq!(hints.resolve(Some("telegram:100")), None);
+    }
+
+    #[test]
+    fn local_language_support() {
+        assert!(local_supports(&[], "es"));
+        assert!(!local_supports(&[], "ja"));
+        let custom = vec!["English".to_string(), "ja".to_string()];
+        assert!(local_supports(&custom, "ja"));
+        assert!(local_supports(&custom, "en"));
+        assert!(!local_supports(&custom, "es"));
+    }
+}
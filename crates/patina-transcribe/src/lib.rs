@@ -1,45 +1,201 @@
 //! Voice transcription with a local-first strategy.
 //!
 //! Primary: local inference via `parakeet-rs` (NVIDIA Parakeet TDT, ONNX Runtime).
-//! Fallback: Groq Whisper API (cloud).
+//! Fallback: cloud APIs (Groq, OpenAI, Deepgram), tried in the configured chain order.
 
 pub mod audio;
+#[cfg(feature = "native-decode")]
+pub mod decode;
+pub mod deepgram;
+pub mod download;
+pub mod error;
 pub mod groq;
+pub mod language;
+pub mod limits;
 pub mod local;
+pub mod metrics;
+pub mod mic;
+pub mod openai;
+pub mod postprocess;
+pub mod provider;
+pub mod vad;
+pub mod wakeword;
+
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use tracing::{info, warn};
 
 use patina_config::{TranscriptionConfig, TranscriptionMode};
 
+use crate::download::ProgressFn;
+use crate::error::BackendError;
+use crate::language::LanguageHints;
+use crate::postprocess::PostProcessor;
+
+/// Cloud backends in the default `cloudPriority` order.
+pub const CLOUD_BACKENDS: [&str; 3] = ["groq", "openai", "deepgram"];
+
+const DEFAULT_LOCAL_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_CLOUD_TIMEOUT_SECS: u64 = 120;
+
+/// API keys for the cloud backends. A missing key disables that backend.
+#[derive(Debug, Clone, Default)]
+pub struct CloudKeys {
+    pub groq: Option<String>,
+    pub openai: Option<String>,
+    pub deepgram: Option<String>,
+}
+
+impl CloudKeys {
+    /// The key for a backend in [`CLOUD_BACKENDS`], if set.
+    pub fn get(&self, backend: &str) -> Option<&str> {
+        let key = match backend {
+            "groq" => &self.groq,
+            "openai" => &self.openai,
+            "deepgram" => &self.deepgram,
+            _ => &None,
+        };
+        key.as_deref().filter(|k| !k.is_empty())
+    }
+}
+
+/// Per-request hints passed to a transcription backend.
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    /// Spoken language (ISO 639-1). `None` lets the backend auto-detect.
+    pub language: Option<String>,
+    /// Originating chat as "channel:chat_id", used to look up per-chat hints.
+    pub chat_key: Option<String>,
+}
+
+/// A transcript together with the spoken language, when known.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub text: String,
+    /// Detected or hinted language (ISO 639-1).
+    pub language: Option<String>,
+    /// Backend in the chain that produced it ("local", "groq", ...).
+    pub backend: Option<String>,
+    /// Model that produced it, when the backend names one.
+    pub model: Option<String>,
+}
+
 /// Transcription backend trait.
 #[async_trait::async_trait]
 pub trait Transcriber: Send + Sync {
     /// Transcribe an audio file at the given path.
     async fn transcribe_file(&self, file_path: &str) -> Result<String>;
+
+    /// Transcribe with hints. Backends that can't use a language hint ignore it
+    /// and report the hint back as the transcript language.
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let text = self.transcribe_file(file_path).await?;
+        Ok(Transcript {
+            text,
+            language: options.language.clone(),
+            backend: None,
+            model: None,
+        })
+    }
+}
+
+/// One backend in the fallback chain.
+struct ChainStep {
+    name: &'static str,
+    backend: Box<dyn Transcriber>,
+    timeout: Option<Duration>,
 }
 
-/// Tries local transcription first, falls back to Groq on error.
+/// Tries each backend in the chain until one succeeds or one fails in a way
+/// that shouldn't fall back (see `error::FailureKind`).
 struct AutoTranscriber {
-    local: Option<Box<dyn Transcriber>>,
-    fallback: Option<Box<dyn Transcriber>>,
+    steps: Vec<ChainStep>,
+    /// Languages the local model handles; see `language::local_supports`.
+    local_languages: Vec<String>,
 }
 
 #[async_trait::async_trait]
 impl Transcriber for AutoTranscriber {
     async fn transcribe_file(&self, file_path: &str) -> Result<String> {
-        if let Some(ref local) = self.local {
-            match local.transcribe_file(file_path).await {
-                Ok(text) => return Ok(text),
+        Ok(self
+            .transcribe(file_path, &TranscribeOptions::default())
+            .await?
+            .text)
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let mut last_err = None;
+        for step in &self.steps {
+            // Only skip local when there is somewhere else to send the audio.
+            if step.name == "local" && self.steps.len() > 1 {
+                if let Some(lang) = options.language.as_deref() {
+                    if !language::local_supports(&self.local_languages, lang) {
+                        info!("Local model does not handle language {lang:?}, skipping");
+                        continue;
+                    }
+                }
+            }
+            let run = step.backend.transcribe(file_path, options);
+            let result = match step.timeout {
+                Some(after) => tokio::time::timeout(after, run).await.unwrap_or_else(|_| {
+                    Err(BackendError::Timeout {
+                        backend: step.name,
+                        after,
+                    }
+                    .into())
+                }),
+                None => run.await,
+            };
+            match result {
+                Ok(mut t) => {
+                    t.backend = Some(step.name.to_string());
+                    return Ok(t);
+                }
                 Err(e) => {
-                    warn!("Local transcription failed, trying fallback: {e}");
+                    let kind = error::classify(&e);
+                    if !kind.falls_back() {
+                        warn!(
+                            "{} transcription failed ({kind}), not falling back: {e}",
+                            step.name
+                        );
+                        return Err(e);
+                    }
+                    warn!("{} transcription failed ({kind}): {e}", step.name);
+                    last_err = Some(e);
                 }
             }
         }
-        if let Some(ref fallback) = self.fallback {
-            return fallback.transcribe_file(file_path).await;
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("No transcription backend available")))
+    }
+}
+
+/// Resolves language hints and runs the configured post-processing pipeline
+/// around another backend.
+struct PipelineTranscriber {
+    inner: Box<dyn Transcriber>,
+    processor: PostProcessor,
+    hints: LanguageHints,
+}
+
+#[async_trait::async_trait]
+impl Transcriber for PipelineTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        Ok(self
+            .transcribe(file_path, &TranscribeOptions::default())
+            .await?
+            .text)
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let mut options = options.clone();
+        if options.language.is_none() {
+            options.language = self.hints.resolve(options.chat_key.as_deref());
         }
-        Err(anyhow::anyhow!("No transcription backend available"))
+        let mut transcript = self.inner.transcribe(file_path, &options).await?;
+        transcript.text = self.processor.process(&transcript.text);
+        Ok(transcript)
     }
 }
 
@@ -53,8 +209,7 @@ fn resolve_model_path(config: &TranscriptionConfig) -> String {
         }
         path.clone()
     } else {
-        let home = dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("."));
-        home.join(".patina")
+        patina_config::data_dir()
             .join("models")
             .join("parakeet-tdt")
             .to_string_lossy()
@@ -70,40 +225,10 @@ pub fn model_files_exist(model_path: &str) -> bool {
         && dir.join("vocab.txt").exists()
 }
 
-async fn download_missing_model_files(model_path: &str, base_url: &str) -> Result<()> {
-    let dir = std::path::Path::new(model_path);
-    std::fs::create_dir_all(dir)?;
-
-    let base = base_url.trim_end_matches('/');
-    let files = [
-        "encoder-model.onnx",
-        "encoder-model.onnx.data",
-        "decoder_joint-model.onnx",
-        "vocab.txt",
-    ];
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120))
-        .build()?;
-
-    for file in files {
-        let target = dir.join(file);
-        if target.exists() {
-            continue;
-        }
-        let url = format!("{base}/{file}");
-        info!("Downloading Parakeet model file: {url}");
-        let resp = client.get(&url).send().await?.error_for_status()?;
-        let bytes = resp.bytes().await?;
-        std::fs::write(&target, &bytes)?;
-    }
-
-    Ok(())
-}
-
 async fn ensure_local_model_available(
     config: &TranscriptionConfig,
     model_path: &str,
+    progress: Option<ProgressFn>,
 ) -> Result<bool> {
     if model_files_exist(model_path) {
         return Ok(true);
@@ -118,7 +243,7 @@ async fn ensure_local_model_available(
         .as_deref()
         .unwrap_or("https://huggingface.co/istupakov/parakeet-tdt-0.6b-v3-onnx/resolve/main");
 
-    match download_missing_model_files(model_path, base).await {
+    match download::download_missing_model_files(model_path, base, progress).await {
         Ok(()) => Ok(model_files_exist(model_path)),
         Err(e) => Err(e),
     }
@@ -126,103 +251,659 @@ async fn ensure_local_model_available(
 
 /// Create a transcriber based on configuration.
 ///
-/// - `mode: Local` — only local, error if model not found or ffmpeg missing
-/// - `mode: Groq` — only Groq, error if no API key
-/// - `mode: Auto` — try local first; if model not found, fall back to Groq only
+/// - `mode: Local` — only local, error if model not found or audio can't be decoded
+/// - `mode: Groq` / `OpenAi` / `Deepgram` — only that backend, error if no API key
+/// - `mode: Auto` — try each available backend in `chain` order (default: local,
+///   then `cloudPriority`)
+///
+/// Every backend runs under its `timeoutSecs` limit, except that local alone
+/// has no default one.
+/// Language hints (`language`, `chatLanguages`) are resolved before the backend
+/// runs, and the `postProcess` pipeline is applied to whichever backend's output.
 pub async fn create_transcriber(
     config: &TranscriptionConfig,
-    groq_api_key: Option<String>,
+    keys: CloudKeys,
 ) -> Result<Box<dyn Transcriber>> {
+    create_transcriber_with_progress(config, keys, None).await
+}
+
+/// Like [`create_transcriber`], reporting local model download progress to `progress`.
+pub async fn create_transcriber_with_progress(
+    config: &TranscriptionConfig,
+    keys: CloudKeys,
+    progress: Option<ProgressFn>,
+) -> Result<Box<dyn Transcriber>> {
+    let backend = create_backend(config, keys, progress).await?;
+    let processor = PostProcessor::new(&config.post_process);
+    let hints = LanguageHints::new(config.language.as_deref(), &config.chat_languages);
+    if processor.is_noop() && hints.is_empty() {
+        return Ok(backend);
+    }
+    Ok(Box::new(PipelineTranscriber {
+        inner: backend,
+        processor,
+        hints,
+    }))
+}
+
+type InitHandle = tokio::task::JoinHandle<Result<Box<dyn Transcriber>>>;
+
+/// A transcriber that is created on a background task, so a slow local model
+/// load (or first-run download) doesn't hold up startup. Calls made before it
+/// is ready wait for it; if creation failed, every call returns that error.
+pub struct BackgroundTranscriber {
+    init: std::sync::Mutex<Option<InitHandle>>,
+    backend: tokio::sync::OnceCell<std::result::Result<Box<dyn Transcriber>, String>>,
+}
+
+impl BackgroundTranscriber {
+    /// Start [`create_transcriber_with_progress`] in the background. Must be
+    /// called inside a Tokio runtime.
+    pub fn spawn(
+        config: TranscriptionConfig,
+        keys: CloudKeys,
+        progress: Option<ProgressFn>,
+    ) -> Self {
+        Self::from_future(async move {
+            let started = std::time::Instant::now();
+            let result = create_transcriber_with_progress(&config, keys, progress).await;
+            match result {
+                Ok(_) => info!(
+                    "Voice transcription initialized in {:.2?}",
+                    started.elapsed()
+                ),
+                Err(ref e) => warn!("Voice transcription unavailable: {e}"),
+            }
+            result
+        })
+    }
+
+    fn from_future(
+        init: impl std::future::Future<Output = Result<Box<dyn Transcriber>>> + Send + 'static,
+    ) -> Self {
+        Self {
+            init: std::sync::Mutex::new(Some(tokio::spawn(init))),
+            backend: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    async fn backend(&self) -> Result<&dyn Transcriber> {
+        let backend = self
+            .backend
+            .get_or_init(|| async {
+                let handle = match self.init.lock() {
+                    Ok(mut g) => g.take(),
+                    Err(e) => e.into_inner().take(),
+                };
+                match handle {
+                    Some(handle) => match handle.await {
+                        Ok(result) => result.map_err(|e| e.to_string()),
+                        Err(e) => Err(format!("initialization task failed: {e}")),
+                    },
+                    None => Err("initialization was interrupted".into()),
+                }
+            })
+            .await;
+        match backend {
+            Ok(backend) => Ok(backend.as_ref()),
+            Err(e) => anyhow::bail!("Voice transcription unavailable: {e}"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transcriber for BackgroundTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        self.backend().await?.transcribe_file(file_path).await
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        self.backend().await?.transcribe(file_path, options).await
+    }
+}
+
+async fn create_backend(
+    config: &TranscriptionConfig,
+    keys: CloudKeys,
+    progress: Option<ProgressFn>,
+) -> Result<Box<dyn Transcriber>> {
+    // Single-backend modes fail loudly instead of leaving the backend out
+    let required = config.mode != TranscriptionMode::Auto;
+    let mut backends = Vec::new();
+    for name in chain_order(config, &keys) {
+        let backend = if name == "local" {
+            create_local(config, progress.clone(), required).await?
+        } else {
+            create_cloud_backend(name, config, &keys)
+        };
+        if let Some(backend) = backend {
+            backends.push((name, backend));
+        }
+    }
+    let alone = backends.len() == 1;
+    let steps: Vec<ChainStep> = backends
+        .into_iter()
+        .map(|(name, backend)| ChainStep {
+            name,
+            backend,
+            timeout: backend_timeout(config, name, alone),
+        })
+        .collect();
+
+    if steps.is_empty() {
+        if required {
+            let mode = mode_name(&config.mode);
+            anyhow::bail!("Transcription mode is '{mode}' but no {mode} API key configured");
+        }
+        info!("No transcription backend available (no local model, no cloud API key)");
+    } else {
+        let names: Vec<&str> = steps.iter().map(|s| s.name).collect();
+        info!("Transcription chain: {}", names.join(" -> "));
+    }
+
+    Ok(Box::new(AutoTranscriber {
+        steps,
+        local_languages: config.local_languages.clone(),
+    }))
+}
+
+/// Set up the local backend. When `required`, a missing model or decoder is an
+/// error; otherwise it's logged and local is left out of the chain.
+async fn create_local(
+    config: &TranscriptionConfig,
+    progress: Option<ProgressFn>,
+    required: bool,
+) -> Result<Option<Box<dyn Transcriber>>> {
     let model_path = resolve_model_path(config);
     let ep = config.execution_provider.as_deref().unwrap_or("cpu");
 
-    match config.mode {
-        TranscriptionMode::Local => {
-            if !ensure_local_model_available(config, &model_path).await? {
-                anyhow::bail!(
-                    "Transcription mode is 'local' but local model files are missing at {model_path}"
-                );
-            }
-            if !audio::ffmpeg_available() {
-                anyhow::bail!("Transcription mode is 'local' but ffmpeg is not installed");
+    if required {
+        if !ensure_local_model_available(config, &model_path, progress).await? {
+            anyhow::bail!(
+                "Transcription mode is 'local' but local model files are missing at {model_path}"
+            );
+        }
+        if !audio::conversion_available() {
+            anyhow::bail!(
+                "Transcription mode is 'local' but no audio decoder is available (install ffmpeg)"
+            );
+        }
+        return local_backend(config, &model_path, ep).map(Some);
+    }
+
+    let local_model_available =
+        match ensure_local_model_available(config, &model_path, progress).await {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Failed to auto-download local model files: {e}");
+                false
             }
-            let local = try_create_local(&model_path, ep)?;
-            Ok(Box::new(AutoTranscriber {
-                local: Some(local),
-                fallback: None,
-            }))
-        }
-        TranscriptionMode::Groq => {
-            let key = groq_api_key.filter(|k| !k.is_empty()).ok_or_else(|| {
-                anyhow::anyhow!("Transcription mode is 'groq' but no Groq API key configured")
-            })?;
-            Ok(Box::new(groq::GroqTranscriber::new(key)))
+        };
+
+    if !audio::conversion_available() {
+        info!("No audio decoder (ffmpeg not found), local transcription unavailable");
+        return Ok(None);
+    }
+    if !local_model_available {
+        info!("Parakeet model not found at {model_path}, local transcription unavailable");
+        return Ok(None);
+    }
+    match local_backend(config, &model_path, ep) {
+        Ok(t) => {
+            info!("Local Parakeet transcription available");
+            Ok(Some(t))
         }
-        TranscriptionMode::Auto => {
-            let mut local_transcriber: Option<Box<dyn Transcriber>> = None;
-            let mut fallback: Option<Box<dyn Transcriber>> = None;
-
-            let local_model_available =
-                match ensure_local_model_available(config, &model_path).await {
-                    Ok(v) => v,
-                    Err(e) => {
-                        warn!("Failed to auto-download local model files: {e}");
-                        false
-                    }
-                };
+        Err(e) => {
+            warn!("Failed to initialize local transcription: {e}");
+            Ok(None)
+        }
+    }
+}
 
-            // Try local
-            if audio::ffmpeg_available() && local_model_available {
-                match try_create_local(&model_path, ep) {
-                    Ok(t) => {
-                        info!("Local Parakeet transcription available");
-                        local_transcriber = Some(t);
-                    }
-                    Err(e) => {
-                        warn!("Failed to initialize local transcription: {e}");
-                    }
-                }
+fn mode_name(mode: &TranscriptionMode) -> &'static str {
+    match mode {
+        TranscriptionMode::Local => "local",
+        TranscriptionMode::Groq => "groq",
+        TranscriptionMode::OpenAi => "openai",
+        TranscriptionMode::Deepgram => "deepgram",
+        TranscriptionMode::Auto => "auto",
+    }
+}
+
+/// Backends to try, in order. A single-backend `mode` is just that backend;
+/// `auto` uses `chain`, or local followed by `cloudPriority` when unset.
+/// Unknown names, duplicates, and cloud backends without an API key are dropped.
+pub fn chain_order(config: &TranscriptionConfig, keys: &CloudKeys) -> Vec<&'static str> {
+    let configured: Vec<String> = match config.mode {
+        TranscriptionMode::Auto if !config.chain.is_empty() => config.chain.clone(),
+        TranscriptionMode::Auto => {
+            let cloud: Vec<String> = if config.cloud_priority.is_empty() {
+                CLOUD_BACKENDS.iter().map(|b| b.to_string()).collect()
             } else {
-                if !audio::ffmpeg_available() {
-                    info!("ffmpeg not found, local transcription unavailable");
-                }
-                if !local_model_available {
-                    info!(
-                        "Parakeet model not found at {model_path}, local transcription unavailable"
-                    );
+                config.cloud_priority.clone()
+            };
+            std::iter::once("local".to_string()).chain(cloud).collect()
+        }
+        ref mode => vec![mode_name(mode).to_string()],
+    };
+
+    let mut order = Vec::new();
+    for name in &configured {
+        let name = name.trim().to_lowercase();
+        let known = std::iter::once("local")
+            .chain(CLOUD_BACKENDS)
+            .find(|b| *b == name);
+        match known {
+            Some(backend) if order.contains(&backend) => {}
+            Some(backend) => {
+                if backend == "local" || keys.get(backend).is_some() {
+                    order.push(backend);
                 }
             }
+            None => warn!("Unknown transcription backend '{name}'"),
+        }
+    }
+    order
+}
 
-            // Set up Groq fallback
-            if let Some(key) = groq_api_key.filter(|k| !k.is_empty()) {
-                fallback = Some(Box::new(groq::GroqTranscriber::new(key)));
-            }
+/// Timeout for one backend: `timeoutSecs.<name>`, or the local/cloud default.
+/// Local has no default limit when it is the whole chain (`alone`): with
+/// nothing to fall back to, cutting off a long recording only fails it.
+fn backend_timeout(config: &TranscriptionConfig, name: &str, alone: bool) -> Option<Duration> {
+    let default = match name {
+        "local" if alone => None,
+        "local" => Some(DEFAULT_LOCAL_TIMEOUT_SECS),
+        _ => Some(DEFAULT_CLOUD_TIMEOUT_SECS),
+    };
+    let secs = config.timeout_secs.get(name).copied().or(default)?;
+    Some(Duration::from_secs(secs.max(1)))
+}
 
-            if local_transcriber.is_none() && fallback.is_none() {
-                info!("No transcription backend available (no local model, no Groq API key)");
-            }
+/// Build a cloud backend by name. `None` when it has no API key.
+fn create_cloud_backend(
+    name: &str,
+    config: &TranscriptionConfig,
+    keys: &CloudKeys,
+) -> Option<Box<dyn Transcriber>> {
+    let key = keys.get(name)?.to_string();
+    let backend: Box<dyn Transcriber> = match name {
+        "groq" => Box::new(groq::GroqTranscriber::new(key).with_prompt(vocabulary_prompt(config))),
+        "openai" => Box::new(
+            openai::OpenAiTranscriber::new(key)
+                .with_model(config.openai_model.clone())
+                .with_prompt(vocabulary_prompt(config)),
+        ),
+        "deepgram" => Box::new(
+            deepgram::DeepgramTranscriber::new(key)
+                .with_model(config.deepgram_model.clone())
+                .with_keyterms(config.post_process.vocabulary.clone()),
+        ),
+        _ => return None,
+    };
+    Some(backend)
+}
 
-            Ok(Box::new(AutoTranscriber {
-                local: local_transcriber,
-                fallback,
-            }))
-        }
+/// Build a Whisper prompt from the configured vocabulary so cloud backends
+/// spell names and jargon correctly in the first place.
+fn vocabulary_prompt(config: &TranscriptionConfig) -> Option<String> {
+    let vocab = &config.post_process.vocabulary;
+    if vocab.is_empty() {
+        None
+    } else {
+        Some(vocab.join(", "))
     }
 }
 
 /// Try to create a local transcriber. Returns an error if the parakeet feature
 /// is not compiled in or if model loading fails.
-fn try_create_local(model_path: &str, execution_provider: &str) -> Result<Box<dyn Transcriber>> {
+/// The local backend: loaded now with `eagerLoad`, otherwise on the first
+/// request and unloaded again after `keepAliveSecs` idle.
+fn local_backend(
+    config: &TranscriptionConfig,
+    model_path: &str,
+    execution_provider: &str,
+) -> Result<Box<dyn Transcriber>> {
+    // Without parakeet there's nothing to load later; fail now instead
+    if config.eager_load || !cfg!(feature = "parakeet") {
+        return try_create_local(model_path, execution_provider, config.workers);
+    }
+    let (model_path, execution_provider) = (model_path.to_string(), execution_provider.to_string());
+    let workers = config.workers;
+    let keep_alive =
+        (config.keep_alive_secs > 0).then(|| Duration::from_secs(config.keep_alive_secs));
+    Ok(Box::new(LazyTranscriber::new(
+        move || try_create_local(&model_path, &execution_provider, workers),
+        keep_alive,
+    )))
+}
+
+type CreateFn = dyn Fn() -> Result<Box<dyn Transcriber>> + Send + Sync;
+
+/// Creates its backend on the first request and drops it after `keep_alive`
+/// without one, so an idle gateway doesn't hold the model in memory.
+/// Requests already running keep the old backend alive until they finish.
+struct LazyTranscriber {
+    create: Arc<CreateFn>,
+    keep_alive: Option<Duration>,
+    state: Arc<tokio::sync::Mutex<LazyState>>,
+}
+
+#[derive(Default)]
+struct LazyState {
+    backend: Option<Arc<dyn Transcriber>>,
+    last_used: Option<std::time::Instant>,
+}
+
+impl LazyTranscriber {
+    fn new(
+        create: impl Fn() -> Result<Box<dyn Transcriber>> + Send + Sync + 'static,
+        keep_alive: Option<Duration>,
+    ) -> Self {
+        Self {
+            create: Arc::new(create),
+            keep_alive,
+            state: Arc::default(),
+        }
+    }
+
+    async fn acquire(&self) -> Result<Arc<dyn Transcriber>> {
+        // Held while loading, so concurrent requests wait for one load
+        let mut state = self.state.lock().await;
+        if let Some(ref backend) = state.backend {
+            return Ok(backend.clone());
+        }
+        let create = self.create.clone();
+        let started = std::time::Instant::now();
+        let backend: Arc<dyn Transcriber> = tokio::task::spawn_blocking(move || create())
+            .await
+            .map_err(|e| anyhow::anyhow!("Local model load failed: {e}"))??
+            .into();
+        info!(
+            "Loaded local transcription model in {:.2?}",
+            started.elapsed()
+        );
+        state.backend = Some(backend.clone());
+        Ok(backend)
+    }
+
+    /// Note a finished request and schedule the idle unload.
+    async fn release(&self) {
+        let now = std::time::Instant::now();
+        self.state.lock().await.last_used = Some(now);
+        let Some(keep_alive) = self.keep_alive else {
+            return;
+        };
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(keep_alive).await;
+            let mut state = state.lock().await;
+            // A later request has its own timer
+            if state.last_used == Some(now) && state.backend.take().is_some() {
+                info!(
+                    "Unloaded local transcription model after {}s idle",
+                    keep_alive.as_secs()
+                );
+            }
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl Transcriber for LazyTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        let backend = self.acquire().await?;
+        let result = backend.transcribe_file(file_path).await;
+        drop(backend);
+        self.release().await;
+        result
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let backend = self.acquire().await?;
+        let result = backend.transcribe(file_path, options).await;
+        drop(backend);
+        self.release().await;
+        result
+    }
+}
+
+fn try_create_local(
+    model_path: &str,
+    execution_provider: &str,
+    workers: usize,
+) -> Result<Box<dyn Transcriber>> {
     #[cfg(feature = "parakeet")]
     {
-        let t = local::LocalTranscriber::new(model_path, execution_provider)?;
+        let t = local::LocalTranscriber::new(model_path, execution_provider, workers)?;
         Ok(Box::new(t))
     }
     #[cfg(not(feature = "parakeet"))]
     {
-        let _ = (model_path, execution_provider);
+        let _ = (model_path, execution_provider, workers);
         Err(anyhow::anyhow!(
             "Local transcription not available: built without 'parakeet' feature"
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(Result<&'static str, u16>);
+
+    #[async_trait::async_trait]
+    impl Transcriber for Fixed {
+        async fn transcribe_file(&self, _file_path: &str) -> Result<String> {
+            match self.0 {
+                Ok(text) => Ok(text.to_string()),
+                Err(status) => Err(BackendError::Http {
+                    backend: "test",
+                    status: reqwest::StatusCode::from_u16(status).unwrap(),
+                }
+                .into()),
+            }
+        }
+    }
+
+    struct Slow;
+
+    #[async_trait::async_trait]
+    impl Transcriber for Slow {
+        async fn transcribe_file(&self, _file_path: &str) -> Result<String> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok("too late".into())
+        }
+    }
+
+    fn step(name: &'static str, backend: impl Transcriber + 'static) -> ChainStep {
+        ChainStep {
+            name,
+            backend: Box::new(backend),
+            timeout: Some(Duration::from_secs(5)),
+        }
+    }
+
+    fn chain(steps: Vec<ChainStep>) -> AutoTranscriber {
+        AutoTranscriber {
+            steps,
+            local_languages: Vec::new(),
+        }
+    }
+
+    fn keys(groq: bool, openai: bool, deepgram: bool) -> CloudKeys {
+        let key = |on: bool| on.then(|| "key".to_string());
+        CloudKeys {
+            groq: key(groq),
+            openai: key(openai),
+            deepgram: key(deepgram),
+        }
+    }
+
+    #[tokio::test]
+    async fn lazy_transcriber_loads_on_use_and_unloads_when_idle() {
+        let loads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = loads.clone();
+        let lazy = LazyTranscriber::new(
+            move || {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(Box::new(Fixed(Ok("hi"))) as Box<dyn Transcriber>)
+            },
+            Some(Duration::from_millis(50)),
+        );
+        let loaded = || loads.load(std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(loaded(), 0);
+
+        assert_eq!(lazy.transcribe_file("a.ogg").await.unwrap(), "hi");
+        assert_eq!(lazy.transcribe_file("b.ogg").await.unwrap(), "hi");
+        assert_eq!(loaded(), 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(lazy.state.lock().await.backend.is_none());
+        assert_eq!(lazy.transcribe_file("c.ogg").await.unwrap(), "hi");
+        assert_eq!(loaded(), 2);
+    }
+
+    #[tokio::test]
+    async fn background_transcriber_waits_for_init() {
+        let ready = BackgroundTranscriber::from_future(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(Box::new(Fixed(Ok("hello"))) as Box<dyn Transcriber>)
+        });
+        assert_eq!(ready.transcribe_file("a.ogg").await.unwrap(), "hello");
+        assert_eq!(ready.transcribe_file("b.ogg").await.unwrap(), "hello");
+
+        let failed = BackgroundTranscriber::from_future(async { anyhow::bail!("no model") });
+        for _ in 0..2 {
+            let err = failed.transcribe_file("a.ogg").await.unwrap_err();
+            assert!(err.to_string().contains("no model"), "{err}");
+        }
+    }
+
+    #[test]
+    fn chain_order_defaults_and_skips_missing_keys() {
+        let config = TranscriptionConfig::default();
+        assert_eq!(
+            chain_order(&config, &keys(true, true, true)),
+            vec!["local", "groq", "openai", "deepgram"]
+        );
+        assert_eq!(
+            chain_order(&config, &keys(false, true, true)),
+            vec!["local", "openai", "deepgram"]
+        );
+        assert_eq!(chain_order(&config, &CloudKeys::default()), vec!["local"]);
+    }
+
+    #[test]
+    fn chain_order_follows_priority() {
+        let config = TranscriptionConfig {
+            cloud_priority: vec![
+                "Deepgram".into(),
+                "whisperx".into(),
+                "groq".into(),
+                "deepgram".into(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            chain_order(&config, &keys(true, true, true)),
+            vec!["local", "deepgram", "groq"]
+        );
+    }
+
+    #[test]
+    fn explicit_chain_overrides_priority() {
+        let config = TranscriptionConfig {
+            chain: vec!["openai".into(), "local".into()],
+            cloud_priority: vec!["groq".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            chain_order(&config, &keys(true, true, false)),
+            vec!["openai", "local"]
+        );
+
+        let single = TranscriptionConfig {
+            mode: TranscriptionMode::Deepgram,
+            chain: vec!["local".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            chain_order(&single, &keys(false, false, true)),
+            vec!["deepgram"]
+        );
+        assert!(chain_order(&single, &CloudKeys::default()).is_empty());
+    }
+
+    #[test]
+    fn timeouts_use_config_or_defaults() {
+        let config = TranscriptionConfig {
+            timeout_secs: [("groq".to_string(), 15)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            backend_timeout(&config, "groq", false),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            backend_timeout(&config, "local", false),
+            Some(Duration::from_secs(DEFAULT_LOCAL_TIMEOUT_SECS))
+        );
+        assert_eq!(
+            backend_timeout(&config, "openai", false),
+            Some(Duration::from_secs(DEFAULT_CLOUD_TIMEOUT_SECS))
+        );
+
+        // Local on its own has nowhere to fall back to, so only an explicit limit applies
+        assert_eq!(backend_timeout(&config, "local", true), None);
+        let capped = TranscriptionConfig {
+            timeout_secs: [("local".to_string(), 600)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            backend_timeout(&capped, "local", true),
+            Some(Duration::from_secs(600))
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_through_recoverable_failures() {
+        let auto = chain(vec![
+            step("local", Fixed(Err(415))),
+            step("groq", Fixed(Err(503))),
+            step("openai", Fixed(Ok("from openai"))),
+            step("deepgram", Fixed(Ok("from deepgram"))),
+        ]);
+        let transcript = auto
+            .transcribe("a.ogg", &TranscribeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(transcript.text, "from openai");
+        assert_eq!(transcript.backend.as_deref(), Some("openai"));
+
+        let failing = chain(vec![
+            step("groq", Fixed(Err(503))),
+            step("deepgram", Fixed(Err(429))),
+        ]);
+        let err = failing.transcribe_file("a.ogg").await.unwrap_err();
+        assert_eq!(error::classify(&err), error::FailureKind::RateLimited);
+    }
+
+    #[tokio::test]
+    async fn stops_on_auth_error() {
+        let auto = chain(vec![
+            step("groq", Fixed(Err(401))),
+            step("openai", Fixed(Ok("from openai"))),
+        ]);
+        let err = auto.transcribe_file("a.ogg").await.unwrap_err();
+        assert_eq!(error::classify(&err), error::FailureKind::Auth);
+    }
+
+    #[tokio::test]
+    async fn slow_backend_times_out_and_falls_back() {
+        let mut slow = step("local", Slow);
+        slow.timeout = Some(Duration::from_millis(50));
+        let auto = chain(vec![slow, step("groq", Fixed(Ok("from groq")))]);
+        assert_eq!(auto.transcribe_file("a.ogg").await.unwrap(), "from groq");
+    }
+}
@@ -0,0 +1,182 @@
+//! Length and size guards for incoming voice messages.
+//!
+//! Channels check the metadata they get for free (Telegram reports duration and
+//! size before download) so oversized recordings are never fetched, then check
+//! the downloaded file again before it's decoded or sent to a cloud backend.
+
+use patina_config::TranscriptionConfig;
+
+use crate::audio;
+
+/// Limits from `transcription.maxDurationSecs` / `maxFileMb`. `None` is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MediaLimits {
+    pub max_duration_secs: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+/// Audio rejected for being over a limit.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LimitExceeded {
+    #[error("audio is {} long, over the {} limit", format_duration(*.actual), format_duration(*.max))]
+    Duration { actual: u64, max: u64 },
+    #[error("audio is {} MB, over the {} MB limit", .actual.div_ceil(MB), .max / MB)]
+    Size { actual: u64, max: u64 },
+}
+
+const MB: u64 = 1024 * 1024;
+
+impl MediaLimits {
+    /// Read limits from config, treating 0 as "no limit".
+    pub fn from_config(config: &TranscriptionConfig) -> Self {
+        Self {
+            max_duration_secs: Some(config.max_duration_secs).filter(|s| *s > 0),
+            max_bytes: Some(config.max_file_mb)
+                .filter(|mb| *mb > 0)
+                .map(|mb| mb.saturating_mul(MB)),
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_duration_secs.is_none() && self.max_bytes.is_none()
+    }
+
+    /// Check known metadata against the limits. Unknown values pass.
+    pub fn check(
+        &self,
+        duration_secs: Option<u64>,
+        size_bytes: Option<u64>,
+    ) -> Result<(), LimitExceeded> {
+        if let (Some(actual), Some(max)) = (size_bytes, self.max_bytes) {
+            if actual > max {
+                return Err(LimitExceeded::Size { actual, max });
+            }
+        }
+        if let (Some(actual), Some(max)) = (duration_secs, self.max_duration_secs) {
+            if actual > max {
+                return Err(LimitExceeded::Duration { actual, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a file on disk, probing its duration only if a duration limit is set.
+    pub async fn check_file(&self, path: &str) -> Result<(), LimitExceeded> {
+        if self.is_unlimited() {
+            return Ok(());
+        }
+        let size = tokio::fs::metadata(path).await.ok().map(|m| m.len());
+        self.check(None, size)?;
+        if self.max_duration_secs.is_some() {
+            self.check(audio::probe_duration_secs(path).await, None)?;
+        }
+        Ok(())
+    }
+}
+
+impl LimitExceeded {
+    /// A friendly reply for the sender explaining why nothing was transcribed.
+    pub fn refusal(&self) -> String {
+        match self {
+            Self::Duration { actual, max } => format!(
+                "Sorry, that recording is {} long and I only transcribe audio up to {}. \
+                 Could you send a shorter clip?",
+                format_duration(*actual),
+                format_duration(*max)
+            ),
+            Self::Size { actual, max } => format!(
+                "Sorry, that file is {} MB and I only transcribe audio up to {} MB. \
+                 Could you send a shorter or more compressed clip?",
+                actual.div_ceil(MB),
+                max / MB
+            ),
+        }
+    }
+}
+
+/// "45s", "15 min", "2h 5m".
+fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    match (h, m, s) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m} min"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, 0, _) => format!("{h}h"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> MediaLimits {
+        MediaLimits {
+            max_duration_secs: Some(900),
+            max_bytes: Some(25 * MB),
+        }
+    }
+
+    #[test]
+    fn zero_means_unlimited() {
+        let config = TranscriptionConfig::default();
+        assert!(MediaLimits::from_config(&config).is_unlimited());
+        let config = TranscriptionConfig {
+            max_duration_secs: 60,
+            max_file_mb: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            MediaLimits::from_config(&config),
+            MediaLimits {
+                max_duration_secs: Some(60),
+                max_bytes: Some(10 * MB),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_long_or_large_audio() {
+        let limits = limits();
+        assert!(limits.check(Some(900), Some(25 * MB)).is_ok());
+        assert!(limits.check(None, None).is_ok());
+
+        let err = limits.check(Some(2 * 3600 + 5 * 60), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "audio is 2h 5m long, over the 15 min limit"
+        );
+        assert!(err
+            .refusal()
+            .starts_with("Sorry, that recording is 2h 5m long"));
+
+        let err = limits.check(Some(10), Some(40 * MB + 1)).unwrap_err();
+        assert_eq!(err.to_string(), "audio is 41 MB, over the 25 MB limit");
+    }
+
+    #[tokio::test]
+    async fn checks_file_size_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.ogg");
+        std::fs::write(&path, vec![0u8; 2048]).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let tight = MediaLimits {
+            max_duration_secs: None,
+            max_bytes: Some(1024),
+        };
+        assert!(matches!(
+            tight.check_file(&path).await,
+            Err(LimitExceeded::Size { actual: 2048, .. })
+        ));
+        assert!(MediaLimits::default().check_file(&path).await.is_ok());
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(600), "10 min");
+        assert_eq!(format_duration(90), "1m 30s");
+        assert_eq!(format_duration(7200), "2h");
+    }
+}
@@ -1,18 +1,26 @@
 //! Local transcription backend using NVIDIA Parakeet TDT via ONNX Runtime.
 //!
 //! The ParakeetTDT model is `!Send` (its ExecutionConfig contains `Rc`),
-//! so it runs on a dedicated OS thread. Async callers communicate via channels.
+//! so each model instance runs on a dedicated OS thread. A small pool of
+//! such workers pulls from one shared queue, so a long file from one chat
+//! doesn't hold up voice notes from others. Async callers communicate via
+//! channels.
 
 #[cfg(feature = "parakeet")]
 pub use inner::LocalTranscriber;
 
 #[cfg(feature = "parakeet")]
 mod inner {
+    use std::sync::{Arc, Mutex};
+
     use anyhow::Result;
     use tokio::sync::{mpsc, oneshot};
-    use tracing::info;
+    use tracing::{info, warn};
 
     use crate::audio::convert_to_wav_16k;
+    use crate::error::BackendError;
+    use crate::metrics;
+    use crate::provider::{record_loaded, select_provider, ExecutionProviderKind, LoadedProvider};
 
     /// Request sent to the worker thread.
     struct TranscribeRequest {
@@ -20,79 +28,162 @@ mod inner {
         reply_tx: oneshot::Sender<Result<String>>,
     }
 
+    /// Queue shared by all workers. Only one idle worker waits on it at a time.
+    type SharedReceiver = Arc<Mutex<mpsc::Receiver<TranscribeRequest>>>;
+
     /// Local transcription backend using ParakeetTDT.
     ///
     /// This is `Send + Sync` because it only holds a channel sender.
-    /// The actual model lives on a dedicated OS thread.
+    /// The actual models live on dedicated worker threads.
     pub struct LocalTranscriber {
         request_tx: mpsc::Sender<TranscribeRequest>,
+        provider: ExecutionProviderKind,
     }
 
     impl LocalTranscriber {
-        /// Initialize the local transcriber.
+        /// Initialize the local transcriber with `workers` model instances.
         ///
-        /// Spawns a dedicated OS thread that loads the model and processes
-        /// requests sequentially. Returns an error if model loading fails.
-        pub fn new(model_path: &str, execution_provider: &str) -> Result<Self> {
+        /// Spawns one OS thread per worker, each loading its own copy of the
+        /// model. If the configured GPU provider is unavailable or fails to
+        /// load, falls back to CPU. Workers that fail to load are dropped with
+        /// a warning; returns an error only if no worker loaded.
+        pub fn new(model_path: &str, execution_provider: &str, workers: usize) -> Result<Self> {
+            let workers = workers.max(1);
             let (request_tx, request_rx) = mpsc::channel::<TranscribeRequest>(32);
-            let model_path = model_path.to_string();
-            let ep = execution_provider.to_string();
-
-            // Report whether model loading succeeded
-            let (init_tx, init_rx) = std::sync::mpsc::channel::<Result<()>>();
-
-            std::thread::Builder::new()
-                .name("parakeet-worker".into())
-                .spawn(move || {
-                    worker_thread(model_path, ep, request_rx, init_tx);
-                })?;
-
-            // Block waiting for model initialization result (happens once at startup)
-            match init_rx.recv() {
-                Ok(Ok(())) => {
-                    info!("Local Parakeet TDT model loaded successfully");
-                    Ok(Self { request_tx })
+            let request_rx: SharedReceiver = Arc::new(Mutex::new(request_rx));
+            let selection = select_provider(Some(execution_provider));
+            if let Some(ref reason) = selection.fallback_reason {
+                warn!(
+                    "Execution provider '{}' unavailable ({reason}), using CPU",
+                    selection.requested
+                );
+            }
+
+            // Each worker reports whether model loading succeeded, and on which provider
+            let (init_tx, init_rx) = std::sync::mpsc::channel::<Result<ExecutionProviderKind>>();
+
+            for i in 0..workers {
+                let model_path = model_path.to_string();
+                let request_rx = request_rx.clone();
+                let init_tx = init_tx.clone();
+                let requested = selection.selected;
+                std::thread::Builder::new()
+                    .name(format!("parakeet-worker-{i}"))
+                    .spawn(move || {
+                        worker_thread(model_path, requested, request_rx, init_tx);
+                    })?;
+            }
+            drop(init_tx);
+
+            // Block waiting for model initialization results (happens once at startup)
+            let mut loaded: Option<ExecutionProviderKind> = None;
+            let mut last_err = None;
+            for result in init_rx.iter().take(workers) {
+                match result {
+                    Ok(provider) => {
+                        // Report the first worker's provider; they all load the same way
+                        loaded.get_or_insert(provider);
+                    }
+                    Err(e) => {
+                        warn!("Parakeet worker failed to load: {e}");
+                        last_err = Some(e);
+                    }
                 }
-                Ok(Err(e)) => Err(e),
-                Err(_) => Err(anyhow::anyhow!(
-                    "Parakeet worker thread died during initialization"
-                )),
             }
+
+            let Some(loaded) = loaded else {
+                return Err(last_err.unwrap_or_else(|| {
+                    anyhow::anyhow!("Parakeet worker thread died during initialization")
+                }));
+            };
+
+            let ready = metrics::queue_stats().workers;
+            info!(
+                "Local Parakeet TDT model loaded successfully (provider: {}, workers: {ready})",
+                loaded.as_str()
+            );
+            let fallback_reason = if loaded == selection.selected {
+                selection.fallback_reason
+            } else {
+                Some(format!(
+                    "{} failed to initialize",
+                    selection.selected.as_str()
+                ))
+            };
+            record_loaded(&LoadedProvider {
+                requested: selection.requested,
+                loaded,
+                fallback_reason,
+                loaded_at: chrono::Local::now().to_rfc3339(),
+                pid: std::process::id(),
+            });
+            Ok(Self {
+                request_tx,
+                provider: loaded,
+            })
+        }
+
+        /// The execution provider the model actually loaded with.
+        pub fn execution_provider(&self) -> ExecutionProviderKind {
+            self.provider
         }
     }
 
-    fn worker_thread(
-        model_path: String,
-        execution_provider: String,
-        mut request_rx: mpsc::Receiver<TranscribeRequest>,
-        init_tx: std::sync::mpsc::Sender<Result<()>>,
-    ) {
+    /// Build the parakeet execution config for a provider.
+    fn execution_config(provider: ExecutionProviderKind) -> Option<parakeet_rs::ExecutionConfig> {
         #[allow(unused_imports)]
-        use parakeet_rs::{ExecutionConfig, ExecutionProvider, ParakeetTDT, Transcriber};
+        use parakeet_rs::{ExecutionConfig, ExecutionProvider};
 
-        // Build execution config
-        let config = match execution_provider.as_str() {
+        match provider {
             #[cfg(feature = "cuda")]
-            "cuda" => Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::Cuda)),
-            #[cfg(feature = "migraphx")]
-            "migraphx" => {
-                Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::MiGraphX))
+            ExecutionProviderKind::Cuda => {
+                Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::Cuda))
             }
             #[cfg(feature = "tensorrt")]
-            "tensorrt" => {
+            ExecutionProviderKind::TensorRt => {
                 Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::TensorRt))
             }
-            "cpu" | "" => None,
-            other => {
-                info!("Unknown execution provider '{other}', falling back to CPU");
-                None
+            #[cfg(feature = "migraphx")]
+            ExecutionProviderKind::MiGraphX => {
+                Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::MiGraphX))
+            }
+            #[cfg(feature = "coreml")]
+            ExecutionProviderKind::CoreMl => {
+                Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::CoreML))
             }
-        };
+            #[cfg(feature = "directml")]
+            ExecutionProviderKind::DirectMl => {
+                Some(ExecutionConfig::new().with_execution_provider(ExecutionProvider::DirectML))
+            }
+            _ => None,
+        }
+    }
 
-        // Load model
-        let mut model = match ParakeetTDT::from_pretrained(&model_path, config) {
-            Ok(m) => {
-                let _ = init_tx.send(Ok(()));
+    fn worker_thread(
+        model_path: String,
+        provider: ExecutionProviderKind,
+        request_rx: SharedReceiver,
+        init_tx: std::sync::mpsc::Sender<Result<ExecutionProviderKind>>,
+    ) {
+        use parakeet_rs::{ParakeetTDT, Transcriber};
+
+        // Load model, retrying on CPU if the GPU provider fails to initialize
+        let loaded = match ParakeetTDT::from_pretrained(&model_path, execution_config(provider)) {
+            Ok(m) => Ok((m, provider)),
+            Err(e) if provider != ExecutionProviderKind::Cpu => {
+                warn!(
+                    "Failed to load Parakeet model with {}: {e}; retrying on CPU",
+                    provider.as_str()
+                );
+                ParakeetTDT::from_pretrained(&model_path, None)
+                    .map(|m| (m, ExecutionProviderKind::Cpu))
+            }
+            Err(e) => Err(e),
+        };
+        let mut model = match loaded {
+            Ok((m, loaded_provider)) => {
+                metrics::worker_started();
+                let _ = init_tx.send(Ok(loaded_provider));
                 m
             }
             Err(e) => {
@@ -102,13 +193,22 @@ mod inner {
                 return;
             }
         };
+        drop(init_tx);
 
-        // Process requests sequentially
-        while let Some(req) = request_rx.blocking_recv() {
+        // Pull requests off the shared queue until the transcriber is dropped
+        loop {
+            let req = {
+                let Ok(mut rx) = request_rx.lock() else { break };
+                rx.blocking_recv()
+            };
+            let Some(req) = req else { break };
+
+            metrics::started();
             let result = model
                 .transcribe_file(&req.wav_path, None)
                 .map(|r| r.text.trim().to_string())
                 .map_err(|e| anyhow::anyhow!("Transcription failed: {e}"));
+            metrics::finished(result.is_ok());
 
             // Clean up the temporary WAV file
             let _ = std::fs::remove_file(&req.wav_path);
@@ -116,21 +216,31 @@ mod inner {
             let _ = req.reply_tx.send(result);
         }
 
+        metrics::worker_stopped();
         info!("Parakeet worker thread shutting down");
     }
 
     #[async_trait::async_trait]
     impl crate::Transcriber for LocalTranscriber {
         async fn transcribe_file(&self, file_path: &str) -> Result<String> {
-            // Convert to 16kHz mono WAV via ffmpeg
-            let wav_path = convert_to_wav_16k(file_path).await?;
+            // Convert to 16kHz mono WAV
+            let wav_path = convert_to_wav_16k(file_path)
+                .await
+                .map_err(|e| BackendError::Decode(e.to_string()))?;
 
-            // Send to worker thread
+            // Queue for the next free worker
             let (reply_tx, reply_rx) = oneshot::channel();
-            self.request_tx
+            // Count before sending so a fast worker never sees the queue go negative
+            metrics::enqueued();
+            if self
+                .request_tx
                 .send(TranscribeRequest { wav_path, reply_tx })
                 .await
-                .map_err(|_| anyhow::anyhow!("Parakeet worker thread not running"))?;
+                .is_err()
+            {
+                metrics::cancelled();
+                anyhow::bail!("Parakeet worker thread not running");
+            }
 
             // Await result
             reply_rx
@@ -0,0 +1,73 @@
+//! Process-wide counters for the local transcription worker pool.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+static WORKERS: AtomicUsize = AtomicUsize::new(0);
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static COMPLETED: AtomicUsize = AtomicUsize::new(0);
+static FAILED: AtomicUsize = AtomicUsize::new(0);
+
+/// Snapshot of the local transcription queue.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// Worker threads with a loaded model.
+    pub workers: usize,
+    /// Requests waiting for a free worker.
+    pub queued: usize,
+    /// Requests currently being transcribed.
+    pub active: usize,
+    pub completed: usize,
+    pub failed: usize,
+}
+
+pub fn queue_stats() -> QueueStats {
+    QueueStats {
+        workers: WORKERS.load(Ordering::Relaxed),
+        queued: QUEUED.load(Ordering::Relaxed),
+        active: ACTIVE.load(Ordering::Relaxed),
+        completed: COMPLETED.load(Ordering::Relaxed),
+        failed: FAILED.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn worker_started() {
+    WORKERS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn worker_stopped() {
+    WORKERS.fetch_sub(1, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn enqueued() {
+    QUEUED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A request never reached the queue.
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn cancelled() {
+    QUEUED.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// A worker picked a request off the queue.
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn started() {
+    QUEUED.fetch_sub(1, Ordering::Relaxed);
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "parakeet"), allow(dead_code))]
+pub(crate) fn finished(ok: bool) {
+    ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    if ok {
+        COMPLETED.fetch_add(1, Ordering::Relaxed);
+    } else {
+        FAILED.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,333 @@
+//! Microphone capture for push-to-talk voice input.
+//!
+//! Records from the default (or a named) input device with cpal until the
+//! voice activity detector hears a pause, then writes 16kHz mono WAV ready for
+//! any transcriber. In wake-word mode the stream first runs through the
+//! wake-word detector, and recording starts once it fires. Capture needs the
+//! `mic` feature (and `wakeword` for [`record_after_wake_word`]); without them
+//! these return an error.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use patina_config::VoiceInputConfig;
+
+use crate::vad::VadConfig;
+use crate::wakeword::WakeWordDetector;
+
+/// How long to wait for someone to start talking before giving up.
+const NO_SPEECH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings for a single recording.
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    /// Input device name. `None` uses the system default.
+    pub device: Option<String>,
+    pub vad: VadConfig,
+    /// Hard cap on recording length.
+    pub max_duration: Duration,
+    /// Stop without a recording if no speech starts within this long.
+    pub no_speech_timeout: Duration,
+}
+
+impl RecordOptions {
+    pub fn from_config(config: &VoiceInputConfig) -> Self {
+        Self {
+            device: config.device.clone().filter(|d| !d.is_empty()),
+            vad: VadConfig {
+                silence_ms: config.silence_ms,
+                threshold: config.threshold,
+                ..VadConfig::default()
+            },
+            max_duration: Duration::from_secs(config.max_secs.max(1) as u64),
+            no_speech_timeout: NO_SPEECH_TIMEOUT,
+        }
+    }
+}
+
+/// Whether microphone capture is compiled in.
+pub fn mic_available() -> bool {
+    cfg!(feature = "mic")
+}
+
+/// Record one utterance into `output` as 16kHz mono WAV.
+///
+/// Returns `false` (and writes nothing) when nobody spoke. Blocking; call from
+/// `spawn_blocking`.
+#[cfg(feature = "mic")]
+pub fn record_utterance(options: &RecordOptions, output: &Path) -> Result<bool> {
+    use crate::vad::Vad;
+
+    let mic = capture::Microphone::open(options.device.as_deref())?;
+    let vad = Vad::new(options.vad, mic.rate);
+    capture::record(&mic, vad, options, output)
+}
+
+#[cfg(not(feature = "mic"))]
+pub fn record_utterance(_options: &RecordOptions, _output: &Path) -> Result<bool> {
+    anyhow::bail!("Microphone input needs a build with the 'mic' feature")
+}
+
+/// Listen until `detector` hears the wake word, then record the utterance that
+/// follows into `output` as 16kHz mono WAV.
+///
+/// Returns `false` when nothing was said after the wake word. Blocks until the
+/// wake word is heard; call from `spawn_blocking`.
+#[cfg(feature = "wakeword")]
+pub fn record_after_wake_word(
+    detector: &mut WakeWordDetector,
+    options: &RecordOptions,
+    output: &Path,
+) -> Result<bool> {
+    use crate::decode::TARGET_RATE;
+    use crate::vad::Vad;
+
+    let mic = capture::Microphone::open(options.device.as_deref())?;
+    let mut resampler = LinearResampler::new(mic.rate, TARGET_RATE);
+    // Runs while idle so the noise floor is known by the time the user speaks
+    let mut idle = Vad::new(options.vad, mic.rate);
+    detector.reset();
+    loop {
+        let Some(chunk) = mic.read()? else {
+            continue;
+        };
+        idle.push(&chunk);
+        if detector.push(&resampler.process(&chunk))? {
+            break;
+        }
+    }
+    let vad = Vad::with_noise_floor(options.vad, mic.rate, idle.noise_floor());
+    capture::record(&mic, vad, options, output)
+}
+
+#[cfg(not(feature = "wakeword"))]
+pub fn record_after_wake_word(
+    _detector: &mut WakeWordDetector,
+    _options: &RecordOptions,
+    _output: &Path,
+) -> Result<bool> {
+    anyhow::bail!("Wake-word detection needs a build with the 'wakeword' feature")
+}
+
+/// Streaming linear-interpolation resampler for live audio, where chunk sizes
+/// vary and latency matters more than fidelity.
+pub struct LinearResampler {
+    /// Input samples advanced per output sample.
+    step: f64,
+    /// Next output position, relative to the start of the next chunk. -1.0
+    /// refers to the last sample of the previous chunk.
+    pos: f64,
+    last: f32,
+}
+
+impl LinearResampler {
+    pub fn new(from: u32, to: u32) -> Self {
+        Self {
+            step: from as f64 / to.max(1) as f64,
+            pos: 0.0,
+            last: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let Some(&tail) = input.last() else {
+            return Vec::new();
+        };
+        let n = input.len() as f64;
+        let at = |i: isize| if i < 0 { self.last } else { input[i as usize] };
+        let mut out = Vec::with_capacity((n / self.step) as usize + 1);
+        while self.pos < n - 1.0 {
+            let i = self.pos.floor();
+            let frac = (self.pos - i) as f32;
+            let (a, b) = (at(i as isize), at(i as isize + 1));
+            out.push(a + (b - a) * frac);
+            self.pos += self.step;
+        }
+        self.pos -= n;
+        self.last = tail;
+        out
+    }
+}
+
+#[cfg(feature = "mic")]
+mod capture {
+    use std::path::Path;
+    use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+    use std::time::{Duration, Instant};
+
+    use anyhow::{anyhow, bail, Context, Result};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, Sample, SampleFormat, SizedSample};
+    use tracing::warn;
+
+    use super::RecordOptions;
+    use crate::decode::{push_mono, resample, write_wav, TARGET_RATE};
+    use crate::vad::{Vad, VadEvent};
+
+    /// An open input stream delivering mono f32 chunks at the device rate.
+    pub(super) struct Microphone {
+        // Capture stops when the stream is dropped
+        _stream: cpal::Stream,
+        rx: Receiver<Vec<f32>>,
+        pub(super) rate: u32,
+    }
+
+    impl Microphone {
+        pub(super) fn open(device: Option<&str>) -> Result<Self> {
+            let device = input_device(device)?;
+            let supported = device
+                .default_input_config()
+                .context("Microphone has no usable input format")?;
+            let rate = supported.sample_rate().0;
+            let config = supported.config();
+
+            let (tx, rx) = mpsc::channel();
+            let stream = match supported.sample_format() {
+                SampleFormat::F32 => build_stream::<f32>(&device, &config, tx)?,
+                SampleFormat::I16 => build_stream::<i16>(&device, &config, tx)?,
+                SampleFormat::U16 => build_stream::<u16>(&device, &config, tx)?,
+                other => bail!("Unsupported microphone sample format: {other:?}"),
+            };
+            stream.play().context("Failed to start microphone")?;
+            Ok(Self {
+                _stream: stream,
+                rx,
+                rate,
+            })
+        }
+
+        /// The next chunk of samples, or `None` if none arrived within 100ms.
+        pub(super) fn read(&self) -> Result<Option<Vec<f32>>> {
+            match self.rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(chunk) => Ok(Some(chunk)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => bail!("Microphone stream closed"),
+            }
+        }
+    }
+
+    /// Capture until `vad` reports the end of an utterance (or a time limit),
+    /// then write 16kHz WAV. Returns `false` if no speech started.
+    pub(super) fn record(
+        mic: &Microphone,
+        mut vad: Vad,
+        options: &RecordOptions,
+        output: &Path,
+    ) -> Result<bool> {
+        let mut samples = Vec::new();
+        let mut heard_speech = false;
+        let started = Instant::now();
+        loop {
+            let elapsed = started.elapsed();
+            if elapsed >= options.max_duration
+                || (!heard_speech && elapsed >= options.no_speech_timeout)
+            {
+                break;
+            }
+            let Some(chunk) = mic.read()? else {
+                continue;
+            };
+            let event = vad.push(&chunk);
+            samples.extend_from_slice(&chunk);
+            match event {
+                Some(VadEvent::SpeechStarted) => heard_speech = true,
+                Some(VadEvent::SpeechEnded) => break,
+                None => {}
+            }
+        }
+
+        if !heard_speech {
+            return Ok(false);
+        }
+        let resampled = resample(&samples, mic.rate, TARGET_RATE)?;
+        write_wav(output, &resampled)?;
+        Ok(true)
+    }
+
+    /// The named input device, or the system default.
+    fn input_device(name: Option<&str>) -> Result<cpal::Device> {
+        let host = cpal::default_host();
+        let Some(name) = name else {
+            return host
+                .default_input_device()
+                .ok_or_else(|| anyhow!("No default microphone found"));
+        };
+        let mut available = Vec::new();
+        for device in host.input_devices()? {
+            let Ok(device_name) = device.name() else {
+                continue;
+            };
+            if device_name == name {
+                return Ok(device);
+            }
+            available.push(device_name);
+        }
+        Err(anyhow!(
+            "Microphone '{name}' not found (available: {})",
+            available.join(", ")
+        ))
+    }
+
+    /// Open an input stream that sends mono f32 chunks to `tx`.
+    fn build_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        tx: Sender<Vec<f32>>,
+    ) -> Result<cpal::Stream>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let channels = config.channels as usize;
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let interleaved: Vec<f32> = data.iter().map(|s| s.to_sample::<f32>()).collect();
+                let mut mono = Vec::with_capacity(interleaved.len() / channels.max(1));
+                push_mono(&mut mono, &interleaved, channels);
+                // The receiver is gone once recording stops
+                let _ = tx.send(mono);
+            },
+            |err| warn!("Microphone stream error: {err}"),
+            None,
+        )?;
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_from_config() {
+        let config = VoiceInputConfig {
+            device: Some(String::new()),
+            silence_ms: 1200,
+            max_secs: 0,
+            threshold: 0.05,
+            ..Default::default()
+        };
+        let options = RecordOptions::from_config(&config);
+        assert!(options.device.is_none());
+        assert_eq!(options.vad.silence_ms, 1200);
+        assert_eq!(options.vad.threshold, 0.05);
+        assert_eq!(options.max_duration, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn resamples_across_chunks() {
+        let input: Vec<f32> = (0..4800).map(|i| i as f32).collect();
+        let mut r = LinearResampler::new(48_000, 16_000);
+        let out: Vec<f32> = input.chunks(441).flat_map(|c| r.process(c)).collect();
+        assert!((1599..=1600).contains(&out.len()));
+        // A ramp stays a ramp: every third input sample, no seams at chunk edges
+        assert!(out.iter().enumerate().all(|(i, v)| *v == (i * 3) as f32));
+
+        let mut same = LinearResampler::new(16_000, 16_000);
+        let out: Vec<f32> = input.chunks(100).flat_map(|c| same.process(c)).collect();
+        assert_eq!(out[..4799], input[..4799]);
+    }
+}
@@ -0,0 +1,201 @@
+//! OpenAI audio transcription backend.
+//!
+//! Also holds the multipart request shared with Groq, whose Whisper endpoint
+//! is OpenAI-compatible.
+
+use anyhow::{bail, Result};
+use tracing::error;
+
+use crate::audio;
+use crate::error::BackendError;
+use crate::language::normalize_language;
+use crate::{TranscribeOptions, Transcriber, Transcript};
+
+pub const DEFAULT_MODEL: &str = "whisper-1";
+
+/// Cloud transcription backend via OpenAI's audio transcriptions API.
+pub struct OpenAiTranscriber {
+    api_key: String,
+    model: String,
+    prompt: Option<String>,
+}
+
+impl OpenAiTranscriber {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            model: DEFAULT_MODEL.to_string(),
+            prompt: None,
+        }
+    }
+
+    /// Override the model (e.g. "gpt-4o-transcribe").
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        if let Some(model) = model.filter(|m| !m.is_empty()) {
+            self.model = model;
+        }
+        self
+    }
+
+    /// Set a prompt used to bias spelling of names and jargon.
+    pub fn with_prompt(mut self, prompt: Option<String>) -> Self {
+        self.prompt = prompt;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Transcriber for OpenAiTranscriber {
+    async fn transcribe_file(&self, file_path: &str) -> Result<String> {
+        Ok(self
+            .transcribe(file_path, &TranscribeOptions::default())
+            .await?
+            .text)
+    }
+
+    async fn transcribe(&self, file_path: &str, options: &TranscribeOptions) -> Result<Transcript> {
+        let request = WhisperRequest {
+            backend: "OpenAI",
+            url: "https://api.openai.com/v1/audio/transcriptions",
+            api_key: &self.api_key,
+            model: &self.model,
+            prompt: self.prompt.as_deref(),
+        };
+        request.send(file_path, options).await
+    }
+}
+
+/// A call to an OpenAI-compatible `/audio/transcriptions` endpoint.
+pub(crate) struct WhisperRequest<'a> {
+    /// Backend name for logs and errors.
+    pub backend: &'static str,
+    pub url: &'a str,
+    pub api_key: &'a str,
+    pub model: &'a str,
+    pub prompt: Option<&'a str>,
+}
+
+impl WhisperRequest<'_> {
+    pub(crate) async fn send(
+        &self,
+        file_path: &str,
+        options: &TranscribeOptions,
+    ) -> Result<Transcript> {
+        let path = std::path::Path::new(file_path);
+        if !path.exists() {
+            bail!("Audio file not found: {file_path}");
+        }
+
+        let file_bytes = tokio::fs::read(path).await?;
+
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        let file_part = reqwest::multipart::Part::bytes(file_bytes)
+            .file_name(file_name)
+            .mime_str(audio::mime_type(path))?;
+
+        // verbose_json includes the detected language alongside the text, but
+        // only Whisper models support it
+        let response_format = if self.model.contains("whisper") {
+            "verbose_json"
+        } else {
+            "json"
+        };
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file_part)
+            .text("model", self.model.to_string())
+            .text("response_format", response_format);
+        if let Some(prompt) = self.prompt {
+            form = form.text("prompt", prompt.to_string());
+        }
+        if let Some(ref language) = options.language {
+            form = form.text("language", language.clone());
+        }
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(self.url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .multipart(form)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            error!("{} transcription failed ({status}): {body}", self.backend);
+            return Err(BackendError::Http {
+                backend: self.backend,
+                status,
+            }
+            .into());
+        }
+
+        let data: serde_json::Value = resp.json().await?;
+        let mut transcript = parse_response(self.backend, &data, options)?;
+        transcript.model = Some(self.model.to_string());
+        Ok(transcript)
+    }
+}
+
+/// Extract text and language from a transcription response.
+fn parse_response(
+    backend: &str,
+    data: &serde_json::Value,
+    options: &TranscribeOptions,
+) -> Result<Transcript> {
+    let text = data
+        .get("text")
+        .and_then(|t| t.as_str())
+        .map(|t| t.trim().to_string())
+        .ok_or_else(|| anyhow::anyhow!("No text field in {backend} response"))?;
+    let language = data
+        .get("language")
+        .and_then(|l| l.as_str())
+        .and_then(normalize_language)
+        .or_else(|| options.language.clone());
+    Ok(Transcript {
+        text,
+        language,
+        backend: None,
+        model: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_detected_language() {
+        let data = serde_json::json!({"text": " Hola, ¿qué tal? ", "language": "spanish"});
+        let t = parse_response("Groq", &data, &TranscribeOptions::default()).unwrap();
+        assert_eq!(t.text, "Hola, ¿qué tal?");
+        assert_eq!(t.language.as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn falls_back_to_hint_without_language_field() {
+        let data = serde_json::json!({"text": "hello"});
+        let options = TranscribeOptions {
+            language: Some("en".into()),
+            chat_key: None,
+        };
+        let t = parse_response("OpenAI", &data, &options).unwrap();
+        assert_eq!(t.language.as_deref(), Some("en"));
+        let err = parse_response("OpenAI", &serde_json::json!({}), &options).unwrap_err();
+        assert!(err.to_string().contains("OpenAI"));
+    }
+
+    #[test]
+    fn model_override_ignores_empty() {
+        let t = OpenAiTranscriber::new("k".into()).with_model(Some(String::new()));
+        assert_eq!(t.model, DEFAULT_MODEL);
+        let t = OpenAiTranscriber::new("k".into()).with_model(Some("gpt-4o-transcribe".into()));
+        assert_eq!(t.model, "gpt-4o-transcribe");
+    }
+}
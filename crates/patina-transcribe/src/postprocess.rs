@@ -0,0 +1,485 @@
+//! Transcript post-processing applied uniformly to every backend.
+//!
+//! Stages (all opt-in via `transcription.postProcess`):
+//! 1. Vocabulary correction — restore configured names/jargon from close misspellings
+//! 2. Phrase replacements — exact, case-insensitive
+//! 3. Profanity masking
+//! 4. Punctuation restoration — sentence capitalization and terminal punctuation
+//! 5. Chunking — split long transcripts into paragraphs
+
+use std::collections::HashSet;
+
+use patina_config::TranscriptPostProcessConfig;
+
+/// Words masked by the profanity filter in addition to any configured ones.
+const DEFAULT_PROFANITY: &[&str] = &[
+    "fuck",
+    "fucking",
+    "fucked",
+    "fucker",
+    "motherfucker",
+    "shit",
+    "shitty",
+    "bullshit",
+    "bitch",
+    "asshole",
+    "bastard",
+    "cunt",
+    "dickhead",
+];
+
+/// A word plus the separator text that follows it.
+#[derive(Debug, Clone)]
+struct Token {
+    word: String,
+    trailing: String,
+}
+
+/// Split text into word tokens, keeping separators so the text can be rebuilt.
+/// Returns the leading non-word prefix and the tokens.
+fn tokenize(text: &str) -> (String, Vec<Token>) {
+    let is_word = |c: char| c.is_alphanumeric() || c == '\'';
+    let mut prefix = String::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_word(c) {
+            if !in_word {
+                tokens.push(Token {
+                    word: String::new(),
+                    trailing: String::new(),
+                });
+                in_word = true;
+            }
+            if let Some(t) = tokens.last_mut() {
+                t.word.push(c);
+            }
+        } else {
+            in_word = false;
+            match tokens.last_mut() {
+                Some(t) => t.trailing.push(c),
+                None => prefix.push(c),
+            }
+        }
+    }
+    (prefix, tokens)
+}
+
+fn rebuild(prefix: &str, tokens: &[Token]) -> String {
+    let mut out = prefix.to_string();
+    for t in tokens {
+        out.push_str(&t.word);
+        out.push_str(&t.trailing);
+    }
+    out
+}
+
+/// Lowercase alphanumeric form used for comparisons.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// Edit distance tolerated for a vocabulary term of the given normalized length.
+fn max_distance(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// A phrase to find in the token stream and the text to put in its place.
+#[derive(Debug, Clone)]
+struct PhraseRule {
+    /// Normalized words of the phrase.
+    words: Vec<String>,
+    /// Normalized phrase with spaces removed (so "jet son" can match "Jetson").
+    joined: String,
+    replacement: String,
+    /// Whether close misspellings also match.
+    fuzzy: bool,
+}
+
+impl PhraseRule {
+    fn new(phrase: &str, replacement: &str, fuzzy: bool) -> Option<Self> {
+        let words: Vec<String> = phrase
+            .split_whitespace()
+            .map(normalize)
+            .filter(|w| !w.is_empty())
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+        Some(Self {
+            joined: words.concat(),
+            words,
+            replacement: replacement.to_string(),
+            fuzzy,
+        })
+    }
+
+    /// If the tokens starting at `start` match this rule, return how many tokens matched
+    /// and the edit distance.
+    fn match_at(&self, tokens: &[Token], start: usize) -> Option<(usize, usize)> {
+        let n = self.words.len();
+        let sizes: Vec<usize> = if self.fuzzy {
+            // Allow the transcriber to split or merge words ("jet son" / "jared wolf")
+            (n.saturating_sub(1).max(1)..=n + 1).collect()
+        } else {
+            vec![n]
+        };
+
+        let mut best: Option<(usize, usize)> = None;
+        for size in sizes {
+            if start + size > tokens.len() {
+                continue;
+            }
+            let window = &tokens[start..start + size];
+            // Never match across sentence or clause boundaries
+            if window[..size - 1]
+                .iter()
+                .any(|t| t.trailing.contains(['.', ',', '!', '?', ';', ':']))
+            {
+                continue;
+            }
+            let dist = if self.fuzzy {
+                let joined: String = window.iter().map(|t| normalize(&t.word)).collect();
+                if joined.chars().next() != self.joined.chars().next() {
+                    continue;
+                }
+                levenshtein(&joined, &self.joined)
+            } else {
+                let words: Vec<String> = window.iter().map(|t| normalize(&t.word)).collect();
+                if words != self.words {
+                    continue;
+                }
+                0
+            };
+            let allowed = if self.fuzzy {
+                max_distance(self.joined.chars().count())
+            } else {
+                0
+            };
+            if dist <= allowed && best.is_none_or(|(_, d)| dist < d) {
+                best = Some((size, dist));
+            }
+        }
+        best
+    }
+}
+
+/// Apply phrase rules left to right; the first rule that matches at a position wins.
+fn apply_rules(tokens: Vec<Token>, rules: &[PhraseRule]) -> Vec<Token> {
+    if rules.is_empty() {
+        return tokens;
+    }
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let matched = rules
+            .iter()
+            .find_map(|r| r.match_at(&tokens, i).map(|(size, _)| (r, size)));
+        match matched {
+            Some((rule, size)) => {
+                out.push(Token {
+                    word: rule.replacement.clone(),
+                    trailing: tokens[i + size - 1].trailing.clone(),
+                });
+                i += size;
+            }
+            None => {
+                out.push(tokens[i].clone());
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Configured transcript post-processing pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct PostProcessor {
+    vocabulary: Vec<PhraseRule>,
+    replacements: Vec<PhraseRule>,
+    profanity: HashSet<String>,
+    punctuation: bool,
+    max_chunk_chars: Option<usize>,
+}
+
+impl PostProcessor {
+    pub fn new(config: &TranscriptPostProcessConfig) -> Self {
+        let vocabulary = config
+            .vocabulary
+            .iter()
+            .filter_map(|term| PhraseRule::new(term, term, true))
+            .collect();
+
+        // Longer phrases first so "new york city" wins over "new york"
+        let mut replacements: Vec<PhraseRule> = config
+            .replacements
+            .iter()
+            .filter_map(|(from, to)| PhraseRule::new(from, to, false))
+            .collect();
+        replacements.sort_by_key(|r| std::cmp::Reverse(r.words.len()));
+
+        let profanity = if config.profanity_filter {
+            DEFAULT_PROFANITY
+                .iter()
+                .map(|w| w.to_string())
+                .chain(config.profanity_words.iter().map(|w| normalize(w)))
+                .filter(|w| !w.is_empty())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        Self {
+            vocabulary,
+            replacements,
+            profanity,
+            punctuation: config.punctuation,
+            max_chunk_chars: config.max_chunk_chars.filter(|n| *n > 0),
+        }
+    }
+
+    /// True when no stage is enabled and `process` would return its input unchanged.
+    pub fn is_noop(&self) -> bool {
+        self.vocabulary.is_empty()
+            && self.replacements.is_empty()
+            && self.profanity.is_empty()
+            && !self.punctuation
+            && self.max_chunk_chars.is_none()
+    }
+
+    /// Run all enabled stages over a transcript.
+    pub fn process(&self, text: &str) -> String {
+        let text = text.trim();
+        if text.is_empty() || self.is_noop() {
+            return text.to_string();
+        }
+
+        let (prefix, tokens) = tokenize(text);
+        let tokens = apply_rules(tokens, &self.vocabulary);
+        let mut tokens = apply_rules(tokens, &self.replacements);
+
+        if !self.profanity.is_empty() {
+            for t in &mut tokens {
+                if self.profanity.contains(&normalize(&t.word)) {
+                    t.word = mask_word(&t.word);
+                }
+            }
+        }
+
+        let mut text = rebuild(&prefix, &tokens);
+        if self.punctuation {
+            text = restore_punctuation(&text);
+        }
+        match self.max_chunk_chars {
+            Some(max) => chunk_text(&text, max).join("\n\n"),
+            None => text,
+        }
+    }
+}
+
+/// Keep the first letter, mask the rest: "shit" → "s***".
+fn mask_word(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => std::iter::once(first).chain(chars.map(|_| '*')).collect(),
+        None => String::new(),
+    }
+}
+
+/// Capitalize sentence starts and the pronoun "I", and end with a period.
+fn restore_punctuation(text: &str) -> String {
+    let (prefix, mut tokens) = tokenize(text);
+    let mut sentence_start = true;
+    for t in &mut tokens {
+        let lower = t.word.to_lowercase();
+        if lower == "i" || lower.starts_with("i'") {
+            t.word.replace_range(..1, "I");
+        } else if sentence_start {
+            let mut chars = t.word.chars();
+            if let Some(first) = chars.next() {
+                t.word = first.to_uppercase().chain(chars).collect();
+            }
+        }
+        sentence_start = t.trailing.contains(['.', '!', '?']);
+    }
+
+    let mut out = rebuild(&prefix, &tokens);
+    if out.chars().last().is_some_and(|c| c.is_alphanumeric()) {
+        out.push('.');
+    }
+    out
+}
+
+/// Split text into chunks of at most `max` bytes, preferring sentence then word boundaries.
+pub fn chunk_text(text: &str, max: usize) -> Vec<String> {
+    if max == 0 || text.len() <= max {
+        return vec![text.to_string()];
+    }
+
+    // Split into sentences, keeping terminal punctuation
+    let mut sentences: Vec<&str> = Vec::new();
+    let mut start = 0;
+    let bytes = text.as_bytes();
+    for (i, c) in text.char_indices() {
+        if matches!(c, '.' | '!' | '?') && bytes.get(i + 1).is_none_or(|b| *b == b' ') {
+            sentences.push(text[start..=i].trim());
+            start = i + 1;
+        }
+    }
+    if start < text.len() && !text[start..].trim().is_empty() {
+        sentences.push(text[start..].trim());
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let push_piece = |piece: &str, current: &mut String, chunks: &mut Vec<String>| {
+        let extra = if current.is_empty() { 0 } else { 1 };
+        if !current.is_empty() && current.len() + extra + piece.len() > max {
+            chunks.push(std::mem::take(current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(piece);
+    };
+
+    for sentence in sentences {
+        if sentence.len() <= max {
+            push_piece(sentence, &mut current, &mut chunks);
+        } else {
+            // Oversized sentence: start a fresh chunk, then fall back to word boundaries
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            for word in sentence.split_whitespace() {
+                push_piece(word, &mut current, &mut chunks);
+            }
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn processor(f: impl FnOnce(&mut TranscriptPostProcessConfig)) -> PostProcessor {
+        let mut cfg = TranscriptPostProcessConfig::default();
+        f(&mut cfg);
+        PostProcessor::new(&cfg)
+    }
+
+    #[test]
+    fn default_config_is_noop() {
+        let p = PostProcessor::new(&TranscriptPostProcessConfig::default());
+        assert!(p.is_noop());
+        assert_eq!(p.process("  hello there  "), "hello there");
+    }
+
+    #[test]
+    fn vocabulary_fixes_close_misspellings() {
+        let p = processor(|c| {
+            c.vocabulary = vec!["Jared Wolff".into(), "Jetson".into(), "Patina".into()];
+        });
+        assert_eq!(
+            p.process("ask jared wolf about the jet son board"),
+            "ask Jared Wolff about the Jetson board"
+        );
+        assert_eq!(p.process("deploy patina tonight"), "deploy Patina tonight");
+        // Too far from any term: untouched
+        assert_eq!(p.process("a jacket"), "a jacket");
+    }
+
+    #[test]
+    fn vocabulary_does_not_cross_sentence_boundaries() {
+        let p = processor(|c| c.vocabulary = vec!["Jetson".into()]);
+        assert_eq!(p.process("the jet. son came"), "the jet. son came");
+    }
+
+    #[test]
+    fn replacements_are_exact_and_case_insensitive() {
+        let p = processor(|c| {
+            c.replacements = HashMap::from([
+                ("new york".to_string(), "NYC".to_string()),
+                ("new york city".to_string(), "New York City".to_string()),
+            ]);
+        });
+        assert_eq!(
+            p.process("Flying to New York City, then new york."),
+            "Flying to New York City, then NYC."
+        );
+    }
+
+    #[test]
+    fn profanity_is_masked() {
+        let p = processor(|c| {
+            c.profanity_filter = true;
+            c.profanity_words = vec!["darn".into()];
+        });
+        assert_eq!(
+            p.process("well shit, that's darn annoying"),
+            "well s***, that's d*** annoying"
+        );
+    }
+
+    #[test]
+    fn punctuation_capitalizes_and_terminates() {
+        let p = processor(|c| c.punctuation = true);
+        assert_eq!(
+            p.process("i think so. i'm not sure what you mean"),
+            "I think so. I'm not sure what you mean."
+        );
+        assert_eq!(p.process("done!"), "Done!");
+    }
+
+    #[test]
+    fn chunking_prefers_sentence_boundaries() {
+        let chunks = chunk_text("One two. Three four five. Six.", 15);
+        assert_eq!(chunks, vec!["One two.", "Three four", "five. Six."]);
+        let chunks = chunk_text("Short.", 100);
+        assert_eq!(chunks, vec!["Short."]);
+    }
+
+    #[test]
+    fn chunking_applied_by_processor() {
+        let p = processor(|c| c.max_chunk_chars = Some(12));
+        assert_eq!(
+            p.process("First one. Second one."),
+            "First one.\n\nSecond one."
+        );
+    }
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+}
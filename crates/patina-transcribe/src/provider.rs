@@ -0,0 +1,279 @@
+//! ONNX Runtime execution provider selection for local inference.
+//!
+//! `transcription.executionProvider` names the provider to try. A provider is
+//! only used when it was compiled in (cargo feature) and the matching hardware
+//! or runtime is detected; otherwise we fall back to CPU and say why. The
+//! provider that actually loaded is recorded so `patina status` can report it.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Execution providers Parakeet can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionProviderKind {
+    Cpu,
+    Cuda,
+    TensorRt,
+    CoreMl,
+    DirectMl,
+    /// AMD GPUs. ONNX Runtime's ROCm provider is deprecated in favor of
+    /// MIGraphX, so "rocm" in config maps here.
+    MiGraphX,
+}
+
+impl ExecutionProviderKind {
+    /// GPU providers in preference order for `"auto"`.
+    pub const GPU: [ExecutionProviderKind; 5] = [
+        Self::TensorRt,
+        Self::Cuda,
+        Self::CoreMl,
+        Self::DirectMl,
+        Self::MiGraphX,
+    ];
+
+    /// Parse a config value. Returns `None` for unknown names.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "" | "cpu" => Some(Self::Cpu),
+            "cuda" => Some(Self::Cuda),
+            "tensorrt" | "trt" => Some(Self::TensorRt),
+            "coreml" => Some(Self::CoreMl),
+            "directml" | "dml" => Some(Self::DirectMl),
+            "migraphx" | "rocm" => Some(Self::MiGraphX),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cpu => "cpu",
+            Self::Cuda => "cuda",
+            Self::TensorRt => "tensorrt",
+            Self::CoreMl => "coreml",
+            Self::DirectMl => "directml",
+            Self::MiGraphX => "migraphx",
+        }
+    }
+
+    /// Whether support for this provider was compiled in.
+    pub fn compiled_in(&self) -> bool {
+        match self {
+            Self::Cpu => true,
+            Self::Cuda => cfg!(feature = "cuda"),
+            Self::TensorRt => cfg!(feature = "tensorrt"),
+            Self::CoreMl => cfg!(feature = "coreml"),
+            Self::DirectMl => cfg!(feature = "directml"),
+            Self::MiGraphX => cfg!(feature = "migraphx"),
+        }
+    }
+
+    /// Best-effort check that the hardware/runtime for this provider is present.
+    pub fn detected(&self) -> bool {
+        match self {
+            Self::Cpu => true,
+            Self::Cuda => nvidia_present(),
+            Self::TensorRt => nvidia_present() && library_present(&["libnvinfer.so"]),
+            Self::CoreMl => cfg!(target_os = "macos"),
+            Self::DirectMl => cfg!(target_os = "windows"),
+            Self::MiGraphX => {
+                Path::new("/dev/kfd").exists() && library_present(&["libmigraphx.so"])
+            }
+        }
+    }
+}
+
+/// The outcome of resolving the configured provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderSelection {
+    /// What the config asked for ("auto", "cuda", ...).
+    pub requested: String,
+    /// What we'll try to load.
+    pub selected: ExecutionProviderKind,
+    /// Why `selected` differs from `requested`, if it does.
+    pub fallback_reason: Option<String>,
+}
+
+/// Resolve the configured provider name against compiled features and hardware.
+pub fn select_provider(requested: Option<&str>) -> ProviderSelection {
+    let requested = requested.unwrap_or("cpu").trim().to_lowercase();
+
+    if requested == "auto" {
+        let selected = ExecutionProviderKind::GPU
+            .into_iter()
+            .find(|p| p.compiled_in() && p.detected())
+            .unwrap_or(ExecutionProviderKind::Cpu);
+        let fallback_reason = (selected == ExecutionProviderKind::Cpu)
+            .then(|| "no compiled-in GPU provider detected usable hardware".to_string());
+        return ProviderSelection {
+            requested,
+            selected,
+            fallback_reason,
+        };
+    }
+
+    let Some(kind) = ExecutionProviderKind::parse(&requested) else {
+        return ProviderSelection {
+            fallback_reason: Some(format!("unknown execution provider '{requested}'")),
+            requested,
+            selected: ExecutionProviderKind::Cpu,
+        };
+    };
+
+    let fallback_reason = if !kind.compiled_in() {
+        Some(format!(
+            "built without the '{}' feature (rebuild with --features patina-transcribe/{})",
+            kind.as_str(),
+            kind.as_str()
+        ))
+    } else if !kind.detected() {
+        Some(format!("no {} device or runtime detected", kind.as_str()))
+    } else {
+        None
+    };
+
+    ProviderSelection {
+        selected: if fallback_reason.is_some() {
+            ExecutionProviderKind::Cpu
+        } else {
+            kind
+        },
+        requested,
+        fallback_reason,
+    }
+}
+
+/// NVIDIA GPU (desktop driver or Jetson/Tegra) visible to this process.
+fn nvidia_present() -> bool {
+    Path::new("/dev/nvidia0").exists()
+        || Path::new("/proc/driver/nvidia/version").exists()
+        || Path::new("/etc/nv_tegra_release").exists()
+        || Path::new("/dev/nvhost-ctrl-gpu").exists()
+        || (cfg!(target_os = "windows") && which::which("nvidia-smi").is_ok())
+}
+
+/// Whether a shared library with one of these name prefixes is in a standard location.
+fn library_present(prefixes: &[&str]) -> bool {
+    let mut dirs: Vec<PathBuf> = [
+        "/usr/lib",
+        "/usr/lib64",
+        "/usr/local/lib",
+        "/usr/lib/x86_64-linux-gnu",
+        "/usr/lib/aarch64-linux-gnu",
+        "/opt/rocm/lib",
+        "/usr/local/cuda/lib64",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+    if let Ok(paths) = std::env::var("LD_LIBRARY_PATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    dirs.iter().any(|dir| {
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries.flatten().any(|e| {
+                    let name = e.file_name();
+                    let name = name.to_string_lossy();
+                    prefixes.iter().any(|p| name.starts_with(p))
+                })
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Which provider the local model actually loaded with, persisted for `patina status`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedProvider {
+    pub requested: String,
+    pub loaded: ExecutionProviderKind,
+    pub fallback_reason: Option<String>,
+    pub loaded_at: String,
+    pub pid: u32,
+}
+
+fn state_path() -> PathBuf {
+    patina_config::data_dir().join("transcription_provider.json")
+}
+
+/// Record the provider that loaded. Failures are ignored; this is diagnostics only.
+pub fn record_loaded(state: &LoadedProvider) {
+    let path = state_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(state) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+/// Read the provider recorded by the most recent model load, if any.
+pub fn last_loaded() -> Option<LoadedProvider> {
+    let content = std::fs::read_to_string(state_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_aliases() {
+        assert_eq!(
+            ExecutionProviderKind::parse("CUDA"),
+            Some(ExecutionProviderKind::Cuda)
+        );
+        assert_eq!(
+            ExecutionProviderKind::parse("trt"),
+            Some(ExecutionProviderKind::TensorRt)
+        );
+        assert_eq!(
+            ExecutionProviderKind::parse(""),
+            Some(ExecutionProviderKind::Cpu)
+        );
+        assert_eq!(
+            ExecutionProviderKind::parse("rocm"),
+            Some(ExecutionProviderKind::MiGraphX)
+        );
+        assert_eq!(ExecutionProviderKind::parse("tpu"), None);
+        for kind in ExecutionProviderKind::GPU {
+            assert_eq!(ExecutionProviderKind::parse(kind.as_str()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn cpu_needs_no_fallback() {
+        let sel = select_provider(None);
+        assert_eq!(sel.selected, ExecutionProviderKind::Cpu);
+        assert!(sel.fallback_reason.is_none());
+    }
+
+    #[test]
+    fn unknown_provider_falls_back_to_cpu() {
+        let sel = select_provider(Some("tpu"));
+        assert_eq!(sel.selected, ExecutionProviderKind::Cpu);
+        assert!(sel.fallback_reason.unwrap().contains("unknown"));
+    }
+
+    #[test]
+    fn missing_feature_is_explained() {
+        // None of the GPU features are enabled in the default test build
+        if !ExecutionProviderKind::Cuda.compiled_in() {
+            let sel = select_provider(Some("cuda"));
+            assert_eq!(sel.selected, ExecutionProviderKind::Cpu);
+            assert!(sel.fallback_reason.unwrap().contains("--features"));
+        }
+    }
+
+    #[test]
+    fn auto_picks_cpu_without_gpu_features() {
+        let sel = select_provider(Some("auto"));
+        if ExecutionProviderKind::GPU.iter().all(|p| !p.compiled_in()) {
+            assert_eq!(sel.selected, ExecutionProviderKind::Cpu);
+            assert!(sel.fallback_reason.is_some());
+        }
+        assert_eq!(sel.requested, "auto");
+    }
+}
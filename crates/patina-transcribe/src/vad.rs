@@ -0,0 +1,214 @@
+//! Energy-based voice activity detection for microphone input.
+//!
+//! Audio is cut into short frames. A frame counts as speech when its RMS energy
+//! clears both a fixed floor and a multiple of the running background-noise
+//! estimate, so a noisy room raises the bar instead of triggering constantly.
+//! The first `calibration_ms` only measure the background. An utterance starts
+//! after `min_speech_ms` of speech and ends after `silence_ms` without it.
+
+/// Detector tuning.
+#[derive(Debug, Clone, Copy)]
+pub struct VadConfig {
+    pub frame_ms: u32,
+    /// Initial audio used only to measure background noise.
+    pub calibration_ms: u32,
+    /// Speech needed before an utterance counts as started.
+    pub min_speech_ms: u32,
+    /// Pause that ends an utterance.
+    pub silence_ms: u32,
+    /// Minimum RMS for a speech frame (samples are in -1.0..=1.0).
+    pub threshold: f32,
+    /// A speech frame must also be this many times louder than the noise floor.
+    pub noise_ratio: f32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            frame_ms: 20,
+            calibration_ms: 200,
+            min_speech_ms: 120,
+            silence_ms: 800,
+            threshold: 0.01,
+            noise_ratio: 3.0,
+        }
+    }
+}
+
+/// Utterance boundaries reported by [`Vad::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadEvent {
+    SpeechStarted,
+    SpeechEnded,
+}
+
+/// Streaming detector over mono f32 samples at a fixed rate.
+pub struct Vad {
+    config: VadConfig,
+    frame_len: usize,
+    pending: Vec<f32>,
+    noise: f32,
+    calibration_frames: u32,
+    calibrated_frames: u32,
+    speech_frames: u32,
+    silence_frames: u32,
+    in_speech: bool,
+}
+
+impl Vad {
+    pub fn new(config: VadConfig, sample_rate: u32) -> Self {
+        let frame_ms = config.frame_ms.max(1);
+        let frame_len = (sample_rate as usize * frame_ms as usize / 1000).max(1);
+        Self {
+            config,
+            frame_len,
+            pending: Vec::with_capacity(frame_len),
+            noise: 0.0,
+            calibration_frames: config.calibration_ms / frame_ms,
+            calibrated_frames: 0,
+            speech_frames: 0,
+            silence_frames: 0,
+            in_speech: false,
+        }
+    }
+
+    /// A detector that skips calibration and starts from a known noise floor,
+    /// e.g. one measured while waiting for a wake word.
+    pub fn with_noise_floor(config: VadConfig, sample_rate: u32, noise: f32) -> Self {
+        let mut vad = Self::new(config, sample_rate);
+        vad.calibration_frames = 0;
+        vad.noise = noise;
+        vad
+    }
+
+    /// Current background-noise estimate (RMS).
+    pub fn noise_floor(&self) -> f32 {
+        self.noise
+    }
+
+    /// Whether an utterance is in progress.
+    pub fn in_speech(&self) -> bool {
+        self.in_speech
+    }
+
+    /// Feed samples, returning the last boundary crossed in them, if any.
+    pub fn push(&mut self, samples: &[f32]) -> Option<VadEvent> {
+        let mut event = None;
+        for &s in samples {
+            self.pending.push(s);
+            if self.pending.len() == self.frame_len {
+                let rms = rms(&self.pending);
+                self.pending.clear();
+                if let Some(e) = self.frame(rms) {
+                    event = Some(e);
+                }
+            }
+        }
+        event
+    }
+
+    fn frame(&mut self, rms: f32) -> Option<VadEvent> {
+        if self.calibrated_frames < self.calibration_frames {
+            self.calibrated_frames += 1;
+            self.noise += (rms - self.noise) / self.calibrated_frames as f32;
+            return None;
+        }
+
+        let frame_ms = self.config.frame_ms.max(1);
+        let loud = rms
+            > self
+                .config
+                .threshold
+                .max(self.noise * self.config.noise_ratio);
+
+        if loud {
+            self.speech_frames += 1;
+            self.silence_frames = 0;
+            if !self.in_speech && self.speech_frames * frame_ms >= self.config.min_speech_ms {
+                self.in_speech = true;
+                return Some(VadEvent::SpeechStarted);
+            }
+            return None;
+        }
+
+        // Only learn the noise floor from quiet frames
+        self.noise = if self.noise == 0.0 {
+            rms
+        } else {
+            self.noise * 0.95 + rms * 0.05
+        };
+        self.silence_frames += 1;
+        if self.in_speech {
+            if self.silence_frames * frame_ms >= self.config.silence_ms {
+                self.in_speech = false;
+                self.speech_frames = 0;
+                return Some(VadEvent::SpeechEnded);
+            }
+        } else {
+            // A cough or click shorter than min_speech_ms doesn't start an utterance
+            self.speech_frames = 0;
+        }
+        None
+    }
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn tone(ms: u32, amplitude: f32) -> Vec<f32> {
+        let n = (RATE * ms / 1000) as usize;
+        (0..n).map(|i| amplitude * (i as f32 * 0.1).sin()).collect()
+    }
+
+    #[test]
+    fn detects_utterance_boundaries() {
+        let mut vad = Vad::new(VadConfig::default(), RATE);
+        assert_eq!(vad.push(&tone(300, 0.001)), None);
+        assert_eq!(vad.push(&tone(500, 0.3)), Some(VadEvent::SpeechStarted));
+        assert!(vad.in_speech());
+        // A short pause mid-sentence doesn't end it
+        assert_eq!(vad.push(&tone(300, 0.001)), None);
+        assert_eq!(vad.push(&tone(200, 0.3)), None);
+        assert_eq!(vad.push(&tone(1000, 0.001)), Some(VadEvent::SpeechEnded));
+        assert!(!vad.in_speech());
+    }
+
+    #[test]
+    fn ignores_short_clicks() {
+        let mut vad = Vad::new(VadConfig::default(), RATE);
+        vad.push(&tone(200, 0.0));
+        for _ in 0..5 {
+            assert_eq!(vad.push(&tone(40, 0.5)), None);
+            assert_eq!(vad.push(&tone(100, 0.0)), None);
+        }
+        assert!(!vad.in_speech());
+    }
+
+    #[test]
+    fn steady_noise_raises_the_bar() {
+        let mut vad = Vad::new(VadConfig::default(), RATE);
+        // Fan noise above the fixed threshold becomes the noise floor
+        vad.push(&tone(200, 0.03));
+        assert_eq!(vad.push(&tone(2000, 0.03)), None);
+        assert_eq!(vad.push(&tone(300, 0.4)), Some(VadEvent::SpeechStarted));
+    }
+
+    #[test]
+    fn carries_over_a_measured_noise_floor() {
+        let mut idle = Vad::new(VadConfig::default(), RATE);
+        idle.push(&tone(1000, 0.03));
+        let noise = idle.noise_floor();
+        assert!(noise > 0.015);
+
+        // Speech right away is detected, not mistaken for background
+        let mut vad = Vad::with_noise_floor(VadConfig::default(), RATE, noise);
+        assert_eq!(vad.push(&tone(200, 0.4)), Some(VadEvent::SpeechStarted));
+    }
+}
@@ -0,0 +1,387 @@
+//! Wake-word detection with openWakeWord models.
+//!
+//! openWakeWord runs three small ONNX models over 16kHz audio in 80ms steps: a
+//! melspectrogram front end, a shared speech-embedding model, and a per-word
+//! classifier that scores the last ~1.3s of embeddings. They run on the same
+//! ONNX Runtime as the local transcriber. Loading the models needs the
+//! `wakeword` feature; the streaming logic here is independent of it.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+use patina_config::WakeWordConfig;
+
+use crate::download::{self, ProgressFn};
+
+pub const DEFAULT_MODEL_URL: &str =
+    "https://github.com/dscripka/openWakeWord/releases/download/v0.5.1";
+
+/// Front-end models shared by every wake word.
+pub const FEATURE_FILES: [&str; 2] = ["melspectrogram.onnx", "embedding_model.onnx"];
+
+/// Wake words with a pretrained model in the openWakeWord release.
+pub const BUILTIN_MODELS: [&str; 4] = ["alexa", "hey_jarvis", "hey_mycroft", "hey_rhasspy"];
+
+/// Samples per detector step (80ms at 16kHz).
+const CHUNK: usize = 1280;
+/// Extra samples the melspectrogram needs on the left edge of each step.
+const MEL_CONTEXT: usize = 480;
+const MEL_BINS: usize = 32;
+/// Melspectrogram frames per embedding window.
+const MEL_WINDOW: usize = 76;
+const EMBEDDING_DIM: usize = 96;
+/// Embeddings scored by the classifier.
+const FEATURE_WINDOW: usize = 16;
+
+/// Model file locations for one wake word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WakeWordModels {
+    pub dir: PathBuf,
+    pub classifier: PathBuf,
+    /// Name shown to the user, e.g. "hey jarvis".
+    pub phrase: String,
+}
+
+impl WakeWordModels {
+    /// Resolve paths from config. Built-in names map to files in `modelDir`;
+    /// anything ending in `.onnx` is a custom classifier path.
+    pub fn from_config(config: &WakeWordConfig) -> Result<Self> {
+        let dir = config
+            .model_dir
+            .as_deref()
+            .map(expand_home)
+            .unwrap_or_else(default_model_dir);
+        let model = config.model.trim();
+        let (classifier, name) = if model.ends_with(".onnx") {
+            let path = expand_home(model);
+            let name = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (path, name)
+        } else if BUILTIN_MODELS.contains(&model) {
+            (dir.join(builtin_file(model)), model.to_string())
+        } else {
+            bail!(
+                "Unknown wake word '{model}'. Use one of {} or a path to an .onnx model",
+                BUILTIN_MODELS.join(", ")
+            );
+        };
+        // "hey_jarvis_v0.1" -> "hey jarvis"
+        let phrase = name
+            .split('_')
+            .filter(|part| {
+                part.strip_prefix('v')
+                    .is_none_or(|v| v.parse::<f32>().is_err())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Self {
+            dir,
+            classifier,
+            phrase,
+        })
+    }
+
+    fn missing(&self) -> Vec<PathBuf> {
+        FEATURE_FILES
+            .iter()
+            .map(|f| self.dir.join(f))
+            .chain(std::iter::once(self.classifier.clone()))
+            .filter(|p| !p.exists())
+            .collect()
+    }
+}
+
+/// Resolve the models and download any missing release files.
+pub async fn ensure_models(
+    config: &WakeWordConfig,
+    progress: Option<ProgressFn>,
+) -> Result<WakeWordModels> {
+    let models = WakeWordModels::from_config(config)?;
+    if config.auto_download && !models.missing().is_empty() {
+        let model = config.model.trim();
+        let classifier = builtin_file(model);
+        let mut files: Vec<&str> = FEATURE_FILES.to_vec();
+        if BUILTIN_MODELS.contains(&model) {
+            files.push(&classifier);
+        }
+        let base = config.model_url.as_deref().unwrap_or(DEFAULT_MODEL_URL);
+        download::download_missing_files(&models.dir, base, &files, progress).await?;
+    }
+    if let Some(path) = models.missing().first() {
+        bail!("Wake-word model not found: {}", path.display());
+    }
+    Ok(models)
+}
+
+fn builtin_file(name: &str) -> String {
+    format!("{name}_v0.1.onnx")
+}
+
+fn default_model_dir() -> PathBuf {
+    patina_config::data_dir()
+        .join("models")
+        .join("openwakeword")
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Whether wake-word detection is compiled in.
+pub fn wakeword_available() -> bool {
+    cfg!(feature = "wakeword")
+}
+
+/// The three openWakeWord stages. Split out so the streaming logic can be
+/// tested without ONNX Runtime.
+trait WakeModel: Send {
+    /// Melspectrogram frames (`MEL_BINS` values each) for int16-scaled audio.
+    fn melspectrogram(&mut self, audio: &[f32]) -> Result<Vec<f32>>;
+    /// One embedding for `MEL_WINDOW` frames.
+    fn embed(&mut self, mel: &[f32]) -> Result<Vec<f32>>;
+    /// Wake-word probability for `FEATURE_WINDOW` embeddings.
+    fn score(&mut self, features: &[f32]) -> Result<f32>;
+}
+
+/// Streaming wake-word detector over 16kHz mono samples.
+pub struct WakeWordDetector {
+    model: Box<dyn WakeModel>,
+    threshold: f32,
+    pending: Vec<f32>,
+    audio: Vec<f32>,
+    mel: Vec<f32>,
+    features: Vec<f32>,
+}
+
+impl WakeWordDetector {
+    /// Load the openWakeWord models.
+    #[cfg(feature = "wakeword")]
+    pub fn load(models: &WakeWordModels, threshold: f32) -> Result<Self> {
+        let model = onnx::OnnxWakeModel::load(models)?;
+        Ok(Self::with_model(Box::new(model), threshold))
+    }
+
+    #[cfg(not(feature = "wakeword"))]
+    pub fn load(_models: &WakeWordModels, _threshold: f32) -> Result<Self> {
+        bail!("Wake-word detection needs a build with the 'wakeword' feature")
+    }
+
+    #[cfg_attr(not(feature = "wakeword"), allow(dead_code))]
+    fn with_model(model: Box<dyn WakeModel>, threshold: f32) -> Self {
+        Self {
+            model,
+            threshold,
+            pending: Vec::with_capacity(CHUNK),
+            audio: vec![0.0; MEL_CONTEXT],
+            mel: Vec::new(),
+            features: Vec::new(),
+        }
+    }
+
+    /// Feed 16kHz samples (-1.0..=1.0). Returns true when the wake word was heard.
+    ///
+    /// After a detection the embedding history is cleared, so the same phrase
+    /// can't trigger twice.
+    pub fn push(&mut self, samples: &[f32]) -> Result<bool> {
+        let mut detected = false;
+        for &s in samples {
+            // openWakeWord models expect int16-range input
+            self.pending.push(s * i16::MAX as f32);
+            if self.pending.len() == CHUNK {
+                detected |= self.step()?;
+            }
+        }
+        Ok(detected)
+    }
+
+    /// Forget buffered audio, e.g. after the microphone was closed.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.audio = vec![0.0; MEL_CONTEXT];
+        self.mel.clear();
+        self.features.clear();
+    }
+
+    fn step(&mut self) -> Result<bool> {
+        self.audio.append(&mut self.pending);
+        keep_last(&mut self.audio, CHUNK + MEL_CONTEXT);
+
+        let mel = self.model.melspectrogram(&self.audio)?;
+        // Same scaling openWakeWord applies before the embedding model
+        self.mel.extend(mel.iter().map(|v| v / 10.0 + 2.0));
+        keep_last(&mut self.mel, MEL_WINDOW * MEL_BINS);
+        if self.mel.len() < MEL_WINDOW * MEL_BINS {
+            return Ok(false);
+        }
+
+        let embedding = self.model.embed(&self.mel)?;
+        if embedding.len() != EMBEDDING_DIM {
+            bail!("Unexpected wake-word embedding size {}", embedding.len());
+        }
+        self.features.extend(embedding);
+        keep_last(&mut self.features, FEATURE_WINDOW * EMBEDDING_DIM);
+        if self.features.len() < FEATURE_WINDOW * EMBEDDING_DIM {
+            return Ok(false);
+        }
+
+        if self.model.score(&self.features)? >= self.threshold {
+            self.features.clear();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+fn keep_last(buf: &mut Vec<f32>, len: usize) {
+    if buf.len() > len {
+        buf.drain(..buf.len() - len);
+    }
+}
+
+#[cfg(feature = "wakeword")]
+mod onnx {
+    use anyhow::{Context, Result};
+    use ort::session::Session;
+    use ort::value::Tensor;
+
+    use super::{WakeModel, WakeWordModels, EMBEDDING_DIM, MEL_BINS, MEL_WINDOW};
+
+    pub(super) struct OnnxWakeModel {
+        mel: Session,
+        embedding: Session,
+        classifier: Session,
+    }
+
+    impl OnnxWakeModel {
+        pub(super) fn load(models: &WakeWordModels) -> Result<Self> {
+            let load = |path: &std::path::Path| -> Result<Session> {
+                Session::builder()?
+                    .commit_from_file(path)
+                    .with_context(|| format!("Failed to load {}", path.display()))
+            };
+            Ok(Self {
+                mel: load(&models.dir.join("melspectrogram.onnx"))?,
+                embedding: load(&models.dir.join("embedding_model.onnx"))?,
+                classifier: load(&models.classifier)?,
+            })
+        }
+    }
+
+    fn run<const N: usize>(
+        session: &mut Session,
+        shape: [usize; N],
+        data: &[f32],
+    ) -> Result<Vec<f32>> {
+        let input = Tensor::from_array((shape, data.to_vec()))?;
+        let outputs = session.run(ort::inputs![input])?;
+        let (_, values) = outputs[0].try_extract_tensor::<f32>()?;
+        Ok(values.to_vec())
+    }
+
+    impl WakeModel for OnnxWakeModel {
+        fn melspectrogram(&mut self, audio: &[f32]) -> Result<Vec<f32>> {
+            run(&mut self.mel, [1, audio.len()], audio)
+        }
+
+        fn embed(&mut self, mel: &[f32]) -> Result<Vec<f32>> {
+            run(&mut self.embedding, [1, MEL_WINDOW, MEL_BINS, 1], mel)
+        }
+
+        fn score(&mut self, features: &[f32]) -> Result<f32> {
+            let n = features.len() / EMBEDDING_DIM;
+            let scores = run(&mut self.classifier, [1, n, EMBEDDING_DIM], features)?;
+            Ok(scores.first().copied().unwrap_or(0.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// Emits 8 mel frames per step and scores by the loudness of recent audio.
+    struct FakeModel {
+        level: std::sync::Arc<std::sync::Mutex<f32>>,
+    }
+
+    impl WakeModel for FakeModel {
+        fn melspectrogram(&mut self, audio: &[f32]) -> Result<Vec<f32>> {
+            let peak = audio.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            *self.level.lock().unwrap() = peak / i16::MAX as f32;
+            Ok(vec![0.0; 8 * MEL_BINS])
+        }
+
+        fn embed(&mut self, mel: &[f32]) -> Result<Vec<f32>> {
+            assert_eq!(mel.len(), MEL_WINDOW * MEL_BINS);
+            Ok(vec![0.0; EMBEDDING_DIM])
+        }
+
+        fn score(&mut self, features: &[f32]) -> Result<f32> {
+            assert_eq!(features.len(), FEATURE_WINDOW * EMBEDDING_DIM);
+            Ok(*self.level.lock().unwrap())
+        }
+    }
+
+    fn detector() -> WakeWordDetector {
+        let model = FakeModel {
+            level: Default::default(),
+        };
+        WakeWordDetector::with_model(Box::new(model), 0.5)
+    }
+
+    #[test]
+    fn fires_once_buffers_are_warm() {
+        let mut d = detector();
+        // 10 steps fill the mel window, then 15 more fill the feature window
+        assert!(!d.push(&vec![0.9; CHUNK * 24]).unwrap());
+        assert!(d.push(&vec![0.9; CHUNK]).unwrap());
+
+        let mut d = detector();
+        assert!(!d.push(&vec![0.1; CHUNK * 40]).unwrap());
+    }
+
+    #[test]
+    fn detection_clears_history() {
+        let mut d = detector();
+        assert!(d.push(&vec![0.9; CHUNK * 25]).unwrap());
+        // The phrase is still ringing but needs a full window again
+        assert!(!d.push(&vec![0.9; CHUNK * 15]).unwrap());
+        assert!(d.push(&vec![0.9; CHUNK]).unwrap());
+    }
+
+    #[test]
+    fn resolves_builtin_and_custom_models() {
+        let config = WakeWordConfig {
+            model_dir: Some("/models/oww".into()),
+            ..Default::default()
+        };
+        let models = WakeWordModels::from_config(&config).unwrap();
+        assert_eq!(
+            models.classifier,
+            PathBuf::from("/models/oww/hey_jarvis_v0.1.onnx")
+        );
+        assert_eq!(models.phrase, "hey jarvis");
+
+        let config = WakeWordConfig {
+            model: "/opt/ok_patina.onnx".into(),
+            ..Default::default()
+        };
+        let models = WakeWordModels::from_config(&config).unwrap();
+        assert_eq!(models.classifier, Path::new("/opt/ok_patina.onnx"));
+        assert_eq!(models.phrase, "ok patina");
+
+        let config = WakeWordConfig {
+            model: "computer".into(),
+            ..Default::default()
+        };
+        assert!(WakeWordModels::from_config(&config).is_err());
+    }
+}
@@ -1,4 +1,26 @@
-import type { Persona, Task, UsageRow, UsageFilters } from "./types";
+import type {
+  Persona,
+  Task,
+  UsageRow,
+  UsageFilters,
+  MemoryDocument,
+  MemorySearchResult,
+  CompareEvent,
+  ComparePreference,
+  CompareTierStats,
+  Comparison,
+  LogLine,
+  GatewayStatus,
+} from "./types";
+
+// The server requires the web password on /api when one is set
+export function apiFetch(input: string, init: RequestInit = {}) {
+  const password = localStorage.getItem("patina-password");
+  if (!password) return fetch(input, init);
+  const headers = new Headers(init.headers);
+  headers.set("Authorization", `Bearer ${password}`);
+  return fetch(input, { ...init, headers });
+}
 
 function buildQuery(params: Record<string, string>): string {
   const parts = Object.entries(params)
@@ -17,19 +39,56 @@ export interface ServerSession {
 }
 
 export async function fetchSessions(): Promise<ServerSession[]> {
-  const res = await fetch("/api/sessions");
+  const res = await apiFetch("/api/sessions");
   return res.json();
 }
 
 export async function deleteSession(id: string): Promise<void> {
-  await fetch(`/api/sessions/${encodeURIComponent(id)}`, { method: "DELETE" });
+  await apiFetch(`/api/sessions/${encodeURIComponent(id)}`, {
+    method: "DELETE",
+  });
+}
+
+export async function shareSession(
+  id: string,
+): Promise<{ url: string; expiresAt: string }> {
+  const res = await apiFetch(`/api/sessions/${encodeURIComponent(id)}/share`, {
+    method: "POST",
+  });
+  if (!res.ok) {
+    const body = await res.json().catch(() => ({}));
+    throw new Error(body.error || `share failed (${res.status})`);
+  }
+  return res.json();
+}
+
+export async function uploadFile(
+  chatId: string,
+  file: File,
+): Promise<{ name: string; size: number }> {
+  const res = await apiFetch(
+    `/api/sessions/${encodeURIComponent(chatId)}/uploads${buildQuery({ name: file.name })}`,
+    {
+      method: "POST",
+      headers: { "Content-Type": "application/octet-stream" },
+      body: file,
+    },
+  );
+  if (!res.ok) {
+    const body = await res.json().catch(() => ({}));
+    throw new Error(
+      body.error ||
+        (res.status === 413 ? "file too large" : `upload failed (${res.status})`),
+    );
+  }
+  return res.json();
 }
 
 // Personas
 
 export async function fetchPersonas(): Promise<Persona[]> {
   try {
-    const res = await fetch("/api/personas");
+    const res = await apiFetch("/api/personas");
     return await res.json();
   } catch {
     return [];
@@ -37,7 +96,7 @@ export async function fetchPersonas(): Promise<Persona[]> {
 }
 
 export async function createPersona(data: Partial<Persona>): Promise<Persona> {
-  const res = await fetch("/api/personas", {
+  const res = await apiFetch("/api/personas", {
     method: "POST",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify(data),
@@ -53,7 +112,7 @@ export async function updatePersona(
   key: string,
   data: Partial<Persona>,
 ): Promise<Persona> {
-  const res = await fetch(`/api/personas/${encodeURIComponent(key)}`, {
+  const res = await apiFetch(`/api/personas/${encodeURIComponent(key)}`, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify(data),
@@ -66,7 +125,7 @@ export async function updatePersona(
 }
 
 export async function deletePersona(key: string): Promise<void> {
-  const res = await fetch(`/api/personas/${encodeURIComponent(key)}`, {
+  const res = await apiFetch(`/api/personas/${encodeURIComponent(key)}`, {
     method: "DELETE",
   });
   if (!res.ok) {
@@ -78,7 +137,7 @@ export async function deletePersona(key: string): Promise<void> {
 export async function generatePersonaPrompt(
   data: Record<string, string>,
 ): Promise<{ preamble: string }> {
-  const res = await fetch("/api/personas/generate-prompt", {
+  const res = await apiFetch("/api/personas/generate-prompt", {
     method: "POST",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify(data),
@@ -88,7 +147,7 @@ export async function generatePersonaPrompt(
 
 export async function fetchModelTiers(): Promise<string[]> {
   try {
-    const res = await fetch("/api/model-tiers");
+    const res = await apiFetch("/api/model-tiers");
     return await res.json();
   } catch {
     return ["default"];
@@ -100,33 +159,33 @@ export async function fetchModelTiers(): Promise<string[]> {
 export async function fetchUsageSummary(
   params: Record<string, string>,
 ): Promise<UsageRow[]> {
-  const res = await fetch(`/api/usage/summary${buildQuery(params)}`);
+  const res = await apiFetch(`/api/usage/summary${buildQuery(params)}`);
   return res.json();
 }
 
 export async function fetchUsageDaily(
   params: Record<string, string>,
 ): Promise<UsageRow[]> {
-  const res = await fetch(`/api/usage/daily${buildQuery(params)}`);
+  const res = await apiFetch(`/api/usage/daily${buildQuery(params)}`);
   return res.json();
 }
 
 export async function fetchUsageFilters(): Promise<UsageFilters> {
-  const res = await fetch("/api/usage/filters");
+  const res = await apiFetch("/api/usage/filters");
   return res.json();
 }
 
 // Tasks
 
 export async function fetchTasks(): Promise<Task[]> {
-  const res = await fetch("/api/tasks");
+  const res = await apiFetch("/api/tasks");
   return res.json();
 }
 
 export async function createTask(
   data: Partial<Task>,
 ): Promise<Task> {
-  const res = await fetch("/api/tasks", {
+  const res = await apiFetch("/api/tasks", {
     method: "POST",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify(data),
@@ -138,7 +197,7 @@ export async function updateTask(
   id: string,
   data: Partial<Task>,
 ): Promise<void> {
-  await fetch(`/api/tasks/${id}`, {
+  await apiFetch(`/api/tasks/${id}`, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify(data),
@@ -146,14 +205,14 @@ export async function updateTask(
 }
 
 export async function deleteTask(id: string): Promise<void> {
-  await fetch(`/api/tasks/${id}`, { method: "DELETE" });
+  await apiFetch(`/api/tasks/${id}`, { method: "DELETE" });
 }
 
 export async function moveTask(
   id: string,
   status: string,
 ): Promise<void> {
-  await fetch(`/api/tasks/${id}/move`, {
+  await apiFetch(`/api/tasks/${id}/move`, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({ status }),
@@ -164,9 +223,188 @@ export async function assignTask(
   id: string,
   assignee: string | null,
 ): Promise<void> {
-  await fetch(`/api/tasks/${id}/assign`, {
+  await apiFetch(`/api/tasks/${id}/assign`, {
     method: "PUT",
     headers: { "Content-Type": "application/json" },
     body: JSON.stringify({ assignee }),
   });
 }
+
+// Memory
+
+async function memoryResponse(res: Response): Promise<MemoryDocument> {
+  if (!res.ok) {
+    const e = await res.json();
+    throw new Error(e.error);
+  }
+  return res.json();
+}
+
+export async function fetchMemory(): Promise<MemoryDocument> {
+  return memoryResponse(await apiFetch("/api/memory"));
+}
+
+export async function saveMemory(content: string): Promise<MemoryDocument> {
+  const res = await apiFetch("/api/memory", {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ content }),
+  });
+  return memoryResponse(res);
+}
+
+export async function searchMemory(q: string): Promise<MemorySearchResult[]> {
+  const res = await apiFetch(`/api/memory/search${buildQuery({ q })}`);
+  if (!res.ok) return [];
+  return res.json();
+}
+
+export async function editMemoryFact(
+  line: number,
+  expected: string,
+  text: string,
+): Promise<MemoryDocument> {
+  const res = await apiFetch(`/api/memory/facts/${line}`, {
+    method: "PUT",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify({ expected, text }),
+  });
+  return memoryResponse(res);
+}
+
+export async function deleteMemoryFact(
+  line: number,
+  expected: string,
+): Promise<MemoryDocument> {
+  const res = await apiFetch(
+    `/api/memory/facts/${line}${buildQuery({ expected })}`,
+    { method: "DELETE" },
+  );
+  return memoryResponse(res);
+}
+
+// Compare
+
+export interface CompareRequest {
+  prompt: string;
+  tierA: string;
+  tierB: string;
+  chatId?: string;
+  persona?: string;
+}
+
+// Streams newline-delimited JSON events until both sides finish
+export async function streamComparison(
+  req: CompareRequest,
+  onEvent: (event: CompareEvent) => void,
+): Promise<void> {
+  const res = await apiFetch("/api/compare", {
+    method: "POST",
+    headers: { "Content-Type": "application/json" },
+    body: JSON.stringify(req),
+  });
+  if (!res.ok || !res.body) {
+    const body = await res.json().catch(() => ({}));
+    throw new Error(body.error || `compare failed (${res.status})`);
+  }
+  const reader = res.body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = "";
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    buffer += decoder.decode(value, { stream: true });
+    const lines = buffer.split("\n");
+    buffer = lines.pop() || "";
+    for (const line of lines) {
+      if (line.trim()) onEvent(JSON.parse(line));
+    }
+  }
+}
+
+export async function setComparePreference(
+  id: string,
+  choice: ComparePreference,
+): Promise<void> {
+  const res = await apiFetch(
+    `/api/compare/${encodeURIComponent(id)}/preference`,
+    {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ choice }),
+    },
+  );
+  if (!res.ok) {
+    const body = await res.json().catch(() => ({}));
+    throw new Error(body.error || `preference failed (${res.status})`);
+  }
+}
+
+export async function fetchCompareStats(): Promise<{
+  tiers: CompareTierStats[];
+  recent: Comparison[];
+}> {
+  const res = await apiFetch("/api/compare/stats");
+  return res.json();
+}
+
+// Status
+
+export async function fetchStatus(): Promise<GatewayStatus> {
+  const res = await apiFetch("/api/status");
+  if (!res.ok) throw new Error(`status failed (${res.status})`);
+  return res.json();
+}
+
+export async function controlChannel(
+  name: string,
+  action: "restart" | "disable" | "enable",
+): Promise<string> {
+  const res = await apiFetch(
+    `/api/channels/${encodeURIComponent(name)}/${action}`,
+    { method: "POST" },
+  );
+  const body = await res.json().catch(() => ({}));
+  if (!res.ok) {
+    throw new Error(body.error || `${action} failed (${res.status})`);
+  }
+  return body.message;
+}
+
+// Logs
+
+// Reads the server-sent events of /api/logs/stream until `signal` aborts.
+// EventSource can't send the password header, so this parses the stream.
+export async function streamLogs(
+  level: string,
+  target: string,
+  onLine: (line: LogLine) => void,
+  signal: AbortSignal,
+): Promise<void> {
+  const res = await apiFetch(
+    `/api/logs/stream${buildQuery({ level, target })}`,
+    { signal },
+  );
+  if (!res.ok || !res.body) {
+    const body = await res.json().catch(() => ({}));
+    throw new Error(body.error || `log stream failed (${res.status})`);
+  }
+  const reader = res.body.getReader();
+  const decoder = new TextDecoder();
+  let buffer = "";
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    buffer += decoder.decode(value, { stream: true });
+    const events = buffer.split("\n\n");
+    buffer = events.pop() || "";
+    for (const event of events) {
+      const data = event
+        .split("\n")
+        .filter((l) => l.startsWith("data:"))
+        .map((l) => l.slice(5).trimStart())
+        .join("\n");
+      if (data) onLine(JSON.parse(data));
+    }
+  }
+}
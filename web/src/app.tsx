@@ -4,6 +4,10 @@ import { Header } from "./components/Header";
 import { ChatView } from "./components/ChatView";
 import { UsageView } from "./components/UsageView";
 import { TasksView } from "./components/TasksView";
+import { MemoryView } from "./components/MemoryView";
+import { CompareView } from "./components/CompareView";
+import { StatusView } from "./components/StatusView";
+import { LogsView } from "./components/LogsView";
 import { PersonaPicker } from "./components/PersonaPicker";
 import { PersonaManager } from "./components/PersonaManager";
 import { PersonaEditor } from "./components/PersonaEditor";
@@ -20,6 +24,8 @@ import { personas, getPersona } from "./state/personas";
 import { clearMessages } from "./state/messages";
 import { send } from "./state/websocket";
 import { taskEditorOpen } from "./state/tasks";
+import { apiFetch, shareSession } from "./api";
+import { Presence } from "./components/Presence";
 import type { Persona } from "./types";
 
 function closeSidebarMobile(setSidebarHidden: (v: boolean) => void) {
@@ -34,6 +40,7 @@ export function App() {
   const [editorOpen, setEditorOpen] = useState(false);
   const [editingPersona, setEditingPersona] = useState<Persona | null>(null);
   const [pickerOpen, setPickerOpen] = useState(false);
+  const [shareLabel, setShareLabel] = useState("Share");
 
   const currentRoute = route.value.name;
   const routeParam = route.value.param;
@@ -72,7 +79,7 @@ export function App() {
   }
 
   function handleDeleteChat(id: string) {
-    fetch(`/api/sessions/${encodeURIComponent(id)}`, {
+    apiFetch(`/api/sessions/${encodeURIComponent(id)}`, {
       method: "DELETE",
     }).catch(() => {});
     send({ type: "delete_session", chatId: id });
@@ -110,6 +117,21 @@ export function App() {
     });
   }
 
+  // Copy a watch-only link for the active chat
+  async function handleShareChat() {
+    const chatId = activeChatId.value;
+    if (!chatId) return;
+    try {
+      const { url } = await shareSession(chatId);
+      await navigator.clipboard.writeText(location.origin + url);
+      setShareLabel("Link copied");
+    } catch (e) {
+      setShareLabel("Share failed");
+      console.error(e);
+    }
+    setTimeout(() => setShareLabel("Share"), 2000);
+  }
+
   function renderHeaderActions() {
     switch (currentRoute) {
       case "chats": {
@@ -145,6 +167,16 @@ export function App() {
                 {chatId.slice(0, 8)}
               </span>
             )}
+            <Presence chatId={chatId} />
+            {chatId && (
+              <button
+                class="btn-sm"
+                title="Copy a read-only link to this chat"
+                onClick={handleShareChat}
+              >
+                {shareLabel}
+              </button>
+            )}
           </>
         );
       }
@@ -170,8 +202,16 @@ export function App() {
     switch (currentRoute) {
       case "tasks":
         return <TasksView initialTaskId={routeParam} />;
+      case "memory":
+        return <MemoryView />;
       case "usage":
         return <UsageView />;
+      case "compare":
+        return <CompareView />;
+      case "status":
+        return <StatusView />;
+      case "logs":
+        return <LogsView />;
       case "chats":
       default:
         return (
@@ -1,17 +1,55 @@
-import { useRef } from "preact/hooks";
+import { useRef, useState } from "preact/hooks";
+import { uploadFile } from "../api";
 import { isGenerating } from "../state/messages";
 import { connectionStatus } from "../state/websocket";
 import css from "./ChatInput.module.css";
 
+interface Attachment {
+  id: number;
+  label: string;
+  // Name the server saved it under, once uploaded
+  name?: string;
+  error?: string;
+}
+
 interface ChatInputProps {
-  onSend: (text: string) => void;
+  onSend: (text: string, files: string[]) => void;
   onCancel: () => void;
+  // Chat that dropped or picked files are uploaded to; no uploads without it
+  chatId?: string | null;
 }
 
-export function ChatInput({ onSend, onCancel }: ChatInputProps) {
+let nextAttachmentId = 0;
+
+export function ChatInput({ onSend, onCancel, chatId }: ChatInputProps) {
   const inputRef = useRef<HTMLTextAreaElement>(null);
+  const fileRef = useRef<HTMLInputElement>(null);
+  const [attachments, setAttachments] = useState<Attachment[]>([]);
+  const [dragging, setDragging] = useState(false);
   const generating = isGenerating.value;
   const connected = connectionStatus.value === "connected";
+  const uploading = attachments.some((a) => !a.name && !a.error);
+
+  function update(id: number, change: Partial<Attachment>) {
+    setAttachments((list) =>
+      list.map((a) => (a.id === id ? { ...a, ...change } : a)),
+    );
+  }
+
+  function addFiles(files: FileList | null) {
+    if (!chatId || !files) return;
+    for (const file of Array.from(files)) {
+      const id = nextAttachmentId++;
+      setAttachments((list) => [...list, { id, label: file.name }]);
+      uploadFile(chatId, file)
+        .then((res) => update(id, { name: res.name }))
+        .catch((e) => update(id, { error: e.message }));
+    }
+  }
+
+  function removeAttachment(id: number) {
+    setAttachments((list) => list.filter((a) => a.id !== id));
+  }
 
   function handleSubmit(e: Event) {
     e.preventDefault();
@@ -20,12 +58,14 @@ export function ChatInput({ onSend, onCancel }: ChatInputProps) {
       return;
     }
     const el = inputRef.current;
-    if (!el) return;
+    if (!el || uploading) return;
     const text = el.value.trim();
-    if (!text) return;
-    onSend(text);
+    const files = attachments.flatMap((a) => (a.name ? [a.name] : []));
+    if (!text && files.length === 0) return;
+    onSend(text, files);
     el.value = "";
     el.style.height = "auto";
+    setAttachments([]);
   }
 
   function handleInput() {
@@ -42,25 +82,93 @@ export function ChatInput({ onSend, onCancel }: ChatInputProps) {
     }
   }
 
+  function handleDragOver(e: DragEvent) {
+    if (!chatId || !e.dataTransfer?.types.includes("Files")) return;
+    e.preventDefault();
+    setDragging(true);
+  }
+
+  function handleDrop(e: DragEvent) {
+    if (!chatId) return;
+    e.preventDefault();
+    setDragging(false);
+    addFiles(e.dataTransfer?.files ?? null);
+  }
+
   return (
-    <form class={css.form} onSubmit={handleSubmit}>
-      <textarea
-        ref={inputRef}
-        class={css.input}
-        placeholder="Type a message..."
-        rows={1}
-        autoComplete="off"
-        disabled={generating}
-        onInput={handleInput}
-        onKeyDown={handleKeyDown}
-      />
-      <button
-        type="submit"
-        class={css.sendBtn}
-        disabled={!generating && !connected}
-      >
-        {generating ? "Stop" : "Send"}
-      </button>
+    <form
+      class={`${css.form} ${dragging ? css.dragging : ""}`}
+      onSubmit={handleSubmit}
+      onDragOver={handleDragOver}
+      onDragLeave={() => setDragging(false)}
+      onDrop={handleDrop}
+    >
+      {attachments.length > 0 && (
+        <div class={css.attachments}>
+          {attachments.map((a) => (
+            <span
+              key={a.id}
+              class={`${css.attachment} ${a.error ? css.failed : ""}`}
+              title={a.error}
+            >
+              {a.label}
+              {!a.name && !a.error && " (uploading...)"}
+              {a.error && ` (${a.error})`}
+              <button
+                type="button"
+                class={css.removeBtn}
+                title="Remove"
+                onClick={() => removeAttachment(a.id)}
+              >
+                &times;
+              </button>
+            </span>
+          ))}
+        </div>
+      )}
+      <div class={css.row}>
+        {chatId && (
+          <>
+            <input
+              ref={fileRef}
+              type="file"
+              multiple
+              hidden
+              onChange={(e) => {
+                const input = e.target as HTMLInputElement;
+                addFiles(input.files);
+                input.value = "";
+              }}
+            />
+            <button
+              type="button"
+              class={css.attachBtn}
+              title="Attach files (or drop them here)"
+              disabled={generating}
+              onClick={() => fileRef.current?.click()}
+            >
+              &#x1F4CE;
+            </button>
+          </>
+        )}
+        <textarea
+          ref={inputRef}
+          class={css.input}
+          placeholder={chatId ? "Type a message or drop a file..." : "Type a message..."}
+          rows={1}
+          autoComplete="off"
+          disabled={generating}
+          onInput={handleInput}
+          onKeyDown={handleKeyDown}
+        />
+        <button
+          type="submit"
+          class={css.sendBtn}
+          disabled={!generating && (!connected || uploading)}
+        >
+          {generating ? "Stop" : "Send"}
+        </button>
+      </div>
     </form>
   );
 }
@@ -36,7 +36,7 @@ export function ChatView({
 
   function handleCancel() {
     if (chatId) {
-      send({ type: "cancel", chatId });
+      send({ type: "stop", chatId });
     }
     isGenerating.value = false;
     showThinkingSignal.value = false;
@@ -54,12 +54,15 @@ export function ChatView({
     return () => document.removeEventListener("keydown", onKeyDown);
   }, [chatId]);
 
-  function handleSend(text: string) {
+  function handleSend(text: string, files: string[]) {
     if (!chatId) return;
 
-    addMessage("user", text);
+    const shown = [text, ...files.map((f) => `[file: ${f}]`)]
+      .filter(Boolean)
+      .join("\n");
+    addMessage("user", shown);
     showThinkingSignal.value = true;
-    updateSessionTitle(chatId, text);
+    updateSessionTitle(chatId, text || files[0]);
     updateSessionTime(chatId);
 
     const msg: Record<string, unknown> = {
@@ -68,6 +71,7 @@ export function ChatView({
       chatId,
     };
     if (personaKey) msg.persona = personaKey;
+    if (files.length) msg.files = files;
 
     send(msg as never);
     isGenerating.value = true;
@@ -105,7 +109,11 @@ export function ChatView({
           >
             &#x2193;
           </button>
-          <ChatInput onSend={handleSend} onCancel={handleCancel} />
+          <ChatInput
+            onSend={handleSend}
+            onCancel={handleCancel}
+            chatId={chatId}
+          />
         </div>
       </div>
     </div>
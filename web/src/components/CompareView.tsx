@@ -0,0 +1,281 @@
+import { useState, useEffect } from "preact/hooks";
+import { fetchModelTiers, streamComparison, setComparePreference } from "../api";
+import { activeChatId, getSessionPersona } from "../state/sessions";
+import { personas } from "../state/personas";
+import { tierStats, loadCompareStats } from "../state/compare";
+import { formatTokens, formatCost } from "../state/usage";
+import { renderMarkdown } from "../lib/markdown";
+import type {
+  CompareEvent,
+  ComparePreference,
+  CompareSideResult,
+} from "../types";
+import css from "./CompareView.module.css";
+
+type Side = Partial<CompareSideResult> & { response: string; done: boolean };
+
+const emptySide = (): Side => ({ response: "", done: false });
+
+function formatLatency(ms: number | undefined): string {
+  if (ms === undefined) return "";
+  return ms < 1000 ? `${ms}ms` : `${(ms / 1000).toFixed(1)}s`;
+}
+
+function SidePanel({ label, side }: { label: string; side: Side }) {
+  return (
+    <div class={css.side}>
+      <div class={css.sideHeader}>
+        <strong>{label}</strong>
+        {side.model && <span class={css.model}>{side.model}</span>}
+      </div>
+      <div
+        class={css.response}
+        dangerouslySetInnerHTML={{ __html: renderMarkdown(side.response) }}
+      />
+      {side.error && <div class={css.error}>{side.error}</div>}
+      {side.done && (
+        <div class={css.meta}>
+          {formatLatency(side.latencyMs)} · {formatTokens(side.tokens)} tokens
+          · {formatCost(side.cost)}
+        </div>
+      )}
+    </div>
+  );
+}
+
+export function CompareView() {
+  const [tiers, setTiers] = useState<string[]>(["default"]);
+  const [tierA, setTierA] = useState("default");
+  const [tierB, setTierB] = useState("default");
+  const [persona, setPersona] = useState("");
+  const [useChat, setUseChat] = useState(false);
+  const [prompt, setPrompt] = useState("");
+  const [running, setRunning] = useState(false);
+  const [id, setId] = useState<string | null>(null);
+  const [a, setA] = useState<Side>(emptySide());
+  const [b, setB] = useState<Side>(emptySide());
+  const [preference, setPreference] = useState<ComparePreference | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const chatId = activeChatId.value;
+
+  useEffect(() => {
+    fetchModelTiers().then((list) => {
+      setTiers(list);
+      setTierA(list.includes("default") ? "default" : list[0]);
+      setTierB(list.find((t) => t !== "default") || list[0]);
+    });
+    loadCompareStats();
+  }, []);
+
+  useEffect(() => {
+    if (useChat && chatId) setPersona(getSessionPersona(chatId) || "");
+  }, [useChat, chatId]);
+
+  function apply(event: CompareEvent) {
+    if ("id" in event) {
+      setId(event.id);
+      return;
+    }
+    if (!("side" in event)) {
+      if (event.error) setError(event.error);
+      return;
+    }
+    const update = event.side === "a" ? setA : setB;
+    if ("delta" in event) {
+      update((s) => ({ ...s, response: s.response + event.delta }));
+    } else {
+      update((s) => ({ ...s, ...event, response: s.response }));
+    }
+  }
+
+  async function handleCompare() {
+    if (!prompt.trim() || running) return;
+    setRunning(true);
+    setId(null);
+    setA(emptySide());
+    setB(emptySide());
+    setPreference(null);
+    setError(null);
+    try {
+      await streamComparison(
+        {
+          prompt,
+          tierA,
+          tierB,
+          chatId: useChat && chatId ? chatId : undefined,
+          persona: persona || undefined,
+        },
+        apply,
+      );
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e));
+    }
+    setRunning(false);
+    loadCompareStats();
+  }
+
+  async function handlePrefer(choice: ComparePreference) {
+    if (!id) return;
+    try {
+      await setComparePreference(id, choice);
+      setPreference(choice);
+      loadCompareStats();
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e));
+    }
+  }
+
+  const choices: { key: ComparePreference; label: string }[] = [
+    { key: "a", label: "A is better" },
+    { key: "tie", label: "Tie" },
+    { key: "b", label: "B is better" },
+    { key: "neither", label: "Neither" },
+  ];
+  const stats = tierStats.value;
+
+  return (
+    <div class={css.view}>
+      <div class={css.content}>
+        <div class={css.filters}>
+          <label>
+            Tier A
+            <select
+              value={tierA}
+              onChange={(e) => setTierA((e.target as HTMLSelectElement).value)}
+            >
+              {tiers.map((t) => (
+                <option key={t} value={t}>
+                  {t}
+                </option>
+              ))}
+            </select>
+          </label>
+          <label>
+            Tier B
+            <select
+              value={tierB}
+              onChange={(e) => setTierB((e.target as HTMLSelectElement).value)}
+            >
+              {tiers.map((t) => (
+                <option key={t} value={t}>
+                  {t}
+                </option>
+              ))}
+            </select>
+          </label>
+          <label>
+            Persona
+            <select
+              value={persona}
+              onChange={(e) => setPersona((e.target as HTMLSelectElement).value)}
+            >
+              <option value="">None</option>
+              {personas.value.map((p) => (
+                <option key={p.key} value={p.key}>
+                  {p.name || p.key}
+                </option>
+              ))}
+            </select>
+          </label>
+          {chatId && (
+            <label class={css.checkbox}>
+              <input
+                type="checkbox"
+                checked={useChat}
+                onChange={(e) =>
+                  setUseChat((e.target as HTMLInputElement).checked)
+                }
+              />
+              Include current chat ({chatId.slice(0, 8)})
+            </label>
+          )}
+        </div>
+
+        <textarea
+          class={css.prompt}
+          placeholder="Prompt to send to both tiers"
+          value={prompt}
+          onInput={(e) => setPrompt((e.target as HTMLTextAreaElement).value)}
+          onKeyDown={(e) => {
+            if (e.key === "Enter" && (e.metaKey || e.ctrlKey)) handleCompare();
+          }}
+        />
+        <div class={css.actions}>
+          <span class={css.hint}>
+            Replies aren't added to the chat. Tools are not available.
+          </span>
+          <button
+            class="btn-primary btn-sm"
+            disabled={running || !prompt.trim()}
+            onClick={handleCompare}
+          >
+            {running ? "Comparing…" : "Compare"}
+          </button>
+        </div>
+
+        {error && <div class={css.error}>{error}</div>}
+
+        {(running || id) && (
+          <>
+            <div class={css.sides}>
+              <SidePanel label={`A · ${a.tier || tierA}`} side={a} />
+              <SidePanel label={`B · ${b.tier || tierB}`} side={b} />
+            </div>
+            {!running && id && (
+              <div class={css.preference}>
+                {choices.map((c) => (
+                  <button
+                    key={c.key}
+                    class={
+                      preference === c.key ? "btn-primary btn-sm" : "btn-sm"
+                    }
+                    onClick={() => handlePrefer(c.key)}
+                  >
+                    {c.label}
+                  </button>
+                ))}
+              </div>
+            )}
+          </>
+        )}
+
+        {stats.length > 0 && (
+          <>
+            <h3>By tier</h3>
+            <table class={css.stats}>
+              <thead>
+                <tr>
+                  <th>Tier</th>
+                  <th>Runs</th>
+                  <th>Preferred</th>
+                  <th>Ties</th>
+                  <th>Neither</th>
+                  <th>Avg latency</th>
+                  <th>Avg tokens</th>
+                  <th>Cost</th>
+                </tr>
+              </thead>
+              <tbody>
+                {stats.map((s) => (
+                  <tr key={s.tier}>
+                    <td>{s.tier}</td>
+                    <td>{s.comparisons}</td>
+                    <td>
+                      {s.preferred}/{s.rated}
+                    </td>
+                    <td>{s.ties}</td>
+                    <td>{s.neither}</td>
+                    <td>{formatLatency(s.avgLatencyMs)}</td>
+                    <td>{formatTokens(s.avgTokens)}</td>
+                    <td>{formatCost(s.totalCost)}</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </>
+        )}
+      </div>
+    </div>
+  );
+}
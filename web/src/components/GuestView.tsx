@@ -0,0 +1,56 @@
+import { activeChatId } from "../state/sessions";
+import { viewOnly } from "../state/guest";
+import { send } from "../state/websocket";
+import {
+  addMessage,
+  isGenerating,
+  showThinking as showThinkingSignal,
+} from "../state/messages";
+import { Header } from "./Header";
+import { MessageList } from "./MessageList";
+import { ChatInput } from "./ChatInput";
+import { Presence } from "./Presence";
+import css from "./ChatView.module.css";
+
+// Single-chat view for guest and watch links: no sidebar, personas, or other
+// tabs. Watch links get no input box.
+export function GuestView() {
+  const chatId = activeChatId.value;
+
+  function handleCancel() {
+    if (chatId) {
+      send({ type: "stop", chatId });
+    }
+    isGenerating.value = false;
+    showThinkingSignal.value = false;
+  }
+
+  function handleSend(text: string) {
+    if (!chatId) return;
+    addMessage("user", text);
+    showThinkingSignal.value = true;
+    send({ type: "message", content: text, chatId } as never);
+    isGenerating.value = true;
+  }
+
+  return (
+    <>
+      <Header guest>
+        {viewOnly.value && <span class="persona-badge">Watching</span>}
+        <Presence chatId={chatId} />
+      </Header>
+      <div class="mainArea">
+        <div class={css.chatArea}>
+          <div class={css.chatBody}>
+            <div class={css.chatMain}>
+              <MessageList />
+              {!viewOnly.value && (
+                <ChatInput onSend={handleSend} onCancel={handleCancel} />
+              )}
+            </div>
+          </div>
+        </div>
+      </div>
+    </>
+  );
+}
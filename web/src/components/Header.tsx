@@ -6,18 +6,25 @@ import css from "./Header.module.css";
 const tabs: { key: RouteName; label: string }[] = [
   { key: "chats", label: "Chats" },
   { key: "tasks", label: "Tasks" },
+  { key: "memory", label: "Memory" },
   { key: "usage", label: "Usage" },
+  { key: "compare", label: "Compare" },
+  { key: "status", label: "Status" },
+  { key: "logs", label: "Logs" },
 ];
 
 interface HeaderProps {
   onToggleSidebar?: () => void;
   showSidebarToggle?: boolean;
+  /** Guest links get the brand and status only. */
+  guest?: boolean;
   children?: ComponentChildren;
 }
 
 export function Header({
   onToggleSidebar,
   showSidebarToggle,
+  guest,
   children,
 }: HeaderProps) {
   const currentRoute = route.value.name;
@@ -37,15 +44,16 @@ export function Header({
       )}
       <span class={css.brand}>Patina</span>
       <nav class={css.nav}>
-        {tabs.map((t) => (
-          <button
-            key={t.key}
-            class={t.key === currentRoute ? css.navTabActive : css.navTab}
-            onClick={() => navigate(t.key)}
-          >
-            {t.label}
-          </button>
-        ))}
+        {!guest &&
+          tabs.map((t) => (
+            <button
+              key={t.key}
+              class={t.key === currentRoute ? css.navTabActive : css.navTab}
+              onClick={() => navigate(t.key)}
+            >
+              {t.label}
+            </button>
+          ))}
       </nav>
       {children && <div class={css.actions}>{children}</div>}
       <span class={`status ${status}`}>{statusLabel}</span>
@@ -0,0 +1,129 @@
+import { useState, useEffect, useRef } from "preact/hooks";
+import { streamLogs } from "../api";
+import type { LogLine } from "../types";
+import css from "./LogsView.module.css";
+
+// Older lines are dropped past this
+const MAX_LINES = 2000;
+
+const levels = ["error", "warn", "info", "debug", "trace"];
+
+const levelClass: Record<LogLine["level"], string> = {
+  ERROR: css.levelError,
+  WARN: css.levelWarn,
+  INFO: css.levelInfo,
+  DEBUG: css.levelQuiet,
+  TRACE: css.levelQuiet,
+};
+
+function formatTime(timestamp: string): string {
+  const d = new Date(timestamp);
+  return isNaN(d.getTime()) ? timestamp : d.toLocaleTimeString();
+}
+
+export function LogsView() {
+  const [level, setLevel] = useState("info");
+  const [target, setTarget] = useState("");
+  const [lines, setLines] = useState<LogLine[]>([]);
+  const [paused, setPaused] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+  const pausedRef = useRef(false);
+  const listRef = useRef<HTMLDivElement>(null);
+  const stickRef = useRef(true);
+
+  pausedRef.current = paused;
+
+  // Reconnect whenever the filters change; the server resends its backlog
+  useEffect(() => {
+    const controller = new AbortController();
+    const timer = setTimeout(() => {
+      setLines([]);
+      setError(null);
+      streamLogs(
+        level,
+        target.trim(),
+        (line) => {
+          if (pausedRef.current) return;
+          setLines((prev) => {
+            const next = [...prev, line];
+            return next.length > MAX_LINES ? next.slice(-MAX_LINES) : next;
+          });
+        },
+        controller.signal,
+      ).catch((e) => {
+        if (!controller.signal.aborted) {
+          setError(e instanceof Error ? e.message : String(e));
+        }
+      });
+    }, 300);
+    return () => {
+      clearTimeout(timer);
+      controller.abort();
+    };
+  }, [level, target]);
+
+  // Follow new lines unless the user scrolled up
+  useEffect(() => {
+    const el = listRef.current;
+    if (el && stickRef.current) el.scrollTop = el.scrollHeight;
+  }, [lines]);
+
+  function handleScroll() {
+    const el = listRef.current;
+    if (!el) return;
+    stickRef.current = el.scrollHeight - el.scrollTop - el.clientHeight < 40;
+  }
+
+  return (
+    <div class={css.view}>
+      <div class={css.toolbar}>
+        <label>
+          Level
+          <select
+            value={level}
+            onChange={(e) => setLevel((e.target as HTMLSelectElement).value)}
+          >
+            {levels.map((l) => (
+              <option key={l} value={l}>
+                {l}
+              </option>
+            ))}
+          </select>
+        </label>
+        <label>
+          Target
+          <input
+            type="text"
+            placeholder="e.g. patina_core::agent"
+            value={target}
+            onInput={(e) => setTarget((e.target as HTMLInputElement).value)}
+          />
+        </label>
+        <div class={css.buttons}>
+          <button class="btn-sm" onClick={() => setPaused(!paused)}>
+            {paused ? "Resume" : "Pause"}
+          </button>
+          <button class="btn-sm" onClick={() => setLines([])}>
+            Clear
+          </button>
+        </div>
+      </div>
+      {error && <div class={css.error}>{error}</div>}
+      <div class={css.lines} ref={listRef} onScroll={handleScroll}>
+        {lines.length === 0 && !error && (
+          <div class={css.empty}>Waiting for log lines…</div>
+        )}
+        {lines.map((l, i) => (
+          <div key={i} class={css.line}>
+            <span class={css.time}>{formatTime(l.timestamp)}</span>
+            <span class={`${css.level} ${levelClass[l.level] || ""}`}>
+              {l.level}
+            </span>
+            <span class={css.target}>{l.target}</span>
+            <span class={css.message}>{l.message}</span>
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,188 @@
+import { useState, useEffect } from "preact/hooks";
+import {
+  memoryContent,
+  memoryFacts,
+  memoryError,
+  searchResults,
+  loadMemory,
+  saveMemory,
+  editFact,
+  deleteFact,
+  search,
+} from "../state/memory";
+import type { MemoryFact } from "../types";
+import css from "./MemoryView.module.css";
+
+function FactRow({ fact }: { fact: MemoryFact }) {
+  const [editing, setEditing] = useState(false);
+  const [text, setText] = useState(fact.text);
+
+  async function handleSave() {
+    await editFact(fact, text);
+    setEditing(false);
+  }
+
+  async function handleDelete() {
+    if (!confirm(`Forget "${fact.text}"?`)) return;
+    await deleteFact(fact);
+  }
+
+  if (editing) {
+    return (
+      <li class={css.fact}>
+        <input
+          type="text"
+          class={css.factInput}
+          value={text}
+          onInput={(e) => setText((e.target as HTMLInputElement).value)}
+          onKeyDown={(e) => {
+            if (e.key === "Enter") handleSave();
+            if (e.key === "Escape") setEditing(false);
+          }}
+        />
+        <button class="btn-primary btn-sm" onClick={handleSave}>
+          Save
+        </button>
+        <button class="btn-text" onClick={() => setEditing(false)}>
+          Cancel
+        </button>
+      </li>
+    );
+  }
+
+  return (
+    <li class={css.fact}>
+      <span class={css.factText}>{fact.text}</span>
+      <button
+        class="btn-inline"
+        onClick={() => {
+          setText(fact.text);
+          setEditing(true);
+        }}
+      >
+        Edit
+      </button>
+      <button class="btn-danger" onClick={handleDelete}>
+        Delete
+      </button>
+    </li>
+  );
+}
+
+export function MemoryView() {
+  const [query, setQuery] = useState("");
+  const [rawMode, setRawMode] = useState(false);
+  const [draft, setDraft] = useState("");
+
+  const facts = memoryFacts.value;
+  const results = searchResults.value;
+  const error = memoryError.value;
+
+  useEffect(() => {
+    loadMemory();
+  }, []);
+
+  // Group facts under their MEMORY.md headings, keeping file order
+  const sections: { name: string; facts: MemoryFact[] }[] = [];
+  for (const fact of facts) {
+    const name = fact.section || "";
+    const last = sections[sections.length - 1];
+    if (last && last.name === name) {
+      last.facts.push(fact);
+    } else {
+      sections.push({ name, facts: [fact] });
+    }
+  }
+
+  async function handleSaveRaw() {
+    await saveMemory(draft);
+    setRawMode(false);
+  }
+
+  return (
+    <div class={css.view}>
+      <div class={css.content}>
+        <form
+          class={css.searchBar}
+          onSubmit={(e) => {
+            e.preventDefault();
+            search(query);
+          }}
+        >
+          <input
+            type="search"
+            placeholder="Search memory, history, and workspace notes"
+            value={query}
+            onInput={(e) => setQuery((e.target as HTMLInputElement).value)}
+          />
+          <button type="submit" class="btn-primary btn-sm">
+            Search
+          </button>
+        </form>
+
+        {results.length > 0 && (
+          <div class={css.results}>
+            {results.map((r) => (
+              <div key={`${r.path}:${r.startLine}`} class={css.result}>
+                <div class={css.resultPath}>
+                  {r.path}:{r.startLine}-{r.endLine}
+                </div>
+                <pre class={css.resultContent}>{r.content}</pre>
+              </div>
+            ))}
+          </div>
+        )}
+
+        <div class={css.titleRow}>
+          <h3>Long-term memory</h3>
+          {!rawMode && (
+            <button
+              class="btn-inline"
+              onClick={() => {
+                setDraft(memoryContent.value);
+                setRawMode(true);
+              }}
+            >
+              Edit MEMORY.md
+            </button>
+          )}
+        </div>
+
+        {error && <div class={css.error}>{error}</div>}
+
+        {rawMode ? (
+          <div class={css.rawEditor}>
+            <textarea
+              value={draft}
+              onInput={(e) => setDraft((e.target as HTMLTextAreaElement).value)}
+            />
+            <div class="modal-actions">
+              <button class="btn-text" onClick={() => setRawMode(false)}>
+                Cancel
+              </button>
+              <button class="btn-primary" onClick={handleSaveRaw}>
+                Save
+              </button>
+            </div>
+          </div>
+        ) : facts.length === 0 ? (
+          <p class={css.empty}>
+            Nothing remembered yet. Facts appear here after conversations are
+            consolidated.
+          </p>
+        ) : (
+          sections.map((section, i) => (
+            <div key={i} class={css.section}>
+              {section.name && <h4>{section.name}</h4>}
+              <ul class={css.facts}>
+                {section.facts.map((fact) => (
+                  <FactRow key={`${fact.line}:${fact.text}`} fact={fact} />
+                ))}
+              </ul>
+            </div>
+          ))
+        )}
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,12 @@
+import { viewers } from "../state/websocket";
+
+// Who else has this chat open, e.g. someone watching through a share link.
+export function Presence({ chatId }: { chatId: string | null }) {
+  const others = chatId ? viewers.value[chatId] || [] : [];
+  if (others.length === 0) return null;
+  return (
+    <span class="presence" title={others.join(", ")}>
+      &#9679; {others.length} watching
+    </span>
+  );
+}
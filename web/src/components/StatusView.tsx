@@ -0,0 +1,306 @@
+import { useState, useEffect } from "preact/hooks";
+import { controlChannel, fetchStatus } from "../api";
+import { formatTokens, formatCost } from "../state/usage";
+import type { GatewayStatus } from "../types";
+import css from "./StatusView.module.css";
+
+const REFRESH_MS = 5000;
+
+function formatUptime(secs: number | null): string {
+  if (secs === null) return "—";
+  const days = Math.floor(secs / 86400);
+  const hours = Math.floor((secs % 86400) / 3600);
+  const mins = Math.floor((secs % 3600) / 60);
+  if (days) return `${days}d ${hours}h`;
+  if (hours) return `${hours}h ${mins}m`;
+  return `${mins}m`;
+}
+
+function formatBytes(n: number): string {
+  if (n >= 1024 * 1024) return (n / (1024 * 1024)).toFixed(1) + " MB";
+  if (n >= 1024) return (n / 1024).toFixed(1) + " KB";
+  return `${n} B`;
+}
+
+function formatTime(value: string | number | null): string {
+  if (value === null) return "—";
+  return new Date(value).toLocaleString();
+}
+
+export function StatusView() {
+  const [status, setStatus] = useState<GatewayStatus | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [notice, setNotice] = useState<string | null>(null);
+  const [busy, setBusy] = useState<string | null>(null);
+
+  async function refresh() {
+    const next = await fetchStatus();
+    setStatus(next);
+    setError(null);
+  }
+
+  async function act(name: string, action: "restart" | "disable" | "enable") {
+    setBusy(name);
+    try {
+      setNotice(await controlChannel(name, action));
+      await refresh();
+    } catch (e) {
+      setError(e instanceof Error ? e.message : String(e));
+    } finally {
+      setBusy(null);
+    }
+  }
+
+  useEffect(() => {
+    let cancelled = false;
+    async function load() {
+      try {
+        const next = await fetchStatus();
+        if (!cancelled) {
+          setStatus(next);
+          setError(null);
+        }
+      } catch (e) {
+        if (!cancelled) setError(e instanceof Error ? e.message : String(e));
+      }
+    }
+    load();
+    const timer = setInterval(load, REFRESH_MS);
+    return () => {
+      cancelled = true;
+      clearInterval(timer);
+    };
+  }, []);
+
+  if (!status) {
+    return (
+      <div class={css.view}>
+        <div class={css.content}>
+          {error ? (
+            <div class={css.error}>{error}</div>
+          ) : (
+            <div class={css.noData}>Loading…</div>
+          )}
+        </div>
+      </div>
+    );
+  }
+
+  const channels = Object.entries(status.channels);
+  const t = status.transcription;
+
+  return (
+    <div class={css.view}>
+      <div class={css.content}>
+        {error && <div class={css.error}>{error}</div>}
+        <div class={css.summaryCards}>
+          <div class={css.card}>
+            <div class={css.cardLabel}>Uptime</div>
+            <div class={css.cardValue}>{formatUptime(status.uptimeSecs)}</div>
+          </div>
+          <div class={css.card}>
+            <div class={css.cardLabel}>Cost today</div>
+            <div class={css.cardValue}>
+              {status.today ? formatCost(status.today.cost) : "—"}
+            </div>
+            {status.today && (
+              <div class={css.cardNote}>
+                {status.today.calls} calls ·{" "}
+                {formatTokens(status.today.totalTokens)} tokens
+              </div>
+            )}
+          </div>
+          <div class={css.card}>
+            <div class={css.cardLabel}>Subagents running</div>
+            <div class={css.cardValue}>
+              {status.subagents ? status.subagents.length : "—"}
+            </div>
+          </div>
+          <div class={css.card}>
+            <div class={css.cardLabel}>Memory index</div>
+            <div class={css.cardValue}>
+              {status.memory ? formatBytes(status.memory.bytes) : "—"}
+            </div>
+            {status.memory && (
+              <div class={css.cardNote}>
+                {status.memory.files} files · {status.memory.chunks} chunks
+              </div>
+            )}
+          </div>
+        </div>
+
+        <h3>Channels</h3>
+        {notice && <div class={css.notice}>{notice}</div>}
+        <table class={css.table}>
+          <thead>
+            <tr>
+              <th>Channel</th>
+              <th>State</th>
+              <th>Since</th>
+              <th>Queued</th>
+              <th>Sent</th>
+              <th>Failed</th>
+              <th></th>
+            </tr>
+          </thead>
+          <tbody>
+            {channels.map(([name, ch]) => (
+              <tr key={name}>
+                <td>{name}</td>
+                <td>
+                  <span class={css[ch.state]}>{ch.state}</span>
+                  {ch.error && <div class={css.detail}>{ch.error}</div>}
+                </td>
+                <td>{formatTime(ch.since)}</td>
+                <td>{ch.queue?.queued ?? 0}</td>
+                <td>{ch.queue?.sent ?? 0}</td>
+                <td>{ch.queue?.failed ?? 0}</td>
+                <td>
+                  {/* Restarting web would drop this page's own server */}
+                  {name !== "web" && (
+                    <div class={css.actions}>
+                      <button
+                        class="btn-sm"
+                        disabled={busy !== null}
+                        onClick={() => act(name, "restart")}
+                      >
+                        Restart
+                      </button>
+                      {ch.state === "disabled" ? (
+                        <button
+                          class="btn-sm"
+                          disabled={busy !== null}
+                          onClick={() => act(name, "enable")}
+                        >
+                          Enable
+                        </button>
+                      ) : (
+                        <button
+                          class="btn-sm"
+                          disabled={busy !== null}
+                          onClick={() => act(name, "disable")}
+                        >
+                          Disable
+                        </button>
+                      )}
+                    </div>
+                  )}
+                </td>
+              </tr>
+            ))}
+            {channels.length === 0 && (
+              <tr>
+                <td colSpan={7} class={css.noData}>
+                  No channels started
+                </td>
+              </tr>
+            )}
+          </tbody>
+        </table>
+
+        <h3>Model tiers</h3>
+        <table class={css.table}>
+          <thead>
+            <tr>
+              <th>Tier</th>
+              <th>Model</th>
+              <th>Provider</th>
+              <th>Waiting for rate limit</th>
+            </tr>
+          </thead>
+          <tbody>
+            {status.models.map((m) => (
+              <tr key={m.tier}>
+                <td>{m.tier}</td>
+                <td>{m.model}</td>
+                <td>{m.provider}</td>
+                <td>{status.providers[m.provider]?.queued ?? "—"}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+
+        {t && (
+          <>
+            <h3>Transcription</h3>
+            <dl class={css.facts}>
+              <dt>Mode</dt>
+              <dd>{t.mode}</dd>
+              <dt>Chain</dt>
+              <dd>{t.chain.length ? t.chain.join(" → ") : "none"}</dd>
+              <dt>Local model</dt>
+              <dd>
+                {t.queue.workers
+                  ? `loaded (${t.queue.workers} workers)`
+                  : "not loaded"}
+                {t.lastLoaded &&
+                  `, last loaded with ${t.lastLoaded.loaded} at ${formatTime(t.lastLoaded.loadedAt)}`}
+              </dd>
+              <dt>Queue</dt>
+              <dd>
+                {t.queue.queued} waiting, {t.queue.active} active,{" "}
+                {t.queue.completed} done, {t.queue.failed} failed
+              </dd>
+            </dl>
+          </>
+        )}
+
+        {status.cron && (
+          <>
+            <h3>Scheduled jobs</h3>
+            <table class={css.table}>
+              <thead>
+                <tr>
+                  <th>Name</th>
+                  <th>Kind</th>
+                  <th>Next run</th>
+                  <th>Last result</th>
+                </tr>
+              </thead>
+              <tbody>
+                {status.cron.map((job) => (
+                  <tr key={job.id}>
+                    <td>{job.name}</td>
+                    <td>{job.kind}</td>
+                    <td>{formatTime(job.nextRunAtMs)}</td>
+                    <td>
+                      {job.lastStatus ?? "—"}
+                      {job.lastError && (
+                        <div class={css.detail}>{job.lastError}</div>
+                      )}
+                    </td>
+                  </tr>
+                ))}
+                {status.cron.length === 0 && (
+                  <tr>
+                    <td colSpan={4} class={css.noData}>
+                      No enabled jobs
+                    </td>
+                  </tr>
+                )}
+              </tbody>
+            </table>
+          </>
+        )}
+
+        {status.subagents && status.subagents.length > 0 && (
+          <>
+            <h3>Subagents</h3>
+            <ul class={css.list}>
+              {status.subagents.map((s) => (
+                <li key={s.id}>
+                  <code>{s.id}</code> {s.label}
+                </li>
+              ))}
+            </ul>
+          </>
+        )}
+
+        <div class={css.footer}>
+          patina {status.version}
+          {status.startedAt && ` · started ${formatTime(status.startedAt)}`}
+        </div>
+      </div>
+    </div>
+  );
+}
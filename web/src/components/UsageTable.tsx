@@ -1,6 +1,6 @@
 import { useState } from "preact/hooks";
 import type { UsageRow } from "../types";
-import { formatTokens, formatCost } from "../state/usage";
+import { formatTokens, formatCost, formatMinutes } from "../state/usage";
 import css from "./UsageView.module.css";
 
 interface UsageTableProps {
@@ -15,6 +15,8 @@ const columns: { key: string; label: string }[] = [
   { key: "output_tokens", label: "Output" },
   { key: "total_tokens", label: "Total" },
   { key: "cached_input_tokens", label: "Cached" },
+  { key: "audio_seconds", label: "Audio" },
+  { key: "images", label: "Images" },
   { key: "estimated_cost", label: "Cost" },
 ];
 
@@ -32,6 +34,9 @@ function getCellValue(row: UsageRow, key: string, firstCol: string): string {
   if (key === "estimated_cost") {
     return formatCost(row.estimated_cost);
   }
+  if (key === "audio_seconds") {
+    return formatMinutes(row.audio_seconds);
+  }
   const val = (row as unknown as Record<string, unknown>)[key];
   return formatTokens(val as number);
 }
@@ -1,5 +1,6 @@
 import { render } from "preact";
 import { App } from "./app";
+import { GuestView } from "./components/GuestView";
 import "./styles/global.css";
 import "./styles/markdown.css";
 import {
@@ -10,20 +11,27 @@ import {
 } from "./state/sessions";
 import { loadPersonas } from "./state/personas";
 import { connectWs } from "./state/websocket";
+import { guestToken } from "./state/guest";
 import { route } from "./router";
 
-// Initialize state
-loadSessions();
-syncSessions();
+if (guestToken) {
+  // The server assigns the chat when the socket connects
+  connectWs();
+  render(<GuestView />, document.getElementById("app")!);
+} else {
+  // Initialize state
+  loadSessions();
+  syncSessions();
 
-const initial = route.peek();
-if (initial.name === "chats" && initial.param) {
-  activeChatId.value = initial.param;
-} else if (sessions.value.length > 0) {
-  activeChatId.value = sessions.value[0].id;
-}
+  const initial = route.peek();
+  if (initial.name === "chats" && initial.param) {
+    activeChatId.value = initial.param;
+  } else if (sessions.value.length > 0) {
+    activeChatId.value = sessions.value[0].id;
+  }
 
-loadPersonas();
-connectWs();
+  loadPersonas();
+  connectWs();
 
-render(<App />, document.getElementById("app")!);
+  render(<App />, document.getElementById("app")!);
+}
@@ -1,13 +1,21 @@
 import { signal } from "@preact/signals";
 
-export type RouteName = "chats" | "tasks" | "usage";
+export type RouteName = "chats" | "tasks" | "memory" | "usage" | "compare" | "status" | "logs";
 
 export interface ParsedRoute {
   name: RouteName;
   param: string | null;
 }
 
-const validRoutes: RouteName[] = ["chats", "tasks", "usage"];
+const validRoutes: RouteName[] = [
+  "chats",
+  "tasks",
+  "memory",
+  "usage",
+  "compare",
+  "status",
+  "logs",
+];
 
 function parseHash(): ParsedRoute {
   const raw = window.location.hash.replace(/^#\/?/, "");
@@ -0,0 +1,13 @@
+import { signal } from "@preact/signals";
+import type { CompareTierStats } from "../types";
+import * as api from "../api";
+
+export const tierStats = signal<CompareTierStats[]>([]);
+
+export async function loadCompareStats() {
+  try {
+    tierStats.value = (await api.fetchCompareStats()).tiers || [];
+  } catch (e) {
+    console.error("Failed to load compare stats:", e);
+  }
+}
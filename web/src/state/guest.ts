@@ -0,0 +1,27 @@
+import { signal } from "@preact/signals";
+
+// Guest mode: the page was opened from a `patina share` link. The token stays
+// in sessionStorage so reloads keep working, and the URL is cleaned up.
+const STORAGE_KEY = "patina-guest";
+
+function readGuestToken(): string | null {
+  const params = new URLSearchParams(location.search);
+  const fromUrl = params.get("guest");
+  if (fromUrl) {
+    sessionStorage.setItem(STORAGE_KEY, fromUrl);
+    params.delete("guest");
+    const query = params.toString();
+    history.replaceState(
+      null,
+      "",
+      location.pathname + (query ? `?${query}` : "") + location.hash,
+    );
+    return fromUrl;
+  }
+  return sessionStorage.getItem(STORAGE_KEY);
+}
+
+export const guestToken = readGuestToken();
+
+// Set when the server says this link only allows watching
+export const viewOnly = signal(false);
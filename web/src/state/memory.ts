@@ -0,0 +1,49 @@
+import { signal } from "@preact/signals";
+import type { MemoryDocument, MemoryFact, MemorySearchResult } from "../types";
+import * as api from "../api";
+
+export const memoryContent = signal("");
+export const memoryFacts = signal<MemoryFact[]>([]);
+export const memoryError = signal<string | null>(null);
+export const searchResults = signal<MemorySearchResult[]>([]);
+
+function apply(doc: MemoryDocument) {
+  memoryContent.value = doc.content;
+  memoryFacts.value = doc.facts;
+  memoryError.value = null;
+}
+
+// Edits are rejected if MEMORY.md changed underneath (e.g. consolidation ran),
+// so reload to show the current facts alongside the error.
+async function run(action: () => Promise<MemoryDocument>) {
+  try {
+    apply(await action());
+  } catch (e) {
+    await loadMemory();
+    memoryError.value = e instanceof Error ? e.message : String(e);
+  }
+}
+
+export async function loadMemory() {
+  try {
+    apply(await api.fetchMemory());
+  } catch (e) {
+    console.error("Failed to load memory:", e);
+  }
+}
+
+export function saveMemory(content: string) {
+  return run(() => api.saveMemory(content));
+}
+
+export function editFact(fact: MemoryFact, text: string) {
+  return run(() => api.editMemoryFact(fact.line, fact.text, text));
+}
+
+export function deleteFact(fact: MemoryFact) {
+  return run(() => api.deleteMemoryFact(fact.line, fact.text));
+}
+
+export async function search(q: string) {
+  searchResults.value = q.trim() ? await api.searchMemory(q) : [];
+}
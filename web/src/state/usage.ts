@@ -19,6 +19,11 @@ export function formatTokens(n: number | null | undefined): string {
   return String(n);
 }
 
+export function formatMinutes(seconds: number | null | undefined): string {
+  if (!seconds) return "0";
+  return (seconds / 60).toFixed(1) + " min";
+}
+
 export function formatCost(n: number | null | undefined): string {
   if (n === null || n === undefined) return "\u2014";
   if (n < 0.01) return "<$0.01";
@@ -20,13 +20,18 @@ import {
   isGenerating,
   showThinking as showThinkingSignal,
 } from "./messages";
+import { guestToken, viewOnly } from "./guest";
 
 export type ConnectionStatus = "connected" | "disconnected" | "reconnecting";
 export const connectionStatus = signal<ConnectionStatus>("disconnected");
 export const statusText = signal("disconnected");
+// Other clients looking at each chat, by chat id
+export const viewers = signal<Record<string, string[]>>({});
 
 let ws: WebSocket | null = null;
 let reconnectDelay = 1000;
+// Set once a guest link is rejected, so we stop reconnecting
+let guestEnded = false;
 
 // Task detail streaming state (managed separately from chat)
 export const activeTaskId = signal<string | null>(null);
@@ -52,7 +57,8 @@ export function connectWs() {
   let url = `${proto}//${location.host}/ws`;
 
   const password = localStorage.getItem("patina-password");
-  if (password) url += `?password=${encodeURIComponent(password)}`;
+  if (guestToken) url += `?guest=${encodeURIComponent(guestToken)}`;
+  else if (password) url += `?password=${encodeURIComponent(password)}`;
 
   ws = new WebSocket(url);
 
@@ -90,7 +96,8 @@ export function connectWs() {
     }
 
     connectionStatus.value = "disconnected";
-    statusText.value = "disconnected";
+    statusText.value = guestEnded ? "link expired" : "disconnected";
+    if (guestEnded) return;
     setTimeout(connectWs, reconnectDelay);
     reconnectDelay = Math.min(reconnectDelay * 2, 30000);
   };
@@ -104,6 +111,13 @@ function handleMessage(data: WsMessage) {
 
   switch (data.type) {
     case "connected":
+      viewOnly.value = data.content === "view_only";
+      // Guest connections are pinned to one chat, named by the server
+      if (data.chatId && data.chatId !== chatId) {
+        activeChatId.value = data.chatId;
+        clearMessages();
+        send({ type: "get_history", chatId: data.chatId });
+      }
       break;
 
     case "history":
@@ -113,6 +127,14 @@ function handleMessage(data: WsMessage) {
       }
       break;
 
+    case "presence":
+      viewers.value = { ...viewers.value, [data.chatId]: data.viewers };
+      break;
+
+    case "status":
+      statusText.value = data.content;
+      break;
+
     case "text_delta":
       if (data.chatId === taskId && data.content) {
         taskIsStreaming.value = true;
@@ -212,7 +234,10 @@ function handleMessage(data: WsMessage) {
       break;
 
     case "error":
-      if (data.content?.includes("Authentication")) {
+      if (guestToken) {
+        guestEnded = true;
+        ws?.close();
+      } else if (data.content?.includes("Authentication")) {
         promptPassword();
       }
       addMessage("system", data.content || "Unknown error");
@@ -29,6 +29,25 @@ export interface Task {
   tags?: string[];
 }
 
+export interface MemoryFact {
+  line: number;
+  text: string;
+  section?: string;
+}
+
+export interface MemoryDocument {
+  content: string;
+  facts: MemoryFact[];
+}
+
+export interface MemorySearchResult {
+  path: string;
+  startLine: number;
+  endLine: number;
+  content: string;
+  score: number;
+}
+
 export interface UsageRow {
   group_key?: string;
   date?: string;
@@ -37,6 +56,8 @@ export interface UsageRow {
   output_tokens: number;
   total_tokens: number;
   cached_input_tokens: number;
+  audio_seconds: number;
+  images: number;
   estimated_cost: number | null;
 }
 
@@ -48,7 +69,8 @@ export interface UsageFilters {
 
 // WebSocket message types (server → client)
 export type WsMessage =
-  | { type: "connected" }
+  | { type: "connected"; chatId?: string; content?: string }
+  | { type: "presence"; chatId: string; viewers: string[] }
   | { type: "history"; chatId: string; messages: Message[] }
   | { type: "text_delta"; chatId: string; content: string }
   | { type: "message"; chatId: string; content: string }
@@ -62,6 +84,7 @@ export type WsMessage =
     }
   | { type: "session_deleted"; chatId: string }
   | { type: "task_history"; chatId: string; messages: Message[] }
+  | { type: "status"; content: string }
   | { type: "error"; content: string };
 
 // WebSocket message types (client → server)
@@ -71,10 +94,123 @@ export type WsOutMessage =
       chatId: string;
       content: string;
       persona?: string | null;
+      files?: string[];
     }
   | { type: "get_history"; chatId: string }
   | { type: "get_task_history"; chatId: string }
-  | { type: "cancel"; chatId: string }
+  | { type: "stop"; chatId: string }
   | { type: "create_session"; chatId: string; content: string }
   | { type: "delete_session"; chatId: string }
   | { type: "task_message"; chatId: string; content: string };
+
+// A/B compare
+
+export type CompareSideKey = "a" | "b";
+
+export type ComparePreference = "a" | "b" | "tie" | "neither";
+
+export interface CompareSideResult {
+  tier: string;
+  model: string;
+  response: string;
+  latencyMs: number;
+  tokens: number;
+  cost: number | null;
+  error?: string | null;
+}
+
+export interface Comparison {
+  id: string;
+  createdAt: string;
+  sessionKey?: string | null;
+  prompt: string;
+  a: CompareSideResult;
+  b: CompareSideResult;
+  preference?: ComparePreference | null;
+}
+
+export interface CompareTierStats {
+  tier: string;
+  comparisons: number;
+  rated: number;
+  preferred: number;
+  ties: number;
+  neither: number;
+  avgLatencyMs: number;
+  avgTokens: number;
+  totalCost: number | null;
+}
+
+// Lines of the /api/compare stream
+export type CompareEvent =
+  | { id: string }
+  | { side: CompareSideKey; delta: string }
+  | {
+      side: CompareSideKey;
+      done: true;
+      tier: string;
+      model: string;
+      latencyMs: number;
+      tokens: number;
+      cost: number | null;
+      error?: string;
+    }
+  | { done: true; error?: string };
+
+// Events of the /api/logs/stream feed
+export interface LogLine {
+  timestamp: string;
+  level: "ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE";
+  target: string;
+  message: string;
+}
+
+// GET /api/status; sections the gateway didn't provide are null
+export interface ChannelStatus {
+  state: "running" | "stopped" | "disabled" | "failed";
+  since: string;
+  error: string | null;
+  queue: { queued: number; sent: number; failed: number; lagMs: number } | null;
+}
+
+export interface CronJobStatus {
+  id: string;
+  name: string;
+  kind: string;
+  nextRunAtMs: number | null;
+  lastStatus: string | null;
+  lastError: string | null;
+}
+
+export interface GatewayStatus {
+  version: string;
+  startedAt: string | null;
+  uptimeSecs: number | null;
+  channels: Record<string, ChannelStatus>;
+  models: { tier: string; model: string; provider: string }[];
+  // Rate-limited providers only
+  providers: Record<
+    string,
+    { queued: number; requests: number; delayed: number; maxWaitMs: number }
+  >;
+  transcription: {
+    mode: string;
+    chain: string[];
+    queue: {
+      workers: number;
+      queued: number;
+      active: number;
+      completed: number;
+      failed: number;
+    };
+    lastLoaded: {
+      loaded: string;
+      loadedAt: string;
+      fallbackReason: string | null;
+    } | null;
+  } | null;
+  cron: CronJobStatus[] | null;
+  subagents: { id: string; label: string }[] | null;
+  memory: { files: number; chunks: number; bytes: number } | null;
+  today: { calls: number; totalTokens: number; cost: number | null } | null;
+}